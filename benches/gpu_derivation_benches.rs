@@ -65,7 +65,6 @@ fn prectiction_benches(
                         &config.algorithm,
                         step,
                         0,
-                        results.estimations.measurements.num_sensors(),
                     );
                 }
             })
@@ -156,6 +155,7 @@ fn setup_inputs(
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let gpu = GPU::new()?;
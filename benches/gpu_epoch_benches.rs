@@ -26,7 +26,7 @@ fn epoch_benches(
 ) -> anyhow::Result<()> {
     for voxel_size in VOXEL_SIZES.iter() {
         let config = setup_config(voxel_size);
-        let (data, mut results, gpu, _results_gpu, epoch_kernel) = setup_inputs(&config)?;
+        let (data, mut results, gpu, results_gpu, epoch_kernel) = setup_inputs(&config)?;
 
         let number_of_voxels = results
             .model
@@ -51,6 +51,28 @@ fn epoch_benches(
                     .expect("GPU queue operations should succeed in benchmark");
             })
         });
+        // the epoch loop used to read back the per-step losses after every
+        // epoch, which it now only does if step metrics are kept
+        group.bench_function(BenchmarkId::new("gpu_step_readback", voxel_size), |b| {
+            b.iter(|| {
+                epoch_kernel.execute().expect("Epoch kernel to succeed.");
+                results
+                    .metrics
+                    .update_steps_from_gpu(&results_gpu.metrics)
+                    .expect("Step readback to succeed.");
+            })
+        });
+        let mut epoch_index = 0;
+        group.bench_function(BenchmarkId::new("gpu_batch_readback", voxel_size), |b| {
+            b.iter(|| {
+                epoch_kernel.execute().expect("Epoch kernel to succeed.");
+                results
+                    .metrics
+                    .update_batch_from_gpu(&results_gpu.metrics, epoch_index)
+                    .expect("Batch readback to succeed.");
+                epoch_index = (epoch_index + 1) % config.algorithm.epochs;
+            })
+        });
     }
     Ok(())
 }
@@ -87,6 +109,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<(Data, Results, GPU, ResultsG
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let gpu = GPU::new()?;
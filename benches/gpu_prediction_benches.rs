@@ -124,6 +124,7 @@ fn setup_inputs(
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let gpu = GPU::new()?;
@@ -63,7 +63,6 @@ fn prectiction_benches(
                 &config.algorithm,
                 step,
                 0,
-                results.estimations.measurements.num_sensors(),
             )?;
         }
         let batch_size = results.estimations.measurements.num_steps();
@@ -161,6 +160,7 @@ fn setup_inputs(
         config.algorithm.batch_size,
         0,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let gpu = GPU::new()?;
@@ -4,10 +4,13 @@ use anyhow::Context;
 use cardiotrust::core::{
     algorithm::{
         estimation::{calculate_residuals, prediction::calculate_system_prediction},
-        refinement::derivation::{
-            calculate_average_delays, calculate_derivatives_coefs_simple,
-            calculate_derivatives_gains, calculate_mapped_residuals,
-            calculate_maximum_regularization, calculate_smoothness_derivatives,
+        refinement::{
+            derivation::{
+                calculate_average_delays, calculate_derivatives_coefs_simple,
+                calculate_derivatives_gains, calculate_mapped_residuals,
+                calculate_maximum_regularization,
+            },
+            loss::{LossContext, LossTermKind},
         },
     },
     config::Config,
@@ -70,19 +73,21 @@ fn bench_smoothness_derivatives(
             setup_inputs(&config).context("Failed to setup benchmark inputs")?;
 
         // run bench
+        let context = LossContext {
+            estimations: &results.estimations,
+            functional_description: &model.functional_description,
+            config: &config.algorithm,
+        };
         let number_of_voxels = model.spatial_description.voxels.count();
         group.throughput(criterion::Throughput::Elements(number_of_voxels as u64));
         group.bench_function(
             BenchmarkId::new("smoothness_derivatives", voxel_size),
             |b| {
                 b.iter(|| {
-                    calculate_smoothness_derivatives(
-                        &mut results.derivatives,
-                        &results.estimations,
-                        &model.functional_description,
-                        &config.algorithm,
-                    )
-                    .expect("Calculation to succeed.");
+                    LossTermKind::DelaySmoothness
+                        .term()
+                        .add_batch_derivatives(&mut results.derivatives, &context, 1.0)
+                        .expect("Calculation to succeed.");
                 })
             },
         );
@@ -193,10 +198,8 @@ fn bench_gains(
                 calculate_derivatives_gains(
                     &mut results.derivatives.gains,
                     &results.estimations.ap_outputs_now,
-                    &results.derivatives.maximum_regularization,
                     &results.derivatives.mapped_residuals,
-                    &config.algorithm,
-                    results.estimations.measurements.num_sensors(),
+                    config.algorithm.mse_strength,
                 );
             })
         });
@@ -240,7 +243,7 @@ fn bench_coefs(
                     &results.estimations,
                     &model.functional_description,
                     STEP,
-                    &config.algorithm,
+                    config.algorithm.mse_strength,
                 )
                 .expect("Calculation to succeed.");
             })
@@ -281,6 +284,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<(Data, Model, Results)> {
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     calculate_system_prediction(
         &mut results.estimations,
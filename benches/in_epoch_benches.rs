@@ -128,7 +128,6 @@ fn bench_derivation(
                     &config.algorithm,
                     STEP,
                     BEAT,
-                    results.estimations.measurements.num_sensors(),
                 )
                 .expect("Update to succeed");
             })
@@ -228,6 +227,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<(Data, Model, Results)> {
         config.algorithm.batch_size,
         0,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     Ok((data, model, results))
 }
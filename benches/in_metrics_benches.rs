@@ -113,6 +113,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<Results> {
         config.algorithm.batch_size,
         0,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
 
@@ -140,6 +140,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<(Data, Model, Results)> {
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     Ok((data, model, results))
 }
@@ -133,6 +133,7 @@ fn setup_inputs(config: &Config) -> anyhow::Result<(Data, Results)> {
         0,
         config.algorithm.batch_size,
         config.algorithm.optimizer,
+        config.algorithm.model.common.neighborhood_radius,
     );
     results.model = Some(model);
 
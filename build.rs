@@ -0,0 +1,32 @@
+use std::process::Command;
+
+/// Embeds the commit the crate is built from as `CARDIOTRUST_GIT_HASH`, with
+/// a `-dirty` suffix if the working tree has uncommitted changes and
+/// "unknown" outside of a git repository.
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+    let hash = git(&["rev-parse", "HEAD"]).map_or_else(
+        || "unknown".to_string(),
+        |hash| {
+            let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+                .is_some_and(|status| !status.is_empty());
+            if dirty {
+                format!("{hash}-dirty")
+            } else {
+                hash
+            }
+        },
+    );
+    println!("cargo:rustc-env=CARDIOTRUST_GIT_HASH={hash}");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+}
@@ -0,0 +1,88 @@
+#ifndef CARDIOTRUST_H
+#define CARDIOTRUST_H
+
+/* Generated with cbindgen, do not edit. Run `just ffi-header` instead. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Opaque handle of a forward model and its simulated outputs.
+ */
+typedef struct CardiotrustModel CardiotrustModel;
+
+/**
+ * Dimensions of the arrays written by [`cardiotrust_model_simulate`].
+ */
+typedef struct CardiotrustDimensions {
+  size_t number_of_beats;
+  size_t number_of_steps;
+  size_t number_of_sensors;
+  size_t number_of_states;
+} CardiotrustDimensions;
+
+/**
+ * Creates a forward model from a TOML config file, either a full scenario
+ * config or only its `simulation` table.
+ *
+ * Returns a null pointer on failure. The model must be released with
+ * [`cardiotrust_model_free`].
+ *
+ * # Safety
+ *
+ * `config_path` must be a valid, null-terminated UTF-8 string.
+ */
+CardiotrustModel *cardiotrust_model_create(const char *config_path);
+
+/**
+ * Releases a model created with [`cardiotrust_model_create`]. Passing a
+ * null pointer does nothing.
+ *
+ * # Safety
+ *
+ * `model` must be null or a pointer returned by
+ * [`cardiotrust_model_create`] that was not released before.
+ */
+void cardiotrust_model_free(CardiotrustModel *model);
+
+/**
+ * Writes the dimensions of the simulated arrays of the model.
+ *
+ * # Safety
+ *
+ * `model` must be a valid model and `dimensions` a valid pointer.
+ */
+int cardiotrust_model_dimensions(const CardiotrustModel *model,
+                                 CardiotrustDimensions *dimensions);
+
+/**
+ * Runs the forward simulation and copies the measurements of shape
+ * (beats, steps, sensors) and, if `system_states` is not null, the system
+ * states of shape (steps, states) into the given buffers.
+ *
+ * The buffer lengths are given in elements and must match the dimensions
+ * returned by [`cardiotrust_model_dimensions`].
+ *
+ * # Safety
+ *
+ * `model` must be a valid model. `measurements` must point to at least
+ * `measurements_len` writable floats, `system_states` must be null or point
+ * to at least `system_states_len` writable floats.
+ */
+int cardiotrust_model_simulate(CardiotrustModel *model,
+                               float *measurements,
+                               size_t measurements_len,
+                               float *system_states,
+                               size_t system_states_len);
+
+/**
+ * Returns the message of the last failure on the calling thread, or null if
+ * no call failed yet. The string stays valid until the next failing call on
+ * the same thread.
+ */
+const char *cardiotrust_last_error(void);
+
+#endif /* CARDIOTRUST_H */
@@ -0,0 +1,168 @@
+use std::{fs, path::Path, sync::mpsc::channel, time::Instant};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    core::{
+        algorithm::refinement::Optimizer,
+        config::{algorithm::AlgorithmType, Config},
+        scenario::{run, summary::Summary, Scenario},
+    },
+    settings::results_directory,
+};
+
+/// A small canonical scenario used to verify that algorithm changes
+/// reproduce reference numbers.
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub name: &'static str,
+    pub config: Config,
+}
+
+/// The outcome of running a single benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub runtime_s: f64,
+    pub summary: Summary,
+}
+
+/// Machine-readable report of a complete benchmark suite run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub version: String,
+    pub benchmarks: Vec<BenchmarkResult>,
+}
+
+/// Returns the canonical benchmark suite.
+///
+/// All benchmarks use the small handcrafted heart model with a pathology so
+/// that the localization metrics are meaningful.
+#[must_use]
+#[tracing::instrument(level = "info")]
+pub fn suite() -> Vec<Benchmark> {
+    info!("Creating benchmark suite");
+    let mut base = Config::default();
+    base.simulation.model.common.pathological = true;
+    base.simulation.duration_s = 0.5;
+    base.algorithm.epochs = 20;
+    base.algorithm.snapshots_interval = 0;
+
+    let mut model_based_sgd = base.clone();
+    model_based_sgd.algorithm.algorithm_type = AlgorithmType::ModelBased;
+    model_based_sgd.algorithm.optimizer = Optimizer::Sgd;
+
+    let mut model_based_adam = base.clone();
+    model_based_adam.algorithm.algorithm_type = AlgorithmType::ModelBased;
+    model_based_adam.algorithm.optimizer = Optimizer::Adam;
+    model_based_adam.algorithm.learning_rate = 1e-3;
+
+    let mut pseudo_inverse = base;
+    pseudo_inverse.algorithm.algorithm_type = AlgorithmType::PseudoInverse;
+
+    vec![
+        Benchmark {
+            name: "model_based_sgd",
+            config: model_based_sgd,
+        },
+        Benchmark {
+            name: "model_based_adam",
+            config: model_based_adam,
+        },
+        Benchmark {
+            name: "pseudo_inverse",
+            config: pseudo_inverse,
+        },
+    ]
+}
+
+/// Runs a single benchmark as a regular scenario with the id
+/// `benchmark-{name}` and returns its summary and runtime. The scenario of a
+/// previous run of the benchmark is replaced.
+///
+/// # Errors
+///
+/// Returns an error if the scenario could not be created, run or reloaded.
+#[tracing::instrument(level = "info", skip_all, fields(name = benchmark.name))]
+pub fn run_benchmark(benchmark: &Benchmark) -> Result<BenchmarkResult> {
+    info!("Running benchmark {}", benchmark.name);
+    let id = format!("benchmark-{}", benchmark.name);
+    let path = results_directory().join(&id);
+    if path.is_dir() {
+        fs::remove_dir_all(&path).with_context(|| {
+            format!(
+                "Failed to remove previous run of benchmark {}",
+                benchmark.name
+            )
+        })?;
+    }
+    let mut scenario = Scenario::build(Some(id.clone()))
+        .with_context(|| format!("Failed to create scenario for benchmark {}", benchmark.name))?;
+    scenario.config = benchmark.config.clone();
+    scenario
+        .schedule()
+        .with_context(|| format!("Failed to schedule benchmark {}", benchmark.name))?;
+
+    let (simulation_tx, _simulation_rx) = channel();
+    let (epoch_tx, _epoch_rx) = channel();
+    let (summary_tx, _summary_rx) = channel();
+    let start = Instant::now();
+    run(scenario, &simulation_tx, &epoch_tx, &summary_tx)
+        .with_context(|| format!("Failed to run benchmark {}", benchmark.name))?;
+    let runtime_s = start.elapsed().as_secs_f64();
+
+    let scenario = Scenario::load(&path)
+        .with_context(|| format!("Failed to reload benchmark {}", benchmark.name))?;
+    let summary = scenario
+        .summary
+        .with_context(|| format!("Benchmark {} finished without a summary", benchmark.name))?;
+
+    Ok(BenchmarkResult {
+        name: benchmark.name.to_string(),
+        runtime_s,
+        summary,
+    })
+}
+
+/// Runs all benchmarks of the suite and writes the report as TOML to the
+/// given path.
+///
+/// # Errors
+///
+/// Returns an error if any benchmark fails or the report could not be written.
+#[tracing::instrument(level = "info")]
+pub fn run_suite(output: &Path) -> Result<BenchmarkReport> {
+    info!("Running benchmark suite");
+    let benchmarks = suite()
+        .iter()
+        .map(run_benchmark)
+        .collect::<Result<Vec<_>>>()?;
+    let report = BenchmarkReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        benchmarks,
+    };
+    let toml = toml::to_string(&report).context("Failed to serialize benchmark report")?;
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(output, toml)
+        .with_context(|| format!("Failed to write benchmark report: {}", output.display()))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suite_names_are_unique() {
+        let suite = suite();
+        let mut names: Vec<&str> = suite.iter().map(|benchmark| benchmark.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), suite.len());
+    }
+}
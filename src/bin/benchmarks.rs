@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cardiotrust::{benchmarks::run_suite, settings::results_directory};
+use tracing::info;
+use tracing_subscriber::{fmt, layer::SubscriberExt};
+
+#[tracing::instrument(level = "info")]
+fn main() {
+    if let Err(e) = run_benchmarks() {
+        eprintln!("Benchmark suite failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+#[tracing::instrument(level = "info")]
+fn run_benchmarks() -> Result<()> {
+    setup_logging()?;
+
+    let output = std::env::args().nth(1).map_or_else(
+        || results_directory().join("benchmark_metrics.toml"),
+        PathBuf::from,
+    );
+
+    info!("Starting CardioTRust benchmark suite");
+    let report = run_suite(&output).context("Failed to run benchmark suite")?;
+    for result in &report.benchmarks {
+        info!(
+            "{}: dice {:.4}, loss {:.4e}, runtime {:.1} s",
+            result.name, result.summary.dice, result.summary.loss, result.runtime_s
+        );
+    }
+    info!("Benchmark metrics written to {}", output.display());
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug")]
+fn setup_logging() -> Result<()> {
+    let subscriber = tracing_subscriber::registry().with(
+        fmt::Layer::new()
+            .with_writer(std::io::stdout)
+            .with_thread_names(true)
+            .with_ansi(true),
+    );
+
+    tracing::subscriber::set_global_default(subscriber)
+        .context("Failed to set up stdout logging")?;
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use cardiotrust::reproducibility::export_bundle;
+use tracing::info;
+use tracing_subscriber::{fmt, layer::SubscriberExt};
+
+#[tracing::instrument(level = "info")]
+fn main() {
+    if let Err(e) = run_bundle() {
+        eprintln!("Bundle export failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+/// Usage: `bundle [--output <path>] <scenario id>...`
+#[tracing::instrument(level = "info")]
+fn run_bundle() -> Result<()> {
+    setup_logging()?;
+
+    let mut output = None;
+    let mut ids = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--output" {
+            output = Some(PathBuf::from(
+                args.next().context("Missing path after --output")?,
+            ));
+        } else {
+            ids.push(arg);
+        }
+    }
+    let output = output.unwrap_or_else(|| {
+        Path::new("./archives").join(format!(
+            "bundle-{}.tar.zst",
+            chrono::Utc::now().format("%Y-%m-%d-%H-%M-%S")
+        ))
+    });
+
+    info!("Starting CardioTRust reproducibility bundle export");
+    let manifest = export_bundle(&ids, &output)?;
+    info!(
+        "Bundled {} scenarios at git hash {} into {}",
+        manifest.scenarios.len(),
+        manifest.git_hash,
+        output.display()
+    );
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug")]
+fn setup_logging() -> Result<()> {
+    let subscriber = tracing_subscriber::registry().with(
+        fmt::Layer::new()
+            .with_writer(std::io::stdout)
+            .with_thread_names(true)
+            .with_ansi(true),
+    );
+
+    tracing::subscriber::set_global_default(subscriber)
+        .context("Failed to set up stdout logging")?;
+
+    Ok(())
+}
@@ -1,12 +1,16 @@
-use std::process::Command;
-
 use anyhow::{Context, Result};
 use bevy::{log::LogPlugin, prelude::*};
 use cardiotrust::{
-    scheduler::SchedulerPlugin, ui::UiPlugin, vis::VisPlugin, ScenarioList, SelectedSenario,
+    core::scenario::provenance::GIT_HASH,
+    profiling::ProfilingLayer,
+    scheduler::SchedulerPlugin,
+    settings::{LogFormat, Settings},
+    ui::UiPlugin,
+    vis::VisPlugin,
+    ScenarioList, SearchList, SelectedSenario, TemplateList,
 };
-use tracing::info;
-use tracing_subscriber::{fmt, layer::SubscriberExt};
+use tracing::{info, level_filters::LevelFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, Layer};
 
 #[tracing::instrument(level = "info")]
 fn main() {
@@ -21,14 +25,13 @@ fn run_app() -> Result<()> {
     // Set up logging with graceful fallback
     setup_logging()?;
 
-    // Get git hash with fallback to "unknown"
-    let git_hash = get_git_hash();
-
-    info!("Starting CardioTRust application. Git hash: {}", git_hash);
+    info!("Starting CardioTRust application. Git hash: {}", GIT_HASH);
 
     App::new()
         .init_resource::<ScenarioList>()
         .init_resource::<SelectedSenario>()
+        .init_resource::<TemplateList>()
+        .init_resource::<SearchList>()
         .add_plugins(
             DefaultPlugins
                 .set(WindowPlugin {
@@ -61,12 +64,17 @@ fn setup_logging() -> Result<()> {
 
 #[tracing::instrument(level = "debug")]
 fn setup_stdout_logging() -> Result<()> {
-    let subscriber = tracing_subscriber::registry().with(
-        fmt::Layer::new()
-            .with_writer(std::io::stdout)
-            .with_thread_names(true)
-            .with_ansi(true),
-    );
+    // the log level filters the output only, the profiling layer always
+    // sees its spans
+    let subscriber = tracing_subscriber::registry()
+        .with(
+            fmt::Layer::new()
+                .with_writer(std::io::stdout)
+                .with_thread_names(true)
+                .with_ansi(true)
+                .with_filter(LevelFilter::from(Settings::global().log_level)),
+        )
+        .with(ProfilingLayer::filtered());
 
     tracing::subscriber::set_global_default(subscriber)
         .context("Failed to set up stdout logging")?;
@@ -76,46 +84,46 @@ fn setup_stdout_logging() -> Result<()> {
 
 #[tracing::instrument(level = "debug")]
 fn try_setup_file_logging() -> Result<()> {
-    let file_appender = tracing_appender::rolling::daily("./logs", "CardioTRust.log");
+    let settings = Settings::global();
+    let file_appender =
+        tracing_appender::rolling::daily(&settings.log_directory, "CardioTRust.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
     // Store the guard to prevent it from being dropped
     std::mem::forget(_guard);
 
+    let level = LevelFilter::from(settings.log_level);
+    // JSON events carry the fields of all enclosing spans, e.g. the scenario
+    // id and epoch of algorithm runs
+    let file_layer = match settings.log_format {
+        LogFormat::Text => fmt::Layer::new()
+            .with_writer(non_blocking)
+            .with_thread_names(true)
+            .with_line_number(true)
+            .fmt_fields(fmt::format::PrettyFields::new())
+            .with_ansi(false)
+            .boxed(),
+        LogFormat::Json => fmt::Layer::new()
+            .json()
+            .with_writer(non_blocking)
+            .with_thread_names(true)
+            .with_line_number(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
     let subscriber = tracing_subscriber::registry()
         .with(
             fmt::Layer::new()
                 .with_writer(std::io::stdout)
                 .with_thread_names(true)
-                .with_ansi(true),
+                .with_ansi(true)
+                .with_filter(level),
         )
-        .with(
-            fmt::Layer::new()
-                .with_writer(non_blocking)
-                .with_thread_names(true)
-                .with_line_number(true)
-                .fmt_fields(fmt::format::PrettyFields::new())
-                .with_ansi(false),
-        );
+        .with(file_layer.with_filter(level))
+        .with(ProfilingLayer::filtered());
 
     tracing::subscriber::set_global_default(subscriber).context("Failed to set up file logging")?;
 
     Ok(())
 }
-
-#[tracing::instrument(level = "debug")]
-fn get_git_hash() -> String {
-    Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout).ok()
-            } else {
-                None
-            }
-        })
-        .map(|hash| hash.trim().to_string())
-        .unwrap_or_else(|| "unknown".to_string())
-}
@@ -1,14 +1,15 @@
-use std::process::Command;
-
 use anyhow::{Context, Result};
 use bevy::prelude::*;
-use cardiotrust::core::{
-    algorithm::refinement::Optimizer,
-    config::{algorithm::Algorithm, model::SensorArrayMotion, simulation::Simulation},
-    scenario::Scenario,
+use cardiotrust::{
+    core::{
+        algorithm::refinement::Optimizer,
+        config::{algorithm::Algorithm, model::SensorArrayMotion, simulation::Simulation},
+        scenario::{provenance::GIT_HASH, Scenario},
+    },
+    settings::{LogFormat, Settings},
 };
-use tracing::info;
-use tracing_subscriber::{fmt, layer::SubscriberExt};
+use tracing::{info, level_filters::LevelFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, Layer};
 
 #[tracing::instrument(level = "info")]
 fn main() {
@@ -23,10 +24,7 @@ fn run_planner() -> Result<()> {
     // Set up logging with graceful fallback
     setup_logging().context("Failed to set up logging for planner")?;
 
-    // Get git hash with fallback to "unknown"
-    let git_hash = get_git_hash();
-
-    info!("Starting CardioTRust planner. Git hash: {}", git_hash);
+    info!("Starting CardioTRust planner. Git hash: {}", GIT_HASH);
 
     plan_scenarios().context("Failed to plan scenarios")?;
 
@@ -148,12 +146,14 @@ fn setup_logging() -> Result<()> {
 
 #[tracing::instrument(level = "debug")]
 fn setup_stdout_logging() -> Result<()> {
-    let subscriber = tracing_subscriber::registry().with(
-        fmt::Layer::new()
-            .with_writer(std::io::stdout)
-            .with_thread_names(true)
-            .with_ansi(true),
-    );
+    let subscriber = tracing_subscriber::registry()
+        .with(LevelFilter::from(Settings::global().log_level))
+        .with(
+            fmt::Layer::new()
+                .with_writer(std::io::stdout)
+                .with_thread_names(true)
+                .with_ansi(true),
+        );
 
     tracing::subscriber::set_global_default(subscriber)
         .context("Failed to set up stdout logging")?;
@@ -163,46 +163,44 @@ fn setup_stdout_logging() -> Result<()> {
 
 #[tracing::instrument(level = "debug")]
 fn try_setup_file_logging() -> Result<()> {
-    let file_appender = tracing_appender::rolling::daily("./logs", "CardioPlanner.log");
+    let settings = Settings::global();
+    let file_appender =
+        tracing_appender::rolling::daily(&settings.log_directory, "CardioPlanner.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
     // Store the guard to prevent it from being dropped
     std::mem::forget(_guard);
 
+    // JSON events carry the fields of all enclosing spans, e.g. the scenario
+    // id and epoch of algorithm runs
+    let file_layer = match settings.log_format {
+        LogFormat::Text => fmt::Layer::new()
+            .with_writer(non_blocking)
+            .with_thread_names(true)
+            .with_line_number(true)
+            .fmt_fields(fmt::format::PrettyFields::new())
+            .with_ansi(false)
+            .boxed(),
+        LogFormat::Json => fmt::Layer::new()
+            .json()
+            .with_writer(non_blocking)
+            .with_thread_names(true)
+            .with_line_number(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
     let subscriber = tracing_subscriber::registry()
+        .with(LevelFilter::from(settings.log_level))
         .with(
             fmt::Layer::new()
                 .with_writer(std::io::stdout)
                 .with_thread_names(true)
                 .with_ansi(true),
         )
-        .with(
-            fmt::Layer::new()
-                .with_writer(non_blocking)
-                .with_thread_names(true)
-                .with_line_number(true)
-                .fmt_fields(fmt::format::PrettyFields::new())
-                .with_ansi(false),
-        );
+        .with(file_layer);
 
     tracing::subscriber::set_global_default(subscriber).context("Failed to set up file logging")?;
 
     Ok(())
 }
-
-#[tracing::instrument(level = "debug")]
-fn get_git_hash() -> String {
-    Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .output()
-        .ok()
-        .and_then(|output| {
-            if output.status.success() {
-                String::from_utf8(output.stdout).ok()
-            } else {
-                None
-            }
-        })
-        .map(|hash| hash.trim().to_string())
-        .unwrap_or_else(|| "unknown".to_string())
-}
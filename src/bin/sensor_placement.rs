@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use cardiotrust::{
+    core::{
+        config::model::Model,
+        model::spatial::sensor_optimization::optimize_sensor_positions_from_model_config,
+        scenario::Scenario,
+    },
+    settings::results_directory,
+};
+use tracing::info;
+use tracing_subscriber::{fmt, layer::SubscriberExt};
+
+#[tracing::instrument(level = "info")]
+fn main() {
+    if let Err(e) = run_sensor_placement() {
+        eprintln!("Sensor placement failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+/// Usage: `sensor_placement [--scenario <id>] [--output <path>] <number of positions>`
+///
+/// The sensor array of the simulation model of the scenario, or of the
+/// default model, is used as the candidate surface.
+#[tracing::instrument(level = "info")]
+fn run_sensor_placement() -> Result<()> {
+    setup_logging()?;
+
+    let mut scenario_id = None;
+    let mut output = PathBuf::from("assets/sensor_positions_mm.npy");
+    let mut number_of_positions = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--scenario" {
+            scenario_id = Some(args.next().context("Missing id after --scenario")?);
+        } else if arg == "--output" {
+            output = PathBuf::from(args.next().context("Missing path after --output")?);
+        } else {
+            number_of_positions = Some(
+                arg.parse::<usize>()
+                    .with_context(|| format!("Invalid number of positions: {arg}"))?,
+            );
+        }
+    }
+    let number_of_positions = number_of_positions.context("Missing number of positions")?;
+    let model = match scenario_id {
+        Some(id) => {
+            Scenario::load(&results_directory().join(&id))
+                .with_context(|| format!("Failed to load scenario with id {id}"))?
+                .config
+                .simulation
+                .model
+        }
+        None => Model::default(),
+    };
+
+    info!("Starting CardioTRust sensor placement");
+    let placement = optimize_sensor_positions_from_model_config(&model, number_of_positions)?;
+    for (position, score) in placement
+        .positions_mm
+        .rows()
+        .into_iter()
+        .zip(&placement.scores)
+    {
+        info!(
+            "Position [{:.1}, {:.1}, {:.1}] mm with leverage {score:.3e}",
+            position[0], position[1], position[2]
+        );
+    }
+    placement.save_npy(&output)?;
+    info!(
+        "Saved {} of {} candidate positions to {}, use them with the FromFile sensor array \
+         geometry",
+        placement.positions_mm.nrows(),
+        placement.number_of_candidates,
+        output.display()
+    );
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug")]
+fn setup_logging() -> Result<()> {
+    let subscriber = tracing_subscriber::registry().with(
+        fmt::Layer::new()
+            .with_writer(std::io::stdout)
+            .with_thread_names(true)
+            .with_ansi(true),
+    );
+
+    tracing::subscriber::set_global_default(subscriber)
+        .context("Failed to set up stdout logging")?;
+
+    Ok(())
+}
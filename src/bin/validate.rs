@@ -0,0 +1,54 @@
+use anyhow::{Context, Result};
+use cardiotrust::validation::{validate_single_dipole, DipoleValidation};
+use tracing::info;
+use tracing_subscriber::{fmt, layer::SubscriberExt};
+
+#[tracing::instrument(level = "info")]
+fn main() {
+    if let Err(e) = run_validation() {
+        eprintln!("Validation failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+#[tracing::instrument(level = "info")]
+fn run_validation() -> Result<()> {
+    setup_logging()?;
+
+    info!("Starting CardioTRust single dipole validation");
+    let report = validate_single_dipole(&DipoleValidation::default())
+        .context("Failed to run single dipole validation")?;
+    info!(
+        "Dipole in state {} of {}, measured by {} sensors",
+        report.dipole_state, report.number_of_states, report.number_of_sensors
+    );
+    info!(
+        "Forward model error {:.3e} (tolerance {:.1e})",
+        report.forward_relative_error, report.forward_tolerance
+    );
+    info!(
+        "Pseudo inverse error {:.3e} (tolerance {:.1e}), localized: {}",
+        report.inverse_relative_error, report.inverse_tolerance, report.localized
+    );
+    anyhow::ensure!(
+        report.passed(),
+        "Results deviate from the analytic dipole solution"
+    );
+    info!("Validation passed");
+    Ok(())
+}
+
+#[tracing::instrument(level = "debug")]
+fn setup_logging() -> Result<()> {
+    let subscriber = tracing_subscriber::registry().with(
+        fmt::Layer::new()
+            .with_writer(std::io::stdout)
+            .with_thread_names(true)
+            .with_ansi(true),
+    );
+
+    tracing::subscriber::set_global_default(subscriber)
+        .context("Failed to set up stdout logging")?;
+
+    Ok(())
+}
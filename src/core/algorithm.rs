@@ -1,20 +1,24 @@
+pub mod diagnostics;
 pub mod estimation;
 pub mod gpu;
+pub mod inverse;
+pub mod kalman;
 pub mod metrics;
 pub mod refinement;
+pub mod streaming;
 #[cfg(test)]
 mod tests;
 
 use anyhow::{Context, Result};
-use nalgebra::{DMatrix, SVD};
-use ndarray::{s, Array1};
+use nalgebra::{DMatrix, Dyn, SVD};
+use ndarray::{s, Array1, Array2};
 use rand::{rng, seq::SliceRandom};
 use refinement::derivation::{calculate_average_delays, calculate_batch_derivatives};
 use tracing::{debug, trace};
 
 use self::estimation::{calculate_residuals, prediction::calculate_system_prediction};
 use super::{
-    config::algorithm::Algorithm,
+    config::algorithm::{Algorithm, RegularizationPath},
     data::{shapes::SystemStates, Data},
     model::functional::FunctionalDescription,
     scenario::results::Results,
@@ -25,6 +29,8 @@ use crate::core::algorithm::refinement::derivation::calculate_step_derivatives;
 ///
 /// This iterates through each time step, calculating the system state estimate, residuals, derivatives, and metrics at each step.
 /// It uses SVD to calculate the pseudo inverse of the measurement matrix.
+/// If the regularization path is enabled in the config, Tikhonov regularized
+/// solutions are evaluated for all its lambdas as well.
 ///
 /// # Errors
 ///
@@ -53,8 +59,6 @@ pub fn calculate_pseudo_inverse(
 
     let decomposition = SVD::new_unordered(measurement_matrix, true, true);
 
-    let num_sensors = data.simulation.measurements.num_sensors();
-
     let estimations = &mut results.estimations;
     let derivatives = &mut results.derivatives;
 
@@ -97,7 +101,6 @@ pub fn calculate_pseudo_inverse(
             config,
             step,
             0,
-            num_sensors,
         )?;
 
         metrics::calculate_step(
@@ -109,9 +112,116 @@ pub fn calculate_pseudo_inverse(
         );
     }
     metrics::calculate_batch(&mut results.metrics, 0)?;
+
+    if config.regularization_path.number_of_lambdas > 0 {
+        calculate_regularization_path(
+            &decomposition,
+            functional_description,
+            results,
+            data,
+            &config.regularization_path,
+        )?;
+    }
+    Ok(())
+}
+
+/// Evaluates Tikhonov regularized solutions of the pseudo inverse for the
+/// log-spaced lambdas of the regularization path.
+///
+/// Stores the lambdas together with the MSE loss and the best dice score of
+/// each solution in the metrics. The regular estimations are left unchanged.
+/// The dice score uses the voxel grid of the simulation, which the model
+/// shares after synchronizing its parameters.
+///
+/// # Errors
+///
+/// Returns an error if the singular vectors are not available or the lambda
+/// range is not positive.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "debug", skip_all)]
+fn calculate_regularization_path(
+    decomposition: &SVD<f32, Dyn, Dyn>,
+    functional_description: &FunctionalDescription,
+    results: &mut Results,
+    data: &Data,
+    config: &RegularizationPath,
+) -> Result<()> {
+    debug!("Calculating regularization path of pseudo inverse");
+    anyhow::ensure!(
+        0.0 < config.min_lambda && config.min_lambda <= config.max_lambda,
+        "Regularization path requires 0 < min_lambda <= max_lambda"
+    );
+    let u = decomposition
+        .u
+        .as_ref()
+        .context("Left singular vectors not calculated for regularization path")?;
+    let v_t = decomposition
+        .v_t
+        .as_ref()
+        .context("Right singular vectors not calculated for regularization path")?;
+    let u = Array2::from_shape_fn(u.shape(), |index| u[index]);
+    let v = Array2::from_shape_fn((v_t.ncols(), v_t.nrows()), |(state, k)| v_t[(k, state)]);
+    let singular_values = Array1::from_iter(decomposition.singular_values.iter().copied());
+
+    let measurement_matrix = functional_description.measurement_matrix.at_beat(0);
+    let measurements = data.simulation.measurements.at_beat(0);
+    // measurements in the basis of the left singular vectors, (steps, rank)
+    let coefficients = measurements.dot(&u);
+    let number_of_values = measurements.len().max(1) as f32;
+
+    let lambdas = Array1::logspace(
+        10.0,
+        config.min_lambda.log10(),
+        config.max_lambda.log10(),
+        config.number_of_lambdas,
+    );
+    let mut loss_mse = Array1::zeros(lambdas.len());
+    let mut dice = Array1::zeros(lambdas.len());
+    let mut estimations = results.estimations.clone();
+    for (index, lambda) in lambdas.iter().enumerate() {
+        let factors = singular_values.mapv(|s| s / s.mul_add(s, *lambda));
+        let system_states = (&coefficients * &factors).dot(&v.t());
+        let residuals = system_states.dot(&measurement_matrix.t()) - &*measurements;
+        loss_mse[index] = residuals.mapv(|r| r.powi(2)).sum() / number_of_values;
+        estimations.system_states.assign(&system_states);
+        dice[index] = metrics::calculate_best_dice(
+            &estimations,
+            &data.simulation.model.spatial_description.voxels.types,
+            &data.simulation.model.spatial_description.voxels.numbers,
+        );
+        trace!(
+            "Lambda {lambda:e}: MSE loss {}, dice {}",
+            loss_mse[index],
+            dice[index]
+        );
+    }
+    results.metrics.regularization_lambdas = lambdas;
+    results.metrics.loss_mse_over_lambda = loss_mse;
+    results.metrics.dice_score_over_lambda = dice;
     Ok(())
 }
 
+/// Builds a dedicated thread pool for the parallel derivative loops.
+///
+/// Returns `None` if the number of threads is zero, in which case the
+/// global rayon pool with one thread per core is used.
+///
+/// # Errors
+///
+/// Returns an error if the thread pool cannot be created.
+#[tracing::instrument(level = "debug")]
+pub fn build_thread_pool(number_of_threads: usize) -> Result<Option<rayon::ThreadPool>> {
+    if number_of_threads == 0 {
+        return Ok(None);
+    }
+    debug!("Building thread pool with {number_of_threads} threads");
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(number_of_threads)
+        .build()
+        .with_context(|| format!("Failed to build thread pool with {number_of_threads} threads"))?;
+    Ok(Some(pool))
+}
+
 /// Runs the algorithm for one epoch.
 ///
 /// This includes calculating the system estimates
@@ -143,8 +253,6 @@ pub fn run_epoch(
     let estimations = &mut results.estimations;
     let derivatives = &mut results.derivatives;
 
-    let num_sensors = data.simulation.measurements.num_sensors();
-
     for beat in beat_indices {
         estimations.reset();
 
@@ -166,7 +274,6 @@ pub fn run_epoch(
                 config,
                 step,
                 beat,
-                num_sensors,
             )?;
 
             metrics::calculate_step(
@@ -189,7 +296,7 @@ pub fn run_epoch(
                     &mut estimations.average_delays,
                     &model_ref.functional_description.ap_params,
                 )?;
-                calculate_batch_derivatives(
+                let loss_term_values = calculate_batch_derivatives(
                     derivatives,
                     estimations,
                     &model_ref.functional_description,
@@ -201,7 +308,7 @@ pub fn run_epoch(
                     .as_mut()
                     .context("Model not available for parameter update")?;
 
-                model_mut.functional_description.ap_params.update(
+                let bound_violations = model_mut.functional_description.ap_params.update(
                     derivatives,
                     config,
                     num_steps,
@@ -210,6 +317,10 @@ pub fn run_epoch(
                 derivatives.reset();
                 *n = 0;
                 metrics::calculate_batch(&mut results.metrics, *batch_index)?;
+                results
+                    .metrics
+                    .record_loss_terms(&loss_term_values, *batch_index);
+                results.metrics.bound_violations_batch[*batch_index] = bound_violations;
                 *batch_index += 1;
             }
         }
@@ -225,7 +336,7 @@ pub fn run_epoch(
                 &mut estimations.average_delays,
                 &model_ref.functional_description.ap_params,
             )?;
-            calculate_batch_derivatives(
+            let loss_term_values = calculate_batch_derivatives(
                 derivatives,
                 estimations,
                 &model_ref.functional_description,
@@ -237,13 +348,17 @@ pub fn run_epoch(
                 .as_mut()
                 .context("Model not available for final parameter update")?;
 
-            model_mut.functional_description.ap_params.update(
+            let bound_violations = model_mut.functional_description.ap_params.update(
                 &mut results.derivatives,
                 config,
                 num_steps,
                 n,
             )?;
             metrics::calculate_batch(&mut results.metrics, *batch_index)?;
+            results
+                .metrics
+                .record_loss_terms(&loss_term_values, *batch_index);
+            results.metrics.bound_violations_batch[*batch_index] = bound_violations;
             *batch_index += 1;
         }
     } else {
@@ -256,7 +371,7 @@ pub fn run_epoch(
             &mut estimations.average_delays,
             &model_ref.functional_description.ap_params,
         )?;
-        calculate_batch_derivatives(
+        let loss_term_values = calculate_batch_derivatives(
             derivatives,
             estimations,
             &model_ref.functional_description,
@@ -268,13 +383,17 @@ pub fn run_epoch(
             .as_mut()
             .context("Model not available for epoch parameter update")?;
 
-        model_mut.functional_description.ap_params.update(
+        let bound_violations = model_mut.functional_description.ap_params.update(
             &mut results.derivatives,
             config,
             num_steps,
             num_beats,
         )?;
         metrics::calculate_batch(&mut results.metrics, *batch_index)?;
+        results
+            .metrics
+            .record_loss_terms(&loss_term_values, *batch_index);
+        results.metrics.bound_violations_batch[*batch_index] = bound_violations;
         *batch_index += 1;
     }
     Ok(())
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use nalgebra::DMatrix;
+use ndarray::{Array1, Array3};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+/// Singular values below this fraction of the largest one are treated as
+/// zero when counting the effective rank, which is close to the precision
+/// of f32.
+const RANK_TOLERANCE: f32 = 1e-6;
+
+/// Conditioning of the measurement matrix for the sensor and voxel geometry
+/// of a model.
+///
+/// The matrices of all beats, i.e. all positions of the sensor array, are
+/// stacked, so the spectrum describes how well the combined measurements
+/// determine the states. A large condition number or an effective rank far
+/// below the number of states means the reconstruction is ill-posed for
+/// this geometry, whatever algorithm is used.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MeasurementConditioning {
+    // in descending order
+    pub singular_values: Array1<f32>,
+    // ratio of the largest to the smallest singular value, infinite if the
+    // smallest one is zero
+    pub condition_number: f32,
+    // number of singular values above the rank tolerance
+    pub effective_rank: usize,
+    pub number_of_measurements: usize,
+    pub number_of_states: usize,
+}
+
+/// Calculates the singular value spectrum, condition number and effective
+/// rank of the measurement matrix with shape (beats, sensors, states).
+///
+/// # Errors
+///
+/// Returns an error if the matrix is empty or not in standard layout.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn calculate_measurement_conditioning(
+    measurement_matrix: &Array3<f32>,
+) -> Result<MeasurementConditioning> {
+    debug!("Calculating conditioning of the measurement matrix");
+    let (number_of_beats, number_of_sensors, number_of_states) = measurement_matrix.dim();
+    let number_of_measurements = number_of_beats * number_of_sensors;
+    anyhow::ensure!(
+        number_of_measurements > 0 && number_of_states > 0,
+        "Measurement matrix is empty"
+    );
+    let matrix = DMatrix::from_row_slice(
+        number_of_measurements,
+        number_of_states,
+        measurement_matrix
+            .as_slice()
+            .context("Measurement matrix is not in standard layout")?,
+    );
+    let mut singular_values: Vec<f32> = matrix.singular_values().iter().copied().collect();
+    singular_values.sort_by(|a, b| b.total_cmp(a));
+
+    let largest = singular_values.first().copied().unwrap_or_default();
+    let smallest = singular_values.last().copied().unwrap_or_default();
+    let condition_number = if smallest > 0.0 {
+        largest / smallest
+    } else {
+        f32::INFINITY
+    };
+    let effective_rank = singular_values
+        .iter()
+        .filter(|value| **value > largest * RANK_TOLERANCE)
+        .count();
+    info!(
+        "Measurement matrix has condition number {condition_number:e} and effective rank {effective_rank} of {number_of_states} states"
+    );
+    Ok(MeasurementConditioning {
+        singular_values: Array1::from(singular_values),
+        condition_number,
+        effective_rank,
+        number_of_measurements,
+        number_of_states,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagonal_matrix_spectrum() -> Result<()> {
+        let mut matrix = Array3::zeros((1, 3, 3));
+        for (index, value) in [2.0, 8.0, 0.5].into_iter().enumerate() {
+            matrix[(0, index, index)] = value;
+        }
+
+        let conditioning = calculate_measurement_conditioning(&matrix)?;
+
+        for (value, expected) in conditioning.singular_values.iter().zip([8.0, 2.0, 0.5]) {
+            assert!((value - expected).abs() < 1e-5, "{conditioning:?}");
+        }
+        assert!((conditioning.condition_number - 16.0).abs() < 1e-3);
+        assert_eq!(conditioning.effective_rank, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn stacked_beats_reveal_rank_deficiency() -> Result<()> {
+        // two array positions measuring the same combination of two states
+        let mut matrix = Array3::zeros((2, 1, 2));
+        matrix[(0, 0, 0)] = 1.0;
+        matrix[(0, 0, 1)] = 1.0;
+        matrix[(1, 0, 0)] = 2.0;
+        matrix[(1, 0, 1)] = 2.0;
+
+        let conditioning = calculate_measurement_conditioning(&matrix)?;
+
+        assert_eq!(conditioning.number_of_measurements, 2);
+        assert_eq!(conditioning.effective_rank, 1);
+        assert!(conditioning.condition_number > 1e5);
+        Ok(())
+    }
+}
@@ -5,12 +5,19 @@ use ocl::Buffer;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use super::refinement::derivation::AverageDelays;
+use super::{
+    gpu::{
+        buffer_bytes,
+        precision::{read_stored_buffer, stored_buffer},
+    },
+    refinement::derivation::AverageDelays,
+};
 use crate::core::{
+    config::algorithm::GpuPrecision,
     data::{
         shapes::{
-            ActivationTimePerStateMs, Measurements, Residuals, SystemStates, SystemStatesSpherical,
-            SystemStatesSphericalMax,
+            ActivationTimePerStateMs, ChannelMask, Measurements, Residuals, SystemStates,
+            SystemStatesSpherical, SystemStatesSphericalMax,
         },
         Data,
     },
@@ -30,6 +37,7 @@ pub struct Estimations {
     pub activation_times: ActivationTimePerStateMs,
     pub measurements: Measurements,
     pub residuals: Residuals,
+    pub channel_mask: ChannelMask,
     pub system_states_spherical_max_delta: SystemStatesSphericalMax,
     pub activation_times_delta: ActivationTimePerStateMs,
     pub average_delays: AverageDelays,
@@ -41,9 +49,29 @@ pub struct EstimationsGPU {
     pub system_states: Buffer<f32>,
     pub measurements: Buffer<f32>,
     pub residuals: Buffer<f32>,
+    pub channel_mask: Buffer<f32>,
     pub step: Buffer<i32>,
     pub beat: Buffer<i32>,
     pub epoch: Buffer<i32>,
+    // storage precision of the system states
+    pub precision: GpuPrecision,
+}
+
+impl EstimationsGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        buffer_bytes(&self.ap_outputs_now)
+            + buffer_bytes(&self.ap_outputs_last)
+            + buffer_bytes(&self.system_states)
+            + buffer_bytes(&self.measurements)
+            + buffer_bytes(&self.residuals)
+            + buffer_bytes(&self.channel_mask)
+            + buffer_bytes(&self.step)
+            + buffer_bytes(&self.beat)
+            + buffer_bytes(&self.epoch)
+    }
 }
 
 impl Estimations {
@@ -55,11 +83,12 @@ impl Estimations {
         number_of_sensors: usize,
         number_of_steps: usize,
         number_of_beats: usize,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating empty estimations");
         Self {
-            ap_outputs_now: Gains::empty(number_of_states),
-            ap_outputs_last: Gains::empty(number_of_states),
+            ap_outputs_now: Gains::empty(number_of_states, neighborhood_radius),
+            ap_outputs_last: Gains::empty(number_of_states, neighborhood_radius),
             system_states: SystemStates::empty(number_of_steps, number_of_states),
             system_states_spherical: SystemStatesSpherical::empty(
                 number_of_steps,
@@ -69,6 +98,7 @@ impl Estimations {
             activation_times: ActivationTimePerStateMs::empty(number_of_states),
             measurements: Measurements::empty(number_of_beats, number_of_steps, number_of_sensors),
             residuals: Residuals::empty(number_of_sensors),
+            channel_mask: ChannelMask::empty(number_of_sensors),
             system_states_spherical_max_delta: SystemStatesSphericalMax::empty(number_of_states),
             activation_times_delta: ActivationTimePerStateMs::empty(number_of_states),
             average_delays: AverageDelays::empty(number_of_states),
@@ -96,13 +126,24 @@ impl Estimations {
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    pub(crate) fn to_gpu(&self, queue: &ocl::Queue) -> Result<EstimationsGPU> {
+    pub(crate) fn to_gpu(
+        &self,
+        queue: &ocl::Queue,
+        precision: GpuPrecision,
+    ) -> Result<EstimationsGPU> {
         Ok(EstimationsGPU {
             ap_outputs_now: self.ap_outputs_now.to_gpu(queue)?,
             ap_outputs_last: self.ap_outputs_last.to_gpu(queue)?,
-            system_states: self.system_states.to_gpu(queue)?,
+            system_states: stored_buffer(
+                queue,
+                self.system_states
+                    .as_slice()
+                    .context("Failed to get system states slice for GPU copy")?,
+                precision,
+            )?,
             measurements: self.measurements.to_gpu(queue)?,
             residuals: self.residuals.to_gpu(queue)?,
+            channel_mask: self.channel_mask.to_gpu(queue)?,
             step: ocl::Buffer::builder()
                 .queue(queue.clone())
                 .len(1)
@@ -121,17 +162,23 @@ impl Estimations {
                 .copy_host_slice(&[0])
                 .build()
                 .context("Failed to create epoch buffer")?,
+            precision,
         })
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
     pub(crate) fn update_from_gpu(&mut self, estimations: &EstimationsGPU) -> Result<()> {
         self.ap_outputs_now
             .update_from_gpu(&estimations.ap_outputs_now)?;
         self.ap_outputs_last
             .update_from_gpu(&estimations.ap_outputs_last)?;
-        self.system_states
-            .update_from_gpu(&estimations.system_states)?;
+        read_stored_buffer(
+            &estimations.system_states,
+            self.system_states
+                .as_slice_mut()
+                .context("Failed to get mutable system states slice for GPU read")?,
+            estimations.precision,
+        )?;
         self.measurements
             .update_from_gpu(&estimations.measurements)?;
         self.residuals.update_from_gpu(&estimations.residuals)?;
@@ -141,13 +188,15 @@ impl Estimations {
 
 /// Calculates the residuals between the predicted and actual measurements for the given time index.
 /// The residuals are stored in the provided `residuals` array.
+/// Residuals of channels excluded by the channel mask are set to zero.
 #[inline]
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn calculate_residuals(estimations: &mut Estimations, data: &Data, beat: usize, step: usize) {
     trace!("Calculating residuals");
     estimations.residuals.assign(
-        &(&*estimations.measurements.at_beat(beat).at_step(step)
-            - &*data.simulation.measurements.at_beat(beat).at_step(step)),
+        &((&*estimations.measurements.at_beat(beat).at_step(step)
+            - &*data.simulation.measurements.at_beat(beat).at_step(step))
+            * &*estimations.channel_mask),
     );
 }
 
@@ -189,6 +238,7 @@ pub fn calculate_delays_delta(
 #[cfg(test)]
 mod tests {
     use anyhow::Result;
+    use approx::assert_relative_eq;
     use ndarray::Dim;
 
     use super::{calculate_residuals, prediction::calculate_system_prediction, Estimations};
@@ -209,6 +259,7 @@ mod tests {
             number_of_sensors,
             number_of_steps,
             number_of_beats,
+            1,
         );
         let functional_description = FunctionalDescription::empty(
             number_of_states,
@@ -216,6 +267,7 @@ mod tests {
             number_of_steps,
             number_of_beats,
             voxels_in_dims,
+            1,
         );
 
         calculate_system_prediction(&mut estimations, &functional_description, beat, step)?;
@@ -237,6 +289,7 @@ mod tests {
             number_of_sensors,
             number_of_steps,
             number_of_beats,
+            1,
         );
         let data = Data::empty(
             number_of_sensors,
@@ -244,8 +297,30 @@ mod tests {
             number_of_steps,
             voxels_in_dims,
             number_of_beats,
+            1,
         );
 
         calculate_residuals(&mut estimations, &data, beat, step);
     }
+
+    #[test]
+    fn residuals_are_masked() -> Result<()> {
+        let number_of_sensors = 10;
+        let mut estimations = Estimations::empty(3, number_of_sensors, 5, 1, 1);
+        let data = Data::empty(number_of_sensors, 3, 5, Dim([1, 1, 1]), 1, 1);
+        estimations.measurements.fill(1.0);
+        estimations.channel_mask.exclude(&[2, 7])?;
+
+        calculate_residuals(&mut estimations, &data, 0, 3);
+
+        for (sensor, residual) in estimations.residuals.iter().enumerate() {
+            let expected = if sensor == 2 || sensor == 7 { 0.0 } else { 1.0 };
+            assert_relative_eq!(*residual, expected);
+        }
+        assert!(estimations
+            .channel_mask
+            .exclude(&[number_of_sensors])
+            .is_err());
+        Ok(())
+    }
 }
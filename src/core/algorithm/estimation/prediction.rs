@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use ndarray::Zip;
 use tracing::trace;
 
 use super::Estimations;
@@ -11,7 +12,7 @@ use crate::core::model::functional::FunctionalDescription;
 ///
 /// Returns an error if algorithm parameters are not properly initialized.
 #[allow(clippy::module_name_repetitions)]
-#[tracing::instrument(level = "trace", skip_all)]
+#[tracing::instrument(level = "info", skip_all, fields(profile = "prediction"))]
 pub fn calculate_system_prediction(
     estimations: &mut Estimations,
     functional_description: &FunctionalDescription,
@@ -65,12 +66,14 @@ pub fn innovate_system_states_v1(
             let coef_index = (index_state / 3, index_offset / 3);
             let coef = unsafe { *ap_params.coefs.uget(coef_index) };
             let delay = unsafe { *ap_params.delays.uget(coef_index) };
-            let input = if delay <= step {
+            // re-entrant connections stay closed until the voxel recovered
+            let gate = ap_params.refractory.gate(coef_index.0, coef_index.1);
+            let input = if delay <= step && gate <= step {
                 unsafe { *system_states.uget((step - delay, output_state_index)) }
             } else {
                 0.0
             };
-            let input_delayed = if delay < step {
+            let input_delayed = if delay < step && gate < step {
                 *unsafe { system_states.uget((step - delay - 1, output_state_index)) }
             } else {
                 0.0
@@ -88,7 +91,8 @@ pub fn innovate_system_states_v1(
 
 /// Adds a control function value multiplied by the control matrix to the
 /// system states for the given time index. This allows an external control
-/// signal to be injected into the system states.
+/// signal to be injected into the system states. States with an onset
+/// receive the control function delayed by their onset.
 #[inline]
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn add_control_function(
@@ -97,16 +101,22 @@ pub fn add_control_function(
     step: usize,
 ) {
     trace!("Adding control function");
+    let control_function_values = &functional_description.control_function_values;
     // Add control function
-    estimations.system_states.at_step_mut(step).scaled_add(
-        functional_description.control_function_values[step],
-        &*functional_description.control_matrix,
-    );
+    Zip::from(&mut *estimations.system_states.at_step_mut(step))
+        .and(&*functional_description.control_matrix)
+        .and(&*functional_description.control_onsets)
+        .for_each(|state, &control, &onset| {
+            if let Some(delayed_step) = step.checked_sub(onset) {
+                *state = control.mul_add(control_function_values[delayed_step], *state);
+            }
+        });
 }
 
 /// Predicts the measurements by multiplying the measurement matrix with the
 /// system states for the given time index. This computes the model predicted
-/// measurements to compare against the actual measurements.
+/// measurements to compare against the actual measurements. For continuous
+/// sensor array motion the measurement matrix is interpolated per step.
 #[inline]
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn predict_measurements(
@@ -121,10 +131,10 @@ pub fn predict_measurements(
         .measurements
         .at_beat_mut(beat)
         .at_step_mut(step)
-        .assign(
-            &functional_description
-                .measurement_matrix
-                .at_beat(beat)
-                .dot(&*estimations.system_states.at_step(step)),
-        );
+        .assign(&functional_description.measurement_matrix.predict(
+            &functional_description.measurement_interpolation,
+            &estimations.system_states.at_step(step),
+            beat,
+            step,
+        ));
 }
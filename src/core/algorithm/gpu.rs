@@ -1,13 +1,21 @@
+use std::sync::OnceLock;
+
 use anyhow::{Context as AnyhowContext, Result};
-use ocl::{Context, Device, Platform, Queue};
+use ocl::{Buffer, Context, Device, OclPrm, Platform, Queue};
+use tracing::{info, warn};
 
+pub mod backend;
 pub mod derivation;
 pub mod epoch;
 pub mod helper;
 pub mod metrics;
+pub mod precision;
 pub mod prediction;
+#[cfg(all(test, feature = "opencl-tests"))]
+mod reference_tests;
 pub mod reset;
 pub mod update;
+pub mod wgpu_backend;
 
 #[derive(Debug, Clone)]
 pub struct GPU {
@@ -29,7 +37,11 @@ impl GPU {
     /// Returns an error if GPU device detection, context creation, or queue initialization fails.
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn new() -> Result<Self> {
-        let platform = Platform::default();
+        // Platform::default panics if no OpenCL platform is installed
+        let platform = Platform::new(
+            ocl::core::default_platform()
+                .context("Failed to find OpenCL platform - no OpenCL runtime installed")?,
+        );
         let device = Device::first(platform)
             .context("Failed to find first GPU device - no OpenCL devices available")?;
 
@@ -61,6 +73,53 @@ impl GPU {
             device,
         })
     }
+
+    /// Returns true if an `OpenCL` GPU can be initialized.
+    ///
+    /// The check runs once per process, later calls return the cached
+    /// result. Logs a warning with the reason if no GPU is available.
+    #[tracing::instrument(level = "trace")]
+    pub fn is_available() -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| match Self::new() {
+            Ok(gpu) => {
+                info!(
+                    "OpenCL GPU available: {}",
+                    gpu.device.name().unwrap_or_default()
+                );
+                true
+            }
+            Err(e) => {
+                warn!("OpenCL GPU not available, GPU scenarios will run on the CPU: {e:#}");
+                false
+            }
+        })
+    }
+
+    /// Returns the global memory of the GPU in bytes, or `None` if no GPU is
+    /// available. The device is only queried on the first call.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn global_memory_bytes() -> Option<u64> {
+        static MEMORY: OnceLock<Option<u64>> = OnceLock::new();
+        *MEMORY.get_or_init(|| {
+            if !Self::is_available() {
+                return None;
+            }
+            let gpu = Self::new().ok()?;
+            match gpu.device.info(ocl::core::DeviceInfo::GlobalMemSize).ok()? {
+                ocl::core::DeviceInfoResult::GlobalMemSize(bytes) => Some(bytes),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Returns the device memory occupied by a buffer in bytes.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn buffer_bytes<T: OclPrm>(buffer: &Buffer<T>) -> usize {
+    buffer.len() * std::mem::size_of::<T>()
 }
 
 #[cfg(test)]
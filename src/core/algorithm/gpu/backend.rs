@@ -0,0 +1,188 @@
+use anyhow::{Context, Result};
+use ocl::Buffer;
+
+use super::{
+    buffer_bytes,
+    epoch::EpochKernel,
+    wgpu_backend::{WgpuBackend, WgpuDevice},
+    GPU,
+};
+use crate::core::{
+    algorithm::{estimation::Estimations, metrics::Metrics},
+    config::algorithm::{Algorithm, GpuBackend},
+    data::Data,
+    model::functional::allpass::APParameters,
+    scenario::results::{Results, ResultsGPU},
+};
+
+/// A GPU implementation of the epochs of the model-based algorithm.
+///
+/// The results stay on the device between epochs and are only read back
+/// on request.
+pub trait ComputeBackend {
+    /// Runs the prediction, derivation and update of one epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a kernel cannot be executed.
+    fn execute_epoch(&mut self) -> Result<()>;
+
+    fn set_freeze_delays(&mut self, value: bool);
+
+    fn set_freeze_gains(&mut self, value: bool);
+
+    /// Reads the losses of the given batch from the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a buffer fails.
+    fn read_batch_metrics(&self, metrics: &mut Metrics, batch_index: usize) -> Result<()>;
+
+    /// Reads the per-step losses of the last epoch from the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a buffer fails.
+    fn read_step_metrics(&self, metrics: &mut Metrics) -> Result<()>;
+
+    /// Reads the estimations and the all-pass parameters, e.g. for a
+    /// snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a buffer fails.
+    fn read_estimations(
+        &self,
+        estimations: &mut Estimations,
+        ap_params: &mut APParameters,
+    ) -> Result<()>;
+
+    /// Reads all results computed on the device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model is missing or reading a buffer fails.
+    fn read_results(&self, results: &mut Results) -> Result<()>;
+
+    /// Returns the device memory occupied by the buffers in bytes.
+    fn memory_bytes(&self) -> usize;
+}
+
+/// Creates the backend selected in the algorithm config and copies the
+/// results and measurements to the device.
+///
+/// # Errors
+///
+/// Returns an error if the device cannot be initialized, the model is
+/// missing or a kernel cannot be built.
+#[tracing::instrument(level = "info", skip_all, fields(backend = ?config.gpu_backend))]
+pub fn create_backend(
+    results: &Results,
+    data: &Data,
+    config: &Algorithm,
+) -> Result<Box<dyn ComputeBackend>> {
+    Ok(match config.gpu_backend {
+        GpuBackend::OpenCl => Box::new(OpenClBackend::new(results, data, config)?),
+        GpuBackend::Wgpu => Box::new(WgpuBackend::new(results, data, config)?),
+    })
+}
+
+/// Returns true if a GPU can be initialized for the given backend. The
+/// result is cached per backend.
+#[tracing::instrument(level = "trace")]
+pub fn is_backend_available(backend: GpuBackend) -> bool {
+    match backend {
+        GpuBackend::OpenCl => GPU::is_available(),
+        GpuBackend::Wgpu => WgpuDevice::is_available(),
+    }
+}
+
+/// Runs the epochs with the `OpenCL` kernels.
+pub struct OpenClBackend {
+    results: ResultsGPU,
+    actual_measurements: Buffer<f32>,
+    epoch_kernel: EpochKernel,
+}
+
+impl OpenClBackend {
+    /// Copies the results and measurements to the first `OpenCL` GPU and
+    /// builds the epoch kernel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the GPU cannot be initialized, the model is
+    /// missing or a kernel cannot be built.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new(results: &Results, data: &Data, config: &Algorithm) -> Result<Self> {
+        let gpu = GPU::new()?;
+        let results_gpu = results.to_gpu_with_precision(&gpu.queue, config.gpu_precision)?;
+        let actual_measurements = data.simulation.measurements.to_gpu(&gpu.queue)?;
+        let spatial_description = &results
+            .model
+            .as_ref()
+            .context("Model should be set during GPU algorithm execution")?
+            .spatial_description;
+        let epoch_kernel = EpochKernel::new(
+            &gpu,
+            &results_gpu,
+            &actual_measurements,
+            config,
+            spatial_description.voxels.count_states() as i32,
+            spatial_description.sensors.count() as i32,
+            results.estimations.measurements.num_steps() as i32,
+        )?;
+        Ok(Self {
+            results: results_gpu,
+            actual_measurements,
+            epoch_kernel,
+        })
+    }
+}
+
+impl ComputeBackend for OpenClBackend {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn execute_epoch(&mut self) -> Result<()> {
+        self.epoch_kernel.execute()
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn set_freeze_delays(&mut self, value: bool) {
+        self.epoch_kernel.set_freeze_delays(value);
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn set_freeze_gains(&mut self, value: bool) {
+        self.epoch_kernel.set_freeze_gains(value);
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn read_batch_metrics(&self, metrics: &mut Metrics, batch_index: usize) -> Result<()> {
+        metrics.update_batch_from_gpu(&self.results.metrics, batch_index)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn read_step_metrics(&self, metrics: &mut Metrics) -> Result<()> {
+        metrics.update_steps_from_gpu(&self.results.metrics)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn read_estimations(
+        &self,
+        estimations: &mut Estimations,
+        ap_params: &mut APParameters,
+    ) -> Result<()> {
+        estimations.update_from_gpu(&self.results.estimations)?;
+        ap_params.update_from_gpu(&self.results.model.functional_description.ap_params)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn read_results(&self, results: &mut Results) -> Result<()> {
+        results.update_from_gpu(&self.results)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn memory_bytes(&self) -> usize {
+        self.results.memory_bytes() + buffer_bytes(&self.actual_measurements)
+    }
+}
@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use ocl::{Buffer, Kernel, Program};
 
-use super::GPU;
+use super::{precision::kernel_header, GPU};
 use crate::core::{
     algorithm::{estimation::EstimationsGPU, refinement::derivation::DerivativesGPU},
     config::algorithm::Algorithm,
@@ -45,6 +45,8 @@ impl DerivationKernel {
         let queue = &gpu.queue;
         let device = &gpu.device;
         let number_of_voxels = number_of_states / 3;
+        let number_of_offsets = model.functional_description.ap_params.number_of_offsets;
+        let precision_src = kernel_header(estimations.precision)?;
 
         let residual_src =
             std::fs::read_to_string("src/core/algorithm/gpu/kernels/calculate_residuals.cl")
@@ -63,6 +65,7 @@ impl DerivationKernel {
             .arg(&estimations.residuals)
             .arg(&estimations.measurements)
             .arg(actual_measurements)
+            .arg(&estimations.channel_mask)
             .arg(&estimations.step)
             .arg(&estimations.beat)
             .arg(number_of_sensors)
@@ -118,7 +121,9 @@ impl DerivationKernel {
             std::fs::read_to_string("src/core/algorithm/gpu/kernels/maximum_regularization.cl")
                 .context("Failed to read maximum regularization kernel source file")?;
         let maximum_regularization_program = Program::builder()
-            .src(format!("{atomic_src}\n{maximum_regularization_src}"))
+            .src(format!(
+                "{precision_src}\n{atomic_src}\n{maximum_regularization_src}"
+            ))
             .build(context)
             .context("Failed to compile maximum regularization kernel for GPU device")?;
 
@@ -153,6 +158,7 @@ impl DerivationKernel {
         .context("Failed to read derivatives gains kernel source file")?;
         let derivatives_gains_program = Program::builder()
             .src(derivatives_gains_src)
+            .cmplr_def("NUM_OFFSETS", number_of_offsets)
             .build(context)
             .context("Failed to compile derivatives gains kernel for GPU device")?;
 
@@ -160,7 +166,7 @@ impl DerivationKernel {
             .program(&derivatives_gains_program)
             .name("calculate_derivatives_gains")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 78])
+            .global_work_size([number_of_states, number_of_offsets])
             .arg(&derivatives.gains)
             .arg(&estimations.ap_outputs_now)
             .arg(&derivatives.maximum_regularization)
@@ -176,7 +182,8 @@ impl DerivationKernel {
         )
         .context("Failed to read derivatives coefficients kernel source file")?;
         let derivatives_coefs_program = Program::builder()
-            .src(derivatives_coefs_src)
+            .src(format!("{precision_src}\n{derivatives_coefs_src}"))
+            .cmplr_def("NUM_OFFSETS", number_of_offsets)
             .build(context)
             .context("Failed to compile derivatives coefficients kernel for GPU device")?;
 
@@ -184,7 +191,7 @@ impl DerivationKernel {
             .program(&derivatives_coefs_program)
             .name("calculate_derivatives_coefs_fir")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 78])
+            .global_work_size([number_of_states, number_of_offsets])
             .arg(&derivatives.coefs_fir)
             .arg(&estimations.system_states)
             .arg(&model.functional_description.ap_params.output_state_indices)
@@ -199,7 +206,7 @@ impl DerivationKernel {
             .program(&derivatives_coefs_program)
             .name("calculate_derivatives_coefs_iir")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 78])
+            .global_work_size([number_of_states, number_of_offsets])
             .arg(&derivatives.coefs_iir)
             .arg(&estimations.ap_outputs_last)
             .arg(&model.functional_description.ap_params.coefs)
@@ -213,7 +220,7 @@ impl DerivationKernel {
             .program(&derivatives_coefs_program)
             .name("calculate_derivatives_coefs_combine")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 78])
+            .global_work_size([number_of_states, number_of_offsets])
             .local_work_size([3, 3])
             .arg(&derivatives.coefs)
             .arg(&derivatives.coefs_iir)
@@ -242,7 +249,7 @@ impl DerivationKernel {
         })
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "derivation"))]
     pub fn execute(&self) -> Result<()> {
         // TODO: Optimize prediction by running multiple beats in parallel using async kernel execution.
         // This would allow better GPU utilization by processing independent beats simultaneously.
@@ -302,11 +309,12 @@ mod tests {
             estimation::{calculate_residuals, prediction::calculate_system_prediction},
             gpu::{derivation::DerivationKernel, prediction::PredictionKernel, GPU},
             refinement::derivation::{
-                calculate_derivatives_coefs_textbook, calculate_derivatives_gains,
+                calculate_derivatives_coefs_textbook,
+                calculate_derivatives_difference_regularization, calculate_derivatives_gains,
                 calculate_mapped_residuals, calculate_maximum_regularization,
             },
         },
-        config::Config,
+        config::{algorithm::APDerivative, Config},
         data::Data,
         scenario::results::Results,
     };
@@ -315,6 +323,7 @@ mod tests {
     #[allow(
         clippy::cast_possible_truncation,
         clippy::cast_possible_wrap,
+        clippy::cast_precision_loss,
         clippy::too_many_lines,
         clippy::similar_names
     )]
@@ -405,25 +414,40 @@ mod tests {
                 &results_cpu.estimations.system_states.at_step(step),
                 config.algorithm.maximum_regularization_threshold,
             );
+            // the kernels fuse the mean squared error with the maximum and
+            // difference regularizations
+            let mse_scaling = 1.0 / number_of_sensors as f32 * config.algorithm.mse_strength;
             calculate_derivatives_gains(
                 &mut results_cpu.derivatives.gains,
                 &results_cpu.estimations.ap_outputs_now,
-                &results_cpu.derivatives.maximum_regularization,
                 &results_cpu.derivatives.mapped_residuals,
-                &config.algorithm,
-                number_of_sensors,
+                mse_scaling,
+            );
+            calculate_derivatives_gains(
+                &mut results_cpu.derivatives.gains,
+                &results_cpu.estimations.ap_outputs_now,
+                &results_cpu.derivatives.maximum_regularization,
+                config.algorithm.maximum_regularization_strength,
             );
+            let functional_description = &results_cpu
+                .model
+                .as_ref()
+                .context("Model not available for derivatives coefficients test")?
+                .functional_description;
             calculate_derivatives_coefs_textbook(
                 &mut results_cpu.derivatives,
                 &results_cpu.estimations,
-                &results_cpu
-                    .model
-                    .as_ref()
-                    .context("Model not available for derivatives coefficients test")?
-                    .functional_description,
+                functional_description,
                 step,
-                &config.algorithm,
+                mse_scaling,
             )?;
+            calculate_derivatives_difference_regularization(
+                &mut results_cpu.derivatives.coefs,
+                &functional_description.ap_params,
+                step,
+                &APDerivative::Textbook,
+                config.algorithm.difference_regularization_strength,
+            );
             results_gpu
                 .estimations
                 .step
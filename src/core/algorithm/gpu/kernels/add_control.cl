@@ -1,6 +1,7 @@
 __kernel void add_control_function(
     __global float* system_states,
     __global const float* control_matrix,
+    __global const int* control_onsets,
     __global const int* step,
     __global float* control_values,
     const int num_states
@@ -8,7 +9,11 @@ __kernel void add_control_function(
     int state_idx = get_global_id(0);
     if (state_idx >= num_states) return;
     int step_idx = step[0];
+    int delayed_step_idx = step_idx - control_onsets[state_idx];
+    if (delayed_step_idx < 0) return;
     
-    system_states[step_idx * num_states + state_idx] += 
-        control_values[step_idx] * control_matrix[state_idx];
+    int system_state_idx = step_idx * num_states + state_idx;
+    STORE_STORED(system_states, system_state_idx,
+        LOAD_STORED(system_states, system_state_idx)
+            + control_values[delayed_step_idx] * control_matrix[state_idx]);
 }
\ No newline at end of file
@@ -9,7 +9,7 @@ __kernel void calculate_derivatives_coefs_fir(
 ) {
     int state_index = get_global_id(0);
     int offset_index = get_global_id(1);
-    int num_offsets = 78;
+    int num_offsets = NUM_OFFSETS;
     int step_idx = step[0];
     
     if (state_index >= num_states || offset_index >= num_offsets) return;
@@ -21,7 +21,7 @@ __kernel void calculate_derivatives_coefs_fir(
     int delay = ap_delays[coef_index];
     float coef = ap_coefs[coef_index];
     if (step_idx >= delay) {
-        float state_val = system_states[(step_idx - delay) * num_states + output_state];
+        float state_val = LOAD_STORED(system_states, (step_idx - delay) * num_states + output_state);
         float derivative_old = derivatives_fir[state_index * num_offsets + offset_index];
         float derivative_new = (-coef) * derivative_old + state_val;
         derivatives_fir[state_index * num_offsets + offset_index] = derivative_new;
@@ -38,7 +38,7 @@ __kernel void calculate_derivatives_coefs_iir(
 ) {
     int state_index = get_global_id(0);
     int offset_index = get_global_id(1);
-    int num_offsets = 78;
+    int num_offsets = NUM_OFFSETS;
     int step_idx = step[0];
     
     if (state_index >= num_states || offset_index >= num_offsets) return;
@@ -70,14 +70,14 @@ __kernel void calculate_derivatives_coefs_combine(
     int lid_x = get_local_id(0);
     int lid_y = get_local_id(1);
     int local_idx = lid_y * 3 + lid_x;
-    int num_offsets = 78;
+    int num_offsets = NUM_OFFSETS;
     int coef_index = (state_index / 3) * (num_offsets / 3) + (offset_index / 3);
     
     float contribution = 0.0f;
     if (state_index < num_states && offset_index < num_offsets) {  
         float iir = derivatives_iir[state_index * num_offsets + offset_index];
         float fir = derivatives_fir[state_index * num_offsets + offset_index];
-        float ap_gain = ap_gains[state_index * num_offsets + offset_index];
+        float ap_gain = LOAD_STORED(ap_gains, state_index * num_offsets + offset_index);
         float mapped_residual = mapped_residuals[state_index];
         
         contribution = ((fir - iir) * ap_gain * mapped_residual) * mse_scaling;
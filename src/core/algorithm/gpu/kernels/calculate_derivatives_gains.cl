@@ -9,7 +9,7 @@ __kernel void calculate_derivatives_gains(
 ) {
     int state_index = get_global_id(0);
     int offset_index = get_global_id(1);
-    int num_offsets = 78;
+    int num_offsets = NUM_OFFSETS;
     
     if (state_index >= num_states || offset_index >= num_offsets) return;
     
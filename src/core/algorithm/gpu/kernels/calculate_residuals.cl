@@ -2,6 +2,7 @@ __kernel void calculate_residuals(
     __global float* residuals,
     __global const float* predicted_measurements,
     __global const float* actual_measurements,
+    __global const float* channel_mask,
     __global int* step,
     __global int* beat,
     int num_sensors,
@@ -12,5 +13,5 @@ __kernel void calculate_residuals(
     int step_idx = step[0];
     int beat_idx = beat[0];
     
-    residuals[sensor_idx] = predicted_measurements[beat_idx * num_sensors * num_steps + step_idx * num_sensors + sensor_idx] - actual_measurements[beat_idx * num_sensors * num_steps + step_idx * num_sensors + sensor_idx];
+    residuals[sensor_idx] = (predicted_measurements[beat_idx * num_sensors * num_steps + step_idx * num_sensors + sensor_idx] - actual_measurements[beat_idx * num_sensors * num_steps + step_idx * num_sensors + sensor_idx]) * channel_mask[sensor_idx];
 }
\ No newline at end of file
@@ -19,17 +19,17 @@ __kernel void calculate_maximum_regularization(
     
     if (voxel_idx < num_voxels) {
         int state_idx = voxel_idx * 3;
-        float sum = fabs(system_states[step_idx * num_states + state_idx]) + 
-                    fabs(system_states[step_idx * num_states + state_idx + 1]) + 
-                    fabs(system_states[step_idx * num_states + state_idx + 2]);
+        float sum = fabs(LOAD_STORED(system_states, step_idx * num_states + state_idx)) + 
+                    fabs(LOAD_STORED(system_states, step_idx * num_states + state_idx + 1)) + 
+                    fabs(LOAD_STORED(system_states, step_idx * num_states + state_idx + 2));
                     
         if (sum > regularization_threshold) {
             float factor = sum - regularization_threshold;
             factor_squared = factor * factor;
 
-            maximum_regularization[state_idx] = factor * sign(system_states[step_idx * num_states + state_idx]);
-            maximum_regularization[state_idx + 1] = factor * sign(system_states[step_idx * num_states + state_idx + 1]);
-            maximum_regularization[state_idx + 2] = factor * sign(system_states[step_idx * num_states + state_idx + 2]);
+            maximum_regularization[state_idx] = factor * sign(LOAD_STORED(system_states, step_idx * num_states + state_idx));
+            maximum_regularization[state_idx + 1] = factor * sign(LOAD_STORED(system_states, step_idx * num_states + state_idx + 1));
+            maximum_regularization[state_idx + 2] = factor * sign(LOAD_STORED(system_states, step_idx * num_states + state_idx + 2));
         } else {
             maximum_regularization[state_idx] = 0.0f;
             maximum_regularization[state_idx + 1] = 0.0f;
@@ -0,0 +1,10 @@
+// Load and store of the system states and gains, see GpuPrecision. In half
+// precision two values are packed into each float of the buffer and
+// converted on access, so all arithmetic stays in single precision.
+#ifdef HALF_PRECISION
+#define LOAD_STORED(buffer, index) vload_half((index), (__global const half*)(buffer))
+#define STORE_STORED(buffer, index, value) vstore_half((value), (index), (__global half*)(buffer))
+#else
+#define LOAD_STORED(buffer, index) ((buffer)[(index)])
+#define STORE_STORED(buffer, index, value) ((buffer)[(index)] = (value))
+#endif
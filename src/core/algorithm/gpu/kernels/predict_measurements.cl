@@ -19,7 +19,7 @@ __kernel void predict_measurements(
     int beat_idx = beat[0];
     
     float contribution = measurement_matrix[beat_idx * num_sensors * num_states + sensor_idx * num_states + state] 
-                      * system_states[step_idx * num_states + state];
+                      * LOAD_STORED(system_states, step_idx * num_states + state);
     
     atomic_add_float(&measurements[beat_idx * num_sensors * num_steps + step_idx * num_sensors + sensor_idx], contribution);
 }
\ No newline at end of file
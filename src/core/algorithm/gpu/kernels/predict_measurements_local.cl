@@ -23,7 +23,7 @@ __kernel void predict_measurements(
     float contribution = 0.0f;
     if (state < num_states) {
         contribution = measurement_matrix[beat_idx * num_sensors * num_states + sensor_idx * num_states + state] 
-                    * system_states[step_idx * num_states + state];
+                    * LOAD_STORED(system_states, step_idx * num_states + state);
     }
     
     partial_sums[lid] = contribution;
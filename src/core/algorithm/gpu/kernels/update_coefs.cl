@@ -2,17 +2,18 @@ __kernel void update_coefs(
     __global float* coefs,
     __global int* delays,
     __global const float* derivatives_coefs,
+    __global const float* coefs_mask,
     float learning_rate_over_batch_size,
     int num_voxels
     ){
         int voxel_idx = get_global_id(0);
         int offset_idx = get_global_id(1);
-        int num_offsets = 26;
+        int num_offsets = NUM_OFFSETS / 3;
         float margin = 1e-4;
 
         if (voxel_idx >= num_voxels || offset_idx >= num_offsets) return;
 
-        coefs[voxel_idx * num_offsets + offset_idx] -= derivatives_coefs[voxel_idx * num_offsets + offset_idx] * learning_rate_over_batch_size;
+        coefs[voxel_idx * num_offsets + offset_idx] -= derivatives_coefs[voxel_idx * num_offsets + offset_idx] * coefs_mask[voxel_idx] * learning_rate_over_batch_size;
 
         int delay = delays[voxel_idx * num_offsets + offset_idx];
 
@@ -1,14 +1,17 @@
 __kernel void update_gains(
     __global float* gains,
     __global const float* derivatives_gains,
+    __global const float* gains_mask,
     float learning_rate_over_batch_size,
     int num_states
     ){
         int state_idx = get_global_id(0);
         int offset_idx = get_global_id(1);
-        int num_offsets = 78;
+        int num_offsets = NUM_OFFSETS;
 
         if (state_idx >= num_states || offset_idx >= num_offsets) return;
 
-        gains[state_idx * num_offsets + offset_idx] -= derivatives_gains[state_idx * num_offsets + offset_idx] * learning_rate_over_batch_size;
+        int gain_idx = state_idx * num_offsets + offset_idx;
+        STORE_STORED(gains, gain_idx,
+            LOAD_STORED(gains, gain_idx) - derivatives_gains[gain_idx] * gains_mask[state_idx / 3] * learning_rate_over_batch_size);
     }
\ No newline at end of file
@@ -0,0 +1,213 @@
+use anyhow::{Context, Result};
+use ocl::{Buffer, Queue};
+
+use crate::core::config::algorithm::GpuPrecision;
+
+/// Returns the kernel source defining the `LOAD_STORED` and `STORE_STORED`
+/// macros for the given precision. Kernels accessing the system states or
+/// gains have to be built with it in front of their own source.
+///
+/// # Errors
+///
+/// Returns an error if the kernel source cannot be read.
+#[tracing::instrument(level = "trace")]
+pub fn kernel_header(precision: GpuPrecision) -> Result<String> {
+    let precision_src = std::fs::read_to_string("src/core/algorithm/gpu/kernels/precision.cl")
+        .context("Failed to read precision kernel source file")?;
+    Ok(match precision {
+        GpuPrecision::Single => precision_src,
+        GpuPrecision::Half => format!("#define HALF_PRECISION\n{precision_src}"),
+    })
+}
+
+/// Creates a buffer holding the values in the given precision. In half
+/// precision two values are packed into each float of the buffer.
+///
+/// # Errors
+///
+/// Returns an error if the buffer cannot be created.
+#[tracing::instrument(level = "trace", skip(queue, values))]
+pub fn stored_buffer(
+    queue: &Queue,
+    values: &[f32],
+    precision: GpuPrecision,
+) -> Result<Buffer<f32>> {
+    let packed;
+    let host_values = match precision {
+        GpuPrecision::Single => values,
+        GpuPrecision::Half => {
+            packed = pack_half(values);
+            packed.as_slice()
+        }
+    };
+    Buffer::builder()
+        .queue(queue.clone())
+        .len(host_values.len())
+        .copy_host_slice(host_values)
+        .build()
+        .context("Failed to build GPU buffer")
+}
+
+/// Reads the values of a buffer created with [`stored_buffer`].
+///
+/// # Errors
+///
+/// Returns an error if reading the buffer fails.
+#[tracing::instrument(level = "trace", skip(buffer, values))]
+pub fn read_stored_buffer(
+    buffer: &Buffer<f32>,
+    values: &mut [f32],
+    precision: GpuPrecision,
+) -> Result<()> {
+    match precision {
+        GpuPrecision::Single => buffer
+            .read(values)
+            .enq()
+            .context("Failed to read data from GPU buffer")?,
+        GpuPrecision::Half => {
+            let mut packed = vec![0.0; values.len().div_ceil(2)];
+            buffer
+                .read(&mut packed)
+                .enq()
+                .context("Failed to read half precision data from GPU buffer")?;
+            unpack_half(&packed, values);
+        }
+    }
+    Ok(())
+}
+
+/// Packs the values as half precision floats, the even indices into the
+/// lower and the odd indices into the upper 16 bits of each float.
+#[tracing::instrument(level = "trace", skip_all)]
+fn pack_half(values: &[f32]) -> Vec<f32> {
+    values
+        .chunks(2)
+        .map(|pair| {
+            let low = u32::from(f32_to_f16_bits(pair[0]));
+            let high = pair
+                .get(1)
+                .map_or(0, |value| u32::from(f32_to_f16_bits(*value)));
+            f32::from_bits(low | (high << 16))
+        })
+        .collect()
+}
+
+/// Unpacks values packed with [`pack_half`].
+#[allow(clippy::cast_possible_truncation)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn unpack_half(packed: &[f32], values: &mut [f32]) {
+    for (index, value) in values.iter_mut().enumerate() {
+        let bits = packed[index / 2].to_bits();
+        let half = if index % 2 == 0 { bits } else { bits >> 16 };
+        *value = f16_bits_to_f32(half as u16);
+    }
+}
+
+/// Converts a single precision float to the bits of the nearest half
+/// precision float, rounding ties to even like `vstore_half`.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace")]
+fn f32_to_f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = (bits >> 16) & 0x8000;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+    if exponent == 0xff {
+        let nan = if mantissa == 0 { 0 } else { 0x0200 };
+        return (sign | 0x7c00 | nan) as u16;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return (sign | 0x7c00) as u16;
+    }
+    // the shift drops the mantissa bits that do not fit into the half
+    let (shift, mantissa, half) = if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign as u16;
+        }
+        (14 - half_exponent as u32, mantissa | 0x0080_0000, sign)
+    } else {
+        (13, mantissa, sign | ((half_exponent as u32) << 10))
+    };
+    let half_mantissa = mantissa >> shift;
+    let halfway = 1 << (shift - 1);
+    let remainder = mantissa & ((halfway << 1) - 1);
+    // a carry out of the mantissa correctly increments the exponent
+    let round_up = remainder > halfway || (remainder == halfway && half_mantissa & 1 == 1);
+    (half + half_mantissa + u32::from(round_up)) as u16
+}
+
+/// Converts the bits of a half precision float to a single precision float.
+#[tracing::instrument(level = "trace")]
+fn f16_bits_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exponent = u32::from((bits >> 10) & 0x1f);
+    let mantissa = u32::from(bits & 0x03ff);
+    let magnitude = match (exponent, mantissa) {
+        (0, 0) => 0,
+        (0, _) => {
+            let value = f32::from(bits & 0x03ff) * 2.0_f32.powi(-24);
+            return if sign == 0 { value } else { -value };
+        }
+        (0x1f, 0) => 0x7f80_0000,
+        (0x1f, _) => 0x7fc0_0000,
+        _ => ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(sign | magnitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn representable_values_round_trip_exactly() {
+        for value in [
+            0.0,
+            -0.0,
+            1.0,
+            -2.5,
+            0.333_251_95,
+            65504.0,
+            2.0_f32.powi(-24),
+        ] {
+            assert_eq!(
+                f16_bits_to_f32(f32_to_f16_bits(value)).to_bits(),
+                value.to_bits()
+            );
+        }
+    }
+
+    #[test]
+    fn conversion_rounds_to_nearest_even() {
+        // 1 + 2^-11 lies halfway between 1 and the next half
+        assert_eq!(f32_to_f16_bits(1.0 + 2.0_f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16_bits(1.0 + 3.0 * 2.0_f32.powi(-11)), 0x3c02);
+        assert_eq!(f32_to_f16_bits(1e6), 0x7c00);
+        assert_eq!(f32_to_f16_bits(2.0_f32.powi(-26)), 0x0000);
+        assert!(f16_bits_to_f32(f32_to_f16_bits(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn packed_values_keep_three_significant_digits() {
+        let values: Vec<f32> = (0..7)
+            .map(|index| (index as f32 - 3.0) * 0.123_456)
+            .collect();
+
+        let packed = pack_half(&values);
+        let mut unpacked = vec![0.0; values.len()];
+        unpack_half(&packed, &mut unpacked);
+
+        assert_eq!(packed.len(), 4);
+        for (value, unpacked) in values.iter().zip(&unpacked) {
+            assert_relative_eq!(value, unpacked, max_relative = 1e-3);
+        }
+    }
+}
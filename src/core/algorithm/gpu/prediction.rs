@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use ocl::{Kernel, Program};
 
-use super::GPU;
+use super::{precision::kernel_header, GPU};
 use crate::core::{algorithm::estimation::EstimationsGPU, model::ModelGPU};
 
 #[allow(clippy::struct_field_names)]
@@ -34,26 +34,41 @@ impl PredictionKernel {
             .context("Failed to read atomic kernel source file")?;
         let innovate_src = std::fs::read_to_string("src/core/algorithm/gpu/kernels/innovate.cl")
             .context("Failed to read innovate kernel source file")?;
+        // the system states and gains share the same storage precision
+        let precision_src = kernel_header(estimations.precision)?;
+        let number_of_offsets = model.functional_description.ap_params.number_of_offsets;
         let innovate_program = Program::builder()
-            .src(format!("{atomic_src}\n{innovate_src}"))
+            .src(format!("{precision_src}\n{atomic_src}\n{innovate_src}"))
+            .cmplr_def("NUM_OFFSETS", number_of_offsets)
             .build(context)
             .context("Failed to build OpenCL program for innovate kernels")?;
 
+        // one work group per state reduces the contributions of all offsets
+        let offsets_work_group_size = (number_of_offsets as usize).next_power_of_two();
+        let max_size = device
+            .max_wg_size()
+            .context("Failed to query GPU device maximum work group size")?;
+        anyhow::ensure!(
+            offsets_work_group_size <= max_size,
+            "The neighborhood with {number_of_offsets} gains per state exceeds the maximum \
+             work group size of {max_size} - reduce the neighborhood radius or use the CPU"
+        );
         let innovate_kernel = Kernel::builder()
             .program(&innovate_program)
             .name("innovate_system_states")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 128])
-            .local_work_size([1, 128])
+            .global_work_size([number_of_states as usize, offsets_work_group_size])
+            .local_work_size([1, offsets_work_group_size])
             .arg(&estimations.ap_outputs_now)
             .arg(&estimations.ap_outputs_last)
             .arg(&estimations.system_states)
             .arg(&model.functional_description.ap_params.coefs)
             .arg(&model.functional_description.ap_params.delays)
+            .arg(&model.functional_description.ap_params.refractory_gates)
             .arg(&model.functional_description.ap_params.gains)
             .arg(&model.functional_description.ap_params.output_state_indices)
             .arg(&estimations.step)
-            .arg_local::<f32>(128)
+            .arg_local::<f32>(offsets_work_group_size)
             .arg_named("num_states", number_of_states)
             .build()
             .context("Failed to build innovate system states kernel")?;
@@ -62,7 +77,7 @@ impl PredictionKernel {
             std::fs::read_to_string("src/core/algorithm/gpu/kernels/add_control.cl")
                 .context("Failed to read add_control kernel source file")?;
         let add_control_program = Program::builder()
-            .src(add_control_src)
+            .src(format!("{precision_src}\n{add_control_src}"))
             .build(context)
             .context("Failed to build OpenCL program for add_control kernel")?;
         let add_control_kernel = Kernel::builder()
@@ -75,6 +90,10 @@ impl PredictionKernel {
                 "control_matrix",
                 &model.functional_description.control_matrix,
             )
+            .arg_named(
+                "control_onsets",
+                &model.functional_description.control_onsets,
+            )
             .arg_named("step", &estimations.step)
             .arg_named(
                 "control_values",
@@ -95,7 +114,9 @@ impl PredictionKernel {
             std::fs::read_to_string("src/core/algorithm/gpu/kernels/predict_measurements_local.cl")
                 .context("Failed to read predict_measurements_local kernel source file")?;
         let predict_measurements_program = Program::builder()
-            .src(format!("{atomic_src}\n{predict_measurements_src}"))
+            .src(format!(
+                "{precision_src}\n{atomic_src}\n{predict_measurements_src}"
+            ))
             .build(context)
             .context("Failed to build OpenCL program for predict_measurements kernel")?;
         let predict_measurements_kernel = Kernel::builder()
@@ -123,7 +144,7 @@ impl PredictionKernel {
         })
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "prediction"))]
     pub fn execute(&self) -> Result<()> {
         // TODO: Optimize prediction by running multiple beats in parallel using async kernel execution.
         // This would allow better GPU utilization by processing independent beats simultaneously.
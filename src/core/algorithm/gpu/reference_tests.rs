@@ -0,0 +1,444 @@
+//! Tests of the `OpenCL` kernels against the CPU implementation on small
+//! randomized problems.
+//!
+//! Only compiled with the `opencl-tests` feature, as they need a machine
+//! with an `OpenCL` GPU. Run them with `just test-opencl`.
+
+use anyhow::{Context, Result};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use super::{
+    derivation::DerivationKernel, prediction::PredictionKernel, update::UpdateKernel, GPU,
+};
+use crate::core::{
+    algorithm::{
+        estimation::{calculate_residuals, prediction::calculate_system_prediction},
+        refinement::{
+            derivation::{
+                calculate_derivatives_coefs_textbook,
+                calculate_derivatives_difference_regularization, calculate_derivatives_gains,
+                calculate_mapped_residuals, calculate_maximum_regularization,
+            },
+            update::{roll_delays, update_delays_sgd, update_gains_sgd},
+            Optimizer,
+        },
+    },
+    config::{algorithm::APDerivative, Config},
+    data::Data,
+    model::Model,
+    scenario::results::{Results, ResultsGPU},
+};
+
+/// Seeds of the randomized problems every kernel is tested on.
+const SEEDS: [u64; 3] = [0, 1, 2];
+
+/// A small problem that is solved step by step on the CPU and the GPU.
+struct Harness {
+    config: Config,
+    data: Data,
+    cpu: Results,
+    gpu: ResultsGPU,
+    from_gpu: Results,
+    prediction_kernel: PredictionKernel,
+    derivation_kernel: DerivationKernel,
+    update_kernel: UpdateKernel,
+    number_of_sensors: usize,
+    number_of_steps: usize,
+}
+
+impl Harness {
+    /// Simulates a coarse pathological heart and randomly perturbs the
+    /// all-pass gains and coefficients of the model, so that the estimation
+    /// differs from the simulation.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn new(seed: u64) -> Result<Self> {
+        let mut config = Config::default();
+        config.simulation.duration_s = 0.05;
+        config.simulation.model.common.pathological = true;
+        config.simulation.model.common.voxel_size_mm = 10.0;
+        config.algorithm.freeze_delays = false;
+        config.algorithm.freeze_gains = false;
+        let data = Data::from_simulation_config(&config.simulation)?;
+        let mut model = Model::from_model_config(
+            &config.simulation.model,
+            config.simulation.sample_rate_hz,
+            config.simulation.duration_s,
+        )?;
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let ap_params = &mut model.functional_description.ap_params;
+        ap_params
+            .gains
+            .iter_mut()
+            .for_each(|gain| *gain *= rng.random_range(0.8..1.2));
+        ap_params.coefs.iter_mut().for_each(|coef| {
+            *coef = (*coef + rng.random_range(-0.1..0.1)).clamp(0.05, 0.95);
+        });
+
+        let number_of_states = model.spatial_description.voxels.count_states();
+        let number_of_sensors = model.spatial_description.sensors.count();
+        let number_of_steps = model.functional_description.control_function_values.len();
+        let mut cpu = Results::new(
+            1,
+            number_of_steps,
+            number_of_sensors,
+            number_of_states,
+            1,
+            0,
+            0,
+            Optimizer::Sgd,
+            ap_params.neighborhood_radius()?,
+        );
+        cpu.model = Some(model);
+
+        let gpu_device = GPU::new()?;
+        let gpu = cpu.to_gpu(&gpu_device.queue)?;
+        let actual_measurements = data.simulation.measurements.to_gpu(&gpu_device.queue)?;
+        let prediction_kernel = PredictionKernel::new(
+            &gpu_device,
+            &gpu.estimations,
+            &gpu.model,
+            number_of_states as i32,
+            number_of_sensors as i32,
+            number_of_steps as i32,
+        )?;
+        let derivation_kernel = DerivationKernel::new(
+            &gpu_device,
+            &gpu.estimations,
+            &gpu.derivatives,
+            &actual_measurements,
+            &gpu.model,
+            number_of_states as i32,
+            number_of_sensors as i32,
+            number_of_steps as i32,
+            &config.algorithm,
+        )?;
+        let update_kernel = UpdateKernel::new(
+            &gpu_device,
+            &gpu.derivatives,
+            &gpu.model,
+            number_of_states as i32,
+            number_of_steps as i32,
+            &config.algorithm,
+        )?;
+        let from_gpu = cpu.clone();
+        Ok(Self {
+            config,
+            data,
+            cpu,
+            gpu,
+            from_gpu,
+            prediction_kernel,
+            derivation_kernel,
+            update_kernel,
+            number_of_sensors,
+            number_of_steps,
+        })
+    }
+
+    /// Runs the prediction and the derivation of one step on both paths and
+    /// reads back the GPU results.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss
+    )]
+    fn step(&mut self, step: usize) -> Result<()> {
+        let algorithm = &self.config.algorithm;
+        let functional_description = &self
+            .cpu
+            .model
+            .as_ref()
+            .context("Model should be available on the CPU")?
+            .functional_description;
+        calculate_system_prediction(&mut self.cpu.estimations, functional_description, 0, step)?;
+        calculate_residuals(&mut self.cpu.estimations, &self.data, 0, step);
+        calculate_mapped_residuals(
+            &mut self.cpu.derivatives.mapped_residuals,
+            &self.cpu.estimations.residuals,
+            &functional_description.measurement_matrix.at_beat(0),
+        );
+        calculate_maximum_regularization(
+            &mut self.cpu.derivatives.maximum_regularization,
+            &mut self.cpu.derivatives.maximum_regularization_sum,
+            &self.cpu.estimations.system_states.at_step(step),
+            algorithm.maximum_regularization_threshold,
+        );
+        // the kernels fuse the mean squared error with the maximum and
+        // difference regularizations
+        let mse_scaling = 1.0 / self.number_of_sensors as f32 * algorithm.mse_strength;
+        calculate_derivatives_gains(
+            &mut self.cpu.derivatives.gains,
+            &self.cpu.estimations.ap_outputs_now,
+            &self.cpu.derivatives.mapped_residuals,
+            mse_scaling,
+        );
+        calculate_derivatives_gains(
+            &mut self.cpu.derivatives.gains,
+            &self.cpu.estimations.ap_outputs_now,
+            &self.cpu.derivatives.maximum_regularization,
+            algorithm.maximum_regularization_strength,
+        );
+        calculate_derivatives_coefs_textbook(
+            &mut self.cpu.derivatives,
+            &self.cpu.estimations,
+            functional_description,
+            step,
+            mse_scaling,
+        )?;
+        calculate_derivatives_difference_regularization(
+            &mut self.cpu.derivatives.coefs,
+            &functional_description.ap_params,
+            step,
+            &APDerivative::Textbook,
+            algorithm.difference_regularization_strength,
+        );
+
+        self.gpu
+            .estimations
+            .step
+            .write([step as i32].as_slice())
+            .enq()
+            .context("Failed to write step value to GPU buffer")?;
+        self.prediction_kernel.execute()?;
+        self.derivation_kernel.execute()?;
+        self.from_gpu.update_from_gpu(&self.gpu)
+    }
+
+    /// Applies the accumulated derivatives on both paths and reads back the
+    /// GPU results.
+    fn update(&mut self) -> Result<()> {
+        let learning_rate = self.config.algorithm.learning_rate;
+        let ap_params = &mut self
+            .cpu
+            .model
+            .as_mut()
+            .context("Model should be available on the CPU")?
+            .functional_description
+            .ap_params;
+        update_gains_sgd(
+            &mut ap_params.gains,
+            &self.cpu.derivatives.gains,
+            learning_rate,
+            self.number_of_steps,
+        );
+        update_delays_sgd(
+            &mut ap_params.coefs,
+            &self.cpu.derivatives.coefs,
+            learning_rate,
+            self.number_of_steps,
+            0.0,
+        );
+        roll_delays(&mut ap_params.coefs, &mut ap_params.delays);
+        self.update_kernel.execute()?;
+        self.from_gpu.update_from_gpu(&self.gpu)
+    }
+}
+
+/// Fails if any value differs by more than `tolerance`, relative to the
+/// magnitude of the CPU value but at least absolute, naming the first
+/// diverging index.
+fn assert_close(name: &str, cpu: &[f32], gpu: &[f32], tolerance: f32) -> Result<()> {
+    anyhow::ensure!(
+        cpu.len() == gpu.len(),
+        "{name}: CPU has {} values, GPU has {}",
+        cpu.len(),
+        gpu.len()
+    );
+    if let Some((index, (expected, actual))) =
+        cpu.iter()
+            .zip(gpu)
+            .enumerate()
+            .find(|(_, (expected, actual))| {
+                (*expected - *actual).abs() > tolerance * expected.abs().max(1.0)
+            })
+    {
+        anyhow::bail!("{name} diverges at index {index}: CPU {expected}, GPU {actual}");
+    }
+    Ok(())
+}
+
+fn slice<'a>(name: &str, values: Option<&'a [f32]>) -> Result<&'a [f32]> {
+    values.with_context(|| format!("{name} should be contiguous"))
+}
+
+#[test]
+fn prediction_matches_cpu() -> Result<()> {
+    for seed in SEEDS {
+        let mut harness = Harness::new(seed)?;
+        for step in 0..harness.number_of_steps {
+            harness.step(step)?;
+            assert_close(
+                "AP outputs",
+                slice(
+                    "AP outputs",
+                    harness.cpu.estimations.ap_outputs_now.as_slice(),
+                )?,
+                slice(
+                    "AP outputs",
+                    harness.from_gpu.estimations.ap_outputs_now.as_slice(),
+                )?,
+                1e-5,
+            )?;
+        }
+        assert_close(
+            "System states",
+            slice(
+                "System states",
+                harness.cpu.estimations.system_states.as_slice(),
+            )?,
+            slice(
+                "System states",
+                harness.from_gpu.estimations.system_states.as_slice(),
+            )?,
+            1e-5,
+        )?;
+        assert_close(
+            "Measurements",
+            slice(
+                "Measurements",
+                harness.cpu.estimations.measurements.as_slice(),
+            )?,
+            slice(
+                "Measurements",
+                harness.from_gpu.estimations.measurements.as_slice(),
+            )?,
+            1e-4,
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn residuals_match_cpu() -> Result<()> {
+    for seed in SEEDS {
+        let mut harness = Harness::new(seed)?;
+        for step in 0..harness.number_of_steps {
+            harness.step(step)?;
+            assert_close(
+                "Residuals",
+                slice("Residuals", harness.cpu.estimations.residuals.as_slice())?,
+                slice(
+                    "Residuals",
+                    harness.from_gpu.estimations.residuals.as_slice(),
+                )?,
+                1e-4,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn mapped_residuals_match_cpu() -> Result<()> {
+    for seed in SEEDS {
+        let mut harness = Harness::new(seed)?;
+        for step in 0..harness.number_of_steps {
+            harness.step(step)?;
+            assert_close(
+                "Mapped residuals",
+                slice(
+                    "Mapped residuals",
+                    harness.cpu.derivatives.mapped_residuals.as_slice(),
+                )?,
+                slice(
+                    "Mapped residuals",
+                    harness.from_gpu.derivatives.mapped_residuals.as_slice(),
+                )?,
+                1e-4,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn derivatives_match_cpu() -> Result<()> {
+    for seed in SEEDS {
+        let mut harness = Harness::new(seed)?;
+        for step in 0..harness.number_of_steps {
+            harness.step(step)?;
+            assert_close(
+                "Maximum regularization",
+                slice(
+                    "Maximum regularization",
+                    harness.cpu.derivatives.maximum_regularization.as_slice(),
+                )?,
+                slice(
+                    "Maximum regularization",
+                    harness
+                        .from_gpu
+                        .derivatives
+                        .maximum_regularization
+                        .as_slice(),
+                )?,
+                1e-4,
+            )?;
+            assert_close(
+                "Gain derivatives",
+                slice("Gain derivatives", harness.cpu.derivatives.gains.as_slice())?,
+                slice(
+                    "Gain derivatives",
+                    harness.from_gpu.derivatives.gains.as_slice(),
+                )?,
+                1e-4,
+            )?;
+            assert_close(
+                "Coefficient derivatives",
+                slice(
+                    "Coefficient derivatives",
+                    harness.cpu.derivatives.coefs.as_slice(),
+                )?,
+                slice(
+                    "Coefficient derivatives",
+                    harness.from_gpu.derivatives.coefs.as_slice(),
+                )?,
+                1e-4,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn update_matches_cpu() -> Result<()> {
+    for seed in SEEDS {
+        let mut harness = Harness::new(seed)?;
+        for step in 0..harness.number_of_steps {
+            harness.step(step)?;
+        }
+        harness.update()?;
+        let cpu = &harness
+            .cpu
+            .model
+            .as_ref()
+            .context("Model should be available on the CPU")?
+            .functional_description
+            .ap_params;
+        let gpu = &harness
+            .from_gpu
+            .model
+            .as_ref()
+            .context("Model should be read back from the GPU")?
+            .functional_description
+            .ap_params;
+        assert_close(
+            "Gains",
+            slice("Gains", cpu.gains.as_slice())?,
+            slice("Gains", gpu.gains.as_slice())?,
+            1e-5,
+        )?;
+        assert_close(
+            "Coefficients",
+            slice("Coefficients", cpu.coefs.as_slice())?,
+            slice("Coefficients", gpu.coefs.as_slice())?,
+            1e-5,
+        )?;
+        anyhow::ensure!(
+            *cpu.delays == *gpu.delays,
+            "Delays diverge after the update for seed {seed}"
+        );
+    }
+    Ok(())
+}
@@ -34,13 +34,11 @@ impl ResetKernel {
         estimations: &EstimationsGPU,
         derivatives: &DerivativesGPU,
         metrics: &MetricsGPU,
-        number_of_states: i32,
         number_of_sensors: i32,
         number_of_steps: i32,
     ) -> Result<Self> {
         let context = &gpu.context;
         let queue = &gpu.queue;
-        let number_of_voxels = number_of_states / 3;
 
         let reset_src = std::fs::read_to_string("src/core/algorithm/gpu/kernels/reset.cl")
             .context("Failed to read reset kernel source file")?;
@@ -52,7 +50,8 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_steps * number_of_states)
+            // packed in half precision, so the buffer length is used
+            .global_work_size(estimations.system_states.len())
             .arg(&estimations.system_states)
             .build()
             .context("Failed to build system states reset kernel")?;
@@ -76,7 +75,7 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_states * 78)
+            .global_work_size(estimations.ap_outputs_now.len())
             .arg(&estimations.ap_outputs_now)
             .build()
             .context("Failed to build AP outputs reset kernel")?;
@@ -84,7 +83,7 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_states * 78)
+            .global_work_size(derivatives.gains.len())
             .arg(&derivatives.gains)
             .build()
             .context("Failed to build gains reset kernel")?;
@@ -92,7 +91,7 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_voxels * 26)
+            .global_work_size(derivatives.coefs.len())
             .arg(&derivatives.coefs)
             .build()
             .context("Failed to build coefficients reset kernel")?;
@@ -100,7 +99,7 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_states * 78)
+            .global_work_size(derivatives.coefs_iir.len())
             .arg(&derivatives.coefs_iir)
             .build()
             .context("Failed to build IIR coefficients reset kernel")?;
@@ -108,7 +107,7 @@ impl ResetKernel {
             .program(&reset_program)
             .name("reset_float")
             .queue(queue.clone())
-            .global_work_size(number_of_states * 78)
+            .global_work_size(derivatives.coefs_fir.len())
             .arg(&derivatives.coefs_fir)
             .build()
             .context("Failed to build FIR coefficients reset kernel")?;
@@ -0,0 +1,25 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> system_states: array<f32>;
+@group(0) @binding(2) var<storage, read> control_matrix: array<f32>;
+@group(0) @binding(3) var<storage, read> control_onsets: array<i32>;
+@group(0) @binding(4) var<storage, read> control_values: array<f32>;
+@group(0) @binding(5) var<storage, read> counters: array<i32>;
+
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn add_control_function(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let state = element_index(group_id, num_groups, local_index);
+    if (state >= params.num_states) {
+        return;
+    }
+    let step = counters[STEP];
+    let delayed_step = step - control_onsets[state];
+    if (delayed_step < 0) {
+        return;
+    }
+    system_states[step * params.num_states + state] +=
+        control_values[delayed_step] * control_matrix[state];
+}
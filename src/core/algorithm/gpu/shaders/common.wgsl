@@ -0,0 +1,51 @@
+// Declarations shared by all shaders, prepended to each of them.
+struct Params {
+    num_states: i32,
+    num_sensors: i32,
+    num_steps: i32,
+    num_offsets: i32,
+    num_voxels: i32,
+    mse_scaling: f32,
+    regularization_strength: f32,
+    regularization_threshold: f32,
+    learning_rate: f32,
+    padding_0: i32,
+    padding_1: i32,
+    padding_2: i32,
+}
+
+// indices into the counters buffer
+const STEP: u32 = 0u;
+const BEAT: u32 = 1u;
+const EPOCH: u32 = 2u;
+
+const ELEMENT_GROUP_SIZE: u32 = 64u;
+const REDUCTION_GROUP_SIZE: u32 = 256u;
+
+var<workgroup> partial_sums: array<f32, REDUCTION_GROUP_SIZE>;
+
+// Dispatches with more than 65535 work groups are split along y.
+fn group_index(group_id: vec3<u32>, num_groups: vec3<u32>) -> i32 {
+    return i32(group_id.x + group_id.y * num_groups.x);
+}
+
+fn element_index(group_id: vec3<u32>, num_groups: vec3<u32>, local_index: u32) -> i32 {
+    return group_index(group_id, num_groups) * i32(ELEMENT_GROUP_SIZE) + i32(local_index);
+}
+
+// Sums the values of all invocations of the work group. Has to be called
+// in uniform control flow.
+fn reduce_partial_sums(local_index: u32, value: f32, group_size: u32) -> f32 {
+    partial_sums[local_index] = value;
+    workgroupBarrier();
+    for (var stride = group_size / 2u; stride > 0u; stride = stride / 2u) {
+        if (local_index < stride) {
+            partial_sums[local_index] += partial_sums[local_index + stride];
+        }
+        workgroupBarrier();
+    }
+    let sum = partial_sums[0];
+    // keeps a following reduction from overwriting the sum before it is read
+    workgroupBarrier();
+    return sum;
+}
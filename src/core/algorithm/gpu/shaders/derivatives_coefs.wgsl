@@ -0,0 +1,33 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> derivatives_coefs: array<f32>;
+@group(0) @binding(2) var<storage, read> derivatives_iir: array<f32>;
+@group(0) @binding(3) var<storage, read> derivatives_fir: array<f32>;
+@group(0) @binding(4) var<storage, read> ap_gains: array<f32>;
+@group(0) @binding(5) var<storage, read> mapped_residuals: array<f32>;
+
+// Each coefficient combines the gains of the three states of its voxel
+// and the three dimensions of its neighbor.
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn calculate_derivatives_coefs_combine(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let coef_index = element_index(group_id, num_groups, local_index);
+    let num_neighbors = params.num_offsets / 3;
+    if (coef_index >= params.num_voxels * num_neighbors) {
+        return;
+    }
+    let voxel = coef_index / num_neighbors;
+    let neighbor = coef_index % num_neighbors;
+
+    var sum = 0.0;
+    for (var state = voxel * 3; state < voxel * 3 + 3; state++) {
+        for (var offset = neighbor * 3; offset < neighbor * 3 + 3; offset++) {
+            let index = state * params.num_offsets + offset;
+            sum += (derivatives_fir[index] - derivatives_iir[index]) * ap_gains[index]
+                * mapped_residuals[state];
+        }
+    }
+    derivatives_coefs[coef_index] += sum * params.mse_scaling;
+}
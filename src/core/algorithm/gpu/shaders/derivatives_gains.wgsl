@@ -0,0 +1,21 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> derivatives_gains: array<f32>;
+@group(0) @binding(2) var<storage, read> ap_outputs: array<f32>;
+@group(0) @binding(3) var<storage, read> maximum_regularization: array<f32>;
+@group(0) @binding(4) var<storage, read> mapped_residuals: array<f32>;
+
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn calculate_derivatives_gains(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = element_index(group_id, num_groups, local_index);
+    if (index >= params.num_states * params.num_offsets) {
+        return;
+    }
+    let state = index / params.num_offsets;
+    derivatives_gains[index] += ap_outputs[index]
+        * (mapped_residuals[state] * params.mse_scaling
+            + maximum_regularization[state] * params.regularization_strength);
+}
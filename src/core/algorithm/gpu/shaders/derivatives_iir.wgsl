@@ -0,0 +1,25 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> derivatives_iir: array<f32>;
+@group(0) @binding(2) var<storage, read> ap_outputs_last: array<f32>;
+@group(0) @binding(3) var<storage, read> ap_coefs: array<f32>;
+@group(0) @binding(4) var<storage, read> ap_delays: array<i32>;
+@group(0) @binding(5) var<storage, read> counters: array<i32>;
+
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn calculate_derivatives_coefs_iir(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = element_index(group_id, num_groups, local_index);
+    if (index >= params.num_states * params.num_offsets) {
+        return;
+    }
+    let state = index / params.num_offsets;
+    let offset = index % params.num_offsets;
+    let coef_index = (state / 3) * (params.num_offsets / 3) + offset / 3;
+    if (counters[STEP] >= ap_delays[coef_index]) {
+        derivatives_iir[index] = -ap_coefs[coef_index] * derivatives_iir[index]
+            + ap_outputs_last[index];
+    }
+}
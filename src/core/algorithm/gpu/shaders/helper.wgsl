@@ -0,0 +1,11 @@
+@group(0) @binding(0) var<storage, read_write> counters: array<i32>;
+
+@compute @workgroup_size(1)
+fn increase_step() {
+    counters[STEP] += 1;
+}
+
+@compute @workgroup_size(1)
+fn increase_epoch() {
+    counters[EPOCH] += 1;
+}
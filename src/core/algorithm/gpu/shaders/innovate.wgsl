@@ -0,0 +1,60 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> ap_outputs_now: array<f32>;
+@group(0) @binding(2) var<storage, read_write> ap_outputs_last: array<f32>;
+@group(0) @binding(3) var<storage, read_write> system_states: array<f32>;
+@group(0) @binding(4) var<storage, read> ap_coefs: array<f32>;
+@group(0) @binding(5) var<storage, read> ap_delays: array<i32>;
+@group(0) @binding(6) var<storage, read> refractory_gates: array<i32>;
+@group(0) @binding(7) var<storage, read> ap_gains: array<f32>;
+@group(0) @binding(8) var<storage, read> output_state_indices: array<i32>;
+@group(0) @binding(9) var<storage, read> counters: array<i32>;
+
+const OFFSETS_GROUP_SIZE: u32 = 64u;
+
+// One work group per state sums the contributions of all offsets.
+@compute @workgroup_size(OFFSETS_GROUP_SIZE)
+fn innovate_system_states(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let state = group_index(group_id, num_groups);
+    if (state >= params.num_states) {
+        return;
+    }
+    let step = counters[STEP];
+
+    var contribution = 0.0;
+    for (var offset = i32(local_index); offset < params.num_offsets; offset += i32(OFFSETS_GROUP_SIZE)) {
+        let ap_index = state * params.num_offsets + offset;
+        let output_state = output_state_indices[ap_index];
+        if (output_state == -1) {
+            continue;
+        }
+        let coef_index = (state / 3) * (params.num_offsets / 3) + offset / 3;
+        ap_outputs_last[ap_index] = ap_outputs_now[ap_index];
+
+        let coef = ap_coefs[coef_index];
+        let delay = ap_delays[coef_index];
+        // re-entrant connections stay closed until the voxel recovered
+        let gate = refractory_gates[coef_index];
+
+        var input = 0.0;
+        if (delay <= step && gate <= step) {
+            input = system_states[(step - delay) * params.num_states + output_state];
+        }
+        var input_delayed = 0.0;
+        if (delay < step && gate < step) {
+            input_delayed = system_states[(step - delay - 1) * params.num_states + output_state];
+        }
+
+        let ap_output = coef * (input - ap_outputs_last[ap_index]) + input_delayed;
+        ap_outputs_now[ap_index] = ap_output;
+        contribution += ap_gains[ap_index] * ap_output;
+    }
+
+    let sum = reduce_partial_sums(local_index, contribution, OFFSETS_GROUP_SIZE);
+    if (local_index == 0u) {
+        system_states[step * params.num_states + state] = sum;
+    }
+}
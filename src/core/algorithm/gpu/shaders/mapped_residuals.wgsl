@@ -0,0 +1,30 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> mapped_residuals: array<f32>;
+@group(0) @binding(2) var<storage, read> measurement_matrix: array<f32>;
+@group(0) @binding(3) var<storage, read> residuals: array<f32>;
+@group(0) @binding(4) var<storage, read> counters: array<i32>;
+
+// One work group per state sums the residuals of all sensors.
+@compute @workgroup_size(REDUCTION_GROUP_SIZE)
+fn calculate_mapped_residuals(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let state = group_index(group_id, num_groups);
+    if (state >= params.num_states) {
+        return;
+    }
+    let beat = counters[BEAT];
+
+    var contribution = 0.0;
+    for (var sensor = i32(local_index); sensor < params.num_sensors; sensor += i32(REDUCTION_GROUP_SIZE)) {
+        contribution += measurement_matrix[(beat * params.num_sensors + sensor) * params.num_states + state]
+            * residuals[sensor];
+    }
+
+    let sum = reduce_partial_sums(local_index, contribution, REDUCTION_GROUP_SIZE);
+    if (local_index == 0u) {
+        mapped_residuals[state] = sum;
+    }
+}
@@ -0,0 +1,37 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> maximum_regularization: array<f32>;
+@group(0) @binding(2) var<storage, read_write> maximum_regularization_sum: array<f32>;
+@group(0) @binding(3) var<storage, read> system_states: array<f32>;
+@group(0) @binding(4) var<storage, read> counters: array<i32>;
+
+// Dispatched as a single work group, so the sum needs no atomics.
+@compute @workgroup_size(REDUCTION_GROUP_SIZE)
+fn calculate_maximum_regularization(
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let step = counters[STEP];
+
+    var factor_squared = 0.0;
+    for (var voxel = i32(local_index); voxel < params.num_voxels; voxel += i32(REDUCTION_GROUP_SIZE)) {
+        let state = voxel * 3;
+        let state_index = step * params.num_states + state;
+        let sum = abs(system_states[state_index])
+            + abs(system_states[state_index + 1])
+            + abs(system_states[state_index + 2]);
+
+        var factor = 0.0;
+        if (sum > params.regularization_threshold) {
+            factor = sum - params.regularization_threshold;
+            factor_squared += factor * factor;
+        }
+        for (var dimension = 0; dimension < 3; dimension++) {
+            maximum_regularization[state + dimension] =
+                factor * sign(system_states[state_index + dimension]);
+        }
+    }
+
+    let sum = reduce_partial_sums(local_index, factor_squared, REDUCTION_GROUP_SIZE);
+    if (local_index == 0u) {
+        maximum_regularization_sum[0] += sum;
+    }
+}
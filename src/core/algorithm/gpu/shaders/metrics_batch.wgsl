@@ -0,0 +1,35 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> loss_mse_batch: array<f32>;
+@group(0) @binding(2) var<storage, read_write> loss_maximum_regularization_batch: array<f32>;
+@group(0) @binding(3) var<storage, read_write> loss_batch: array<f32>;
+@group(0) @binding(4) var<storage, read> loss_mse: array<f32>;
+@group(0) @binding(5) var<storage, read> loss_maximum_regularization: array<f32>;
+@group(0) @binding(6) var<storage, read> loss: array<f32>;
+@group(0) @binding(7) var<storage, read> counters: array<i32>;
+
+// Dispatched as a single work group.
+@compute @workgroup_size(REDUCTION_GROUP_SIZE)
+fn calculate_metrics_batch(
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    var sum_mse = 0.0;
+    var sum_maximum_regularization = 0.0;
+    var sum_loss = 0.0;
+    for (var step = i32(local_index); step < params.num_steps; step += i32(REDUCTION_GROUP_SIZE)) {
+        sum_mse += loss_mse[step];
+        sum_maximum_regularization += loss_maximum_regularization[step];
+        sum_loss += loss[step];
+    }
+
+    let num_steps = f32(params.num_steps);
+    let mean_mse = reduce_partial_sums(local_index, sum_mse, REDUCTION_GROUP_SIZE) / num_steps;
+    let mean_maximum_regularization =
+        reduce_partial_sums(local_index, sum_maximum_regularization, REDUCTION_GROUP_SIZE) / num_steps;
+    let mean_loss = reduce_partial_sums(local_index, sum_loss, REDUCTION_GROUP_SIZE) / num_steps;
+    if (local_index == 0u) {
+        let epoch = counters[EPOCH];
+        loss_mse_batch[epoch] += mean_mse;
+        loss_maximum_regularization_batch[epoch] += mean_maximum_regularization;
+        loss_batch[epoch] += mean_loss;
+    }
+}
@@ -0,0 +1,27 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> loss_mse: array<f32>;
+@group(0) @binding(2) var<storage, read_write> loss_maximum_regularization: array<f32>;
+@group(0) @binding(3) var<storage, read_write> loss: array<f32>;
+@group(0) @binding(4) var<storage, read> residuals: array<f32>;
+@group(0) @binding(5) var<storage, read> maximum_regularization_sum: array<f32>;
+@group(0) @binding(6) var<storage, read> counters: array<i32>;
+
+// Dispatched as a single work group.
+@compute @workgroup_size(REDUCTION_GROUP_SIZE)
+fn calculate_metrics_step(
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    var squared_residuals = 0.0;
+    for (var sensor = i32(local_index); sensor < params.num_sensors; sensor += i32(REDUCTION_GROUP_SIZE)) {
+        squared_residuals += residuals[sensor] * residuals[sensor];
+    }
+
+    let sum = reduce_partial_sums(local_index, squared_residuals, REDUCTION_GROUP_SIZE);
+    if (local_index == 0u) {
+        let step = counters[STEP];
+        loss_mse[step] += sum / f32(params.num_sensors);
+        loss_maximum_regularization[step] = maximum_regularization_sum[0];
+        loss[step] = params.regularization_strength * loss_maximum_regularization[step]
+            + loss_mse[step];
+    }
+}
@@ -0,0 +1,32 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> measurements: array<f32>;
+@group(0) @binding(2) var<storage, read> measurement_matrix: array<f32>;
+@group(0) @binding(3) var<storage, read> system_states: array<f32>;
+@group(0) @binding(4) var<storage, read> counters: array<i32>;
+
+// One work group per sensor sums the contributions of all states.
+@compute @workgroup_size(REDUCTION_GROUP_SIZE)
+fn predict_measurements(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let sensor = group_index(group_id, num_groups);
+    if (sensor >= params.num_sensors) {
+        return;
+    }
+    let step = counters[STEP];
+    let beat = counters[BEAT];
+    let matrix_offset = (beat * params.num_sensors + sensor) * params.num_states;
+
+    var contribution = 0.0;
+    for (var state = i32(local_index); state < params.num_states; state += i32(REDUCTION_GROUP_SIZE)) {
+        contribution += measurement_matrix[matrix_offset + state]
+            * system_states[step * params.num_states + state];
+    }
+
+    let sum = reduce_partial_sums(local_index, contribution, REDUCTION_GROUP_SIZE);
+    if (local_index == 0u) {
+        measurements[(beat * params.num_steps + step) * params.num_sensors + sensor] = sum;
+    }
+}
@@ -0,0 +1,21 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> residuals: array<f32>;
+@group(0) @binding(2) var<storage, read> predicted_measurements: array<f32>;
+@group(0) @binding(3) var<storage, read> actual_measurements: array<f32>;
+@group(0) @binding(4) var<storage, read> channel_mask: array<f32>;
+@group(0) @binding(5) var<storage, read> counters: array<i32>;
+
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn calculate_residuals(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let sensor = element_index(group_id, num_groups, local_index);
+    if (sensor >= params.num_sensors) {
+        return;
+    }
+    let index = (counters[BEAT] * params.num_steps + counters[STEP]) * params.num_sensors + sensor;
+    residuals[sensor] = (predicted_measurements[index] - actual_measurements[index])
+        * channel_mask[sensor];
+}
@@ -0,0 +1,41 @@
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> coefs: array<f32>;
+@group(0) @binding(2) var<storage, read_write> delays: array<i32>;
+@group(0) @binding(3) var<storage, read> derivatives_coefs: array<f32>;
+@group(0) @binding(4) var<storage, read> coefs_mask: array<f32>;
+
+const MARGIN: f32 = 1e-4;
+
+// Rolls the delay over by one sample when a coefficient leaves (0, 1).
+@compute @workgroup_size(ELEMENT_GROUP_SIZE)
+fn update_coefs(
+    @builtin(workgroup_id) group_id: vec3<u32>,
+    @builtin(num_workgroups) num_groups: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let index = element_index(group_id, num_groups, local_index);
+    if (index >= params.num_voxels * (params.num_offsets / 3)) {
+        return;
+    }
+    let voxel = index / (params.num_offsets / 3);
+    let coef = coefs[index] - derivatives_coefs[index] * coefs_mask[voxel] * params.learning_rate;
+    let delay = delays[index];
+
+    if (coef < MARGIN) {
+        if (delay < 1000) {
+            coefs[index] = 1.0 - 2.0 * MARGIN;
+            delays[index] = delay + 1;
+        } else {
+            coefs[index] = MARGIN;
+        }
+    } else if (coef > 1.0 - MARGIN) {
+        if (delay > 1) {
+            coefs[index] = 2.0 * MARGIN;
+            delays[index] = delay - 1;
+        } else {
+            coefs[index] = 1.0 - MARGIN;
+        }
+    } else {
+        coefs[index] = coef;
+    }
+}
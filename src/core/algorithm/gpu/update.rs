@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use ocl::{Kernel, Program};
 
-use super::GPU;
+use super::{precision::kernel_header, GPU};
 use crate::core::{
     algorithm::refinement::derivation::DerivativesGPU, config::algorithm::Algorithm,
     model::ModelGPU,
@@ -34,20 +34,24 @@ impl UpdateKernel {
         let context = &gpu.context;
         let queue = &gpu.queue;
         let number_of_voxels = number_of_states / 3;
+        let number_of_offsets = model.functional_description.ap_params.number_of_offsets;
 
+        let precision_src = kernel_header(model.functional_description.ap_params.precision)?;
         let gains_src = std::fs::read_to_string("src/core/algorithm/gpu/kernels/update_gains.cl")
             .context("Failed to read update_gains kernel source file")?;
         let gains_program = Program::builder()
-            .src(gains_src)
+            .src(format!("{precision_src}\n{gains_src}"))
+            .cmplr_def("NUM_OFFSETS", number_of_offsets)
             .build(context)
             .context("Failed to build OpenCL program for update_gains kernel")?;
         let gains_kernel = Kernel::builder()
             .program(&gains_program)
             .name("update_gains")
             .queue(queue.clone())
-            .global_work_size([number_of_states, 78])
+            .global_work_size([number_of_states, number_of_offsets])
             .arg(&model.functional_description.ap_params.gains)
             .arg(&derivatives.gains)
+            .arg(&derivatives.gains_mask)
             .arg(config.learning_rate / number_of_steps as f32) // not accounting for batch size at the moment. might want to fix that later
             .arg(number_of_states)
             .build()
@@ -57,6 +61,7 @@ impl UpdateKernel {
             .context("Failed to read update_coefs kernel source file")?;
         let coefs_program = Program::builder()
             .src(coefs_src)
+            .cmplr_def("NUM_OFFSETS", number_of_offsets)
             .build(context)
             .context("Failed to build OpenCL program for update_coefs kernel")?;
 
@@ -64,10 +69,11 @@ impl UpdateKernel {
             .program(&coefs_program)
             .name("update_coefs")
             .queue(queue.clone())
-            .global_work_size([number_of_voxels, 26])
+            .global_work_size([number_of_voxels, number_of_offsets / 3])
             .arg(&model.functional_description.ap_params.coefs)
             .arg(&model.functional_description.ap_params.delays)
             .arg(&derivatives.coefs)
+            .arg(&derivatives.coefs_mask)
             .arg(config.learning_rate / number_of_steps as f32) // not accounting for batch size at the moment. might want to fix that later
             .arg(number_of_states)
             .build()
@@ -0,0 +1,1117 @@
+use std::sync::{mpsc, OnceLock};
+
+use anyhow::{Context, Result};
+use bytemuck::Pod;
+use futures_lite::future::block_on;
+use tracing::{info, warn};
+use wgpu::util::DeviceExt;
+
+use super::backend::ComputeBackend;
+use crate::core::{
+    algorithm::{estimation::Estimations, metrics::Metrics, refinement::update::UpdateMask},
+    config::algorithm::{Algorithm, GpuPrecision},
+    data::Data,
+    model::functional::allpass::APParameters,
+    scenario::results::Results,
+};
+
+const SHADER_DIR: &str = "src/core/algorithm/gpu/shaders";
+// invocations per work group of the element-wise shaders, see common.wgsl
+const ELEMENT_GROUP_SIZE: usize = 64;
+// dispatches with more work groups are split along y
+const MAX_GROUPS_PER_DIMENSION: u32 = 65535;
+// size of the params uniform, a multiple of 16 bytes
+const PARAMS_SIZE: usize = 48;
+// step, beat and epoch
+const NUMBER_OF_COUNTERS: usize = 3;
+
+/// A compute device of wgpu, running on Vulkan, Metal or DX12.
+#[derive(Debug)]
+pub struct WgpuDevice {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub adapter_info: wgpu::AdapterInfo,
+}
+
+impl WgpuDevice {
+    /// Creates a device on the high performance adapter.
+    ///
+    /// The device is requested with the limits of the adapter instead of
+    /// the defaults, so that the measurement matrix fits into a single
+    /// storage buffer binding.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no GPU adapter is found or the device cannot be
+    /// created.
+    #[tracing::instrument(level = "trace")]
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .context("Failed to find wgpu adapter - no Vulkan, Metal or DX12 device available")?;
+        let adapter_info = adapter.get_info();
+        anyhow::ensure!(
+            adapter_info.device_type != wgpu::DeviceType::Cpu,
+            "Adapter is not a GPU - found {}",
+            adapter_info.name
+        );
+        let (device, queue) = block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("cardiotrust compute"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .context("Failed to create wgpu device")?;
+        Ok(Self {
+            device,
+            queue,
+            adapter_info,
+        })
+    }
+
+    /// Returns true if a wgpu GPU can be initialized.
+    ///
+    /// The check runs once per process, later calls return the cached
+    /// result. Logs a warning with the reason if no GPU is available.
+    #[tracing::instrument(level = "trace")]
+    pub fn is_available() -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| match Self::new() {
+            Ok(gpu) => {
+                info!(
+                    "wgpu GPU available: {} ({:?})",
+                    gpu.adapter_info.name, gpu.adapter_info.backend
+                );
+                true
+            }
+            Err(e) => {
+                warn!("wgpu GPU not available: {e:#}");
+                false
+            }
+        })
+    }
+
+    /// Runs `create` and turns validation errors, e.g. of a shader, into an
+    /// error instead of the default panic of wgpu.
+    #[tracing::instrument(level = "trace", skip(self, create))]
+    fn validated<T>(&self, label: &str, create: impl FnOnce(&wgpu::Device) -> T) -> Result<T> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = create(&self.device);
+        if let Some(error) = block_on(self.device.pop_error_scope()) {
+            anyhow::bail!("Failed to create {label}: {error}");
+        }
+        Ok(value)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self, values))]
+    fn storage_buffer<T: Pod>(&self, label: &str, values: &[T]) -> Result<wgpu::Buffer> {
+        self.validated(label, |device| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(values),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+            })
+        })
+    }
+
+    /// Reads `values.len()` elements starting at `offset` from the buffer.
+    #[tracing::instrument(level = "trace", skip(self, buffer, values))]
+    fn read_buffer<T: Pod>(
+        &self,
+        buffer: &wgpu::Buffer,
+        offset: usize,
+        values: &mut [T],
+    ) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        let size = std::mem::size_of_val(values) as u64;
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("read"),
+            });
+        encoder.copy_buffer_to_buffer(
+            buffer,
+            (offset * std::mem::size_of::<T>()) as u64,
+            &staging,
+            0,
+            size,
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .context("Staging buffer mapping was cancelled")?
+            .context("Failed to map staging buffer")?;
+        values.copy_from_slice(bytemuck::cast_slice::<u8, T>(&slice.get_mapped_range()));
+        staging.unmap();
+        Ok(())
+    }
+
+    /// Reads a buffer of f32 values into an array.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn read_array<D: ndarray::Dimension>(
+        &self,
+        buffer: &wgpu::Buffer,
+        array: &mut ndarray::Array<f32, D>,
+    ) -> Result<()> {
+        self.read_buffer(
+            buffer,
+            0,
+            array
+                .as_slice_mut()
+                .context("Failed to get mutable array slice for GPU read")?,
+        )
+    }
+}
+
+/// A compute pipeline with its bound buffers and work group count.
+struct ComputeKernel {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    groups: (u32, u32),
+}
+
+impl ComputeKernel {
+    /// Builds the entry point of the shader, binding the buffers in order
+    /// of their binding index.
+    ///
+    /// The shader source is read at runtime and prepended with the shared
+    /// declarations in common.wgsl.
+    #[tracing::instrument(level = "trace", skip(gpu, buffers))]
+    fn new(
+        gpu: &WgpuDevice,
+        shader: &str,
+        entry_point: &str,
+        buffers: &[&wgpu::Buffer],
+        groups: usize,
+    ) -> Result<Self> {
+        let common_src = std::fs::read_to_string(format!("{SHADER_DIR}/common.wgsl"))
+            .context("Failed to read common shader source file")?;
+        let shader_src = std::fs::read_to_string(format!("{SHADER_DIR}/{shader}.wgsl"))
+            .with_context(|| format!("Failed to read {shader} shader source file"))?;
+        let pipeline = gpu.validated(entry_point, |device| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(shader),
+                source: wgpu::ShaderSource::Wgsl(format!("{common_src}\n{shader_src}").into()),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        })?;
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .zip(0..)
+            .map(|(buffer, binding)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bind_group = gpu.validated(entry_point, |device| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(entry_point),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &entries,
+            })
+        })?;
+        let groups = u32::try_from(groups)
+            .with_context(|| format!("Too many work groups for {entry_point}"))?;
+        let groups_x = groups.clamp(1, MAX_GROUPS_PER_DIMENSION);
+        Ok(Self {
+            pipeline,
+            bind_group,
+            groups: (groups_x, groups.div_ceil(groups_x)),
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn dispatch(&self, pass: &mut wgpu::ComputePass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.dispatch_workgroups(self.groups.0, self.groups.1, 1);
+    }
+}
+
+/// The device buffers of the results, named like their host counterparts.
+struct WgpuBuffers {
+    params: wgpu::Buffer,
+    // step, beat and epoch
+    counters: wgpu::Buffer,
+    actual_measurements: wgpu::Buffer,
+    // estimations
+    ap_outputs_now: wgpu::Buffer,
+    ap_outputs_last: wgpu::Buffer,
+    system_states: wgpu::Buffer,
+    measurements: wgpu::Buffer,
+    residuals: wgpu::Buffer,
+    channel_mask: wgpu::Buffer,
+    // model
+    gains: wgpu::Buffer,
+    output_state_indices: wgpu::Buffer,
+    coefs: wgpu::Buffer,
+    delays: wgpu::Buffer,
+    refractory_gates: wgpu::Buffer,
+    measurement_matrix: wgpu::Buffer,
+    control_matrix: wgpu::Buffer,
+    control_onsets: wgpu::Buffer,
+    control_function_values: wgpu::Buffer,
+    // derivatives
+    derivatives_gains: wgpu::Buffer,
+    derivatives_coefs: wgpu::Buffer,
+    derivatives_coefs_iir: wgpu::Buffer,
+    derivatives_coefs_fir: wgpu::Buffer,
+    mapped_residuals: wgpu::Buffer,
+    maximum_regularization: wgpu::Buffer,
+    maximum_regularization_sum: wgpu::Buffer,
+    gains_mask: wgpu::Buffer,
+    coefs_mask: wgpu::Buffer,
+    // metrics
+    loss: wgpu::Buffer,
+    loss_batch: wgpu::Buffer,
+    loss_mse: wgpu::Buffer,
+    loss_mse_batch: wgpu::Buffer,
+    loss_maximum_regularization: wgpu::Buffer,
+    loss_maximum_regularization_batch: wgpu::Buffer,
+}
+
+impl WgpuBuffers {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::too_many_lines
+    )]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn new(gpu: &WgpuDevice, results: &Results, data: &Data, params: &[u8]) -> Result<Self> {
+        let model = results.model.as_ref().context("Model not available")?;
+        let functional_description = &model.functional_description;
+        let ap_params = &functional_description.ap_params;
+        let estimations = &results.estimations;
+        let derivatives = &results.derivatives;
+        let metrics = &results.metrics;
+
+        let output_state_indices: Vec<i32> = ap_params
+            .output_state_indices
+            .iter()
+            .map(|index| index.map_or(-1, |index| index as i32))
+            .collect();
+        let delays: Vec<i32> = ap_params.delays.iter().map(|&delay| delay as i32).collect();
+        let refractory_gates: Vec<i32> = ap_params
+            .refractory
+            .gates()
+            .iter()
+            .map(|&gate| i32::try_from(gate).unwrap_or(i32::MAX))
+            .collect();
+        let control_onsets: Vec<i32> = functional_description
+            .control_onsets
+            .iter()
+            .map(|&onset| onset as i32)
+            .collect();
+        let update_mask = derivatives
+            .update_mask
+            .clone()
+            .unwrap_or_else(|| UpdateMask::full(ap_params.coefs.shape()[0]));
+
+        let slice = |array: &'static str, values: Option<&[f32]>| {
+            values.with_context(|| format!("Failed to get {array} slice for GPU copy"))
+        };
+        Ok(Self {
+            params: gpu.validated("params", |device| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("params"),
+                    contents: params,
+                    usage: wgpu::BufferUsages::UNIFORM,
+                })
+            })?,
+            counters: gpu.storage_buffer("counters", &[0i32; NUMBER_OF_COUNTERS])?,
+            actual_measurements: gpu.storage_buffer(
+                "actual_measurements",
+                slice(
+                    "actual measurements",
+                    data.simulation.measurements.as_slice(),
+                )?,
+            )?,
+            ap_outputs_now: gpu.storage_buffer(
+                "ap_outputs_now",
+                slice("ap outputs", estimations.ap_outputs_now.as_slice())?,
+            )?,
+            ap_outputs_last: gpu.storage_buffer(
+                "ap_outputs_last",
+                slice("ap outputs", estimations.ap_outputs_last.as_slice())?,
+            )?,
+            system_states: gpu.storage_buffer(
+                "system_states",
+                slice("system states", estimations.system_states.as_slice())?,
+            )?,
+            measurements: gpu.storage_buffer(
+                "measurements",
+                slice("measurements", estimations.measurements.as_slice())?,
+            )?,
+            residuals: gpu.storage_buffer(
+                "residuals",
+                slice("residuals", estimations.residuals.as_slice())?,
+            )?,
+            channel_mask: gpu.storage_buffer(
+                "channel_mask",
+                slice("channel mask", estimations.channel_mask.as_slice())?,
+            )?,
+            gains: gpu.storage_buffer("gains", slice("gains", ap_params.gains.as_slice())?)?,
+            output_state_indices: gpu
+                .storage_buffer("output_state_indices", &output_state_indices)?,
+            coefs: gpu.storage_buffer("coefs", slice("coefs", ap_params.coefs.as_slice())?)?,
+            delays: gpu.storage_buffer("delays", &delays)?,
+            refractory_gates: gpu.storage_buffer("refractory_gates", &refractory_gates)?,
+            measurement_matrix: gpu.storage_buffer(
+                "measurement_matrix",
+                slice(
+                    "measurement matrix",
+                    functional_description.measurement_matrix.as_slice(),
+                )?,
+            )?,
+            control_matrix: gpu.storage_buffer(
+                "control_matrix",
+                slice(
+                    "control matrix",
+                    functional_description.control_matrix.as_slice(),
+                )?,
+            )?,
+            control_onsets: gpu.storage_buffer("control_onsets", &control_onsets)?,
+            control_function_values: gpu.storage_buffer(
+                "control_function_values",
+                slice(
+                    "control function",
+                    functional_description.control_function_values.as_slice(),
+                )?,
+            )?,
+            derivatives_gains: gpu.storage_buffer(
+                "derivatives_gains",
+                slice("derivatives gains", derivatives.gains.as_slice())?,
+            )?,
+            derivatives_coefs: gpu.storage_buffer(
+                "derivatives_coefs",
+                slice("derivatives coefs", derivatives.coefs.as_slice())?,
+            )?,
+            derivatives_coefs_iir: gpu.storage_buffer(
+                "derivatives_coefs_iir",
+                slice("derivatives coefs iir", derivatives.coefs_iir.as_slice())?,
+            )?,
+            derivatives_coefs_fir: gpu.storage_buffer(
+                "derivatives_coefs_fir",
+                slice("derivatives coefs fir", derivatives.coefs_fir.as_slice())?,
+            )?,
+            mapped_residuals: gpu.storage_buffer(
+                "mapped_residuals",
+                slice("mapped residuals", derivatives.mapped_residuals.as_slice())?,
+            )?,
+            maximum_regularization: gpu.storage_buffer(
+                "maximum_regularization",
+                slice(
+                    "maximum regularization",
+                    derivatives.maximum_regularization.as_slice(),
+                )?,
+            )?,
+            maximum_regularization_sum: gpu.storage_buffer(
+                "maximum_regularization_sum",
+                &[derivatives.maximum_regularization_sum],
+            )?,
+            gains_mask: gpu.storage_buffer(
+                "gains_mask",
+                slice("gains mask", update_mask.gains.as_slice())?,
+            )?,
+            coefs_mask: gpu.storage_buffer(
+                "coefs_mask",
+                slice("coefs mask", update_mask.coefs.as_slice())?,
+            )?,
+            loss: gpu.storage_buffer("loss", slice("loss", metrics.loss.as_slice())?)?,
+            loss_batch: gpu.storage_buffer(
+                "loss_batch",
+                slice("loss batch", metrics.loss_batch.as_slice())?,
+            )?,
+            loss_mse: gpu
+                .storage_buffer("loss_mse", slice("loss mse", metrics.loss_mse.as_slice())?)?,
+            loss_mse_batch: gpu.storage_buffer(
+                "loss_mse_batch",
+                slice("loss mse batch", metrics.loss_mse_batch.as_slice())?,
+            )?,
+            loss_maximum_regularization: gpu.storage_buffer(
+                "loss_maximum_regularization",
+                slice(
+                    "loss maximum regularization",
+                    metrics.loss_maximum_regularization.as_slice(),
+                )?,
+            )?,
+            loss_maximum_regularization_batch: gpu.storage_buffer(
+                "loss_maximum_regularization_batch",
+                slice(
+                    "loss maximum regularization batch",
+                    metrics.loss_maximum_regularization_batch.as_slice(),
+                )?,
+            )?,
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn memory_bytes(&self) -> usize {
+        [
+            &self.params,
+            &self.counters,
+            &self.actual_measurements,
+            &self.ap_outputs_now,
+            &self.ap_outputs_last,
+            &self.system_states,
+            &self.measurements,
+            &self.residuals,
+            &self.channel_mask,
+            &self.gains,
+            &self.output_state_indices,
+            &self.coefs,
+            &self.delays,
+            &self.refractory_gates,
+            &self.measurement_matrix,
+            &self.control_matrix,
+            &self.control_onsets,
+            &self.control_function_values,
+            &self.derivatives_gains,
+            &self.derivatives_coefs,
+            &self.derivatives_coefs_iir,
+            &self.derivatives_coefs_fir,
+            &self.mapped_residuals,
+            &self.maximum_regularization,
+            &self.maximum_regularization_sum,
+            &self.gains_mask,
+            &self.coefs_mask,
+            &self.loss,
+            &self.loss_batch,
+            &self.loss_mse,
+            &self.loss_mse_batch,
+            &self.loss_maximum_regularization,
+            &self.loss_maximum_regularization_batch,
+        ]
+        .iter()
+        .map(|buffer| usize::try_from(buffer.size()).unwrap_or(usize::MAX))
+        .sum()
+    }
+}
+
+/// The shaders of one epoch, see the `OpenCL` kernels of the same names.
+struct WgpuKernels {
+    innovate: ComputeKernel,
+    add_control: ComputeKernel,
+    predict_measurements: ComputeKernel,
+    residuals: ComputeKernel,
+    mapped_residuals: ComputeKernel,
+    maximum_regularization: ComputeKernel,
+    derivatives_gains: ComputeKernel,
+    derivatives_coefs_fir: ComputeKernel,
+    derivatives_coefs_iir: ComputeKernel,
+    derivatives_coefs: ComputeKernel,
+    update_gains: ComputeKernel,
+    update_coefs: ComputeKernel,
+    metrics_step: ComputeKernel,
+    metrics_batch: ComputeKernel,
+    increase_step: ComputeKernel,
+    increase_epoch: ComputeKernel,
+}
+
+impl WgpuKernels {
+    #[allow(clippy::too_many_lines)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn new(
+        gpu: &WgpuDevice,
+        buffers: &WgpuBuffers,
+        number_of_states: usize,
+        number_of_sensors: usize,
+        number_of_offsets: usize,
+    ) -> Result<Self> {
+        let b = buffers;
+        let gains_groups = (number_of_states * number_of_offsets).div_ceil(ELEMENT_GROUP_SIZE);
+        let coefs_groups =
+            (number_of_states / 3 * number_of_offsets / 3).div_ceil(ELEMENT_GROUP_SIZE);
+        Ok(Self {
+            // one work group per state
+            innovate: ComputeKernel::new(
+                gpu,
+                "innovate",
+                "innovate_system_states",
+                &[
+                    &b.params,
+                    &b.ap_outputs_now,
+                    &b.ap_outputs_last,
+                    &b.system_states,
+                    &b.coefs,
+                    &b.delays,
+                    &b.refractory_gates,
+                    &b.gains,
+                    &b.output_state_indices,
+                    &b.counters,
+                ],
+                number_of_states,
+            )?,
+            add_control: ComputeKernel::new(
+                gpu,
+                "add_control",
+                "add_control_function",
+                &[
+                    &b.params,
+                    &b.system_states,
+                    &b.control_matrix,
+                    &b.control_onsets,
+                    &b.control_function_values,
+                    &b.counters,
+                ],
+                number_of_states.div_ceil(ELEMENT_GROUP_SIZE),
+            )?,
+            // one work group per sensor
+            predict_measurements: ComputeKernel::new(
+                gpu,
+                "predict_measurements",
+                "predict_measurements",
+                &[
+                    &b.params,
+                    &b.measurements,
+                    &b.measurement_matrix,
+                    &b.system_states,
+                    &b.counters,
+                ],
+                number_of_sensors,
+            )?,
+            residuals: ComputeKernel::new(
+                gpu,
+                "residuals",
+                "calculate_residuals",
+                &[
+                    &b.params,
+                    &b.residuals,
+                    &b.measurements,
+                    &b.actual_measurements,
+                    &b.channel_mask,
+                    &b.counters,
+                ],
+                number_of_sensors.div_ceil(ELEMENT_GROUP_SIZE),
+            )?,
+            // one work group per state
+            mapped_residuals: ComputeKernel::new(
+                gpu,
+                "mapped_residuals",
+                "calculate_mapped_residuals",
+                &[
+                    &b.params,
+                    &b.mapped_residuals,
+                    &b.measurement_matrix,
+                    &b.residuals,
+                    &b.counters,
+                ],
+                number_of_states,
+            )?,
+            maximum_regularization: ComputeKernel::new(
+                gpu,
+                "maximum_regularization",
+                "calculate_maximum_regularization",
+                &[
+                    &b.params,
+                    &b.maximum_regularization,
+                    &b.maximum_regularization_sum,
+                    &b.system_states,
+                    &b.counters,
+                ],
+                1,
+            )?,
+            derivatives_gains: ComputeKernel::new(
+                gpu,
+                "derivatives_gains",
+                "calculate_derivatives_gains",
+                &[
+                    &b.params,
+                    &b.derivatives_gains,
+                    &b.ap_outputs_now,
+                    &b.maximum_regularization,
+                    &b.mapped_residuals,
+                ],
+                gains_groups,
+            )?,
+            derivatives_coefs_fir: ComputeKernel::new(
+                gpu,
+                "derivatives_fir",
+                "calculate_derivatives_coefs_fir",
+                &[
+                    &b.params,
+                    &b.derivatives_coefs_fir,
+                    &b.system_states,
+                    &b.output_state_indices,
+                    &b.coefs,
+                    &b.delays,
+                    &b.counters,
+                ],
+                gains_groups,
+            )?,
+            derivatives_coefs_iir: ComputeKernel::new(
+                gpu,
+                "derivatives_iir",
+                "calculate_derivatives_coefs_iir",
+                &[
+                    &b.params,
+                    &b.derivatives_coefs_iir,
+                    &b.ap_outputs_last,
+                    &b.coefs,
+                    &b.delays,
+                    &b.counters,
+                ],
+                gains_groups,
+            )?,
+            derivatives_coefs: ComputeKernel::new(
+                gpu,
+                "derivatives_coefs",
+                "calculate_derivatives_coefs_combine",
+                &[
+                    &b.params,
+                    &b.derivatives_coefs,
+                    &b.derivatives_coefs_iir,
+                    &b.derivatives_coefs_fir,
+                    &b.gains,
+                    &b.mapped_residuals,
+                ],
+                coefs_groups,
+            )?,
+            update_gains: ComputeKernel::new(
+                gpu,
+                "update_gains",
+                "update_gains",
+                &[&b.params, &b.gains, &b.derivatives_gains, &b.gains_mask],
+                gains_groups,
+            )?,
+            update_coefs: ComputeKernel::new(
+                gpu,
+                "update_coefs",
+                "update_coefs",
+                &[
+                    &b.params,
+                    &b.coefs,
+                    &b.delays,
+                    &b.derivatives_coefs,
+                    &b.coefs_mask,
+                ],
+                coefs_groups,
+            )?,
+            metrics_step: ComputeKernel::new(
+                gpu,
+                "metrics_step",
+                "calculate_metrics_step",
+                &[
+                    &b.params,
+                    &b.loss_mse,
+                    &b.loss_maximum_regularization,
+                    &b.loss,
+                    &b.residuals,
+                    &b.maximum_regularization_sum,
+                    &b.counters,
+                ],
+                1,
+            )?,
+            metrics_batch: ComputeKernel::new(
+                gpu,
+                "metrics_batch",
+                "calculate_metrics_batch",
+                &[
+                    &b.params,
+                    &b.loss_mse_batch,
+                    &b.loss_maximum_regularization_batch,
+                    &b.loss_batch,
+                    &b.loss_mse,
+                    &b.loss_maximum_regularization,
+                    &b.loss,
+                    &b.counters,
+                ],
+                1,
+            )?,
+            increase_step: ComputeKernel::new(gpu, "helper", "increase_step", &[&b.counters], 1)?,
+            increase_epoch: ComputeKernel::new(gpu, "helper", "increase_epoch", &[&b.counters], 1)?,
+        })
+    }
+}
+
+/// Runs the epochs with WGSL compute shaders on wgpu.
+///
+/// Mirrors the `OpenCL` kernels, but sums with one work group per output
+/// instead of float atomics, which are not available on all platforms.
+/// All dispatches of an epoch are recorded into a single submission.
+pub struct WgpuBackend {
+    gpu: WgpuDevice,
+    buffers: WgpuBuffers,
+    kernels: WgpuKernels,
+    number_of_steps: usize,
+    freeze_gains: bool,
+    freeze_delays: bool,
+}
+
+impl WgpuBackend {
+    /// Copies the results and measurements to the device and builds the
+    /// shaders.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no GPU is available, the model is missing, a
+    /// buffer exceeds the device limits or a shader cannot be built.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss
+    )]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn new(results: &Results, data: &Data, config: &Algorithm) -> Result<Self> {
+        if config.gpu_precision != GpuPrecision::Single {
+            warn!(
+                "Half precision storage is not supported by the wgpu backend and will be ignored"
+            );
+        }
+        let gpu = WgpuDevice::new()?;
+        let spatial_description = &results
+            .model
+            .as_ref()
+            .context("Model should be set during GPU algorithm execution")?
+            .spatial_description;
+        let number_of_states = spatial_description.voxels.count_states();
+        let number_of_sensors = spatial_description.sensors.count();
+        let number_of_steps = results.estimations.measurements.num_steps();
+        let number_of_offsets = results.estimations.ap_outputs_now.shape()[1];
+
+        let mut params = Vec::with_capacity(PARAMS_SIZE);
+        for count in [
+            number_of_states,
+            number_of_sensors,
+            number_of_steps,
+            number_of_offsets,
+            number_of_states / 3,
+        ] {
+            params.extend_from_slice(&(count as i32).to_ne_bytes());
+        }
+        for value in [
+            config.mse_strength / number_of_sensors as f32,
+            config.maximum_regularization_strength,
+            config.maximum_regularization_threshold,
+            // not accounting for batch size, like the OpenCL kernels
+            config.learning_rate / number_of_steps as f32,
+        ] {
+            params.extend_from_slice(&value.to_ne_bytes());
+        }
+        params.resize(PARAMS_SIZE, 0);
+
+        let buffers = WgpuBuffers::new(&gpu, results, data, &params)?;
+        let kernels = WgpuKernels::new(
+            &gpu,
+            &buffers,
+            number_of_states,
+            number_of_sensors,
+            number_of_offsets,
+        )?;
+        Ok(Self {
+            gpu,
+            buffers,
+            kernels,
+            number_of_steps,
+            freeze_gains: config.freeze_gains,
+            freeze_delays: config.freeze_delays,
+        })
+    }
+}
+
+impl ComputeBackend for WgpuBackend {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn execute_epoch(&mut self) -> Result<()> {
+        let b = &self.buffers;
+        let k = &self.kernels;
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("epoch"),
+            });
+
+        // reset, the step counter is the first counter
+        for buffer in [
+            &b.system_states,
+            &b.measurements,
+            &b.loss_mse,
+            &b.ap_outputs_now,
+            &b.derivatives_gains,
+            &b.derivatives_coefs,
+            &b.derivatives_coefs_iir,
+            &b.derivatives_coefs_fir,
+            &b.maximum_regularization_sum,
+        ] {
+            encoder.clear_buffer(buffer, 0, None);
+        }
+        encoder.clear_buffer(&b.counters, 0, Some(std::mem::size_of::<i32>() as u64));
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("epoch"),
+                timestamp_writes: None,
+            });
+            for _ in 0..self.number_of_steps {
+                // prediction
+                k.innovate.dispatch(&mut pass);
+                k.add_control.dispatch(&mut pass);
+                k.predict_measurements.dispatch(&mut pass);
+                // derivation
+                k.residuals.dispatch(&mut pass);
+                if !(self.freeze_gains && self.freeze_delays) {
+                    k.mapped_residuals.dispatch(&mut pass);
+                }
+                k.maximum_regularization.dispatch(&mut pass);
+                if !self.freeze_gains {
+                    k.derivatives_gains.dispatch(&mut pass);
+                }
+                if !self.freeze_delays {
+                    k.derivatives_coefs_fir.dispatch(&mut pass);
+                    k.derivatives_coefs_iir.dispatch(&mut pass);
+                    k.derivatives_coefs.dispatch(&mut pass);
+                }
+                k.metrics_step.dispatch(&mut pass);
+                k.increase_step.dispatch(&mut pass);
+            }
+            if !self.freeze_gains {
+                k.update_gains.dispatch(&mut pass);
+            }
+            if !self.freeze_delays {
+                k.update_coefs.dispatch(&mut pass);
+            }
+            k.metrics_batch.dispatch(&mut pass);
+            k.increase_epoch.dispatch(&mut pass);
+        }
+        self.gpu.queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn set_freeze_delays(&mut self, value: bool) {
+        self.freeze_delays = value;
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn set_freeze_gains(&mut self, value: bool) {
+        self.freeze_gains = value;
+    }
+
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    fn read_batch_metrics(&self, metrics: &mut Metrics, batch_index: usize) -> Result<()> {
+        for (metric, buffer) in [
+            (&mut metrics.loss_batch, &self.buffers.loss_batch),
+            (&mut metrics.loss_mse_batch, &self.buffers.loss_mse_batch),
+            (
+                &mut metrics.loss_maximum_regularization_batch,
+                &self.buffers.loss_maximum_regularization_batch,
+            ),
+        ] {
+            let value = metric
+                .get_mut(batch_index)
+                .with_context(|| format!("Batch index {batch_index} out of bounds"))?;
+            self.gpu
+                .read_buffer(buffer, batch_index, std::slice::from_mut(value))?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    fn read_step_metrics(&self, metrics: &mut Metrics) -> Result<()> {
+        self.gpu.read_array(&self.buffers.loss, &mut metrics.loss)?;
+        self.gpu
+            .read_array(&self.buffers.loss_mse, &mut metrics.loss_mse)?;
+        self.gpu.read_array(
+            &self.buffers.loss_maximum_regularization,
+            &mut metrics.loss_maximum_regularization,
+        )
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    fn read_estimations(
+        &self,
+        estimations: &mut Estimations,
+        ap_params: &mut APParameters,
+    ) -> Result<()> {
+        let b = &self.buffers;
+        self.gpu
+            .read_array(&b.ap_outputs_now, &mut estimations.ap_outputs_now)?;
+        self.gpu
+            .read_array(&b.ap_outputs_last, &mut estimations.ap_outputs_last)?;
+        self.gpu
+            .read_array(&b.system_states, &mut estimations.system_states)?;
+        self.gpu
+            .read_array(&b.measurements, &mut estimations.measurements)?;
+        self.gpu
+            .read_array(&b.residuals, &mut estimations.residuals)?;
+        self.gpu.read_array(&b.gains, &mut ap_params.gains)?;
+        self.gpu.read_array(&b.coefs, &mut ap_params.coefs)?;
+        let mut delays = vec![0i32; ap_params.delays.len()];
+        self.gpu.read_buffer(&b.delays, 0, &mut delays)?;
+        ap_params
+            .delays
+            .iter_mut()
+            .zip(&delays)
+            .for_each(|(dest, &src)| *dest = src as usize);
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    fn read_results(&self, results: &mut Results) -> Result<()> {
+        let b = &self.buffers;
+        self.read_step_metrics(&mut results.metrics)?;
+        self.gpu
+            .read_array(&b.loss_batch, &mut results.metrics.loss_batch)?;
+        self.gpu
+            .read_array(&b.loss_mse_batch, &mut results.metrics.loss_mse_batch)?;
+        self.gpu.read_array(
+            &b.loss_maximum_regularization_batch,
+            &mut results.metrics.loss_maximum_regularization_batch,
+        )?;
+
+        let derivatives = &mut results.derivatives;
+        self.gpu
+            .read_array(&b.derivatives_gains, &mut derivatives.gains)?;
+        self.gpu
+            .read_array(&b.derivatives_coefs, &mut derivatives.coefs)?;
+        self.gpu
+            .read_array(&b.derivatives_coefs_iir, &mut derivatives.coefs_iir)?;
+        self.gpu
+            .read_array(&b.derivatives_coefs_fir, &mut derivatives.coefs_fir)?;
+        self.gpu
+            .read_array(&b.mapped_residuals, &mut derivatives.mapped_residuals)?;
+        self.gpu.read_array(
+            &b.maximum_regularization,
+            &mut derivatives.maximum_regularization,
+        )?;
+
+        let model = results.model.as_mut().context("Model not available")?;
+        self.read_estimations(
+            &mut results.estimations,
+            &mut model.functional_description.ap_params,
+        )
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn memory_bytes(&self) -> usize {
+        self.buffers.memory_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use approx::assert_relative_eq;
+
+    use super::WgpuBackend;
+    use crate::core::{
+        algorithm::{
+            gpu::backend::{ComputeBackend, OpenClBackend},
+            run_epoch,
+        },
+        config::Config,
+        data::Data,
+        scenario::results::Results,
+    };
+
+    #[test]
+    #[ignore = "expensive integration test"]
+    fn test_epoch_matches_cpu() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.algorithm.epochs = 10;
+        config.algorithm.freeze_delays = false;
+        config.algorithm.learning_rate = 100.0;
+        let data = Data::get_default()?;
+        let mut results_cpu = Results::get_default();
+        let mut results_wgpu = results_cpu.clone();
+        let mut backend = WgpuBackend::new(&results_wgpu, &data, &config.algorithm)?;
+
+        let mut batch_index = 0;
+        for _ in 0..config.algorithm.epochs {
+            run_epoch(&mut results_cpu, &mut batch_index, &data, &config.algorithm)?;
+            backend.execute_epoch()?;
+        }
+        backend.read_results(&mut results_wgpu)?;
+
+        assert_relative_eq!(
+            results_cpu
+                .metrics
+                .loss_batch
+                .as_slice()
+                .context("Failed to convert CPU loss batch to slice")?,
+            results_wgpu
+                .metrics
+                .loss_batch
+                .as_slice()
+                .context("Failed to convert wgpu loss batch to slice")?,
+            epsilon = 1e-5
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "expensive integration test"]
+    fn test_epoch_matches_opencl() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        config.algorithm.epochs = 5;
+        config.algorithm.freeze_delays = false;
+        let data = Data::get_default()?;
+        let mut results_opencl = Results::get_default();
+        let mut results_wgpu = results_opencl.clone();
+        let mut opencl = OpenClBackend::new(&results_opencl, &data, &config.algorithm)?;
+        let mut wgpu = WgpuBackend::new(&results_wgpu, &data, &config.algorithm)?;
+
+        for _ in 0..config.algorithm.epochs {
+            opencl.execute_epoch()?;
+            wgpu.execute_epoch()?;
+        }
+        opencl.read_results(&mut results_opencl)?;
+        wgpu.read_results(&mut results_wgpu)?;
+
+        assert_relative_eq!(
+            &*results_opencl.estimations.system_states,
+            &*results_wgpu.estimations.system_states,
+            epsilon = 1e-4
+        );
+        assert_relative_eq!(
+            &*results_opencl
+                .model
+                .as_ref()
+                .context("OpenCL model not available")?
+                .functional_description
+                .ap_params
+                .gains,
+            &*results_wgpu
+                .model
+                .as_ref()
+                .context("wgpu model not available")?
+                .functional_description
+                .ap_params
+                .gains,
+            epsilon = 1e-4
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use anyhow::{Context, Result};
+use nalgebra::{DMatrix, SVD};
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+use tracing::{debug, info, trace};
+
+use super::{
+    estimation::{calculate_residuals, prediction::predict_measurements},
+    metrics,
+    refinement::derivation::calculate_step_derivatives,
+};
+use crate::core::{
+    config::algorithm::{Algorithm, AlgorithmType, RegularizationSelection},
+    data::Data,
+    model::functional::FunctionalDescription,
+    scenario::results::Results,
+};
+
+/// Number of candidate regularization strengths used for the automatic
+/// selection, log-spaced between `1e-6` and `10`.
+const NUMBER_OF_CANDIDATES: usize = 36;
+/// Maximum number of iterations used to find the eLORETA weights.
+const MAX_ELORETA_ITERATIONS: usize = 50;
+/// Relative change of the eLORETA weights below which the iteration is
+/// considered converged.
+const ELORETA_TOLERANCE: f64 = 1e-6;
+
+/// Estimates the system states with a linear inverse solution of the
+/// measurement matrix.
+///
+/// Supports the minimum-norm estimate (MNE), standardized LORETA (sLORETA)
+/// and exact LORETA (eLORETA). The regularization strength is given relative
+/// to the mean eigenvalue of the gram matrix of the measurement matrix and
+/// is either fixed or selected per beat by generalized cross-validation or
+/// the L-curve criterion. Residuals, derivatives and metrics are calculated
+/// as for the pseudo inverse.
+///
+/// # Errors
+///
+/// Returns an error if the algorithm type is not an inverse solver or the
+/// inverse operator cannot be calculated.
+#[allow(clippy::cast_possible_truncation)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn run_inverse_solver(
+    functional_description: &FunctionalDescription,
+    results: &mut Results,
+    data: &Data,
+    config: &Algorithm,
+) -> Result<()> {
+    debug!("Running inverse solver");
+    let num_steps = results.estimations.system_states.num_steps();
+    let estimations = &mut results.estimations;
+    let derivatives = &mut results.derivatives;
+
+    for beat in 0..data.simulation.measurements.num_beats() {
+        let measurement_matrix = functional_description
+            .measurement_matrix
+            .at_beat(beat)
+            .mapv(f64::from);
+        let measurements = data.simulation.measurements.at_beat(beat).mapv(f64::from);
+        let operator = calculate_inverse_operator(
+            &measurement_matrix.view(),
+            &measurements.view(),
+            &config.algorithm_type,
+            &config.regularization_selection,
+            config.regularization_strength,
+        )
+        .with_context(|| format!("Failed to calculate inverse operator for beat {beat}"))?
+        .mapv(|v| v as f32);
+
+        estimations.reset();
+        for step in 0..num_steps {
+            let actual_measurements = data.simulation.measurements.at_beat(beat);
+            estimations
+                .system_states
+                .at_step_mut(step)
+                .assign(&operator.dot(&*actual_measurements.at_step(step)));
+
+            predict_measurements(estimations, functional_description, beat, step);
+            calculate_residuals(estimations, data, beat, step);
+
+            calculate_step_derivatives(
+                derivatives,
+                estimations,
+                functional_description,
+                config,
+                step,
+                beat,
+            )?;
+
+            metrics::calculate_step(
+                &mut results.metrics,
+                estimations,
+                derivatives.maximum_regularization_sum,
+                config.maximum_regularization_strength,
+                step,
+            );
+        }
+    }
+    metrics::calculate_batch(&mut results.metrics, 0)?;
+    Ok(())
+}
+
+/// Calculates the inverse operator with dimensions (`number_of_states`,
+/// `number_of_sensors`) for the given solver.
+///
+/// `measurements` with dimensions (`number_of_steps`, `number_of_sensors`)
+/// are only used for the automatic selection of the regularization strength.
+///
+/// # Errors
+///
+/// Returns an error if the algorithm type is not an inverse solver, the
+/// regularization strength is not positive or the eLORETA iteration fails.
+#[tracing::instrument(level = "debug", skip(measurement_matrix, measurements))]
+pub fn calculate_inverse_operator(
+    measurement_matrix: &ArrayView2<f64>,
+    measurements: &ArrayView2<f64>,
+    algorithm_type: &AlgorithmType,
+    selection: &RegularizationSelection,
+    regularization_strength: f32,
+) -> Result<Array2<f64>> {
+    debug!("Calculating inverse operator");
+    let decomposition = Decomposition::new(measurement_matrix)?;
+    let relative_strength = match selection {
+        RegularizationSelection::Fixed => {
+            anyhow::ensure!(
+                regularization_strength > 0.0,
+                "Regularization strength must be positive"
+            );
+            f64::from(regularization_strength)
+        }
+        RegularizationSelection::GeneralizedCrossValidation | RegularizationSelection::LCurve => {
+            decomposition.select_regularization(measurements, selection)
+        }
+    };
+    info!("Using relative regularization strength {relative_strength:e}");
+    match algorithm_type {
+        AlgorithmType::MinimumNorm => Ok(decomposition.minimum_norm_operator(relative_strength)),
+        AlgorithmType::SLoreta => Ok(decomposition.sloreta_operator(relative_strength)),
+        AlgorithmType::ELoreta => eloreta_operator(measurement_matrix, relative_strength),
+        _ => Err(anyhow::anyhow!(
+            "Algorithm type {algorithm_type:?} is not an inverse solver"
+        )),
+    }
+}
+
+/// Singular value decomposition of the measurement matrix, with the left and
+/// right singular vectors as ndarrays.
+struct Decomposition {
+    // (number_of_sensors, rank)
+    u: Array2<f64>,
+    singular_values: Array1<f64>,
+    // (number_of_states, rank)
+    v: Array2<f64>,
+    num_sensors: usize,
+    // mean eigenvalue of H H^T, used to scale the regularization strength
+    scale: f64,
+}
+
+impl Decomposition {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn new(measurement_matrix: &ArrayView2<f64>) -> Result<Self> {
+        let (num_sensors, num_states) = measurement_matrix.dim();
+        let matrix =
+            DMatrix::from_row_iterator(num_sensors, num_states, measurement_matrix.iter().copied());
+        let svd = SVD::new(matrix, true, true);
+        let u = svd
+            .u
+            .context("Left singular vectors of measurement matrix not calculated")?;
+        let v_t = svd
+            .v_t
+            .context("Right singular vectors of measurement matrix not calculated")?;
+        let rank = svd.singular_values.len();
+        let singular_values = Array1::from_iter(svd.singular_values.iter().copied());
+        #[allow(clippy::cast_precision_loss)]
+        let scale = singular_values.mapv(|s| s * s).sum() / num_sensors.max(1) as f64;
+        anyhow::ensure!(scale > 0.0, "Measurement matrix is zero");
+        Ok(Self {
+            u: Array2::from_shape_fn((num_sensors, rank), |index| u[index]),
+            singular_values,
+            v: Array2::from_shape_fn((num_states, rank), |(state, k)| v_t[(k, state)]),
+            num_sensors,
+            scale,
+        })
+    }
+
+    /// Filter factors `s / (s^2 + lambda)` of the minimum-norm estimate.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn filter_factors(&self, relative_strength: f64) -> Array1<f64> {
+        let lambda = relative_strength * self.scale;
+        self.singular_values.mapv(|s| s / s.mul_add(s, lambda))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn minimum_norm_operator(&self, relative_strength: f64) -> Array2<f64> {
+        let factors = self.filter_factors(relative_strength);
+        (&self.v * &factors).dot(&self.u.t())
+    }
+
+    /// Normalizes every row of the minimum-norm operator by the square root
+    /// of the corresponding diagonal element of the resolution matrix.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn sloreta_operator(&self, relative_strength: f64) -> Array2<f64> {
+        let factors = self.filter_factors(relative_strength);
+        let resolution_factors = &factors * &self.singular_values;
+        let resolution_diagonal = (&self.v * &self.v * &resolution_factors).sum_axis(Axis(1));
+        let mut operator = self.minimum_norm_operator(relative_strength);
+        for (mut row, resolution) in operator.outer_iter_mut().zip(resolution_diagonal.iter()) {
+            if *resolution > 0.0 {
+                row /= resolution.sqrt();
+            }
+        }
+        operator
+    }
+
+    /// Selects the relative regularization strength from log-spaced
+    /// candidates using the given criterion.
+    #[tracing::instrument(level = "trace", skip(self, measurements))]
+    fn select_regularization(
+        &self,
+        measurements: &ArrayView2<f64>,
+        selection: &RegularizationSelection,
+    ) -> f64 {
+        // coefficients of the measurements in the basis of the left singular
+        // vectors, energy per singular value summed over all steps
+        let coefficients = self.u.t().dot(&measurements.t());
+        let coefficient_energy = coefficients.mapv(|c| c * c).sum_axis(Axis(1));
+        let outside_energy =
+            (measurements.mapv(|y| y * y).sum() - coefficient_energy.sum()).max(0.0);
+        let candidates = Array1::logspace(10.0, -6.0, 1.0, NUMBER_OF_CANDIDATES);
+
+        let norms: Vec<(f64, f64, f64)> = candidates
+            .iter()
+            .map(|&relative_strength| {
+                let lambda = relative_strength * self.scale;
+                let mut residual_norm = outside_energy;
+                let mut solution_norm = 0.0;
+                let mut degrees_of_freedom = 0.0;
+                for (s, energy) in self.singular_values.iter().zip(coefficient_energy.iter()) {
+                    let denominator = s.mul_add(*s, lambda);
+                    residual_norm += (lambda / denominator).powi(2) * energy;
+                    solution_norm += (s / denominator).powi(2) * energy;
+                    degrees_of_freedom += s * s / denominator;
+                }
+                #[allow(clippy::cast_precision_loss)]
+                let trace = self.num_sensors as f64 - degrees_of_freedom;
+                (residual_norm, solution_norm, trace)
+            })
+            .collect();
+
+        let selected = match selection {
+            RegularizationSelection::LCurve => l_curve_corner(&norms),
+            _ => norms
+                .iter()
+                .map(|(residual_norm, _, trace)| residual_norm / trace.powi(2))
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(0, |(index, _)| index),
+        };
+        trace!("Selected regularization candidate {selected}");
+        candidates[selected]
+    }
+}
+
+/// Returns the index of the point with the largest curvature of the L-curve,
+/// given by the squared residual and solution norms for increasing
+/// regularization strengths.
+#[tracing::instrument(level = "trace", skip_all)]
+fn l_curve_corner(norms: &[(f64, f64, f64)]) -> usize {
+    let points: Vec<(f64, f64)> = norms
+        .iter()
+        .map(|(residual_norm, solution_norm, _)| {
+            (
+                0.5 * residual_norm.max(f64::MIN_POSITIVE).ln(),
+                0.5 * solution_norm.max(f64::MIN_POSITIVE).ln(),
+            )
+        })
+        .collect();
+    (1..points.len().saturating_sub(1))
+        .map(|index| {
+            let (a, b, c) = (points[index - 1], points[index], points[index + 1]);
+            // menger curvature, positive where the curve turns counterclockwise
+            let cross = (b.0 - a.0).mul_add(c.1 - a.1, -(b.1 - a.1) * (c.0 - a.0));
+            let lengths = (b.0 - a.0).hypot(b.1 - a.1)
+                * (c.0 - b.0).hypot(c.1 - b.1)
+                * (c.0 - a.0).hypot(c.1 - a.1);
+            let curvature = if lengths > 0.0 {
+                2.0 * cross / lengths
+            } else {
+                0.0
+            };
+            (index, curvature)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(index, _)| index)
+}
+
+/// Calculates the eLORETA operator `W^-1 H^T (H W^-1 H^T + lambda I)^-1`
+/// with the weights `W` found by fixed-point iteration.
+///
+/// The weights are calculated per state, so each component of a voxel is
+/// weighted separately.
+///
+/// # Errors
+///
+/// Returns an error if the regularized gram matrix is not positive definite.
+#[tracing::instrument(level = "trace", skip(measurement_matrix))]
+fn eloreta_operator(
+    measurement_matrix: &ArrayView2<f64>,
+    relative_strength: f64,
+) -> Result<Array2<f64>> {
+    let (num_sensors, num_states) = measurement_matrix.dim();
+    let mut weights = Array1::<f64>::ones(num_states);
+    let mut gram_inverse = Array2::zeros((num_sensors, num_sensors));
+    for iteration in 0..MAX_ELORETA_ITERATIONS {
+        let weighted = measurement_matrix / &weights;
+        let mut gram = weighted.dot(&measurement_matrix.t());
+        #[allow(clippy::cast_precision_loss)]
+        let lambda = relative_strength * gram.diag().sum() / num_sensors.max(1) as f64;
+        gram.diag_mut().mapv_inplace(|v| v + lambda);
+        let inverse = DMatrix::from_row_iterator(num_sensors, num_sensors, gram.iter().copied())
+            .cholesky()
+            .context("Regularized gram matrix is not positive definite")?
+            .inverse();
+        gram_inverse = Array2::from_shape_fn((num_sensors, num_sensors), |index| inverse[index]);
+
+        let new_weights = (&gram_inverse.dot(measurement_matrix) * measurement_matrix)
+            .sum_axis(Axis(0))
+            .mapv(|v| v.max(f64::MIN_POSITIVE).sqrt());
+        let change = (&new_weights - &weights)
+            .mapv(f64::abs)
+            .fold(0.0, |max: f64, &v| max.max(v));
+        let scale = new_weights.fold(f64::MIN_POSITIVE, |max: f64, &v| max.max(v));
+        weights = new_weights;
+        if change <= ELORETA_TOLERANCE * scale {
+            trace!(
+                "eLORETA weights converged after {} iterations",
+                iteration + 1
+            );
+            break;
+        }
+    }
+    let weighted = measurement_matrix / &weights;
+    Ok(weighted.t().dot(&gram_inverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+
+    fn measurement_matrix() -> Array2<f64> {
+        arr2(&[
+            [1.0, 0.5, 0.2, 0.0],
+            [0.0, 1.0, 0.5, 0.2],
+            [0.2, 0.0, 1.0, 0.5],
+        ])
+    }
+
+    #[test]
+    fn minimum_norm_approaches_pseudo_inverse() -> anyhow::Result<()> {
+        let h = measurement_matrix();
+        let measurements = Array2::zeros((1, 3));
+
+        let operator = calculate_inverse_operator(
+            &h.view(),
+            &measurements.view(),
+            &AlgorithmType::MinimumNorm,
+            &RegularizationSelection::Fixed,
+            1e-9,
+        )?;
+
+        // H G is the identity for a full rank H with more states than sensors
+        let product = h.dot(&operator);
+        for ((row, column), value) in product.indexed_iter() {
+            let expected = if row == column { 1.0 } else { 0.0 };
+            assert_relative_eq!(*value, expected, epsilon = 1e-6);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sloreta_standardizes_resolution_diagonal() -> anyhow::Result<()> {
+        let h = measurement_matrix();
+        let measurements = Array2::zeros((1, 3));
+
+        let operator = calculate_inverse_operator(
+            &h.view(),
+            &measurements.view(),
+            &AlgorithmType::SLoreta,
+            &RegularizationSelection::Fixed,
+            1e-2,
+        )?;
+
+        // rows scaled by the inverse square root of the resolution diagonal
+        let minimum_norm = calculate_inverse_operator(
+            &h.view(),
+            &measurements.view(),
+            &AlgorithmType::MinimumNorm,
+            &RegularizationSelection::Fixed,
+            1e-2,
+        )?;
+        let resolution = minimum_norm.dot(&h);
+        let standardized = operator.dot(&h);
+        for state in 0..4 {
+            assert_relative_eq!(
+                standardized[(state, state)],
+                resolution[(state, state)].sqrt(),
+                epsilon = 1e-9
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn automatic_selection_returns_candidate() -> anyhow::Result<()> {
+        let h = measurement_matrix();
+        let states = arr2(&[[1.0, 0.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0]]);
+        let measurements = states.dot(&h.t()) + 1e-3;
+        let decomposition = Decomposition::new(&h.view())?;
+
+        for selection in [
+            RegularizationSelection::GeneralizedCrossValidation,
+            RegularizationSelection::LCurve,
+        ] {
+            let strength = decomposition.select_regularization(&measurements.view(), &selection);
+            assert!((1e-6..=10.0).contains(&strength));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn eloreta_operator_has_expected_shape() -> anyhow::Result<()> {
+        let h = measurement_matrix();
+        let measurements = Array2::zeros((1, 3));
+
+        let operator = calculate_inverse_operator(
+            &h.view(),
+            &measurements.view(),
+            &AlgorithmType::ELoreta,
+            &RegularizationSelection::Fixed,
+            1e-2,
+        )?;
+
+        assert_eq!(operator.dim(), (4, 3));
+        assert!(operator.iter().all(|v| v.is_finite()));
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use nalgebra::DMatrix;
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+use tracing::{debug, trace};
+
+use super::{
+    estimation::{
+        calculate_residuals,
+        prediction::{calculate_system_prediction, predict_measurements},
+    },
+    metrics,
+    refinement::derivation::calculate_step_derivatives,
+};
+use crate::core::{
+    config::algorithm::Algorithm, data::Data, model::functional::FunctionalDescription,
+    scenario::results::Results,
+};
+
+/// Maximum number of Riccati iterations used to find the steady-state gain.
+const MAX_RICCATI_ITERATIONS: usize = 200;
+/// Relative change of the state covariance below which the Riccati
+/// iteration is considered converged.
+const RICCATI_TOLERANCE: f64 = 1e-6;
+
+/// Estimates the system states with a steady-state Kalman filter.
+///
+/// The all-pass state-space model, using the corrected states of the past
+/// steps, provides the prediction. The prediction is then corrected with the
+/// gain calculated by [`calculate_steady_state_gain`] for the measurement
+/// matrix of the beat. Residuals, derivatives and metrics are calculated
+/// from the corrected states, so the results are comparable to the other
+/// algorithms.
+///
+/// # Errors
+///
+/// Returns an error if the gain calculation fails or the algorithm
+/// parameters are not properly initialized.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn run_kalman_filter(
+    functional_description: &FunctionalDescription,
+    results: &mut Results,
+    data: &Data,
+    config: &Algorithm,
+) -> Result<()> {
+    debug!("Running Kalman filter");
+    let num_steps = results.estimations.system_states.num_steps();
+    let estimations = &mut results.estimations;
+    let derivatives = &mut results.derivatives;
+
+    for beat in 0..data.simulation.measurements.num_beats() {
+        let gain = calculate_steady_state_gain(
+            &functional_description.measurement_matrix.at_beat(beat),
+            config.kalman_process_covariance,
+            config.kalman_measurement_covariance,
+        )
+        .with_context(|| format!("Failed to calculate Kalman gain for beat {beat}"))?;
+        estimations.reset();
+        for step in 0..num_steps {
+            calculate_system_prediction(estimations, functional_description, beat, step)?;
+            calculate_residuals(estimations, data, beat, step);
+
+            // residuals are predicted minus actual measurements
+            let correction = gain.dot(&*estimations.residuals);
+            estimations
+                .system_states
+                .at_step_mut(step)
+                .scaled_add(-1.0, &correction);
+
+            predict_measurements(estimations, functional_description, beat, step);
+            calculate_residuals(estimations, data, beat, step);
+
+            calculate_step_derivatives(
+                derivatives,
+                estimations,
+                functional_description,
+                config,
+                step,
+                beat,
+            )?;
+
+            metrics::calculate_step(
+                &mut results.metrics,
+                estimations,
+                derivatives.maximum_regularization_sum,
+                config.maximum_regularization_strength,
+                step,
+            );
+        }
+    }
+    metrics::calculate_batch(&mut results.metrics, 0)?;
+    Ok(())
+}
+
+/// Calculates the steady-state Kalman gain for the given measurement matrix.
+///
+/// The state transition is approximated by the identity for the covariance
+/// propagation and the state covariance is approximated by its diagonal,
+/// which keeps the calculation linear in the number of states. The process
+/// and measurement covariances are scaled identity matrices. The Riccati
+/// iteration runs until the state covariance converges.
+///
+/// Returns the gain with dimensions (`number_of_states`, `number_of_sensors`).
+///
+/// # Errors
+///
+/// Returns an error if the covariances are not positive or the innovation
+/// covariance is not positive definite.
+#[allow(clippy::cast_possible_truncation)]
+#[tracing::instrument(level = "debug", skip(measurement_matrix))]
+pub fn calculate_steady_state_gain(
+    measurement_matrix: &ArrayView2<f32>,
+    process_covariance: f32,
+    measurement_covariance: f32,
+) -> Result<Array2<f32>> {
+    debug!("Calculating steady-state Kalman gain");
+    anyhow::ensure!(
+        process_covariance > 0.0 && measurement_covariance > 0.0,
+        "Kalman covariances must be positive"
+    );
+    let (num_sensors, num_states) = measurement_matrix.dim();
+    let h = measurement_matrix.mapv(f64::from);
+    let process_covariance = f64::from(process_covariance);
+    let measurement_covariance = f64::from(measurement_covariance);
+
+    let mut state_covariance = Array1::from_elem(num_states, process_covariance);
+    let mut gain_transposed = Array2::zeros((num_sensors, num_states));
+    for iteration in 0..MAX_RICCATI_ITERATIONS {
+        let prior_covariance = &state_covariance + process_covariance;
+        // H P, with P diagonal
+        let hp = &h * &prior_covariance;
+        let mut innovation_covariance = hp.dot(&h.t());
+        innovation_covariance
+            .diag_mut()
+            .mapv_inplace(|v| v + measurement_covariance);
+
+        let cholesky = DMatrix::from_row_slice(
+            num_sensors,
+            num_sensors,
+            innovation_covariance
+                .as_slice()
+                .context("Innovation covariance not in standard layout")?,
+        )
+        .cholesky()
+        .context("Innovation covariance is not positive definite")?;
+        let solution = cholesky.solve(&DMatrix::from_row_slice(
+            num_sensors,
+            num_states,
+            hp.as_slice().context("H P not in standard layout")?,
+        ));
+        gain_transposed = Array2::from_shape_fn((num_sensors, num_states), |index| solution[index]);
+
+        let posterior_covariance = &prior_covariance - (&gain_transposed * &hp).sum_axis(Axis(0));
+        let change = (&posterior_covariance - &state_covariance)
+            .mapv(f64::abs)
+            .fold(0.0, |max: f64, &v| max.max(v));
+        let scale = posterior_covariance.fold(f64::MIN_POSITIVE, |max: f64, &v| max.max(v));
+        state_covariance = posterior_covariance;
+        if change <= RICCATI_TOLERANCE * scale {
+            trace!(
+                "Riccati iteration converged after {} iterations",
+                iteration + 1
+            );
+            break;
+        }
+    }
+    Ok(gain_transposed.t().mapv(|v| v as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn scalar_gain_matches_riccati_solution() -> anyhow::Result<()> {
+        let (q, r) = (0.1f32, 0.5f32);
+        let measurement_matrix = arr2(&[[1.0f32]]);
+
+        let gain = calculate_steady_state_gain(&measurement_matrix.view(), q, r)?;
+
+        // steady-state prior covariance solves p^2 - q p - q r = 0
+        let prior = q.mul_add(q, 4.0 * q * r).sqrt().mul_add(0.5, q / 2.0);
+        assert_relative_eq!(gain[(0, 0)], prior / (prior + r), epsilon = 1e-4);
+        Ok(())
+    }
+
+    #[test]
+    fn non_positive_covariance_is_rejected() {
+        let measurement_matrix = arr2(&[[1.0f32, 0.5]]);
+        assert!(calculate_steady_state_gain(&measurement_matrix.view(), 0.0, 1.0).is_err());
+    }
+}
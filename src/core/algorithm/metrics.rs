@@ -1,19 +1,32 @@
+pub mod activation_time;
+pub mod localization;
+pub mod parameter_history;
+pub mod velocity;
+
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::BufWriter,
     ops::{Deref, DerefMut},
 };
 
 use anyhow::{Context, Result};
-use ndarray::Array1;
+use ndarray::{Array1, Array2, Axis};
 use ndarray_npy::WriteNpyExt;
 use ndarray_stats::QuantileExt;
 use ocl::Buffer;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use super::estimation::Estimations;
-use crate::core::model::spatial::voxels::{VoxelNumbers, VoxelType, VoxelTypes};
+use self::parameter_history::ParameterHistory;
+use super::{estimation::Estimations, gpu::buffer_bytes, refinement::loss::LossTermKind};
+use crate::core::{
+    config::algorithm::ParameterRecording,
+    model::{
+        functional::allpass::APParameters,
+        spatial::voxels::{VoxelNumbers, VoxelType, VoxelTypes},
+    },
+};
 
 #[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -34,6 +47,38 @@ pub struct Metrics {
     pub precision_over_threshold: Array1<f32>,
     #[serde(default)]
     pub recall_over_threshold: Array1<f32>,
+    #[serde(default)]
+    pub false_positive_rate_over_threshold: Array1<f32>,
+
+    // regularization path of the pseudo inverse, empty if not calculated
+    #[serde(default)]
+    pub regularization_lambdas: Array1<f32>,
+    #[serde(default)]
+    pub loss_mse_over_lambda: Array1<f32>,
+    #[serde(default)]
+    pub dice_score_over_lambda: Array1<f32>,
+
+    // per-step losses of every epoch, only recorded if requested in the
+    // algorithm config. otherwise the sample-wise metrics above only serve
+    // as working buffers for the current epoch and are discarded before
+    // the results are stored.
+    #[serde(default)]
+    pub step_traces: Option<StepTraces>,
+
+    // all-pass parameters of every epoch, only recorded if requested in the
+    // algorithm config
+    #[serde(default)]
+    pub parameter_history: Option<ParameterHistory>,
+
+    // weighted values of the loss terms per batch. they are tracked
+    // separately, the total loss above stays comparable to the GPU algorithm
+    // which does not support them.
+    #[serde(default)]
+    pub loss_terms: BTreeMap<LossTermKind, BatchWiseMetric>,
+
+    // number of parameters projected into the physiological bounds per batch
+    #[serde(default)]
+    pub bound_violations_batch: Array1<usize>,
 }
 
 pub struct MetricsGPU {
@@ -45,6 +90,20 @@ pub struct MetricsGPU {
     pub loss_maximum_regularization_batch: Buffer<f32>,
 }
 
+impl MetricsGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        buffer_bytes(&self.loss)
+            + buffer_bytes(&self.loss_batch)
+            + buffer_bytes(&self.loss_mse)
+            + buffer_bytes(&self.loss_mse_batch)
+            + buffer_bytes(&self.loss_maximum_regularization)
+            + buffer_bytes(&self.loss_maximum_regularization_batch)
+    }
+}
+
 impl Metrics {
     /// Creates a new `Metrics` struct initialized with zeroed arrays for tracking metrics
     /// over epochs and steps.
@@ -71,7 +130,135 @@ impl Metrics {
             iou_over_threshold: Array1::zeros(101),
             precision_over_threshold: Array1::zeros(101),
             recall_over_threshold: Array1::zeros(101),
+            false_positive_rate_over_threshold: Array1::zeros(101),
+
+            regularization_lambdas: Array1::zeros(0),
+            loss_mse_over_lambda: Array1::zeros(0),
+            dice_score_over_lambda: Array1::zeros(0),
+
+            step_traces: None,
+            parameter_history: None,
+            loss_terms: BTreeMap::new(),
+            bound_violations_batch: Array1::zeros(number_of_epochs * number_of_batches),
+        }
+    }
+
+    /// Returns the area under the ROC curve, i.e. the recall over the false
+    /// positive rate of all thresholds.
+    ///
+    /// Returns 0.0 if the false positive rates were not calculated.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn roc_auc(&self) -> f32 {
+        if self.false_positive_rate_over_threshold.len() != self.recall_over_threshold.len() {
+            return 0.0;
+        }
+        let mut points: Vec<(f32, f32)> = self
+            .false_positive_rate_over_threshold
+            .iter()
+            .copied()
+            .zip(self.recall_over_threshold.iter().copied())
+            .collect();
+        points.push((0.0, 0.0));
+        points.push((1.0, 1.0));
+        area_under_curve(points)
+    }
+
+    /// Returns the area under the precision-recall curve.
+    ///
+    /// Thresholds without any recalled voxel are skipped and the curve is
+    /// extended to zero recall with the precision of the lowest recall.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn precision_recall_auc(&self) -> f32 {
+        let mut points: Vec<(f32, f32)> = self
+            .recall_over_threshold
+            .iter()
+            .copied()
+            .zip(self.precision_over_threshold.iter().copied())
+            .filter(|(recall, _)| *recall > 0.0)
+            .collect();
+        if let Some(&(_, precision)) = points
+            .iter()
+            .min_by(|a, b| a.0.total_cmp(&b.0).then(b.1.total_cmp(&a.1)))
+        {
+            points.push((0.0, precision));
+        }
+        area_under_curve(points)
+    }
+
+    /// Appends the per-step losses of the current epoch to the step traces,
+    /// creating them on first use.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of steps changed between epochs.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn record_step_traces(&mut self) -> Result<()> {
+        trace!("Recording step traces");
+        let number_of_steps = self.loss.len();
+        let traces = self.step_traces.get_or_insert_with(|| StepTraces {
+            loss: Array2::zeros((0, number_of_steps)),
+            loss_mse: Array2::zeros((0, number_of_steps)),
+            loss_maximum_regularization: Array2::zeros((0, number_of_steps)),
+        });
+        traces
+            .loss
+            .push_row(self.loss.view())
+            .context("Failed to record loss trace")?;
+        traces
+            .loss_mse
+            .push_row(self.loss_mse.view())
+            .context("Failed to record MSE loss trace")?;
+        traces
+            .loss_maximum_regularization
+            .push_row(self.loss_maximum_regularization.view())
+            .context("Failed to record maximum regularization loss trace")?;
+        Ok(())
+    }
+
+    /// Appends the current all-pass parameters to the parameter history,
+    /// creating it on first use. Does nothing if no parameters are recorded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a recorded voxel is out of range.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn record_parameter_history(
+        &mut self,
+        ap_params: &APParameters,
+        recording: &ParameterRecording,
+    ) -> Result<()> {
+        if !recording.is_enabled() {
+            return Ok(());
         }
+        trace!("Recording parameter history");
+        self.parameter_history
+            .get_or_insert_with(|| ParameterHistory::new(recording.voxels.clone()))
+            .record(ap_params, recording.statistics)
+    }
+
+    /// Stores the weighted values of the loss terms for the given batch,
+    /// creating their metrics on first use.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn record_loss_terms(&mut self, values: &[(LossTermKind, f32)], batch_index: usize) {
+        trace!("Recording loss terms");
+        let number_of_batches = self.loss_batch.len();
+        for &(kind, value) in values {
+            self.loss_terms
+                .entry(kind)
+                .or_insert_with(|| BatchWiseMetric::new(number_of_batches, 1))[batch_index] = value;
+        }
+    }
+
+    /// Drops the per-step losses of the last epoch so that only the
+    /// batch-wise aggregates and recorded step traces are stored.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn discard_step_metrics(&mut self) {
+        debug!("Discarding per-step metrics");
+        self.loss = SampleWiseMetric::new(0);
+        self.loss_mse = SampleWiseMetric::new(0);
+        self.loss_maximum_regularization = SampleWiseMetric::new(0);
     }
 
     /// Saves all metric arrays to .npy files in the given path.
@@ -86,15 +273,37 @@ impl Metrics {
         fs::create_dir_all(path)
             .with_context(|| format!("Failed to create metrics directory: {}", path.display()))?;
 
-        self.loss.save_npy(path, "loss.npy")?;
+        if !self.loss.is_empty() {
+            self.loss.save_npy(path, "loss.npy")?;
+            self.loss_mse.save_npy(path, "loss_mse.npy")?;
+            self.loss_maximum_regularization
+                .save_npy(path, "loss_maximum_regularization.npy")?;
+        }
         self.loss_batch.save_npy(path, "loss_epoch.npy")?;
-
-        self.loss_mse.save_npy(path, "loss_mse.npy")?;
         self.loss_mse_batch.save_npy(path, "loss_mse_epoch.npy")?;
-        self.loss_maximum_regularization
-            .save_npy(path, "loss_maximum_regularization.npy")?;
         self.loss_maximum_regularization_batch
             .save_npy(path, "loss_maximum_regularization_epoch.npy")?;
+        if let Some(traces) = &self.step_traces {
+            traces.save_npy(path)?;
+        }
+        if let Some(history) = &self.parameter_history {
+            history.save_npy(path)?;
+        }
+        for (kind, values) in &self.loss_terms {
+            values.save_npy(path, &format!("loss_{kind:?}_epoch.npy"))?;
+        }
+        let writer = BufWriter::new(
+            File::create(path.join("bound_violations_epoch.npy")).with_context(|| {
+                format!(
+                    "Failed to create bound_violations_epoch.npy file in {}",
+                    path.display()
+                )
+            })?,
+        );
+        self.bound_violations_batch
+            .mapv(|violations| violations as u64)
+            .write_npy(writer)
+            .context("Failed to write bound violations to NPY file")?;
 
         let writer =
             BufWriter::new(File::create(path.join("dice.npy")).with_context(|| {
@@ -128,6 +337,34 @@ impl Metrics {
             .write_npy(writer)
             .context("Failed to write recall data to NPY file")?;
 
+        let writer = BufWriter::new(
+            File::create(path.join("false_positive_rate.npy")).with_context(|| {
+                format!(
+                    "Failed to create false_positive_rate.npy file in {}",
+                    path.display()
+                )
+            })?,
+        );
+        self.false_positive_rate_over_threshold
+            .write_npy(writer)
+            .context("Failed to write false positive rate data to NPY file")?;
+
+        if !self.regularization_lambdas.is_empty() {
+            for (file_name, values) in [
+                ("regularization_lambdas.npy", &self.regularization_lambdas),
+                ("loss_mse_over_lambda.npy", &self.loss_mse_over_lambda),
+                ("dice_over_lambda.npy", &self.dice_score_over_lambda),
+            ] {
+                let writer =
+                    BufWriter::new(File::create(path.join(file_name)).with_context(|| {
+                        format!("Failed to create {file_name} file in {}", path.display())
+                    })?);
+                values
+                    .write_npy(writer)
+                    .with_context(|| format!("Failed to write {file_name}"))?;
+            }
+        }
+
         Ok(())
     }
 
@@ -145,17 +382,52 @@ impl Metrics {
         })
     }
 
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
     pub(crate) fn update_from_gpu(&mut self, metrics: &MetricsGPU) -> Result<()> {
-        self.loss.update_from_gpu(&metrics.loss)?;
+        self.update_steps_from_gpu(metrics)?;
         self.loss_batch.update_from_gpu(&metrics.loss_batch)?;
-        self.loss_mse.update_from_gpu(&metrics.loss_mse)?;
         self.loss_mse_batch
             .update_from_gpu(&metrics.loss_mse_batch)?;
+        self.loss_maximum_regularization_batch
+            .update_from_gpu(&metrics.loss_maximum_regularization_batch)?;
+        Ok(())
+    }
+
+    /// Reads the per-step losses of the last epoch from the GPU.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a buffer fails.
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    pub fn update_steps_from_gpu(&mut self, metrics: &MetricsGPU) -> Result<()> {
+        self.loss.update_from_gpu(&metrics.loss)?;
+        self.loss_mse.update_from_gpu(&metrics.loss_mse)?;
         self.loss_maximum_regularization
             .update_from_gpu(&metrics.loss_maximum_regularization)?;
+        Ok(())
+    }
+
+    /// Reads only the batch-wise losses of the given batch from the GPU.
+    ///
+    /// Used during training instead of reading all metrics, so that a batch
+    /// transfers three values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the batch index is out of bounds or reading a
+    /// buffer fails.
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    pub fn update_batch_from_gpu(
+        &mut self,
+        metrics: &MetricsGPU,
+        batch_index: usize,
+    ) -> Result<()> {
+        self.loss_batch
+            .update_value_from_gpu(&metrics.loss_batch, batch_index)?;
+        self.loss_mse_batch
+            .update_value_from_gpu(&metrics.loss_mse_batch, batch_index)?;
         self.loss_maximum_regularization_batch
-            .update_from_gpu(&metrics.loss_maximum_regularization_batch)?;
+            .update_value_from_gpu(&metrics.loss_maximum_regularization_batch, batch_index)?;
         Ok(())
     }
 }
@@ -219,8 +491,8 @@ pub fn calculate_batch(metrics: &mut Metrics, epoch_index: usize) -> Result<()>
 }
 
 /// Calculates metrics over the full range of thresholds from 0 to 1 by incrementing
-/// in steps of 0.01. Stores the dice score, `IoU`, precision, and recall for each
-/// threshold value in the given metric arrays.
+/// in steps of 0.01. Stores the dice score, `IoU`, precision, recall, and false
+/// positive rate for each threshold value in the given metric arrays.
 #[allow(clippy::cast_precision_loss)]
 #[tracing::instrument(level = "debug", skip_all)]
 pub fn calculate_final(
@@ -232,15 +504,35 @@ pub fn calculate_final(
     debug!("Calculating final metrics");
     for i in 0..=100 {
         let threshold = i as f32 / 100.0;
-        let (dice, iou, precision, recall) =
+        let (dice, iou, precision, recall, false_positive_rate) =
             calculate_for_threshold(estimations, ground_truth, voxel_numbers, threshold);
         metrics.dice_score_over_threshold[i] = dice;
         metrics.iou_over_threshold[i] = iou;
         metrics.precision_over_threshold[i] = precision;
         metrics.recall_over_threshold[i] = recall;
+        metrics.false_positive_rate_over_threshold[i] = false_positive_rate;
     }
 }
-/// Calculates Dice score, `IoU`, precision, and recall for the given estimations, ground truth, and voxel numbers at the specified threshold.
+/// Returns the highest dice score over the thresholds from 0 to 1 in steps of
+/// 0.01, as used for the summary.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn calculate_best_dice(
+    estimations: &Estimations,
+    ground_truth: &VoxelTypes,
+    voxel_numbers: &VoxelNumbers,
+) -> f32 {
+    trace!("Calculating best dice score");
+    (0..=100)
+        .map(|i| {
+            let predictions =
+                predict_voxeltype(estimations, ground_truth, voxel_numbers, i as f32 / 100.0);
+            calculate_dice(&predictions, ground_truth)
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Calculates Dice score, `IoU`, precision, recall, and false positive rate for the given estimations, ground truth, and voxel numbers at the specified threshold.
 ///
 /// The estimations, ground truth, and voxel numbers are used to generate voxel type predictions at the given threshold.
 /// These predictions are then compared to the ground truth to calculate the metrics.
@@ -250,7 +542,7 @@ fn calculate_for_threshold(
     ground_truth: &VoxelTypes,
     voxel_numbers: &VoxelNumbers,
     threshold: f32,
-) -> (f32, f32, f32, f32) {
+) -> (f32, f32, f32, f32, f32) {
     trace!(
         "Calculating segmentation metrics for threshold {}",
         threshold
@@ -261,8 +553,41 @@ fn calculate_for_threshold(
     let iou = calculate_iou(&predictions, ground_truth);
     let precision = calculate_precision(&predictions, ground_truth);
     let recall = calculate_recall(&predictions, ground_truth);
+    let false_positive_rate = calculate_false_positive_rate(&predictions, ground_truth);
+
+    (dice, iou, precision, recall, false_positive_rate)
+}
 
-    (dice, iou, precision, recall)
+/// Calculates the false positive rate for the given predictions and ground truth voxel types.
+///
+/// The false positive rate is defined as the ratio of false positives to total
+/// negatives, i.e. predicted voxels that are not pathological in the ground truth.
+/// Returns 0.0 if there are no ground truth negatives.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace")]
+fn calculate_false_positive_rate(predictions: &VoxelTypes, ground_truth: &VoxelTypes) -> f32 {
+    trace!("Calculating false positive rate");
+    let gt_negatives = predictions
+        .iter()
+        .zip(ground_truth.iter())
+        .filter(|(prediction, ground_truth)| {
+            **prediction != VoxelType::None && **ground_truth != VoxelType::Pathological
+        })
+        .count();
+
+    let false_positives = predictions
+        .iter()
+        .zip(ground_truth.iter())
+        .filter(|(prediction, ground_truth)| {
+            **ground_truth != VoxelType::Pathological && **prediction == VoxelType::Pathological
+        })
+        .count();
+
+    if gt_negatives == 0 {
+        0.0
+    } else {
+        false_positives as f32 / gt_negatives as f32
+    }
 }
 
 /// Calculates the recall for the given predictions and ground truth voxel types.
@@ -446,6 +771,63 @@ pub fn predict_voxeltype(
     predictions
 }
 
+/// Calculates the area under the curve through the given (x, y) points with
+/// the trapezoidal rule, after sorting them by x.
+#[tracing::instrument(level = "trace", skip_all)]
+fn area_under_curve(mut points: Vec<(f32, f32)>) -> f32 {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0) * (pair[0].1 + pair[1].1) / 2.0)
+        .sum()
+}
+
+/// Per-step losses of all epochs.
+///
+/// Has dimensions (`number_of_epochs`, `number_of_steps`), epochs are
+/// appended as they finish.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StepTraces {
+    pub loss: Array2<f32>,
+    pub loss_mse: Array2<f32>,
+    pub loss_maximum_regularization: Array2<f32>,
+}
+
+impl StepTraces {
+    /// Returns the number of recorded epochs.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn num_epochs(&self) -> usize {
+        self.loss.len_of(Axis(0))
+    }
+
+    /// Saves the traces to .npy files in the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file I/O operation fails.
+    #[tracing::instrument(level = "trace")]
+    fn save_npy(&self, path: &std::path::Path) -> Result<()> {
+        trace!("Saving step traces to npy");
+        for (file_name, values) in [
+            ("loss_trace.npy", &self.loss),
+            ("loss_mse_trace.npy", &self.loss_mse),
+            (
+                "loss_maximum_regularization_trace.npy",
+                &self.loss_maximum_regularization,
+            ),
+        ] {
+            let writer = BufWriter::new(File::create(path.join(file_name)).with_context(|| {
+                format!("Failed to create {file_name} file in {}", path.display())
+            })?);
+            values
+                .write_npy(writer)
+                .with_context(|| format!("Failed to write {file_name}"))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct SampleWiseMetric(Array1<f32>);
 
@@ -587,6 +969,25 @@ impl BatchWiseMetric {
             .context("Failed to read batch-wise metric from GPU buffer")?;
         Ok(())
     }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn update_value_from_gpu(
+        &mut self,
+        loss_batch: &Buffer<f32>,
+        batch_index: usize,
+    ) -> Result<()> {
+        let value = self
+            .get_mut(batch_index)
+            .with_context(|| format!("Batch index {batch_index} out of bounds"))?;
+        let mut host_value = [0.0];
+        loss_batch
+            .read(host_value.as_mut_slice())
+            .offset(batch_index)
+            .enq()
+            .context("Failed to read batch-wise metric value from GPU buffer")?;
+        *value = host_value[0];
+        Ok(())
+    }
 }
 
 impl Deref for BatchWiseMetric {
@@ -604,3 +1005,38 @@ impl DerefMut for BatchWiseMetric {
         &mut self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn perfect_classifier_has_unit_auc() {
+        let mut metrics = Metrics::new(1, 1, 1);
+        metrics.false_positive_rate_over_threshold.fill(0.0);
+        metrics.recall_over_threshold.fill(1.0);
+        metrics.precision_over_threshold.fill(1.0);
+
+        assert_relative_eq!(metrics.roc_auc(), 1.0);
+        assert_relative_eq!(metrics.precision_recall_auc(), 1.0);
+    }
+
+    #[test]
+    fn chance_classifier_has_half_roc_auc() {
+        let mut metrics = Metrics::new(1, 1, 1);
+        metrics.false_positive_rate_over_threshold = Array1::linspace(0.0, 1.0, 101);
+        metrics.recall_over_threshold = Array1::linspace(0.0, 1.0, 101);
+
+        assert_relative_eq!(metrics.roc_auc(), 0.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn missing_false_positive_rates_yield_zero_roc_auc() {
+        let mut metrics = Metrics::new(1, 1, 1);
+        metrics.false_positive_rate_over_threshold = Array1::zeros(0);
+
+        assert_relative_eq!(metrics.roc_auc(), 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::model::functional::allpass::shapes::ActivationTimeMs;
+
+// factor of the standard deviation for the 95 % limits of agreement
+const LIMITS_OF_AGREEMENT_FACTOR: f32 = 1.96;
+
+/// Agreement of the estimated with the simulated activation times of all
+/// voxels that are activated in both, in ms.
+///
+/// The errors are taken as estimation minus simulation, so a positive bias
+/// means the estimated activation is late.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ActivationTimeStatistics {
+    pub count: usize,
+    pub mean_absolute_error_ms: f32,
+    pub bias_ms: f32,
+    pub std_ms: f32,
+    pub lower_limit_of_agreement_ms: f32,
+    pub upper_limit_of_agreement_ms: f32,
+}
+
+impl ActivationTimeStatistics {
+    /// Calculates the statistics of the given errors.
+    ///
+    /// Returns `None` if no errors are given.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip(errors_ms))]
+    pub fn from_errors(errors_ms: &[f32]) -> Option<Self> {
+        if errors_ms.is_empty() {
+            return None;
+        }
+        let count = errors_ms.len();
+        let bias = errors_ms.iter().sum::<f32>() / count as f32;
+        let mean_absolute_error = errors_ms.iter().map(|e| e.abs()).sum::<f32>() / count as f32;
+        let std = (errors_ms.iter().map(|e| (e - bias).powi(2)).sum::<f32>() / count as f32).sqrt();
+        Some(Self {
+            count,
+            mean_absolute_error_ms: mean_absolute_error,
+            bias_ms: bias,
+            std_ms: std,
+            lower_limit_of_agreement_ms: LIMITS_OF_AGREEMENT_FACTOR.mul_add(-std, bias),
+            upper_limit_of_agreement_ms: LIMITS_OF_AGREEMENT_FACTOR.mul_add(std, bias),
+        })
+    }
+}
+
+/// Returns the (simulated, estimated) activation time pairs of all voxels
+/// that are activated in both.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn activation_time_pairs(
+    simulation: &ActivationTimeMs,
+    estimation: &ActivationTimeMs,
+) -> Vec<(f32, f32)> {
+    simulation
+        .iter()
+        .zip(estimation.iter())
+        .filter_map(|(simulated, estimated)| Some(((*simulated)?, (*estimated)?)))
+        .collect()
+}
+
+/// Calculates the activation time error statistics between simulation and
+/// estimation.
+///
+/// Voxels that are not activated in either of them are skipped. Returns
+/// `None` if no voxel is activated in both.
+#[must_use]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn calculate_activation_time_statistics(
+    simulation: &ActivationTimeMs,
+    estimation: &ActivationTimeMs,
+) -> Option<ActivationTimeStatistics> {
+    debug!("Calculating activation time error statistics");
+    let errors_ms: Vec<f32> = activation_time_pairs(simulation, estimation)
+        .into_iter()
+        .map(|(simulated, estimated)| estimated - simulated)
+        .collect();
+    ActivationTimeStatistics::from_errors(&errors_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::Dim;
+
+    use super::*;
+
+    #[test]
+    fn statistics_of_known_errors() -> anyhow::Result<()> {
+        let statistics = ActivationTimeStatistics::from_errors(&[1.0, -1.0, 3.0, 1.0])
+            .ok_or_else(|| anyhow::anyhow!("Expected statistics for non-empty errors"))?;
+
+        assert_eq!(statistics.count, 4);
+        assert_relative_eq!(statistics.bias_ms, 1.0);
+        assert_relative_eq!(statistics.mean_absolute_error_ms, 1.5);
+        assert_relative_eq!(statistics.std_ms, 2.0f32.sqrt());
+        assert_relative_eq!(
+            statistics.lower_limit_of_agreement_ms,
+            1.96f32.mul_add(-(2.0f32.sqrt()), 1.0)
+        );
+        assert_relative_eq!(
+            statistics.upper_limit_of_agreement_ms,
+            1.96f32.mul_add(2.0f32.sqrt(), 1.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inactive_voxels_are_skipped() {
+        let mut simulation = ActivationTimeMs::empty(Dim([2, 1, 1]));
+        let mut estimation = ActivationTimeMs::empty(Dim([2, 1, 1]));
+        simulation[(0, 0, 0)] = Some(10.0);
+        estimation[(0, 0, 0)] = Some(12.0);
+        simulation[(1, 0, 0)] = Some(5.0);
+
+        let statistics = calculate_activation_time_statistics(&simulation, &estimation);
+
+        assert_eq!(statistics.map(|statistics| statistics.count), Some(1));
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::model::spatial::voxels::{VoxelPositions, VoxelType, VoxelTypes};
+
+/// Localization of the predicted pathology relative to the ground truth.
+///
+/// The error is the Euclidean distance between the centroids of the
+/// predicted and the true pathological voxels. The extent error is the
+/// predicted minus the true pathological volume, so a positive value means
+/// the pathology is overestimated.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Localization {
+    pub error_mm: f32,
+    pub extent_error_mm3: f32,
+}
+
+/// Calculates the localization of the predicted pathology.
+///
+/// Returns `None` if either the prediction or the ground truth contains no
+/// pathological voxel.
+#[must_use]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn calculate_localization(
+    predictions: &VoxelTypes,
+    ground_truth: &VoxelTypes,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+) -> Option<Localization> {
+    debug!("Calculating pathology localization");
+    let (predicted_centroid, predicted_count) =
+        pathology_centroid(predictions, voxel_positions_mm)?;
+    let (true_centroid, true_count) = pathology_centroid(ground_truth, voxel_positions_mm)?;
+    let error_mm = predicted_centroid
+        .iter()
+        .zip(true_centroid.iter())
+        .map(|(predicted, truth)| (predicted - truth).powi(2))
+        .sum::<f32>()
+        .sqrt();
+    #[allow(clippy::cast_precision_loss)]
+    let extent_error_mm3 = (predicted_count as f32 - true_count as f32) * voxel_size_mm.powi(3);
+    Some(Localization {
+        error_mm,
+        extent_error_mm3,
+    })
+}
+
+/// Returns the centroid in mm and the number of pathological voxels, or
+/// `None` if there are no pathological voxels.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn pathology_centroid(
+    types: &VoxelTypes,
+    voxel_positions_mm: &VoxelPositions,
+) -> Option<([f32; 3], usize)> {
+    let mut sum = [0.0; 3];
+    let mut count = 0;
+    for ((x, y, z), voxel_type) in types.indexed_iter() {
+        if *voxel_type == VoxelType::Pathological {
+            for (dimension, value) in sum.iter_mut().enumerate() {
+                *value += voxel_positions_mm[(x, y, z, dimension)];
+            }
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return None;
+    }
+    Some((sum.map(|value| value / count as f32), count))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    fn positions(voxels_in_dims: [usize; 3], voxel_size_mm: f32) -> VoxelPositions {
+        let mut positions = VoxelPositions::empty(voxels_in_dims);
+        #[allow(clippy::cast_precision_loss)]
+        for ((x, y, z, dimension), value) in positions.indexed_iter_mut() {
+            *value = [x, y, z][dimension] as f32 * voxel_size_mm;
+        }
+        positions
+    }
+
+    #[test]
+    fn shifted_prediction_has_distance_and_extent_error() -> anyhow::Result<()> {
+        let positions = positions([4, 1, 1], 2.0);
+        let mut ground_truth = VoxelTypes::empty([4, 1, 1]);
+        let mut predictions = VoxelTypes::empty([4, 1, 1]);
+        ground_truth[(0, 0, 0)] = VoxelType::Pathological;
+        predictions[(2, 0, 0)] = VoxelType::Pathological;
+        predictions[(3, 0, 0)] = VoxelType::Pathological;
+
+        let localization = calculate_localization(&predictions, &ground_truth, &positions, 2.0)
+            .ok_or_else(|| anyhow::anyhow!("Expected a localization"))?;
+
+        assert_relative_eq!(localization.error_mm, 5.0);
+        assert_relative_eq!(localization.extent_error_mm3, 8.0);
+        Ok(())
+    }
+
+    #[test]
+    fn no_prediction_yields_no_localization() {
+        let positions = positions([2, 1, 1], 1.0);
+        let mut ground_truth = VoxelTypes::empty([2, 1, 1]);
+        ground_truth[(0, 0, 0)] = VoxelType::Pathological;
+
+        assert!(calculate_localization(
+            &VoxelTypes::empty([2, 1, 1]),
+            &ground_truth,
+            &positions,
+            1.0
+        )
+        .is_none());
+    }
+}
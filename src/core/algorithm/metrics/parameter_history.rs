@@ -0,0 +1,215 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::{Context, Result};
+use ndarray::{Array1, Array2, Axis};
+use ndarray_npy::WriteNpyExt;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use crate::core::model::functional::allpass::{from_coef_to_samples, APParameters};
+
+/// All-pass parameters recorded after every epoch.
+///
+/// `voxel_delays` has dimensions (`number_of_epochs`, `voxels.len()`) and
+/// holds the mean delay in samples over the connected neighbors of each
+/// recorded voxel, NaN for voxels without connections. The statistics are
+/// taken over all connections and stay empty if they are not recorded.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ParameterHistory {
+    pub voxels: Vec<usize>,
+    pub voxel_delays: Array2<f32>,
+    pub gains_mean: Vec<f32>,
+    pub gains_std: Vec<f32>,
+    pub delays_mean: Vec<f32>,
+    pub delays_std: Vec<f32>,
+}
+
+impl ParameterHistory {
+    /// Creates an empty history recording the delays of the given voxels.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn new(voxels: Vec<usize>) -> Self {
+        debug!("Creating new parameter history");
+        let number_of_voxels = voxels.len();
+        Self {
+            voxels,
+            voxel_delays: Array2::zeros((0, number_of_voxels)),
+            gains_mean: Vec::new(),
+            gains_std: Vec::new(),
+            delays_mean: Vec::new(),
+            delays_std: Vec::new(),
+        }
+    }
+
+    /// Returns the number of recorded epochs.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn num_epochs(&self) -> usize {
+        self.voxel_delays.len_of(Axis(0)).max(self.gains_mean.len())
+    }
+
+    /// Appends the current parameters as a new epoch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a recorded voxel is out of range of the
+    /// parameters.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn record(&mut self, ap_params: &APParameters, statistics: bool) -> Result<()> {
+        trace!("Recording parameter history");
+        let number_of_voxels = ap_params.delays.shape()[0];
+        let number_of_neighbors = ap_params.delays.shape()[1];
+        let connected = |voxel: usize, neighbor: usize| {
+            ap_params.output_state_indices[(voxel * 3, neighbor * 3)].is_some()
+        };
+        let delay = |voxel: usize, neighbor: usize| {
+            ap_params.delays[(voxel, neighbor)] as f32
+                + from_coef_to_samples(ap_params.coefs[(voxel, neighbor)])
+        };
+
+        let mut voxel_delays = Array1::zeros(self.voxels.len());
+        for (value, &voxel) in voxel_delays.iter_mut().zip(&self.voxels) {
+            anyhow::ensure!(
+                voxel < number_of_voxels,
+                "Recorded voxel {voxel} is out of range of {number_of_voxels} voxels"
+            );
+            let delays: Vec<f32> = (0..number_of_neighbors)
+                .filter(|&neighbor| connected(voxel, neighbor))
+                .map(|neighbor| delay(voxel, neighbor))
+                .collect();
+            *value = mean_and_std(&delays).map_or(f32::NAN, |(mean, _)| mean);
+        }
+        self.voxel_delays
+            .push_row(voxel_delays.view())
+            .context("Failed to record voxel delays")?;
+
+        if statistics {
+            let gains: Vec<f32> = ap_params
+                .gains
+                .iter()
+                .zip(ap_params.output_state_indices.iter())
+                .filter(|(_, index)| index.is_some())
+                .map(|(gain, _)| *gain)
+                .collect();
+            let (mean, std) = mean_and_std(&gains).unwrap_or((f32::NAN, f32::NAN));
+            self.gains_mean.push(mean);
+            self.gains_std.push(std);
+
+            let delays: Vec<f32> = (0..number_of_voxels)
+                .flat_map(|voxel| (0..number_of_neighbors).map(move |neighbor| (voxel, neighbor)))
+                .filter(|&(voxel, neighbor)| connected(voxel, neighbor))
+                .map(|(voxel, neighbor)| delay(voxel, neighbor))
+                .collect();
+            let (mean, std) = mean_and_std(&delays).unwrap_or((f32::NAN, f32::NAN));
+            self.delays_mean.push(mean);
+            self.delays_std.push(std);
+        }
+        Ok(())
+    }
+
+    /// Saves the history to .npy files in the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file I/O operation fails.
+    #[tracing::instrument(level = "trace")]
+    pub(crate) fn save_npy(&self, path: &Path) -> Result<()> {
+        trace!("Saving parameter history to npy");
+        let voxels: Array1<u64> = self.voxels.iter().map(|&voxel| voxel as u64).collect();
+        let writer = BufWriter::new(File::create(path.join("history_voxels.npy")).with_context(
+            || {
+                format!(
+                    "Failed to create history_voxels.npy file in {}",
+                    path.display()
+                )
+            },
+        )?);
+        voxels
+            .write_npy(writer)
+            .context("Failed to write history_voxels.npy")?;
+        let writer = BufWriter::new(
+            File::create(path.join("history_voxel_delays.npy")).with_context(|| {
+                format!(
+                    "Failed to create history_voxel_delays.npy file in {}",
+                    path.display()
+                )
+            })?,
+        );
+        self.voxel_delays
+            .write_npy(writer)
+            .context("Failed to write history_voxel_delays.npy")?;
+        for (file_name, values) in [
+            ("history_gains_mean.npy", &self.gains_mean),
+            ("history_gains_std.npy", &self.gains_std),
+            ("history_delays_mean.npy", &self.delays_mean),
+            ("history_delays_std.npy", &self.delays_std),
+        ] {
+            let writer = BufWriter::new(File::create(path.join(file_name)).with_context(|| {
+                format!("Failed to create {file_name} file in {}", path.display())
+            })?);
+            Array1::from(values.clone())
+                .write_npy(writer)
+                .with_context(|| format!("Failed to write {file_name}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the mean and the population standard deviation of the values, or
+/// `None` if there are none.
+#[tracing::instrument(level = "trace", skip_all)]
+fn mean_and_std(values: &[f32]) -> Option<(f32, f32)> {
+    if values.is_empty() {
+        return None;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let count = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / count;
+    Some((mean, variance.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::Dim;
+
+    use super::*;
+    use crate::core::model::functional::allpass::from_samples_to_coef;
+
+    #[test]
+    fn records_connected_parameters() -> Result<()> {
+        let mut ap_params = APParameters::empty(6, Dim([2, 1, 1]), 1);
+        ap_params.delays.fill(2);
+        ap_params.coefs.fill(from_samples_to_coef(0.5));
+        ap_params.gains.fill(1.0);
+        // only the first voxel is connected, to one neighbor with a larger gain
+        for output in 0..3 {
+            for input in 0..3 {
+                ap_params.output_state_indices[(input, output)] = Some(3 + input);
+                ap_params.gains[(input, output)] = 3.0;
+            }
+        }
+
+        let mut history = ParameterHistory::new(vec![0, 1]);
+        history.record(&ap_params, true)?;
+        history.record(&ap_params, false)?;
+
+        assert_eq!(history.num_epochs(), 2);
+        assert_relative_eq!(history.voxel_delays[(0, 0)], 2.5, epsilon = 1e-5);
+        assert!(history.voxel_delays[(1, 1)].is_nan());
+        assert_eq!(history.gains_mean.len(), 1);
+        assert_relative_eq!(history.gains_mean[0], 3.0);
+        assert_relative_eq!(history.gains_std[0], 0.0);
+        assert_relative_eq!(history.delays_mean[0], 2.5, epsilon = 1e-5);
+        assert!(ParameterHistory::new(vec![2])
+            .record(&ap_params, false)
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use tracing::debug;
+
+use crate::core::{
+    algorithm::refinement::derivation::AverageDelays,
+    model::spatial::voxels::{VoxelNumbers, VoxelType, VoxelTypes},
+};
+
+/// Distribution of the effective propagation velocity of all voxels of one
+/// ground-truth voxel type, in m/s.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct VelocityStatistics {
+    pub voxel_type: VoxelType,
+    pub count: usize,
+    pub mean_m_per_s: f32,
+    pub std_m_per_s: f32,
+    pub min_m_per_s: f32,
+    pub lower_quartile_m_per_s: f32,
+    pub median_m_per_s: f32,
+    pub upper_quartile_m_per_s: f32,
+    pub max_m_per_s: f32,
+}
+
+impl VelocityStatistics {
+    /// Calculates the statistics of the given velocities.
+    ///
+    /// Returns `None` if no velocities are given. Quartiles are linearly
+    /// interpolated between the closest ranks.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip(velocities))]
+    pub fn from_velocities(voxel_type: VoxelType, mut velocities: Vec<f32>) -> Option<Self> {
+        if velocities.is_empty() {
+            return None;
+        }
+        velocities.sort_by(f32::total_cmp);
+        let count = velocities.len();
+        let mean = velocities.iter().sum::<f32>() / count as f32;
+        let std =
+            (velocities.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / count as f32).sqrt();
+        Some(Self {
+            voxel_type,
+            count,
+            mean_m_per_s: mean,
+            std_m_per_s: std,
+            min_m_per_s: velocities[0],
+            lower_quartile_m_per_s: quantile(&velocities, 0.25),
+            median_m_per_s: quantile(&velocities, 0.5),
+            upper_quartile_m_per_s: quantile(&velocities, 0.75),
+            max_m_per_s: velocities[count - 1],
+        })
+    }
+}
+
+/// Calculates the effective propagation velocity of every voxel and
+/// aggregates it per ground-truth voxel type.
+///
+/// The average delays are given in samples per voxel distance, so the
+/// velocity is the voxel size divided by the delay in seconds. Voxels
+/// without a finite, positive delay are skipped. Only voxel types with at
+/// least one velocity are returned, in the order of [`VoxelType`].
+#[must_use]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn calculate_velocity_statistics(
+    average_delays: &AverageDelays,
+    voxel_numbers: &VoxelNumbers,
+    ground_truth: &VoxelTypes,
+    voxel_size_mm: f32,
+    sample_rate_hz: f32,
+) -> Vec<VelocityStatistics> {
+    debug!("Calculating propagation velocity per voxel type");
+    let mut velocities: Vec<(VoxelType, Vec<f32>)> = VoxelType::iter()
+        .map(|voxel_type| (voxel_type, Vec::new()))
+        .collect();
+    for (number, voxel_type) in voxel_numbers.iter().zip(ground_truth.iter()) {
+        let Some(number) = number else {
+            continue;
+        };
+        let Some(Some(delay_samples)) = average_delays.get(number / 3) else {
+            continue;
+        };
+        let delay_s = delay_samples / sample_rate_hz;
+        if delay_s > 0.0 && delay_s.is_finite() {
+            if let Some((_, type_velocities)) =
+                velocities.iter_mut().find(|(other, _)| other == voxel_type)
+            {
+                type_velocities.push(voxel_size_mm / 1000.0 / delay_s);
+            }
+        }
+    }
+    velocities
+        .into_iter()
+        .filter_map(|(voxel_type, velocities)| {
+            VelocityStatistics::from_velocities(voxel_type, velocities)
+        })
+        .collect()
+}
+
+/// Returns the quantile of the sorted, non-empty values.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace", skip(sorted))]
+fn quantile(sorted: &[f32], quantile: f32) -> f32 {
+    let position = quantile * (sorted.len() - 1) as f32;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f32;
+    (sorted[upper] - sorted[lower]).mul_add(fraction, sorted[lower])
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn statistics_of_known_values() -> anyhow::Result<()> {
+        let statistics = VelocityStatistics::from_velocities(
+            VoxelType::Ventricle,
+            vec![4.0, 1.0, 3.0, 2.0, 5.0],
+        )
+        .ok_or_else(|| anyhow::anyhow!("Expected statistics for non-empty velocities"))?;
+
+        assert_eq!(statistics.count, 5);
+        assert_relative_eq!(statistics.mean_m_per_s, 3.0);
+        assert_relative_eq!(statistics.std_m_per_s, 2.0f32.sqrt());
+        assert_relative_eq!(statistics.min_m_per_s, 1.0);
+        assert_relative_eq!(statistics.lower_quartile_m_per_s, 2.0);
+        assert_relative_eq!(statistics.median_m_per_s, 3.0);
+        assert_relative_eq!(statistics.upper_quartile_m_per_s, 4.0);
+        assert_relative_eq!(statistics.max_m_per_s, 5.0);
+        Ok(())
+    }
+
+    #[test]
+    fn no_velocities_yield_no_statistics() {
+        assert!(VelocityStatistics::from_velocities(VoxelType::Atrium, Vec::new()).is_none());
+    }
+}
@@ -1,7 +1,10 @@
 use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
+pub mod bounds;
+pub mod confidence;
 pub mod derivation;
+pub mod loss;
 pub mod update;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default, Copy)]
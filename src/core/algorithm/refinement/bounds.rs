@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use approx::AbsDiffEq;
+use ndarray::{Array2, Zip};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{
+    config::algorithm::PhysiologicalBounds,
+    model::functional::allpass::{
+        delay_index_to_offset, from_coef_to_samples, from_samples_to_coef, from_samples_to_usize,
+        APParameters,
+    },
+};
+
+/// Hard bounds on the all-pass parameters, enforced by projection after
+/// every update.
+///
+/// The delay bounds are in samples per connection and follow from the
+/// velocity bounds and the distance between the connected voxels. Entries
+/// of unconnected neighbors are ignored.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ParameterBounds {
+    pub min_delay_samples: Array2<f32>,
+    pub max_delay_samples: Array2<f32>,
+    pub max_gain: Option<f32>,
+}
+
+impl ParameterBounds {
+    /// Creates the bounds for the given parameters from the config.
+    ///
+    /// Returns `None` if no bound is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the velocity bounds are contradicting or the
+    /// parameters do not form a cubic neighborhood.
+    #[tracing::instrument(level = "debug", skip(ap_params))]
+    pub fn from_config(
+        config: &PhysiologicalBounds,
+        ap_params: &APParameters,
+        voxel_size_mm: f32,
+        sample_rate_hz: f32,
+    ) -> Result<Option<Self>> {
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+        debug!("Creating physiological parameter bounds");
+        let min_velocity = config.min_velocity_m_per_s;
+        let max_velocity = config.max_velocity_m_per_s;
+        anyhow::ensure!(
+            min_velocity >= 0.0 && max_velocity >= 0.0 && config.max_gain >= 0.0,
+            "Physiological bounds must not be negative"
+        );
+        anyhow::ensure!(
+            max_velocity.abs_diff_eq(&0.0, f32::EPSILON) || min_velocity <= max_velocity,
+            "The minimum velocity {min_velocity} m/s exceeds the maximum velocity {max_velocity} m/s"
+        );
+
+        let neighborhood_radius = ap_params.neighborhood_radius()?;
+        let mut min_delay_samples = Array2::zeros(ap_params.delays.raw_dim());
+        let mut max_delay_samples = Array2::from_elem(ap_params.delays.raw_dim(), f32::INFINITY);
+        for offset in 0..ap_params.delays.shape()[1] {
+            let x_y_z_offset = delay_index_to_offset(offset, neighborhood_radius)
+                .context("Invalid delay offset index")?;
+            #[allow(clippy::cast_precision_loss)]
+            let distance_m = voxel_size_mm / 1000.0
+                * (x_y_z_offset.map(|value| value.pow(2)).iter().sum::<i32>() as f32).sqrt();
+            let samples_at = |velocity: f32| distance_m / velocity * sample_rate_hz;
+            if max_velocity > 0.0 {
+                min_delay_samples
+                    .column_mut(offset)
+                    .fill(samples_at(max_velocity));
+            }
+            if min_velocity > 0.0 {
+                max_delay_samples
+                    .column_mut(offset)
+                    .fill(samples_at(min_velocity));
+            }
+        }
+        Ok(Some(Self {
+            min_delay_samples,
+            max_delay_samples,
+            max_gain: (config.max_gain > 0.0).then_some(config.max_gain),
+        }))
+    }
+
+    /// Clamps the delays of all connections into the bounds and returns
+    /// the number of delays that had to be changed.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn project_delays(&self, ap_params: &mut APParameters) -> usize {
+        debug!("Projecting delays into physiological bounds");
+        let mut violations = 0;
+        let output_state_indices = &ap_params.output_state_indices;
+        Zip::indexed(&mut *ap_params.delays)
+            .and(&mut *ap_params.coefs)
+            .and(&self.min_delay_samples)
+            .and(&self.max_delay_samples)
+            .for_each(|(voxel_index, offset), delay, coef, &min, &max| {
+                if output_state_indices[(voxel_index * 3, offset * 3)].is_none() {
+                    return;
+                }
+                #[allow(clippy::cast_precision_loss)]
+                let samples = *delay as f32 + from_coef_to_samples(*coef);
+                let projected = samples.clamp(min, max);
+                if projected.abs_diff_ne(&samples, f32::EPSILON) {
+                    *delay = from_samples_to_usize(projected);
+                    *coef = from_samples_to_coef(projected);
+                    violations += 1;
+                }
+            });
+        violations
+    }
+
+    /// Clamps the magnitudes of the gains to the cap and returns the number
+    /// of gains that had to be changed.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn project_gains(&self, ap_params: &mut APParameters) -> usize {
+        let Some(max_gain) = self.max_gain else {
+            return 0;
+        };
+        debug!("Projecting gains into physiological bounds");
+        let mut violations = 0;
+        ap_params.gains.iter_mut().for_each(|gain| {
+            if gain.abs() > max_gain {
+                *gain = gain.clamp(-max_gain, max_gain);
+                violations += 1;
+            }
+        });
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::Dim;
+
+    use super::*;
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn out_of_bounds_parameters_are_projected() -> Result<()> {
+        let mut ap_params = APParameters::empty(6, Dim([2, 1, 1]), 1);
+        for input in 0..3 {
+            for output in 0..3 {
+                ap_params.output_state_indices[(input, output)] = Some(3 + output);
+            }
+        }
+        // the neighbor at offset zero is a diagonal one, sqrt(3) voxels away
+        ap_params.delays.fill(20);
+        ap_params.coefs.fill(from_samples_to_coef(0.5));
+        ap_params.gains.fill(3.0);
+        let config = PhysiologicalBounds {
+            min_velocity_m_per_s: 0.5,
+            max_velocity_m_per_s: 0.0,
+            max_gain: 2.0,
+        };
+
+        let bounds = ParameterBounds::from_config(&config, &ap_params, 1.0, 2000.0)?
+            .context("Bounds should be enabled")?;
+        let delay_violations = bounds.project_delays(&mut ap_params);
+        let gain_violations = bounds.project_gains(&mut ap_params);
+
+        let max_delay = 3.0_f32.sqrt() / 1000.0 / 0.5 * 2000.0;
+        assert_eq!(delay_violations, 1);
+        assert_relative_eq!(
+            ap_params.delays[(0, 0)] as f32 + from_coef_to_samples(ap_params.coefs[(0, 0)]),
+            max_delay,
+            epsilon = 1e-3
+        );
+        assert_eq!(ap_params.delays[(1, 0)], 20);
+        assert_eq!(gain_violations, ap_params.gains.len());
+        assert_relative_eq!(ap_params.gains[(0, 0)], 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_bounds_are_none() -> Result<()> {
+        let ap_params = APParameters::empty(3, Dim([1, 1, 1]), 1);
+        let bounds =
+            ParameterBounds::from_config(&PhysiologicalBounds::default(), &ap_params, 1.0, 2000.0)?;
+
+        assert!(bounds.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use ndarray::{s, Array1, Array2, Axis, Zip};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{
+    algorithm::estimation::{
+        calculate_residuals, prediction::calculate_system_prediction, Estimations,
+    },
+    data::Data,
+    model::{
+        functional::{
+            allpass::{
+                shapes::{ActivationTimeMs, Coefs, Gains},
+                APParameters,
+            },
+            FunctionalDescription,
+        },
+        spatial::voxels::VoxelNumbers,
+    },
+};
+
+/// Uncertainty of the all-pass parameters after convergence.
+///
+/// The standard deviations follow from the diagonal of the Gauss-Newton
+/// approximation `J^T J` of the Hessian of the squared residuals, scaled by
+/// the residual variance. The Jacobian uses the same simplified derivatives
+/// as the refinement, i.e. it ignores the recursion of the states.
+///
+/// Unconnected parameters are `NaN`, parameters the measurements do not
+/// depend on are infinite.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ParameterConfidence {
+    pub gains_std: Gains,
+    pub delays_std_samples: Coefs,
+    pub residual_variance: f32,
+}
+
+impl ParameterConfidence {
+    /// Returns the mean standard deviation of the gains of every voxel on
+    /// the voxel grid.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn gains_std_map(&self, numbers: &VoxelNumbers) -> ActivationTimeMs {
+        debug!("Mapping gain standard deviations onto the voxels");
+        voxel_mean_map(&self.gains_std, 3, numbers)
+    }
+
+    /// Returns the mean standard deviation of the delays of every voxel on
+    /// the voxel grid in samples.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn delays_std_map(&self, numbers: &VoxelNumbers) -> ActivationTimeMs {
+        debug!("Mapping delay standard deviations onto the voxels");
+        voxel_mean_map(&self.delays_std_samples, 1, numbers)
+    }
+}
+
+/// Estimates the confidence of the all-pass parameters of the given
+/// functional description.
+///
+/// Runs one pass over all beats with fixed parameters on a copy of the
+/// estimations and accumulates the diagonal of `J^T J` per step.
+///
+/// # Errors
+///
+/// Returns an error if the model parameters are not properly initialized.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "info", skip_all)]
+pub fn calculate_parameter_confidence(
+    estimations: &Estimations,
+    functional_description: &FunctionalDescription,
+    data: &Data,
+) -> Result<ParameterConfidence> {
+    debug!("Calculating parameter confidence");
+    let ap_params = &functional_description.ap_params;
+    let neighborhood_radius = ap_params.neighborhood_radius()?;
+    let number_of_states = ap_params.gains.shape()[0];
+    let mut estimations = estimations.clone();
+    let mut gains_jtj = Gains::empty(number_of_states, neighborhood_radius);
+    let mut coefs_jtj = Coefs::empty(number_of_states, neighborhood_radius);
+    let mut squared_residuals = 0.0;
+    let mut number_of_residuals = 0;
+
+    let number_of_steps = estimations.system_states.num_steps();
+    let used_channels = estimations
+        .channel_mask
+        .iter()
+        .filter(|mask| **mask > 0.0)
+        .count();
+    for beat in 0..data.simulation.measurements.num_beats() {
+        estimations.reset();
+        // sensitivity of the squared residuals to each state
+        let state_weights = (&*functional_description.measurement_matrix.at_beat(beat)
+            * &estimations.channel_mask.view().insert_axis(Axis(1)))
+            .mapv(|value| value.powi(2))
+            .sum_axis(Axis(0));
+        for step in 0..number_of_steps {
+            calculate_system_prediction(&mut estimations, functional_description, beat, step)?;
+            calculate_residuals(&mut estimations, data, beat, step);
+            squared_residuals += estimations.residuals.mapv(|r| r.powi(2)).sum();
+            number_of_residuals += used_channels;
+            accumulate_jtj(
+                &mut gains_jtj,
+                &mut coefs_jtj,
+                &estimations,
+                ap_params,
+                &state_weights,
+                step,
+            )?;
+        }
+    }
+
+    let residual_variance = squared_residuals / number_of_residuals.max(1) as f32;
+    let mut gains_std = Gains::empty(number_of_states, neighborhood_radius);
+    Zip::from(&mut *gains_std)
+        .and(&*gains_jtj)
+        .and(&*ap_params.output_state_indices)
+        .for_each(|deviation, &jtj, output_state| {
+            *deviation =
+                output_state.map_or(f32::NAN, |_| standard_deviation(residual_variance, jtj));
+        });
+    let mut delays_std_samples = Coefs::empty(number_of_states, neighborhood_radius);
+    Zip::indexed(&mut *delays_std_samples)
+        .and(&*coefs_jtj)
+        .and(&*ap_params.coefs)
+        .for_each(|(voxel_index, neighbor_index), deviation, &jtj, &coef| {
+            *deviation = if ap_params.output_state_indices[(voxel_index * 3, neighbor_index * 3)]
+                .is_some()
+            {
+                // the fractional delay changes by -2 / (1 + coef)^2 per coefficient
+                standard_deviation(residual_variance, jtj) * 2.0 / (1.0 + coef).powi(2)
+            } else {
+                f32::NAN
+            };
+        });
+    Ok(ParameterConfidence {
+        gains_std,
+        delays_std_samples,
+        residual_variance,
+    })
+}
+
+/// Adds the squared sensitivities of the weighted residuals to the gains and
+/// coefficients of the current step.
+///
+/// # Errors
+///
+/// Returns an error if the output state indices and delays do not match.
+#[inline]
+#[tracing::instrument(level = "trace", skip_all)]
+fn accumulate_jtj(
+    gains_jtj: &mut Gains,
+    coefs_jtj: &mut Coefs,
+    estimations: &Estimations,
+    ap_params: &APParameters,
+    state_weights: &Array1<f32>,
+    step: usize,
+) -> Result<()> {
+    Zip::indexed(&mut **gains_jtj)
+        .and(&*estimations.ap_outputs_now)
+        .for_each(|(state_index, _), jtj, &ap_output| {
+            *jtj += ap_output.powi(2) * state_weights[state_index];
+        });
+    for ((voxel_index, neighbor_index), jtj) in coefs_jtj.indexed_iter_mut() {
+        let delay = ap_params.delays[(voxel_index, neighbor_index)];
+        if step < delay {
+            continue;
+        }
+        for state_index in voxel_index * 3..voxel_index * 3 + 3 {
+            let mut sensitivity = 0.0;
+            for offset_index in neighbor_index * 3..neighbor_index * 3 + 3 {
+                let Some(output_state_index) =
+                    ap_params.output_state_indices[(state_index, offset_index)]
+                else {
+                    continue;
+                };
+                let input = *estimations
+                    .system_states
+                    .get((step - delay, output_state_index))
+                    .context("Output state index out of bounds")?;
+                sensitivity += ap_params.gains[(state_index, offset_index)]
+                    * (input - estimations.ap_outputs_last[(state_index, offset_index)]);
+            }
+            *jtj += sensitivity.powi(2) * state_weights[state_index];
+        }
+    }
+    Ok(())
+}
+
+/// Returns the standard deviation of a parameter given the residual
+/// variance and its diagonal entry of `J^T J`.
+#[inline]
+#[tracing::instrument(level = "trace")]
+fn standard_deviation(residual_variance: f32, jtj: f32) -> f32 {
+    if jtj > 0.0 {
+        (residual_variance / jtj).sqrt()
+    } else {
+        f32::INFINITY
+    }
+}
+
+/// Averages the finite values in the rows of every voxel and places them
+/// onto the voxel grid. `rows_per_voxel` is three for per-state arrays and
+/// one for per-voxel arrays.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn voxel_mean_map(
+    values: &Array2<f32>,
+    rows_per_voxel: usize,
+    numbers: &VoxelNumbers,
+) -> ActivationTimeMs {
+    let mut map = ActivationTimeMs::empty(numbers.raw_dim());
+    map.iter_mut()
+        .zip(numbers.iter())
+        .for_each(|(value, number)| {
+            let Some(number) = number else {
+                return;
+            };
+            let first_row = number / 3 * rows_per_voxel;
+            let finite: Vec<f32> = values
+                .slice(s![first_row..first_row + rows_per_voxel, ..])
+                .iter()
+                .copied()
+                .filter(|value| value.is_finite())
+                .collect();
+            if !finite.is_empty() {
+                *value = Some(finite.iter().sum::<f32>() / finite.len() as f32);
+            }
+        });
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn standard_deviation_of_unobserved_parameter_is_infinite() {
+        assert_relative_eq!(standard_deviation(4.0, 1.0), 2.0);
+        assert!(standard_deviation(4.0, 0.0).is_infinite());
+    }
+
+    #[test]
+    fn voxel_mean_map_skips_non_finite_values() {
+        let mut numbers = VoxelNumbers::empty([2, 1, 1]);
+        numbers[(0, 0, 0)] = Some(0);
+        numbers[(1, 0, 0)] = Some(3);
+        let values = arr2(&[[1.0, f32::NAN], [3.0, f32::INFINITY]]);
+
+        let map = voxel_mean_map(&values, 1, &numbers);
+
+        assert_eq!(map[(0, 0, 0)], Some(1.0));
+        assert_eq!(map[(1, 0, 0)], Some(3.0));
+    }
+}
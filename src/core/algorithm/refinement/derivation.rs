@@ -1,15 +1,19 @@
 use std::ops::{Deref, DerefMut, Sub};
 
 use anyhow::{Context, Result};
-use approx::AbsDiffEq;
-use ndarray::Array1;
+use ndarray::{parallel::prelude::*, Array1, Axis, Zip};
 use ocl::Buffer;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use super::Optimizer;
+use super::{
+    bounds::ParameterBounds,
+    loss::{self, LossContext, LossTermKind},
+    update::UpdateMask,
+    Optimizer,
+};
 use crate::core::{
-    algorithm::estimation::Estimations,
+    algorithm::{estimation::Estimations, gpu::buffer_bytes},
     config::algorithm::{APDerivative, Algorithm},
     data::shapes::{Residuals, SystemStatesAtStep},
     model::functional::{
@@ -41,6 +45,8 @@ pub struct Derivatives {
     pub coefs_first_moment: Option<Coefs>,
     /// Second moment of the coeficients derivatives
     pub coefs_second_moment: Option<Coefs>,
+    /// Derivatives of the refractory times of the voxels
+    pub refractory: Array1<f32>,
     pub step: usize,
     /// IIR component of the coeficients derivatives
     /// only used for internal computation
@@ -55,6 +61,14 @@ pub struct Derivatives {
     /// Stored internally to avoid redundant computation
     pub maximum_regularization: MaximumRegularization,
     pub maximum_regularization_sum: f32,
+    /// Per-voxel factors of the derivatives, `None` if all voxels are
+    /// optimized
+    #[serde(default)]
+    pub update_mask: Option<UpdateMask>,
+    /// Physiological bounds the parameters are projected into after every
+    /// update, `None` if unbounded
+    #[serde(default)]
+    pub parameter_bounds: Option<ParameterBounds>,
 }
 
 pub struct DerivativesGPU {
@@ -65,44 +79,66 @@ pub struct DerivativesGPU {
     pub mapped_residuals: Buffer<f32>,
     pub maximum_regularization: Buffer<f32>,
     pub maximum_regularization_sum: Buffer<f32>,
+    pub gains_mask: Buffer<f32>,
+    pub coefs_mask: Buffer<f32>,
+}
+
+impl DerivativesGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        buffer_bytes(&self.gains)
+            + buffer_bytes(&self.coefs)
+            + buffer_bytes(&self.coefs_iir)
+            + buffer_bytes(&self.coefs_fir)
+            + buffer_bytes(&self.mapped_residuals)
+            + buffer_bytes(&self.maximum_regularization)
+            + buffer_bytes(&self.maximum_regularization_sum)
+            + buffer_bytes(&self.gains_mask)
+            + buffer_bytes(&self.coefs_mask)
+    }
 }
 
 impl Derivatives {
     /// Creates a new Derivatives struct with empty arrays initialized to
-    /// the given number of states.
+    /// the given number of states and neighborhood radius.
     #[must_use]
     #[tracing::instrument(level = "debug")]
-    pub fn new(number_of_states: usize, optimizer: Optimizer) -> Self {
+    pub fn new(number_of_states: usize, optimizer: Optimizer, neighborhood_radius: usize) -> Self {
         debug!("Creating empty derivatives");
         let gains_first_moment = match optimizer {
             Optimizer::Sgd => None,
-            Optimizer::Adam => Some(Gains::empty(number_of_states)),
+            Optimizer::Adam => Some(Gains::empty(number_of_states, neighborhood_radius)),
         };
         let gains_second_moment = match optimizer {
             Optimizer::Sgd => None,
-            Optimizer::Adam => Some(Gains::empty(number_of_states)),
+            Optimizer::Adam => Some(Gains::empty(number_of_states, neighborhood_radius)),
         };
         let coefs_first_moment = match optimizer {
             Optimizer::Sgd => None,
-            Optimizer::Adam => Some(Coefs::empty(number_of_states)),
+            Optimizer::Adam => Some(Coefs::empty(number_of_states, neighborhood_radius)),
         };
         let coefs_second_moment = match optimizer {
             Optimizer::Sgd => None,
-            Optimizer::Adam => Some(Coefs::empty(number_of_states)),
+            Optimizer::Adam => Some(Coefs::empty(number_of_states, neighborhood_radius)),
         };
         Self {
-            gains: Gains::empty(number_of_states),
+            gains: Gains::empty(number_of_states, neighborhood_radius),
             gains_first_moment,
             gains_second_moment,
-            coefs: Coefs::empty(number_of_states),
+            coefs: Coefs::empty(number_of_states, neighborhood_radius),
             coefs_first_moment,
             coefs_second_moment,
+            refractory: Array1::zeros(number_of_states / 3),
             step: 1,
-            coefs_iir: Gains::empty(number_of_states),
-            coefs_fir: Gains::empty(number_of_states),
+            coefs_iir: Gains::empty(number_of_states, neighborhood_radius),
+            coefs_fir: Gains::empty(number_of_states, neighborhood_radius),
             mapped_residuals: MappedResiduals::new(number_of_states),
             maximum_regularization: MaximumRegularization::new(number_of_states),
             maximum_regularization_sum: 0.0,
+            update_mask: None,
+            parameter_bounds: None,
         }
     }
 
@@ -117,12 +153,29 @@ impl Derivatives {
         self.coefs.fill(0.0);
         self.coefs_iir.fill(0.0);
         self.coefs_fir.fill(0.0);
+        self.refractory.fill(0.0);
         self.maximum_regularization.fill(0.0);
         self.maximum_regularization_sum = 0.0;
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
     pub(crate) fn to_gpu(&self, queue: &ocl::Queue) -> Result<DerivativesGPU> {
+        let update_mask = self
+            .update_mask
+            .clone()
+            .unwrap_or_else(|| UpdateMask::full(self.coefs.shape()[0]));
+        let mask_buffer = |values: &Array1<f32>| {
+            Buffer::builder()
+                .queue(queue.clone())
+                .len(values.len())
+                .copy_host_slice(
+                    values
+                        .as_slice()
+                        .context("Failed to get update mask slice for GPU copy")?,
+                )
+                .build()
+                .context("Failed to create update mask GPU buffer")
+        };
         Ok(DerivativesGPU {
             gains: self.gains.to_gpu(queue)?,
             coefs: self.coefs.to_gpu(queue)?,
@@ -136,6 +189,8 @@ impl Derivatives {
                 .copy_host_slice(&[self.maximum_regularization_sum])
                 .build()
                 .context("Failed to create maximum_regularization_sum buffer")?,
+            gains_mask: mask_buffer(&update_mask.gains)?,
+            coefs_mask: mask_buffer(&update_mask.coefs)?,
         })
     }
 
@@ -160,7 +215,8 @@ impl Derivatives {
     }
 }
 
-/// Calculates the derivatives for the given time index.
+/// Calculates the derivatives of all active loss terms for the given time
+/// index.
 ///
 /// CAUTION: adds to old values. use "reset" after using the
 /// derivatives to update the parameters.
@@ -169,7 +225,7 @@ impl Derivatives {
 ///
 /// Returns an error if algorithm parameters are not properly initialized.
 #[inline]
-#[tracing::instrument(level = "debug", skip_all)]
+#[tracing::instrument(level = "info", skip_all, fields(profile = "derivation"))]
 pub fn calculate_step_derivatives(
     derivates: &mut Derivatives,
     estimations: &Estimations,
@@ -177,14 +233,29 @@ pub fn calculate_step_derivatives(
     config: &Algorithm,
     step: usize,
     beat: usize,
-    number_of_sensors: usize,
 ) -> Result<()> {
     debug!("Calculating derivatives");
-    calculate_mapped_residuals(
-        &mut derivates.mapped_residuals,
-        &estimations.residuals,
-        &functional_description.measurement_matrix.at_beat(beat),
-    );
+    // the mapped residuals and the maximum regularization are shared by the
+    // loss terms, the regularization sum is tracked in the metrics even if
+    // its term is disabled
+    let measurement_matrix = &functional_description.measurement_matrix;
+    match functional_description
+        .measurement_interpolation
+        .at_step(step)
+    {
+        Some(keyframes) => calculate_mapped_residuals_interpolated(
+            &mut derivates.mapped_residuals,
+            &estimations.residuals,
+            &measurement_matrix.at_beat(keyframes.lower),
+            &measurement_matrix.at_beat(keyframes.upper),
+            keyframes.weight,
+        ),
+        None => calculate_mapped_residuals(
+            &mut derivates.mapped_residuals,
+            &estimations.residuals,
+            &measurement_matrix.at_beat(beat),
+        ),
+    }
 
     calculate_maximum_regularization(
         &mut derivates.maximum_regularization,
@@ -193,42 +264,19 @@ pub fn calculate_step_derivatives(
         config.maximum_regularization_threshold,
     );
 
-    if !config.freeze_gains {
-        calculate_derivatives_gains(
-            &mut derivates.gains,
-            &estimations.ap_outputs_now,
-            &derivates.maximum_regularization,
-            &derivates.mapped_residuals,
+    loss::add_step_derivatives(
+        derivates,
+        &LossContext {
+            estimations,
+            functional_description,
             config,
-            number_of_sensors,
-        );
-    }
-    if !config.freeze_delays {
-        match config.ap_derivative {
-            APDerivative::Simple => {
-                calculate_derivatives_coefs_simple(
-                    derivates,
-                    estimations,
-                    functional_description,
-                    step,
-                    config,
-                )?;
-            }
-            APDerivative::Textbook => {
-                calculate_derivatives_coefs_textbook(
-                    derivates,
-                    estimations,
-                    functional_description,
-                    step,
-                    config,
-                )?;
-            }
-        }
-    }
-    Ok(())
+        },
+        step,
+    )
 }
 
-/// Calculates batch-wise derivatives.
+/// Calculates batch-wise derivatives and returns the weighted values of the
+/// loss terms.
 ///
 /// CAUTION: adds to old values. use "reset" after using the
 /// derivatives to update the parameters.
@@ -237,219 +285,186 @@ pub fn calculate_step_derivatives(
 ///
 /// Returns an error if algorithm parameters are not properly initialized.
 #[inline]
-#[tracing::instrument(level = "debug", skip_all)]
+#[tracing::instrument(level = "info", skip_all, fields(profile = "derivation"))]
 pub fn calculate_batch_derivatives(
     derivatives: &mut Derivatives,
     estimations: &Estimations,
     functional_description: &FunctionalDescription,
     config: &Algorithm,
-) -> Result<()> {
+) -> Result<Vec<(LossTermKind, f32)>> {
     debug!("Calculating batch derivatives");
-    if !config.freeze_delays
-        && config
-            .smoothness_regularization_strength
-            .abs_diff_ne(&0.0, f32::EPSILON)
-    {
-        calculate_smoothness_derivatives(derivatives, estimations, functional_description, config)?;
-    }
-    Ok(())
+    let context = LossContext {
+        estimations,
+        functional_description,
+        config,
+    };
+    loss::add_batch_derivatives(derivatives, &context)?;
+    Ok(loss::weighted_values(&context))
 }
 
-#[allow(clippy::cast_precision_loss)]
-#[tracing::instrument(level = "trace")]
-pub fn calculate_smoothness_derivatives(
-    derivatives: &mut Derivatives,
-    estimations: &Estimations,
-    functional_description: &FunctionalDescription,
-    config: &Algorithm,
-) -> Result<()> {
-    debug!("Calculating smoothness derivatives");
-    for voxel_index in 0..derivatives.coefs.shape()[0] {
-        for output_offset in 0..derivatives.coefs.shape()[1] {
-            let average_delay_in_voxel = unsafe { *estimations.average_delays.uget(voxel_index) };
-            let Some(average_delay_in_voxel) = average_delay_in_voxel else {
-                continue;
-            };
-            let mut average_delay_in_neighborhood = average_delay_in_voxel;
-            let mut divisor = 1.0;
-
-            for voxel_offset in 0..functional_description.ap_params.delays.shape()[1] {
-                let neighbor_index = unsafe {
-                    functional_description
-                        .ap_params
-                        .output_state_indices
-                        .uget((voxel_index * 3, voxel_offset * 3))
-                };
-                let Some(neighbor_index) = neighbor_index else {
-                    continue;
-                };
-                let neighbor_index = neighbor_index / 3;
-                let delay = unsafe { *estimations.average_delays.uget(neighbor_index) };
-                if let Some(delay) = delay {
-                    average_delay_in_neighborhood += delay;
-                    divisor += 1.0;
-                }
-            }
-            average_delay_in_neighborhood /= divisor;
-
-            let difference = average_delay_in_neighborhood - average_delay_in_voxel;
-
-            let derivative = unsafe { derivatives.coefs.uget_mut((voxel_index, output_offset)) };
-            *derivative += config.smoothness_regularization_strength * difference;
-        }
-    }
-    Ok(())
-}
-/// Calculates the derivatives for the allpass filter gains.
+/// Calculates the derivatives for the allpass filter gains of a loss term
+/// given by its derivative with respect to every system state, like the
+/// mapped residuals or the maximum regularization.
 #[inline]
-#[allow(clippy::cast_precision_loss)]
 #[tracing::instrument(level = "trace")]
 pub fn calculate_derivatives_gains(
     derivatives_gains: &mut Gains,
     ap_outputs: &Gains,
-    maximum_regularization: &MaximumRegularization,
-    mapped_residuals: &MappedResiduals,
-    config: &Algorithm,
-    number_of_sensors: usize,
+    state_derivatives: &Array1<f32>,
+    scaling: f32,
 ) {
-    let mse_scaling = 1.0 / number_of_sensors as f32 * config.mse_strength;
-    let regularization_scaling = config.maximum_regularization_strength;
-
-    for gain_index in 0..derivatives_gains.shape()[0] {
-        for offset_index in 0..derivatives_gains.shape()[1] {
+    Zip::indexed(&mut **derivatives_gains).par_for_each(
+        |(gain_index, offset_index), derivative| {
             let ap_output = unsafe { ap_outputs.uget((gain_index, offset_index)) };
-            let max_reg = unsafe { maximum_regularization.uget(gain_index) };
-            let residual = unsafe { mapped_residuals.uget(gain_index) };
-            let derivative = unsafe { derivatives_gains.uget_mut((gain_index, offset_index)) };
+            let state_derivative = unsafe { state_derivatives.uget(gain_index) };
 
-            *derivative +=
-                ap_output * residual.mul_add(mse_scaling, max_reg * regularization_scaling);
+            *derivative += ap_output * state_derivative * scaling;
+        },
+    );
+}
+/// Calculates the derivatives of the refractory times.
+///
+/// A re-entrant connection starts passing its input at the end of the
+/// refractory window. Delaying the window by one sample removes the
+/// contribution of the connection at that step, which gives the derivative.
+#[inline]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn calculate_derivatives_refractory(
+    derivatives_refractory: &mut Array1<f32>,
+    estimations: &Estimations,
+    functional_description: &FunctionalDescription,
+    mapped_residuals: &MappedResiduals,
+    step: usize,
+    mse_scaling: f32,
+) {
+    let ap_params = &functional_description.ap_params;
+    let refractory = &ap_params.refractory;
+    for ((voxel_index, delay_index), reentrant) in refractory.reentrant.indexed_iter() {
+        if !*reentrant || refractory.gate(voxel_index, delay_index) != step {
+            continue;
+        }
+        for state_index in voxel_index * 3..voxel_index * 3 + 3 {
+            let residual = mapped_residuals[state_index] * mse_scaling;
+            for offset_index in delay_index * 3..delay_index * 3 + 3 {
+                let gain = ap_params.gains[(state_index, offset_index)];
+                let ap_output = estimations.ap_outputs_now[(state_index, offset_index)];
+                derivatives_refractory[voxel_index] -= residual * gain * ap_output;
+            }
         }
     }
 }
-/// Calculates the derivatives for the allpass filter coefficients using a simplified form for the AP derivative.
+
+/// Calculates the mean squared error derivatives for the allpass filter coefficients using a simplified form for the AP derivative.
 ///
 /// # Errors
 ///
 /// Returns an error if algorithm parameters are not properly initialized.
 #[inline]
-#[allow(clippy::cast_precision_loss)]
 #[tracing::instrument(level = "trace")]
 pub fn calculate_derivatives_coefs_simple(
     derivatives: &mut Derivatives,
     estimations: &Estimations,
     functional_description: &FunctionalDescription,
     step: usize,
-    config: &Algorithm,
+    mse_scaling: f32,
 ) -> Result<()> {
-    let mse_scaling = 1.0 / estimations.measurements.num_sensors() as f32 * config.mse_strength;
-    for state_index in 0..derivatives.coefs_iir.shape()[0] {
-        for offset_index in 0..derivatives.coefs_iir.shape()[1] {
-            let coef_index = (state_index / 3, offset_index / 3);
-            let delay = unsafe { *functional_description.ap_params.delays.uget(coef_index) } as f32
-                + from_coef_to_samples(unsafe {
-                    *functional_description.ap_params.coefs.uget(coef_index)
-                });
-            let delay_delta = (unsafe {
-                *functional_description
-                    .ap_params
-                    .initial_delays
-                    .uget(coef_index)
-            } - delay)
-                .powi(5);
-            let delay = unsafe { functional_description.ap_params.delays.uget(coef_index) };
-            let output_state = unsafe {
-                functional_description
-                    .ap_params
-                    .output_state_indices
-                    .uget((state_index, offset_index))
-            };
-            if output_state.is_none() {
-                continue;
-            }
-            if step >= *delay {
-                let ap_output_last = unsafe {
-                    estimations
-                        .ap_outputs_last
-                        .uget((state_index, offset_index))
-                };
-                let output_state = output_state.context(
-                    "Output state index not initialized - algorithm parameter corruption",
-                )?;
-                let state_val =
-                    unsafe { estimations.system_states.uget((step - delay, output_state)) };
-                let ap_gain = unsafe {
-                    functional_description
-                        .ap_params
-                        .gains
-                        .uget((state_index, offset_index))
-                };
-                let mapped_residual = unsafe { derivatives.mapped_residuals.uget(state_index) };
-                let coef_derivative = unsafe { derivatives.coefs.uget_mut(coef_index) };
-                *coef_derivative += ((state_val - ap_output_last) * ap_gain * mapped_residual)
-                    .mul_add(
-                        mse_scaling,
-                        config.difference_regularization_strength * delay_delta,
-                    );
+    let ap_params = &functional_description.ap_params;
+    let mapped_residuals = &derivatives.mapped_residuals;
+    let number_of_offsets = derivatives.coefs_iir.shape()[1];
+
+    // every voxel accumulates the derivatives of its three states into its
+    // own row of the coefficient derivatives, so the rows are independent
+    derivatives
+        .coefs
+        .axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .enumerate()
+        .try_for_each(|(voxel_index, mut coef_derivatives)| -> Result<()> {
+            for state_index in voxel_index * 3..voxel_index * 3 + 3 {
+                for offset_index in 0..number_of_offsets {
+                    let coef_index = (voxel_index, offset_index / 3);
+                    let delay = unsafe { ap_params.delays.uget(coef_index) };
+                    let output_state = unsafe {
+                        ap_params
+                            .output_state_indices
+                            .uget((state_index, offset_index))
+                    };
+                    if output_state.is_none() {
+                        continue;
+                    }
+                    if step >= *delay {
+                        let ap_output_last = unsafe {
+                            estimations
+                                .ap_outputs_last
+                                .uget((state_index, offset_index))
+                        };
+                        let output_state = output_state.context(
+                            "Output state index not initialized - algorithm parameter corruption",
+                        )?;
+                        let state_val =
+                            unsafe { estimations.system_states.uget((step - delay, output_state)) };
+                        let ap_gain = unsafe { ap_params.gains.uget((state_index, offset_index)) };
+                        let mapped_residual = unsafe { mapped_residuals.uget(state_index) };
+                        let coef_derivative =
+                            unsafe { coef_derivatives.uget_mut(offset_index / 3) };
+                        *coef_derivative +=
+                            (state_val - ap_output_last) * ap_gain * mapped_residual * mse_scaling;
+                    }
+                }
             }
-        }
-    }
-    Ok(())
+            Ok(())
+        })
 }
 
-/// Calculates the derivatives for the allpass filter coefficients using the textbook form for the AP derivative.
+/// Calculates the mean squared error derivatives for the allpass filter coefficients using the textbook form for the AP derivative.
 ///
 /// # Errors
 ///
 /// Returns an error if algorithm parameters are not properly initialized.
 #[inline]
-#[allow(clippy::cast_precision_loss)]
 #[tracing::instrument(level = "trace")]
 pub fn calculate_derivatives_coefs_textbook(
     derivatives: &mut Derivatives,
     estimations: &Estimations,
     functional_description: &FunctionalDescription,
     step: usize,
-    config: &Algorithm,
+    mse_scaling: f32,
 ) -> Result<()> {
-    let mse_scaling = 1.0 / estimations.measurements.num_sensors() as f32 * config.mse_strength;
+    let ap_params = &functional_description.ap_params;
 
     // FIR derivatives calculation
-    for state_index in 0..derivatives.coefs_fir.shape()[0] {
-        for offset_index in 0..derivatives.coefs_fir.shape()[1] {
+    Zip::indexed(&mut *derivatives.coefs_fir).par_for_each(
+        |(state_index, offset_index), derivative_fir| {
             let output_state = unsafe {
-                functional_description
-                    .ap_params
+                ap_params
                     .output_state_indices
                     .uget((state_index, offset_index))
             };
-            if output_state.is_none() {
-                continue;
-            }
+            let Some(output_state) = output_state else {
+                return;
+            };
 
             let coef_index = (state_index / 3, offset_index / 3);
-            let delay = unsafe { functional_description.ap_params.delays.uget(coef_index) };
-            let coef = unsafe { functional_description.ap_params.coefs.uget(coef_index) };
+            let delay = unsafe { ap_params.delays.uget(coef_index) };
+            let coef = unsafe { ap_params.coefs.uget(coef_index) };
 
             if step >= *delay {
-                let output_state = output_state.context(
-                    "Output state index not initialized - algorithm parameter corruption",
-                )?;
-                let state_val =
-                    unsafe { estimations.system_states.uget((step - delay, output_state)) };
-                let derivative_fir =
-                    unsafe { derivatives.coefs_fir.uget_mut((state_index, offset_index)) };
+                let state_val = unsafe {
+                    estimations
+                        .system_states
+                        .uget((step - delay, *output_state))
+                };
                 *derivative_fir = (-*coef).mul_add(*derivative_fir, *state_val);
             }
-        }
-    }
+        },
+    );
 
     // IIR derivatives calculation
-    for state_index in 0..derivatives.coefs_iir.shape()[0] {
-        for offset_index in 0..derivatives.coefs_iir.shape()[1] {
+    Zip::indexed(&mut *derivatives.coefs_iir).par_for_each(
+        |(state_index, offset_index), derivative_iir| {
             let coef_index = (state_index / 3, offset_index / 3);
-            let delay = unsafe { functional_description.ap_params.delays.uget(coef_index) };
-            let coef = unsafe { functional_description.ap_params.coefs.uget(coef_index) };
+            let delay = unsafe { ap_params.delays.uget(coef_index) };
+            let coef = unsafe { ap_params.coefs.uget(coef_index) };
 
             if step >= *delay {
                 let ap_output_last = unsafe {
@@ -457,49 +472,86 @@ pub fn calculate_derivatives_coefs_textbook(
                         .ap_outputs_last
                         .uget((state_index, offset_index))
                 };
-                let derivative_iir =
-                    unsafe { derivatives.coefs_iir.uget_mut((state_index, offset_index)) };
                 *derivative_iir = (-*coef).mul_add(*derivative_iir, *ap_output_last);
             }
-        }
-    }
-
-    // Combine results
-    for state_index in 0..derivatives.coefs_iir.shape()[0] {
-        for offset_index in 0..derivatives.coefs_iir.shape()[1] {
-            let coef_index = (state_index / 3, offset_index / 3);
-            let delay = unsafe { *functional_description.ap_params.delays.uget(coef_index) } as f32
-                + from_coef_to_samples(unsafe {
-                    *functional_description.ap_params.coefs.uget(coef_index)
-                });
-            let delay_delta = (unsafe {
-                *functional_description
-                    .ap_params
-                    .initial_delays
-                    .uget(coef_index)
-            } - delay)
-                .powi(5);
-
-            let iir = unsafe { derivatives.coefs_iir.uget((state_index, offset_index)) };
-            let fir = unsafe { derivatives.coefs_fir.uget((state_index, offset_index)) };
-            let ap_gain = unsafe {
-                functional_description
-                    .ap_params
-                    .gains
-                    .uget((state_index, offset_index))
-            };
-            let mapped_residual = unsafe { derivatives.mapped_residuals.uget(state_index) };
+        },
+    );
 
-            let coef_derivative = unsafe { derivatives.coefs.uget_mut(coef_index) };
-            *coef_derivative += ((fir - iir) * ap_gain * mapped_residual).mul_add(
-                mse_scaling,
-                config.difference_regularization_strength * delay_delta,
-            );
-        }
-    }
+    // Combine results, every voxel accumulates the derivatives of its three
+    // states into its own row of the coefficient derivatives
+    let coefs_iir = &derivatives.coefs_iir;
+    let coefs_fir = &derivatives.coefs_fir;
+    let mapped_residuals = &derivatives.mapped_residuals;
+    let number_of_offsets = coefs_iir.shape()[1];
+    derivatives
+        .coefs
+        .axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(voxel_index, mut coef_derivatives)| {
+            for state_index in voxel_index * 3..voxel_index * 3 + 3 {
+                for offset_index in 0..number_of_offsets {
+                    let iir = unsafe { coefs_iir.uget((state_index, offset_index)) };
+                    let fir = unsafe { coefs_fir.uget((state_index, offset_index)) };
+                    let ap_gain = unsafe { ap_params.gains.uget((state_index, offset_index)) };
+                    let mapped_residual = unsafe { mapped_residuals.uget(state_index) };
+
+                    let coef_derivative = unsafe { coef_derivatives.uget_mut(offset_index / 3) };
+                    *coef_derivative += (fir - iir) * ap_gain * mapped_residual * mse_scaling;
+                }
+            }
+        });
     Ok(())
 }
 
+/// Calculates the derivatives of the difference regularization, which pulls
+/// the delays of the connections towards the delays of the initial model.
+///
+/// The regularization is added once for every gain of the connection that
+/// takes part in the mean squared error derivatives of the coefficients, the
+/// connected gains once the delay has passed for the simplified form and all
+/// gains for the textbook form, like in the GPU kernels.
+#[inline]
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn calculate_derivatives_difference_regularization(
+    derivatives_coefs: &mut Coefs,
+    ap_params: &APParameters,
+    step: usize,
+    ap_derivative: &APDerivative,
+    weight: f32,
+) {
+    derivatives_coefs
+        .axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(voxel_index, mut coef_derivatives)| {
+            for (neighbor_index, coef_derivative) in coef_derivatives.iter_mut().enumerate() {
+                let delay = ap_params.delays[(voxel_index, neighbor_index)];
+                let number_of_gains = match ap_derivative {
+                    APDerivative::Simple => {
+                        if step < delay {
+                            continue;
+                        }
+                        (voxel_index * 3..voxel_index * 3 + 3)
+                            .flat_map(|state_index| {
+                                (neighbor_index * 3..neighbor_index * 3 + 3)
+                                    .map(move |offset_index| (state_index, offset_index))
+                            })
+                            .filter(|&index| ap_params.output_state_indices[index].is_some())
+                            .count()
+                    }
+                    APDerivative::Textbook => 9,
+                };
+                let delay_delta = (ap_params.initial_delays[(voxel_index, neighbor_index)]
+                    - (delay as f32
+                        + from_coef_to_samples(ap_params.coefs[(voxel_index, neighbor_index)])))
+                .powi(5);
+                *coef_derivative += number_of_gains as f32 * weight * delay_delta;
+            }
+        });
+}
+
 /// Calculates the maximum regularization for the given system states.
 /// Iterates through the states, calculates the sum of the absolute values,
 /// compares to the threshold, and calculates & assigns maximum regularization
@@ -549,20 +601,51 @@ pub fn calculate_mapped_residuals(
         &mut mapped_residuals.view_mut().insert_axis(ndarray::Axis(1)),
     );
 }
+
+/// Calculates the mapped residuals for a sensor array between two keyframes
+/// of a continuous trajectory, using the same linear interpolation of the
+/// measurement matrices as the prediction.
+#[inline]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn calculate_mapped_residuals_interpolated(
+    mapped_residuals: &mut MappedResiduals,
+    residuals: &Residuals,
+    lower_measurement_matrix: &MeasurementMatrixAtBeat,
+    upper_measurement_matrix: &MeasurementMatrixAtBeat,
+    weight: f32,
+) {
+    trace!("Calculating interpolated mapped residuals");
+    ndarray::linalg::general_mat_mul(
+        1.0 - weight,
+        &lower_measurement_matrix.t(),
+        &residuals.view().insert_axis(ndarray::Axis(1)),
+        0.0,
+        &mut mapped_residuals.view_mut().insert_axis(ndarray::Axis(1)),
+    );
+    ndarray::linalg::general_mat_mul(
+        weight,
+        &upper_measurement_matrix.t(),
+        &residuals.view().insert_axis(ndarray::Axis(1)),
+        1.0,
+        &mut mapped_residuals.view_mut().insert_axis(ndarray::Axis(1)),
+    );
+}
 #[allow(clippy::cast_precision_loss)]
 #[tracing::instrument(level = "trace", skip_all)]
 pub fn calculate_average_delays(
     average_delays: &mut AverageDelays,
     ap_params: &APParameters,
 ) -> Result<()> {
+    let neighborhood_radius = ap_params.neighborhood_radius()?;
     for voxel_index in 0..average_delays.shape()[0] {
         let mut delay_sum = 0.0;
         let mut gain_sum = 0.0;
 
         for offset in 0..ap_params.delays.shape()[1] {
-            let x_y_z_offset = delay_index_to_offset(offset)
+            let x_y_z_offset = delay_index_to_offset(offset, neighborhood_radius)
                 .context("Invalid delay offset index - algorithm parameter corruption")?;
-            let x_y_z_sum: f32 = x_y_z_offset.map(i32::abs).iter().sum::<i32>() as f32;
+            // squared distance to the neighbor in voxels
+            let x_y_z_sum: f32 = x_y_z_offset.map(|value| value.pow(2)).iter().sum::<i32>() as f32;
 
             let delay = unsafe { *ap_params.delays.uget((voxel_index, offset)) } as f32
                 + from_coef_to_samples(unsafe { *ap_params.coefs.uget((voxel_index, offset)) });
@@ -794,12 +877,13 @@ mod tests {
         let number_of_sensors = 10;
         let number_of_beats = 1;
         let step = 10;
-        let mut derivatives = Derivatives::new(number_of_states, Optimizer::Sgd);
+        let mut derivatives = Derivatives::new(number_of_states, Optimizer::Sgd, 1);
         let estimations = Estimations::empty(
             number_of_states,
             number_of_sensors,
             number_of_steps,
             number_of_beats,
+            1,
         );
         let functional_description = FunctionalDescription::empty(
             number_of_states,
@@ -807,6 +891,7 @@ mod tests {
             number_of_steps,
             number_of_beats,
             Dim([1000, 1, 1]),
+            1,
         );
         let config = Algorithm {
             maximum_regularization_strength: 0.0,
@@ -819,7 +904,7 @@ mod tests {
             &estimations,
             &functional_description,
             step,
-            &config,
+            config.mse_strength,
         )?;
         Ok(())
     }
@@ -838,19 +923,21 @@ mod tests {
             ..Default::default()
         };
 
-        let mut derivates = Derivatives::new(number_of_states, config.optimizer);
+        let mut derivates = Derivatives::new(number_of_states, config.optimizer, 1);
         let functional_description = FunctionalDescription::empty(
             number_of_states,
             number_of_sensors,
             number_of_steps,
             number_of_beats,
             voxels_in_dims,
+            1,
         );
         let estimations = Estimations::empty(
             number_of_states,
             number_of_sensors,
             number_of_steps,
             number_of_beats,
+            1,
         );
 
         calculate_step_derivatives(
@@ -860,14 +947,13 @@ mod tests {
             &config,
             step,
             0,
-            estimations.measurements.num_sensors(),
         )?;
         Ok(())
     }
 
     #[test]
     fn calculate_average_delays_single_voxel() -> Result<()> {
-        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]));
+        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]), 1);
 
         let mut average_delays = AverageDelays::empty(3);
         let delays = Array2::from_elem((1, 26), 2);
@@ -889,7 +975,7 @@ mod tests {
 
     #[test]
     fn test_calculate_average_delays_multiple_voxels() -> Result<()> {
-        let mut ap_params = APParameters::empty(6, Dim([2, 1, 1]));
+        let mut ap_params = APParameters::empty(6, Dim([2, 1, 1]), 1);
 
         let mut average_delays = AverageDelays::empty(6);
         let delays = Array2::from_elem((2, 26), 2);
@@ -916,7 +1002,7 @@ mod tests {
 
     #[test]
     fn test_calculate_average_delays_zero_gains() -> Result<()> {
-        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]));
+        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]), 1);
 
         let mut average_delays = AverageDelays::empty(3);
         let delays = Array2::from_elem((1, 26), 2);
@@ -934,7 +1020,7 @@ mod tests {
 
     #[test]
     fn test_calculate_average_delays_mixed_gains() -> Result<()> {
-        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]));
+        let mut ap_params = APParameters::empty(3, Dim([1, 1, 1]), 1);
 
         let mut average_delays = AverageDelays::empty(3);
         let delays = Array2::from_elem((1, 26), 2);
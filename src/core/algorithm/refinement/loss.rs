@@ -0,0 +1,778 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use approx::AbsDiffEq;
+use ndarray::Zip;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+use tracing::{debug, trace};
+
+use super::derivation::{
+    calculate_derivatives_coefs_simple, calculate_derivatives_coefs_textbook,
+    calculate_derivatives_difference_regularization, calculate_derivatives_gains,
+    calculate_derivatives_refractory, Derivatives,
+};
+use crate::core::{
+    algorithm::estimation::Estimations,
+    config::algorithm::{APDerivative, Algorithm},
+    model::functional::{
+        allpass::{from_coef_to_samples, APParameters},
+        FunctionalDescription,
+    },
+};
+
+/// Everything a loss term can depend on.
+#[derive(Debug, Clone, Copy)]
+pub struct LossContext<'a> {
+    pub estimations: &'a Estimations,
+    pub functional_description: &'a FunctionalDescription,
+    pub config: &'a Algorithm,
+}
+
+/// A term of the loss of the model-based algorithm.
+///
+/// All terms implement this trait and are registered in
+/// [`LossTermKind::term`], so they can be weighted via the config without
+/// changes to the derivation. The GPU kernels fuse the terms marked by
+/// [`LossTermKind::is_fused`] into their derivative loops and do not
+/// support the others.
+///
+/// Derivatives are accumulated over the steps of a batch and scaled by the
+/// batch size in the parameter update.
+pub trait LossTerm: Sync {
+    /// Returns the unweighted value of the term for the current estimations.
+    fn value(&self, context: &LossContext) -> f32;
+
+    /// Adds the weighted derivatives of the term at the given step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the estimations do not match the model.
+    fn add_step_derivatives(
+        &self,
+        _derivatives: &mut Derivatives,
+        _context: &LossContext,
+        _step: usize,
+        _weight: f32,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Adds the weighted derivatives of the term at the end of a batch.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the estimations do not match the model.
+    fn add_batch_derivatives(
+        &self,
+        _derivatives: &mut Derivatives,
+        _context: &LossContext,
+        _weight: f32,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The registered loss terms.
+///
+/// * `MeanSquaredError`: squared residuals of the measurements, averaged over
+///   the sensors.
+/// * `MaximumRegularization`: squared amount by which the summed absolute
+///   states of a voxel exceed the `maximum_regularization_threshold`.
+/// * `DifferenceRegularization`: sixth power of the difference between the
+///   delays and the delays of the initial model, divided by six.
+/// * `DelaySmoothness`: squared difference between the average delay of a
+///   voxel and of its neighborhood.
+/// * `GainSparsity`: L1 norm of the connected gains.
+/// * `StateTemporalSmoothness`: squared difference of the system states
+///   between consecutive steps, averaged over the steps.
+/// * `AnatomicalPrior`: squared difference between the delays and the
+///   delays of the initial model, which follow the anatomy.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, EnumIter)]
+pub enum LossTermKind {
+    MeanSquaredError,
+    MaximumRegularization,
+    DifferenceRegularization,
+    DelaySmoothness,
+    GainSparsity,
+    StateTemporalSmoothness,
+    AnatomicalPrior,
+}
+
+impl LossTermKind {
+    /// Returns the implementation of the term.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn term(self) -> &'static dyn LossTerm {
+        match self {
+            Self::MeanSquaredError => &MeanSquaredError,
+            Self::MaximumRegularization => &MaximumRegularization,
+            Self::DifferenceRegularization => &DifferenceRegularization,
+            Self::DelaySmoothness => &DelaySmoothness,
+            Self::GainSparsity => &GainSparsity,
+            Self::StateTemporalSmoothness => &StateTemporalSmoothness,
+            Self::AnatomicalPrior => &AnatomicalPrior,
+        }
+    }
+
+    /// Returns true if the term is fused into the derivative kernels of the
+    /// GPU algorithm.
+    #[must_use]
+    pub const fn is_fused(self) -> bool {
+        matches!(
+            self,
+            Self::MeanSquaredError | Self::MaximumRegularization | Self::DifferenceRegularization
+        )
+    }
+}
+
+impl Display for LossTermKind {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MeanSquaredError => write!(f, "Mean squared error"),
+            Self::MaximumRegularization => write!(f, "Maximum regularization"),
+            Self::DifferenceRegularization => write!(f, "Difference regularization"),
+            Self::DelaySmoothness => write!(f, "Delay smoothness"),
+            Self::GainSparsity => write!(f, "Gain sparsity"),
+            Self::StateTemporalSmoothness => write!(f, "State temporal smoothness"),
+            Self::AnatomicalPrior => write!(f, "Anatomical prior"),
+        }
+    }
+}
+
+/// A loss term and its weight in the loss.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct WeightedLossTerm {
+    pub kind: LossTermKind,
+    pub weight: f32,
+}
+
+/// Returns the terms weighted by their own field of the config.
+#[tracing::instrument(level = "trace", skip_all)]
+fn config_terms(config: &Algorithm) -> [WeightedLossTerm; 4] {
+    [
+        (LossTermKind::MeanSquaredError, config.mse_strength),
+        (
+            LossTermKind::MaximumRegularization,
+            config.maximum_regularization_strength,
+        ),
+        (
+            LossTermKind::DifferenceRegularization,
+            config.difference_regularization_strength,
+        ),
+        (
+            LossTermKind::DelaySmoothness,
+            config.smoothness_regularization_strength,
+        ),
+    ]
+    .map(|(kind, weight)| WeightedLossTerm { kind, weight })
+}
+
+/// Returns the terms with a non-zero weight, including the terms weighted by
+/// the `mse_strength`, `maximum_regularization_strength`,
+/// `difference_regularization_strength` and
+/// `smoothness_regularization_strength` of the config.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn active_terms(config: &Algorithm) -> impl Iterator<Item = WeightedLossTerm> + '_ {
+    config_terms(config)
+        .into_iter()
+        .chain(config.loss_terms.iter().copied())
+        .filter(|term| term.weight.abs_diff_ne(&0.0, f32::EPSILON))
+}
+
+/// Returns the weight of the term in the config, zero if it is not listed.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(config))]
+pub fn weight(config: &Algorithm, kind: LossTermKind) -> f32 {
+    if let Some(term) = config_terms(config)
+        .into_iter()
+        .find(|term| term.kind == kind)
+    {
+        return term.weight;
+    }
+    config
+        .loss_terms
+        .iter()
+        .filter(|term| term.kind == kind)
+        .map(|term| term.weight)
+        .sum()
+}
+
+/// Sets the weight of the term in the config, removing it if the weight is
+/// zero.
+#[tracing::instrument(level = "trace", skip(config))]
+pub fn set_weight(config: &mut Algorithm, kind: LossTermKind, weight: f32) {
+    let field = match kind {
+        LossTermKind::MeanSquaredError => &mut config.mse_strength,
+        LossTermKind::MaximumRegularization => &mut config.maximum_regularization_strength,
+        LossTermKind::DifferenceRegularization => &mut config.difference_regularization_strength,
+        LossTermKind::DelaySmoothness => &mut config.smoothness_regularization_strength,
+        LossTermKind::GainSparsity
+        | LossTermKind::StateTemporalSmoothness
+        | LossTermKind::AnatomicalPrior => {
+            config.loss_terms.retain(|term| term.kind != kind);
+            if weight.abs_diff_ne(&0.0, f32::EPSILON) {
+                config.loss_terms.push(WeightedLossTerm { kind, weight });
+            }
+            return;
+        }
+    };
+    *field = weight;
+}
+
+/// Adds the step derivatives of all active loss terms.
+///
+/// # Errors
+///
+/// Returns an error if a term fails.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn add_step_derivatives(
+    derivatives: &mut Derivatives,
+    context: &LossContext,
+    step: usize,
+) -> Result<()> {
+    for term in active_terms(context.config) {
+        term.kind
+            .term()
+            .add_step_derivatives(derivatives, context, step, term.weight)?;
+    }
+    Ok(())
+}
+
+/// Adds the batch derivatives of all active loss terms.
+///
+/// # Errors
+///
+/// Returns an error if a term fails.
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn add_batch_derivatives(derivatives: &mut Derivatives, context: &LossContext) -> Result<()> {
+    for term in active_terms(context.config) {
+        term.kind
+            .term()
+            .add_batch_derivatives(derivatives, context, term.weight)?;
+    }
+    Ok(())
+}
+
+/// Returns the weighted values of all active loss terms that are not fused.
+/// The mean squared error and the maximum regularization of the fused terms
+/// are tracked step-wise by the loss metrics, like on the GPU.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn weighted_values(context: &LossContext) -> Vec<(LossTermKind, f32)> {
+    trace!("Calculating loss term values");
+    active_terms(context.config)
+        .filter(|term| !term.kind.is_fused())
+        .map(|term| (term.kind, term.weight * term.kind.term().value(context)))
+        .collect()
+}
+
+/// Mean squared error of the measurements, see [`LossTermKind`].
+///
+/// The value and the derivatives are those of the current step, the
+/// derivatives use the mapped residuals calculated for the step.
+struct MeanSquaredError;
+
+impl LossTerm for MeanSquaredError {
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let estimations = context.estimations;
+        estimations
+            .residuals
+            .mapv(|residual| residual.powi(2))
+            .sum()
+            / estimations.measurements.num_sensors() as f32
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_step_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        step: usize,
+        weight: f32,
+    ) -> Result<()> {
+        let LossContext {
+            estimations,
+            functional_description,
+            config,
+        } = *context;
+        let scaling = 1.0 / estimations.measurements.num_sensors() as f32 * weight;
+        if !config.freeze_gains {
+            calculate_derivatives_gains(
+                &mut derivatives.gains,
+                &estimations.ap_outputs_now,
+                &derivatives.mapped_residuals,
+                scaling,
+            );
+        }
+        if !config.freeze_delays {
+            match config.ap_derivative {
+                APDerivative::Simple => calculate_derivatives_coefs_simple(
+                    derivatives,
+                    estimations,
+                    functional_description,
+                    step,
+                    scaling,
+                )?,
+                APDerivative::Textbook => calculate_derivatives_coefs_textbook(
+                    derivatives,
+                    estimations,
+                    functional_description,
+                    step,
+                    scaling,
+                )?,
+            }
+        }
+        if !config.freeze_refractory {
+            calculate_derivatives_refractory(
+                &mut derivatives.refractory,
+                estimations,
+                functional_description,
+                &derivatives.mapped_residuals,
+                step,
+                scaling,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Maximum regularization of the system states, see [`LossTermKind`].
+///
+/// The value is averaged over the steps, the derivatives use the
+/// regularization calculated for the current step.
+struct MaximumRegularization;
+
+impl LossTerm for MaximumRegularization {
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let threshold = context.config.maximum_regularization_threshold;
+        let system_states = &context.estimations.system_states;
+        let number_of_steps = system_states.num_steps();
+        if number_of_steps == 0 {
+            return 0.0;
+        }
+        let sum: f32 = (0..number_of_steps)
+            .map(|step| {
+                let states = system_states.at_step(step);
+                (0..states.len())
+                    .step_by(3)
+                    .map(|state_index| {
+                        let magnitude = states[state_index].abs()
+                            + states[state_index + 1].abs()
+                            + states[state_index + 2].abs();
+                        (magnitude - threshold).max(0.0).powi(2)
+                    })
+                    .sum::<f32>()
+            })
+            .sum();
+        sum / number_of_steps as f32
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_step_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        _step: usize,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_gains {
+            return Ok(());
+        }
+        calculate_derivatives_gains(
+            &mut derivatives.gains,
+            &context.estimations.ap_outputs_now,
+            &derivatives.maximum_regularization,
+            weight,
+        );
+        Ok(())
+    }
+}
+
+/// Deviation of the delays from the delays of the initial model, see
+/// [`LossTermKind`].
+///
+/// Unlike the [`AnatomicalPrior`], the derivatives are accumulated every
+/// step, see [`calculate_derivatives_difference_regularization`].
+struct DifferenceRegularization;
+
+impl LossTerm for DifferenceRegularization {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let ap_params = &context.functional_description.ap_params;
+        let (number_of_voxels, number_of_offsets) = ap_params.delays.dim();
+        (0..number_of_voxels)
+            .flat_map(|voxel_index| (0..number_of_offsets).map(move |offset| (voxel_index, offset)))
+            .filter(|&(voxel_index, offset)| {
+                AnatomicalPrior::is_connected(ap_params, voxel_index, offset)
+            })
+            .map(|(voxel_index, offset)| {
+                AnatomicalPrior::delay_difference(ap_params, voxel_index, offset).powi(6) / 6.0
+            })
+            .sum()
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_step_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        step: usize,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_delays {
+            return Ok(());
+        }
+        calculate_derivatives_difference_regularization(
+            &mut derivatives.coefs,
+            &context.functional_description.ap_params,
+            step,
+            &context.config.ap_derivative,
+            weight,
+        );
+        Ok(())
+    }
+}
+
+/// Smoothness of the average delays, see [`LossTermKind`].
+struct DelaySmoothness;
+
+impl DelaySmoothness {
+    /// Returns the average delay of the neighborhood of the voxel, including
+    /// the voxel itself, minus the average delay of the voxel.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn neighborhood_difference(
+        voxel_index: usize,
+        estimations: &Estimations,
+        ap_params: &APParameters,
+    ) -> Option<f32> {
+        let average_delay_in_voxel = estimations.average_delays[voxel_index]?;
+        let mut average_delay_in_neighborhood = average_delay_in_voxel;
+        let mut divisor = 1.0;
+        for voxel_offset in 0..ap_params.delays.shape()[1] {
+            let Some(neighbor_index) =
+                ap_params.output_state_indices[(voxel_index * 3, voxel_offset * 3)]
+            else {
+                continue;
+            };
+            if let Some(delay) = estimations.average_delays[neighbor_index / 3] {
+                average_delay_in_neighborhood += delay;
+                divisor += 1.0;
+            }
+        }
+        Some(average_delay_in_neighborhood / divisor - average_delay_in_voxel)
+    }
+}
+
+impl LossTerm for DelaySmoothness {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let ap_params = &context.functional_description.ap_params;
+        (0..ap_params.delays.shape()[0])
+            .filter_map(|voxel_index| {
+                Self::neighborhood_difference(voxel_index, context.estimations, ap_params)
+            })
+            .map(|difference| 0.5 * difference.powi(2))
+            .sum()
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_batch_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_delays {
+            return Ok(());
+        }
+        debug!("Calculating smoothness derivatives");
+        let ap_params = &context.functional_description.ap_params;
+        for (voxel_index, mut coef_derivatives) in derivatives.coefs.outer_iter_mut().enumerate() {
+            let Some(difference) =
+                Self::neighborhood_difference(voxel_index, context.estimations, ap_params)
+            else {
+                continue;
+            };
+            coef_derivatives += weight * difference;
+        }
+        Ok(())
+    }
+}
+
+/// L1 norm of the gains, see [`LossTermKind`].
+struct GainSparsity;
+
+impl LossTerm for GainSparsity {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let ap_params = &context.functional_description.ap_params;
+        ap_params
+            .gains
+            .iter()
+            .zip(ap_params.output_state_indices.iter())
+            .filter(|(_, index)| index.is_some())
+            .map(|(gain, _)| gain.abs())
+            .sum()
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_batch_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_gains {
+            return Ok(());
+        }
+        let ap_params = &context.functional_description.ap_params;
+        Zip::from(&mut *derivatives.gains)
+            .and(&*ap_params.gains)
+            .and(&*ap_params.output_state_indices)
+            .for_each(|derivative, gain, index| {
+                if index.is_some() && gain.abs_diff_ne(&0.0, f32::EPSILON) {
+                    *derivative += weight * gain.signum();
+                }
+            });
+        Ok(())
+    }
+}
+
+/// Temporal smoothness of the system states, see [`LossTermKind`].
+///
+/// The derivative only considers the difference to the previous step, like
+/// the maximum regularization only considers the current step.
+struct StateTemporalSmoothness;
+
+impl LossTerm for StateTemporalSmoothness {
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let system_states = &context.estimations.system_states;
+        let number_of_steps = system_states.num_steps();
+        if number_of_steps < 2 {
+            return 0.0;
+        }
+        let sum: f32 = (1..number_of_steps)
+            .map(|step| {
+                (&*system_states.at_step(step) - &*system_states.at_step(step - 1))
+                    .mapv(|difference| difference.powi(2))
+                    .sum()
+            })
+            .sum();
+        sum / (number_of_steps - 1) as f32
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_step_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        step: usize,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_gains || step == 0 {
+            return Ok(());
+        }
+        let system_states = &context.estimations.system_states;
+        let now = system_states.at_step(step);
+        let last = system_states.at_step(step - 1);
+        let ap_outputs = &context.estimations.ap_outputs_now;
+        anyhow::ensure!(
+            ap_outputs.shape() == derivatives.gains.shape(),
+            "AP outputs do not match the gains derivatives"
+        );
+        for (state_index, mut gain_derivatives) in derivatives.gains.outer_iter_mut().enumerate() {
+            let gradient = 2.0 * weight * (now[state_index] - last[state_index]);
+            gain_derivatives.scaled_add(gradient, &ap_outputs.row(state_index));
+        }
+        Ok(())
+    }
+}
+
+/// Deviation of the delays from the delays of the initial model, see
+/// [`LossTermKind`].
+struct AnatomicalPrior;
+
+impl AnatomicalPrior {
+    /// Returns the continuous delay in samples of the connection minus its
+    /// initial delay.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn delay_difference(ap_params: &APParameters, voxel_index: usize, offset: usize) -> f32 {
+        ap_params.delays[(voxel_index, offset)] as f32
+            + from_coef_to_samples(ap_params.coefs[(voxel_index, offset)])
+            - ap_params.initial_delays[(voxel_index, offset)]
+    }
+
+    /// Returns true if the voxel is connected to the neighbor at the offset.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn is_connected(ap_params: &APParameters, voxel_index: usize, offset: usize) -> bool {
+        ap_params.output_state_indices[(voxel_index * 3, offset * 3)].is_some()
+    }
+}
+
+impl LossTerm for AnatomicalPrior {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn value(&self, context: &LossContext) -> f32 {
+        let ap_params = &context.functional_description.ap_params;
+        let (number_of_voxels, number_of_offsets) = ap_params.delays.dim();
+        (0..number_of_voxels)
+            .flat_map(|voxel_index| (0..number_of_offsets).map(move |offset| (voxel_index, offset)))
+            .filter(|&(voxel_index, offset)| Self::is_connected(ap_params, voxel_index, offset))
+            .map(|(voxel_index, offset)| {
+                0.5 * Self::delay_difference(ap_params, voxel_index, offset).powi(2)
+            })
+            .sum()
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn add_batch_derivatives(
+        &self,
+        derivatives: &mut Derivatives,
+        context: &LossContext,
+        weight: f32,
+    ) -> Result<()> {
+        if context.config.freeze_delays {
+            return Ok(());
+        }
+        let ap_params = &context.functional_description.ap_params;
+        for ((voxel_index, offset), derivative) in derivatives.coefs.indexed_iter_mut() {
+            if !Self::is_connected(ap_params, voxel_index, offset) {
+                continue;
+            }
+            // the delay in samples is (1 - coef) / (1 + coef)
+            let coef = ap_params.coefs[(voxel_index, offset)];
+            let samples_per_coef = -2.0 / (coef + 1.0).powi(2);
+            *derivative +=
+                weight * Self::delay_difference(ap_params, voxel_index, offset) * samples_per_coef;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::Dim;
+
+    use super::*;
+    use crate::core::algorithm::refinement::Optimizer;
+
+    fn connected_context_parts() -> (Estimations, FunctionalDescription) {
+        let number_of_states = 6;
+        let estimations = Estimations::empty(number_of_states, 1, 3, 1, 1);
+        let mut functional_description =
+            FunctionalDescription::empty(number_of_states, 1, 3, 1, Dim([2, 1, 1]), 1);
+        let ap_params = &mut functional_description.ap_params;
+        for input in 0..3 {
+            for output in 0..3 {
+                ap_params.output_state_indices[(input, output)] = Some(3 + output);
+                ap_params.output_state_indices[(3 + input, 3 + output)] = Some(output);
+            }
+        }
+        (estimations, functional_description)
+    }
+
+    #[test]
+    fn only_weighted_terms_are_active() {
+        let config = Algorithm {
+            smoothness_regularization_strength: 0.0,
+            loss_terms: vec![
+                WeightedLossTerm {
+                    kind: LossTermKind::GainSparsity,
+                    weight: 0.5,
+                },
+                WeightedLossTerm {
+                    kind: LossTermKind::AnatomicalPrior,
+                    weight: 0.0,
+                },
+            ],
+            ..Default::default()
+        };
+
+        let kinds: Vec<LossTermKind> = active_terms(&config).map(|term| term.kind).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                LossTermKind::MeanSquaredError,
+                LossTermKind::MaximumRegularization,
+                LossTermKind::GainSparsity
+            ]
+        );
+    }
+
+    #[test]
+    fn fused_term_weights_are_stored_in_their_config_fields() {
+        let mut config = Algorithm::default();
+
+        set_weight(&mut config, LossTermKind::MaximumRegularization, 5.0);
+        set_weight(&mut config, LossTermKind::MeanSquaredError, 0.0);
+
+        assert_relative_eq!(config.maximum_regularization_strength, 5.0);
+        assert_relative_eq!(weight(&config, LossTermKind::MeanSquaredError), 0.0);
+        assert!(config.loss_terms.is_empty());
+        assert!(active_terms(&config).all(|term| term.kind != LossTermKind::MeanSquaredError));
+    }
+
+    #[test]
+    fn gain_sparsity_pushes_gains_towards_zero() -> Result<()> {
+        let (estimations, mut functional_description) = connected_context_parts();
+        functional_description.ap_params.gains.fill(-2.0);
+        let config = Algorithm {
+            freeze_gains: false,
+            ..Default::default()
+        };
+        let context = LossContext {
+            estimations: &estimations,
+            functional_description: &functional_description,
+            config: &config,
+        };
+        let mut derivatives = Derivatives::new(6, Optimizer::Sgd, 1);
+
+        GainSparsity.add_batch_derivatives(&mut derivatives, &context, 0.5)?;
+
+        assert_relative_eq!(GainSparsity.value(&context), 36.0);
+        assert_relative_eq!(derivatives.gains[(0, 0)], -0.5);
+        assert_relative_eq!(derivatives.gains[(0, 3)], 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn anatomical_prior_is_zero_at_initial_delays() -> Result<()> {
+        let (estimations, mut functional_description) = connected_context_parts();
+        let ap_params = &mut functional_description.ap_params;
+        ap_params.delays.fill(2);
+        ap_params.coefs.fill(0.5);
+        let initial = 2.0 + from_coef_to_samples(0.5);
+        ap_params.initial_delays.fill(initial);
+        let config = Algorithm {
+            freeze_delays: false,
+            ..Default::default()
+        };
+        let mut derivatives = Derivatives::new(6, Optimizer::Sgd, 1);
+        let context = LossContext {
+            estimations: &estimations,
+            functional_description: &functional_description,
+            config: &config,
+        };
+
+        AnatomicalPrior.add_batch_derivatives(&mut derivatives, &context, 1.0)?;
+
+        assert_relative_eq!(AnatomicalPrior.value(&context), 0.0);
+        assert!(derivatives
+            .coefs
+            .iter()
+            .all(|derivative| derivative.abs_diff_eq(&0.0, f32::EPSILON)));
+        Ok(())
+    }
+}
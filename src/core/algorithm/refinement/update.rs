@@ -1,16 +1,127 @@
 use anyhow::{Context, Result};
+use ndarray::{Array1, Axis};
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use super::derivation::Derivatives;
 use crate::core::{
     algorithm::refinement::Optimizer,
-    config::algorithm::Algorithm,
-    model::functional::allpass::{
-        shapes::{Coefs, Gains, UnitDelays},
-        APParameters,
+    config::algorithm::{Algorithm, OptimizationRegion},
+    model::{
+        functional::allpass::{
+            shapes::{Coefs, Gains, UnitDelays},
+            APParameters,
+        },
+        spatial::{
+            nifti::{label_at_position, load_from_nii},
+            SpatialDescription,
+        },
     },
 };
 
+/// Per-voxel factors applied to the derivatives before the update.
+///
+/// Voxels with a factor of one are optimized, voxels with a factor of zero
+/// keep their parameters. The coefficient factors also apply to the
+/// refractory times.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct UpdateMask {
+    pub gains: Array1<f32>,
+    pub coefs: Array1<f32>,
+}
+
+impl UpdateMask {
+    /// Creates a mask that optimizes all voxels.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn full(number_of_voxels: usize) -> Self {
+        debug!("Creating full update mask");
+        Self {
+            gains: Array1::ones(number_of_voxels),
+            coefs: Array1::ones(number_of_voxels),
+        }
+    }
+
+    /// Creates the mask of the optimization region and the frozen voxel
+    /// types of the algorithm, or `None` if all parameters are optimized.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the NIfTI volume of the region cannot be read.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_config(
+        config: &Algorithm,
+        spatial_description: &SpatialDescription,
+    ) -> Result<Option<Self>> {
+        debug!("Creating update mask from config");
+        if config.optimization_region == OptimizationRegion::All
+            && config
+                .frozen_voxel_types
+                .values()
+                .all(|frozen| !frozen.gains && !frozen.delays)
+        {
+            return Ok(None);
+        }
+        let voxels = &spatial_description.voxels;
+        let mut mask = Self::full(voxels.count_states() / 3);
+        let voxels_in_dims = voxels.count_xyz();
+        let mri_data = match &config.optimization_region {
+            OptimizationRegion::NiftiLabel { path, .. } => Some(
+                load_from_nii(path)
+                    .context("Failed to read the NIfTI volume of the optimization region")?,
+            ),
+            _ => None,
+        };
+        for ((x, y, z), number) in voxels.numbers.indexed_iter() {
+            let Some(state) = *number else {
+                continue;
+            };
+            let inside = match &config.optimization_region {
+                OptimizationRegion::All => true,
+                OptimizationRegion::Cuboid(region) => region.contains([x, y, z], voxels_in_dims),
+                OptimizationRegion::NiftiLabel { label, .. } => {
+                    let position_mm = [
+                        voxels.positions_mm[(x, y, z, 0)],
+                        voxels.positions_mm[(x, y, z, 1)],
+                        voxels.positions_mm[(x, y, z, 2)],
+                    ];
+                    mri_data.as_ref().and_then(|mri_data| {
+                        label_at_position(&config.model, position_mm, mri_data)
+                    }) == Some(*label)
+                }
+            };
+            let frozen = config
+                .frozen_voxel_types
+                .get(&voxels.types[(x, y, z)])
+                .copied()
+                .unwrap_or_default();
+            if !inside || frozen.gains {
+                mask.gains[state / 3] = 0.0;
+            }
+            if !inside || frozen.delays {
+                mask.coefs[state / 3] = 0.0;
+            }
+        }
+        Ok(Some(mask))
+    }
+
+    /// Scales the derivatives of every voxel by its factors.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn apply(&self, gains: &mut Gains, coefs: &mut Coefs, refractory: &mut Array1<f32>) {
+        debug!("Applying update mask");
+        // the gains of a voxel are the rows of its three states
+        gains
+            .axis_chunks_iter_mut(Axis(0), 3)
+            .zip(&self.gains)
+            .for_each(|(mut rows, factor)| rows *= *factor);
+        coefs
+            .axis_iter_mut(Axis(0))
+            .zip(&self.coefs)
+            .for_each(|(mut row, factor)| row *= *factor);
+        *refractory *= &self.coefs;
+    }
+}
+
 impl APParameters {
     /// Updates the allpass filter parameters based on the provided derivatives.
     ///
@@ -19,6 +130,10 @@ impl APParameters {
     /// and batch size. Freezing gains or delays can be configured via the Algorithm
     /// config. Gradient clamping is also applied based on the config threshold.
     ///
+    /// Afterwards the updated parameters are projected into the physiological
+    /// bounds of the derivatives, if any. Returns the number of projected
+    /// parameters.
+    ///
     /// # Errors
     ///
     /// Returns an error if optimizer configuration is invalid (e.g. Adam optimizer without moment arrays).
@@ -30,13 +145,21 @@ impl APParameters {
         config: &Algorithm,
         number_of_steps: usize,
         number_of_beats: usize,
-    ) -> Result<()> {
+    ) -> Result<usize> {
         debug!("Updating allpass filter parameters");
         let batch_size = match config.batch_size {
             0 => number_of_steps * number_of_beats,
             _ => number_of_steps * config.batch_size,
         };
 
+        if let Some(mask) = &derivatives.update_mask {
+            mask.apply(
+                &mut derivatives.gains,
+                &mut derivatives.coefs,
+                &mut derivatives.refractory,
+            );
+        }
+
         if !config.freeze_gains {
             match config.optimizer {
                 Optimizer::Sgd => {
@@ -92,8 +215,26 @@ impl APParameters {
             }
             roll_delays(&mut self.coefs, &mut self.delays);
         }
+
+        if !config.freeze_refractory {
+            update_refractory_sgd(
+                &mut self.refractory.samples,
+                &derivatives.refractory,
+                config.learning_rate,
+                batch_size,
+            );
+        }
+        let mut violations = 0;
+        if let Some(bounds) = &derivatives.parameter_bounds {
+            if !config.freeze_gains {
+                violations += bounds.project_gains(self);
+            }
+            if !config.freeze_delays {
+                violations += bounds.project_delays(self);
+            }
+        }
         derivatives.step += 1;
-        Ok(())
+        Ok(violations)
     }
 }
 
@@ -113,6 +254,23 @@ pub fn update_gains_sgd(
     **gains -= &(learning_rate / batch_size as f32 * &**derivatives);
 }
 
+/// Updates the refractory times of the voxels based on the provided
+/// derivatives, learning rate and batch size. The refractory times are
+/// kept non-negative.
+#[allow(clippy::cast_precision_loss)]
+#[inline]
+#[tracing::instrument(level = "debug")]
+pub fn update_refractory_sgd(
+    refractory_samples: &mut Array1<f32>,
+    derivatives: &Array1<f32>,
+    learning_rate: f32,
+    batch_size: usize,
+) {
+    debug!("Updating refractory times");
+    *refractory_samples -= &(learning_rate / batch_size as f32 * derivatives);
+    refractory_samples.mapv_inplace(|samples| samples.max(0.0));
+}
+
 #[allow(clippy::cast_precision_loss)]
 #[inline]
 #[tracing::instrument(level = "debug")]
@@ -230,13 +388,17 @@ pub fn roll_delays(ap_coefs: &mut Coefs, delays: &mut UnitDelays) {
 #[cfg(test)]
 mod tests {
 
+    use approx::{assert_relative_eq, relative_eq};
+    use ndarray::s;
+
     use super::*;
+    use crate::core::{config::algorithm::FrozenParameters, model::spatial::voxels::VoxelType};
 
     #[test]
     fn update_gains_success() {
         let number_of_states = 10;
-        let mut gains = Gains::empty(number_of_states);
-        let mut derivatives = Gains::empty(number_of_states);
+        let mut gains = Gains::empty(number_of_states, 1);
+        let mut derivatives = Gains::empty(number_of_states, 1);
         derivatives.fill(-0.5);
         let learning_rate = 1.0;
 
@@ -248,9 +410,9 @@ mod tests {
     #[test]
     fn update_delays_success() {
         let number_of_states = 12;
-        let mut ap_coefs = Coefs::empty(number_of_states);
-        let mut delays = UnitDelays::empty(number_of_states);
-        let mut derivatives = Coefs::empty(number_of_states);
+        let mut ap_coefs = Coefs::empty(number_of_states, 1);
+        let mut delays = UnitDelays::empty(number_of_states, 1);
+        let mut derivatives = Coefs::empty(number_of_states, 1);
         derivatives.fill(-0.5);
         let learning_rate = 1.0;
 
@@ -259,4 +421,63 @@ mod tests {
 
         assert_eq!(-&*derivatives, &*ap_coefs);
     }
+
+    #[test]
+    fn update_mask_zeroes_fixed_voxels() {
+        let number_of_states = 6;
+        let mut gains = Gains::empty(number_of_states, 1);
+        let mut coefs = Coefs::empty(number_of_states, 1);
+        let mut refractory = Array1::ones(number_of_states / 3);
+        gains.fill(1.0);
+        coefs.fill(1.0);
+        let mut mask = UpdateMask::full(number_of_states / 3);
+        mask.gains[1] = 0.0;
+        mask.coefs[0] = 0.0;
+
+        mask.apply(&mut gains, &mut coefs, &mut refractory);
+
+        assert!(gains
+            .slice(s![..3, ..])
+            .iter()
+            .all(|gain| relative_eq!(*gain, 1.0)));
+        assert!(gains
+            .slice(s![3.., ..])
+            .iter()
+            .all(|gain| relative_eq!(*gain, 0.0)));
+        assert!(coefs.row(0).iter().all(|coef| relative_eq!(*coef, 0.0)));
+        assert!(coefs.row(1).iter().all(|coef| relative_eq!(*coef, 1.0)));
+        assert_relative_eq!(refractory, Array1::from_vec(vec![0.0, 1.0]));
+    }
+
+    #[test]
+    fn update_mask_freezes_voxel_types() -> anyhow::Result<()> {
+        let mut config = Algorithm::default();
+        let spatial_description = SpatialDescription::from_model_config(&config.model)?;
+        assert!(UpdateMask::from_config(&config, &spatial_description)?.is_none());
+        config.frozen_voxel_types.insert(
+            VoxelType::HPS,
+            FrozenParameters {
+                gains: false,
+                delays: true,
+            },
+        );
+
+        let mask = UpdateMask::from_config(&config, &spatial_description)?
+            .expect("Mask to be created for frozen voxel types");
+
+        let voxels = &spatial_description.voxels;
+        for (voxel_type, number) in voxels.types.iter().zip(voxels.numbers.iter()) {
+            let Some(state) = *number else {
+                continue;
+            };
+            let expected = if *voxel_type == VoxelType::HPS {
+                0.0
+            } else {
+                1.0
+            };
+            assert_relative_eq!(mask.coefs[state / 3], expected);
+            assert_relative_eq!(mask.gains[state / 3], 1.0);
+        }
+        Ok(())
+    }
 }
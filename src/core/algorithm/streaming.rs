@@ -0,0 +1,359 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use ndarray_npy::read_npy;
+use tracing::{debug, info, trace};
+
+use super::{
+    estimation::{
+        prediction::{calculate_system_prediction, predict_measurements},
+        Estimations,
+    },
+    kalman::calculate_steady_state_gain,
+};
+use crate::core::{
+    config::algorithm::Algorithm,
+    model::{functional::FunctionalDescription, Model},
+};
+
+// how long a source waits for a sample before checking for a stop request
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A source of measurement samples, one value per sensor, for the streaming
+/// estimator.
+pub trait SampleSource: Send {
+    /// Returns the next sample, blocking until it is available, or `None`
+    /// once the source is exhausted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample could not be received.
+    fn next_sample(&mut self, stop: &AtomicBool) -> Result<Option<Array1<f32>>>;
+}
+
+/// Replays recorded measurements of shape (steps, sensors) at real-time
+/// speed times the playback speed.
+#[derive(Debug)]
+pub struct ReplaySource {
+    measurements: Array2<f32>,
+    period: Duration,
+    index: usize,
+    start: Option<Instant>,
+}
+
+impl ReplaySource {
+    /// Creates a source replaying the given measurements of shape
+    /// (steps, sensors).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample rate or playback speed is not
+    /// positive.
+    #[tracing::instrument(level = "debug", skip(measurements))]
+    pub fn new(
+        measurements: ArrayView2<f32>,
+        sample_rate_hz: f32,
+        playback_speed: f32,
+    ) -> Result<Self> {
+        debug!("Creating replay source");
+        anyhow::ensure!(
+            sample_rate_hz > 0.0 && playback_speed > 0.0,
+            "Sample rate and playback speed must be positive"
+        );
+        Ok(Self {
+            measurements: measurements.to_owned(),
+            period: Duration::from_secs_f32(1.0 / (sample_rate_hz * playback_speed)),
+            index: 0,
+            start: None,
+        })
+    }
+
+    /// Creates a source replaying the measurements of shape (steps, sensors)
+    /// stored in a .npy file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or the sample rate or
+    /// playback speed is not positive.
+    #[tracing::instrument(level = "debug")]
+    pub fn from_npy(path: &Path, sample_rate_hz: f32, playback_speed: f32) -> Result<Self> {
+        debug!("Creating replay source from npy file");
+        let measurements: Array2<f32> = read_npy(path)
+            .with_context(|| format!("Failed to read measurements: {}", path.display()))?;
+        Self::new(measurements.view(), sample_rate_hz, playback_speed)
+    }
+}
+
+impl SampleSource for ReplaySource {
+    #[allow(clippy::cast_possible_truncation)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn next_sample(&mut self, stop: &AtomicBool) -> Result<Option<Array1<f32>>> {
+        if self.index >= self.measurements.nrows() || stop.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let due = start + self.period * self.index as u32;
+        if let Some(remaining) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(remaining);
+        }
+        let sample = self.measurements.row(self.index).to_owned();
+        self.index += 1;
+        Ok(Some(sample))
+    }
+}
+
+/// Receives samples pushed from another thread, e.g. a network client.
+/// The source is exhausted once all senders are dropped.
+#[derive(Debug)]
+pub struct ChannelSource(pub Receiver<Array1<f32>>);
+
+impl SampleSource for ChannelSource {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn next_sample(&mut self, stop: &AtomicBool) -> Result<Option<Array1<f32>>> {
+        while !stop.load(Ordering::Relaxed) {
+            match self.0.recv_timeout(RECEIVE_TIMEOUT) {
+                Ok(sample) => return Ok(Some(sample)),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The estimate after one streamed sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamUpdate {
+    // number of samples processed so far, including this one
+    pub sample_count: usize,
+    // step within the current beat
+    pub step: usize,
+    // magnitude of the corrected current density per voxel
+    pub magnitudes: Array1<f32>,
+    // root mean square of the residuals of the included channels
+    pub residual_rms: f32,
+}
+
+/// Estimates the system states online, one measurement sample at a time.
+///
+/// Every sample is processed like a step of [`super::kalman::run_kalman_filter`]:
+/// the all-pass model predicts the states, which are corrected with the
+/// steady-state Kalman gain of the first sensor array position. The stream
+/// is split into beats of the model's number of steps, the states are reset
+/// at the start of every beat.
+#[derive(Debug)]
+pub struct StreamingEstimator {
+    functional_description: FunctionalDescription,
+    estimations: Estimations,
+    gain: Array2<f32>,
+    step: usize,
+    sample_count: usize,
+}
+
+impl StreamingEstimator {
+    /// Creates an estimator for the given model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Kalman gain cannot be calculated or an
+    /// excluded channel is out of range.
+    #[tracing::instrument(level = "debug", skip(model))]
+    pub fn new(model: &Model, config: &Algorithm) -> Result<Self> {
+        debug!("Creating streaming estimator");
+        let functional_description = model.functional_description.clone();
+        let gain = calculate_steady_state_gain(
+            &functional_description.measurement_matrix.at_beat(0),
+            config.kalman_process_covariance,
+            config.kalman_measurement_covariance,
+        )
+        .context("Failed to calculate Kalman gain for streaming")?;
+        let mut estimations = Estimations::empty(
+            model.spatial_description.voxels.count_states(),
+            model.spatial_description.sensors.count(),
+            functional_description.control_function_values.len(),
+            1,
+            config.model.common.neighborhood_radius,
+        );
+        estimations
+            .channel_mask
+            .exclude(&config.excluded_channels)?;
+        Ok(Self {
+            functional_description,
+            estimations,
+            gain,
+            step: 0,
+            sample_count: 0,
+        })
+    }
+
+    /// Number of sensors a sample must contain.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn number_of_sensors(&self) -> usize {
+        self.estimations.residuals.len()
+    }
+
+    /// Updates the states with the given sample and returns the estimate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sample does not contain one value per sensor
+    /// or the prediction fails.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn push_sample(&mut self, sample: ArrayView1<f32>) -> Result<StreamUpdate> {
+        trace!("Pushing sample to streaming estimator");
+        anyhow::ensure!(
+            sample.len() == self.number_of_sensors(),
+            "Sample has {} values, but the model has {} sensors",
+            sample.len(),
+            self.number_of_sensors()
+        );
+        if self.step >= self.estimations.system_states.num_steps() {
+            self.estimations.reset();
+            self.step = 0;
+        }
+        let step = self.step;
+        let estimations = &mut self.estimations;
+
+        calculate_system_prediction(estimations, &self.functional_description, 0, step)?;
+        calculate_stream_residuals(estimations, &sample, step);
+        // residuals are predicted minus actual measurements
+        let correction = self.gain.dot(&*estimations.residuals);
+        estimations
+            .system_states
+            .at_step_mut(step)
+            .scaled_add(-1.0, &correction);
+        predict_measurements(estimations, &self.functional_description, 0, step);
+        calculate_stream_residuals(estimations, &sample, step);
+
+        self.step += 1;
+        self.sample_count += 1;
+        Ok(StreamUpdate {
+            sample_count: self.sample_count,
+            step,
+            magnitudes: voxel_magnitudes(&estimations.system_states.at_step(step)),
+            residual_rms: root_mean_square(&estimations.residuals),
+        })
+    }
+}
+
+/// Feeds the samples of the source into the estimator and sends every
+/// update, until the source is exhausted, a stop is requested or the
+/// receiver is dropped.
+///
+/// Returns the number of processed samples.
+///
+/// # Errors
+///
+/// Returns an error if receiving or processing a sample fails.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn run_stream(
+    estimator: &mut StreamingEstimator,
+    source: &mut dyn SampleSource,
+    update_tx: &Sender<StreamUpdate>,
+    stop: &AtomicBool,
+) -> Result<usize> {
+    info!("Running streaming estimation");
+    let mut processed = 0;
+    while let Some(sample) = source.next_sample(stop)? {
+        let update = estimator.push_sample(sample.view())?;
+        processed += 1;
+        if update_tx.send(update).is_err() {
+            break;
+        }
+    }
+    info!("Streaming estimation stopped after {processed} samples");
+    Ok(processed)
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn calculate_stream_residuals(
+    estimations: &mut Estimations,
+    sample: &ArrayView1<f32>,
+    step: usize,
+) {
+    estimations.residuals.assign(
+        &((&*estimations.measurements.at_beat(0).at_step(step) - sample)
+            * &*estimations.channel_mask),
+    );
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn voxel_magnitudes(states: &ArrayView1<f32>) -> Array1<f32> {
+    states
+        .exact_chunks(3)
+        .into_iter()
+        .map(|state| state.dot(&state).sqrt())
+        .collect()
+}
+
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn root_mean_square(values: &Array1<f32>) -> f32 {
+    (values.dot(values) / values.len().max(1) as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use ndarray::{arr1, s};
+
+    use super::*;
+    use crate::core::{config::Config, data::Data};
+
+    #[test]
+    fn voxel_magnitudes_combine_three_components() {
+        let magnitudes = voxel_magnitudes(&arr1(&[3.0, 4.0, 0.0, 0.0, 0.0, 2.0]).view());
+
+        assert_eq!(magnitudes, arr1(&[5.0, 2.0]));
+    }
+
+    #[test]
+    fn channel_source_ends_when_sender_is_dropped() -> Result<()> {
+        let (sample_tx, sample_rx) = channel();
+        let mut source = ChannelSource(sample_rx);
+        sample_tx.send(arr1(&[1.0, 2.0]))?;
+        drop(sample_tx);
+        let stop = AtomicBool::new(false);
+
+        assert_eq!(source.next_sample(&stop)?, Some(arr1(&[1.0, 2.0])));
+        assert_eq!(source.next_sample(&stop)?, None);
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "expensive integration test"]
+    fn streaming_replay_tracks_simulation() -> Result<()> {
+        let config = Config::default();
+        let data = Data::from_simulation_config(&config.simulation)?;
+        let model = &data.simulation.model;
+        let mut estimator = StreamingEstimator::new(model, &config.algorithm)?;
+        let mut source = ReplaySource::new(
+            data.simulation.measurements.slice(s![0, .., ..]),
+            config.simulation.sample_rate_hz,
+            100.0,
+        )?;
+        let (update_tx, update_rx) = channel();
+
+        let processed = run_stream(
+            &mut estimator,
+            &mut source,
+            &update_tx,
+            &AtomicBool::new(false),
+        )?;
+
+        assert_eq!(processed, data.simulation.measurements.num_steps());
+        assert_eq!(update_rx.iter().count(), processed);
+        Ok(())
+    }
+}
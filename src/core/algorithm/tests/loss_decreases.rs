@@ -14,7 +14,7 @@ use crate::{
     vis::plotting::{
         gif::states::states_spherical_plot_over_time,
         png::{line::standard_y_plot, states::states_spherical_plot},
-        PlotSlice, StateSphericalPlotMode,
+        PlotSlice, PlotTheme, StateSphericalPlotMode,
     },
 };
 
@@ -71,6 +71,7 @@ fn loss_decreases() -> anyhow::Result<()> {
         0,
         algorithm_config.batch_size,
         algorithm_config.optimizer,
+        algorithm_config.model.common.neighborhood_radius,
     );
     results.model = Some(model);
 
@@ -126,6 +127,7 @@ fn loss_decreases_and_plot() -> anyhow::Result<()> {
         0,
         algorithm_config.batch_size,
         algorithm_config.optimizer,
+        algorithm_config.model.common.neighborhood_radius,
     );
     results.model = Some(model);
 
@@ -138,6 +140,7 @@ fn loss_decreases_and_plot() -> anyhow::Result<()> {
         "Loss",
         "Loss",
         "Step",
+        PlotTheme::Light,
     )
     .with_context(|| format!("Failed to create loss plot at {}", path.display()))?;
 
@@ -150,6 +153,7 @@ fn loss_decreases_and_plot() -> anyhow::Result<()> {
         "Sum Loss Per Epoch",
         "Loss",
         "Epoch",
+        PlotTheme::Light,
     )
     .with_context(|| format!("Failed to create loss epoch plot at {}", path.display()))?;
 
@@ -173,6 +177,8 @@ fn loss_decreases_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         None,
         None,
+        None,
+        PlotTheme::Light,
     )
     .with_context(|| {
         format!(
@@ -203,6 +209,7 @@ fn loss_decreases_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(playback_speed),
         Some(fps),
+        PlotTheme::Light,
     )
     .with_context(|| {
         format!(
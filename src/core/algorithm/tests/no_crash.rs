@@ -27,6 +27,7 @@ fn run_epoch_no_crash() -> anyhow::Result<()> {
         number_of_steps,
         voxels_in_dims,
         number_of_beats,
+        1,
     );
 
     let mut results = Results::new(
@@ -38,6 +39,7 @@ fn run_epoch_no_crash() -> anyhow::Result<()> {
         number_of_snapshots,
         config.batch_size,
         config.optimizer,
+        config.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let data = Data::empty(
@@ -46,6 +48,7 @@ fn run_epoch_no_crash() -> anyhow::Result<()> {
         number_of_steps,
         voxels_in_dims,
         number_of_beats,
+        1,
     );
 
     let mut batch_index = 0;
@@ -72,6 +75,7 @@ fn run_no_crash() -> anyhow::Result<()> {
         number_of_steps,
         voxels_in_dims,
         number_of_beats,
+        1,
     );
     let mut results = Results::new(
         algorithm_config.epochs,
@@ -82,6 +86,7 @@ fn run_no_crash() -> anyhow::Result<()> {
         number_of_snapshots,
         algorithm_config.batch_size,
         algorithm_config.optimizer,
+        algorithm_config.model.common.neighborhood_radius,
     );
     results.model = Some(model);
     let data = Data::empty(
@@ -90,6 +95,7 @@ fn run_no_crash() -> anyhow::Result<()> {
         number_of_steps,
         voxels_in_dims,
         number_of_beats,
+        1,
     );
 
     run(&mut results, &data, &algorithm_config)?;
@@ -128,6 +134,7 @@ fn pseudo_inverse_success() -> anyhow::Result<()> {
         0,
         algorithm_config.batch_size,
         algorithm_config.optimizer,
+        algorithm_config.model.common.neighborhood_radius,
     );
 
     calculate_pseudo_inverse(
@@ -138,3 +145,59 @@ fn pseudo_inverse_success() -> anyhow::Result<()> {
     )?;
     Ok(())
 }
+
+#[test]
+#[ignore = "expensive integration test"]
+fn pseudo_inverse_regularization_path_success() -> anyhow::Result<()> {
+    let mut simulation_config = SimulationConfig::default();
+    simulation_config.model.common.sensor_array_geometry = SensorArrayGeometry::Cube;
+    simulation_config.model.common.sensor_array_motion = SensorArrayMotion::Static;
+    let data = Data::from_simulation_config(&simulation_config)?;
+
+    let mut algorithm_config = Algorithm::default();
+    algorithm_config.model.common.sensor_array_geometry = SensorArrayGeometry::Cube;
+    algorithm_config.model.common.sensor_array_motion = SensorArrayMotion::Static;
+    algorithm_config.regularization_path = RegularizationPath {
+        number_of_lambdas: 5,
+        ..Default::default()
+    };
+
+    let model = Model::from_model_config(
+        &algorithm_config.model,
+        simulation_config.sample_rate_hz,
+        simulation_config.duration_s,
+    )?;
+
+    let mut results = Results::new(
+        algorithm_config.epochs,
+        model.functional_description.control_function_values.shape()[0],
+        model.spatial_description.sensors.count(),
+        model.spatial_description.voxels.count_states(),
+        simulation_config
+            .model
+            .common
+            .sensor_array_motion_steps
+            .iter()
+            .product(),
+        0,
+        algorithm_config.batch_size,
+        algorithm_config.optimizer,
+        algorithm_config.model.common.neighborhood_radius,
+    );
+
+    calculate_pseudo_inverse(
+        &model.functional_description,
+        &mut results,
+        &data,
+        &algorithm_config,
+    )?;
+
+    assert_eq!(results.metrics.regularization_lambdas.len(), 5);
+    assert_eq!(results.metrics.dice_score_over_lambda.len(), 5);
+    assert!(results
+        .metrics
+        .loss_mse_over_lambda
+        .iter()
+        .all(|loss| loss.is_finite()));
+    Ok(())
+}
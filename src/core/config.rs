@@ -1,11 +1,17 @@
 pub mod algorithm;
 pub mod model;
 pub mod simulation;
+pub mod storage;
+pub mod validation;
 
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use toml::Value;
+use tracing::{debug, info};
 
-use self::{algorithm::Algorithm, simulation::Simulation};
+use self::{algorithm::Algorithm, simulation::Simulation, storage::Storage};
 
 /// Struct to hold the configuration for a simulation run.
 ///
@@ -14,10 +20,13 @@ use self::{algorithm::Algorithm, simulation::Simulation};
 /// - `measurement`: Path to the measurement data file.
 /// - `simulation`: Simulation parameters.
 /// - `algorithm`: Algorithm parameters.
+/// - `storage`: Compression and contents of the saved data and results.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Config {
     pub simulation: Simulation,
     pub algorithm: Algorithm,
+    #[serde(default)]
+    pub storage: Storage,
 }
 
 impl Default for Config {
@@ -28,7 +37,109 @@ impl Default for Config {
         Self {
             simulation: Simulation::default(),
             algorithm: Algorithm::default(),
+            storage: Storage::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Compares this config to another one and returns every parameter whose
+    /// value differs, sorted by its dotted path.
+    ///
+    /// Nested sections present in only one of the configs, like the
+    /// handcrafted and MRI model settings, are expanded into their
+    /// individual parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one of the configs could not be serialized.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn diff(&self, other: &Self) -> Result<Vec<FieldDiff>> {
+        debug!("Comparing configs");
+        let left = Value::try_from(self).context("Failed to serialize config")?;
+        let right = Value::try_from(other).context("Failed to serialize other config")?;
+        let mut diffs = Vec::new();
+        diff_values("", Some(&left), Some(&right), &mut diffs);
+        Ok(diffs)
+    }
+}
+
+/// A single parameter that differs between two configs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// Dotted path of the parameter, e.g. `algorithm.learning_rate`.
+    pub path: String,
+    /// Formatted value in the config `diff` was called on, `None` if unset.
+    pub left: Option<String>,
+    /// Formatted value in the other config, `None` if unset.
+    pub right: Option<String>,
+}
+
+/// Recursively collects the differences between two serialized config values.
+#[tracing::instrument(level = "trace", skip(diffs))]
+fn diff_values(
+    path: &str,
+    left: Option<&Value>,
+    right: Option<&Value>,
+    diffs: &mut Vec<FieldDiff>,
+) {
+    let left_table = left.and_then(Value::as_table);
+    let right_table = right.and_then(Value::as_table);
+    let expand = match (left, right) {
+        (Some(_), Some(_)) => left_table.is_some() && right_table.is_some(),
+        _ => left_table.is_some() || right_table.is_some(),
+    };
+    if expand {
+        let keys: BTreeSet<&String> = left_table
+            .into_iter()
+            .chain(right_table)
+            .flat_map(toml::Table::keys)
+            .collect();
+        for key in keys {
+            let key_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            diff_values(
+                &key_path,
+                left_table.and_then(|table| table.get(key)),
+                right_table.and_then(|table| table.get(key)),
+                diffs,
+            );
+        }
+    } else if left != right {
+        diffs.push(FieldDiff {
+            path: path.to_string(),
+            left: left.map(format_value),
+            right: right.map(format_value),
+        });
+    }
+}
+
+/// Formats a config value for display. Floats that were serialized from
+/// `f32` are printed with `f32` precision.
+#[allow(clippy::cast_possible_truncation, clippy::float_cmp)]
+#[tracing::instrument(level = "trace")]
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Float(float) => {
+            let single = *float as f32;
+            if f64::from(single) == *float {
+                single.to_string()
+            } else {
+                float.to_string()
+            }
         }
+        Value::Array(array) => format!(
+            "[{}]",
+            array
+                .iter()
+                .map(format_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => value.to_string(),
     }
 }
 
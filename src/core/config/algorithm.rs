@@ -1,8 +1,13 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use super::model::Model;
-use crate::core::algorithm::refinement::Optimizer;
+use super::model::{Model, PathologyRegion};
+use crate::core::{
+    algorithm::refinement::{loss::WeightedLossTerm, Optimizer},
+    model::spatial::voxels::VoxelType,
+};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
 #[allow(clippy::module_name_repetitions)]
@@ -11,6 +16,22 @@ pub enum AlgorithmType {
     ModelBased,
     ModelBasedGPU,
     PseudoInverse,
+    KalmanFilter,
+    MinimumNorm,
+    SLoreta,
+    ELoreta,
+    // only simulates the data, e.g. to generate synthetic datasets, and
+    // skips the estimation
+    None,
+}
+
+/// How the regularization strength of the inverse solvers is chosen.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub enum RegularizationSelection {
+    #[default]
+    GeneralizedCrossValidation,
+    LCurve,
+    Fixed,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
@@ -20,6 +41,28 @@ pub enum APDerivative {
     Textbook,
 }
 
+/// Storage precision of the system states and gains of the GPU algorithm.
+///
+/// `Half` stores both as 16 bit floats and converts them to single precision
+/// on load, so that all arithmetic and accumulation stays in single
+/// precision. This halves their memory and bandwidth at the cost of about
+/// three significant digits.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum GpuPrecision {
+    #[default]
+    Single,
+    Half,
+}
+
+/// Compute API of the GPU algorithm. `Wgpu` runs on Vulkan, Metal and DX12
+/// where no `OpenCL` runtime is available.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum GpuBackend {
+    #[default]
+    OpenCl,
+    Wgpu,
+}
+
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct Algorithm {
@@ -32,6 +75,11 @@ pub struct Algorithm {
     #[serde(default)]
     pub batch_size: usize,
     pub snapshots_interval: usize,
+    // the results are autosaved every this many epochs, so an interrupted
+    // scenario can be resumed. zero disables autosave. only respected by the
+    // model-based CPU algorithm.
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval: usize,
     pub learning_rate: f32,
     #[serde(default)]
     pub learning_rate_reduction_factor: f32,
@@ -50,11 +98,88 @@ pub struct Algorithm {
     pub difference_regularization_strength: f32,
     #[serde(default)]
     pub smoothness_regularization_strength: f32,
+    // further weighted loss terms of the model-based cpu algorithm
+    #[serde(default)]
+    pub loss_terms: Vec<WeightedLossTerm>,
     #[serde(default)]
     pub freeze_gains: bool,
     pub freeze_delays: bool,
+    // refractory times of the re-entrant connections. only learned by the
+    // model-based CPU algorithm.
+    #[serde(default = "default_freeze_refractory")]
+    pub freeze_refractory: bool,
+    // overrides freeze_gains and freeze_delays from the start epoch of each
+    // stage on. only respected by the model-based CPU algorithm.
+    #[serde(default)]
+    pub freeze_schedule: Vec<FreezeStage>,
     #[serde(default)]
     pub ap_derivative: APDerivative,
+    #[serde(default)]
+    pub preprocessing: Preprocessing,
+    // process and measurement noise of the kalman filter, both used as
+    // scaled identity matrices
+    #[serde(default = "default_kalman_process_covariance")]
+    pub kalman_process_covariance: f32,
+    #[serde(default = "default_kalman_measurement_covariance")]
+    pub kalman_measurement_covariance: f32,
+    // regularization of the minimum-norm type inverse solvers, relative to
+    // the mean eigenvalue of the gram matrix. the strength is only used if
+    // the selection is fixed.
+    #[serde(default)]
+    pub regularization_selection: RegularizationSelection,
+    #[serde(default = "default_regularization_strength")]
+    pub regularization_strength: f32,
+    #[serde(default)]
+    pub regularization_path: RegularizationPath,
+    #[serde(default)]
+    pub ensemble: Ensemble,
+    // starting point of the all-pass parameters of the model-based
+    // algorithms
+    #[serde(default)]
+    pub initialization: Initialization,
+    #[serde(default)]
+    pub coarse_to_fine: CoarseToFine,
+    // voxels whose gains and delays are optimized by the model-based
+    // algorithms, all other voxels keep their model values
+    #[serde(default)]
+    pub optimization_region: OptimizationRegion,
+    // parameters that are not optimized in voxels of the given types
+    #[serde(default)]
+    pub frozen_voxel_types: BTreeMap<VoxelType, FrozenParameters>,
+    // estimate the standard deviations of the all-pass parameters after
+    // convergence. only used by the model-based algorithms.
+    #[serde(default)]
+    pub parameter_confidence: bool,
+    // calculate the singular values and the condition number of the
+    // measurement matrix of the algorithm model before running
+    #[serde(default)]
+    pub measurement_diagnostics: bool,
+    // storage precision of the states and gains. only used by the
+    // model-based GPU algorithm.
+    #[serde(default)]
+    pub gpu_precision: GpuPrecision,
+    #[serde(default)]
+    pub gpu_backend: GpuBackend,
+    // threads used for the cpu derivative loops, zero uses the global pool
+    // with one thread per core
+    #[serde(default)]
+    pub number_of_threads: usize,
+    // store the per-step losses of every epoch instead of only the
+    // per-batch aggregates
+    #[serde(default)]
+    pub keep_step_metrics: bool,
+    // hard bounds on the parameters of the model-based cpu algorithm
+    #[serde(default)]
+    pub physiological_bounds: PhysiologicalBounds,
+    // all-pass parameters recorded after every epoch
+    #[serde(default)]
+    pub parameter_recording: ParameterRecording,
+    // exclude the faulty channels of the simulation from the residuals
+    #[serde(default)]
+    pub mask_bad_channels: bool,
+    // indices of additional channels excluded from the residuals
+    #[serde(default)]
+    pub excluded_channels: Vec<usize>,
 }
 impl Default for Algorithm {
     /// Returns a default `Algorithm` configuration with reasonable defaults for most use cases.
@@ -67,6 +192,7 @@ impl Default for Algorithm {
             epochs: 10,
             batch_size: 0,
             snapshots_interval: 0,
+            autosave_interval: default_autosave_interval(),
             learning_rate: 200.0,
             learning_rate_reduction_factor: 0.0,
             learning_rate_reduction_interval: 0,
@@ -76,10 +202,531 @@ impl Default for Algorithm {
             maximum_regularization_threshold: 1.01,
             difference_regularization_strength: 0.0,
             smoothness_regularization_strength: 0.0,
+            loss_terms: Vec::new(),
             model: Model::default(),
             freeze_gains: false,
             freeze_delays: true,
+            freeze_refractory: default_freeze_refractory(),
+            freeze_schedule: Vec::new(),
             ap_derivative: APDerivative::default(),
+            preprocessing: Preprocessing::default(),
+            kalman_process_covariance: default_kalman_process_covariance(),
+            kalman_measurement_covariance: default_kalman_measurement_covariance(),
+            regularization_selection: RegularizationSelection::default(),
+            regularization_strength: default_regularization_strength(),
+            regularization_path: RegularizationPath::default(),
+            ensemble: Ensemble::default(),
+            initialization: Initialization::default(),
+            coarse_to_fine: CoarseToFine::default(),
+            optimization_region: OptimizationRegion::default(),
+            frozen_voxel_types: BTreeMap::new(),
+            parameter_confidence: false,
+            measurement_diagnostics: false,
+            gpu_precision: GpuPrecision::default(),
+            gpu_backend: GpuBackend::default(),
+            number_of_threads: 0,
+            keep_step_metrics: false,
+            physiological_bounds: PhysiologicalBounds::default(),
+            parameter_recording: ParameterRecording::default(),
+            mask_bad_channels: false,
+            excluded_channels: Vec::new(),
         }
     }
 }
+
+#[tracing::instrument(level = "trace")]
+fn default_kalman_process_covariance() -> f32 {
+    1e-2
+}
+
+#[tracing::instrument(level = "trace")]
+fn default_kalman_measurement_covariance() -> f32 {
+    1e-3
+}
+
+#[tracing::instrument(level = "trace")]
+fn default_regularization_strength() -> f32 {
+    1e-2
+}
+
+#[tracing::instrument(level = "trace")]
+const fn default_freeze_refractory() -> bool {
+    true
+}
+
+#[tracing::instrument(level = "trace")]
+const fn default_autosave_interval() -> usize {
+    100
+}
+
+impl Algorithm {
+    /// Returns the freeze stage active at the given epoch, i.e. the stage with
+    /// the latest start epoch that is not after the given epoch.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn freeze_stage_at(&self, epoch: usize) -> Option<&FreezeStage> {
+        self.freeze_schedule
+            .iter()
+            .filter(|stage| stage.start_epoch <= epoch)
+            .max_by_key(|stage| stage.start_epoch)
+    }
+
+    /// Returns all parameters of the recommended ranges that apply to this
+    /// configuration but lie outside of their range, together with their
+    /// current value.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn out_of_range_parameters(&self) -> Vec<(&'static RecommendedRange, f32)> {
+        RECOMMENDED_RANGES
+            .iter()
+            .filter_map(|range| {
+                (range.value)(self)
+                    .filter(|value| !range.contains(*value))
+                    .map(|value| (range, value))
+            })
+            .collect()
+    }
+}
+
+/// A stage of the freeze schedule.
+///
+/// From `start_epoch` on, the given parameter groups are frozen and the
+/// learning rate is multiplied by `learning_rate_multiplier`, until the next
+/// stage starts.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct FreezeStage {
+    pub start_epoch: usize,
+    pub freeze_gains: bool,
+    pub freeze_delays: bool,
+    pub learning_rate_multiplier: f32,
+}
+
+/// Filters applied to the measurements before the estimation.
+///
+/// The band-pass is a Butterworth filter of the given order per edge, the
+/// notch a second order IIR notch with the given quality factor. Both are
+/// applied forward and backward, so they do not shift the signals in time.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Preprocessing {
+    pub band_pass: bool,
+    pub band_pass_low_hz: f32,
+    pub band_pass_high_hz: f32,
+    pub band_pass_order: usize,
+    pub notch: bool,
+    pub notch_frequency_hz: f32,
+    pub notch_quality_factor: f32,
+    #[serde(default)]
+    pub beat_detection: BeatDetection,
+}
+
+impl Default for Preprocessing {
+    /// Returns a default `Preprocessing` with both filters disabled.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default preprocessing");
+        Self {
+            band_pass: false,
+            band_pass_low_hz: 0.5,
+            band_pass_high_hz: 100.0,
+            band_pass_order: 4,
+            notch: false,
+            notch_frequency_hz: 50.0,
+            notch_quality_factor: 30.0,
+            beat_detection: BeatDetection::default(),
+        }
+    }
+}
+
+/// Segmentation of the measurements into beats, applied after filtering.
+///
+/// Triggers are detected as peaks of the absolute value of the trigger
+/// channel, or of the root mean square over all channels if none is given,
+/// that exceed the threshold relative to the largest peak. Beats are windows
+/// of the number of steps of the model, starting the pre-trigger time before
+/// their trigger.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(default)]
+pub struct BeatDetection {
+    pub enabled: bool,
+    pub trigger_channel: Option<usize>,
+    pub threshold: f32,
+    pub refractory_ms: f32,
+    pub pre_trigger_ms: f32,
+    // replace every beat of the measurements with the average of all
+    // detected beats
+    pub average: bool,
+}
+
+impl Default for BeatDetection {
+    /// Returns a default `BeatDetection` that is disabled and averages once
+    /// enabled.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default beat detection");
+        Self {
+            enabled: false,
+            trigger_channel: None,
+            threshold: 0.6,
+            refractory_ms: 250.0,
+            pre_trigger_ms: 100.0,
+            average: true,
+        }
+    }
+}
+
+/// Tikhonov regularization parameters evaluated by the pseudo inverse in
+/// addition to its regular solution.
+///
+/// The `number_of_lambdas` parameters are log-spaced between `min_lambda`
+/// and `max_lambda`, both absolute values added to the squared singular
+/// values. The sweep is disabled if `number_of_lambdas` is zero.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct RegularizationPath {
+    pub number_of_lambdas: usize,
+    pub min_lambda: f32,
+    pub max_lambda: f32,
+}
+
+impl Default for RegularizationPath {
+    /// Returns a default `RegularizationPath` that is disabled and spans
+    /// `1e-6` to `1e2` once enabled.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default regularization path");
+        Self {
+            number_of_lambdas: 0,
+            min_lambda: 1e-6,
+            max_lambda: 1e2,
+        }
+    }
+}
+
+/// Additional estimations with perturbed starting points used to quantify
+/// the uncertainty of the solution.
+///
+/// Each of the `number_of_members` members starts from gains multiplied by
+/// a factor drawn around one with `initial_gain_std` and sees the
+/// measurements with additional white noise of `measurement_noise_std`. The
+/// ensemble is disabled if `number_of_members` is zero.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Ensemble {
+    pub number_of_members: usize,
+    pub initial_gain_std: f32,
+    pub measurement_noise_std: f32,
+    pub seed: u64,
+}
+
+impl Default for Ensemble {
+    /// Returns a default `Ensemble` that is disabled and perturbs the gains
+    /// by 10 % and the measurements by `1e-3` once enabled.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default ensemble");
+        Self {
+            number_of_members: 0,
+            initial_gain_std: 0.1,
+            measurement_noise_std: 1e-3,
+            seed: 0,
+        }
+    }
+}
+
+/// The starting point of the all-pass parameters.
+///
+/// `Model` uses the gains and delays derived from the model config.
+/// `FromScenario` uses the estimated gains and delays of the finished
+/// scenario with the given id, which has to use the same voxel grid and
+/// neighborhood radius.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub enum Initialization {
+    #[default]
+    Model,
+    FromScenario(String),
+}
+
+/// A preceding estimation on a coarsened voxel grid that initializes the
+/// all-pass parameters of the model-based algorithms.
+///
+/// The coarse grid uses voxels `coarsening_factor` times the size of the
+/// model voxels and runs for `coarse_epochs` epochs on the CPU. Its learned
+/// gains and delays are then upsampled onto the fine grid. The coarse stage
+/// is disabled if `coarse_epochs` is zero.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct CoarseToFine {
+    pub coarse_epochs: usize,
+    pub coarsening_factor: f32,
+}
+
+impl Default for CoarseToFine {
+    /// Returns a default `CoarseToFine` that is disabled and doubles the
+    /// voxel size once enabled.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default coarse to fine settings");
+        Self {
+            coarse_epochs: 0,
+            coarsening_factor: 2.0,
+        }
+    }
+}
+
+/// Hard bounds on the all-pass parameters, enforced after every update.
+///
+/// The velocity bounds translate into bounds on the delay of every
+/// connection based on the distance between the connected voxels. A bound of
+/// zero is disabled.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct PhysiologicalBounds {
+    #[serde(default)]
+    pub min_velocity_m_per_s: f32,
+    #[serde(default)]
+    pub max_velocity_m_per_s: f32,
+    // cap on the magnitude of the gains
+    #[serde(default)]
+    pub max_gain: f32,
+}
+
+impl PhysiologicalBounds {
+    /// Returns true if any bound is set.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn is_enabled(&self) -> bool {
+        self.min_velocity_m_per_s > 0.0 || self.max_velocity_m_per_s > 0.0 || self.max_gain > 0.0
+    }
+}
+
+/// The all-pass parameters recorded after every epoch of the model-based
+/// algorithms.
+///
+/// `statistics` records the mean and standard deviation of the gains and
+/// delays of all connections. `voxels` lists the voxels whose average delay
+/// is recorded. Nothing is recorded if both are unset.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct ParameterRecording {
+    #[serde(default)]
+    pub statistics: bool,
+    #[serde(default)]
+    pub voxels: Vec<usize>,
+}
+
+impl ParameterRecording {
+    /// Returns true if any parameter is recorded.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn is_enabled(&self) -> bool {
+        self.statistics || !self.voxels.is_empty()
+    }
+}
+
+/// The voxels whose all-pass parameters are optimized.
+///
+/// `Cuboid` selects the voxels of a cuboid given in percent of the voxel grid
+/// along each axis, the current factor of the region is ignored.
+/// `NiftiLabel` selects the voxels whose center lies on the given label of a
+/// NIfTI volume on the grid of the MRI scan of the model.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub enum OptimizationRegion {
+    #[default]
+    All,
+    Cuboid(PathologyRegion),
+    NiftiLabel {
+        path: PathBuf,
+        label: usize,
+    },
+}
+
+/// The parameter groups that stay at their model values in the voxels of a
+/// type, in addition to the global `freeze_gains` and `freeze_delays`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FrozenParameters {
+    pub gains: bool,
+    pub delays: bool,
+}
+
+/// Named starting points for the hyperparameters of the model-based
+/// algorithms.
+///
+/// Applying a preset only changes the optimization parameters, the model and
+/// preprocessing settings are kept.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum AlgorithmPreset {
+    /// Small SGD steps with a decaying learning rate and frozen delays.
+    Conservative,
+    /// Adam on mini-batches, learning gains and delays at the same time.
+    Aggressive,
+    /// Long SGD runs on the GPU for models with many voxels.
+    GpuLargeModel,
+}
+
+impl AlgorithmPreset {
+    pub const ALL: [Self; 3] = [Self::Conservative, Self::Aggressive, Self::GpuLargeModel];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Conservative => "Conservative",
+            Self::Aggressive => "Aggressive",
+            Self::GpuLargeModel => "GPU large-model",
+        }
+    }
+
+    /// Overwrites the optimization parameters of the given config with the
+    /// values of this preset.
+    #[tracing::instrument(level = "debug", skip(algorithm))]
+    pub fn apply(self, algorithm: &mut Algorithm) {
+        debug!("Applying algorithm preset {}", self.name());
+        match self {
+            Self::Conservative => {
+                algorithm.algorithm_type = AlgorithmType::ModelBased;
+                algorithm.optimizer = Optimizer::Sgd;
+                algorithm.epochs = 5_000;
+                algorithm.batch_size = 0;
+                algorithm.learning_rate = 50.0;
+                algorithm.learning_rate_reduction_factor = 0.5;
+                algorithm.learning_rate_reduction_interval = 1_000;
+                algorithm.maximum_regularization_strength = 1.0;
+                algorithm.maximum_regularization_threshold = 1.01;
+                algorithm.freeze_gains = false;
+                algorithm.freeze_delays = true;
+            }
+            Self::Aggressive => {
+                algorithm.algorithm_type = AlgorithmType::ModelBased;
+                algorithm.optimizer = Optimizer::Adam;
+                algorithm.epochs = 1_000;
+                algorithm.batch_size = 100;
+                algorithm.learning_rate = 1e-2;
+                algorithm.learning_rate_reduction_factor = 0.0;
+                algorithm.learning_rate_reduction_interval = 0;
+                algorithm.maximum_regularization_strength = 10.0;
+                algorithm.maximum_regularization_threshold = 1.01;
+                algorithm.freeze_gains = false;
+                algorithm.freeze_delays = false;
+            }
+            Self::GpuLargeModel => {
+                algorithm.algorithm_type = AlgorithmType::ModelBasedGPU;
+                algorithm.optimizer = Optimizer::Sgd;
+                algorithm.epochs = 20_000;
+                algorithm.batch_size = 0;
+                algorithm.snapshots_interval = 0;
+                algorithm.learning_rate = 200.0;
+                algorithm.learning_rate_reduction_factor = 0.0;
+                algorithm.learning_rate_reduction_interval = 0;
+                algorithm.maximum_regularization_strength = 1.0;
+                algorithm.maximum_regularization_threshold = 1.01;
+                algorithm.freeze_gains = false;
+                algorithm.freeze_delays = true;
+            }
+        }
+    }
+}
+
+/// Range of values of a hyperparameter that has been validated on the
+/// handcrafted and MRI models.
+///
+/// `value` returns `None` if the parameter is not used by the config, e.g.
+/// the Kalman covariances for the model-based algorithm.
+#[derive(Debug)]
+pub struct RecommendedRange {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub value: fn(&Algorithm) -> Option<f32>,
+}
+
+impl RecommendedRange {
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+const fn is_model_based(algorithm: &Algorithm) -> bool {
+    matches!(
+        algorithm.algorithm_type,
+        AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU
+    )
+}
+
+/// Recommended ranges of the algorithm hyperparameters.
+#[allow(clippy::cast_precision_loss)]
+pub const RECOMMENDED_RANGES: [RecommendedRange; 9] = [
+    RecommendedRange {
+        name: "Learning rate (SGD)",
+        min: 1.0,
+        max: 1_000.0,
+        value: |algorithm| {
+            (is_model_based(algorithm) && algorithm.optimizer == Optimizer::Sgd)
+                .then_some(algorithm.learning_rate)
+        },
+    },
+    RecommendedRange {
+        name: "Learning rate (Adam)",
+        min: 1e-4,
+        max: 1e-1,
+        value: |algorithm| {
+            (is_model_based(algorithm) && algorithm.optimizer == Optimizer::Adam)
+                .then_some(algorithm.learning_rate)
+        },
+    },
+    RecommendedRange {
+        name: "Epochs",
+        min: 1.0,
+        max: 100_000.0,
+        value: |algorithm| is_model_based(algorithm).then_some(algorithm.epochs as f32),
+    },
+    RecommendedRange {
+        name: "Learning rate reduction factor",
+        min: 0.0,
+        max: 1.0,
+        value: |algorithm| {
+            is_model_based(algorithm).then_some(algorithm.learning_rate_reduction_factor)
+        },
+    },
+    RecommendedRange {
+        name: "Regularization threshold",
+        min: 1.0,
+        max: 1.5,
+        value: |algorithm| {
+            is_model_based(algorithm).then_some(algorithm.maximum_regularization_threshold)
+        },
+    },
+    RecommendedRange {
+        name: "Regularization strength",
+        min: 0.0,
+        max: 100.0,
+        value: |algorithm| {
+            is_model_based(algorithm).then_some(algorithm.maximum_regularization_strength)
+        },
+    },
+    RecommendedRange {
+        name: "Kalman process covariance",
+        min: 1e-6,
+        max: 1.0,
+        value: |algorithm| {
+            (algorithm.algorithm_type == AlgorithmType::KalmanFilter)
+                .then_some(algorithm.kalman_process_covariance)
+        },
+    },
+    RecommendedRange {
+        name: "Kalman measurement covariance",
+        min: 1e-6,
+        max: 1.0,
+        value: |algorithm| {
+            (algorithm.algorithm_type == AlgorithmType::KalmanFilter)
+                .then_some(algorithm.kalman_measurement_covariance)
+        },
+    },
+    RecommendedRange {
+        name: "Inverse regularization strength",
+        min: 1e-6,
+        max: 1.0,
+        value: |algorithm| {
+            (matches!(
+                algorithm.algorithm_type,
+                AlgorithmType::MinimumNorm | AlgorithmType::SLoreta | AlgorithmType::ELoreta
+            ) && algorithm.regularization_selection == RegularizationSelection::Fixed)
+                .then_some(algorithm.regularization_strength)
+        },
+    },
+];
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    f32::consts::PI,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use tracing::debug;
@@ -12,6 +15,51 @@ pub struct Model {
     pub mri: Option<Mri>,
 }
 
+impl Model {
+    /// Returns the factor by which the current densities are reduced in the
+    /// pathological voxel at the given index of a grid with the given number
+    /// of voxels per dimension.
+    ///
+    /// Uses the factor of the first pathology region containing the voxel and
+    /// falls back to the common current factor in pathology.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn current_factor_in_pathology(
+        &self,
+        index: [usize; 3],
+        voxels_in_dims: [usize; 3],
+    ) -> f32 {
+        self.handcrafted
+            .as_ref()
+            .and_then(|handcrafted| {
+                handcrafted
+                    .pathology_regions
+                    .iter()
+                    .find(|region| region.contains(index, voxels_in_dims))
+            })
+            .and_then(|region| region.current_factor)
+            .unwrap_or(self.common.current_factor_in_pathology)
+    }
+
+    /// Returns the factor by which the current densities are reduced in a
+    /// voxel of the given type at the given index. Healthy tissue has a
+    /// factor of one.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn current_factor(
+        &self,
+        voxel_type: VoxelType,
+        index: [usize; 3],
+        voxels_in_dims: [usize; 3],
+    ) -> f32 {
+        match voxel_type {
+            VoxelType::Pathological => self.current_factor_in_pathology(index, voxels_in_dims),
+            VoxelType::BorderZone => self.common.current_factor_in_border_zone,
+            _ => 1.0,
+        }
+    }
+}
+
 impl Default for Model {
     #[tracing::instrument(level = "debug")]
     fn default() -> Self {
@@ -36,16 +84,39 @@ pub struct Handcrafted {
     pub heart_size_mm: [f32; 3],
     pub sa_x_center_percentage: f32,
     pub sa_y_center_percentage: f32,
+    // extent of the sinoatrial node along z, start and stop voxels are
+    // included. same for the atrioventricular node and hps below.
+    #[serde(default)]
+    pub sa_z_start_percentage: f32,
+    #[serde(default = "default_z_stop_percentage")]
+    pub sa_z_stop_percentage: f32,
     pub atrium_y_start_percentage: f32,
     pub av_x_center_percentage: f32,
+    #[serde(default)]
+    pub av_z_start_percentage: f32,
+    #[serde(default = "default_z_stop_percentage")]
+    pub av_z_stop_percentage: f32,
     pub hps_y_stop_percentage: f32,
     pub hps_x_start_percentage: f32,
     pub hps_x_stop_percentage: f32,
     pub hps_y_up_percentage: f32,
-    pub pathology_x_start_percentage: f32,
-    pub pathology_x_stop_percentage: f32,
-    pub pathology_y_start_percentage: f32,
-    pub pathology_y_stop_percentage: f32,
+    #[serde(default)]
+    pub hps_z_start_percentage: f32,
+    #[serde(default = "default_z_stop_percentage")]
+    pub hps_z_stop_percentage: f32,
+    // if set, the hps only lines the lowest z layer, the endocardium, and
+    // the ventricular wall above it is excited transmurally.
+    // overrides the z extent of the hps.
+    #[serde(default)]
+    pub layered_ventricle_wall: bool,
+    // cuboid regions of pathological tissue, placed if the model is
+    // pathological. voxels inside several regions use the first one.
+    #[serde(default = "default_pathology_regions")]
+    pub pathology_regions: Vec<PathologyRegion>,
+    // width of the border zone placed around the pathology regions in
+    // voxels. zero disables the border zone.
+    #[serde(default)]
+    pub border_zone_width_voxels: usize,
     pub include_atrium: bool,
     pub include_av: bool,
     pub include_hps: bool,
@@ -72,16 +143,21 @@ impl Default for Handcrafted {
             heart_size_mm: [65.0, 92.5, 2.5],
             sa_x_center_percentage: 0.2,
             sa_y_center_percentage: 0.85,
+            sa_z_start_percentage: 0.0,
+            sa_z_stop_percentage: default_z_stop_percentage(),
             atrium_y_start_percentage: 0.7,
             av_x_center_percentage: 0.5,
+            av_z_start_percentage: 0.0,
+            av_z_stop_percentage: default_z_stop_percentage(),
             hps_y_stop_percentage: 0.15,
             hps_x_start_percentage: 0.2,
             hps_x_stop_percentage: 0.8,
             hps_y_up_percentage: 0.5,
-            pathology_x_start_percentage: 0.0,
-            pathology_x_stop_percentage: 0.2,
-            pathology_y_start_percentage: 0.5,
-            pathology_y_stop_percentage: 0.7,
+            hps_z_start_percentage: 0.0,
+            hps_z_stop_percentage: default_z_stop_percentage(),
+            layered_ventricle_wall: false,
+            pathology_regions: default_pathology_regions(),
+            border_zone_width_voxels: 0,
             include_atrium: true,
             include_av: true,
             include_hps: true,
@@ -89,6 +165,104 @@ impl Default for Handcrafted {
     }
 }
 
+#[tracing::instrument(level = "trace")]
+fn default_pathology_regions() -> Vec<PathologyRegion> {
+    vec![PathologyRegion::default()]
+}
+
+/// A cuboid of pathological tissue in a handcrafted model. The extent is
+/// given in percent of the heart size along each axis, start and stop
+/// voxels are included.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PathologyRegion {
+    pub x_start_percentage: f32,
+    pub x_stop_percentage: f32,
+    pub y_start_percentage: f32,
+    pub y_stop_percentage: f32,
+    #[serde(default)]
+    pub z_start_percentage: f32,
+    #[serde(default = "default_z_stop_percentage")]
+    pub z_stop_percentage: f32,
+    // factor by which the current densities in the region are reduced.
+    // falls back to the common current factor in pathology if not set.
+    #[serde(default)]
+    pub current_factor: Option<f32>,
+}
+
+const fn default_z_stop_percentage() -> f32 {
+    1.0
+}
+
+impl Default for PathologyRegion {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default pathology region");
+        Self {
+            x_start_percentage: 0.0,
+            x_stop_percentage: 0.2,
+            y_start_percentage: 0.5,
+            y_stop_percentage: 0.7,
+            z_start_percentage: 0.0,
+            z_stop_percentage: default_z_stop_percentage(),
+            current_factor: None,
+        }
+    }
+}
+
+impl PathologyRegion {
+    /// Returns true if the voxel at the given index of a grid with the given
+    /// number of voxels per dimension lies inside the region.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn contains(&self, index: [usize; 3], voxels_in_dims: [usize; 3]) -> bool {
+        self.contains_with_margin(index, voxels_in_dims, 0)
+    }
+
+    /// Returns true if the voxel at the given index lies inside the region
+    /// grown by the given number of voxels along each axis.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[tracing::instrument(level = "trace")]
+    pub fn contains_with_margin(
+        &self,
+        index: [usize; 3],
+        voxels_in_dims: [usize; 3],
+        margin_voxels: usize,
+    ) -> bool {
+        let percentages = [
+            (self.x_start_percentage, self.x_stop_percentage),
+            (self.y_start_percentage, self.y_stop_percentage),
+            (self.z_start_percentage, self.z_stop_percentage),
+        ];
+        index.into_iter().zip(voxels_in_dims).zip(percentages).all(
+            |((index, voxels), (start, stop))| {
+                let start_index = ((voxels as f32 * start) as usize).saturating_sub(margin_voxels);
+                let stop_index = (voxels as f32 * stop) as usize + margin_voxels;
+                start_index <= index && index <= stop_index
+            },
+        )
+    }
+}
+
+/// Returns true if the index lies between the start and stop percentages of
+/// an axis with the given number of voxels, both ends included.
+#[must_use]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+#[tracing::instrument(level = "trace")]
+pub fn percentage_range_contains(index: usize, voxels: usize, start: f32, stop: f32) -> bool {
+    let start_index = (voxels as f32 * start) as usize;
+    let stop_index = (voxels as f32 * stop) as usize;
+    start_index <= index && index <= stop_index
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub struct Mri {
     pub path: PathBuf,
@@ -105,11 +279,18 @@ impl Default for Mri {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub enum ControlFunction {
     Ohara,
     Triangle,
     Ramp,
+    /// One beat of a sampled waveform at 60 bpm, read from a .npy file or
+    /// a .csv file with one value per row, and resampled to the sample rate
+    /// of the scenario.
+    FromFile {
+        path: PathBuf,
+        sample_rate_hz: f32,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -117,12 +298,180 @@ pub enum SensorArrayGeometry {
     Cube,
     SparseCube,
     Cylinder,
+    /// Sensor positions in mm read from a .npy file of shape (positions, 3),
+    /// e.g. an array designed with the sensor optimization. The origin is
+    /// ignored and the orientations alternate between the axes as for the
+    /// cube.
+    FromFile {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub enum SensorType {
+    // one magnetometer per sensor position, the axes alternate between
+    // x, y and z
+    SingleAxis,
+    // three magnetometers along x, y and z per sensor position
+    ThreeAxis,
+    // three first-order axial gradiometers along x, y and z per sensor
+    // position, each measuring the difference to a second coil shifted by
+    // the baseline along its axis
+    Gradiometer { baseline_mm: f32 },
+}
+
+impl SensorType {
+    /// Returns the number of sensors placed at each sensor position.
+    #[must_use]
+    pub const fn sensors_per_position(self) -> usize {
+        match self {
+            Self::SingleAxis => 1,
+            Self::ThreeAxis | Self::Gradiometer { .. } => 3,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum SensorArrayModality {
+    // magnetometers only
+    Magnetic,
+    // one electric potential electrode per sensor position
+    Electric,
+    // magnetometers plus one electrode per sensor position
+    Mixed,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum SensorArrayMotion {
     Static,
     Grid,
+    // continuous motion along the sensor array trajectory during a single
+    // recording
+    Trajectory,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum SensorArrayTrajectory {
+    // circular orbit of the array around its origin in the x-y plane, the
+    // measurement matrix is calculated at evenly spaced keyframes
+    Orbit {
+        radius_mm: f32,
+        period_s: f32,
+        number_of_keyframes: usize,
+    },
+    // piecewise linear path through the waypoints, which are used as
+    // keyframes of the measurement matrix
+    Waypoints {
+        waypoints: Vec<Waypoint>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Waypoint {
+    pub time_s: f32,
+    pub offset_mm: [f32; 3],
+}
+
+impl Default for SensorArrayTrajectory {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default sensor array trajectory");
+        Self::Orbit {
+            radius_mm: 20.0,
+            period_s: 1.0,
+            number_of_keyframes: 8,
+        }
+    }
+}
+
+impl SensorArrayTrajectory {
+    /// Returns the offsets of the sensor array at the keyframes of the
+    /// trajectory. Contains at least one keyframe.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace")]
+    pub fn keyframe_offsets_mm(&self) -> Vec<[f32; 3]> {
+        match self {
+            Self::Orbit {
+                radius_mm,
+                number_of_keyframes,
+                ..
+            } => {
+                let number_of_keyframes = (*number_of_keyframes).max(1);
+                (0..number_of_keyframes)
+                    .map(|keyframe| {
+                        let angle_rad = 2.0 * PI * keyframe as f32 / number_of_keyframes as f32;
+                        let (sin, cos) = angle_rad.sin_cos();
+                        [radius_mm * cos, radius_mm * sin, 0.0]
+                    })
+                    .collect()
+            }
+            Self::Waypoints { waypoints } => {
+                if waypoints.is_empty() {
+                    vec![[0.0; 3]]
+                } else {
+                    waypoints
+                        .iter()
+                        .map(|waypoint| waypoint.offset_mm)
+                        .collect()
+                }
+            }
+        }
+    }
+
+    /// Returns the two keyframes enclosing the array position at the given
+    /// time together with the weight of the second keyframe.
+    ///
+    /// The orbit repeats after each period. Along the waypoints, which have
+    /// to be sorted by time, the array rests at the first waypoint before its
+    /// time and at the last waypoint after its time.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    #[tracing::instrument(level = "trace")]
+    pub fn keyframe_weights(&self, time_s: f32) -> (usize, usize, f32) {
+        match self {
+            Self::Orbit {
+                period_s,
+                number_of_keyframes,
+                ..
+            } => {
+                let number_of_keyframes = (*number_of_keyframes).max(1);
+                if *period_s <= 0.0 {
+                    return (0, 0, 0.0);
+                }
+                let position = (time_s / period_s).rem_euclid(1.0) * number_of_keyframes as f32;
+                let lower = (position.floor() as usize).min(number_of_keyframes - 1);
+                let upper = (lower + 1) % number_of_keyframes;
+                (lower, upper, position - lower as f32)
+            }
+            Self::Waypoints { waypoints } => {
+                let Some(first) = waypoints.first() else {
+                    return (0, 0, 0.0);
+                };
+                if time_s <= first.time_s {
+                    return (0, 0, 0.0);
+                }
+                for (index, segment) in waypoints.windows(2).enumerate() {
+                    let (start, end) = (&segment[0], &segment[1]);
+                    if time_s < end.time_s {
+                        let duration_s = end.time_s - start.time_s;
+                        let weight = if duration_s > 0.0 {
+                            (time_s - start.time_s) / duration_s
+                        } else {
+                            1.0
+                        };
+                        return (index, index + 1, weight);
+                    }
+                }
+                let last = waypoints.len() - 1;
+                (last, last, 0.0)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -133,6 +482,12 @@ pub struct PropagationVelocitiesMPerS {
     pub hps: f32,
     pub ventricle: f32,
     pub pathological: f32,
+    #[serde(default = "default_border_zone_velocity")]
+    pub border_zone: f32,
+}
+
+const fn default_border_zone_velocity() -> f32 {
+    0.4
 }
 
 impl PropagationVelocitiesMPerS {
@@ -145,6 +500,7 @@ impl PropagationVelocitiesMPerS {
             VoxelType::HPS => self.hps,
             VoxelType::Ventricle => self.ventricle,
             VoxelType::Pathological => self.pathological,
+            VoxelType::BorderZone => self.border_zone,
             VoxelType::None | VoxelType::Vessel | VoxelType::Torso | VoxelType::Chamber => 0.0,
         }
     }
@@ -161,6 +517,7 @@ impl Default for PropagationVelocitiesMPerS {
             hps: 4.5,
             ventricle: 1.1,
             pathological: 0.1,
+            border_zone: default_border_zone_velocity(),
         }
     }
 }
@@ -171,14 +528,20 @@ pub struct Common {
     pub pathological: bool,
     pub sensor_array_geometry: SensorArrayGeometry,
     pub sensor_array_motion: SensorArrayMotion,
-    pub three_d_sensors: bool,            // used for both kinds
+    pub sensor_array_origin_mm: [f32; 3], // used for both kinds
     pub number_of_sensors: usize,         // used for cylinder and sparse cube
     pub sensor_array_radius_mm: f32,      // used for cylinder only
     pub sensors_per_axis: [usize; 3],     // used for cube only
     pub sensor_array_size_mm: [f32; 3],   // used for cube only
-    pub sensor_array_origin_mm: [f32; 3], // used for both kinds
     pub sensor_array_motion_range_mm: [f32; 3],
     pub sensor_array_motion_steps: [usize; 3],
+    #[serde(default)]
+    pub sensor_array_trajectory: SensorArrayTrajectory,
+    // used for both kinds
+    #[serde(default = "default_sensor_type")]
+    pub sensor_type: SensorType,
+    #[serde(default = "default_sensor_array_modality")]
+    pub sensor_array_modality: SensorArrayModality,
     pub voxel_size_mm: f32,
     pub heart_offset_mm: [f32; 3],
     pub measurement_covariance_mean: f32,
@@ -189,12 +552,158 @@ pub struct Common {
     pub measurement_covariance_std: f32,
     pub propagation_velocities: PropagationVelocitiesMPerS,
     pub current_factor_in_pathology: f32,
+    // factor by which the current densities in border zone voxels are
+    // reduced, between healthy (one) and pathological tissue.
+    #[serde(default = "default_current_factor_in_border_zone")]
+    pub current_factor_in_border_zone: f32,
+    // direction of the muscle fibers in each voxel. the propagation
+    // velocities apply along the fibers, across them they are scaled by the
+    // transverse velocity ratio. propagation is isotropic if not set.
+    #[serde(default)]
+    pub fiber_orientation: Option<FiberOrientation>,
+    #[serde(default = "default_transverse_velocity_ratio")]
+    pub transverse_velocity_ratio: f32,
+    // volume conductor used for the measurement matrix
+    #[serde(default)]
+    pub torso_model: TorsoModel,
+    // voxels up to this many voxels away along each axis are connected by
+    // all-pass filters. one gives the 26-voxel neighborhood, larger values
+    // allow fast conduction bundles to skip voxels on coarse grids.
+    #[serde(default = "default_neighborhood_radius")]
+    pub neighborhood_radius: usize,
+    // rate at which the control function repeats. the reference waveforms
+    // describe one beat at 60 bpm.
+    #[serde(default = "default_heart_rate_bpm")]
+    pub heart_rate_bpm: f32,
+    // duration of the action potential relative to the reference waveform
+    #[serde(default = "default_action_potential_duration_factor")]
+    pub action_potential_duration_factor: f32,
+    // additionally shortens the action potential at higher heart rates
+    // following bazett's square root relation
+    #[serde(default)]
+    pub action_potential_rate_adaptation: bool,
+    // additional sites stimulated by the control function besides the
+    // sinoatrial node, e.g. ectopic foci or pacemaker leads
+    #[serde(default)]
+    pub pacing_sites: Vec<PacingSite>,
+    // allows pathological and border zone voxels to be activated again by
+    // another neighbor once the refractory period has passed, so that the
+    // activation can circulate around the pathology
+    #[serde(default)]
+    pub reentry: bool,
+    #[serde(default = "default_refractory_period_ms")]
+    pub refractory_period_ms: f32,
+}
+
+const fn default_sensor_type() -> SensorType {
+    SensorType::ThreeAxis
+}
+
+const fn default_sensor_array_modality() -> SensorArrayModality {
+    SensorArrayModality::Magnetic
+}
+
+const fn default_neighborhood_radius() -> usize {
+    1
+}
+
+const fn default_heart_rate_bpm() -> f32 {
+    REFERENCE_HEART_RATE_BPM
+}
+
+const fn default_action_potential_duration_factor() -> f32 {
+    1.0
+}
+
+const fn default_refractory_period_ms() -> f32 {
+    250.0
+}
+
+const fn default_current_factor_in_border_zone() -> f32 {
+    0.5
+}
+
+const fn default_transverse_velocity_ratio() -> f32 {
+    0.4
+}
+
+/// Volume conductor model used to calculate the magnetic flux density at the
+/// sensors from the current densities in the voxels.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum TorsoModel {
+    /// Only the primary currents in an unbounded homogeneous conductor.
+    Unbounded,
+    /// A spherically symmetric torso around the given center. Includes the
+    /// field of the volume currents using Sarvas' formula, which does not
+    /// depend on the radius or conductivity of the sphere. All sensors have
+    /// to lie outside of the torso.
+    Spherical { center_mm: [f32; 3] },
+}
+
+impl Default for TorsoModel {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default torso model");
+        Self::Unbounded
+    }
+}
+
+/// A site stimulated by the control function in addition to the sinoatrial
+/// node, used for premature ventricular contractions or paced rhythms. The
+/// heart voxel closest to the position is stimulated, delayed by the onset.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PacingSite {
+    // position in the same coordinates as the voxels and sensors
+    pub position_mm: [f32; 3],
+    // delay of the stimulus relative to the sinoatrial node
+    pub onset_ms: f32,
+}
+
+impl Default for PacingSite {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default pacing site");
+        Self {
+            position_mm: DEFAULT_HEART_OFFSET_HANDCRAFTED,
+            onset_ms: 200.0,
+        }
+    }
+}
+
+/// Rule or volume from which the fiber direction of each voxel is taken.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum FiberOrientation {
+    /// The same fiber direction in every voxel.
+    Uniform { direction: [f32; 3] },
+    /// Fibers lie in the x-y plane and rotate linearly through the wall,
+    /// from the endocardial angle in the lowest z layer to the epicardial
+    /// angle in the highest. Angles are measured from the x-axis.
+    Helical {
+        endocardial_angle_deg: f32,
+        epicardial_angle_deg: f32,
+    },
+    /// A NIFTI volume holding a fiber vector per voxel on the grid of the
+    /// MRI segmentation.
+    Nifti { path: PathBuf },
+}
+
+impl Default for FiberOrientation {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default fiber orientation");
+        Self::Helical {
+            endocardial_angle_deg: 60.0,
+            epicardial_angle_deg: -60.0,
+        }
+    }
 }
 
 pub const DEFAULT_HEART_OFFSET_HANDCRAFTED: [f32; 3] = [25.0, -250.0, 150.0];
 pub const DEFAULT_HEART_OFFSET_MRI: [f32; 3] = [-130.0, -300.0, -30.0];
 pub const DEFAULT_SENSOR_ORIGIN_CUBE: [f32; 3] = [-50.0, -300.0, 270.0];
 pub const DEFAULT_SENSOR_ORIGIN_CYLINDER: [f32; 3] = [0.0, -200.0, 100.0];
+pub const DEFAULT_GRADIOMETER_BASELINE_MM: f32 = 50.0;
+pub const REFERENCE_HEART_RATE_BPM: f32 = 60.0;
 
 impl Default for Common {
     #[tracing::instrument(level = "debug")]
@@ -205,7 +714,8 @@ impl Default for Common {
             pathological: false,
             sensor_array_geometry: SensorArrayGeometry::Cube,
             sensor_array_motion: SensorArrayMotion::Static,
-            three_d_sensors: true,
+            sensor_type: default_sensor_type(),
+            sensor_array_modality: default_sensor_array_modality(),
             number_of_sensors: 40,
             sensor_array_radius_mm: 400.0,
             sensors_per_axis: [4, 4, 4],
@@ -213,15 +723,29 @@ impl Default for Common {
             sensor_array_origin_mm: DEFAULT_SENSOR_ORIGIN_CUBE,
             sensor_array_motion_range_mm: [100.0, 200.0, 100.0],
             sensor_array_motion_steps: [1, 2, 1],
+            sensor_array_trajectory: SensorArrayTrajectory::default(),
             voxel_size_mm: 2.5,
             heart_offset_mm: [25.0, -250.0, 150.0],
             measurement_covariance_mean: 1e-3,
             measurement_covariance_std: 0.0,
             propagation_velocities: PropagationVelocitiesMPerS::default(),
             current_factor_in_pathology: 0.00,
+            current_factor_in_border_zone: default_current_factor_in_border_zone(),
+            fiber_orientation: None,
+            transverse_velocity_ratio: default_transverse_velocity_ratio(),
+            torso_model: TorsoModel::default(),
+            neighborhood_radius: default_neighborhood_radius(),
+            heart_rate_bpm: default_heart_rate_bpm(),
+            action_potential_duration_factor: default_action_potential_duration_factor(),
+            action_potential_rate_adaptation: false,
+            pacing_sites: Vec::new(),
+            reentry: false,
+            refractory_period_ms: default_refractory_period_ms(),
         };
         match config.sensor_array_geometry {
-            SensorArrayGeometry::Cube | SensorArrayGeometry::SparseCube => {
+            SensorArrayGeometry::Cube
+            | SensorArrayGeometry::SparseCube
+            | SensorArrayGeometry::FromFile { .. } => {
                 config.sensor_array_origin_mm = DEFAULT_SENSOR_ORIGIN_CUBE;
             }
             SensorArrayGeometry::Cylinder => {
@@ -231,3 +755,19 @@ impl Default for Common {
         config
     }
 }
+
+impl Common {
+    /// Returns the factor by which the action potential of the reference
+    /// waveform is stretched in time, including the rate adaptation if
+    /// enabled.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn action_potential_duration_scale(&self) -> f32 {
+        let rate_adaptation = if self.action_potential_rate_adaptation {
+            (REFERENCE_HEART_RATE_BPM / self.heart_rate_bpm).sqrt()
+        } else {
+            1.0
+        };
+        self.action_potential_duration_factor * rate_adaptation
+    }
+}
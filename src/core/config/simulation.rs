@@ -8,6 +8,16 @@ pub struct Simulation {
     pub model: Model,
     pub sample_rate_hz: f32,
     pub duration_s: f32,
+    // positions used for the virtual 12-lead ECG export.
+    // if set to none, the electrodes are placed around the heart automatically
+    #[serde(default)]
+    pub virtual_electrodes: Option<VirtualElectrodes>,
+    #[serde(default)]
+    pub beat_variability: BeatVariability,
+    #[serde(default)]
+    pub structured_noise: StructuredNoise,
+    #[serde(default)]
+    pub sensor_faults: SensorFaults,
 }
 impl Default for Simulation {
     /// Returns a default `Simulation` struct with sample rate 2000 Hz,
@@ -19,6 +29,143 @@ impl Default for Simulation {
             model: Model::default(),
             sample_rate_hz: 2000.0,
             duration_s: 1.0,
+            virtual_electrodes: None,
+            beat_variability: BeatVariability::default(),
+            structured_noise: StructuredNoise::default(),
+            sensor_faults: SensorFaults::default(),
+        }
+    }
+}
+
+/// Controlled variability between the simulated beats.
+///
+/// Each beat is stretched in time by a heart-rate factor and scaled in
+/// amplitude, both drawn from normal distributions around one with the given
+/// relative standard deviations. The sensor array of each beat is displaced
+/// by a normally distributed offset per axis. The displacement only affects
+/// the simulated measurements, the model used by the algorithms keeps the
+/// nominal sensor positions. All standard deviations set to zero yield
+/// identical beats.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct BeatVariability {
+    pub heart_rate_jitter_std: f32,
+    pub amplitude_scaling_std: f32,
+    pub sensor_displacement_std_mm: f32,
+    pub seed: u64,
+}
+
+impl BeatVariability {
+    /// Returns true if any of the standard deviations is non-zero.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn is_enabled(&self) -> bool {
+        self.heart_rate_jitter_std > 0.0
+            || self.amplitude_scaling_std > 0.0
+            || self.sensor_displacement_std_mm > 0.0
+    }
+}
+
+/// Structured noise sources added to the simulated measurements on top of
+/// the gaussian measurement noise.
+///
+/// All amplitudes are given in the unit of the measurements. The drift and
+/// powerline amplitudes are the peak amplitudes of the sinusoids, the 1/f
+/// amplitude is the RMS value of the pink noise. An amplitude of zero
+/// disables the respective source.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct StructuredNoise {
+    pub baseline_drift_amplitude: f32,
+    pub baseline_drift_frequency_hz: f32,
+    pub powerline_amplitude: f32,
+    pub powerline_frequency_hz: f32,
+    pub pink_noise_amplitude: f32,
+    pub seed: u64,
+}
+
+impl Default for StructuredNoise {
+    /// Returns a default `StructuredNoise` with all sources disabled, a
+    /// respiratory drift frequency of 0.25 Hz and a powerline frequency of
+    /// 50 Hz.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default structured noise");
+        Self {
+            baseline_drift_amplitude: 0.0,
+            baseline_drift_frequency_hz: 0.25,
+            powerline_amplitude: 0.0,
+            powerline_frequency_hz: 50.0,
+            pink_noise_amplitude: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Faulty channels of the simulated sensor array.
+///
+/// The given fractions of the sensors are drawn at random, a sensor is either
+/// dropped or saturated. Dropped sensors record a flat zero signal, saturated
+/// sensors are clipped at `saturation_level` times their peak absolute
+/// amplitude. The faults are applied after all noise sources. Fractions of
+/// zero disable the respective fault.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct SensorFaults {
+    pub dropout_fraction: f32,
+    pub saturation_fraction: f32,
+    pub saturation_level: f32,
+    pub seed: u64,
+}
+
+impl Default for SensorFaults {
+    /// Returns a default `SensorFaults` with all faults disabled and a
+    /// saturation level of half the peak amplitude.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default sensor faults");
+        Self {
+            dropout_fraction: 0.0,
+            saturation_fraction: 0.0,
+            saturation_level: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+/// Positions of the virtual electrodes used to derive 12-lead ECG style traces.
+///
+/// Contains the three limb electrodes (right arm, left arm, left leg) and the
+/// six precordial electrodes V1 to V6, all in millimeters.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct VirtualElectrodes {
+    pub right_arm_mm: [f32; 3],
+    pub left_arm_mm: [f32; 3],
+    pub left_leg_mm: [f32; 3],
+    pub precordial_mm: [[f32; 3]; 6],
+}
+
+impl VirtualElectrodes {
+    /// Places the electrodes on a sphere with the given radius around the
+    /// given center.
+    ///
+    /// The limb electrodes form the Einthoven triangle in the frontal (x-y)
+    /// plane, the precordial electrodes follow an arc from the right sternal
+    /// border (V1) to the left mid-axillary line (V6) on the anterior (+z)
+    /// side.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn around(center_mm: [f32; 3], radius_mm: f32) -> Self {
+        debug!("Placing virtual electrodes around heart");
+        let [x, y, z] = center_mm;
+        let r = radius_mm;
+        let precordial_angles_deg: [f32; 6] = [-15.0, 5.0, 25.0, 45.0, 70.0, 90.0];
+        let precordial_mm = precordial_angles_deg.map(|angle_deg| {
+            let (sin, cos) = angle_deg.to_radians().sin_cos();
+            [r.mul_add(sin, x), 0.1f32.mul_add(-r, y), r.mul_add(cos, z)]
+        });
+        Self {
+            right_arm_mm: [x - r, y + r, z],
+            left_arm_mm: [x + r, y + r, z],
+            left_leg_mm: [0.5f32.mul_add(r, x), 1.5f32.mul_add(-r, y), z],
+            precordial_mm,
         }
     }
 }
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// How the data and results of a scenario are written to disk.
+///
+/// `compression_level` is the zstd level of the data and the results parts,
+/// zero stores them uncompressed. If `persist_estimations` is false, the
+/// estimations, derivatives and snapshots are not written, so only the
+/// metrics, the model and the summary are available after reloading.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct Storage {
+    #[serde(default)]
+    pub compression_level: i32,
+    #[serde(default = "default_persist_estimations")]
+    pub persist_estimations: bool,
+}
+
+const fn default_persist_estimations() -> bool {
+    true
+}
+
+impl Default for Storage {
+    /// Returns a default `Storage` that writes everything uncompressed.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default storage settings");
+        Self {
+            compression_level: 0,
+            persist_estimations: default_persist_estimations(),
+        }
+    }
+}
@@ -0,0 +1,474 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use tracing::{trace, warn};
+
+use super::{
+    algorithm::{Algorithm, AlgorithmType, OptimizationRegion},
+    model::{Handcrafted, Model, PathologyRegion, SensorArrayGeometry},
+    Config,
+};
+
+/// How severe a problem in a config is.
+///
+/// Errors make a scenario fail to build or run, warnings point out
+/// combinations that run but are most likely not intended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A single problem found while validating a config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    /// Dotted path of the offending parameter, e.g.
+    /// `simulation.model.common.voxel_size_mm`.
+    pub path: String,
+    /// What is wrong and how to fix it.
+    pub message: String,
+}
+
+impl Display for ConfigIssue {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Config {
+    /// Checks the constraints between the parameters that deserializing
+    /// alone cannot catch and returns every problem found, errors first.
+    ///
+    /// The algorithm model is checked against the sample rate of the
+    /// simulation, which it runs at.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn issues(&self) -> Vec<ConfigIssue> {
+        trace!("Validating config");
+        let mut issues = Issues::default();
+        let simulation = &self.simulation;
+        if simulation.sample_rate_hz <= 0.0 {
+            issues.error(
+                "simulation.sample_rate_hz",
+                format!("must be positive, got {} Hz", simulation.sample_rate_hz),
+            );
+        }
+        if simulation.duration_s <= 0.0 {
+            issues.error(
+                "simulation.duration_s",
+                format!("must be positive, got {} s", simulation.duration_s),
+            );
+        }
+        model_issues(
+            "simulation.model",
+            &simulation.model,
+            simulation.sample_rate_hz,
+            &mut issues,
+        );
+        model_issues(
+            "algorithm.model",
+            &self.algorithm.model,
+            simulation.sample_rate_hz,
+            &mut issues,
+        );
+        algorithm_issues(&self.algorithm, &mut issues);
+        let mut issues = issues.0;
+        issues.sort_by(|a, b| b.severity.cmp(&a.severity));
+        issues
+    }
+
+    /// Validates the config, logging warnings and failing on errors.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing every invalid parameter if any constraint is
+    /// violated.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn validate(&self) -> Result<()> {
+        let issues = self.issues();
+        for issue in issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Warning)
+        {
+            warn!("Suspicious config: {issue}");
+        }
+        let errors: Vec<String> = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| format!("  - {issue}"))
+            .collect();
+        if !errors.is_empty() {
+            anyhow::bail!("Invalid config:\n{}", errors.join("\n"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn error(&mut self, path: &str, message: String) {
+        self.push(Severity::Error, path, message);
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn warning(&mut self, path: &str, message: String) {
+        self.push(Severity::Warning, path, message);
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn push(&mut self, severity: Severity, path: &str, message: String) {
+        self.0.push(ConfigIssue {
+            severity,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    /// Adds an error if the percentage is outside of zero to one.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn percentage(&mut self, path: &str, value: f32) {
+        if !(0.0..=1.0).contains(&value) {
+            self.error(
+                path,
+                format!("is a fraction of the heart size and must be between 0 and 1, got {value}"),
+            );
+        }
+    }
+
+    /// Adds an error if the range between the percentages is empty.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn percentage_range(&mut self, path: &str, start: (&str, f32), stop: (&str, f32)) {
+        self.percentage(&format!("{path}.{}", start.0), start.1);
+        self.percentage(&format!("{path}.{}", stop.0), stop.1);
+        if start.1 > stop.1 {
+            self.error(
+                &format!("{path}.{}", start.0),
+                format!(
+                    "start {} is after stop {} = {}, the range is empty",
+                    start.1, stop.0, stop.1
+                ),
+            );
+        }
+    }
+}
+
+/// Returns the largest propagation velocity that still delays the
+/// activation by at least one sample between neighbouring voxels.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn maximum_velocity_m_per_s(voxel_size_mm: f32, sample_rate_hz: f32) -> f32 {
+    voxel_size_mm * sample_rate_hz / 1000.0
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn model_issues(path: &str, model: &Model, sample_rate_hz: f32, issues: &mut Issues) {
+    let common = &model.common;
+    if common.voxel_size_mm <= 0.0 {
+        issues.error(
+            &format!("{path}.common.voxel_size_mm"),
+            format!("must be positive, got {} mm", common.voxel_size_mm),
+        );
+        return;
+    }
+
+    if sample_rate_hz > 0.0 {
+        let maximum = maximum_velocity_m_per_s(common.voxel_size_mm, sample_rate_hz);
+        let velocities = &common.propagation_velocities;
+        let mut checked = vec![
+            ("sinoatrial", velocities.sinoatrial),
+            ("atrium", velocities.atrium),
+            ("atrioventricular", velocities.atrioventricular),
+            ("hps", velocities.hps),
+            ("ventricle", velocities.ventricle),
+        ];
+        if common.pathological {
+            checked.push(("pathological", velocities.pathological));
+            checked.push(("border_zone", velocities.border_zone));
+        }
+        for (name, velocity) in checked {
+            let velocity_path = format!("{path}.common.propagation_velocities.{name}");
+            if velocity <= 0.0 {
+                issues.error(
+                    &velocity_path,
+                    format!("must be positive, got {velocity} m/s"),
+                );
+            } else if velocity > maximum {
+                issues.error(
+                    &velocity_path,
+                    format!(
+                        "{velocity} m/s exceeds the limit of {maximum} m/s for {} mm voxels at \
+                         {sample_rate_hz} Hz, lower the velocity, increase the voxel size or \
+                         increase the sample rate",
+                        common.voxel_size_mm
+                    ),
+                );
+            }
+        }
+    }
+
+    if common.transverse_velocity_ratio <= 0.0 {
+        issues.error(
+            &format!("{path}.common.transverse_velocity_ratio"),
+            format!("must be positive, got {}", common.transverse_velocity_ratio),
+        );
+    } else if common.transverse_velocity_ratio > 1.0 {
+        issues.warning(
+            &format!("{path}.common.transverse_velocity_ratio"),
+            format!(
+                "{} makes propagation across the fibers faster than along them",
+                common.transverse_velocity_ratio
+            ),
+        );
+    }
+
+    match &common.sensor_array_geometry {
+        SensorArrayGeometry::Cube => {
+            if common.sensors_per_axis.contains(&0) {
+                issues.error(
+                    &format!("{path}.common.sensors_per_axis"),
+                    format!(
+                        "needs at least one sensor along every axis, got {:?}",
+                        common.sensors_per_axis
+                    ),
+                );
+            }
+        }
+        SensorArrayGeometry::SparseCube | SensorArrayGeometry::Cylinder => {
+            if common.number_of_sensors == 0 {
+                issues.error(
+                    &format!("{path}.common.number_of_sensors"),
+                    "needs at least one sensor".to_string(),
+                );
+            }
+        }
+        SensorArrayGeometry::FromFile { path: file } => {
+            if !file.is_file() {
+                issues.error(
+                    &format!("{path}.common.sensor_array_geometry"),
+                    format!("sensor position file {} does not exist", file.display()),
+                );
+            }
+        }
+    }
+    if common.sensor_array_geometry == SensorArrayGeometry::Cylinder
+        && common.sensor_array_radius_mm <= 0.0
+    {
+        issues.error(
+            &format!("{path}.common.sensor_array_radius_mm"),
+            format!("must be positive, got {} mm", common.sensor_array_radius_mm),
+        );
+    }
+
+    if let Some(handcrafted) = model.handcrafted.as_ref() {
+        handcrafted_issues(
+            &format!("{path}.handcrafted"),
+            handcrafted,
+            common.voxel_size_mm,
+            issues,
+        );
+    } else if model.mri.is_none() {
+        issues.error(
+            path,
+            "needs either a handcrafted or an MRI section".to_string(),
+        );
+    }
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn handcrafted_issues(
+    path: &str,
+    handcrafted: &Handcrafted,
+    voxel_size_mm: f32,
+    issues: &mut Issues,
+) {
+    if handcrafted
+        .heart_size_mm
+        .iter()
+        .any(|size| *size < voxel_size_mm)
+    {
+        issues.error(
+            &format!("{path}.heart_size_mm"),
+            format!(
+                "{:?} mm is smaller than one {voxel_size_mm} mm voxel along an axis, increase \
+                 the heart size or decrease the voxel size",
+                handcrafted.heart_size_mm
+            ),
+        );
+    }
+
+    for (name, value) in [
+        ("sa_x_center_percentage", handcrafted.sa_x_center_percentage),
+        ("sa_y_center_percentage", handcrafted.sa_y_center_percentage),
+        (
+            "atrium_y_start_percentage",
+            handcrafted.atrium_y_start_percentage,
+        ),
+        ("av_x_center_percentage", handcrafted.av_x_center_percentage),
+        ("hps_y_stop_percentage", handcrafted.hps_y_stop_percentage),
+        ("hps_y_up_percentage", handcrafted.hps_y_up_percentage),
+    ] {
+        issues.percentage(&format!("{path}.{name}"), value);
+    }
+    for (start, stop) in [
+        (
+            ("sa_z_start_percentage", handcrafted.sa_z_start_percentage),
+            ("sa_z_stop_percentage", handcrafted.sa_z_stop_percentage),
+        ),
+        (
+            ("av_z_start_percentage", handcrafted.av_z_start_percentage),
+            ("av_z_stop_percentage", handcrafted.av_z_stop_percentage),
+        ),
+        (
+            ("hps_x_start_percentage", handcrafted.hps_x_start_percentage),
+            ("hps_x_stop_percentage", handcrafted.hps_x_stop_percentage),
+        ),
+        (
+            ("hps_z_start_percentage", handcrafted.hps_z_start_percentage),
+            ("hps_z_stop_percentage", handcrafted.hps_z_stop_percentage),
+        ),
+    ] {
+        issues.percentage_range(path, start, stop);
+    }
+    for (index, region) in handcrafted.pathology_regions.iter().enumerate() {
+        pathology_region_issues(
+            &format!("{path}.pathology_regions[{index}]"),
+            region,
+            issues,
+        );
+    }
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn pathology_region_issues(path: &str, region: &PathologyRegion, issues: &mut Issues) {
+    for (start, stop) in [
+        (
+            ("x_start_percentage", region.x_start_percentage),
+            ("x_stop_percentage", region.x_stop_percentage),
+        ),
+        (
+            ("y_start_percentage", region.y_start_percentage),
+            ("y_stop_percentage", region.y_stop_percentage),
+        ),
+        (
+            ("z_start_percentage", region.z_start_percentage),
+            ("z_stop_percentage", region.z_stop_percentage),
+        ),
+    ] {
+        issues.percentage_range(path, start, stop);
+    }
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn algorithm_issues(algorithm: &Algorithm, issues: &mut Issues) {
+    if algorithm.epochs == 0 {
+        issues.error("algorithm.epochs", "needs at least one epoch".to_string());
+    }
+    let model_based = matches!(
+        algorithm.algorithm_type,
+        AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU
+    );
+    if model_based && !(algorithm.learning_rate.is_finite() && algorithm.learning_rate > 0.0) {
+        issues.error(
+            "algorithm.learning_rate",
+            format!(
+                "must be positive for the model-based algorithms, got {}",
+                algorithm.learning_rate
+            ),
+        );
+    }
+    if let OptimizationRegion::Cuboid(region) = &algorithm.optimization_region {
+        pathology_region_issues("algorithm.optimization_region", region, issues);
+    }
+    if algorithm.snapshots_interval > algorithm.epochs {
+        issues.warning(
+            "algorithm.snapshots_interval",
+            format!(
+                "{} is larger than the {} epochs, only the first epoch is recorded",
+                algorithm.snapshots_interval, algorithm.epochs
+            ),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        let config = Config::default();
+
+        assert_eq!(config.issues(), Vec::new());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn velocity_above_sampling_limit_is_error() {
+        let mut config = Config::default();
+        config.simulation.model.common.propagation_velocities.hps = 6.0;
+
+        let issues = config.issues();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(
+            issues[0].path,
+            "simulation.model.common.propagation_velocities.hps"
+        );
+        let error = format!(
+            "{:#}",
+            config
+                .validate()
+                .expect_err("Velocity above the limit should be invalid")
+        );
+        assert!(error.contains("exceeds the limit of 5 m/s"), "{error}");
+    }
+
+    #[test]
+    fn empty_pathology_range_is_error() -> anyhow::Result<()> {
+        let mut config = Config::default();
+        let handcrafted = config
+            .simulation
+            .model
+            .handcrafted
+            .as_mut()
+            .context("Default model should be handcrafted")?;
+        handcrafted.pathology_regions[0].x_start_percentage = 0.8;
+        handcrafted.pathology_regions[0].x_stop_percentage = 0.2;
+        handcrafted.sa_x_center_percentage = 1.5;
+
+        let paths: Vec<String> = config
+            .issues()
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                "simulation.model.handcrafted.sa_x_center_percentage",
+                "simulation.model.handcrafted.pathology_regions[0].x_start_percentage",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn warnings_do_not_fail_validation() {
+        let mut config = Config::default();
+        config.algorithm.snapshots_interval = config.algorithm.epochs + 1;
+
+        let issues = config.issues();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert!(config.validate().is_ok());
+    }
+}
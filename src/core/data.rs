@@ -1,17 +1,27 @@
+pub mod beats;
+pub mod ecg;
+pub mod faults;
+pub mod filter;
+pub mod noise;
 pub mod shapes;
 pub mod simulation;
 
+use std::sync::mpsc::Sender;
+
 use anyhow::{Context, Result};
 use ndarray::Dim;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use self::simulation::Simulation;
+use self::{beats::Beats, simulation::Simulation};
 use crate::core::{config::simulation::Simulation as SimulationConfig, data::shapes::Measurements};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Data {
     pub simulation: Simulation,
+    // beats detected during preprocessing, if enabled
+    #[serde(default)]
+    pub beats: Option<Beats>,
 }
 
 impl Data {
@@ -24,6 +34,7 @@ impl Data {
         number_of_steps: usize,
         voxels_in_dims: Dim<[usize; 3]>,
         number_of_beats: usize,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating empty data");
         Self {
@@ -33,7 +44,9 @@ impl Data {
                 number_of_steps,
                 voxels_in_dims,
                 number_of_beats,
+                neighborhood_radius,
             ),
+            beats: None,
         }
     }
 
@@ -50,7 +63,32 @@ impl Data {
         let mut simulation = Simulation::from_config(config)?;
         simulation.run()?;
         simulation.update_activation_time();
-        Ok(Self { simulation })
+        Ok(Self {
+            simulation,
+            beats: None,
+        })
+    }
+
+    /// Creates a new [`Data`] instance from a [`SimulationConfig`] like
+    /// [`Data::from_simulation_config`], sending the fraction of the
+    /// simulation that is done through the given channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if creating the `Simulation` from the config fails.
+    #[tracing::instrument(level = "debug", skip(progress_tx))]
+    pub fn from_simulation_config_with_progress(
+        config: &SimulationConfig,
+        progress_tx: &Sender<f32>,
+    ) -> Result<Self> {
+        debug!("Creating data from simulation config with progress");
+        let mut simulation = Simulation::from_config(config)?;
+        simulation.run_with_progress(progress_tx)?;
+        simulation.update_activation_time();
+        Ok(Self {
+            simulation,
+            beats: None,
+        })
     }
 
     /// # Panics
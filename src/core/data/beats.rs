@@ -0,0 +1,228 @@
+use anyhow::{Context, Result};
+use ndarray::{s, Array1, Array2, Array3, ArrayView1, ArrayView2, Axis};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use super::shapes::Measurements;
+use crate::core::config::algorithm::BeatDetection;
+
+/// Beats detected in the measurements, with all steps counted in the
+/// continuous recording formed by concatenating the beats of the
+/// measurements.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Beats {
+    pub triggers: Vec<usize>,
+    // first step of every beat whose window lies within the recording
+    pub window_starts: Vec<usize>,
+    pub window_length: usize,
+    // step of the trigger within a window
+    pub pre_trigger_steps: usize,
+    // whether the measurements were replaced with the average beat
+    pub averaged: bool,
+}
+
+impl Beats {
+    /// Returns the steps of the triggers that fall into the given beat of
+    /// the measurements, relative to the start of the beat.
+    ///
+    /// After averaging every beat holds the average beat, whose trigger lies
+    /// at the pre-trigger step.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn triggers_in_beat(&self, beat: usize, number_of_steps: usize) -> Vec<usize> {
+        if self.averaged {
+            return vec![self.pre_trigger_steps];
+        }
+        let start = beat * number_of_steps;
+        self.triggers
+            .iter()
+            .filter(|&&trigger| (start..start + number_of_steps).contains(&trigger))
+            .map(|trigger| trigger - start)
+            .collect()
+    }
+}
+
+/// Detects the beats in the measurements as configured and replaces every
+/// beat with the average beat if requested.
+///
+/// # Errors
+///
+/// Returns an error if the trigger channel does not exist or no beat window
+/// lies within the recording.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+#[tracing::instrument(level = "debug", skip(measurements))]
+pub fn segment_beats(
+    measurements: &mut Measurements,
+    config: &BeatDetection,
+    sample_rate_hz: f32,
+) -> Result<Beats> {
+    debug!("Segmenting measurements into beats");
+    let number_of_steps = measurements.num_steps();
+    let recording = measurements
+        .view()
+        .into_shape_with_order((
+            measurements.num_beats() * number_of_steps,
+            measurements.num_sensors(),
+        ))
+        .context("Failed to concatenate the beats of the measurements")?;
+    let signal = trigger_signal(recording, config.trigger_channel)?;
+    let refractory_steps = (config.refractory_ms / 1000.0 * sample_rate_hz).round() as usize;
+    let pre_trigger_steps = (config.pre_trigger_ms / 1000.0 * sample_rate_hz).round() as usize;
+    let triggers = detect_triggers(signal.view(), config.threshold, refractory_steps);
+    let window_starts: Vec<usize> = triggers
+        .iter()
+        .filter_map(|trigger| trigger.checked_sub(pre_trigger_steps))
+        .filter(|start| start + number_of_steps <= recording.nrows())
+        .collect();
+    let mut beats = Beats {
+        triggers,
+        window_starts,
+        window_length: number_of_steps,
+        pre_trigger_steps,
+        averaged: false,
+    };
+    if config.average {
+        anyhow::ensure!(
+            !beats.window_starts.is_empty(),
+            "None of the {} detected beats lies within the recording",
+            beats.triggers.len()
+        );
+        let average = average_beat(&segment(recording, &beats.window_starts, number_of_steps));
+        for mut beat in measurements.axis_iter_mut(Axis(0)) {
+            beat.assign(&average);
+        }
+        beats.averaged = true;
+    }
+    Ok(beats)
+}
+
+/// Returns the signal used for detecting triggers, the absolute value of the
+/// given channel or the root mean square over all channels.
+///
+/// # Errors
+///
+/// Returns an error if the channel does not exist.
+#[tracing::instrument(level = "trace", skip(recording))]
+pub fn trigger_signal(recording: ArrayView2<f32>, channel: Option<usize>) -> Result<Array1<f32>> {
+    trace!("Calculating trigger signal");
+    match channel {
+        Some(channel) => {
+            anyhow::ensure!(
+                channel < recording.ncols(),
+                "Trigger channel {channel} does not exist, the measurements have {} channels",
+                recording.ncols()
+            );
+            Ok(recording.column(channel).mapv(f32::abs))
+        }
+        None => Ok(recording
+            .mapv(|value| value * value)
+            .mean_axis(Axis(1))
+            .context("Measurements have no channels")?
+            .mapv(f32::sqrt)),
+    }
+}
+
+/// Detects the steps of the local maxima of the signal that exceed the
+/// threshold relative to its maximum. Of several maxima within the
+/// refractory period, only the first is kept.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(signal))]
+pub fn detect_triggers(
+    signal: ArrayView1<f32>,
+    threshold: f32,
+    refractory_steps: usize,
+) -> Vec<usize> {
+    trace!("Detecting beat triggers");
+    let maximum = signal.iter().copied().fold(0.0, f32::max);
+    if maximum <= 0.0 {
+        return Vec::new();
+    }
+    let level = threshold * maximum;
+    let mut triggers: Vec<usize> = Vec::new();
+    for (step, &value) in signal.iter().enumerate() {
+        let is_peak = value >= level
+            && (step == 0 || signal[step - 1] < value)
+            && (step + 1 == signal.len() || signal[step + 1] <= value);
+        let is_refractory = triggers
+            .last()
+            .is_some_and(|&last| step - last < refractory_steps);
+        if is_peak && !is_refractory {
+            triggers.push(step);
+        }
+    }
+    triggers
+}
+
+/// Cuts windows of the given length starting at the given steps out of the
+/// recording, returning an array of shape (beats, steps, sensors).
+#[must_use]
+#[tracing::instrument(level = "trace", skip(recording))]
+pub fn segment(recording: ArrayView2<f32>, window_starts: &[usize], length: usize) -> Array3<f32> {
+    trace!("Cutting beat windows");
+    let mut segments = Array3::zeros((window_starts.len(), length, recording.ncols()));
+    for (mut segment, &start) in segments.axis_iter_mut(Axis(0)).zip(window_starts) {
+        segment.assign(&recording.slice(s![start..start + length, ..]));
+    }
+    segments
+}
+
+/// Averages the segments over the beats.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn average_beat(segments: &Array3<f32>) -> Array2<f32> {
+    segments
+        .mean_axis(Axis(0))
+        .unwrap_or_else(|| Array2::zeros((segments.shape()[1], segments.shape()[2])))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr1;
+
+    use super::*;
+
+    #[test]
+    fn triggers_respect_threshold_and_refractory_period() {
+        let signal = arr1(&[0.0, 1.0, 0.0, 0.9, 0.0, 0.0, 0.3, 0.0, 0.0, 1.0, 0.0]);
+
+        let triggers = detect_triggers(signal.view(), 0.5, 3);
+
+        assert_eq!(triggers, vec![1, 9]);
+    }
+
+    #[test]
+    fn beats_are_averaged_around_triggers() -> Result<()> {
+        // one sensor with a spike every 10 steps, spread over two beats of
+        // 20 steps each
+        let mut measurements = Measurements::empty(2, 20, 1);
+        for (index, value) in measurements.iter_mut().enumerate() {
+            if index % 10 == 5 {
+                *value = 2.0;
+            }
+        }
+        let config = BeatDetection {
+            enabled: true,
+            refractory_ms: 5.0,
+            pre_trigger_ms: 5.0,
+            ..BeatDetection::default()
+        };
+
+        let beats = segment_beats(&mut measurements, &config, 1000.0)?;
+
+        assert_eq!(beats.triggers, vec![5, 15, 25, 35]);
+        assert_eq!(beats.window_starts, vec![0, 10, 20]);
+        assert!(beats.averaged);
+        assert_relative_eq!(measurements[(1, 5, 0)], 2.0);
+        assert_relative_eq!(measurements[(1, 6, 0)], 0.0);
+        assert_eq!(beats.triggers_in_beat(1, 20), vec![5]);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_trigger_channel_is_an_error() {
+        let recording = Array2::<f32>::zeros((10, 2));
+
+        assert!(trigger_signal(recording.view(), Some(2)).is_err());
+    }
+}
@@ -0,0 +1,283 @@
+use std::{
+    f32::consts::PI,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    ops::Deref,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use ndarray::{s, Array1, Array2};
+use ndarray_npy::WriteNpyExt;
+use tracing::{debug, trace};
+
+use super::shapes::SystemStates;
+use crate::{
+    core::{config::simulation::VirtualElectrodes, model::spatial::voxels::Voxels},
+    vis::plotting::{png::line::small_multiples_time_plot, PlotTheme},
+};
+
+/// Conductivity of the homogeneous volume conductor in S/mm (0.2 S/m).
+const TISSUE_CONDUCTIVITY_S_PER_MM: f32 = 2e-4;
+/// Distance between the outermost heart voxel and the automatically placed electrodes.
+const ELECTRODE_MARGIN_MM: f32 = 50.0;
+
+pub const LEAD_NAMES: [&str; 12] = [
+    "I", "II", "III", "aVR", "aVL", "aVF", "V1", "V2", "V3", "V4", "V5", "V6",
+];
+
+/// Virtual 12-lead ECG traces in mV.
+///
+/// Has dimensions (`number_of_steps`, 12), with the leads ordered as in
+/// [`LEAD_NAMES`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct TwelveLeadEcg(Array2<f32>);
+
+impl TwelveLeadEcg {
+    /// Calculates the 12-lead ECG from the given system states.
+    ///
+    /// The system states are interpreted as current densities per voxel. The
+    /// potentials at the electrode positions are calculated using the
+    /// current dipole in an infinite homogeneous volume conductor. If no
+    /// electrodes are given, they are placed around the heart automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the voxel numbers are not initialized correctly.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_system_states(
+        system_states: &SystemStates,
+        voxels: &Voxels,
+        electrodes: Option<&VirtualElectrodes>,
+    ) -> Result<Self> {
+        debug!("Calculating virtual 12-lead ECG");
+        let electrodes = electrodes.map_or_else(
+            || electrodes_around_heart(voxels),
+            |electrodes| Ok(electrodes.clone()),
+        )?;
+        let lead_field = calculate_electrode_lead_field(voxels, &electrodes)?;
+        let potentials_mv = system_states.dot(&lead_field.t()) * 1e3;
+        Ok(Self::from_electrode_potentials(&potentials_mv))
+    }
+
+    /// Derives the 12 leads from the electrode potentials.
+    ///
+    /// `potentials` has dimensions (`number_of_steps`, 9) with the electrodes
+    /// ordered right arm, left arm, left leg, V1 to V6. The precordial leads
+    /// are referenced to the Wilson central terminal.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_electrode_potentials(potentials: &Array2<f32>) -> Self {
+        debug!("Deriving leads from electrode potentials");
+        let number_of_steps = potentials.shape()[0];
+        let mut leads = Array2::zeros((number_of_steps, LEAD_NAMES.len()));
+        for step in 0..number_of_steps {
+            let ra = potentials[(step, 0)];
+            let la = potentials[(step, 1)];
+            let ll = potentials[(step, 2)];
+            let wilson = (ra + la + ll) / 3.0;
+            leads[(step, 0)] = la - ra;
+            leads[(step, 1)] = ll - ra;
+            leads[(step, 2)] = ll - la;
+            leads[(step, 3)] = ra - (la + ll) / 2.0;
+            leads[(step, 4)] = la - (ra + ll) / 2.0;
+            leads[(step, 5)] = ll - (ra + la) / 2.0;
+            for precordial in 0..6 {
+                leads[(step, 6 + precordial)] = potentials[(step, 3 + precordial)] - wilson;
+            }
+        }
+        Self(leads)
+    }
+
+    /// Saves the ECG as `twelve_lead_ecg.npy`, `twelve_lead_ecg.csv` and
+    /// `twelve_lead_ecg.png` in the given directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file I/O or plotting operation fails.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save(&self, path: &Path, sample_rate_hz: f32, title: &str) -> Result<()> {
+        debug!("Saving virtual 12-lead ECG");
+        self.save_npy(path)?;
+        self.save_csv(path, sample_rate_hz)?;
+        small_multiples_time_plot(
+            &self.0,
+            sample_rate_hz,
+            &path.join("twelve_lead_ecg.png"),
+            title,
+            Some(LEAD_NAMES.as_slice()),
+            PlotTheme::Light,
+        )
+        .context("Failed to plot virtual 12-lead ECG")?;
+        Ok(())
+    }
+
+    /// Saves the ECG to a .npy file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if directory creation, file creation, or NPY writing fails.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn save_npy(&self, path: &Path) -> Result<()> {
+        trace!("Saving virtual 12-lead ECG to npy");
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        let writer = BufWriter::new(
+            File::create(path.join("twelve_lead_ecg.npy"))
+                .context("Failed to create twelve_lead_ecg.npy file")?,
+        );
+        self.0
+            .write_npy(writer)
+            .context("Failed to write virtual 12-lead ECG to NPY file")?;
+        Ok(())
+    }
+
+    /// Saves the ECG to a .csv file at the given path, with one row per step
+    /// and a leading time column in seconds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if directory or file creation, or writing fails.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn save_csv(&self, path: &Path, sample_rate_hz: f32) -> Result<()> {
+        trace!("Saving virtual 12-lead ECG to csv");
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        let file_path = path.join("twelve_lead_ecg.csv");
+        let mut writer = BufWriter::new(
+            File::create(&file_path)
+                .with_context(|| format!("Failed to create file: {}", file_path.display()))?,
+        );
+        writeln!(writer, "t_s,{}", LEAD_NAMES.join(","))?;
+        for (step, leads) in self.0.outer_iter().enumerate() {
+            let values: Vec<String> = leads.iter().map(ToString::to_string).collect();
+            writeln!(
+                writer,
+                "{},{}",
+                step as f32 / sample_rate_hz,
+                values.join(",")
+            )?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write file: {}", file_path.display()))?;
+        Ok(())
+    }
+}
+
+impl Deref for TwelveLeadEcg {
+    type Target = Array2<f32>;
+
+    #[tracing::instrument(level = "trace")]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Places the virtual electrodes around the center of all connectable
+/// voxels, with a margin around the outermost voxel.
+///
+/// # Errors
+///
+/// Returns an error if the model does not contain any connectable voxels.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn electrodes_around_heart(voxels: &Voxels) -> Result<VirtualElectrodes> {
+    debug!("Placing virtual electrodes around heart");
+    let positions: Vec<Array1<f32>> = voxels
+        .types
+        .indexed_iter()
+        .filter(|(_, v_type)| v_type.is_connectable())
+        .map(|((x, y, z), _)| voxels.positions_mm.slice(s![x, y, z, ..]).to_owned())
+        .collect();
+    if positions.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Cannot place virtual electrodes for a model without connectable voxels"
+        ));
+    }
+    let center = positions
+        .iter()
+        .fold(Array1::<f32>::zeros(3), |acc, position| acc + position)
+        / positions.len() as f32;
+    let radius = positions
+        .iter()
+        .map(|position| (position - &center).mapv(|v| v.powi(2)).sum().sqrt())
+        .fold(0.0, f32::max);
+    Ok(VirtualElectrodes::around(
+        [center[0], center[1], center[2]],
+        radius + ELECTRODE_MARGIN_MM,
+    ))
+}
+
+/// Calculates the matrix mapping the system states to the electrode
+/// potentials in V.
+///
+/// Has dimensions (9, `number_of_states`), with the electrodes ordered right
+/// arm, left arm, left leg, V1 to V6.
+#[tracing::instrument(level = "debug", skip_all)]
+fn calculate_electrode_lead_field(
+    voxels: &Voxels,
+    electrodes: &VirtualElectrodes,
+) -> Result<Array2<f32>> {
+    debug!("Calculating electrode lead field");
+    let mut electrode_positions = vec![
+        electrodes.right_arm_mm,
+        electrodes.left_arm_mm,
+        electrodes.left_leg_mm,
+    ];
+    electrode_positions.extend_from_slice(&electrodes.precordial_mm);
+
+    let mut lead_field = Array2::zeros((electrode_positions.len(), voxels.count_states()));
+    let voxel_volume_mm3 = voxels.size_mm.powi(3);
+    let common_factor = voxel_volume_mm3 / (4.0 * PI * TISSUE_CONDUCTIVITY_S_PER_MM);
+
+    for (index, v_type) in voxels.types.indexed_iter() {
+        if !v_type.is_connectable() {
+            continue;
+        }
+        let v_num = voxels.numbers[index].with_context(|| {
+            format!("Voxel number not initialized for connectable voxel at index {index:?}")
+        })?;
+        let v_pos_mm = voxels.positions_mm.slice(s![index.0, index.1, index.2, ..]);
+        for (e_num, e_pos_mm) in electrode_positions.iter().enumerate() {
+            let distance_mm = Array1::from_vec(e_pos_mm.to_vec()) - v_pos_mm;
+            let distance_cubed_mm3 = distance_mm.mapv(|v| v.powi(2)).sum().sqrt().powi(3);
+            for dim in 0..3 {
+                lead_field[(e_num, v_num + dim)] =
+                    common_factor * distance_mm[dim] / distance_cubed_mm3;
+            }
+        }
+    }
+    Ok(lead_field)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn einthoven_and_goldberger_relations_hold() {
+        let potentials = arr2(&[
+            [0.3, -0.2, 1.1, 0.0, 0.1, 0.2, 0.3, 0.4, 0.5],
+            [-1.0, 2.0, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0],
+        ]);
+
+        let ecg = TwelveLeadEcg::from_electrode_potentials(&potentials);
+
+        for step in 0..2 {
+            // II = I + III
+            assert_relative_eq!(ecg[(step, 1)], ecg[(step, 0)] + ecg[(step, 2)]);
+            // aVR + aVL + aVF = 0
+            assert_relative_eq!(
+                ecg[(step, 3)] + ecg[(step, 4)] + ecg[(step, 5)],
+                0.0,
+                epsilon = 1e-6
+            );
+        }
+        assert_relative_eq!(ecg[(1, 6)], 1.0 - 1.5 / 3.0);
+    }
+}
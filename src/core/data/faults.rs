@@ -0,0 +1,156 @@
+use ndarray::s;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::shapes::Measurements;
+use crate::core::config::simulation::SensorFaults as SensorFaultsConfig;
+
+/// Realization of the sensor faults of a simulation.
+///
+/// Stores the configuration together with the randomly drawn faulty
+/// channels, so that the algorithms can exclude them from the residuals.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SensorFaults {
+    pub config: SensorFaultsConfig,
+    pub dropped_channels: Vec<usize>,
+    pub saturated_channels: Vec<usize>,
+}
+
+impl SensorFaults {
+    /// Creates sensor faults without any faulty channels.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn empty() -> Self {
+        debug!("Creating empty sensor faults");
+        Self {
+            config: SensorFaultsConfig::default(),
+            dropped_channels: Vec::new(),
+            saturated_channels: Vec::new(),
+        }
+    }
+
+    /// Draws the dropped and saturated channels from the seed given in the
+    /// configuration.
+    ///
+    /// The fractions are clamped to the unit interval and the number of
+    /// saturated channels is limited to the channels that are not dropped.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    #[tracing::instrument(level = "debug")]
+    pub fn from_config(config: &SensorFaultsConfig, number_of_sensors: usize) -> Self {
+        debug!("Creating sensor faults from config");
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let mut channels: Vec<usize> = (0..number_of_sensors).collect();
+        channels.shuffle(&mut rng);
+
+        let number_dropped =
+            (config.dropout_fraction.clamp(0.0, 1.0) * number_of_sensors as f32).round() as usize;
+        let number_saturated = ((config.saturation_fraction.clamp(0.0, 1.0)
+            * number_of_sensors as f32)
+            .round() as usize)
+            .min(number_of_sensors - number_dropped);
+
+        let mut dropped_channels = channels[..number_dropped].to_vec();
+        let mut saturated_channels =
+            channels[number_dropped..number_dropped + number_saturated].to_vec();
+        dropped_channels.sort_unstable();
+        saturated_channels.sort_unstable();
+        Self {
+            config: config.clone(),
+            dropped_channels,
+            saturated_channels,
+        }
+    }
+
+    /// Zeroes the dropped channels and clips the saturated channels of the
+    /// measurements.
+    ///
+    /// The saturation limit is relative to the peak absolute amplitude of
+    /// each channel over all beats.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn apply(&self, measurements: &mut Measurements) {
+        debug!("Applying sensor faults");
+        for &sensor in &self.dropped_channels {
+            measurements.slice_mut(s![.., .., sensor]).fill(0.0);
+        }
+        for &sensor in &self.saturated_channels {
+            let mut channel = measurements.slice_mut(s![.., .., sensor]);
+            let peak = channel
+                .iter()
+                .fold(0.0f32, |peak, value| peak.max(value.abs()));
+            let limit = self.config.saturation_level.max(0.0) * peak;
+            channel.mapv_inplace(|value| value.clamp(-limit, limit));
+        }
+    }
+
+    /// Returns the sorted indices of all dropped and saturated channels.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn bad_channels(&self) -> Vec<usize> {
+        let mut channels: Vec<usize> = self
+            .dropped_channels
+            .iter()
+            .chain(&self.saturated_channels)
+            .copied()
+            .collect();
+        channels.sort_unstable();
+        channels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faults_are_applied_to_disjoint_channels() {
+        let config = SensorFaultsConfig {
+            dropout_fraction: 0.2,
+            saturation_fraction: 0.3,
+            saturation_level: 0.5,
+            seed: 3,
+        };
+        let faults = SensorFaults::from_config(&config, 10);
+        assert_eq!(faults.dropped_channels.len(), 2);
+        assert_eq!(faults.saturated_channels.len(), 3);
+        assert_eq!(faults.bad_channels().len(), 5);
+
+        let mut measurements = Measurements::empty(2, 50, 10);
+        measurements
+            .indexed_iter_mut()
+            .for_each(|((beat, step, sensor), value)| {
+                #[allow(clippy::cast_precision_loss)]
+                let phase = (beat * 50 + step + sensor) as f32;
+                *value = 2.0 * (0.3 * phase).sin();
+            });
+        let clean = measurements.clone();
+        faults.apply(&mut measurements);
+
+        for sensor in 0..10 {
+            let channel = measurements.slice(s![.., .., sensor]);
+            if faults.dropped_channels.contains(&sensor) {
+                assert!(channel.iter().all(|value| value.abs() < f32::EPSILON));
+            } else if faults.saturated_channels.contains(&sensor) {
+                let peak = clean
+                    .slice(s![.., .., sensor])
+                    .iter()
+                    .fold(0.0f32, |peak, value| peak.max(value.abs()));
+                assert!(channel.iter().all(|value| value.abs() <= 0.5 * peak));
+            } else {
+                assert_eq!(channel, clean.slice(s![.., .., sensor]));
+            }
+        }
+    }
+
+    #[test]
+    fn disabled_faults_have_no_bad_channels() {
+        let faults = SensorFaults::from_config(&SensorFaultsConfig::default(), 10);
+        assert!(faults.bad_channels().is_empty());
+    }
+}
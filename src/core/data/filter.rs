@@ -0,0 +1,329 @@
+use std::f64::consts::PI;
+
+use anyhow::{ensure, Result};
+use ndarray::{s, Array1, ArrayView1};
+use tracing::{debug, trace};
+
+use super::shapes::Measurements;
+use crate::core::config::algorithm::Preprocessing;
+
+/// A second order IIR section with the coefficients normalized by `a0`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Biquad {
+    pub b: [f64; 3],
+    pub a: [f64; 2],
+}
+
+impl Biquad {
+    /// Creates a section from unnormalized coefficients.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+        }
+    }
+
+    /// Second order low-pass with the given quality factor.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn low_pass(cutoff_hz: f64, quality_factor: f64, sample_rate_hz: f64) -> Self {
+        let (sin, cos) = (2.0 * PI * cutoff_hz / sample_rate_hz).sin_cos();
+        let alpha = sin / (2.0 * quality_factor);
+        Self::new(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Second order high-pass with the given quality factor.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn high_pass(cutoff_hz: f64, quality_factor: f64, sample_rate_hz: f64) -> Self {
+        let (sin, cos) = (2.0 * PI * cutoff_hz / sample_rate_hz).sin_cos();
+        let alpha = sin / (2.0 * quality_factor);
+        Self::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// First order low-pass, stored as a section with vanishing second order
+    /// coefficients.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn first_order_low_pass(cutoff_hz: f64, sample_rate_hz: f64) -> Self {
+        let k = (PI * cutoff_hz / sample_rate_hz).tan();
+        Self::new([k, k, 0.0], [1.0 + k, k - 1.0, 0.0])
+    }
+
+    /// First order high-pass, stored as a section with vanishing second order
+    /// coefficients.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn first_order_high_pass(cutoff_hz: f64, sample_rate_hz: f64) -> Self {
+        let k = (PI * cutoff_hz / sample_rate_hz).tan();
+        Self::new([1.0, -1.0, 0.0], [1.0 + k, k - 1.0, 0.0])
+    }
+
+    /// Notch at the given frequency with the given quality factor.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn notch(frequency_hz: f64, quality_factor: f64, sample_rate_hz: f64) -> Self {
+        let (sin, cos) = (2.0 * PI * frequency_hz / sample_rate_hz).sin_cos();
+        let alpha = sin / (2.0 * quality_factor);
+        Self::new(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// Filters the signal in place using the transposed direct form II.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn process(&self, signal: &mut [f64]) {
+        let (mut z1, mut z2) = (0.0, 0.0);
+        for sample in signal.iter_mut() {
+            let input = *sample;
+            let output = self.b[0].mul_add(input, z1);
+            z1 = self.b[1].mul_add(input, -self.a[0] * output) + z2;
+            z2 = self.b[2].mul_add(input, -self.a[1] * output);
+            *sample = output;
+        }
+    }
+}
+
+/// A cascade of second order sections.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FilterChain(Vec<Biquad>);
+
+impl FilterChain {
+    /// Creates the filter chain described by the preprocessing config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a cutoff or notch frequency is not between zero
+    /// and the Nyquist frequency, the band-pass edges are not ordered, or the
+    /// band-pass order or notch quality factor is zero.
+    #[tracing::instrument(level = "debug")]
+    pub fn from_config(config: &Preprocessing, sample_rate_hz: f32) -> Result<Self> {
+        debug!("Creating filter chain from preprocessing config");
+        let sample_rate_hz = f64::from(sample_rate_hz);
+        let nyquist_hz = sample_rate_hz / 2.0;
+        let mut sections = Vec::new();
+        if config.band_pass {
+            let low_hz = f64::from(config.band_pass_low_hz);
+            let high_hz = f64::from(config.band_pass_high_hz);
+            ensure!(
+                0.0 < low_hz && low_hz < high_hz && high_hz < nyquist_hz,
+                "Band-pass edges {low_hz} Hz and {high_hz} Hz must satisfy \
+                 0 < low < high < {nyquist_hz} Hz"
+            );
+            ensure!(
+                config.band_pass_order > 0,
+                "Band-pass order must be positive"
+            );
+            sections.extend(butterworth(
+                config.band_pass_order,
+                |q| Biquad::high_pass(low_hz, q, sample_rate_hz),
+                || Biquad::first_order_high_pass(low_hz, sample_rate_hz),
+            ));
+            sections.extend(butterworth(
+                config.band_pass_order,
+                |q| Biquad::low_pass(high_hz, q, sample_rate_hz),
+                || Biquad::first_order_low_pass(high_hz, sample_rate_hz),
+            ));
+        }
+        if config.notch {
+            let frequency_hz = f64::from(config.notch_frequency_hz);
+            ensure!(
+                0.0 < frequency_hz && frequency_hz < nyquist_hz,
+                "Notch frequency {frequency_hz} Hz must be between 0 and {nyquist_hz} Hz"
+            );
+            ensure!(
+                config.notch_quality_factor > 0.0,
+                "Notch quality factor must be positive"
+            );
+            sections.push(Biquad::notch(
+                frequency_hz,
+                f64::from(config.notch_quality_factor),
+                sample_rate_hz,
+            ));
+        }
+        Ok(Self(sections))
+    }
+
+    /// Returns true if the chain contains no sections.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Filters the signal forward and backward, resulting in zero phase
+    /// shift and the squared magnitude response of the chain.
+    ///
+    /// The signal is extended by an odd reflection at both ends to reduce
+    /// the transients at the edges.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn filtfilt(&self, signal: ArrayView1<f32>) -> Array1<f32> {
+        trace!("Applying zero-phase filter");
+        let length = signal.len();
+        if self.is_empty() || length < 2 {
+            return signal.to_owned();
+        }
+        let padding = (6 * self.0.len()).min(length - 1);
+        let first = f64::from(signal[0]);
+        let last = f64::from(signal[length - 1]);
+        let mut extended: Vec<f64> = Vec::with_capacity(length + 2 * padding);
+        extended.extend(
+            (1..=padding)
+                .rev()
+                .map(|i| 2.0f64.mul_add(first, -f64::from(signal[i]))),
+        );
+        extended.extend(signal.iter().map(|&v| f64::from(v)));
+        extended.extend(
+            (1..=padding).map(|i| 2.0f64.mul_add(last, -f64::from(signal[length - 1 - i]))),
+        );
+
+        for section in &self.0 {
+            section.process(&mut extended);
+        }
+        extended.reverse();
+        for section in &self.0 {
+            section.process(&mut extended);
+        }
+        extended.reverse();
+
+        extended[padding..padding + length]
+            .iter()
+            .map(|&v| v as f32)
+            .collect()
+    }
+
+    /// Filters every sensor of every beat of the measurements.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn apply(&self, measurements: &mut Measurements) {
+        debug!("Filtering measurements");
+        if self.is_empty() {
+            return;
+        }
+        for beat in 0..measurements.num_beats() {
+            for sensor in 0..measurements.num_sensors() {
+                let filtered = self.filtfilt(measurements.slice(s![beat, .., sensor]));
+                measurements
+                    .slice_mut(s![beat, .., sensor])
+                    .assign(&filtered);
+            }
+        }
+    }
+}
+
+/// Filters the measurements as configured in the preprocessing config.
+///
+/// # Errors
+///
+/// Returns an error if the filter configuration is invalid.
+#[tracing::instrument(level = "debug", skip(measurements))]
+pub fn preprocess(
+    measurements: &mut Measurements,
+    config: &Preprocessing,
+    sample_rate_hz: f32,
+) -> Result<()> {
+    debug!("Preprocessing measurements");
+    FilterChain::from_config(config, sample_rate_hz)?.apply(measurements);
+    Ok(())
+}
+
+/// Splits a Butterworth filter of the given order into second order
+/// sections, plus a first order section for odd orders.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn butterworth(
+    order: usize,
+    second_order: impl Fn(f64) -> Biquad,
+    first_order: impl Fn() -> Biquad,
+) -> Vec<Biquad> {
+    let mut sections: Vec<Biquad> = (0..order / 2)
+        .map(|k| {
+            let angle = (2 * k + 1) as f64 * PI / (2 * order) as f64;
+            second_order(1.0 / (2.0 * angle.cos()))
+        })
+        .collect();
+    if order % 2 == 1 {
+        sections.push(first_order());
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sine(frequency_hz: f32, sample_rate_hz: f32, length: usize) -> Array1<f32> {
+        Array1::from_shape_fn(length, |i| {
+            (2.0 * std::f32::consts::PI * frequency_hz * i as f32 / sample_rate_hz).sin()
+        })
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn rms(signal: ArrayView1<f32>) -> f32 {
+        (signal.mapv(|v| v.powi(2)).sum() / signal.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn notch_removes_powerline() -> anyhow::Result<()> {
+        let config = Preprocessing {
+            notch: true,
+            ..Default::default()
+        };
+        let chain = FilterChain::from_config(&config, 1000.0)?;
+        let signal = sine(50.0, 1000.0, 4000);
+
+        let filtered = chain.filtfilt(signal.view());
+
+        // ignore the edges, where the filter settles
+        assert!(rms(filtered.slice(s![1000..3000])) < 0.05);
+        Ok(())
+    }
+
+    #[test]
+    fn band_pass_keeps_pass_band_and_removes_offset() -> anyhow::Result<()> {
+        let config = Preprocessing {
+            band_pass: true,
+            band_pass_low_hz: 1.0,
+            band_pass_high_hz: 100.0,
+            band_pass_order: 3,
+            ..Default::default()
+        };
+        let chain = FilterChain::from_config(&config, 1000.0)?;
+        let in_band = sine(10.0, 1000.0, 4000);
+        let offset = Array1::from_elem(4000, 1.0f32);
+
+        let filtered_in_band = chain.filtfilt(in_band.view());
+        let filtered_offset = chain.filtfilt(offset.view());
+
+        assert_relative_eq!(
+            rms(filtered_in_band.slice(s![1000..3000])),
+            rms(in_band.slice(s![1000..3000])),
+            epsilon = 0.02
+        );
+        assert!(rms(filtered_offset.slice(s![1000..3000])) < 0.05);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_cutoff_is_rejected() {
+        let config = Preprocessing {
+            band_pass: true,
+            band_pass_high_hz: 600.0,
+            ..Default::default()
+        };
+        assert!(FilterChain::from_config(&config, 1000.0).is_err());
+    }
+}
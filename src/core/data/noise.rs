@@ -0,0 +1,184 @@
+use std::f32::consts::PI;
+
+use ndarray::Array1;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use super::shapes::Measurements;
+use crate::core::config::simulation::StructuredNoise as StructuredNoiseConfig;
+
+/// Realization of the structured noise sources of a simulation.
+///
+/// Stores the configuration together with the randomly drawn per-sensor
+/// phases, so that the exact noise added to the measurements can be
+/// reproduced. The 1/f noise is fully determined by the seed in the
+/// configuration.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StructuredNoise {
+    pub config: StructuredNoiseConfig,
+    pub drift_phases_rad: Vec<f32>,
+    pub powerline_phases_rad: Vec<f32>,
+}
+
+impl StructuredNoise {
+    /// Creates structured noise with all sources disabled.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn empty(number_of_sensors: usize) -> Self {
+        debug!("Creating empty structured noise");
+        Self {
+            config: StructuredNoiseConfig::default(),
+            drift_phases_rad: vec![0.0; number_of_sensors],
+            powerline_phases_rad: vec![0.0; number_of_sensors],
+        }
+    }
+
+    /// Draws the per-sensor phases of the sinusoidal sources from the seed
+    /// given in the configuration.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn from_config(config: &StructuredNoiseConfig, number_of_sensors: usize) -> Self {
+        debug!("Creating structured noise from config");
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let drift_phases_rad = (0..number_of_sensors)
+            .map(|_| rng.random_range(0.0..2.0 * PI))
+            .collect();
+        let powerline_phases_rad = (0..number_of_sensors)
+            .map(|_| rng.random_range(0.0..2.0 * PI))
+            .collect();
+        Self {
+            config: config.clone(),
+            drift_phases_rad,
+            powerline_phases_rad,
+        }
+    }
+
+    /// Adds the enabled noise sources to the measurements.
+    ///
+    /// The beats are treated as consecutive recordings, so the sinusoids and
+    /// the 1/f noise continue from one beat to the next.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn apply(&self, measurements: &mut Measurements, sample_rate_hz: f32) {
+        debug!("Applying structured noise");
+        let config = &self.config;
+        let num_beats = measurements.num_beats();
+        let num_steps = measurements.num_steps();
+        let num_samples = num_beats * num_steps;
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed.wrapping_add(1));
+
+        for sensor in 0..measurements.num_sensors() {
+            let pink = if config.pink_noise_amplitude > 0.0 {
+                Some(pink_noise(num_samples, &mut rng) * config.pink_noise_amplitude)
+            } else {
+                None
+            };
+            for beat in 0..num_beats {
+                for step in 0..num_steps {
+                    let sample = beat * num_steps + step;
+                    let time_s = sample as f32 / sample_rate_hz;
+                    let mut noise = config.baseline_drift_amplitude
+                        * (2.0 * PI)
+                            .mul_add(
+                                config.baseline_drift_frequency_hz * time_s,
+                                self.drift_phases_rad[sensor],
+                            )
+                            .sin();
+                    noise += config.powerline_amplitude
+                        * (2.0 * PI)
+                            .mul_add(
+                                config.powerline_frequency_hz * time_s,
+                                self.powerline_phases_rad[sensor],
+                            )
+                            .sin();
+                    if let Some(pink) = pink.as_ref() {
+                        noise += pink[sample];
+                    }
+                    measurements[[beat, step, sensor]] += noise;
+                }
+            }
+        }
+    }
+}
+
+/// Generates 1/f noise with unit RMS by filtering white noise.
+///
+/// Uses Paul Kellet's economy pink noise filter, which is accurate to about
+/// 0.5 dB above a ninth of the sample rate.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(rng))]
+fn pink_noise(number_of_samples: usize, rng: &mut ChaCha8Rng) -> Array1<f32> {
+    trace!("Generating pink noise");
+    let mut b = [0.0f32; 3];
+    let mut noise: Array1<f32> = (0..number_of_samples)
+        .map(|_| {
+            let white: f32 = rng.sample(StandardNormal);
+            b[0] = 0.997_65f32.mul_add(b[0], white * 0.099_046);
+            b[1] = 0.963f32.mul_add(b[1], white * 0.296_516_4);
+            b[2] = 0.57f32.mul_add(b[2], white * 1.052_691_3);
+            white.mul_add(0.1848, b[0] + b[1] + b[2])
+        })
+        .collect();
+    if number_of_samples > 0 {
+        let mean = noise.sum() / number_of_samples as f32;
+        noise -= mean;
+        let rms = (noise.mapv(|v| v.powi(2)).sum() / number_of_samples as f32).sqrt();
+        if rms > 0.0 {
+            noise /= rms;
+        }
+    }
+    noise
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray_stats::QuantileExt;
+
+    use super::*;
+
+    #[test]
+    fn disabled_noise_leaves_measurements_unchanged() {
+        let mut measurements = Measurements::empty(2, 100, 3);
+        let noise = StructuredNoise::from_config(&StructuredNoiseConfig::default(), 3);
+
+        noise.apply(&mut measurements, 1000.0);
+
+        assert_relative_eq!(*measurements.max_skipnan(), 0.0);
+        assert_relative_eq!(*measurements.min_skipnan(), 0.0);
+    }
+
+    #[test]
+    fn pink_noise_has_requested_rms() {
+        let config = StructuredNoiseConfig {
+            pink_noise_amplitude: 2.0,
+            ..Default::default()
+        };
+        let mut measurements = Measurements::empty(1, 10_000, 1);
+        let noise = StructuredNoise::from_config(&config, 1);
+
+        noise.apply(&mut measurements, 1000.0);
+
+        let rms = (measurements.mapv(|v| v.powi(2)).sum() / 10_000.0).sqrt();
+        assert_relative_eq!(rms, 2.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn powerline_is_bounded_by_amplitude() {
+        let config = StructuredNoiseConfig {
+            powerline_amplitude: 0.5,
+            ..Default::default()
+        };
+        let mut measurements = Measurements::empty(1, 1000, 2);
+        let noise = StructuredNoise::from_config(&config, 2);
+
+        noise.apply(&mut measurements, 1000.0);
+
+        assert!(*measurements.max_skipnan() <= 0.5);
+        assert!(*measurements.max_skipnan() > 0.49);
+        assert!(*measurements.min_skipnan() >= -0.5);
+    }
+}
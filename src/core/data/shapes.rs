@@ -640,3 +640,67 @@ impl DerefMut for Residuals {
         &mut self.0
     }
 }
+
+/// Shape for the channel mask of the residuals.
+///
+/// Has dimensions (`number_of_sensors`). Contains one for channels used by
+/// the algorithms and zero for excluded channels.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ChannelMask(Array1<f32>);
+
+impl ChannelMask {
+    /// Creates a `ChannelMask` that includes all channels.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn empty(sensors: usize) -> Self {
+        trace!("Creating empty channel mask");
+        Self(Array1::ones(sensors))
+    }
+
+    /// Excludes the given channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a channel index is out of range.
+    #[tracing::instrument(level = "trace")]
+    pub fn exclude(&mut self, channels: &[usize]) -> Result<()> {
+        trace!("Excluding channels from channel mask");
+        let number_of_sensors = self.len();
+        for &channel in channels {
+            *self.get_mut(channel).with_context(|| {
+                format!("Channel {channel} is out of range for {number_of_sensors} sensors")
+            })? = 0.0;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub(crate) fn to_gpu(&self, queue: &ocl::Queue) -> Result<ocl::Buffer<f32>> {
+        let buffer = ocl::Buffer::builder()
+            .queue(queue.clone())
+            .len(self.len())
+            .copy_host_slice(
+                self.as_slice()
+                    .context("Failed to get array slice for GPU copy")?,
+            )
+            .build()
+            .context("Failed to build GPU buffer for channel mask")?;
+        Ok(buffer)
+    }
+}
+
+impl Deref for ChannelMask {
+    type Target = Array1<f32>;
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ChannelMask {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -1,25 +1,34 @@
 #[cfg(test)]
 mod tests;
 
+use std::sync::mpsc::Sender;
+
 use anyhow::{Context, Result};
-use ndarray::Dim;
+use ndarray::{s, Array2, Dim};
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, trace};
 
-use super::shapes::{
-    ActivationTimePerStateMs, SystemStates, SystemStatesSpherical, SystemStatesSphericalMax,
+use super::{
+    faults::SensorFaults,
+    noise::StructuredNoise,
+    shapes::{
+        ActivationTimePerStateMs, SystemStates, SystemStatesSpherical, SystemStatesSphericalMax,
+    },
 };
 use crate::core::{
     algorithm::{
         estimation::{prediction::calculate_system_prediction, Estimations},
         refinement::derivation::{calculate_average_delays, AverageDelays},
     },
-    config::{model::SensorArrayMotion, simulation::Simulation as SimulationConfig},
+    config::{
+        model::SensorArrayMotion,
+        simulation::{BeatVariability, Simulation as SimulationConfig},
+    },
     data::Measurements,
-    model::Model,
+    model::{functional::measurement::MeasurementMatrix, Model},
 };
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -32,6 +41,12 @@ pub struct Simulation {
     pub average_delays: AverageDelays,
     pub sample_rate_hz: f32,
     pub model: Model,
+    // only needed while running the simulation, the realized per-beat
+    // factors are logged instead of stored
+    #[serde(skip)]
+    pub beat_variability: BeatVariability,
+    pub structured_noise: StructuredNoise,
+    pub sensor_faults: SensorFaults,
 }
 impl Simulation {
     /// Creates an empty Simulation with the given dimensions and number of
@@ -44,6 +59,7 @@ impl Simulation {
         number_of_steps: usize,
         voxels_in_dims: Dim<[usize; 3]>,
         sensor_motion_steps: usize,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating empty simulation");
         Self {
@@ -67,7 +83,11 @@ impl Simulation {
                 number_of_steps,
                 voxels_in_dims,
                 sensor_motion_steps,
+                neighborhood_radius,
             ),
+            beat_variability: BeatVariability::default(),
+            structured_noise: StructuredNoise::empty(number_of_sensors),
+            sensor_faults: SensorFaults::empty(),
         }
     }
 
@@ -97,6 +117,7 @@ impl Simulation {
                 .sensor_array_motion_steps
                 .iter()
                 .product(),
+            SensorArrayMotion::Trajectory => 1,
         };
 
         let measurements = Measurements::empty(number_of_beats, number_of_steps, number_of_sensors);
@@ -116,26 +137,62 @@ impl Simulation {
             average_delays,
             sample_rate_hz: config.sample_rate_hz,
             model,
+            beat_variability: config.beat_variability.clone(),
+            structured_noise: StructuredNoise::from_config(
+                &config.structured_noise,
+                number_of_sensors,
+            ),
+            sensor_faults: SensorFaults::from_config(&config.sensor_faults, number_of_sensors),
         })
     }
 
-    /// Runs a simulation by calculating system predictions, adding measurement
-    /// noise, and storing results in the measurements and `system_states` fields.
+    /// Runs a simulation by calculating system predictions, applying the beat
+    /// variability, adding gaussian and structured measurement noise,
+    /// applying the sensor faults, and storing results in the measurements
+    /// and `system_states` fields.
     ///
     /// # Errors
     ///
-    /// Returns an error if measurement noise configuration fails (negative covariance values).
+    /// Returns an error if measurement noise configuration fails (negative covariance values)
+    /// or the beat variability is invalid.
     #[tracing::instrument(level = "info", skip_all)]
     pub fn run(&mut self) -> Result<()> {
+        self.run_reporting(None)
+    }
+
+    /// Runs the simulation like [`Simulation::run`] and sends the fraction
+    /// of simulated time steps whenever another percent of the beats has
+    /// been simulated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if measurement noise configuration fails (negative covariance values)
+    /// or the beat variability is invalid.
+    #[tracing::instrument(level = "info", skip_all)]
+    pub fn run_with_progress(&mut self, progress_tx: &Sender<f32>) -> Result<()> {
+        self.run_reporting(Some(progress_tx))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "info", skip_all)]
+    fn run_reporting(&mut self, progress_tx: Option<&Sender<f32>>) -> Result<()> {
         info!("Running simulation");
 
+        let neighborhood_radius = self
+            .model
+            .functional_description
+            .ap_params
+            .neighborhood_radius()?;
         let mut estimations = Estimations::empty(
             self.system_states.num_states(),
             self.measurements.num_sensors(),
             self.measurements.num_steps(),
             self.measurements.num_beats(),
+            neighborhood_radius,
         );
 
+        let number_of_steps = self.measurements.num_beats() * self.measurements.num_steps();
+        let mut reported_percent = 0;
         for beat in 0..self.measurements.num_beats() {
             estimations.reset();
             for step in 0..self.measurements.num_steps() {
@@ -145,11 +202,20 @@ impl Simulation {
                     beat,
                     step,
                 )?;
+                if let Some(progress_tx) = progress_tx {
+                    let simulated_steps = beat * self.measurements.num_steps() + step + 1;
+                    let percent = simulated_steps * 100 / number_of_steps;
+                    if percent > reported_percent {
+                        reported_percent = percent;
+                        let _ = progress_tx.send(simulated_steps as f32 / number_of_steps as f32);
+                    }
+                }
             }
         }
 
         self.measurements.assign(&*estimations.measurements);
         self.system_states.assign(&*estimations.system_states);
+        self.apply_beat_variability()?;
 
         let mut rng = ChaCha8Rng::seed_from_u64(42);
         for sensor_index in 0..self.measurements.num_sensors() {
@@ -168,10 +234,85 @@ impl Simulation {
                 }
             }
         }
+        self.structured_noise
+            .apply(&mut self.measurements, self.sample_rate_hz);
+        self.sensor_faults.apply(&mut self.measurements);
         self.calculate_plotting_arrays()?;
         Ok(())
     }
 
+    /// Applies the configured beat variability to the noise free measurements.
+    ///
+    /// For each beat the measurements are recalculated with a displaced sensor
+    /// array, stretched in time by the heart-rate factor and scaled by the
+    /// amplitude factor. The measurement matrix of the model is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any standard deviation is negative or not finite,
+    /// or if the displaced measurement matrix cannot be calculated.
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn apply_beat_variability(&mut self) -> Result<()> {
+        if !self.beat_variability.is_enabled() {
+            return Ok(());
+        }
+        debug!("Applying beat variability");
+        let variability = &self.beat_variability;
+        let mut rng = ChaCha8Rng::seed_from_u64(variability.seed);
+        let heart_rate_dist = Normal::new(1.0, variability.heart_rate_jitter_std)
+            .context("Invalid heart rate jitter standard deviation")?;
+        let amplitude_dist = Normal::new(1.0, variability.amplitude_scaling_std)
+            .context("Invalid amplitude scaling standard deviation")?;
+        let displacement_dist = Normal::new(0.0, variability.sensor_displacement_std_mm)
+            .context("Invalid sensor displacement standard deviation")?;
+
+        let displaced_measurement_matrix = if variability.sensor_displacement_std_mm > 0.0 {
+            let mut spatial_description = self.model.spatial_description.clone();
+            spatial_description
+                .sensors
+                .array_offsets_mm
+                .mapv_inplace(|offset| offset + displacement_dist.sample(&mut rng));
+            Some(MeasurementMatrix::from_model_spatial_description(
+                &spatial_description,
+            )?)
+        } else {
+            None
+        };
+
+        let interpolation = &self.model.functional_description.measurement_interpolation;
+        for beat in 0..self.measurements.num_beats() {
+            let clean = displaced_measurement_matrix.as_ref().map_or_else(
+                || self.measurements.slice(s![beat, .., ..]).to_owned(),
+                |matrix| {
+                    let mut clean = Array2::zeros((
+                        self.measurements.num_steps(),
+                        self.measurements.num_sensors(),
+                    ));
+                    for (step, mut measurements) in clean.outer_iter_mut().enumerate() {
+                        measurements.assign(&matrix.predict(
+                            interpolation,
+                            &self.system_states.at_step(step),
+                            beat,
+                            step,
+                        ));
+                    }
+                    clean
+                },
+            );
+            // keep the factors positive, even for unreasonably large deviations
+            let heart_rate_factor = heart_rate_dist.sample(&mut rng).max(0.1);
+            let amplitude_factor = amplitude_dist.sample(&mut rng).max(0.0);
+            debug!(
+                "Beat {beat}: heart rate factor {heart_rate_factor}, amplitude factor {amplitude_factor}"
+            );
+            let varied = stretch_in_time(&clean, heart_rate_factor) * amplitude_factor;
+            self.measurements
+                .slice_mut(s![beat, .., ..])
+                .assign(&varied);
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     pub(crate) fn calculate_plotting_arrays(&mut self) -> anyhow::Result<()> {
         let system_states = &mut self.system_states;
@@ -207,3 +348,34 @@ impl Simulation {
         self.model.update_activation_time(&self.activation_times);
     }
 }
+
+/// Resamples the measurements of a single beat, with dimensions
+/// (`number_of_steps`, `number_of_sensors`), so that the beat runs faster
+/// (`factor` > 1) or slower (`factor` < 1).
+///
+/// Uses linear interpolation and holds the last sample once the stretched
+/// beat runs past the end of the original one.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace", skip(measurements))]
+fn stretch_in_time(measurements: &Array2<f32>, factor: f32) -> Array2<f32> {
+    let number_of_steps = measurements.shape()[0];
+    let mut stretched = Array2::zeros(measurements.raw_dim());
+    if number_of_steps == 0 {
+        return stretched;
+    }
+    let last_step = number_of_steps - 1;
+    for step in 0..number_of_steps {
+        let position = (step as f32 * factor).min(last_step as f32);
+        let lower = position.floor() as usize;
+        let upper = (lower + 1).min(last_step);
+        let weight = position - lower as f32;
+        let interpolated = &measurements.slice(s![lower, ..]) * (1.0 - weight)
+            + &measurements.slice(s![upper, ..]) * weight;
+        stretched.slice_mut(s![step, ..]).assign(&interpolated);
+    }
+    stretched
+}
@@ -14,7 +14,7 @@ use crate::{
             line::{plot_state_xyz, standard_time_plot},
             states::states_spherical_plot,
         },
-        PlotSlice, StateSphericalPlotMode,
+        PlotSlice, PlotTheme, StateSphericalPlotMode,
     },
 };
 
@@ -31,6 +31,19 @@ fn create_simulation_no_crash() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn stretch_in_time_interpolates_and_holds_last_sample() {
+    let measurements = ndarray::arr2(&[[0.0], [1.0], [2.0], [3.0]]);
+
+    let unchanged = stretch_in_time(&measurements, 1.0);
+    let faster = stretch_in_time(&measurements, 1.5);
+
+    assert_eq!(unchanged, measurements);
+    assert_relative_eq!(faster[(1, 0)], 1.5);
+    assert_relative_eq!(faster[(2, 0)], 3.0);
+    assert_relative_eq!(faster[(3, 0)], 3.0);
+}
+
 #[test]
 #[ignore = "expensive integration test"]
 fn run_simulation_default() -> anyhow::Result<()> {
@@ -44,6 +57,21 @@ fn run_simulation_default() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+#[ignore = "expensive integration test"]
+fn run_simulation_reports_progress() -> anyhow::Result<()> {
+    let config = &SimulationConfig::default();
+    let mut simulation = Simulation::from_config(config)?;
+    let (progress_tx, progress_rx) = std::sync::mpsc::channel();
+    simulation.run_with_progress(&progress_tx)?;
+    let progress: Vec<f32> = progress_rx.try_iter().collect();
+    assert!(!progress.is_empty());
+    assert!(progress.len() <= 100);
+    assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_relative_eq!(progress[progress.len() - 1], 1.0);
+    Ok(())
+}
+
 #[test]
 #[ignore = "expensive integration test"]
 #[allow(clippy::too_many_lines)]
@@ -71,6 +99,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         config.sample_rate_hz,
         path.as_path(),
         "Simulated Current Density Sinoatrial Node",
+        PlotTheme::Light,
     )?;
 
     let av_index = simulation
@@ -86,6 +115,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         config.sample_rate_hz,
         path.as_path(),
         "Simulated Current Density Atrioventricular Node",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_x.png");
@@ -95,6 +125,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - x",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_y.png");
@@ -104,6 +135,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - y",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_z.png");
@@ -113,6 +145,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - z",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let time_index = simulation.system_states.shape()[0] / 3;
@@ -129,6 +162,8 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(time_index),
         Some((0.0, 1.0)),
+        None,
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("states_max.png");
@@ -143,6 +178,8 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         None,
         None,
+        None,
+        PlotTheme::Light,
     )?;
 
     let fps = 20;
@@ -161,6 +198,7 @@ fn run_simulation_default_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(playback_speed),
         Some(fps),
+        PlotTheme::Light,
     )?;
     Ok(())
 }
@@ -207,6 +245,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         config.sample_rate_hz,
         path.as_path(),
         "Simulated Current Density Sinoatrial Node",
+        PlotTheme::Light,
     )?;
 
     let av_index = simulation
@@ -222,6 +261,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         config.sample_rate_hz,
         path.as_path(),
         "Simulated Current Density Atrioventricular Node",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_x.png");
@@ -231,6 +271,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - x",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_y.png");
@@ -240,6 +281,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - y",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_z.png");
@@ -249,6 +291,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - z",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let time_index = simulation.system_states.shape()[0] / 3;
@@ -265,6 +308,8 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(time_index),
         None,
+        None,
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("states_max.png");
@@ -279,6 +324,8 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         None,
         None,
+        None,
+        PlotTheme::Light,
     )?;
 
     let fps = 20;
@@ -296,6 +343,7 @@ fn run_simulation_pathological_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(playback_speed),
         Some(fps),
+        PlotTheme::Light,
     )?;
     Ok(())
 }
@@ -397,6 +445,7 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         config.sample_rate_hz,
         path.as_path(),
         "Simulated Current Density Sinoatrial Node",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_x.png");
@@ -406,6 +455,7 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - x",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_y.png");
@@ -415,6 +465,7 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - y",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("sensor_0_z.png");
@@ -424,6 +475,7 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         path.as_path(),
         "Simulated Measurement Sensor 0 - z",
         "H [pT]",
+        PlotTheme::Light,
     )?;
 
     let time_index = simulation.system_states.shape()[0] / 3;
@@ -440,6 +492,8 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(time_index),
         None,
+        None,
+        PlotTheme::Light,
     )?;
 
     let path = folder.join("states_max.png");
@@ -454,6 +508,8 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         None,
         None,
+        None,
+        PlotTheme::Light,
     )?;
 
     let fps = 20;
@@ -471,6 +527,7 @@ fn run_simulation_mri_and_plot() -> anyhow::Result<()> {
         Some(StateSphericalPlotMode::ABS),
         Some(playback_speed),
         Some(fps),
+        PlotTheme::Light,
     )?;
     Ok(())
 }
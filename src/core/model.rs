@@ -13,7 +13,7 @@ use self::{
     spatial::SpatialDescription,
 };
 use super::{
-    config::{model::Model as ModelConfig, simulation::Simulation},
+    config::{algorithm::GpuPrecision, model::Model as ModelConfig, simulation::Simulation},
     data::Data,
 };
 
@@ -28,6 +28,15 @@ pub struct ModelGPU {
     pub functional_description: FunctionalDescriptionGPU,
 }
 
+impl ModelGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        self.functional_description.memory_bytes()
+    }
+}
+
 impl Model {
     /// Creates an empty `Model` with the given parameters.
     #[must_use]
@@ -38,6 +47,7 @@ impl Model {
         number_of_steps: usize,
         voxels_in_dims: Dim<[usize; 3]>,
         number_of_beats: usize,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating empty model");
         Self {
@@ -47,6 +57,7 @@ impl Model {
                 number_of_steps,
                 number_of_beats,
                 voxels_in_dims,
+                neighborhood_radius,
             ),
             spatial_description: SpatialDescription::empty(
                 number_of_sensors,
@@ -95,6 +106,15 @@ impl Model {
                 .functional_description
                 .measurement_matrix,
         );
+        self.functional_description
+            .measurement_interpolation
+            .clone_from(
+                &data
+                    .simulation
+                    .model
+                    .functional_description
+                    .measurement_interpolation,
+            );
         self.functional_description.measurement_covariance.assign(
             &*data
                 .simulation
@@ -118,6 +138,10 @@ impl Model {
                 .sensors
                 .positions_mm,
         );
+        self.spatial_description
+            .sensors
+            .modalities
+            .clone_from(&data.simulation.model.spatial_description.sensors.modalities);
     }
 
     /// Saves the functional and spatial descriptions of the model
@@ -149,9 +173,9 @@ impl Model {
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn to_gpu(&self, queue: &ocl::Queue) -> Result<ModelGPU> {
+    pub fn to_gpu(&self, queue: &ocl::Queue, precision: GpuPrecision) -> Result<ModelGPU> {
         Ok(ModelGPU {
-            functional_description: self.functional_description.to_gpu(queue)?,
+            functional_description: self.functional_description.to_gpu(queue, precision)?,
         })
     }
 
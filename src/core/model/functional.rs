@@ -10,18 +10,23 @@ use tracing::{debug, trace};
 
 use self::{
     allpass::{APParameters, APParametersGPU},
-    control::{ControlFunction, ControlMatrix},
-    measurement::{MeasurementCovariance, MeasurementMatrix},
+    control::{ControlFunction, ControlMatrix, ControlOnsets},
+    measurement::{MeasurementCovariance, MeasurementInterpolation, MeasurementMatrix},
 };
 use super::spatial::SpatialDescription;
-use crate::core::config::model::Model;
+use crate::core::{
+    algorithm::gpu::buffer_bytes,
+    config::{algorithm::GpuPrecision, model::Model},
+};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions)]
 pub struct FunctionalDescription {
     pub ap_params: APParameters,
     pub measurement_matrix: MeasurementMatrix,
+    pub measurement_interpolation: MeasurementInterpolation,
     pub control_matrix: ControlMatrix,
+    pub control_onsets: ControlOnsets,
     pub measurement_covariance: MeasurementCovariance,
     pub control_function_values: ControlFunction,
 }
@@ -30,10 +35,25 @@ pub struct FunctionalDescriptionGPU {
     pub ap_params: APParametersGPU,
     pub measurement_matrix: Buffer<f32>,
     pub control_matrix: Buffer<f32>,
+    pub control_onsets: Buffer<i32>,
     pub measurement_covariance: Buffer<f32>,
     pub control_function_values: Buffer<f32>,
 }
 
+impl FunctionalDescriptionGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        self.ap_params.memory_bytes()
+            + buffer_bytes(&self.measurement_matrix)
+            + buffer_bytes(&self.control_matrix)
+            + buffer_bytes(&self.control_onsets)
+            + buffer_bytes(&self.measurement_covariance)
+            + buffer_bytes(&self.control_function_values)
+    }
+}
+
 impl FunctionalDescription {
     /// Creates an empty `FunctionalDescription` with the given dimensions.
     ///
@@ -47,16 +67,19 @@ impl FunctionalDescription {
         number_of_steps: usize,
         number_of_motion_steps: usize,
         voxels_in_dims: Dim<[usize; 3]>,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating empty functional description");
         Self {
-            ap_params: APParameters::empty(number_of_states, voxels_in_dims),
+            ap_params: APParameters::empty(number_of_states, voxels_in_dims, neighborhood_radius),
             measurement_matrix: MeasurementMatrix::empty(
                 number_of_motion_steps,
                 number_of_states,
                 number_of_sensors,
             ),
+            measurement_interpolation: MeasurementInterpolation::empty(),
             control_matrix: ControlMatrix::empty(number_of_states),
+            control_onsets: ControlOnsets::empty(number_of_states),
             measurement_covariance: MeasurementCovariance::empty(number_of_sensors),
             control_function_values: ControlFunction::empty(number_of_steps),
         }
@@ -85,7 +108,11 @@ impl FunctionalDescription {
             APParameters::from_model_config(config, spatial_description, sample_rate_hz)?;
         let measurement_matrix =
             MeasurementMatrix::from_model_spatial_description(spatial_description)?;
+        let measurement_interpolation =
+            MeasurementInterpolation::from_model_config(config, sample_rate_hz, duration_s);
         let control_matrix = ControlMatrix::from_model_config(config, spatial_description)?;
+        let control_onsets =
+            ControlOnsets::from_model_config(config, spatial_description, sample_rate_hz)?;
         let measurement_covariance =
             MeasurementCovariance::from_model_config(config, spatial_description)?;
         let control_function_values =
@@ -94,7 +121,9 @@ impl FunctionalDescription {
         Ok(Self {
             ap_params,
             measurement_matrix,
+            measurement_interpolation,
             control_matrix,
+            control_onsets,
             measurement_covariance,
             control_function_values,
         })
@@ -116,17 +145,23 @@ impl FunctionalDescription {
         self.ap_params.save_npy(path)?;
         self.measurement_matrix.save_npy(path)?;
         self.control_matrix.save_npy(path)?;
+        self.control_onsets.save_npy(path)?;
         self.measurement_covariance.save_npy(path)?;
         self.control_function_values.save_npy(path)?;
         Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn to_gpu(&self, queue: &Queue) -> Result<FunctionalDescriptionGPU> {
+    pub fn to_gpu(
+        &self,
+        queue: &Queue,
+        precision: GpuPrecision,
+    ) -> Result<FunctionalDescriptionGPU> {
         Ok(FunctionalDescriptionGPU {
-            ap_params: self.ap_params.to_gpu(queue)?,
+            ap_params: self.ap_params.to_gpu(queue, precision)?,
             measurement_matrix: self.measurement_matrix.to_gpu(queue)?,
             control_matrix: self.control_matrix.to_gpu(queue)?,
+            control_onsets: self.control_onsets.to_gpu(queue)?,
             measurement_covariance: self.measurement_covariance.to_gpu(queue)?,
             control_function_values: self.control_function_values.to_gpu(queue)?,
         })
@@ -162,7 +197,7 @@ mod tests {
         let number_of_states = 3000;
         let voxels_in_dims = Dim([1000, 1, 1]);
 
-        let _ap_params = APParameters::empty(number_of_states, voxels_in_dims);
+        let _ap_params = APParameters::empty(number_of_states, voxels_in_dims, 1);
     }
 
     #[test]
@@ -179,6 +214,7 @@ mod tests {
             number_of_steps,
             number_of_motion_steps,
             voxels_in_dims,
+            1,
         );
     }
 
@@ -14,10 +14,15 @@ use tracing::{debug, trace};
 
 use self::{
     delay::calculate_delay_samples_array,
-    shapes::{ActivationTimeMs, Coefs, Gains, Indices, UnitDelays},
+    shapes::{ActivationTimeMs, Coefs, Gains, Indices, Refractory, UnitDelays},
 };
+use super::control::pacing_site_voxels;
 use crate::core::{
-    config::model::Model,
+    algorithm::gpu::{
+        buffer_bytes,
+        precision::{read_stored_buffer, stored_buffer},
+    },
+    config::{algorithm::GpuPrecision, model::Model},
     model::spatial::{
         voxels::{self, VoxelType},
         SpatialDescription,
@@ -33,6 +38,7 @@ pub struct APParameters {
     pub delays: UnitDelays,
     pub initial_delays: Coefs,
     pub activation_time_ms: ActivationTimeMs,
+    pub refractory: Refractory,
 }
 
 pub struct APParametersGPU {
@@ -40,25 +46,62 @@ pub struct APParametersGPU {
     pub output_state_indices: Buffer<i32>,
     pub coefs: Buffer<f32>,
     pub delays: Buffer<i32>,
+    // first step at which each connection passes its input
+    pub refractory_gates: Buffer<i32>,
+    // number of gains per state, passed to the kernels as `NUM_OFFSETS`
+    pub number_of_offsets: i32,
+    // storage precision of the gains
+    pub precision: GpuPrecision,
+}
+
+impl APParametersGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        buffer_bytes(&self.gains)
+            + buffer_bytes(&self.output_state_indices)
+            + buffer_bytes(&self.coefs)
+            + buffer_bytes(&self.delays)
+            + buffer_bytes(&self.refractory_gates)
+    }
 }
 
 impl APParameters {
     #[must_use]
-    /// Creates an empty `APParameters` struct with the given number of states and
-    /// voxel dimensions.
+    /// Creates an empty `APParameters` struct with the given number of states,
+    /// voxel dimensions and neighborhood radius.
     #[tracing::instrument(level = "debug")]
-    pub fn empty(number_of_states: usize, voxels_in_dims: Dim<[usize; 3]>) -> Self {
+    pub fn empty(
+        number_of_states: usize,
+        voxels_in_dims: Dim<[usize; 3]>,
+        neighborhood_radius: usize,
+    ) -> Self {
         debug!("Creating empty AP parameters");
         Self {
-            gains: Gains::empty(number_of_states),
-            output_state_indices: Indices::empty(number_of_states),
-            coefs: Coefs::empty(number_of_states),
-            delays: UnitDelays::empty(number_of_states),
-            initial_delays: Coefs::empty(number_of_states),
+            gains: Gains::empty(number_of_states, neighborhood_radius),
+            output_state_indices: Indices::empty(number_of_states, neighborhood_radius),
+            coefs: Coefs::empty(number_of_states, neighborhood_radius),
+            delays: UnitDelays::empty(number_of_states, neighborhood_radius),
+            initial_delays: Coefs::empty(number_of_states, neighborhood_radius),
             activation_time_ms: ActivationTimeMs::empty(voxels_in_dims),
+            refractory: Refractory::empty(number_of_states, neighborhood_radius),
         }
     }
 
+    /// Returns the neighborhood radius the parameters were created with,
+    /// derived from the number of delays per voxel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the number of delays does not belong to a cubic neighborhood.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn neighborhood_radius(&self) -> Result<usize> {
+        let count = self.delays.shape()[1];
+        neighborhood_radius_from_neighbors(count)
+            .with_context(|| format!("{count} delays per voxel do not form a cubic neighborhood"))
+    }
+
     /// Creates AP parameters from the model config and spatial description.
     ///
     /// Calculates the delay samples and coefficients from the propagation velocities.
@@ -74,9 +117,15 @@ impl APParameters {
         sample_rate_hz: f32,
     ) -> Result<Self> {
         debug!("Creating AP parameters from model config");
+        let neighborhood_radius = config.common.neighborhood_radius;
+        anyhow::ensure!(
+            neighborhood_radius > 0,
+            "The neighborhood radius has to be at least one voxel"
+        );
         let mut ap_params = Self::empty(
             spatial_description.voxels.count_states(),
             spatial_description.voxels.types.raw_dim(),
+            neighborhood_radius,
         );
 
         connect_voxels(spatial_description, config, &mut ap_params)?;
@@ -84,10 +133,13 @@ impl APParameters {
         let delays_samples = calculate_delay_samples_array(
             spatial_description,
             &config.common.propagation_velocities,
+            config.common.transverse_velocity_ratio,
             sample_rate_hz,
+            neighborhood_radius,
         )?;
 
-        ap_params.output_state_indices = init_output_state_indicies(spatial_description)?;
+        ap_params.output_state_indices =
+            init_output_state_indicies(spatial_description, neighborhood_radius)?;
 
         ap_params
             .delays
@@ -103,9 +155,114 @@ impl APParameters {
 
         ap_params.initial_delays = delays_samples;
 
+        init_refractory(
+            &mut ap_params,
+            spatial_description,
+            config.common.refractory_period_ms,
+            sample_rate_hz,
+        );
+
         Ok(ap_params)
     }
 
+    /// Initializes the parameters from parameters learned on a coarser voxel
+    /// grid.
+    ///
+    /// Every voxel takes the gains and delays of the coarse voxel containing
+    /// its center. The delays are scaled by the ratio of the voxel sizes, as
+    /// the same neighbor offset spans a shorter distance on the fine grid.
+    /// Connections that do not exist on both grids keep their values.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parameters use different neighborhood radii.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn resample_from(
+        &mut self,
+        spatial_description: &SpatialDescription,
+        coarse: &Self,
+        coarse_spatial_description: &SpatialDescription,
+    ) -> Result<()> {
+        debug!("Resampling AP parameters from coarse grid");
+        anyhow::ensure!(
+            self.neighborhood_radius()? == coarse.neighborhood_radius()?,
+            "Coarse and fine AP parameters have different neighborhood radii"
+        );
+        let voxels = &spatial_description.voxels;
+        let coarse_voxels = &coarse_spatial_description.voxels;
+        let delay_scaling = voxels.size_mm / coarse_voxels.size_mm;
+        for ((x, y, z), number) in voxels.numbers.indexed_iter() {
+            let Some(state) = *number else {
+                continue;
+            };
+            let position_mm = [
+                voxels.positions_mm[(x, y, z, 0)],
+                voxels.positions_mm[(x, y, z, 1)],
+                voxels.positions_mm[(x, y, z, 2)],
+            ];
+            let Some(coarse_state) = coarse_voxels.containing_voxel_number(position_mm) else {
+                continue;
+            };
+            for delay_index in 0..self.delays.shape()[1] {
+                if self.output_state_indices[(state, delay_index * 3)].is_none()
+                    || coarse.output_state_indices[(coarse_state, delay_index * 3)].is_none()
+                {
+                    continue;
+                }
+                let coarse_index = (coarse_state / 3, delay_index);
+                let samples = (coarse.delays[coarse_index] as f32
+                    + from_coef_to_samples(coarse.coefs[coarse_index]))
+                    * delay_scaling;
+                self.delays[(state / 3, delay_index)] = from_samples_to_usize(samples);
+                self.coefs[(state / 3, delay_index)] = from_samples_to_coef(samples);
+                for input_dimension in 0..3 {
+                    for output_dimension in 0..3 {
+                        self.gains[(state + input_dimension, delay_index * 3 + output_dimension)] =
+                            coarse.gains[(
+                                coarse_state + input_dimension,
+                                delay_index * 3 + output_dimension,
+                            )];
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Initializes the gains and delays from parameters estimated on the
+    /// same voxel grid, e.g. by a previous scenario.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the gains, coefficients or delays have different
+    /// shapes or the voxels are connected differently.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn copy_from(&mut self, source: &Self) -> Result<()> {
+        debug!("Copying AP parameters");
+        anyhow::ensure!(
+            self.gains.shape() == source.gains.shape(),
+            "Gains have shape {:?}, but the source gains have shape {:?}",
+            self.gains.shape(),
+            source.gains.shape()
+        );
+        anyhow::ensure!(
+            self.coefs.shape() == source.coefs.shape()
+                && self.delays.shape() == source.delays.shape(),
+            "Delays have shape {:?}, but the source delays have shape {:?}",
+            self.delays.shape(),
+            source.delays.shape()
+        );
+        anyhow::ensure!(
+            self.output_state_indices == source.output_state_indices,
+            "The voxels of the source parameters are connected differently"
+        );
+        self.gains.assign(&*source.gains);
+        self.coefs.assign(&*source.coefs);
+        self.delays.assign(&*source.delays);
+        Ok(())
+    }
+
     /// Saves the allpass filter parameters to .npy files.
     ///
     /// # Errors
@@ -120,24 +277,29 @@ impl APParameters {
         self.coefs.save_npy(path)?;
         self.delays.save_npy(path)?;
         self.activation_time_ms.save_npy(path)?;
+        self.refractory.save_npy(path)?;
         Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
     #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
-    pub fn to_gpu(&self, queue: &Queue) -> Result<APParametersGPU> {
+    pub fn to_gpu(&self, queue: &Queue, precision: GpuPrecision) -> Result<APParametersGPU> {
         let delays_i32: Vec<i32> = self.delays.iter().map(|&x| x as i32).collect();
+        let gates_i32: Vec<i32> = self
+            .refractory
+            .gates()
+            .iter()
+            .map(|&gate| i32::try_from(gate).unwrap_or(i32::MAX))
+            .collect();
         Ok(APParametersGPU {
-            gains: Buffer::builder()
-                .queue(queue.clone())
-                .len(self.gains.len())
-                .copy_host_slice(
-                    self.gains
-                        .as_slice()
-                        .context("Failed to get gains slice for GPU copy")?,
-                )
-                .build()
-                .context("Failed to create gains GPU buffer")?,
+            gains: stored_buffer(
+                queue,
+                self.gains
+                    .as_slice()
+                    .context("Failed to get gains slice for GPU copy")?,
+                precision,
+            )
+            .context("Failed to create gains GPU buffer")?,
             output_state_indices: Buffer::builder()
                 .queue(queue.clone())
                 .len(self.output_state_indices.len())
@@ -165,21 +327,29 @@ impl APParameters {
                 .copy_host_slice(delays_i32.as_slice())
                 .build()
                 .context("Failed to create delays GPU buffer")?,
+            refractory_gates: Buffer::builder()
+                .queue(queue.clone())
+                .len(gates_i32.len())
+                .copy_host_slice(gates_i32.as_slice())
+                .build()
+                .context("Failed to create refractory gates GPU buffer")?,
+            number_of_offsets: i32::try_from(self.gains.shape()[1])
+                .context("Number of gain offsets exceeds i32::MAX")?,
+            precision,
         })
     }
 
     #[allow(clippy::cast_sign_loss)]
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
     pub(crate) fn update_from_gpu(&mut self, ap_params: &APParametersGPU) -> Result<()> {
-        ap_params
-            .gains
-            .read(
-                self.gains
-                    .as_slice_mut()
-                    .context("Failed to get mutable gains slice for GPU read")?,
-            )
-            .enq()
-            .context("Failed to read gains from GPU buffer")?;
+        read_stored_buffer(
+            &ap_params.gains,
+            self.gains
+                .as_slice_mut()
+                .context("Failed to get mutable gains slice for GPU read")?,
+            ap_params.precision,
+        )
+        .context("Failed to read gains from GPU buffer")?;
         ap_params
             .coefs
             .read(
@@ -209,9 +379,17 @@ impl APParameters {
 /// allows signals to propagate from input voxels to neighboring output voxels
 /// through the allpass filter.
 #[tracing::instrument(level = "debug", skip_all)]
-fn init_output_state_indicies(spatial_description: &SpatialDescription) -> Result<Indices> {
+fn init_output_state_indicies(
+    spatial_description: &SpatialDescription,
+    neighborhood_radius: usize,
+) -> Result<Indices> {
     debug!("Initializing output state indices");
-    let mut output_state_indices = Indices::empty(spatial_description.voxels.count_states());
+    let mut output_state_indices = Indices::empty(
+        spatial_description.voxels.count_states(),
+        neighborhood_radius,
+    );
+    let radius = i32::try_from(neighborhood_radius)
+        .with_context(|| format!("Neighborhood radius {neighborhood_radius} exceeds i32::MAX"))?;
     let v_types = &spatial_description.voxels.types;
     let v_numbers = &spatial_description.voxels.numbers;
     // TODO: write tests
@@ -220,8 +398,9 @@ fn init_output_state_indicies(spatial_description: &SpatialDescription) -> Resul
             continue;
         }
         let (x_in, y_in, z_in) = input_voxel_index;
-        for ((x_offset, y_offset), z_offset) in
-            (-1..=1).cartesian_product(-1..=1).cartesian_product(-1..=1)
+        for ((x_offset, y_offset), z_offset) in (-radius..=radius)
+            .cartesian_product(-radius..=radius)
+            .cartesian_product(-radius..=radius)
         {
             if x_offset == 0 && y_offset == 0 && z_offset == 0 {
                 continue;
@@ -273,7 +452,7 @@ fn init_output_state_indicies(spatial_description: &SpatialDescription) -> Resul
                 })?;
                 let input_state_number = input_base_number + input_direction;
                 for output_dimension in 0..3 {
-                    let gain_index = offset_to_gain_index(x_offset, y_offset, z_offset, output_dimension)
+                    let gain_index = offset_to_gain_index(x_offset, y_offset, z_offset, output_dimension, neighborhood_radius)
                         .with_context(|| format!("Failed to calculate gain index for offset ({x_offset}, {y_offset}, {z_offset}) and output dimension {output_dimension}"))?;
                     let output_base_number = v_numbers[output_voxel_index].with_context(|| {
                         format!("Output voxel at {output_voxel_index:?} has no assigned number")
@@ -304,6 +483,12 @@ fn connect_voxels(
         Array4::<f32>::zeros(spatial_description.voxels.positions_mm.raw_dim());
 
     let v_types = &spatial_description.voxels.types;
+    let radius = i32::try_from(config.common.neighborhood_radius).with_context(|| {
+        format!(
+            "Neighborhood radius {} exceeds i32::MAX",
+            config.common.neighborhood_radius
+        )
+    })?;
 
     let mut current_time_s: f32 = 0.0;
     // Handle Sinoatrial node
@@ -316,6 +501,14 @@ fn connect_voxels(
                 .slice_mut(s![index.0, index.1, index.2, ..])
                 .assign(&arr1(&[1.0, 0.0, 0.0]));
         });
+    // Handle pacing sites, which are driven by the delayed control function
+    // instead of their neighbors
+    for (index, onset_s) in pacing_site_voxels(config, spatial_description)? {
+        activation_time_s[index] = Some(onset_s);
+        current_directions
+            .slice_mut(s![index.0, index.1, index.2, ..])
+            .assign(&arr1(&[1.0, 0.0, 0.0]));
+    }
     let mut connected_something = true;
 
     while connected_something {
@@ -334,9 +527,9 @@ fn connect_voxels(
         let output_voxel_indices = find_candidate_voxels(&activation_time_s, current_time_s);
 
         for output_voxel_index in output_voxel_indices {
-            for x_offset in -1..=1 {
-                for y_offset in -1..=1 {
-                    for z_offset in -1..=1 {
+            for x_offset in -radius..=radius {
+                for y_offset in -radius..=radius {
+                    for z_offset in -radius..=radius {
                         connected_something |= try_to_connect(
                             (x_offset, y_offset, z_offset),
                             output_voxel_index,
@@ -366,6 +559,7 @@ fn connect_voxels(
         .activation_time_ms
         .iter_mut()
         .zip(activation_time_s)
+        .filter(|(ms, _)| ms.is_none())
         .for_each(|(ms, s)| *ms = s.map(|time| time * 1000.0));
     Ok(())
 }
@@ -433,20 +627,23 @@ fn try_to_connect(
     })?;
 
     let input_voxel_index = [x_in_usize, y_in_usize, z_in_usize];
-    // SKip if the input voxel is already connected
-    if activation_time_s[input_voxel_index].is_some() {
-        return Ok(false);
-    }
     let output_voxel_type = &v_types[output_voxel_index];
     let input_voxel_type = &v_types[input_voxel_index];
+    // Skip if the input voxel is already connected, unless it can be
+    // re-activated by a re-entrant wave
+    let last_activation_time = activation_time_s[input_voxel_index];
+    if last_activation_time.is_some() && !is_reentry_allowed(config, *input_voxel_type) {
+        return Ok(false);
+    }
     // Skip if connection is not alowed
     if !voxels::is_connection_allowed(output_voxel_type, input_voxel_type) {
         return Ok(false);
     }
-    // Skip pathologies if the propagation factor is zero
-    if input_voxel_type == &VoxelType::Pathological
-        && relative_eq!(config.common.current_factor_in_pathology, 0.0)
-    {
+    let voxels_in_dims = [v_types.shape()[0], v_types.shape()[1], v_types.shape()[2]];
+    let input_current_factor =
+        config.current_factor(*input_voxel_type, input_voxel_index, voxels_in_dims);
+    // Skip pathologies and border zones if the propagation factor is zero
+    if relative_eq!(input_current_factor, 0.0) {
         return Ok(false);
     }
     // Now we finally found something that we want to connect.
@@ -455,7 +652,17 @@ fn try_to_connect(
     let output_position_mm = &v_position_mm.slice(s![x_out, y_out, z_out, ..]);
     let [x_in, y_in, z_in] = input_voxel_index;
     let input_position_mm = &v_position_mm.slice(s![x_in, y_in, z_in, ..]);
-    let propagation_velocity_m_per_s = config.common.propagation_velocities.get(*input_voxel_type);
+    let input_fiber_direction = &spatial_description
+        .voxels
+        .fibers
+        .slice(s![x_in, y_in, z_in, ..]);
+    let propagation_velocity_m_per_s = delay::calculate_velocity_m_per_s(
+        input_position_mm,
+        output_position_mm,
+        input_fiber_direction,
+        config.common.propagation_velocities.get(*input_voxel_type),
+        config.common.transverse_velocity_ratio,
+    );
     let delay_s = delay::calculate_delay_s(
         input_position_mm,
         output_position_mm,
@@ -465,8 +672,41 @@ fn try_to_connect(
     let output_activation_time = activation_time_s[output_voxel_index].with_context(|| {
         format!("Output voxel at {output_voxel_index:?} has no activation time")
     })?;
-    activation_time_s[input_voxel_index] = Some(output_activation_time + delay_s);
-    let direction = direction::calculate(input_position_mm, output_position_mm);
+    let input_activation_time = output_activation_time + delay_s;
+    if let Some(last_activation_time) = last_activation_time {
+        // a re-activation needs a new connection and a recovered voxel, this
+        // also ends the propagation once all neighbors are connected
+        if input_activation_time - last_activation_time
+            < config.common.refractory_period_ms / 1000.0
+            || is_connected(
+                ap_params,
+                input_state_number,
+                (x_offset, y_offset, z_offset),
+                config.common.neighborhood_radius,
+            )
+        {
+            return Ok(false);
+        }
+        // keep the first activation for the activation time map
+        let first_activation_ms = &mut ap_params.activation_time_ms[input_voxel_index];
+        if first_activation_ms.is_none() {
+            *first_activation_ms = Some(last_activation_time * 1000.0);
+        }
+        let delay_index = offset_to_delay_index(
+            x_offset,
+            y_offset,
+            z_offset,
+            config.common.neighborhood_radius,
+        )
+        .context("Offsets to be valid")?;
+        ap_params.refractory.reentrant[(input_state_number / 3, delay_index)] = true;
+    }
+    activation_time_s[input_voxel_index] = Some(input_activation_time);
+    let direction = direction::apply_fibers(
+        direction::calculate(input_position_mm, output_position_mm),
+        input_fiber_direction,
+        config.common.transverse_velocity_ratio,
+    );
     current_directions
         .slice_mut(s![x_in, y_in, z_in, ..])
         .assign(&direction);
@@ -474,25 +714,84 @@ fn try_to_connect(
         &direction,
         current_directions.slice(s![x_out, y_out, z_out, ..]),
     );
-    if *input_voxel_type == VoxelType::Pathological && *output_voxel_type != VoxelType::Pathological
-    {
-        gain *= config.common.current_factor_in_pathology;
-    }
-    if *output_voxel_type == VoxelType::Pathological && *input_voxel_type != VoxelType::Pathological
-    {
-        gain *= 1.0 / config.common.current_factor_in_pathology;
+    // Scale the current densities when crossing between tissue types
+    if input_voxel_type != output_voxel_type {
+        gain *= input_current_factor
+            / config.current_factor(*output_voxel_type, [x_out, y_out, z_out], voxels_in_dims);
     }
     assign_gain(
         ap_params,
         input_state_number,
-        x_offset,
-        y_offset,
-        z_offset,
+        (x_offset, y_offset, z_offset),
+        config.common.neighborhood_radius,
         &gain,
     );
     Ok(true)
 }
 
+/// Sets the refractory time of every voxel and the first activation of the
+/// voxels in samples, so that the re-entrant connections are blocked until
+/// the voxel has recovered.
+#[tracing::instrument(level = "debug", skip(ap_params, spatial_description))]
+fn init_refractory(
+    ap_params: &mut APParameters,
+    spatial_description: &SpatialDescription,
+    refractory_period_ms: f32,
+    sample_rate_hz: f32,
+) {
+    debug!("Initializing refractory times");
+    let refractory = &mut ap_params.refractory;
+    refractory
+        .samples
+        .fill(refractory_period_ms / 1000.0 * sample_rate_hz);
+    for (activation_time_ms, voxel_number) in ap_params
+        .activation_time_ms
+        .iter()
+        .zip(spatial_description.voxels.numbers.iter())
+    {
+        if let (Some(activation_time_ms), Some(voxel_number)) = (activation_time_ms, voxel_number) {
+            refractory.activation_samples[voxel_number / 3] =
+                activation_time_ms / 1000.0 * sample_rate_hz;
+        }
+    }
+}
+
+/// Whether voxels of the given type can be activated again by a
+/// re-entrant wave.
+#[tracing::instrument(level = "trace")]
+fn is_reentry_allowed(config: &Model, voxel_type: VoxelType) -> bool {
+    config.common.reentry && matches!(voxel_type, VoxelType::Pathological | VoxelType::BorderZone)
+}
+
+/// Whether the input state already receives a current from the voxel at the
+/// given offset.
+#[tracing::instrument(level = "trace", skip(ap_params))]
+fn is_connected(
+    ap_params: &APParameters,
+    input_state_number: usize,
+    voxel_offset: (i32, i32, i32),
+    neighborhood_radius: usize,
+) -> bool {
+    let (x_offset, y_offset, z_offset) = voxel_offset;
+    (0..3).any(|input_dimension| {
+        (0..3).any(|output_dimension| {
+            offset_to_gain_index(
+                x_offset,
+                y_offset,
+                z_offset,
+                output_dimension,
+                neighborhood_radius,
+            )
+            .is_some_and(|gain_index| {
+                !relative_eq!(
+                    ap_params.gains[(input_state_number + input_dimension, gain_index)],
+                    0.0
+                )
+            })
+        })
+    })
+}
+
 /// Assigns the given gain values to the appropriate indices in the
 /// all-pass filter parameter gains array. Maps the gain values from the
 /// (`input_dim`, `output_dim`) coordinate space to the flattened 22D gains array
@@ -501,11 +800,11 @@ fn try_to_connect(
 fn assign_gain(
     ap_params: &mut APParameters,
     input_state_number: usize,
-    x_offset: i32,
-    y_offset: i32,
-    z_offset: i32,
+    voxel_offset: (i32, i32, i32),
+    neighborhood_radius: usize,
     gain: &ndarray::ArrayBase<ndarray::OwnedRepr<f32>, Dim<[usize; 2]>>,
 ) {
+    let (x_offset, y_offset, z_offset) = voxel_offset;
     trace!(
         "Assigning gain {:?} to input state number {}",
         gain,
@@ -515,77 +814,109 @@ fn assign_gain(
         for output_dimension in 0..3 {
             ap_params.gains[(
                 input_state_number + input_dimension,
-                offset_to_gain_index(x_offset, y_offset, z_offset, output_dimension)
-                    .expect("Offsets to be valid"),
+                offset_to_gain_index(
+                    x_offset,
+                    y_offset,
+                    z_offset,
+                    output_dimension,
+                    neighborhood_radius,
+                )
+                .expect("Offsets to be valid"),
             )] = gain[(input_dimension, output_dimension)];
         }
     }
 }
 
+/// Returns the number of neighbors of a voxel for the given neighborhood
+/// radius, i.e. all voxels of the surrounding cube except the voxel itself.
+///
+/// A radius of one gives the 26-voxel neighborhood.
+#[must_use]
+pub const fn number_of_neighbors(neighborhood_radius: usize) -> usize {
+    let side = 2 * neighborhood_radius + 1;
+    side * side * side - 1
+}
+
+/// Converts a number of neighbors back to the neighborhood radius.
+/// Returns None if the number does not belong to a cubic neighborhood.
+#[must_use]
+pub const fn neighborhood_radius_from_neighbors(count: usize) -> Option<usize> {
+    let mut radius = 1;
+    while number_of_neighbors(radius) < count {
+        radius += 1;
+    }
+    if number_of_neighbors(radius) == count {
+        Some(radius)
+    } else {
+        None
+    }
+}
+
 /// Converts the given x, y, z offset values to an index in the 2D gains array.
 ///
 /// The offsets are relative to a given input voxel. The output dimension
 /// indicates which output voxel the gain value is for. Handles converting the
-/// 3D coordinate offsets to 1D index. Returns None if offsets are all zero.
-#[allow(clippy::cast_sign_loss)]
+/// 3D coordinate offsets to 1D index. Returns None if offsets are all zero
+/// or outside of the neighborhood radius.
 #[must_use]
 pub const fn offset_to_gain_index(
     x_offset: i32,
     y_offset: i32,
     z_offset: i32,
     output_dimension: usize,
+    neighborhood_radius: usize,
 ) -> Option<usize> {
-    if x_offset == 0 && y_offset == 0 && z_offset == 0 {
-        return None;
+    match offset_to_delay_index(x_offset, y_offset, z_offset, neighborhood_radius) {
+        Some(delay_index) => Some(delay_index * 3 + output_dimension),
+        None => None,
     }
-    let mut index = output_dimension
-        + (z_offset + 1) as usize * 3
-        + (y_offset + 1) as usize * 9
-        + (x_offset + 1) as usize * 27;
-    if index > 27 + 9 + 3 {
-        index -= 3;
-    }
-    Some(index)
 }
 
 /// Converts a 1D index into the gains array to the corresponding
 /// x, y, z offset values and output dimension. Returns None if the index
 /// is out of bounds of the gains array.
-#[allow(
-    clippy::cast_sign_loss,
-    clippy::cast_possible_truncation,
-    clippy::cast_possible_wrap
-)]
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
 #[must_use]
-pub const fn gain_index_to_offset(gain_index: usize) -> Option<[i32; 4]> {
-    if gain_index > 77 {
-        return None;
+pub const fn gain_index_to_offset(
+    gain_index: usize,
+    neighborhood_radius: usize,
+) -> Option<[i32; 4]> {
+    match delay_index_to_offset(gain_index / 3, neighborhood_radius) {
+        Some([x_offset, y_offset, z_offset]) => {
+            Some([x_offset, y_offset, z_offset, (gain_index % 3) as i32])
+        }
+        None => None,
     }
-    let corrected_index = if gain_index >= 27 + 9 + 3 {
-        gain_index + 3
-    } else {
-        gain_index
-    };
-
-    let output_dimension = (corrected_index % 3) as i32;
-    let z_offset = ((corrected_index / 3) % 3) as i32 - 1;
-    let y_offset = ((corrected_index / 9) % 3) as i32 - 1;
-    let x_offset = ((corrected_index / 27) % 3) as i32 - 1;
-
-    Some([x_offset, y_offset, z_offset, output_dimension])
 }
 
 /// Converts the given x, y, z offset values to a 1D index into the delays array.
-/// Returns None if x, y, z offsets are all 0.
-#[allow(clippy::cast_sign_loss)]
+/// Returns None if x, y, z offsets are all 0 or outside of the neighborhood radius.
+#[allow(
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap
+)]
 #[must_use]
-pub const fn offset_to_delay_index(x_offset: i32, y_offset: i32, z_offset: i32) -> Option<usize> {
-    if x_offset == 0 && y_offset == 0 && z_offset == 0 {
+pub const fn offset_to_delay_index(
+    x_offset: i32,
+    y_offset: i32,
+    z_offset: i32,
+    neighborhood_radius: usize,
+) -> Option<usize> {
+    let radius = neighborhood_radius as i32;
+    if (x_offset == 0 && y_offset == 0 && z_offset == 0)
+        || x_offset.abs() > radius
+        || y_offset.abs() > radius
+        || z_offset.abs() > radius
+    {
         return None;
     }
-    let mut index =
-        (z_offset + 1) as usize + (y_offset + 1) as usize * 3 + (x_offset + 1) as usize * 9;
-    if index > 9 + 3 + 1 {
+    let side = 2 * neighborhood_radius + 1;
+    let mut index = (z_offset + radius) as usize
+        + (y_offset + radius) as usize * side
+        + (x_offset + radius) as usize * side * side;
+    // the voxel itself sits in the center of the cube and is skipped
+    if index > number_of_neighbors(neighborhood_radius) / 2 {
         index -= 1;
     }
     Some(index)
@@ -593,26 +924,28 @@ pub const fn offset_to_delay_index(x_offset: i32, y_offset: i32, z_offset: i32)
 
 /// Converts a 1D index into the delay array to the corresponding
 /// x, y, z offset values. Returns None if the index
-/// is out of bounds of the gains array.
-#[allow(
-    clippy::cast_sign_loss,
-    clippy::cast_possible_truncation,
-    clippy::cast_possible_wrap
-)]
+/// is out of bounds of the delays array.
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
 #[must_use]
-pub const fn delay_index_to_offset(delay_index: usize) -> Option<[i32; 3]> {
-    if delay_index > 26 {
+pub const fn delay_index_to_offset(
+    delay_index: usize,
+    neighborhood_radius: usize,
+) -> Option<[i32; 3]> {
+    let count = number_of_neighbors(neighborhood_radius);
+    if delay_index >= count {
         return None;
     }
-    let corrected_index = if delay_index > 9 + 3 {
+    let corrected_index = if delay_index >= count / 2 {
         delay_index + 1
     } else {
         delay_index
     };
 
-    let z_offset = (corrected_index % 3) as i32 - 1;
-    let y_offset = ((corrected_index / 3) % 3) as i32 - 1;
-    let x_offset = ((corrected_index / 9) % 3) as i32 - 1;
+    let radius = neighborhood_radius as i32;
+    let side = 2 * neighborhood_radius + 1;
+    let z_offset = (corrected_index % side) as i32 - radius;
+    let y_offset = ((corrected_index / side) % side) as i32 - radius;
+    let x_offset = ((corrected_index / (side * side)) % side) as i32 - radius;
 
     Some([x_offset, y_offset, z_offset])
 }
@@ -669,12 +1002,73 @@ pub fn from_coef_to_samples(coef: f32) -> f32 {
 
 #[cfg(test)]
 mod test {
-    use approx::assert_relative_eq;
+    use approx::{assert_relative_eq, relative_eq};
 
     use crate::core::model::functional::allpass::{
-        from_samples_to_coef, from_samples_to_usize, offset_to_gain_index,
+        delay_index_to_offset, from_samples_to_coef, from_samples_to_usize, gain_index_to_offset,
+        neighborhood_radius_from_neighbors, number_of_neighbors, offset_to_delay_index,
+        offset_to_gain_index, APParameters, Model, SpatialDescription,
     };
 
+    #[test]
+    fn reentry_adds_connections_in_border_zone() -> anyhow::Result<()> {
+        let mut config = Model::default();
+        config.common.pathological = true;
+        config
+            .handcrafted
+            .as_mut()
+            .expect("Default model to be handcrafted")
+            .border_zone_width_voxels = 2;
+        config.common.refractory_period_ms = 5.0;
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+        let focal = APParameters::from_model_config(&config, &spatial_description, 2000.0)?;
+        config.common.reentry = true;
+
+        let reentrant = APParameters::from_model_config(&config, &spatial_description, 2000.0)?;
+
+        let count_connections = |ap_params: &APParameters| {
+            ap_params
+                .gains
+                .iter()
+                .filter(|gain| !relative_eq!(**gain, 0.0))
+                .count()
+        };
+        assert!(count_connections(&reentrant) > count_connections(&focal));
+        assert_eq!(
+            reentrant.activation_time_ms.values,
+            focal.activation_time_ms.values
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reentrant_connections_are_gated_by_refractory_time() -> anyhow::Result<()> {
+        let mut config = Model::default();
+        config.common.pathological = true;
+        config
+            .handcrafted
+            .as_mut()
+            .expect("Default model to be handcrafted")
+            .border_zone_width_voxels = 2;
+        config.common.refractory_period_ms = 5.0;
+        config.common.reentry = true;
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+
+        let ap_params = APParameters::from_model_config(&config, &spatial_description, 2000.0)?;
+
+        let refractory = &ap_params.refractory;
+        assert!(refractory.reentrant.iter().any(|reentrant| *reentrant));
+        for ((voxel_index, delay_index), reentrant) in refractory.reentrant.indexed_iter() {
+            let gate = refractory.gate(voxel_index, delay_index);
+            if *reentrant {
+                assert!(gate >= 10);
+            } else {
+                assert_eq!(gate, 0);
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn from_samples_to_usize_1() {
         assert_eq!(1, from_samples_to_usize(1.0));
@@ -697,35 +1091,104 @@ mod test {
     #[test]
     fn offset_to_index_test() {
         let desired = 2;
-        let actual = offset_to_gain_index(-1, -1, -1, 2).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(-1, -1, -1, 2, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 5;
-        let actual = offset_to_gain_index(-1, -1, 0, 2).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(-1, -1, 0, 2, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 8;
-        let actual = offset_to_gain_index(-1, -1, 1, 2).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(-1, -1, 1, 2, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 77;
-        let actual = offset_to_gain_index(1, 1, 1, 2).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(1, 1, 1, 2, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 42;
-        let actual = offset_to_gain_index(0, 1, -1, 0).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(0, 1, -1, 0, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 45;
-        let actual = offset_to_gain_index(0, 1, 0, 0).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(0, 1, 0, 0, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 60;
-        let actual = offset_to_gain_index(1, 0, -1, 0).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(1, 0, -1, 0, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
 
         let desired = 63;
-        let actual = offset_to_gain_index(1, 0, 0, 0).expect("Offsets to be valid.");
+        let actual = offset_to_gain_index(1, 0, 0, 0, 1).expect("Offsets to be valid.");
         assert_eq!(desired, actual);
     }
+
+    #[test]
+    fn number_of_neighbors_per_radius() {
+        assert_eq!(26, number_of_neighbors(1));
+        assert_eq!(124, number_of_neighbors(2));
+        assert_eq!(Some(1), neighborhood_radius_from_neighbors(26));
+        assert_eq!(Some(2), neighborhood_radius_from_neighbors(124));
+        assert_eq!(None, neighborhood_radius_from_neighbors(27));
+    }
+
+    #[test]
+    fn offset_round_trip_radius_2() {
+        let radius = 2;
+        for delay_index in 0..number_of_neighbors(radius) {
+            let [x_offset, y_offset, z_offset] =
+                delay_index_to_offset(delay_index, radius).expect("Index to be valid.");
+            assert_eq!(
+                Some(delay_index),
+                offset_to_delay_index(x_offset, y_offset, z_offset, radius)
+            );
+            assert_eq!(
+                Some([x_offset, y_offset, z_offset, 1]),
+                gain_index_to_offset(delay_index * 3 + 1, radius)
+            );
+        }
+        assert_eq!(
+            None,
+            delay_index_to_offset(number_of_neighbors(radius), radius)
+        );
+        assert_eq!(None, offset_to_delay_index(0, 0, 0, radius));
+        assert_eq!(None, offset_to_delay_index(3, 0, 0, radius));
+        assert_eq!(None, offset_to_gain_index(2, 0, 0, 0, 1));
+    }
+
+    #[test]
+    fn resample_from_same_grid_keeps_parameters() -> anyhow::Result<()> {
+        let config = Model::default();
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+        let ap_params = APParameters::from_model_config(&config, &spatial_description, 2000.0)?;
+        let mut resampled = ap_params.clone();
+        resampled.gains.fill(0.0);
+
+        resampled.resample_from(&spatial_description, &ap_params, &spatial_description)?;
+
+        assert_eq!(ap_params.gains, resampled.gains);
+        assert_eq!(ap_params.delays, resampled.delays);
+        assert_relative_eq!(*ap_params.coefs, *resampled.coefs, epsilon = 1e-4);
+        Ok(())
+    }
+
+    #[test]
+    fn copy_from_checks_shapes() -> anyhow::Result<()> {
+        let config = Model::default();
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+        let ap_params = APParameters::from_model_config(&config, &spatial_description, 2000.0)?;
+        let mut copied = ap_params.clone();
+        copied.gains.fill(0.0);
+
+        copied.copy_from(&ap_params)?;
+        assert_eq!(ap_params.gains, copied.gains);
+
+        let mut coarse_config = config.clone();
+        coarse_config.common.voxel_size_mm *= 2.0;
+        let coarse_spatial_description = SpatialDescription::from_model_config(&coarse_config)?;
+        let coarse =
+            APParameters::from_model_config(&coarse_config, &coarse_spatial_description, 2000.0)?;
+        assert!(copied.copy_from(&coarse).is_err());
+        Ok(())
+    }
 }
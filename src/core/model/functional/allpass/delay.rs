@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use approx::relative_eq;
 use itertools::Itertools;
 use ndarray::{s, ArrayBase, Dim, ViewRepr};
 use tracing::trace;
@@ -22,12 +23,48 @@ pub fn calculate_delay_s(
     distance_norm_m / propagation_velocity_m_per_s
 }
 
+/// Calculates the propagation velocity between the given input and output
+/// positions in a voxel with the given fiber direction.
+///
+/// The velocity along the fibers is the given propagation velocity, across
+/// them it is scaled by the transverse velocity ratio. In between, the
+/// velocities lie on an ellipse. A zero fiber direction gives isotropic
+/// propagation.
+#[tracing::instrument(level = "trace")]
+pub fn calculate_velocity_m_per_s(
+    input_position_mm: &ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>,
+    output_position_mm: &ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>,
+    fiber_direction: &ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>,
+    propagation_velocity_m_per_s: f32,
+    transverse_velocity_ratio: f32,
+) -> f32 {
+    trace!("Calculating propagation velocity");
+    let distance_mm = input_position_mm - output_position_mm;
+    let distance_norm_mm = distance_mm.mapv(|v| v.powi(2)).sum().sqrt();
+    if relative_eq!(distance_norm_mm, 0.0) || fiber_direction.iter().all(|v| relative_eq!(*v, 0.0))
+    {
+        return propagation_velocity_m_per_s;
+    }
+    let cos = distance_mm.dot(fiber_direction) / distance_norm_mm;
+    let sin_squared = cos.mul_add(-cos, 1.0).max(0.0);
+    let transverse_velocity_m_per_s = propagation_velocity_m_per_s * transverse_velocity_ratio;
+    propagation_velocity_m_per_s * transverse_velocity_m_per_s
+        / transverse_velocity_m_per_s
+            .powi(2)
+            .mul_add(
+                cos.powi(2),
+                propagation_velocity_m_per_s.powi(2) * sin_squared,
+            )
+            .sqrt()
+}
+
 /// Calculates an array of delay values in samples for each voxel and its neighborhood,
 /// based on the spatial description, material propagation velocities, and sample rate.
 ///
 /// The delay values are calculated by taking the Euclidean distance between each voxel
 /// and its neighbors, dividing by the propagation velocity to get delay in seconds,
-/// and multiplying by the sample rate to convert to samples.
+/// and multiplying by the sample rate to convert to samples. The propagation velocity
+/// depends on the angle between the connection and the fiber direction of the voxel.
 ///
 /// Returns the 2D array of delay values, with dimensions corresponding to the
 /// voxel numbers and neighbor offsets.
@@ -35,14 +72,22 @@ pub fn calculate_delay_s(
 pub fn calculate_delay_samples_array(
     spatial_description: &SpatialDescription,
     propagation_velocities: &PropagationVelocitiesMPerS,
+    transverse_velocity_ratio: f32,
     sample_rate_hz: f32,
+    neighborhood_radius: usize,
 ) -> Result<Coefs> {
     trace!("Calculating delay samples array");
-    let mut delay_samples_array = Coefs::empty(spatial_description.voxels.count_states());
+    let mut delay_samples_array = Coefs::empty(
+        spatial_description.voxels.count_states(),
+        neighborhood_radius,
+    );
+    let radius = i32::try_from(neighborhood_radius)
+        .with_context(|| format!("Neighborhood radius {neighborhood_radius} exceeds i32::MAX"))?;
 
     let v_types = &spatial_description.voxels.types;
     let v_position_mm = &spatial_description.voxels.positions_mm;
     let v_numbers = &spatial_description.voxels.numbers;
+    let v_fibers = &spatial_description.voxels.fibers;
 
     // Fill the delays_samples tensor
     for (input_voxel_index, v_type) in v_types.indexed_iter() {
@@ -51,8 +96,10 @@ pub fn calculate_delay_samples_array(
         }
         let (x_in, y_in, z_in) = input_voxel_index;
         let input_position_mm = &v_position_mm.slice(s![x_in, y_in, z_in, ..]);
-        for ((x_offset, y_offset), z_offset) in
-            (-1..=1).cartesian_product(-1..=1).cartesian_product(-1..=1)
+        let fiber_direction = &v_fibers.slice(s![x_in, y_in, z_in, ..]);
+        for ((x_offset, y_offset), z_offset) in (-radius..=radius)
+            .cartesian_product(-radius..=radius)
+            .cartesian_product(-radius..=radius)
         {
             if x_offset == 0 && y_offset == 0 && z_offset == 0 {
                 continue;
@@ -84,10 +131,17 @@ pub fn calculate_delay_samples_array(
             ];
             let output_position_mm = &v_position_mm.slice(s![x_out, y_out, z_out, ..]);
 
-            let delay_s = calculate_delay_s(
+            let propagation_velocity_m_per_s = calculate_velocity_m_per_s(
                 input_position_mm,
                 output_position_mm,
+                fiber_direction,
                 propagation_velocities.get(*v_type),
+                transverse_velocity_ratio,
+            );
+            let delay_s = calculate_delay_s(
+                input_position_mm,
+                output_position_mm,
+                propagation_velocity_m_per_s,
             );
             let delay_samples = delay_s * sample_rate_hz;
 
@@ -102,7 +156,7 @@ pub fn calculate_delay_samples_array(
             delay_samples_array[(
                 v_numbers[input_voxel_index]
                     .with_context(|| format!("Voxel number not found for connectable voxel at index {input_voxel_index:?}"))? / 3,
-                offset_to_delay_index(x_offset, y_offset, z_offset)
+                offset_to_delay_index(x_offset, y_offset, z_offset, neighborhood_radius)
                     .expect("Offsets to not all be zero."),
             )] = delay_samples;
         }
@@ -112,11 +166,15 @@ pub fn calculate_delay_samples_array(
 
 #[cfg(test)]
 mod test {
+    use anyhow::Context;
     use approx::assert_relative_eq;
     use ndarray::{arr1, Array1};
     use ndarray_stats::QuantileExt;
 
-    use super::{calculate_delay_s, calculate_delay_samples_array};
+    use super::{
+        calculate_delay_s, calculate_delay_samples_array, calculate_velocity_m_per_s,
+        offset_to_delay_index,
+    };
     use crate::core::{
         config::model::Model,
         model::spatial::{voxels::VoxelType, SpatialDescription},
@@ -152,6 +210,29 @@ mod test {
         assert_relative_eq!(delay_s, 2.5);
     }
 
+    #[test]
+    fn calculate_velocity_anisotropic() {
+        let input_position_mm: Array1<f32> = arr1(&[1.0, 0.0, 0.0]);
+        let along_mm: Array1<f32> = arr1(&[0.0, 0.0, 0.0]);
+        let across_mm: Array1<f32> = arr1(&[1.0, 1.0, 0.0]);
+        let fiber_direction: Array1<f32> = arr1(&[1.0, 0.0, 0.0]);
+        let isotropic: Array1<f32> = arr1(&[0.0, 0.0, 0.0]);
+
+        let velocity = |output_position_mm: &Array1<f32>, fiber: &Array1<f32>| {
+            calculate_velocity_m_per_s(
+                &input_position_mm.view(),
+                &output_position_mm.view(),
+                &fiber.view(),
+                1.0,
+                0.4,
+            )
+        };
+
+        assert_relative_eq!(velocity(&along_mm, &fiber_direction), 1.0);
+        assert_relative_eq!(velocity(&across_mm, &fiber_direction), 0.4);
+        assert_relative_eq!(velocity(&across_mm, &isotropic), 1.0);
+    }
+
     #[test]
     fn calculate_delay_samples_array_1() -> anyhow::Result<()> {
         let config = &Model::default();
@@ -161,7 +242,9 @@ mod test {
         let delay_samples = calculate_delay_samples_array(
             spatial_description,
             &config.common.propagation_velocities,
+            config.common.transverse_velocity_ratio,
             sample_rate_hz,
+            config.common.neighborhood_radius,
         )?;
 
         let max = delay_samples.max_skipnan();
@@ -176,4 +259,33 @@ mod test {
         assert_relative_eq!(*max, expected);
         Ok(())
     }
+
+    #[test]
+    fn calculate_delay_samples_array_radius_2() -> anyhow::Result<()> {
+        let config = &Model::default();
+        let spatial_description = &SpatialDescription::from_model_config(config)?;
+        let sample_rate_hz = 2000.0;
+
+        let delay_samples = calculate_delay_samples_array(
+            spatial_description,
+            &config.common.propagation_velocities,
+            config.common.transverse_velocity_ratio,
+            sample_rate_hz,
+            2,
+        )?;
+
+        let near = offset_to_delay_index(1, 0, 0, 2).expect("Offsets to be valid.");
+        let far = offset_to_delay_index(2, 0, 0, 2).expect("Offsets to be valid.");
+        let (voxel, _) = delay_samples
+            .outer_iter()
+            .enumerate()
+            .find(|(_, delays)| delays[near] > 0.0 && delays[far] > 0.0)
+            .context("Expected a voxel with two neighbors along the x axis")?;
+
+        assert_relative_eq!(
+            delay_samples[(voxel, far)],
+            2.0 * delay_samples[(voxel, near)]
+        );
+        Ok(())
+    }
 }
@@ -21,12 +21,36 @@ pub fn calculate(
     distance_m / distance_norm_m
 }
 
+/// Bends the current direction towards the fiber direction of the voxel.
+///
+/// The conductivity across the fibers is reduced by the square of the
+/// transverse velocity ratio, so the current flows preferably along the
+/// fibers. A zero fiber direction leaves the direction unchanged. The
+/// result is normalized like in [`calculate`].
+#[tracing::instrument(level = "trace")]
+pub fn apply_fibers(
+    direction: Array1<f32>,
+    fiber_direction: &ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>,
+    transverse_velocity_ratio: f32,
+) -> Array1<f32> {
+    trace!("Applying fiber direction");
+    let conductivity_ratio = transverse_velocity_ratio.powi(2);
+    let along = direction.dot(fiber_direction) * (1.0 - conductivity_ratio);
+    let current = direction * conductivity_ratio + fiber_direction * along;
+    let current_norm = current.mapv(f32::abs).sum();
+    if current_norm > 0.0 {
+        current / current_norm
+    } else {
+        current
+    }
+}
+
 #[cfg(test)]
 mod test {
     use approx::assert_relative_eq;
     use ndarray::{arr1, Array1};
 
-    use super::calculate;
+    use super::{apply_fibers, calculate};
 
     #[test]
     fn calculate_direction_simple() {
@@ -66,4 +90,22 @@ mod test {
             assert_relative_eq!(direction[i], expected[i], epsilon = 0.01);
         }
     }
+
+    #[test]
+    fn apply_fibers_diag() {
+        let output_position_mm: Array1<f32> = arr1(&[1.0, 1.0, 1.0]);
+        let input_position_mm: Array1<f32> = arr1(&[2.0, 0.0, 1.0]);
+        let fiber_direction: Array1<f32> = arr1(&[1.0, 0.0, 0.0]);
+        let expected: Array1<f32> = arr1(&[0.8, -0.2, 0.0]);
+
+        let direction = apply_fibers(
+            calculate(&input_position_mm.view(), &output_position_mm.view()),
+            &fiber_direction.view(),
+            0.5,
+        );
+
+        for i in 0..3 {
+            assert_relative_eq!(direction[i], expected[i], epsilon = 0.01);
+        }
+    }
 }
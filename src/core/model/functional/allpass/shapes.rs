@@ -6,12 +6,14 @@ use std::{
 
 use anyhow::{Context, Result};
 use approx::assert_relative_eq;
-use ndarray::{Array2, Array3, Dim};
+use ndarray::{Array1, Array2, Array3, Dim};
 use ndarray_npy::WriteNpyExt;
 use ocl::Buffer;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
+use super::number_of_neighbors;
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ActivationTimeMs {
     pub values: Array3<Option<f32>>,
@@ -83,18 +85,115 @@ impl DerefMut for ActivationTimeMs {
     }
 }
 
+/// Refractory times of the voxels. Connections added by a re-activation of a
+/// voxel only pass their input once the refractory time after the first
+/// activation of the voxel has passed, so the voxel cannot be triggered
+/// again within its refractory window.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Refractory {
+    // refractory time of each voxel in samples
+    pub samples: Array1<f32>,
+    // first activation of each voxel in samples
+    pub activation_samples: Array1<f32>,
+    // connections added by a re-activation, indexed like the delays
+    pub reentrant: Array2<bool>,
+}
+
+impl Refractory {
+    /// Creates new `Refractory` times of zero without re-entrant
+    /// connections for the given number of states and neighborhood radius.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn empty(number_of_states: usize, neighborhood_radius: usize) -> Self {
+        trace!("Creating empty refractory times");
+        Self {
+            samples: Array1::zeros(number_of_states / 3),
+            activation_samples: Array1::zeros(number_of_states / 3),
+            reentrant: Array2::from_elem(
+                (
+                    number_of_states / 3,
+                    number_of_neighbors(neighborhood_radius),
+                ),
+                false,
+            ),
+        }
+    }
+
+    /// Returns the first step at which the connection of the voxel to the
+    /// neighbor at the given delay index passes its input.
+    #[inline]
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn gate(&self, voxel_index: usize, delay_index: usize) -> usize {
+        if self.reentrant[(voxel_index, delay_index)] {
+            (self.activation_samples[voxel_index] + self.samples[voxel_index])
+                .ceil()
+                .max(0.0) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Returns the gates of all connections, indexed like the delays.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn gates(&self) -> Array2<usize> {
+        Array2::from_shape_fn(self.reentrant.raw_dim(), |(voxel_index, delay_index)| {
+            self.gate(voxel_index, delay_index)
+        })
+    }
+
+    /// Saves the refractory times in samples to a .npy file at the given
+    /// path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file cannot be written.
+    #[tracing::instrument(level = "trace")]
+    pub(crate) fn save_npy(&self, path: &std::path::Path) -> Result<()> {
+        trace!("Saving refractory times to npy");
+        fs::create_dir_all(path).with_context(|| {
+            format!(
+                "Failed to create directory for refractory times: {}",
+                path.display()
+            )
+        })?;
+
+        let file_path = path.join("refractory_samples.npy");
+        let writer = BufWriter::new(File::create(&file_path).with_context(|| {
+            format!(
+                "Failed to create refractory times file: {}",
+                file_path.display()
+            )
+        })?);
+
+        self.samples.write_npy(writer).with_context(|| {
+            format!(
+                "Failed to write refractory times to: {}",
+                file_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Gains(Array2<f32>);
 
 impl Gains {
-    /// Creates a new `ArrayGains` with the given number of states,
-    /// initializing all values to zeros.
+    /// Creates a new `ArrayGains` with the given number of states and
+    /// neighborhood radius, initializing all values to zeros.
     #[must_use]
     #[tracing::instrument(level = "trace")]
-    pub fn empty(number_of_states: usize) -> Self {
+    pub fn empty(number_of_states: usize, neighborhood_radius: usize) -> Self {
         trace!("Creating empty gains array");
-        Self(Array2::zeros((number_of_states, 78)))
+        Self(Array2::zeros((
+            number_of_states,
+            number_of_neighbors(neighborhood_radius) * 3,
+        )))
     }
 
     /// Saves the array values to a .npy file at the given path with the given name.
@@ -170,13 +269,19 @@ impl DerefMut for Gains {
 pub struct Indices(Array2<Option<usize>>);
 
 impl Indices {
-    /// Creates a new `ArrayIndicesGains` with the given number of states,
-    /// initializing all values to `None`.
+    /// Creates a new `ArrayIndicesGains` with the given number of states and
+    /// neighborhood radius, initializing all values to `None`.
     #[must_use]
     #[tracing::instrument(level = "trace")]
-    pub fn empty(number_of_states: usize) -> Self {
+    pub fn empty(number_of_states: usize, neighborhood_radius: usize) -> Self {
         trace!("Creating empty indices gains array");
-        Self(Array2::from_elem((number_of_states, 78), None))
+        Self(Array2::from_elem(
+            (
+                number_of_states,
+                number_of_neighbors(neighborhood_radius) * 3,
+            ),
+            None,
+        ))
     }
 
     /// Saves the array indices values to a .npy file at the given path.
@@ -237,8 +342,9 @@ impl DerefMut for Indices {
 pub struct Coefs(Array2<f32>);
 
 impl Coefs {
-    /// Creates a new `ArrayDelays` with the given number of states,
-    /// initializing all values to 0. The number of states must be divisible by 3.
+    /// Creates a new `ArrayDelays` with the given number of states and
+    /// neighborhood radius, initializing all values to 0. The number of states
+    /// must be divisible by 3.
     ///
     /// # Panics
     ///
@@ -246,10 +352,13 @@ impl Coefs {
     #[must_use]
     #[allow(clippy::cast_precision_loss)]
     #[tracing::instrument(level = "trace")]
-    pub fn empty(number_of_states: usize) -> Self {
+    pub fn empty(number_of_states: usize, neighborhood_radius: usize) -> Self {
         trace!("Creating empty delays array");
         assert_relative_eq!(number_of_states as f32 % 3.0, 0.0);
-        Self(Array2::zeros((number_of_states / 3, 26)))
+        Self(Array2::zeros((
+            number_of_states / 3,
+            number_of_neighbors(neighborhood_radius),
+        )))
     }
 
     /// Saves the values in this `ArrayDelays` to a .npy file at the given path.
@@ -327,10 +436,13 @@ impl UnitDelays {
     #[must_use]
     #[allow(clippy::cast_precision_loss)]
     #[tracing::instrument(level = "trace")]
-    pub fn empty(number_of_states: usize) -> Self {
+    pub fn empty(number_of_states: usize, neighborhood_radius: usize) -> Self {
         trace!("Creating empty delays array");
         assert_relative_eq!(number_of_states as f32 % 3.0, 0.0);
-        Self(Array2::zeros((number_of_states / 3, 26)))
+        Self(Array2::zeros((
+            number_of_states / 3,
+            number_of_neighbors(neighborhood_radius),
+        )))
     }
     /// Saves the delay line values in this `ArrayDelays` to a .npy file at the given path.
     ///
@@ -2,6 +2,7 @@ use std::{
     fs::{self, File},
     io::BufWriter,
     ops::{Deref, DerefMut},
+    path::Path,
 };
 
 use anyhow::{Context, Result};
@@ -14,7 +15,10 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
 use crate::core::{
-    config::{self, model::Model},
+    config::{
+        self,
+        model::{Common, Model, REFERENCE_HEART_RATE_BPM},
+    },
     model::spatial::{voxels::VoxelType, SpatialDescription},
 };
 
@@ -34,11 +38,13 @@ impl ControlMatrix {
 
     /// Creates a `ControlMatrix` from the given `Model` configuration and
     /// `SpatialDescription`. Initializes the control matrix by setting the value
-    /// for the state of the sinoatrial voxel to 1.0, and all other states to 0.
+    /// for the state of the sinoatrial voxel and the voxels of the pacing
+    /// sites to 1.0, and all other states to 0.
     ///
     /// # Errors
     ///
-    /// Returns an error if the sinoatrial node voxel number is not available.
+    /// Returns an error if the sinoatrial node voxel number is not available
+    /// or a pacing site cannot be placed.
     #[tracing::instrument(level = "debug")]
     pub fn from_model_config(
         config: &Model,
@@ -59,6 +65,9 @@ impl ControlMatrix {
                 control_matrix[voxel_number] = 1.0;
             }
         }
+        for (voxel_number, _) in pacing_site_numbers(config, spatial_description)? {
+            control_matrix[voxel_number] = 1.0;
+        }
 
         Ok(control_matrix)
     }
@@ -137,6 +146,162 @@ impl DerefMut for ControlMatrix {
     }
 }
 
+/// Delay in steps after which the control function reaches each state.
+/// Zero for the sinoatrial node, the onset for the pacing sites.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ControlOnsets(Array1<usize>);
+
+impl ControlOnsets {
+    /// Creates new `ControlOnsets` without delays for the given number of
+    /// states.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn empty(number_of_states: usize) -> Self {
+        debug!("Creating empty control onsets");
+        Self(Array1::zeros(number_of_states))
+    }
+
+    /// Creates the `ControlOnsets` from the pacing sites of the model config,
+    /// rounding the onsets to whole steps.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a pacing site cannot be placed.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_model_config(
+        config: &Model,
+        spatial_description: &SpatialDescription,
+        sample_rate_hz: f32,
+    ) -> Result<Self> {
+        debug!("Creating control onsets from model config");
+        let mut control_onsets = Self::empty(spatial_description.voxels.count_states());
+        for (voxel_number, onset_s) in pacing_site_numbers(config, spatial_description)? {
+            control_onsets[voxel_number] = (onset_s * sample_rate_hz).round() as usize;
+        }
+        Ok(control_onsets)
+    }
+
+    /// Saves the control onsets to a .npy file at the given path.
+    ///
+    /// Casts the `usize` values to `u32` before writing to satisfy `.npy` format limitations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file cannot be written.
+    #[tracing::instrument(level = "trace")]
+    pub(crate) fn save_npy(&self, path: &std::path::Path) -> Result<()> {
+        trace!("Saving control onsets to npy");
+        fs::create_dir_all(path).with_context(|| {
+            format!(
+                "Failed to create directory for control onsets: {}",
+                path.display()
+            )
+        })?;
+
+        let file_path = path.join("control_onsets.npy");
+        let writer = BufWriter::new(File::create(&file_path).with_context(|| {
+            format!(
+                "Failed to create control onsets file: {}",
+                file_path.display()
+            )
+        })?);
+
+        self.mapv(|onset| u32::try_from(onset).unwrap_or(u32::MAX))
+            .write_npy(writer)
+            .with_context(|| {
+                format!("Failed to write control onsets to: {}", file_path.display())
+            })?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub(crate) fn to_gpu(&self, queue: &ocl::Queue) -> Result<Buffer<i32>> {
+        let onsets_i32: Vec<i32> = self.iter().map(|&onset| onset as i32).collect();
+        let buffer = Buffer::builder()
+            .queue(queue.clone())
+            .len(onsets_i32.len())
+            .copy_host_slice(onsets_i32.as_slice())
+            .build()
+            .context("Failed to build GPU buffer for control onsets")?;
+        Ok(buffer)
+    }
+}
+
+impl Deref for ControlOnsets {
+    type Target = Array1<usize>;
+
+    #[tracing::instrument(level = "trace")]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ControlOnsets {
+    #[tracing::instrument(level = "trace")]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Returns the index of the voxel closest to each pacing site together
+/// with its onset in seconds.
+///
+/// # Errors
+///
+/// Returns an error if an onset is negative or the model has no heart
+/// voxels.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn pacing_site_voxels(
+    config: &Model,
+    spatial_description: &SpatialDescription,
+) -> Result<Vec<((usize, usize, usize), f32)>> {
+    debug!("Placing pacing sites");
+    config
+        .common
+        .pacing_sites
+        .iter()
+        .map(|site| {
+            anyhow::ensure!(
+                site.onset_ms >= 0.0,
+                "Onset of the pacing site at {:?} must not be negative",
+                site.position_mm
+            );
+            let index = spatial_description
+                .voxels
+                .closest_connectable_index(site.position_mm)
+                .context("Model has no heart voxels to place pacing sites")?;
+            Ok((index, site.onset_ms / 1000.0))
+        })
+        .collect()
+}
+
+/// Returns the first state number of the voxel closest to each pacing site
+/// together with its onset in seconds.
+///
+/// # Errors
+///
+/// Returns an error if a pacing site cannot be placed or its voxel has no
+/// number.
+#[tracing::instrument(level = "debug", skip_all)]
+fn pacing_site_numbers(
+    config: &Model,
+    spatial_description: &SpatialDescription,
+) -> Result<Vec<(usize, f32)>> {
+    pacing_site_voxels(config, spatial_description)?
+        .into_iter()
+        .map(|(index, onset_s)| {
+            let voxel_number = spatial_description.voxels.numbers[index].with_context(|| {
+                format!("Pacing site voxel at {index:?} has no assigned number")
+            })?;
+            Ok((voxel_number, onset_s))
+        })
+        .collect()
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions)]
 pub struct ControlFunction(Array1<f32>);
@@ -149,22 +314,22 @@ impl ControlFunction {
         Self(Array1::zeros(number_of_steps))
     }
 
-    /// Creates a new `ControlFunction` by reading a control function .npy file,
-    /// resampling it to match the given sample rate and duration, and returning
-    /// the resampled values as a new `ControlFunction`.
-    ///
-    /// The control function .npy file is assumed to be located in `assets/`.
-    /// The resampling is done by looping through the target number of samples
-    /// based on sample rate and duration, and taking values from the .npy file
-    /// using modulo to wrap the index.
+    /// Creates a new `ControlFunction` from the model config for the given
+    /// sample rate and duration.
     ///
-    /// This allows creating a `ControlFunction` of arbitrary duration from a fixed
-    /// length control function file.
+    /// The O'Hara waveform is read from `assets/control_function_ohara.npy`
+    /// and resampled from 2 kHz to the given sample rate, a waveform from a
+    /// file is resampled from its configured sample rate, the triangle is
+    /// generated directly. Both describe one beat at 60 bpm and are scaled in
+    /// time to the heart rate and action potential duration of the config,
+    /// see [`scale_cycle`], before they are repeated over the duration. The
+    /// ramp spans the whole duration and is not scaled.
     ///
     /// # Errors
     ///
-    /// Returns an error if the control function input file cannot be read or
-    /// if resampling operations fail.
+    /// Returns an error if the heart rate or action potential duration
+    /// factor is not positive, or if the control function input file cannot
+    /// be read, is empty or cannot be resampled.
     #[tracing::instrument(level = "debug")]
     #[allow(
         clippy::cast_possible_truncation,
@@ -174,79 +339,19 @@ impl ControlFunction {
     pub fn from_model_config(config: &Model, sample_rate_hz: f32, duration_s: f32) -> Result<Self> {
         debug!("Creating control function from model config");
         let desired_length_samples = (duration_s * sample_rate_hz) as usize;
+        anyhow::ensure!(
+            config.common.heart_rate_bpm > 0.0
+                && config.common.action_potential_duration_factor > 0.0,
+            "Heart rate and action potential duration factor must be positive"
+        );
 
-        match config.common.control_function {
-            config::model::ControlFunction::Ohara => {
-                let mut control_function_raw: Array1<f32> = read_npy(
-                    "assets/control_function_ohara.npy",
-                )
-                .context(
-                    "Failed to load O'Hara control function from assets/control_function_ohara.npy",
-                )?;
-
-                let from_sample_rate_hz = 2000.0;
-
-                if !from_sample_rate_hz.relative_eq(&sample_rate_hz, 1e-3, 1e-3) {
-                    let params = SincInterpolationParameters {
-                        sinc_len: 256,
-                        f_cutoff: 0.95,
-                        oversampling_factor: 256,
-                        interpolation: rubato::SincInterpolationType::Cubic,
-                        window: rubato::WindowFunction::BlackmanHarris2,
-                    };
-                    let mut resampler = SincFixedIn::<f32>::new(
-                        f64::from(sample_rate_hz) / f64::from(from_sample_rate_hz),
-                        10.0,
-                        params,
-                        control_function_raw.len(),
-                        1,
-                    )
-                    .with_context(|| format!(
-                        "Failed to create resampler for O'Hara control function (from {from_sample_rate_hz}Hz to {sample_rate_hz}Hz)"
-                    ))?;
-
-                    let input_frames: Vec<Vec<f32>> = vec![control_function_raw.to_vec()];
-
-                    let output_frames = resampler.process(&input_frames, None)
-                        .with_context(|| format!(
-                            "Failed to resample O'Hara control function from {from_sample_rate_hz}Hz to {sample_rate_hz}Hz"
-                        ))?;
-
-                    control_function_raw = output_frames[0].clone().into();
-                }
-
-                let control_function_values: Vec<f32> = (0..desired_length_samples)
-                    .map(|i| {
-                        let index = i % control_function_raw.len();
-                        control_function_raw[index]
-                    })
-                    .collect();
-
-                Ok(Self(Array1::from(control_function_values)))
-            }
-            config::model::ControlFunction::Triangle => {
-                let mut control_function_values = Array1::<f32>::zeros(desired_length_samples);
-
-                let triangle_half_length = (0.5 * sample_rate_hz) as i32;
-
-                let increase_per_step = 1.0 / (triangle_half_length + 1) as f32;
-
-                for i in 0..triangle_half_length {
-                    let value = (i + 1) as f32 * increase_per_step;
-                    control_function_values[i as usize] = value;
-                    control_function_values[2 * triangle_half_length as usize - i as usize - 1] =
-                        value;
-                }
-
-                control_function_values[triangle_half_length as usize] = 1.0;
-
-                for i in sample_rate_hz as usize..desired_length_samples {
-                    control_function_values[i] =
-                        control_function_values[i % sample_rate_hz as usize];
-                }
-
-                Ok(Self(control_function_values))
-            }
+        let reference = match &config.common.control_function {
+            config::model::ControlFunction::Ohara => ohara_reference(sample_rate_hz)?,
+            config::model::ControlFunction::Triangle => triangle_reference(sample_rate_hz),
+            config::model::ControlFunction::FromFile {
+                path,
+                sample_rate_hz: file_sample_rate_hz,
+            } => file_reference(path, *file_sample_rate_hz, sample_rate_hz)?,
             config::model::ControlFunction::Ramp => {
                 let mut control_function_values = Array1::<f32>::zeros(desired_length_samples);
 
@@ -256,9 +361,16 @@ impl ControlFunction {
                     let value = i as f32 * increase_per_step;
                     control_function_values[i] = -value;
                 }
-                Ok(Self(control_function_values))
+                return Ok(Self(control_function_values));
             }
-        }
+        };
+        let cycle = scale_cycle(&reference, &config.common, sample_rate_hz);
+
+        let control_function_values: Vec<f32> = (0..desired_length_samples)
+            .map(|i| cycle[i % cycle.len()])
+            .collect();
+
+        Ok(Self(Array1::from(control_function_values)))
     }
 
     /// Saves the control function values to a .npy file at the given path.
@@ -323,6 +435,203 @@ impl ControlFunction {
     }
 }
 
+/// Reads the O'Hara waveform of one beat and resamples it from 2 kHz to the
+/// given sample rate.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or resampling fails.
+#[tracing::instrument(level = "debug")]
+fn ohara_reference(sample_rate_hz: f32) -> Result<Array1<f32>> {
+    let control_function_raw: Array1<f32> = read_npy("assets/control_function_ohara.npy")
+        .context("Failed to load O'Hara control function from assets/control_function_ohara.npy")?;
+
+    resample_waveform(control_function_raw, 2000.0, sample_rate_hz)
+        .context("Failed to resample O'Hara control function")
+}
+
+/// Reads one beat of a waveform from a .npy file or a .csv file and
+/// resamples it from the sample rate of the file to the given sample rate.
+///
+/// The .csv file holds one value per row, only the first column is used.
+/// A header row that is not a number is skipped.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed, holds no values,
+/// its sample rate is not positive or resampling fails.
+#[tracing::instrument(level = "debug")]
+pub fn file_reference(
+    path: &Path,
+    file_sample_rate_hz: f32,
+    sample_rate_hz: f32,
+) -> Result<Array1<f32>> {
+    debug!("Reading control function from file");
+    anyhow::ensure!(
+        file_sample_rate_hz > 0.0,
+        "Sample rate of the control function file must be positive"
+    );
+    let is_csv = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+    let control_function_raw: Array1<f32> = if is_csv {
+        read_csv_waveform(path)?
+    } else {
+        read_npy(path)
+            .with_context(|| format!("Failed to load control function from {}", path.display()))?
+    };
+    anyhow::ensure!(
+        !control_function_raw.is_empty(),
+        "Control function file {} holds no values",
+        path.display()
+    );
+
+    resample_waveform(control_function_raw, file_sample_rate_hz, sample_rate_hz).with_context(
+        || {
+            format!(
+                "Failed to resample control function from {}",
+                path.display()
+            )
+        },
+    )
+}
+
+/// Reads the first column of a .csv file, skipping a header row.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a value cannot be parsed.
+#[tracing::instrument(level = "trace")]
+fn read_csv_waveform(path: &Path) -> Result<Array1<f32>> {
+    trace!("Reading control function from csv");
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read control function from {}", path.display()))?;
+    let mut values = Vec::new();
+    for (row, line) in content.lines().enumerate() {
+        let field = line.split([',', ';']).next().unwrap_or_default().trim();
+        if field.is_empty() {
+            continue;
+        }
+        match field.parse::<f32>() {
+            Ok(value) => values.push(value),
+            Err(_) if row == 0 => {}
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to parse value {field:?} in row {} of {}",
+                        row + 1,
+                        path.display()
+                    )
+                })
+            }
+        }
+    }
+    Ok(Array1::from(values))
+}
+
+/// Resamples a waveform between two sample rates with a sinc interpolation,
+/// returning it unchanged if the rates match.
+///
+/// # Errors
+///
+/// Returns an error if the resampler cannot be created or fails.
+#[tracing::instrument(level = "debug", skip(values))]
+fn resample_waveform(
+    values: Array1<f32>,
+    from_sample_rate_hz: f32,
+    sample_rate_hz: f32,
+) -> Result<Array1<f32>> {
+    if from_sample_rate_hz.relative_eq(&sample_rate_hz, 1e-3, 1e-3) {
+        return Ok(values);
+    }
+    let params = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        oversampling_factor: 256,
+        interpolation: rubato::SincInterpolationType::Cubic,
+        window: rubato::WindowFunction::BlackmanHarris2,
+    };
+    let mut resampler = SincFixedIn::<f32>::new(
+        f64::from(sample_rate_hz) / f64::from(from_sample_rate_hz),
+        10.0,
+        params,
+        values.len(),
+        1,
+    )
+    .with_context(|| {
+        format!("Failed to create resampler (from {from_sample_rate_hz}Hz to {sample_rate_hz}Hz)")
+    })?;
+
+    let input_frames: Vec<Vec<f32>> = vec![values.to_vec()];
+
+    let output_frames = resampler.process(&input_frames, None).with_context(|| {
+        format!("Failed to resample from {from_sample_rate_hz}Hz to {sample_rate_hz}Hz")
+    })?;
+
+    Ok(output_frames[0].clone().into())
+}
+
+/// Creates a triangle rising to one over the first half second and falling
+/// back over the second half, one beat at 60 bpm.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "debug")]
+fn triangle_reference(sample_rate_hz: f32) -> Array1<f32> {
+    let mut control_function_values = Array1::<f32>::zeros(sample_rate_hz as usize);
+
+    let triangle_half_length = (0.5 * sample_rate_hz) as i32;
+
+    let increase_per_step = 1.0 / (triangle_half_length + 1) as f32;
+
+    for i in 0..triangle_half_length {
+        let value = (i + 1) as f32 * increase_per_step;
+        control_function_values[i as usize] = value;
+        control_function_values[2 * triangle_half_length as usize - i as usize - 1] = value;
+    }
+
+    control_function_values[triangle_half_length as usize] = 1.0;
+
+    control_function_values
+}
+
+/// Scales one beat of a reference waveform at 60 bpm to the heart rate and
+/// action potential duration of the config.
+///
+/// The cycle is shortened or extended to the beat length of the heart rate,
+/// while the waveform is stretched by the action potential duration scale.
+/// Once the stretched waveform has ended, its last value is held until the
+/// next beat, if it outlasts the beat it is cut off. The reference is
+/// returned unchanged at 60 bpm without scaling.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "debug", skip(reference))]
+pub fn scale_cycle(reference: &Array1<f32>, config: &Common, sample_rate_hz: f32) -> Array1<f32> {
+    let scale = config.action_potential_duration_scale();
+    if reference.is_empty()
+        || ((config.heart_rate_bpm - REFERENCE_HEART_RATE_BPM).abs() < f32::EPSILON
+            && (scale - 1.0).abs() < f32::EPSILON)
+    {
+        return reference.clone();
+    }
+    let cycle_length = ((60.0 / config.heart_rate_bpm * sample_rate_hz).round() as usize).max(1);
+    let last = reference.len() - 1;
+    Array1::from_shape_fn(cycle_length, |step| {
+        let position = step as f32 / scale;
+        if position >= last as f32 {
+            return reference[last];
+        }
+        let lower = position.floor() as usize;
+        let weight = position - lower as f32;
+        reference[lower].mul_add(1.0 - weight, reference[lower + 1] * weight)
+    })
+}
+
 impl Deref for ControlFunction {
     type Target = Array1<f32>;
 
@@ -347,7 +656,10 @@ mod test {
     use approx::assert_relative_eq;
 
     use super::*;
-    use crate::{core::config::model::Model, vis::plotting::png::line::standard_time_plot};
+    use crate::{
+        core::config::model::{Model, PacingSite},
+        vis::plotting::{png::line::standard_time_plot, PlotTheme},
+    };
 
     const COMMON_PATH: &str = "tests/core/model/functional/control/";
 
@@ -375,6 +687,32 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn pacing_site_is_driven_after_onset() -> Result<()> {
+        let mut config = Model::default();
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+        let sinoatrial_sum = ControlMatrix::from_model_config(&config, &spatial_description)?.sum();
+        config.common.pacing_sites = vec![PacingSite {
+            position_mm: [1000.0, 1000.0, 1000.0],
+            onset_ms: 100.0,
+        }];
+
+        let control_matrix = ControlMatrix::from_model_config(&config, &spatial_description)?;
+        let control_onsets =
+            ControlOnsets::from_model_config(&config, &spatial_description, 2000.0)?;
+
+        assert_relative_eq!(control_matrix.sum(), sinoatrial_sum + 1.0);
+        let paced_states: Vec<usize> = control_onsets
+            .indexed_iter()
+            .filter(|(_, &onset)| onset > 0)
+            .map(|(state, _)| state)
+            .collect();
+        assert_eq!(paced_states.len(), 1);
+        assert_eq!(control_onsets[paced_states[0]], 200);
+        assert_relative_eq!(control_matrix[paced_states[0]], 1.0);
+        Ok(())
+    }
+
     #[test]
     fn function_from_model_config_no_crash() -> Result<()> {
         let sample_rate_hz = 3000.0;
@@ -418,6 +756,7 @@ mod test {
             path.as_path(),
             "Control Function",
             "j [A/mm^2]",
+            PlotTheme::Light,
         )
         .context("Failed to generate control function plot")?;
         Ok(())
@@ -448,6 +787,7 @@ mod test {
             path.as_path(),
             "Control Function",
             "j [A/mm^2]",
+            PlotTheme::Light,
         )
         .context("Failed to generate control function plot")?;
         Ok(())
@@ -478,8 +818,82 @@ mod test {
             path.as_path(),
             "Control Function",
             "j [A/mm^2]",
+            PlotTheme::Light,
         )
         .context("Failed to generate control function plot")?;
         Ok(())
     }
+
+    #[test]
+    fn cycle_is_shortened_with_heart_rate() {
+        let reference = Array1::from_shape_fn(10, |i| if i < 4 { 1.0 } else { 0.0 });
+        let config = Common {
+            heart_rate_bpm: 120.0,
+            ..Common::default()
+        };
+
+        let cycle = scale_cycle(&reference, &config, 10.0);
+
+        assert_eq!(cycle.len(), 5);
+        assert_relative_eq!(cycle[3], 1.0);
+        assert_relative_eq!(cycle[4], 0.0);
+    }
+
+    #[test]
+    fn action_potential_is_stretched_by_duration_factor() {
+        let reference = Array1::from_shape_fn(10, |i| if i < 4 { 1.0 } else { 0.0 });
+        let config = Common {
+            action_potential_duration_factor: 2.0,
+            ..Common::default()
+        };
+
+        let cycle = scale_cycle(&reference, &config, 10.0);
+
+        assert_eq!(cycle.len(), 10);
+        assert_relative_eq!(cycle[6], 1.0);
+        assert_relative_eq!(cycle[8], 0.0);
+    }
+
+    #[test]
+    fn rate_adaptation_follows_bazett() {
+        let config = Common {
+            heart_rate_bpm: 240.0,
+            action_potential_rate_adaptation: true,
+            ..Common::default()
+        };
+
+        assert_relative_eq!(config.action_potential_duration_scale(), 0.5);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn function_from_csv_file_is_repeated() -> Result<()> {
+        setup(None);
+        let path = Path::new(COMMON_PATH).join("control_function_from_file.csv");
+        let rows: Vec<String> = (0..100).map(|i| format!("{}", i as f32 / 100.0)).collect();
+        fs::write(&path, format!("current\n{}\n", rows.join("\n")))?;
+        let mut config = Model::default();
+        config.common.control_function = config::model::ControlFunction::FromFile {
+            path,
+            sample_rate_hz: 1000.0,
+        };
+
+        let control_function = ControlFunction::from_model_config(&config, 1000.0, 0.25)?;
+
+        assert_eq!(control_function.len(), 250);
+        assert_relative_eq!(control_function[99], 0.99);
+        assert_relative_eq!(control_function[100], 0.0);
+        assert_relative_eq!(control_function[142], 0.42);
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_csv_value_is_an_error() -> Result<()> {
+        setup(None);
+        let path = Path::new(COMMON_PATH).join("control_function_invalid.csv");
+        fs::write(&path, "current\n0.1\nabc\n")?;
+
+        assert!(file_reference(&path, 1000.0, 1000.0).is_err());
+        Ok(())
+    }
 }
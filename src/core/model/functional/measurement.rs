@@ -7,7 +7,7 @@ use std::{
 
 use anyhow::{Context, Result};
 use approx::relative_eq;
-use ndarray::{s, Array2, Array3, ArrayView2};
+use ndarray::{arr1, s, Array1, Array2, Array3, ArrayView1, ArrayView2};
 use ndarray_npy::WriteNpyExt;
 use ocl::{Buffer, Queue};
 use physical_constants::VACUUM_MAG_PERMEABILITY;
@@ -15,7 +15,13 @@ use rand_distr::{Distribution, Normal};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use crate::core::{config::model::Model, model::spatial::SpatialDescription};
+use crate::core::{
+    config::model::{Model, SensorArrayMotion, TorsoModel},
+    model::spatial::{sensors::SensorModality, SpatialDescription},
+};
+
+/// Conductivity of the torso used for the potentials measured by electrodes.
+const TORSO_CONDUCTIVITY_S_PER_M: f32 = 0.2;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions, clippy::unsafe_derive_deserialize)]
@@ -57,7 +63,12 @@ impl MeasurementMatrix {
     /// `SpatialDescription`. Initializes the matrix values by calculating the
     /// magnetic flux density at each sensor position for each voxel, based on
     /// voxel type, position, sensor position and orientation.
-    /// Uses the Biot-Savart law to calculate the magnetic flux density.
+    /// Uses the Biot-Savart law to calculate the magnetic flux density, or
+    /// Sarvas' formula for a spherical torso. Gradiometers measure the
+    /// difference to a second coil shifted by the baseline along the sensor
+    /// orientation. Electrodes measure the electric potential of the current
+    /// dipoles in an unbounded homogeneous conductor, regardless of the torso
+    /// model.
     ///
     /// # Errors
     ///
@@ -68,7 +79,7 @@ impl MeasurementMatrix {
     ) -> Result<Self> {
         debug!("Creating measurement matrix from model config");
         let mut measurement_matrix = Self::empty(
-            spatial_description.sensors.count_beats(),
+            spatial_description.sensors.count_array_positions(),
             spatial_description.voxels.count_states(),
             spatial_description.sensors.count(),
         );
@@ -81,13 +92,19 @@ impl MeasurementMatrix {
         let sensor_positions = &spatial_description.sensors.positions_mm;
         let sensor_offsets = &spatial_description.sensors.array_offsets_mm;
         let sensor_orientations = &spatial_description.sensors.orientations_xyz;
+        let sensor_modalities = &spatial_description.sensors.modalities;
+        let torso_center_m = match &spatial_description.torso {
+            TorsoModel::Unbounded => None,
+            TorsoModel::Spherical { center_mm } => Some(arr1(center_mm) / 1000.0),
+        };
 
         let voxel_volume_m3 = (spatial_description.voxels.size_mm / 1000.0).powi(3);
 
         #[allow(clippy::cast_possible_truncation)]
         let common_factor = (VACUUM_MAG_PERMEABILITY as f32 * voxel_volume_m3) / (4.0 * PI) * 1e12;
+        let electric_factor = voxel_volume_m3 / (4.0 * PI * TORSO_CONDUCTIVITY_S_PER_M) * 1e3;
 
-        for beat in 0..spatial_description.sensors.count_beats() {
+        for beat in 0..spatial_description.sensors.count_array_positions() {
             for (index, v_type) in types.indexed_iter() {
                 if !v_type.is_connectable() {
                     continue;
@@ -103,18 +120,38 @@ impl MeasurementMatrix {
                         + &sensor_offsets.slice(s![beat, ..]);
                     let s_ori = sensor_orientations.slice(s![s_num, ..]);
 
-                    let distace_m = (&s_pos_mm - &v_pos_mm) / 1000.0;
-                    let distance_cubed_m3 = distace_m.mapv(|v| v.powi(2)).sum().sqrt().powi(3);
-
-                    m[(beat, s_num, v_num)] = common_factor
-                        * s_ori[2].mul_add(distace_m[1], -s_ori[1] * distace_m[2])
-                        / distance_cubed_m3;
-                    m[(beat, s_num, v_num + 1)] = common_factor
-                        * s_ori[0].mul_add(distace_m[2], -s_ori[2] * distace_m[0])
-                        / distance_cubed_m3;
-                    m[(beat, s_num, v_num + 2)] = common_factor
-                        * s_ori[1].mul_add(distace_m[0], -s_ori[0] * distace_m[1])
-                        / distance_cubed_m3;
+                    let lead_field = match sensor_modalities[s_num] {
+                        SensorModality::Magnetic => {
+                            magnetic_lead_field(&s_pos_mm, v_pos_mm, s_ori, torso_center_m.as_ref())
+                                .map(|value| common_factor * value)
+                        }
+                        SensorModality::Gradiometer { baseline_mm } => {
+                            let far_pos_mm = &s_pos_mm + &(&s_ori * baseline_mm);
+                            let near = magnetic_lead_field(
+                                &s_pos_mm,
+                                v_pos_mm,
+                                s_ori,
+                                torso_center_m.as_ref(),
+                            );
+                            let far = magnetic_lead_field(
+                                &far_pos_mm,
+                                v_pos_mm,
+                                s_ori,
+                                torso_center_m.as_ref(),
+                            );
+                            [0, 1, 2].map(|i| common_factor * (near[i] - far[i]))
+                        }
+                        SensorModality::Electric => {
+                            let distance_m = (&s_pos_mm - &v_pos_mm) / 1000.0;
+                            let distance_cubed_m3 =
+                                distance_m.mapv(|v| v.powi(2)).sum().sqrt().powi(3);
+                            [0, 1, 2].map(|i| electric_factor * distance_m[i] / distance_cubed_m3)
+                        }
+                    };
+
+                    for (component, value) in lead_field.into_iter().enumerate() {
+                        m[(beat, s_num, v_num + component)] = value;
+                    }
                 }
             }
         }
@@ -162,6 +199,32 @@ impl MeasurementMatrix {
         MeasurementMatrixAtBeat(self.slice(s![beat, .., ..]))
     }
 
+    /// Predicts the measurements at the given beat and step from the system
+    /// states. For continuous motion the prediction is interpolated between
+    /// the keyframes enclosing the array position at that step.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn predict(
+        &self,
+        interpolation: &MeasurementInterpolation,
+        system_states: &ArrayView1<f32>,
+        beat: usize,
+        step: usize,
+    ) -> Array1<f32> {
+        match interpolation.at_step(step) {
+            Some(keyframes) => {
+                let mut measurements =
+                    self.at_beat(keyframes.lower).dot(system_states) * (1.0 - keyframes.weight);
+                measurements.scaled_add(
+                    keyframes.weight,
+                    &self.at_beat(keyframes.upper).dot(system_states),
+                );
+                measurements
+            }
+            None => self.at_beat(beat).dot(system_states),
+        }
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     pub(crate) fn update_from_gpu(&mut self, measurement_matrix: &Buffer<f32>) -> Result<()> {
         measurement_matrix
@@ -202,6 +265,77 @@ impl<'a> Deref for MeasurementMatrixAtBeat<'a> {
     }
 }
 
+/// The keyframes of the measurement matrix enclosing the array position at a
+/// step, together with the weight of the upper keyframe.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct KeyframeWeights {
+    pub lower: usize,
+    pub upper: usize,
+    pub weight: f32,
+}
+
+/// Per-step interpolation between the keyframes of the measurement matrix
+/// for sensor arrays moving along a continuous trajectory.
+///
+/// Interpolating the matrices linearly approximates the array moving on a
+/// straight line between two keyframes. Empty for static and grid motion,
+/// where the measurement matrix of a beat is used for all of its steps.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MeasurementInterpolation(Vec<KeyframeWeights>);
+
+impl MeasurementInterpolation {
+    /// Creates an empty `MeasurementInterpolation`, i.e. without motion during
+    /// a beat.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn empty() -> Self {
+        debug!("Creating empty measurement interpolation");
+        Self(Vec::new())
+    }
+
+    /// Creates a new `MeasurementInterpolation` from the sensor array
+    /// trajectory of the model configuration. Returns an empty interpolation
+    /// unless the sensor array moves along a trajectory.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    #[tracing::instrument(level = "debug")]
+    pub fn from_model_config(config: &Model, sample_rate_hz: f32, duration_s: f32) -> Self {
+        debug!("Creating measurement interpolation from model config");
+        if config.common.sensor_array_motion != SensorArrayMotion::Trajectory {
+            return Self::empty();
+        }
+        let number_of_steps = (sample_rate_hz * duration_s) as usize;
+        Self(
+            (0..number_of_steps)
+                .map(|step| {
+                    let (lower, upper, weight) = config
+                        .common
+                        .sensor_array_trajectory
+                        .keyframe_weights(step as f32 / sample_rate_hz);
+                    KeyframeWeights {
+                        lower,
+                        upper,
+                        weight,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the keyframe weights at the given step, or none if the sensor
+    /// array does not move during a beat.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn at_step(&self, step: usize) -> Option<KeyframeWeights> {
+        self.0.get(step).copied()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions, clippy::unsafe_derive_deserialize)]
 pub struct MeasurementCovariance(Array2<f32>);
@@ -330,14 +464,87 @@ impl DerefMut for MeasurementCovariance {
     }
 }
 
+/// Calculates how the three current density components of a voxel contribute
+/// to the magnetic flux density measured by a magnetometer at the given
+/// position with the given orientation, without the common factor.
+///
+/// Positions are given in mm. Uses the Biot-Savart law for an unbounded
+/// conductor, or Sarvas' formula if the center of a spherical torso is given.
+#[tracing::instrument(level = "trace")]
+fn magnetic_lead_field(
+    sensor_mm: &Array1<f32>,
+    voxel_mm: ArrayView1<f32>,
+    orientation: ArrayView1<f32>,
+    torso_center_m: Option<&Array1<f32>>,
+) -> [f32; 3] {
+    torso_center_m.map_or_else(
+        || {
+            let distance_m = (sensor_mm - &voxel_mm) / 1000.0;
+            let distance_cubed_m3 = distance_m.mapv(|v| v.powi(2)).sum().sqrt().powi(3);
+            cross(distance_m.view(), orientation).map(|value| value / distance_cubed_m3)
+        },
+        |center_m| {
+            spherical_lead_field(
+                &(sensor_mm / 1000.0 - center_m),
+                &(&voxel_mm / 1000.0 - center_m),
+                orientation,
+            )
+        },
+    )
+}
+
+/// Calculates how the three current density components of a voxel contribute
+/// to the magnetic flux density measured by a sensor with the given
+/// orientation, inside a spherically symmetric conductor.
+///
+/// Uses Sarvas' formula, which includes the field of the volume currents.
+/// Positions are given in meters relative to the center of the sphere, the
+/// common factor of the Biot-Savart law is not included. Radial currents
+/// produce no field outside of the sphere.
+#[tracing::instrument(level = "trace")]
+fn spherical_lead_field(
+    sensor_m: &Array1<f32>,
+    voxel_m: &Array1<f32>,
+    orientation: ArrayView1<f32>,
+) -> [f32; 3] {
+    let distance_m = sensor_m - voxel_m;
+    let a = distance_m.mapv(|v| v.powi(2)).sum().sqrt();
+    let r = sensor_m.mapv(|v| v.powi(2)).sum().sqrt();
+    let a_dot_r = distance_m.dot(sensor_m);
+    let f = a * r.mul_add(a, r.mul_add(r, -voxel_m.dot(sensor_m)));
+    let grad_f = sensor_m * 2.0_f32.mul_add(a + r, a.powi(2) / r + a_dot_r / a)
+        - voxel_m * 2.0_f32.mul_add(r, a + a_dot_r / a);
+    let grad_f_dot_orientation = grad_f.dot(&orientation);
+    let voxel_cross_orientation = cross(voxel_m.view(), orientation);
+    let voxel_cross_sensor = cross(voxel_m.view(), sensor_m.view());
+    [0, 1, 2].map(|i| {
+        f.mul_add(
+            voxel_cross_orientation[i],
+            -grad_f_dot_orientation * voxel_cross_sensor[i],
+        ) / f.powi(2)
+    })
+}
+
+/// Calculates the cross product of two vectors.
+#[tracing::instrument(level = "trace")]
+fn cross(a: ArrayView1<f32>, b: ArrayView1<f32>) -> [f32; 3] {
+    [
+        a[1].mul_add(b[2], -a[2] * b[1]),
+        a[2].mul_add(b[0], -a[0] * b[2]),
+        a[0].mul_add(b[1], -a[1] * b[0]),
+    ]
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::Path;
 
+    use approx::assert_relative_eq;
+
     use super::*;
     use crate::{
-        core::config::model::{Common, SensorArrayGeometry},
-        vis::plotting::png::matrix::matrix_plot,
+        core::config::model::{Common, SensorArrayGeometry, SensorArrayTrajectory, SensorType},
+        vis::plotting::{png::matrix::matrix_plot, PlotTheme},
     };
 
     const COMMON_PATH: &str = "tests/core/model/functional/measurement/";
@@ -398,6 +605,7 @@ mod tests {
             None,
             None,
             None,
+            None,
             Some(path.as_path()),
             Some("Default Measurement Matrix"),
             Some("State Index"),
@@ -405,6 +613,7 @@ mod tests {
             Some("[pT / A / m^2]"),
             None,
             None,
+            PlotTheme::Light,
         )
         .context("Failed to generate measurement covariance plot")?;
         Ok(())
@@ -436,6 +645,7 @@ mod tests {
             None,
             None,
             None,
+            None,
             Some(path.as_path()),
             Some("Default Measurement Matrix"),
             Some("State Index"),
@@ -443,6 +653,7 @@ mod tests {
             Some("[pT / A / m^2]"),
             None,
             None,
+            PlotTheme::Light,
         )
         .context("Failed to generate measurement covariance plot")?;
         Ok(())
@@ -454,7 +665,7 @@ mod tests {
             common: Common {
                 sensors_per_axis: [10, 10, 10],
                 sensor_array_geometry: SensorArrayGeometry::Cube,
-                three_d_sensors: true,
+                sensor_type: SensorType::ThreeAxis,
                 ..Default::default()
             },
             ..Default::default()
@@ -463,7 +674,7 @@ mod tests {
             common: Common {
                 sensors_per_axis: [10, 10, 10],
                 sensor_array_geometry: SensorArrayGeometry::SparseCube,
-                three_d_sensors: true,
+                sensor_type: SensorType::ThreeAxis,
                 number_of_sensors: 1000,
                 ..Default::default()
             },
@@ -481,4 +692,124 @@ mod tests {
         assert_eq!(measurement_matrix_full, measurement_matrix_sparse);
         Ok(())
     }
+
+    #[test]
+    fn spherical_lead_field_radial_current_is_silent() {
+        let sensor_m = arr1(&[0.05, -0.02, 0.2]);
+        let voxel_m = arr1(&[0.01, 0.02, 0.03]);
+        let orientation = arr1(&[0.3, 0.4, 0.866]);
+
+        let lead_field = spherical_lead_field(&sensor_m, &voxel_m, orientation.view());
+
+        assert_relative_eq!(arr1(&lead_field).dot(&voxel_m), 0.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn spherical_lead_field_radial_sensor_matches_unbounded() {
+        let sensor_m = arr1(&[0.05, -0.02, 0.2]);
+        let voxel_m = arr1(&[0.01, 0.02, 0.03]);
+        let orientation = &sensor_m / sensor_m.dot(&sensor_m).sqrt();
+        let distance_m = &sensor_m - &voxel_m;
+        let distance_cubed_m3 = distance_m.dot(&distance_m).sqrt().powi(3);
+
+        let lead_field = spherical_lead_field(&sensor_m, &voxel_m, orientation.view());
+        let unbounded = cross(distance_m.view(), orientation.view());
+
+        for (spherical, unbounded) in lead_field.iter().zip(unbounded) {
+            assert_relative_eq!(
+                *spherical,
+                unbounded / distance_cubed_m3,
+                max_relative = 1e-3
+            );
+        }
+    }
+
+    #[test]
+    fn spherical_torso_changes_measurement_matrix() -> Result<()> {
+        let mut config = Model {
+            common: Common {
+                sensors_per_axis: [3, 3, 3],
+                voxel_size_mm: 20.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let unbounded = MeasurementMatrix::from_model_spatial_description(
+            &SpatialDescription::from_model_config(&config)?,
+        )?;
+        config.common.torso_model = TorsoModel::Spherical {
+            center_mm: [60.0, -200.0, 0.0],
+        };
+        let spherical = MeasurementMatrix::from_model_spatial_description(
+            &SpatialDescription::from_model_config(&config)?,
+        )?;
+
+        assert!(spherical.iter().all(|value| value.is_finite()));
+        assert_ne!(unbounded, spherical);
+        Ok(())
+    }
+
+    #[test]
+    fn gradiometer_with_distant_second_coil_matches_magnetometer() -> Result<()> {
+        let mut config = Model {
+            common: Common {
+                sensors_per_axis: [3, 3, 3],
+                voxel_size_mm: 20.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let magnetometer = MeasurementMatrix::from_model_spatial_description(
+            &SpatialDescription::from_model_config(&config)?,
+        )?;
+        config.common.sensor_type = SensorType::Gradiometer { baseline_mm: 1e7 };
+        let distant = MeasurementMatrix::from_model_spatial_description(
+            &SpatialDescription::from_model_config(&config)?,
+        )?;
+        config.common.sensor_type = SensorType::Gradiometer { baseline_mm: 50.0 };
+        let close = MeasurementMatrix::from_model_spatial_description(
+            &SpatialDescription::from_model_config(&config)?,
+        )?;
+
+        assert_relative_eq!(*magnetometer, *distant, epsilon = 1e-6, max_relative = 1e-4);
+        assert_ne!(magnetometer, close);
+        Ok(())
+    }
+
+    #[test]
+    fn trajectory_interpolates_between_keyframes() -> Result<()> {
+        let config = Model {
+            common: Common {
+                sensors_per_axis: [2, 2, 2],
+                voxel_size_mm: 20.0,
+                sensor_array_motion: SensorArrayMotion::Trajectory,
+                sensor_array_trajectory: SensorArrayTrajectory::Orbit {
+                    radius_mm: 30.0,
+                    period_s: 1.0,
+                    number_of_keyframes: 4,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let spatial_description = SpatialDescription::from_model_config(&config)?;
+        assert_eq!(spatial_description.sensors.count_beats(), 1);
+        assert_eq!(spatial_description.sensors.count_array_positions(), 4);
+
+        let measurement_matrix =
+            MeasurementMatrix::from_model_spatial_description(&spatial_description)?;
+        let interpolation = MeasurementInterpolation::from_model_config(&config, 8.0, 1.0);
+        let keyframes = interpolation.at_step(1).unwrap();
+        assert_eq!((keyframes.lower, keyframes.upper), (0, 1));
+        assert_relative_eq!(keyframes.weight, 0.5);
+        assert_eq!(interpolation.at_step(7).unwrap().upper, 0);
+
+        let system_states = Array1::ones(measurement_matrix.shape()[2]);
+        let predicted = measurement_matrix.predict(&interpolation, &system_states.view(), 0, 1);
+        let expected = (measurement_matrix.at_beat(0).dot(&system_states)
+            + measurement_matrix.at_beat(1).dot(&system_states))
+            * 0.5;
+        assert_relative_eq!(predicted, expected, max_relative = 1e-5);
+        Ok(())
+    }
 }
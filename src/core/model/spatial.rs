@@ -1,4 +1,5 @@
 pub mod nifti;
+pub mod sensor_optimization;
 pub mod sensors;
 pub mod voxels;
 
@@ -7,15 +8,16 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
 use self::{sensors::Sensors, voxels::Voxels};
-use crate::core::config::model::Model;
+use crate::core::config::model::{Model, TorsoModel};
 
 /// Struct containing fields for the heart,
-/// voxels and sensors spatial model components.
+/// voxels, sensors and torso spatial model components.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[allow(clippy::module_name_repetitions)]
 pub struct SpatialDescription {
     pub voxels: Voxels,
     pub sensors: Sensors,
+    pub torso: TorsoModel,
 }
 
 impl SpatialDescription {
@@ -32,13 +34,15 @@ impl SpatialDescription {
         Self {
             voxels: Voxels::empty(voxels_in_dims),
             sensors: Sensors::empty(number_of_sensors, sensor_motion_steps),
+            torso: TorsoModel::default(),
         }
     }
 
     /// Creates a `SpatialDescription` from the given [`Model`] configuration.
     ///
     /// Constructs the `heart`, `voxels`, and `sensors` fields by calling their
-    /// respective `from_model_config()` methods.
+    /// respective `from_model_config()` methods. The torso model is taken
+    /// from the config as is.
     #[tracing::instrument(level = "debug", skip_all)]
     pub fn from_model_config(config: &Model) -> Result<Self> {
         debug!("Creating spatial description from model config");
@@ -48,9 +52,13 @@ impl SpatialDescription {
             Voxels::from_mri_model_config(config)?
         };
 
-        let sensors = Sensors::from_model_config(&config.common);
+        let sensors = Sensors::from_model_config(&config.common)?;
 
-        Ok(Self { voxels, sensors })
+        Ok(Self {
+            voxels,
+            sensors,
+            torso: config.common.torso_model.clone(),
+        })
     }
 
     /// Saves the spatial description components to .npy files.
@@ -78,7 +86,7 @@ mod tests {
     use crate::{
         core::config::model::{Common, Handcrafted, Mri},
         tests::setup_folder,
-        vis::plotting::gif::voxel_type::voxel_types_over_slices_plot,
+        vis::plotting::{gif::voxel_type::voxel_types_over_slices_plot, PlotTheme},
     };
 
     const COMMON_PATH: &str = "tests/core/model/spatial";
@@ -144,6 +152,7 @@ mod tests {
             Some(Axis(0)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -156,6 +165,7 @@ mod tests {
             Some(Axis(1)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -168,6 +178,7 @@ mod tests {
             Some(Axis(2)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
         Ok(())
@@ -196,6 +207,7 @@ mod tests {
             Some(Axis(0)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -208,6 +220,7 @@ mod tests {
             Some(Axis(1)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -220,6 +233,7 @@ mod tests {
             Some(Axis(2)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
         Ok(())
@@ -249,6 +263,7 @@ mod tests {
             Some(Axis(0)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -261,6 +276,7 @@ mod tests {
             Some(Axis(1)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
 
@@ -273,6 +289,7 @@ mod tests {
             Some(Axis(2)),
             Some(&path),
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create voxel types plot");
         Ok(())
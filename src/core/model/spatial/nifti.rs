@@ -1,13 +1,16 @@
-use std::path::Path;
+use std::{fs, ops::Range, path::Path};
 
 use anyhow::{anyhow, Context};
-use ndarray::{s, Ix3};
-use nifti::{IntoNdArray, NiftiObject, ReaderOptions};
+use ndarray::{s, Array1, Array3, Array4, Ix3};
+use nifti::{writer::WriterOptions, IntoNdArray, NiftiObject, ReaderOptions};
 use strum::EnumCount;
-use tracing::{debug, trace};
+use tracing::{debug, info, trace};
 
-use super::voxels::VoxelType;
-use crate::core::config::model::Model;
+use super::voxels::{VoxelPositions, VoxelType, Voxels};
+use crate::core::{
+    algorithm::{estimation::Estimations, metrics::predict_voxeltype},
+    config::model::Model,
+};
 
 #[derive(Debug)]
 pub struct MriData {
@@ -42,6 +45,101 @@ where
     })
 }
 
+#[derive(Debug)]
+pub struct FiberData {
+    pub fibers: Array4<f32>,
+    pub voxel_size_mm: [f32; 3],
+}
+
+/// Loads a NIFTI volume holding a fiber vector per voxel, stored either as
+/// a 4D volume or as a 5D vector volume with a singleton time axis.
+///
+/// The volume and the fiber vectors are reoriented the same way as the
+/// segmentation in [`load_from_nii`].
+#[tracing::instrument(level = "debug")]
+pub(crate) fn load_fibers_from_nii<P>(path: P) -> anyhow::Result<FiberData>
+where
+    P: AsRef<Path> + std::fmt::Debug,
+{
+    debug!("Loading fiber nifti file from {path:?}");
+    let object = ReaderOptions::new()
+        .read_file(&path)
+        .with_context(|| format!("Failed to read NIFTI file: {path:?}"))?;
+    let header = object.header();
+    let volume = object.volume();
+    let data = volume.into_ndarray::<f32>().with_context(|| {
+        format!("Failed to convert NIFTI volume to f32 array for file: {path:?}")
+    })?;
+    let shape = data.shape().to_vec();
+    anyhow::ensure!(
+        shape.len() >= 4 && shape[3..].iter().product::<usize>() == 3,
+        "Expected a fiber vector with three components per voxel in {path:?}, found shape {shape:?}"
+    );
+    let data = data
+        .as_standard_layout()
+        .into_owned()
+        .into_shape_with_order((shape[0], shape[1], shape[2], 3))
+        .with_context(|| format!("Failed to reshape fiber volume of file: {path:?}"))?;
+    let mut fibers = data;
+    fibers.swap_axes(1, 2);
+    let fibers = fibers.slice(s![.., .., ..;-1, ..]);
+    // the vector components follow the reoriented axes
+    let mut reoriented = Array4::zeros(fibers.raw_dim());
+    reoriented
+        .slice_mut(s![.., .., .., 0])
+        .assign(&fibers.slice(s![.., .., .., 0]));
+    reoriented
+        .slice_mut(s![.., .., .., 1])
+        .assign(&fibers.slice(s![.., .., .., 2]));
+    reoriented
+        .slice_mut(s![.., .., .., 2])
+        .assign(&fibers.slice(s![.., .., .., 1]).mapv(|value| -value));
+    let voxel_size_mm = [header.pixdim[1], header.pixdim[3], header.pixdim[2]];
+    Ok(FiberData {
+        fibers: reoriented,
+        voxel_size_mm,
+    })
+}
+
+/// Returns the fiber vector at the center of the area covered by the model
+/// voxel at the given position, or zeros if it lies outside of the volume.
+#[tracing::instrument(level = "trace", skip_all)]
+pub(crate) fn determine_fiber_direction(
+    config: &Model,
+    position: ndarray::ArrayBase<ndarray::ViewRepr<&f32>, ndarray::Dim<[usize; 1]>>,
+    fiber_data: &FiberData,
+) -> Array1<f32> {
+    let shape = fiber_data.fibers.shape();
+    let [x, y, z] = index_ranges(config, position, fiber_data.voxel_size_mm)
+        .map(|range| (range.start + range.end) / 2);
+    if x >= shape[0] || y >= shape[1] || z >= shape[2] {
+        return Array1::zeros(3);
+    }
+    fiber_data.fibers.slice(s![x, y, z, ..]).to_owned()
+}
+
+/// Returns the label of the volume at the given position, or `None` if it
+/// lies outside of the volume.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub(crate) fn label_at_position(
+    config: &Model,
+    position_mm: [f32; 3],
+    mri_data: &MriData,
+) -> Option<usize> {
+    let shape = mri_data.segmentation.shape();
+    let mut index = [0; 3];
+    for dimension in 0..3 {
+        let offset_mm = position_mm[dimension] - config.common.heart_offset_mm[dimension];
+        let cell = (offset_mm / mri_data.voxel_size_mm[dimension]).floor();
+        if cell < 0.0 || cell as usize >= shape[dimension] {
+            return None;
+        }
+        index[dimension] = cell as usize;
+    }
+    Some(mri_data.segmentation[index] as usize)
+}
+
 #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
 #[tracing::instrument(level = "trace", skip_all)]
 pub(crate) fn determine_voxel_type(
@@ -53,30 +151,11 @@ pub(crate) fn determine_voxel_type(
     let mut count = [0; VoxelType::COUNT];
     trace!("Determining voxel type at position {position:?}");
 
-    // calculate the search area
-    let x_start_mm =
-        position[0] - config.common.heart_offset_mm[0] - config.common.voxel_size_mm / 2.0;
-    let x_stop_mm =
-        position[0] - config.common.heart_offset_mm[0] + config.common.voxel_size_mm / 2.0;
-    let y_start_mm =
-        position[1] - config.common.heart_offset_mm[1] - config.common.voxel_size_mm / 2.0;
-    let y_stop_mm =
-        position[1] - config.common.heart_offset_mm[1] + config.common.voxel_size_mm / 2.0;
-    let z_start_mm =
-        position[2] - config.common.heart_offset_mm[2] - config.common.voxel_size_mm / 2.0;
-    let z_stop_mm =
-        position[2] - config.common.heart_offset_mm[2] + config.common.voxel_size_mm / 2.0;
-
-    let x_start_index = (x_start_mm / mri_data.voxel_size_mm[0]).floor() as usize;
-    let x_stop_index = (x_stop_mm / mri_data.voxel_size_mm[0]).ceil() as usize;
-    let y_start_index = (y_start_mm / mri_data.voxel_size_mm[1]).floor() as usize;
-    let y_stop_index = (y_stop_mm / mri_data.voxel_size_mm[1]).ceil() as usize;
-    let z_start_index = (z_start_mm / mri_data.voxel_size_mm[2]).floor() as usize;
-    let z_stop_index = (z_stop_mm / mri_data.voxel_size_mm[2]).ceil() as usize;
-
-    for x in x_start_index..x_stop_index {
-        for y in y_start_index..y_stop_index {
-            for z in z_start_index..z_stop_index {
+    let [x_range, y_range, z_range] = mri_index_ranges(config, position, mri_data);
+
+    for x in x_range {
+        for y in y_range.clone() {
+            for z in z_range.clone() {
                 let voxel_type =
                     VoxelType::from_mri_data(mri_data.segmentation[[x, y, z]] as usize);
                 count[voxel_type as usize] += 1;
@@ -111,6 +190,155 @@ pub(crate) fn determine_voxel_type(
     Ok(voxel_type)
 }
 
+/// Calculates the ranges of MRI indices covered by the model voxel at the
+/// given position.
+#[tracing::instrument(level = "trace", skip_all)]
+fn mri_index_ranges(
+    config: &Model,
+    position: ndarray::ArrayBase<ndarray::ViewRepr<&f32>, ndarray::Dim<[usize; 1]>>,
+    mri_data: &MriData,
+) -> [Range<usize>; 3] {
+    index_ranges(config, position, mri_data.voxel_size_mm)
+}
+
+/// Calculates the ranges of indices of a volume with the given voxel size
+/// covered by the model voxel at the given position.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn index_ranges(
+    config: &Model,
+    position: ndarray::ArrayBase<ndarray::ViewRepr<&f32>, ndarray::Dim<[usize; 1]>>,
+    voxel_size_mm: [f32; 3],
+) -> [Range<usize>; 3] {
+    // calculate the search area
+    [0, 1, 2].map(|dimension| {
+        let center_mm = position[dimension] - config.common.heart_offset_mm[dimension];
+        let start_mm = center_mm - config.common.voxel_size_mm / 2.0;
+        let stop_mm = center_mm + config.common.voxel_size_mm / 2.0;
+        let start_index = (start_mm / voxel_size_mm[dimension]).floor() as usize;
+        let stop_index = (stop_mm / voxel_size_mm[dimension]).ceil() as usize;
+        start_index..stop_index
+    })
+}
+
+/// Writes the estimated activation times, average delays and predicted voxel
+/// types of a reconstruction as NIFTI volumes into the given directory.
+///
+/// The volumes share the grid and header of the MRI scan the model was built
+/// from, so they can be overlaid on it in standard imaging tools. Predicted
+/// voxel types are stored as [`VoxelType`] discriminants, MRI voxels outside
+/// of the model are NaN.
+///
+/// # Errors
+///
+/// Returns an error if the model was not built from an MRI scan, the scan
+/// cannot be read or a volume cannot be written.
+#[tracing::instrument(level = "info", skip(estimations))]
+pub fn export_results_to_nii(
+    config: &Model,
+    voxels: &Voxels,
+    estimations: &Estimations,
+    threshold: f32,
+    path: &Path,
+) -> anyhow::Result<()> {
+    info!(
+        "Exporting reconstruction to NIFTI volumes in {}",
+        path.display()
+    );
+    fs::create_dir_all(path)
+        .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+    let shape = voxels.numbers.raw_dim();
+    let mut activation_time_ms = Array3::from_elem(shape, f32::NAN);
+    let mut average_delay_samples = Array3::from_elem(shape, f32::NAN);
+    for ((index, number), (activation_time, average_delay)) in voxels.numbers.indexed_iter().zip(
+        activation_time_ms
+            .iter_mut()
+            .zip(average_delay_samples.iter_mut()),
+    ) {
+        let Some(number) = number else {
+            continue;
+        };
+        *activation_time = *estimations
+            .activation_times
+            .get(number / 3)
+            .with_context(|| format!("Missing activation time for voxel {index:?}"))?;
+        if let Some(Some(delay)) = estimations.average_delays.get(number / 3) {
+            *average_delay = *delay;
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let voxel_types = predict_voxeltype(estimations, &voxels.types, &voxels.numbers, threshold)
+        .map(|voxel_type| *voxel_type as usize as f32);
+
+    save_volume_to_nii(
+        config,
+        &voxels.positions_mm,
+        &activation_time_ms,
+        &path.join("activation_time_ms.nii"),
+    )?;
+    save_volume_to_nii(
+        config,
+        &voxels.positions_mm,
+        &average_delay_samples,
+        &path.join("average_delay_samples.nii"),
+    )?;
+    save_volume_to_nii(
+        config,
+        &voxels.positions_mm,
+        &voxel_types,
+        &path.join("voxel_types.nii"),
+    )?;
+    Ok(())
+}
+
+/// Resamples values given on the model voxel grid onto the grid of the MRI
+/// scan the model was built from and writes them to a NIFTI file, using the
+/// header of the scan as reference.
+///
+/// # Errors
+///
+/// Returns an error if the model was not built from an MRI scan, the scan
+/// cannot be read or the file cannot be written.
+#[tracing::instrument(level = "debug", skip(positions, values))]
+pub fn save_volume_to_nii(
+    config: &Model,
+    positions: &VoxelPositions,
+    values: &Array3<f32>,
+    path: &Path,
+) -> anyhow::Result<()> {
+    debug!("Saving volume to nifti file {}", path.display());
+    let mri_path = &config
+        .mri
+        .as_ref()
+        .context("NIFTI export requires a model built from an MRI scan")?
+        .path;
+    let reference = ReaderOptions::new()
+        .read_file(mri_path)
+        .with_context(|| format!("Failed to read NIFTI file: {}", mri_path.display()))?;
+    let mri_data = load_from_nii(mri_path)?;
+    let shape = mri_data.segmentation.raw_dim();
+    let mut volume = Array3::from_elem(shape, f32::NAN);
+    for ((x, y, z), value) in values.indexed_iter() {
+        if value.is_nan() {
+            continue;
+        }
+        let ranges = mri_index_ranges(config, positions.slice(s![x, y, z, ..]), &mri_data);
+        let [x_range, y_range, z_range] = [0, 1, 2].map(|dimension| {
+            ranges[dimension].start.min(shape[dimension])
+                ..ranges[dimension].end.min(shape[dimension])
+        });
+        volume.slice_mut(s![x_range, y_range, z_range]).fill(*value);
+    }
+    // undo the reorientation applied in `load_from_nii`
+    let mut volume = volume.slice(s![.., .., ..;-1]).to_owned();
+    volume.swap_axes(1, 2);
+    WriterOptions::new(path)
+        .reference_header(reference.header())
+        .write_nifti(&volume)
+        .with_context(|| format!("Failed to write NIFTI file: {}", path.display()))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -119,7 +347,11 @@ mod tests {
     use ndarray::Axis;
 
     use super::*;
-    use crate::{tests::setup_folder, vis::plotting::gif::matrix::matrix_over_slices_plot};
+    use crate::{
+        core::config::model::{Mri, DEFAULT_HEART_OFFSET_MRI},
+        tests::setup_folder,
+        vis::plotting::{gif::matrix::matrix_over_slices_plot, PlotTheme},
+    };
 
     const COMMON_PATH: &str = "tests/core/model/spatial/nifti";
 
@@ -130,6 +362,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[allow(
+        clippy::float_cmp,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn volume_round_trip_aligns_with_mri() -> anyhow::Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let mut config = Model::default();
+        config.handcrafted = None;
+        config.mri = Some(Mri {
+            path: Path::new("assets/Segmentation.nii").to_path_buf(),
+        });
+        config.common.heart_offset_mm = DEFAULT_HEART_OFFSET_MRI;
+        let voxels = Voxels::from_mri_model_config(&config)?;
+        let values = voxels.types.map(|voxel_type| {
+            if voxel_type.is_connectable() {
+                1.0
+            } else {
+                f32::NAN
+            }
+        });
+        let file_path = path.join("connectable.nii");
+
+        save_volume_to_nii(&config, &voxels.positions_mm, &values, &file_path)?;
+
+        let exported = load_from_nii(&file_path)?;
+        let original = load_from_nii("assets/Segmentation.nii")?;
+        assert_eq!(exported.segmentation.shape(), original.segmentation.shape());
+        assert_eq!(exported.voxel_size_mm, original.voxel_size_mm);
+        let overlap = exported
+            .segmentation
+            .iter()
+            .zip(original.segmentation.iter())
+            .filter(|(value, label)| {
+                **value == 1.0 && VoxelType::from_mri_data(**label as usize).is_connectable()
+            })
+            .count();
+        assert!(overlap > 0);
+        Ok(())
+    }
+
     #[test]
     #[allow(clippy::cast_possible_truncation)]
     #[ignore = "expensive integration test"]
@@ -156,6 +431,7 @@ mod tests {
             None,
             None,
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create matrix plot");
         let path = Path::new(COMMON_PATH).join("slice_y.gif");
@@ -174,6 +450,7 @@ mod tests {
             None,
             None,
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create matrix plot");
         let path = Path::new(COMMON_PATH).join("slice_z.gif");
@@ -192,6 +469,7 @@ mod tests {
             None,
             None,
             Some(time_per_frame_ms),
+            PlotTheme::Light,
         )
         .expect("Failed to create matrix plot");
         Ok(())
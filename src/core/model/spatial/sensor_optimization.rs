@@ -0,0 +1,298 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use nalgebra::{DMatrix, SymmetricEigen};
+use ndarray::{arr1, Array2, ArrayView2, Axis};
+use ndarray_npy::WriteNpyExt;
+use tracing::{debug, info, trace};
+
+use super::SpatialDescription;
+use crate::core::{config::model::Model, model::functional::measurement::MeasurementMatrix};
+
+/// Eigenvalues of the gram matrix below this fraction of the largest one
+/// belong to singular values below 1e-6 of the largest and are ignored.
+const RANK_TOLERANCE: f64 = 1e-12;
+
+/// Rows whose squared residual falls below this value are already spanned by
+/// the selected positions and are not used to deflate the basis.
+const DEFLATION_TOLERANCE: f64 = 1e-10;
+
+/// Sensor positions chosen from a candidate array, in the order they were
+/// selected.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SensorPlacement {
+    // selected positions, one per row
+    pub positions_mm: Array2<f32>,
+    // index of each selected position among the candidate positions
+    pub candidate_indices: Vec<usize>,
+    // leverage of each position when it was selected, i.e. the part of the
+    // dominant measurement subspace it adds to the positions before it
+    pub scores: Vec<f32>,
+    pub number_of_candidates: usize,
+}
+
+impl SensorPlacement {
+    /// Saves the selected positions to a .npy file of shape (positions, 3),
+    /// which scenarios can use with the `FromFile` sensor array geometry.
+    /// Creates the parent directory if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or the file cannot
+    /// be written.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save_npy(&self, path: &Path) -> Result<()> {
+        debug!("Saving sensor placement to {}", path.display());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "Failed to create directory for sensor positions: {}",
+                    parent.display()
+                )
+            })?;
+        }
+        let writer = BufWriter::new(File::create(path).with_context(|| {
+            format!("Failed to create sensor position file: {}", path.display())
+        })?);
+        self.positions_mm
+            .write_npy(writer)
+            .with_context(|| format!("Failed to write sensor positions to: {}", path.display()))
+    }
+}
+
+/// Selects the given number of sensor positions from the sensor array of
+/// the model config, which serves as the candidate surface. See
+/// [`optimize_sensor_positions`].
+///
+/// # Errors
+///
+/// Returns an error if the model cannot be created or the selection fails.
+#[tracing::instrument(level = "info", skip(config))]
+pub fn optimize_sensor_positions_from_model_config(
+    config: &Model,
+    number_of_positions: usize,
+) -> Result<SensorPlacement> {
+    info!("Optimizing sensor positions from model config");
+    let spatial_description = SpatialDescription::from_model_config(config)
+        .context("Failed to create the candidate sensor array")?;
+    optimize_sensor_positions(&spatial_description, number_of_positions)
+}
+
+/// Greedily selects the sensor positions that carry the most information
+/// about the heart, using the sensors of the spatial description as
+/// candidates.
+///
+/// All sensors at the same position, e.g. the three axes of a vector
+/// magnetometer, and all array positions of a moving array form one
+/// candidate. The dominant subspace of the stacked measurement matrix is
+/// spanned by its leading left singular vectors, as many as rows can be
+/// selected. Each step picks the candidate with the largest leverage score
+/// in that subspace and removes the directions it covers from the remaining
+/// candidates, which is a QR decomposition with pivoting over the
+/// candidates. Rows are not whitened, so in mixed arrays the modality with
+/// the larger values dominates.
+///
+/// # Errors
+///
+/// Returns an error if the measurement matrix cannot be created or the
+/// number of positions is zero or exceeds the number of candidates.
+#[allow(clippy::cast_possible_truncation, clippy::too_many_lines)]
+#[tracing::instrument(level = "info", skip(spatial_description))]
+pub fn optimize_sensor_positions(
+    spatial_description: &SpatialDescription,
+    number_of_positions: usize,
+) -> Result<SensorPlacement> {
+    info!("Selecting {number_of_positions} sensor positions");
+    let sensors = &spatial_description.sensors;
+    let candidates = candidate_positions(&sensors.positions_mm.view());
+    anyhow::ensure!(
+        number_of_positions > 0 && number_of_positions <= candidates.len(),
+        "Number of sensor positions must be between 1 and the {} candidates, got {}",
+        candidates.len(),
+        number_of_positions
+    );
+
+    let measurement_matrix = MeasurementMatrix::from_model_spatial_description(spatial_description)
+        .context("Failed to create the measurement matrix of the candidates")?;
+    let (number_of_beats, number_of_sensors, number_of_states) = measurement_matrix.dim();
+    let rows = ArrayView2::from_shape(
+        (number_of_beats * number_of_sensors, number_of_states),
+        measurement_matrix
+            .as_slice()
+            .context("Measurement matrix is not in standard layout")?,
+    )
+    .context("Failed to stack the beats of the measurement matrix")?;
+    // rows of all array positions of a sensor belong to its candidate
+    let candidate_rows: Vec<Vec<usize>> = candidates
+        .iter()
+        .map(|(_, sensor_indices)| {
+            (0..number_of_beats)
+                .flat_map(|beat| {
+                    sensor_indices
+                        .iter()
+                        .map(move |sensor| beat * number_of_sensors + sensor)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut rows_per_candidate: Vec<usize> = candidate_rows.iter().map(Vec::len).collect();
+    rows_per_candidate.sort_unstable_by(|a, b| b.cmp(a));
+    let selectable_rows = rows_per_candidate[..number_of_positions].iter().sum();
+    let mut residual = dominant_basis(&rows, selectable_rows);
+
+    let mut selected = vec![false; candidates.len()];
+    let mut candidate_indices = Vec::with_capacity(number_of_positions);
+    let mut scores = Vec::with_capacity(number_of_positions);
+    for _ in 0..number_of_positions {
+        let (best, score) = candidate_rows
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !selected[*index])
+            .map(|(index, row_indices)| {
+                let leverage: f64 = row_indices
+                    .iter()
+                    .map(|row| residual.row(*row).dot(&residual.row(*row)))
+                    .sum();
+                (index, leverage)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .context("No candidate position left to select")?;
+        trace!("Selected candidate {best} with leverage {score}");
+        selected[best] = true;
+        candidate_indices.push(best);
+        scores.push(score as f32);
+        for row in &candidate_rows[best] {
+            let direction = residual.row(*row).to_owned();
+            let norm_squared = direction.dot(&direction);
+            if norm_squared < DEFLATION_TOLERANCE {
+                continue;
+            }
+            let direction = direction / norm_squared.sqrt();
+            let projections = residual.dot(&direction);
+            residual -= &projections
+                .insert_axis(Axis(1))
+                .dot(&direction.insert_axis(Axis(0)));
+        }
+    }
+
+    let mut positions_mm = Array2::zeros((number_of_positions, 3));
+    for (row, candidate) in candidate_indices.iter().enumerate() {
+        positions_mm
+            .row_mut(row)
+            .assign(&arr1(&candidates[*candidate].0));
+    }
+    info!(
+        "Selected {number_of_positions} of {} sensor positions",
+        candidates.len()
+    );
+    Ok(SensorPlacement {
+        positions_mm,
+        candidate_indices,
+        scores,
+        number_of_candidates: candidates.len(),
+    })
+}
+
+/// Groups the sensors by position, keeping the order in which the
+/// positions first appear.
+#[tracing::instrument(level = "trace", skip_all)]
+fn candidate_positions(positions_mm: &ArrayView2<f32>) -> Vec<([f32; 3], Vec<usize>)> {
+    trace!("Grouping sensors by position");
+    let mut candidates: Vec<([f32; 3], Vec<usize>)> = Vec::new();
+    for (sensor, position) in positions_mm.rows().into_iter().enumerate() {
+        let position = [position[0], position[1], position[2]];
+        match candidates
+            .iter_mut()
+            .find(|(candidate, _)| *candidate == position)
+        {
+            Some((_, sensors)) => sensors.push(sensor),
+            None => candidates.push((position, vec![sensor])),
+        }
+    }
+    candidates
+}
+
+/// Returns the leading left singular vectors of the rows as columns, at
+/// most the given number and only those above the rank tolerance.
+///
+/// They are the eigenvectors of the gram matrix of the rows, which is much
+/// smaller than the measurement matrix itself.
+#[tracing::instrument(level = "debug", skip(rows))]
+fn dominant_basis(rows: &ArrayView2<f32>, maximum_rank: usize) -> Array2<f64> {
+    debug!("Calculating dominant measurement subspace");
+    let gram = rows.dot(&rows.t());
+    let number_of_rows = gram.nrows();
+    let eigen = SymmetricEigen::new(DMatrix::from_fn(number_of_rows, number_of_rows, |i, j| {
+        f64::from(gram[(i, j)])
+    }));
+    let mut order: Vec<usize> = (0..number_of_rows).collect();
+    order.sort_by(|a, b| eigen.eigenvalues[*b].total_cmp(&eigen.eigenvalues[*a]));
+    let largest = order.first().map_or(0.0, |index| eigen.eigenvalues[*index]);
+    let rank = order
+        .iter()
+        .take_while(|index| eigen.eigenvalues[**index] > largest * RANK_TOLERANCE)
+        .count()
+        .min(maximum_rank);
+    debug!("Dominant subspace has rank {rank}");
+    Array2::from_shape_fn((number_of_rows, rank), |(row, column)| {
+        eigen.eigenvectors[(row, order[column])]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::core::{
+        config::model::{SensorArrayGeometry, SensorType},
+        model::spatial::sensors::Sensors,
+    };
+
+    #[test]
+    fn selects_distinct_candidates_with_decreasing_leverage() -> Result<()> {
+        let config = Model::default();
+        let placement = optimize_sensor_positions_from_model_config(&config, 8)?;
+
+        assert_eq!(placement.positions_mm.shape(), &[8, 3]);
+        assert_eq!(
+            placement.number_of_candidates,
+            config.common.sensors_per_axis.iter().product::<usize>()
+        );
+        let mut indices = placement.candidate_indices.clone();
+        indices.sort_unstable();
+        indices.dedup();
+        assert_eq!(indices.len(), 8);
+        for pair in placement.scores.windows(2) {
+            assert!(pair[1] <= pair[0] * 1.001, "{:?}", placement.scores);
+        }
+        assert!(optimize_sensor_positions_from_model_config(&config, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn placement_is_usable_as_file_geometry() -> Result<()> {
+        let mut config = Model::default();
+        let placement = optimize_sensor_positions_from_model_config(&config, 5)?;
+        let path = env::temp_dir()
+            .join("cardiotrust_sensor_optimization")
+            .join("sensor_positions_mm.npy");
+        placement.save_npy(&path)?;
+
+        config.common.sensor_array_geometry = SensorArrayGeometry::FromFile { path: path.clone() };
+        config.common.sensor_type = SensorType::ThreeAxis;
+        let sensors = Sensors::from_model_config(&config.common)?;
+
+        assert_eq!(sensors.count(), 15);
+        for (position, expected) in placement.positions_mm.rows().into_iter().enumerate() {
+            assert_eq!(sensors.positions_mm.row(3 * position), expected);
+        }
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
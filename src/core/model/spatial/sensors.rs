@@ -3,14 +3,39 @@ use std::{
     io::BufWriter,
 };
 
-use anyhow::Context;
-use ndarray::{arr1, s, Array1, Array2};
-use ndarray_npy::WriteNpyExt;
+use anyhow::{Context, Result};
+use ndarray::{arr1, concatenate, s, Array1, Array2, Axis};
+use ndarray_npy::{read_npy, WriteNpyExt};
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace};
 
-use crate::core::config::model::{Common, SensorArrayGeometry, SensorArrayMotion};
+use crate::core::config::model::{
+    Common, SensorArrayGeometry, SensorArrayModality, SensorArrayMotion, SensorType,
+};
+
+/// The quantity measured by a sensor.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum SensorModality {
+    /// Magnetic flux density along the sensor orientation in pT.
+    Magnetic,
+    /// Difference of the magnetic flux density along the sensor orientation
+    /// to a second coil shifted by the baseline along the orientation in pT.
+    Gradiometer { baseline_mm: f32 },
+    /// Electric potential in mV.
+    Electric,
+}
+
+impl SensorModality {
+    /// Returns the unit of the values measured by the sensor.
+    #[must_use]
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::Magnetic | Self::Gradiometer { .. } => "pT",
+            Self::Electric => "mV",
+        }
+    }
+}
 
 #[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -20,6 +45,10 @@ pub struct Sensors {
     pub array_radius_mm: f32,
     pub positions_mm: Array2<f32>,
     pub orientations_xyz: Array2<f32>,
+    pub modalities: Vec<SensorModality>,
+    // the array offsets are keyframes of a continuous trajectory during a
+    // single beat instead of one offset per beat
+    pub continuous_motion: bool,
 }
 
 impl Sensors {
@@ -35,6 +64,8 @@ impl Sensors {
             array_radius_mm: 100.0,
             positions_mm: Array2::zeros((number_of_sensors, 3)),
             orientations_xyz: Array2::zeros((number_of_sensors, 3)),
+            modalities: vec![SensorModality::Magnetic; number_of_sensors],
+            continuous_motion: false,
         }
     }
 
@@ -45,16 +76,25 @@ impl Sensors {
     /// array volume, starting from the configured `sensor_array_origin_mm`.
     ///
     /// The sensor orientations alternate between x, y, and z axes aligned.
-    #[must_use]
-    #[allow(clippy::cast_precision_loss)]
+    /// Depending on the modality, the magnetometers are replaced by or
+    /// complemented with one electrode per sensor position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sensor positions of a file geometry cannot be
+    /// read or do not have three coordinates.
+    #[allow(clippy::cast_precision_loss, clippy::too_many_lines)]
     #[tracing::instrument(level = "debug", skip_all)]
-    pub fn from_model_config(config: &Common) -> Self {
+    pub fn from_model_config(config: &Common) -> Result<Self> {
         debug!("Creating sensors from model config");
         let number_of_motion_steps = match config.sensor_array_motion {
             SensorArrayMotion::Static => 1,
             SensorArrayMotion::Grid => config.sensor_array_motion_steps.iter().product(),
+            SensorArrayMotion::Trajectory => {
+                config.sensor_array_trajectory.keyframe_offsets_mm().len()
+            }
         };
-        let mut sensors = match config.sensor_array_geometry {
+        let mut sensors = match &config.sensor_array_geometry {
             SensorArrayGeometry::Cube => {
                 #[allow(clippy::cast_precision_loss)]
                 let distance = [
@@ -62,7 +102,7 @@ impl Sensors {
                     config.sensor_array_size_mm[1] / config.sensors_per_axis[1] as f32,
                     config.sensor_array_size_mm[2] / config.sensors_per_axis[2] as f32,
                 ];
-                let dim = if config.three_d_sensors { 3 } else { 1 };
+                let dim = config.sensor_type.sensors_per_position();
                 let num_sensors = config.sensors_per_axis.iter().product::<usize>() * dim;
                 let mut sensors = Self::empty(num_sensors, number_of_motion_steps);
                 let mut i: usize = 0;
@@ -103,7 +143,7 @@ impl Sensors {
                     config.sensor_array_size_mm[1] / config.sensors_per_axis[1] as f32,
                     config.sensor_array_size_mm[2] / config.sensors_per_axis[2] as f32,
                 ];
-                let dim = if config.three_d_sensors { 3 } else { 1 };
+                let dim = config.sensor_type.sensors_per_position();
                 let num_sensors = config.number_of_sensors * dim;
                 let num_occupied = config.number_of_sensors;
                 let num_places = config.sensors_per_axis.iter().product::<usize>();
@@ -156,7 +196,7 @@ impl Sensors {
                 sensors
             }
             SensorArrayGeometry::Cylinder => {
-                let dim = if config.three_d_sensors { 3 } else { 1 };
+                let dim = config.sensor_type.sensors_per_position();
                 let num = config.number_of_sensors * dim;
                 let mut sensors = Self::empty(num, number_of_motion_steps);
                 let radius = config.sensor_array_radius_mm;
@@ -190,6 +230,41 @@ impl Sensors {
                 sensors.array_radius_mm = config.sensor_array_radius_mm;
                 sensors
             }
+            SensorArrayGeometry::FromFile { path } => {
+                let positions_mm: Array2<f32> = read_npy(path).with_context(|| {
+                    format!("Failed to read sensor positions from {}", path.display())
+                })?;
+                anyhow::ensure!(
+                    positions_mm.nrows() > 0 && positions_mm.ncols() == 3,
+                    "Sensor position file {} must hold at least one position with three \
+                     coordinates, got shape {:?}",
+                    path.display(),
+                    positions_mm.shape()
+                );
+                let dim = config.sensor_type.sensors_per_position();
+                let mut sensors = Self::empty(positions_mm.nrows() * dim, number_of_motion_steps);
+                let mut i = 0;
+                for position in positions_mm.rows() {
+                    for _ in 0..dim {
+                        sensors.positions_mm.slice_mut(s![i, ..]).assign(&position);
+                        let orientation = match i % 3 {
+                            0 => arr1(&[1.0, 0.0, 0.0]),
+                            1 => arr1(&[0.0, 1.0, 0.0]),
+                            2 => arr1(&[0.0, 0.0, 1.0]),
+                            _ => arr1(&[0.0, 0.0, 0.0]),
+                        };
+                        sensors
+                            .orientations_xyz
+                            .slice_mut(s![i, ..])
+                            .assign(&orientation);
+                        i += 1;
+                    }
+                }
+                if let Some(center_mm) = positions_mm.mean_axis(Axis(0)) {
+                    sensors.array_center_mm = center_mm;
+                }
+                sensors
+            }
         };
         if config.sensor_array_motion == SensorArrayMotion::Grid {
             let step_size_mm_x = if config.sensor_array_motion_steps[0] > 1 {
@@ -223,7 +298,90 @@ impl Sensors {
                 }
             }
         }
-        sensors
+        if config.sensor_array_motion == SensorArrayMotion::Trajectory {
+            for (keyframe, offset_mm) in config
+                .sensor_array_trajectory
+                .keyframe_offsets_mm()
+                .iter()
+                .enumerate()
+            {
+                sensors
+                    .array_offsets_mm
+                    .slice_mut(s![keyframe, ..])
+                    .assign(&arr1(offset_mm));
+            }
+            sensors.continuous_motion = true;
+        }
+        if let SensorType::Gradiometer { baseline_mm } = config.sensor_type {
+            sensors
+                .modalities
+                .fill(SensorModality::Gradiometer { baseline_mm });
+        }
+        sensors.apply_modality(
+            config.sensor_array_modality,
+            config.sensor_type.sensors_per_position(),
+        );
+        Ok(sensors)
+    }
+
+    /// Turns the magnetometers into electrodes or adds an electrode at
+    /// every sensor position, depending on the modality. Electrodes have no
+    /// orientation and are appended after the magnetometers.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn apply_modality(&mut self, modality: SensorArrayModality, sensors_per_position: usize) {
+        trace!("Applying sensor modality");
+        if modality == SensorArrayModality::Magnetic {
+            return;
+        }
+        let electrode_positions_mm = self
+            .positions_mm
+            .slice(s![..;sensors_per_position, ..])
+            .to_owned();
+        let number_of_electrodes = electrode_positions_mm.shape()[0];
+        if modality == SensorArrayModality::Electric {
+            self.positions_mm = electrode_positions_mm;
+            self.orientations_xyz = Array2::zeros((number_of_electrodes, 3));
+            self.modalities = vec![SensorModality::Electric; number_of_electrodes];
+        } else {
+            self.positions_mm = concatenate(
+                Axis(0),
+                &[self.positions_mm.view(), electrode_positions_mm.view()],
+            )
+            .expect("Sensor positions to have three coordinates.");
+            self.orientations_xyz = concatenate(
+                Axis(0),
+                &[
+                    self.orientations_xyz.view(),
+                    Array2::zeros((number_of_electrodes, 3)).view(),
+                ],
+            )
+            .expect("Sensor orientations to have three coordinates.");
+            self.modalities.extend(std::iter::repeat_n(
+                SensorModality::Electric,
+                number_of_electrodes,
+            ));
+        }
+    }
+
+    /// Returns the axis label for measurements of the sensor with the given
+    /// index, or of all sensors if no index is given.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn measurement_label(&self, index: Option<usize>) -> String {
+        trace!("Creating measurement label");
+        if let Some(modality) = index.and_then(|index| self.modalities.get(index)) {
+            return format!("z [{}]", modality.unit());
+        }
+        let mut units = Vec::new();
+        for modality in &self.modalities {
+            if !units.contains(&modality.unit()) {
+                units.push(modality.unit());
+            }
+        }
+        if units.is_empty() {
+            units.push(SensorModality::Magnetic.unit());
+        }
+        format!("z [{}]", units.join(" / "))
     }
 
     /// Returns the number of sensors.
@@ -237,10 +395,25 @@ impl Sensors {
         self.positions_mm.shape()[0]
     }
 
+    /// Returns the number of beats, which is one for continuous motion.
     #[must_use]
     #[tracing::instrument(level = "trace")]
     pub fn count_beats(&self) -> usize {
         trace!("Retrieving number of beats");
+        if self.continuous_motion {
+            1
+        } else {
+            self.count_array_positions()
+        }
+    }
+
+    /// Returns the number of array positions at which the measurement matrix
+    /// is calculated, i.e. the beats or the keyframes of a continuous
+    /// trajectory.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn count_array_positions(&self) -> usize {
+        trace!("Retrieving number of array positions");
         self.array_offsets_mm.shape()[0]
     }
 
@@ -300,36 +473,69 @@ mod tests {
     }
 
     #[test]
-    fn count_from_simulation() {
+    fn count_from_simulation() -> Result<()> {
         let config = Common {
             sensors_per_axis: [10, 20, 30],
             sensor_array_geometry: SensorArrayGeometry::Cube,
-            three_d_sensors: false,
+            sensor_type: SensorType::SingleAxis,
             ..Default::default()
         };
-        let sensors = Sensors::from_model_config(&config);
+        let sensors = Sensors::from_model_config(&config)?;
 
         assert_eq!(6000, sensors.count());
+        Ok(())
     }
 
     #[test]
-    fn equality_sparse_full() {
+    fn equality_sparse_full() -> Result<()> {
         let config_full = Common {
             sensors_per_axis: [10, 10, 10],
             sensor_array_geometry: SensorArrayGeometry::Cube,
-            three_d_sensors: true,
+            sensor_type: SensorType::ThreeAxis,
             ..Default::default()
         };
         let config_sparse = Common {
             sensors_per_axis: [10, 10, 10],
             sensor_array_geometry: SensorArrayGeometry::SparseCube,
-            three_d_sensors: true,
+            sensor_type: SensorType::ThreeAxis,
             number_of_sensors: 1000,
             ..Default::default()
         };
-        let sensors = Sensors::from_model_config(&config_full);
-        let sensors_2 = Sensors::from_model_config(&config_sparse);
+        let sensors = Sensors::from_model_config(&config_full)?;
+        let sensors_2 = Sensors::from_model_config(&config_sparse)?;
 
         assert_eq!(sensors, sensors_2);
+        Ok(())
+    }
+
+    #[test]
+    fn modalities_from_simulation() -> Result<()> {
+        let config = Common {
+            sensors_per_axis: [2, 2, 2],
+            sensor_array_geometry: SensorArrayGeometry::Cube,
+            sensor_type: SensorType::ThreeAxis,
+            sensor_array_modality: SensorArrayModality::Mixed,
+            ..Default::default()
+        };
+        let mixed = Sensors::from_model_config(&config)?;
+        let electric = Sensors::from_model_config(&Common {
+            sensor_array_modality: SensorArrayModality::Electric,
+            ..config
+        })?;
+
+        assert_eq!(24 + 8, mixed.count());
+        assert_eq!(mixed.modalities.len(), mixed.count());
+        assert_eq!(8, electric.count());
+        assert!(electric
+            .modalities
+            .iter()
+            .all(|modality| *modality == SensorModality::Electric));
+        assert_eq!(
+            mixed.positions_mm.slice(s![24.., ..]),
+            electric.positions_mm
+        );
+        assert_eq!("z [pT / mV]", mixed.measurement_label(None));
+        assert_eq!("z [mV]", mixed.measurement_label(Some(24)));
+        Ok(())
     }
 }
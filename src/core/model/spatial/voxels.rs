@@ -5,15 +5,20 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use ndarray::{arr1, s, Array3, Array4, Dim};
+use ndarray::{arr1, s, Array3, Array4, Axis, Dim};
 use ndarray_npy::WriteNpyExt;
 use num_derive::FromPrimitive;
 use serde::{Deserialize, Serialize};
 use strum_macros::{EnumCount, EnumIter};
 use tracing::{debug, trace};
 
-use super::nifti::{determine_voxel_type, MriData};
-use crate::core::{config::model::Model, model::spatial::nifti::load_from_nii};
+use super::nifti::{
+    determine_fiber_direction, determine_voxel_type, load_fibers_from_nii, MriData,
+};
+use crate::core::{
+    config::model::{percentage_range_contains, FiberOrientation, Model},
+    model::spatial::nifti::load_from_nii,
+};
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Voxels {
@@ -21,6 +26,7 @@ pub struct Voxels {
     pub types: VoxelTypes,
     pub numbers: VoxelNumbers,
     pub positions_mm: VoxelPositions,
+    pub fibers: VoxelFibers,
 }
 
 impl Voxels {
@@ -34,6 +40,7 @@ impl Voxels {
             types: VoxelTypes::empty(voxels_in_dims),
             numbers: VoxelNumbers::empty(voxels_in_dims),
             positions_mm: VoxelPositions::empty(voxels_in_dims),
+            fibers: VoxelFibers::empty(voxels_in_dims),
         }
     }
 
@@ -44,11 +51,13 @@ impl Voxels {
         let types = VoxelTypes::from_handcrafted_model_config(config)?;
         let numbers = VoxelNumbers::from_voxel_types(&types);
         let positions = VoxelPositions::from_handcrafted_model_config(config, types.raw_dim());
+        let fibers = VoxelFibers::from_model_config(config, &positions)?;
         Ok(Self {
             size_mm: config.common.voxel_size_mm,
             types,
             numbers,
             positions_mm: positions,
+            fibers,
         })
     }
 
@@ -65,11 +74,13 @@ impl Voxels {
         let positions = VoxelPositions::from_mri_model_config(config, &mri_data);
         let types = VoxelTypes::from_mri_model_config(config, &positions, &mri_data)?;
         let numbers = VoxelNumbers::from_voxel_types(&types);
+        let fibers = VoxelFibers::from_model_config(config, &positions)?;
         Ok(Self {
             size_mm: config.common.voxel_size_mm,
             types,
             numbers,
             positions_mm: positions,
+            fibers,
         })
     }
 
@@ -149,6 +160,60 @@ impl Voxels {
         self.types[(x_usize, y_usize, z_usize)].is_connectable()
     }
 
+    /// Returns the index of the connectable voxel whose center is closest to
+    /// the given position, or `None` if there is no connectable voxel.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn closest_connectable_index(
+        &self,
+        position_mm: [f32; 3],
+    ) -> Option<(usize, usize, usize)> {
+        trace!("Finding closest connectable voxel");
+        self.types
+            .indexed_iter()
+            .filter(|(_, v_type)| v_type.is_connectable())
+            .map(|((x, y, z), _)| {
+                let distance_squared: f32 = self
+                    .positions_mm
+                    .slice(s![x, y, z, ..])
+                    .iter()
+                    .zip(position_mm)
+                    .map(|(voxel, position)| (voxel - position).powi(2))
+                    .sum();
+                ((x, y, z), distance_squared)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the number of the voxel whose cell contains the given
+    /// position, or `None` if the position lies outside of the grid or in a
+    /// voxel without a number.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn containing_voxel_number(&self, position_mm: [f32; 3]) -> Option<usize> {
+        trace!("Finding voxel containing position");
+        let counts = self.count_xyz();
+        if counts.contains(&0) {
+            return None;
+        }
+        let mut index = [0; 3];
+        for dimension in 0..3 {
+            let origin_mm = self.positions_mm[(0, 0, 0, dimension)] - self.size_mm / 2.0;
+            let cell = ((position_mm[dimension] - origin_mm) / self.size_mm).floor();
+            if cell < 0.0 || cell >= counts[dimension] as f32 {
+                return None;
+            }
+            index[dimension] = cell as usize;
+        }
+        self.numbers[(index[0], index[1], index[2])]
+    }
+
     /// Returns the index of the first voxel of type `v_type`.
     ///
     /// # Errors
@@ -195,6 +260,7 @@ impl Voxels {
         self.types.save_npy(path)?;
         self.numbers.save_npy(path)?;
         self.positions_mm.save_npy(path)?;
+        self.fibers.save_npy(path)?;
         Ok(())
     }
 }
@@ -261,28 +327,53 @@ impl VoxelTypes {
         let hps_x_stop_index =
             (voxels_in_dims[0] as f32 * handcrafted.hps_x_stop_percentage) as usize;
         let hps_y_up_index = (voxels_in_dims[1] as f32 * handcrafted.hps_y_up_percentage) as usize;
-        let pathology_x_start_index =
-            (voxels_in_dims[0] as f32 * handcrafted.pathology_x_start_percentage) as usize;
-        let pathology_x_stop_index =
-            (voxels_in_dims[0] as f32 * handcrafted.pathology_x_stop_percentage) as usize;
-        let pathology_y_start_index =
-            (voxels_in_dims[1] as f32 * handcrafted.pathology_y_start_percentage) as usize;
-        let pathology_y_stop_index =
-            (voxels_in_dims[1] as f32 * handcrafted.pathology_y_stop_percentage) as usize;
 
         let mut voxel_types = Self::empty(voxels_in_dims);
         voxel_types
             .indexed_iter_mut()
-            .for_each(|((x, y, _z), voxel_type)| {
-                if (x == sa_x_center_index) && (y == sa_y_center_index) {
+            .for_each(|((x, y, z), voxel_type)| {
+                let in_z =
+                    |start, stop| percentage_range_contains(z, voxels_in_dims[2], start, stop);
+                let hps_in_z = if handcrafted.layered_ventricle_wall {
+                    z == 0
+                } else {
+                    in_z(
+                        handcrafted.hps_z_start_percentage,
+                        handcrafted.hps_z_stop_percentage,
+                    )
+                };
+                if (x == sa_x_center_index)
+                    && (y == sa_y_center_index)
+                    && in_z(
+                        handcrafted.sa_z_start_percentage,
+                        handcrafted.sa_z_stop_percentage,
+                    )
+                {
                     *voxel_type = VoxelType::Sinoatrial;
                 } else if (config.common.pathological)
-                    && (x >= pathology_x_start_index && x <= pathology_x_stop_index)
-                    && (pathology_y_start_index <= y && y <= pathology_y_stop_index)
+                    && handcrafted
+                        .pathology_regions
+                        .iter()
+                        .any(|region| region.contains([x, y, z], voxels_in_dims))
                 {
                     *voxel_type = VoxelType::Pathological;
+                } else if (config.common.pathological)
+                    && handcrafted.border_zone_width_voxels > 0
+                    && handcrafted.pathology_regions.iter().any(|region| {
+                        region.contains_with_margin(
+                            [x, y, z],
+                            voxels_in_dims,
+                            handcrafted.border_zone_width_voxels,
+                        )
+                    })
+                {
+                    *voxel_type = VoxelType::BorderZone;
                 } else if x == av_x_center_index
                     && y == atrium_y_start_index
+                    && in_z(
+                        handcrafted.av_z_start_percentage,
+                        handcrafted.av_z_stop_percentage,
+                    )
                     && handcrafted.include_av
                 {
                     *voxel_type = VoxelType::Atrioventricular;
@@ -293,6 +384,7 @@ impl VoxelTypes {
                     || ((x == hps_x_start_index || x == hps_x_stop_index)
                         && y < hps_y_up_index
                         && y >= hps_y_stop_index))
+                    && hps_in_z
                     && handcrafted.include_hps
                 {
                     *voxel_type = VoxelType::HPS;
@@ -647,11 +739,139 @@ impl DerefMut for VoxelPositions {
     }
 }
 
+#[allow(clippy::unsafe_derive_deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct VoxelFibers(Array4<f32>);
+
+impl VoxelFibers {
+    /// Creates a new `VoxelFibers` instance with the given dimensions.
+    /// All fiber directions are zero, so propagation is isotropic.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn empty(voxels_in_dims: [usize; 3]) -> Self {
+        trace!("Creating empty voxel fibers");
+        Self(Array4::zeros((
+            voxels_in_dims[0],
+            voxels_in_dims[1],
+            voxels_in_dims[2],
+            3,
+        )))
+    }
+
+    /// Creates the fiber directions of the voxels at the given positions
+    /// from the fiber orientation of the `Model` config.
+    ///
+    /// Directions are normalized to unit length. Voxels without a fiber
+    /// direction keep a zero vector and propagate isotropically.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fiber volume cannot be loaded.
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip(positions))]
+    pub fn from_model_config(config: &Model, positions: &VoxelPositions) -> Result<Self> {
+        trace!("Creating voxel fibers from model config");
+        let shape = positions.shape();
+        let mut fibers = Self::empty([shape[0], shape[1], shape[2]]);
+        match &config.common.fiber_orientation {
+            None => {}
+            Some(FiberOrientation::Uniform { direction }) => {
+                let direction = arr1(direction);
+                for mut fiber in fibers.lanes_mut(Axis(3)) {
+                    fiber.assign(&direction);
+                }
+            }
+            Some(FiberOrientation::Helical {
+                endocardial_angle_deg,
+                epicardial_angle_deg,
+            }) => {
+                let layers = shape[2];
+                for z in 0..layers {
+                    let fraction = if layers > 1 {
+                        z as f32 / (layers - 1) as f32
+                    } else {
+                        0.0
+                    };
+                    let angle = (epicardial_angle_deg - endocardial_angle_deg)
+                        .mul_add(fraction, *endocardial_angle_deg)
+                        .to_radians();
+                    let direction = arr1(&[angle.cos(), angle.sin(), 0.0]);
+                    for mut fiber in fibers.slice_mut(s![.., .., z, ..]).lanes_mut(Axis(2)) {
+                        fiber.assign(&direction);
+                    }
+                }
+            }
+            Some(FiberOrientation::Nifti { path }) => {
+                let fiber_data = load_fibers_from_nii(path)?;
+                for x in 0..shape[0] {
+                    for y in 0..shape[1] {
+                        for z in 0..shape[2] {
+                            let fiber = determine_fiber_direction(
+                                config,
+                                positions.slice(s![x, y, z, ..]),
+                                &fiber_data,
+                            );
+                            fibers.slice_mut(s![x, y, z, ..]).assign(&fiber);
+                        }
+                    }
+                }
+            }
+        }
+        for mut fiber in fibers.lanes_mut(Axis(3)) {
+            let norm = fiber.mapv(|value| value.powi(2)).sum().sqrt();
+            if norm > 0.0 {
+                fiber /= norm;
+            } else {
+                fiber.fill(0.0);
+            }
+        }
+        Ok(fibers)
+    }
+
+    /// Saves the fiber directions to a .npy file at the given path.
+    #[tracing::instrument(level = "trace")]
+    fn save_npy(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        trace!("Saving voxel fibers to npy files");
+        let fibers_file_path = path.join("voxel_fibers.npy");
+        let writer = BufWriter::new(File::create(&fibers_file_path).with_context(|| {
+            format!(
+                "Failed to create voxel fibers file: {}",
+                fibers_file_path.display()
+            )
+        })?);
+        self.write_npy(writer).with_context(|| {
+            format!(
+                "Failed to write voxel fibers to: {}",
+                fibers_file_path.display()
+            )
+        })?;
+        Ok(())
+    }
+}
+
+impl Deref for VoxelFibers {
+    type Target = Array4<f32>;
+
+    #[tracing::instrument(level = "trace")]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for VoxelFibers {
+    #[tracing::instrument(level = "trace")]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
 #[derive(
     Default,
     Debug,
     PartialEq,
     Eq,
+    PartialOrd,
+    Ord,
     Hash,
     Deserialize,
     Serialize,
@@ -673,6 +893,7 @@ pub enum VoxelType {
     Vessel,
     Torso,
     Chamber,
+    BorderZone,
 }
 
 impl VoxelType {
@@ -683,6 +904,7 @@ impl VoxelType {
             3 => Self::Torso,
             5 => Self::Chamber,
             6 => Self::Sinoatrial,
+            7 => Self::BorderZone,
             _ => Self::None,
         }
     }
@@ -696,6 +918,7 @@ impl VoxelType {
                 | Self::HPS
                 | Self::Ventricle
                 | Self::Pathological
+                | Self::BorderZone
         )
     }
 }
@@ -711,6 +934,7 @@ pub fn is_connection_allowed(output_voxel_type: &VoxelType, input_voxel_type: &V
         VoxelType::Sinoatrial => [
             VoxelType::Atrium,
             VoxelType::Pathological,
+            VoxelType::BorderZone,
             VoxelType::Ventricle,
         ]
         .contains(input_voxel_type),
@@ -719,33 +943,42 @@ pub fn is_connection_allowed(output_voxel_type: &VoxelType, input_voxel_type: &V
             VoxelType::Atrium,
             VoxelType::Atrioventricular,
             VoxelType::Pathological,
+            VoxelType::BorderZone,
+        ]
+        .contains(input_voxel_type),
+        VoxelType::Atrioventricular => [
+            VoxelType::Atrium,
+            VoxelType::HPS,
+            VoxelType::Pathological,
+            VoxelType::BorderZone,
         ]
         .contains(input_voxel_type),
-        VoxelType::Atrioventricular => {
-            [VoxelType::Atrium, VoxelType::HPS, VoxelType::Pathological].contains(input_voxel_type)
-        }
         VoxelType::HPS => [
             VoxelType::HPS,
             VoxelType::Atrioventricular,
             VoxelType::Ventricle,
             VoxelType::Pathological,
+            VoxelType::BorderZone,
         ]
         .contains(input_voxel_type),
         VoxelType::Ventricle => [
             VoxelType::Ventricle,
             VoxelType::HPS,
             VoxelType::Pathological,
+            VoxelType::BorderZone,
         ]
         .contains(input_voxel_type),
-        VoxelType::Pathological => true,
+        VoxelType::Pathological | VoxelType::BorderZone => true,
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use approx::assert_relative_eq;
+
     use super::*;
-    use crate::core::config::model::{Common, Handcrafted};
+    use crate::core::config::model::{Common, FiberOrientation, Handcrafted, PathologyRegion};
 
     const _COMMON_PATH: &str = "tests/core/model/spatial/voxel/";
 
@@ -786,6 +1019,177 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn multiple_pathology_regions() -> Result<()> {
+        let config = Model {
+            handcrafted: Some(Handcrafted {
+                heart_size_mm: [10.0, 10.0, 10.0],
+                pathology_regions: vec![
+                    PathologyRegion {
+                        x_start_percentage: 0.0,
+                        x_stop_percentage: 0.1,
+                        y_start_percentage: 0.0,
+                        y_stop_percentage: 0.1,
+                        ..Default::default()
+                    },
+                    PathologyRegion {
+                        x_start_percentage: 0.5,
+                        x_stop_percentage: 0.6,
+                        y_start_percentage: 0.5,
+                        y_stop_percentage: 0.6,
+                        z_start_percentage: 0.0,
+                        z_stop_percentage: 0.3,
+                        current_factor: Some(0.5),
+                    },
+                ],
+                ..Default::default()
+            }),
+            common: Common {
+                voxel_size_mm: 1.0,
+                pathological: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let voxels = Voxels::from_handcrafted_model_config(&config)?;
+
+        let pathological = voxels
+            .types
+            .iter()
+            .filter(|voxel_type| **voxel_type == VoxelType::Pathological)
+            .count();
+        assert_eq!(2 * 2 * 10 + 2 * 2 * 4, pathological);
+        assert_eq!(voxels.types[(5, 6, 3)], VoxelType::Pathological);
+        assert_ne!(voxels.types[(5, 6, 4)], VoxelType::Pathological);
+        assert_relative_eq!(
+            config.current_factor_in_pathology([5, 6, 3], [10, 10, 10]),
+            0.5
+        );
+        assert_relative_eq!(
+            config.current_factor_in_pathology([0, 0, 9], [10, 10, 10]),
+            config.common.current_factor_in_pathology
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn border_zone_surrounds_pathology() -> Result<()> {
+        let config = Model {
+            handcrafted: Some(Handcrafted {
+                heart_size_mm: [10.0, 10.0, 10.0],
+                pathology_regions: vec![PathologyRegion {
+                    x_start_percentage: 0.5,
+                    x_stop_percentage: 0.6,
+                    y_start_percentage: 0.5,
+                    y_stop_percentage: 0.6,
+                    ..Default::default()
+                }],
+                border_zone_width_voxels: 1,
+                ..Default::default()
+            }),
+            common: Common {
+                voxel_size_mm: 1.0,
+                pathological: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let voxels = Voxels::from_handcrafted_model_config(&config)?;
+
+        let count = |v_type| {
+            voxels
+                .types
+                .iter()
+                .filter(|voxel_type| **voxel_type == v_type)
+                .count()
+        };
+        assert_eq!(2 * 2 * 10, count(VoxelType::Pathological));
+        assert_eq!((4 * 4 - 2 * 2) * 10, count(VoxelType::BorderZone));
+        assert_eq!(voxels.types[(4, 7, 0)], VoxelType::BorderZone);
+        assert_ne!(voxels.types[(3, 5, 0)], VoxelType::BorderZone);
+        assert_relative_eq!(
+            config.current_factor(VoxelType::BorderZone, [4, 7, 0], [10, 10, 10]),
+            config.common.current_factor_in_border_zone
+        );
+        assert_relative_eq!(
+            config.current_factor(VoxelType::Ventricle, [0, 0, 0], [10, 10, 10]),
+            1.0
+        );
+        assert!(is_connection_allowed(
+            &VoxelType::Ventricle,
+            &VoxelType::BorderZone
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn z_placement_and_layered_wall() -> Result<()> {
+        let config = Model {
+            handcrafted: Some(Handcrafted {
+                heart_size_mm: [10.0, 10.0, 10.0],
+                sa_z_start_percentage: 0.0,
+                sa_z_stop_percentage: 0.1,
+                layered_ventricle_wall: true,
+                ..Default::default()
+            }),
+            common: Common {
+                voxel_size_mm: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let voxels = Voxels::from_handcrafted_model_config(&config)?;
+
+        let sa_layers: Vec<usize> = voxels
+            .types
+            .indexed_iter()
+            .filter(|(_, voxel_type)| **voxel_type == VoxelType::Sinoatrial)
+            .map(|((_, _, z), _)| z)
+            .collect();
+        assert_eq!(sa_layers, vec![0, 1]);
+        let hps_layers: Vec<usize> = voxels
+            .types
+            .indexed_iter()
+            .filter(|(_, voxel_type)| **voxel_type == VoxelType::HPS)
+            .map(|((_, _, z), _)| z)
+            .collect();
+        assert!(!hps_layers.is_empty());
+        assert!(hps_layers.iter().all(|z| *z == 0));
+        Ok(())
+    }
+
+    #[test]
+    fn helical_fibers_rotate_through_wall() -> Result<()> {
+        let config = Model {
+            handcrafted: Some(Handcrafted {
+                heart_size_mm: [10.0, 10.0, 3.0],
+                ..Default::default()
+            }),
+            common: Common {
+                voxel_size_mm: 1.0,
+                fiber_orientation: Some(FiberOrientation::Helical {
+                    endocardial_angle_deg: 60.0,
+                    epicardial_angle_deg: -60.0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let voxels = Voxels::from_handcrafted_model_config(&config)?;
+
+        let angle_deg = |z: usize| {
+            let fiber = voxels.fibers.slice(s![0, 0, z, ..]);
+            assert_relative_eq!(fiber[2], 0.0);
+            fiber[1].atan2(fiber[0]).to_degrees()
+        };
+        assert_relative_eq!(angle_deg(0), 60.0, epsilon = 1e-4);
+        assert_relative_eq!(angle_deg(1), 0.0, epsilon = 1e-4);
+        assert_relative_eq!(angle_deg(2), -60.0, epsilon = 1e-4);
+        let isotropic = Voxels::from_handcrafted_model_config(&Model::default())?;
+        assert_relative_eq!(isotropic.fibers.mapv(f32::abs).sum(), 0.0);
+        Ok(())
+    }
+
     #[test]
     fn is_connection_allowed_true() {
         let output_voxel_type = VoxelType::HPS;
@@ -854,4 +1258,37 @@ mod tests {
         assert_eq!(num_pathological, 0);
         Ok(())
     }
+
+    #[test]
+    fn containing_voxel_number_matches_cells() -> Result<()> {
+        let config = Model {
+            handcrafted: Some(Handcrafted {
+                heart_size_mm: [10.0, 10.0, 10.0],
+                ..Default::default()
+            }),
+            common: Common {
+                voxel_size_mm: 2.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let voxels = Voxels::from_handcrafted_model_config(&config)?;
+
+        for ((x, y, z), number) in voxels.numbers.indexed_iter() {
+            // a point inside the cell, off its center
+            let position_mm = [
+                voxels.positions_mm[(x, y, z, 0)] + 0.9,
+                voxels.positions_mm[(x, y, z, 1)] - 0.9,
+                voxels.positions_mm[(x, y, z, 2)],
+            ];
+            assert_eq!(*number, voxels.containing_voxel_number(position_mm));
+        }
+        let outside_mm = [
+            voxels.positions_mm[(0, 0, 0, 0)] - 2.0,
+            voxels.positions_mm[(0, 0, 0, 1)],
+            voxels.positions_mm[(0, 0, 0, 2)],
+        ];
+        assert_eq!(None, voxels.containing_voxel_number(outside_mm));
+        Ok(())
+    }
 }
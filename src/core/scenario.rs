@@ -1,36 +1,101 @@
+pub mod aggregate;
+pub mod autosave;
+pub mod coarse_to_fine;
+pub mod compression;
+pub mod ensemble;
+pub mod export;
+pub mod footprint;
+pub mod provenance;
+pub mod query;
 pub mod results;
+pub mod robustness;
+pub mod search;
 pub mod summary;
+pub mod template;
 #[cfg(test)]
 mod tests;
+pub mod warm_start;
 
 use std::{
     fs::{self, File},
-    io::{BufReader, Write},
-    path::Path,
+    io,
+    path::{Component, Path, PathBuf},
     sync::mpsc::Sender,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use bincode;
 use chrono::{self, DateTime, Utc};
-use ndarray_stats::QuantileExt;
-use serde::{Deserialize, Serialize};
+use ndarray::s;
+use rand::{distr::Alphanumeric, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use toml;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, info, info_span, trace, warn};
 
-use self::{results::Results, summary::Summary};
+use self::{
+    autosave::AutosaveProgress,
+    compression::{
+        compress_file, compressed_path, disk_usage_bytes, existing_file, is_compressed,
+        open_reader, write_bytes, write_file, COMPRESSED_EXTENSION,
+    },
+    provenance::Provenance,
+    results::{Results, ResultsIndex, RESULTS_INDEX_FILE},
+    robustness::{PerturbationConfig, RobustnessReport},
+    summary::Summary,
+};
 use super::{
-    algorithm::{self, calculate_pseudo_inverse},
-    config::{algorithm::AlgorithmType, Config},
-    data::Data,
-    model::Model,
+    algorithm::{
+        self, calculate_pseudo_inverse, inverse::run_inverse_solver, kalman::run_kalman_filter,
+    },
+    config::{
+        algorithm::{AlgorithmType, Initialization},
+        model::SensorArrayMotion,
+        validation::ConfigIssue,
+        Config,
+    },
+    data::{beats, ecg::TwelveLeadEcg, filter, Data},
+    model::{spatial::nifti::export_results_to_nii, Model},
 };
-use crate::core::algorithm::{
-    gpu::{epoch::EpochKernel, GPU},
-    metrics,
-    refinement::derivation::calculate_average_delays,
+use crate::{
+    core::algorithm::{
+        diagnostics::calculate_measurement_conditioning,
+        gpu::backend::{create_backend, is_backend_available},
+        metrics::{
+            self, activation_time::calculate_activation_time_statistics,
+            localization::calculate_localization, predict_voxeltype,
+            velocity::calculate_velocity_statistics,
+        },
+        refinement::{
+            bounds::ParameterBounds, confidence::calculate_parameter_confidence,
+            derivation::calculate_average_delays, loss, update::UpdateMask,
+        },
+    },
+    profiling::{self, PerformanceReport, PERFORMANCE_REPORT_FILE},
+    settings::results_directory,
+    vis::plotting::{
+        png::{
+            activation_time::activation_time_plot,
+            line::{measurement_butterfly_plot, standard_time_plot},
+            states::states_spherical_plot,
+            voxel_type::voxel_type_plot,
+        },
+        PlotSlice, PlotTheme, StateSphericalPlotMode,
+    },
 };
 
+/// Files and directories of a scenario directory that are bundled into archives.
+const ARCHIVE_ENTRIES: [&str; 7] = [
+    "scenario.toml",
+    "data.bin",
+    "data.bin.zst",
+    "results.bin",
+    "results.bin.zst",
+    "results",
+    "img",
+];
+/// zstd compression level used for scenario archives.
+pub(crate) const ARCHIVE_COMPRESSION_LEVEL: i32 = 3;
+
 /// Struct representing a scenario configuration and results.
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct Scenario {
@@ -42,9 +107,15 @@ pub struct Scenario {
     #[serde(skip_serializing, skip_deserializing)]
     pub results: Option<Results>,
     pub summary: Option<Summary>,
+    // markdown notes
     #[serde(default)]
     pub comment: String,
     #[serde(default)]
+    tags: Vec<String>,
+    // build and machine the scenario was created with
+    #[serde(default)]
+    pub created_with: Option<Provenance>,
+    #[serde(default)]
     pub started: Option<DateTime<Utc>>,
     #[serde(default)]
     pub last_update: Option<DateTime<Utc>>,
@@ -52,6 +123,99 @@ pub struct Scenario {
     pub finished: Option<DateTime<Utc>>,
     #[serde(default)]
     pub duration_s: Option<i64>,
+    // results directory the scenario was loaded from
+    #[serde(skip_serializing, skip_deserializing, default = "default_root")]
+    root: PathBuf,
+    // scenarios in archive directories are never written to
+    #[serde(skip_serializing, skip_deserializing)]
+    read_only: bool,
+    // fraction of the data generation that is done while simulating
+    #[serde(skip_serializing, skip_deserializing)]
+    simulation_progress: f32,
+}
+
+#[tracing::instrument(level = "trace")]
+fn default_root() -> PathBuf {
+    results_directory().to_path_buf()
+}
+
+/// Number of random characters appended to generated scenario IDs.
+const ID_SUFFIX_LENGTH: usize = 4;
+/// Number of generated IDs that are tried before giving up.
+const ID_ATTEMPTS: usize = 16;
+
+/// Generates a scenario ID from the current date and time followed by a
+/// short random suffix, e.g. `2024-05-01-12-30-00-123456789-k3x9`.
+///
+/// The suffix keeps IDs apart that are generated within the same clock tick
+/// or after the clock was reset.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn generate_id() -> String {
+    let suffix: String = rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(ID_SUFFIX_LENGTH)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    format!(
+        "{}-{suffix}",
+        chrono::Utc::now().format("%Y-%m-%d-%H-%M-%S-%f")
+    )
+}
+
+/// Creates the directory of a new scenario in the root and returns its ID.
+///
+/// A generated ID that is already taken is replaced by a new one. Creating
+/// the directory fails if it exists, so two scenarios created at the same
+/// time cannot claim the same directory.
+///
+/// # Errors
+///
+/// Returns an error if the given ID is already taken, no free ID could be
+/// generated or the directory could not be created.
+#[tracing::instrument(level = "debug")]
+fn reserve_directory(root: &Path, id: Option<String>) -> Result<String> {
+    fs::create_dir_all(root)
+        .with_context(|| format!("Failed to create directory: {}", root.display()))?;
+    let generated = id.is_none();
+    let mut id = id.unwrap_or_else(generate_id);
+    for _ in 0..ID_ATTEMPTS {
+        match fs::create_dir(root.join(&id)) {
+            Ok(()) => return Ok(id),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if !generated {
+                    bail!("Scenario with id {id} already exists in {}", root.display());
+                }
+                warn!("Generated scenario id {id} is already taken, generating a new one");
+                id = generate_id();
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to create scenario directory: {}",
+                        root.join(&id).display()
+                    )
+                });
+            }
+        }
+    }
+    bail!(
+        "Failed to generate a free scenario id in {} after {ID_ATTEMPTS} attempts",
+        root.display()
+    )
+}
+
+/// Deserializes a top-level field of a damaged scenario.toml, discarding it
+/// if it cannot be parsed.
+#[tracing::instrument(level = "trace", skip(table))]
+fn salvage_field<T: DeserializeOwned>(table: &toml::Table, key: &str) -> Option<T> {
+    match table.get(key)?.clone().try_into() {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Discarding unreadable field {}: {}", key, e);
+            None
+        }
+    }
 }
 
 impl Scenario {
@@ -74,40 +238,67 @@ impl Scenario {
             results: None,
             summary: None,
             comment: "EMPTY".into(),
+            tags: Vec::new(),
+            created_with: None,
             started: None,
             last_update: None,
             finished: None,
             duration_s: None,
+            root: default_root(),
+            read_only: false,
+            simulation_progress: 0.0,
         }
     }
 
     /// Creates a new Scenario with a generated ID and default values.
     ///
-    /// The ID is generated from the current date and time. The status is set to
+    /// The ID is generated from the current date and time followed by a short
+    /// random suffix, see [`generate_id`]. The status is set to
     /// Planning, the config to default, data and results to None, summary to
-    /// None, and comment to empty string.
+    /// None, and comment to empty string. The provenance of the running build
+    /// is recorded in `created_with`.
+    ///
+    /// The directory of the scenario is created before anything is written,
+    /// so a scenario never overwrites the directory of another one.
     ///
     /// # Errors
     ///
-    /// Returns an error if the new scenario could not be saved to the filesystem.
+    /// Returns an error if a scenario with the given ID already exists or the
+    /// new scenario could not be saved to the filesystem.
     #[tracing::instrument(level = "debug")]
     pub fn build(id: Option<String>) -> Result<Self> {
-        debug!("Building new scenario");
+        Self::build_in(&default_root(), id)
+    }
+
+    /// Creates a new Scenario like [`Self::build`], but in the given root
+    /// instead of the results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a scenario with the given ID already exists or the
+    /// new scenario could not be saved to the filesystem.
+    #[tracing::instrument(level = "debug")]
+    pub fn build_in(root: &Path, id: Option<String>) -> Result<Self> {
+        debug!("Building new scenario in {}", root.display());
+        let root = root.to_path_buf();
+        let id = reserve_directory(&root, id)?;
         let scenario = Self {
-            id: id.map_or_else(
-                || format!("{}", chrono::Utc::now().format("%Y-%m-%d-%H-%M-%S-%f")),
-                |id| id,
-            ),
+            id,
             status: Status::Planning,
             config: Config::default(),
             data: None,
             results: None,
             summary: None,
             comment: String::new(),
+            tags: Vec::new(),
+            created_with: Some(Provenance::current()),
             started: None,
             last_update: None,
             finished: None,
             duration_s: None,
+            root,
+            read_only: false,
+            simulation_progress: 0.0,
         };
         scenario
             .save()
@@ -118,7 +309,8 @@ impl Scenario {
     /// Loads a Scenario from the scenario.toml file in the given path.
     ///
     /// Reads the contents of the scenario.toml file and parses it into a
-    /// Scenario struct.
+    /// Scenario struct. The parent of the path becomes the root of the
+    /// scenario.
     ///
     /// # Errors
     ///
@@ -134,19 +326,118 @@ impl Scenario {
             )
         })?;
 
-        let scenario: Self = toml::from_str(&contents).with_context(|| {
+        let mut scenario: Self = toml::from_str(&contents).with_context(|| {
             format!(
                 "Failed to parse scenario.toml in directory: {}",
                 path.display()
             )
         })?;
+        if let Some(root) = path.parent() {
+            scenario.root = root.to_path_buf();
+        }
+        if path
+            .file_name()
+            .is_some_and(|name| name != scenario.id.as_str())
+        {
+            bail!(
+                "Scenario ID {} does not match its directory {}, saving it would write to another directory",
+                scenario.id,
+                path.display()
+            );
+        }
+        scenario
+            .config
+            .validate()
+            .with_context(|| format!("Invalid config in {}", scenario_path.display()))?;
 
         Ok(scenario)
     }
 
-    /// Saves the Scenario to a scenario.toml file in the ./results directory.
+    /// Makes a best-effort attempt to repair a scenario that cannot be
+    /// loaded and saves the repaired scenario.
+    ///
+    /// Every top-level field of scenario.toml that still parses is kept, all
+    /// other fields and an invalid config fall back to their defaults and
+    /// the ID is taken from the directory name. If the summary is missing but
+    /// results were stored, the summary is regenerated from the stored
+    /// metrics and the scenario is marked as done unless its status could be
+    /// recovered.
     ///
-    /// Creates the directory path from the scenario ID. Converts the Scenario to a TOML string. Creates the file and writes the TOML string to it.
+    /// # Errors
+    ///
+    /// Returns an error if the path has no directory name or the repaired
+    /// scenario could not be saved.
+    #[tracing::instrument(level = "info", skip_all)]
+    pub fn repair(path: &Path) -> Result<Self> {
+        info!("Repairing scenario in {}", path.display());
+        let id = path
+            .file_name()
+            .with_context(|| format!("Scenario path has no directory name: {}", path.display()))?
+            .to_string_lossy()
+            .into_owned();
+        let table = fs::read_to_string(path.join("scenario.toml"))
+            .context("Failed to read scenario.toml")
+            .and_then(|contents| {
+                toml::from_str::<toml::Table>(&contents).context("Failed to parse scenario.toml")
+            })
+            .unwrap_or_else(|e| {
+                warn!("Rebuilding scenario {} from defaults: {:#}", id, e);
+                toml::Table::new()
+            });
+
+        let status: Option<Status> = salvage_field(&table, "status");
+        let mut scenario = Self {
+            id,
+            status: status.clone().unwrap_or(Status::Planning),
+            config: salvage_field::<Config>(&table, "config")
+                .filter(|config| {
+                    config
+                        .validate()
+                        .map_err(|e| warn!("Discarding config of {}: {:#}", id, e))
+                        .is_ok()
+                })
+                .unwrap_or_default(),
+            data: None,
+            results: None,
+            summary: salvage_field(&table, "summary"),
+            comment: salvage_field(&table, "comment").unwrap_or_default(),
+            tags: salvage_field(&table, "tags").unwrap_or_default(),
+            created_with: salvage_field(&table, "created_with"),
+            started: salvage_field(&table, "started"),
+            last_update: salvage_field(&table, "last_update"),
+            finished: salvage_field(&table, "finished"),
+            duration_s: salvage_field(&table, "duration_s"),
+            root: path.parent().map_or_else(default_root, Path::to_path_buf),
+            read_only: false,
+            simulation_progress: 0.0,
+        };
+        if scenario.summary.is_none() {
+            match scenario.load_results() {
+                Ok(()) => {
+                    if let Some(results) = scenario.results.take() {
+                        info!("Regenerating summary of scenario {}", scenario.id);
+                        scenario.summary = Some(Summary::from_metrics(&results.metrics));
+                        if status.is_none() {
+                            scenario.status = Status::Done;
+                        }
+                    }
+                }
+                Err(e) => warn!(
+                    "Failed to load results of scenario {}: {:#}",
+                    scenario.id, e
+                ),
+            }
+        }
+        scenario
+            .save()
+            .context("Failed to save repaired scenario")?;
+        Ok(scenario)
+    }
+
+    /// Saves the Scenario to a scenario.toml file in its root directory.
+    ///
+    /// Creates the directory path from the scenario ID. Converts the Scenario to a TOML string and writes it to the file,
+    /// replacing it atomically so a crash while saving leaves the previous file intact.
     /// If the scenario has data, calls `save_data()`. If the scenario has results, calls `save_results()`.
     ///
     /// # Panics
@@ -159,11 +450,10 @@ impl Scenario {
     #[tracing::instrument(level = "info", skip(self))]
     pub fn save(&self) -> Result<()> {
         info!("Saving scenario with id {}", self.id);
-        let path = Path::new("./results").join(&self.id);
+        let path = self.writable_directory()?;
         let toml = toml::to_string(&self).context("Failed to serialize scenario to TOML format")?;
         fs::create_dir_all(&path)?;
-        let mut f = File::create(path.join("scenario.toml"))?;
-        f.write_all(toml.as_bytes())?;
+        write_bytes(&path.join("scenario.toml"), toml.as_bytes())?;
         if self.data.is_some() {
             self.save_data()?;
         }
@@ -179,6 +469,75 @@ impl Scenario {
         &self.id
     }
 
+    /// Returns the results directory the scenario was loaded from.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the directory the files of the scenario are stored in.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn get_directory(&self) -> PathBuf {
+        self.root.join(&self.id)
+    }
+
+    /// Returns true if the scenario belongs to a read-only archive directory.
+    #[must_use]
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the tags of the scenario in the order they were added.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Adds a tag to the scenario, e.g. the name of the experiment it belongs
+    /// to. Surrounding whitespace is removed.
+    ///
+    /// Returns false if the tag is empty or the scenario already has it.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        let tag = tag.trim();
+        if tag.is_empty() || self.tags.iter().any(|existing| existing == tag) {
+            return false;
+        }
+        debug!("Adding tag {} to scenario {}", tag, self.id);
+        self.tags.push(tag.to_string());
+        true
+    }
+
+    /// Removes a tag from the scenario. Returns false if the scenario did not
+    /// have the tag.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let length = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != length
+    }
+
+    /// Marks the scenario as read-only, so that it is never written to.
+    pub const fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Returns the directory of the scenario if it may be written to.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn writable_directory(&self) -> Result<PathBuf> {
+        if self.read_only {
+            bail!(
+                "Scenario with id {} in {} is read-only",
+                self.id,
+                self.root.display()
+            );
+        }
+        Ok(self.get_directory())
+    }
+
     /// Returns a string representation of the scenario's status.
     /// Matches the Status enum variant names.
     #[must_use]
@@ -206,6 +565,7 @@ impl Scenario {
             Status::Running(_) => "Running".to_string(),
             Status::Aborted => "Aborted".to_string(),
             Status::Scheduled => "Scheduled".to_string(),
+            Status::Interrupted => "Interrupted".to_string(),
         }
     }
 
@@ -221,8 +581,11 @@ impl Scenario {
         debug!("Scheduling scenario");
         match self.status {
             Status::Planning => {
-                self.status = Status::Scheduled;
                 self.unify_configs();
+                self.config
+                    .validate()
+                    .context("Can not schedule scenario with an invalid config")?;
+                self.status = Status::Scheduled;
                 Ok(())
             }
             _ => Err(anyhow::anyhow!(
@@ -238,30 +601,17 @@ impl Scenario {
     /// Also sets algorithm epochs to 1 if it is `PseudoInverse`.
     #[tracing::instrument(level = "debug")]
     fn unify_configs(&mut self) {
-        debug!("Unifying algorithm and simulation configs");
-        let model = &mut self.config.algorithm.model;
-        let simulation = &self.config.simulation;
-        model.common.sensor_array_geometry = simulation.model.common.sensor_array_geometry.clone();
-        model.common.three_d_sensors = simulation.model.common.three_d_sensors;
-        model.common.number_of_sensors = simulation.model.common.number_of_sensors;
-        model.common.sensor_array_radius_mm = simulation.model.common.sensor_array_radius_mm;
-        model.common.sensors_per_axis = simulation.model.common.sensors_per_axis;
-        model.common.sensor_array_size_mm = simulation.model.common.sensor_array_size_mm;
-        model.common.sensor_array_origin_mm = simulation.model.common.sensor_array_origin_mm;
-        model.common.voxel_size_mm = simulation.model.common.voxel_size_mm;
-        model.common.heart_offset_mm = simulation.model.common.heart_offset_mm;
-        model.common.sensor_array_motion = simulation.model.common.sensor_array_motion.clone();
-        model.common.sensor_array_motion_range_mm =
-            simulation.model.common.sensor_array_motion_range_mm;
-        model.common.sensor_array_motion_steps = simulation.model.common.sensor_array_motion_steps;
-        if let Some(handcrafted) = simulation.model.handcrafted.as_ref() {
-            if let Some(model_handcrafted) = model.handcrafted.as_mut() {
-                model_handcrafted.heart_size_mm = handcrafted.heart_size_mm;
-            }
-        }
-        if self.config.algorithm.algorithm_type == AlgorithmType::PseudoInverse {
-            self.config.algorithm.epochs = 1;
-        }
+        unify_config(&mut self.config);
+    }
+
+    /// Returns the problems of the config as it would be scheduled, with the
+    /// model settings of the simulation copied to the algorithm.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn config_issues(&self) -> Vec<ConfigIssue> {
+        let mut config = self.config.clone();
+        unify_config(&mut config);
+        config.issues()
     }
 
     /// Set't the status of the scenario to "Planning".
@@ -289,11 +639,17 @@ impl Scenario {
         }
     }
 
-    /// Sets the scenario status to Running with the given epoch number.
+    /// Sets the scenario status to Simulating with no progress.
     #[tracing::instrument(level = "debug")]
     pub fn set_simulating(&mut self) {
         debug!("Setting scenario status to simulating");
         self.status = Status::Simulating;
+        self.simulation_progress = 0.0;
+    }
+
+    /// Sets the fraction of the data generation that is done.
+    pub const fn set_simulation_progress(&mut self, progress: f32) {
+        self.simulation_progress = progress;
     }
 
     /// Sets the scenario status to Running with the given epoch number.
@@ -321,39 +677,341 @@ impl Scenario {
         }
     }
 
-    /// Deletes the results directory for this scenario.
+    /// Marks the scenario as interrupted if it claims to be simulating or
+    /// running.
+    ///
+    /// Must only be called when no worker thread exists for this scenario,
+    /// e.g. directly after loading it from disk. A scenario in such a state
+    /// was left behind by a crashed or killed application.
+    ///
+    /// Returns true if the status was changed.
+    #[tracing::instrument(level = "debug")]
+    pub fn mark_interrupted_if_stale(&mut self) -> bool {
+        debug!("Checking scenario for stale running status");
+        match self.status {
+            Status::Simulating | Status::Running(_) => {
+                warn!(
+                    "Scenario {} was left in status {} without a worker thread, marking it as interrupted",
+                    self.id,
+                    self.get_status_str()
+                );
+                self.status = Status::Interrupted;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns true if an autosave of an unfinished run exists in the
+    /// directory of the scenario.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn has_autosave(&self) -> bool {
+        autosave::find(&self.get_directory()).is_some()
+    }
+
+    /// Restores the summary and the time of the last update of an
+    /// interrupted scenario from its autosave, which may be more recent than
+    /// the saved scenario.
+    ///
+    /// Returns true if the progress was restored.
+    #[tracing::instrument(level = "debug")]
+    pub fn restore_autosave_progress(&mut self) -> bool {
+        debug!("Restoring autosave progress");
+        if self.status != Status::Interrupted || !self.has_autosave() {
+            return false;
+        }
+        match autosave::load_progress(&self.get_directory()) {
+            Ok(progress) => {
+                info!(
+                    "Restored progress of scenario {} up to epoch {}",
+                    self.id, progress.epoch
+                );
+                self.summary = Some(progress.summary);
+                if progress.last_update > self.last_update {
+                    self.last_update = progress.last_update;
+                }
+                true
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to restore autosave of scenario {}: {:#}",
+                    self.id, e
+                );
+                false
+            }
+        }
+    }
+
+    /// Recovers an interrupted scenario using the given action.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the scenario is not in the
+    /// interrupted phase, should be resumed without an autosave or its
+    /// autosave could not be removed when rescheduling.
+    #[tracing::instrument(level = "debug")]
+    pub fn recover(&mut self, action: RecoveryAction) -> Result<()> {
+        debug!("Recovering interrupted scenario with {:?}", action);
+        if self.status != Status::Interrupted {
+            return Err(anyhow::anyhow!(
+                "Can only recover scenarios that are interrupted but scenario was in phase {:?}",
+                self.get_status_str()
+            ));
+        }
+        match action {
+            RecoveryAction::Reschedule => {
+                autosave::remove(&self.writable_directory()?)?;
+                self.status = Status::Scheduled;
+                self.summary = None;
+                self.started = None;
+                self.last_update = None;
+                self.finished = None;
+                self.duration_s = None;
+            }
+            RecoveryAction::Resume => {
+                if !self.has_autosave() {
+                    bail!("Scenario {} has no autosave to resume from", self.id);
+                }
+                self.status = Status::Scheduled;
+            }
+            RecoveryAction::Abort => {
+                self.status = Status::Aborted;
+            }
+        }
+        Ok(())
+    }
+
+    /// Deletes the directory of this scenario.
     ///
     /// # Errors
     ///
-    /// This function will return an error if the results directory could not be deleted.
+    /// This function will return an error if the scenario is read-only or its
+    /// directory could not be deleted.
     #[tracing::instrument(level = "info", skip_all)]
     pub fn delete(&self) -> Result<(), std::io::Error> {
         info!("Deleting scenario with id {}", self.id);
-        let path = Path::new("./results").join(&self.id);
-        fs::remove_dir_all(path)?;
+        if self.read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("Scenario with id {} is read-only", self.id),
+            ));
+        }
+        fs::remove_dir_all(self.get_directory())?;
         Ok(())
     }
 
+    /// Returns the disk space occupied by the directory of this scenario in
+    /// bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn disk_usage_bytes(&self) -> u64 {
+        disk_usage_bytes(&self.get_directory())
+    }
+
+    /// Moves the directory of this scenario into the given archive directory
+    /// and returns the archived scenario, which is read-only.
+    ///
+    /// The data and the results are zstd compressed on the way, all other
+    /// files are copied as they are. The original directory is only removed
+    /// once the copy in the archive directory is complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scenario is read-only, a scenario with the
+    /// same id already exists in the archive directory or a file could not
+    /// be copied.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn move_to_archive(&self, archive_root: &Path) -> Result<Self> {
+        info!(
+            "Moving scenario with id {} to {}",
+            self.id,
+            archive_root.display()
+        );
+        let source = self.writable_directory()?;
+        let target = archive_root.join(&self.id);
+        if target.exists() {
+            bail!(
+                "Scenario with id {} already exists in {}",
+                self.id,
+                archive_root.display()
+            );
+        }
+        if let Err(e) = copy_compressed(&source, &target) {
+            if let Err(cleanup) = fs::remove_dir_all(&target) {
+                warn!("Failed to remove incomplete archive copy: {}", cleanup);
+            }
+            return Err(e);
+        }
+        let results_path = target.join("results");
+        if results_path.join(RESULTS_INDEX_FILE).is_file() {
+            let mut index = ResultsIndex::load(&results_path)?;
+            for part in &mut index.parts {
+                if !is_compressed(Path::new(&part.file)) {
+                    part.file = format!("{}.{COMPRESSED_EXTENSION}", part.file);
+                }
+            }
+            index.save(&results_path)?;
+        }
+        fs::remove_dir_all(&source)
+            .with_context(|| format!("Failed to remove directory: {}", source.display()))?;
+        let mut archived = Self::load(&target)?;
+        archived.set_read_only(true);
+        Ok(archived)
+    }
+
+    /// Returns the default location of the archive for this scenario,
+    /// ./archives/<id>.tar.zst.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn default_archive_path(&self) -> PathBuf {
+        trace!(
+            "Getting default archive path for scenario with id {}",
+            self.id
+        );
+        Path::new("./archives").join(format!("{}.tar.zst", self.id))
+    }
+
+    /// Exports the scenario into a single zstd compressed tar archive.
+    ///
+    /// Bundles scenario.toml, data.bin, the results and the generated images
+    /// below a top level directory named after the scenario id, so the archive
+    /// can be shared and restored with `import_archive`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scenario was never saved or the archive could not be written.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn export_archive(&self, path: &Path) -> Result<()> {
+        info!(
+            "Exporting scenario with id {} to {}",
+            self.id,
+            path.display()
+        );
+        let scenario_path = self.get_directory();
+        if !scenario_path.join("scenario.toml").is_file() {
+            bail!(
+                "Scenario with id {} has not been saved to {}",
+                self.id,
+                scenario_path.display()
+            );
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create archive directory: {}", parent.display())
+            })?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create archive file: {}", path.display()))?;
+        let encoder = zstd::Encoder::new(file, ARCHIVE_COMPRESSION_LEVEL)
+            .context("Failed to create zstd encoder")?;
+        let mut builder = tar::Builder::new(encoder);
+        for name in ARCHIVE_ENTRIES {
+            let entry_path = scenario_path.join(name);
+            let archive_path = Path::new(&self.id).join(name);
+            if entry_path.is_file() {
+                builder
+                    .append_path_with_name(&entry_path, &archive_path)
+                    .with_context(|| format!("Failed to archive {}", entry_path.display()))?;
+            } else if entry_path.is_dir() {
+                builder
+                    .append_dir_all(&archive_path, &entry_path)
+                    .with_context(|| format!("Failed to archive {}", entry_path.display()))?;
+            }
+        }
+        builder
+            .into_inner()
+            .context("Failed to finish tar archive")?
+            .finish()
+            .context("Failed to finish zstd stream")?;
+        Ok(())
+    }
+
+    /// Imports a scenario from an archive written by `export_archive`.
+    ///
+    /// The archive is unpacked into the results directory. Existing
+    /// scenarios are never overwritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is malformed, a scenario with the same
+    /// id already exists or the unpacked scenario could not be loaded.
+    #[tracing::instrument(level = "info")]
+    pub fn import_archive(path: &Path) -> Result<Self> {
+        Self::import_archive_into(path, results_directory())
+    }
+
+    /// Imports a scenario from an archive like [`Self::import_archive`], but
+    /// unpacks it into the given root instead of the results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is malformed, a scenario with the same
+    /// id already exists or the unpacked scenario could not be loaded.
+    #[tracing::instrument(level = "info")]
+    pub fn import_archive_into(path: &Path, root: &Path) -> Result<Self> {
+        info!("Importing scenario from {}", path.display());
+        let id = archive_scenario_id(path)?;
+        let scenario_path = root.join(&id);
+        if scenario_path.exists() {
+            bail!("Scenario with id {id} already exists");
+        }
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open archive file: {}", path.display()))?;
+        let decoder = zstd::Decoder::new(file).context("Failed to create zstd decoder")?;
+        tar::Archive::new(decoder)
+            .unpack(root)
+            .with_context(|| format!("Failed to unpack archive: {}", path.display()))?;
+        let scenario = Self::load(&scenario_path)?;
+        if scenario.id != id {
+            bail!(
+                "Archived scenario id {} does not match its directory {id}",
+                scenario.id
+            );
+        }
+        Ok(scenario)
+    }
+
     /// Returns an immutable reference to the scenario status.
     #[must_use]
     pub const fn get_status(&self) -> &Status {
         &self.status
     }
 
-    /// Returns the progress of the scenario as a percentage. The progress will be
-    /// 0.0 if the scenario status is not Running. Otherwise it will return the
-    /// current epoch divided by the total number of epochs.
+    /// Returns the progress of the scenario as a percentage. While simulating
+    /// it is the fraction of the data generation that is done, while running
+    /// the current epoch divided by the total number of epochs and 0.0
+    /// otherwise.
     #[must_use]
     #[tracing::instrument(level = "trace")]
     pub fn get_progress(&self) -> f32 {
         trace!("Getting progress for scenario with id {}", self.id);
         #[allow(clippy::cast_precision_loss)]
         match self.status {
+            Status::Simulating => self.simulation_progress,
             Status::Running(epoch) => epoch as f32 / self.config.algorithm.epochs as f32,
             _ => 0.0,
         }
     }
 
+    /// Returns the average number of epochs per second since the scenario
+    /// started. Returns `None` if the scenario is not running or no epoch
+    /// has finished yet.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn get_epochs_per_second(&self) -> Option<f32> {
+        trace!("Getting epochs per second for scenario with id {}", self.id);
+        let Status::Running(epoch) = self.status else {
+            return None;
+        };
+        let elapsed_ms = (self.last_update? - self.started?).num_milliseconds();
+        if epoch == 0 || elapsed_ms <= 0 {
+            return None;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        Some(epoch as f32 * 1000.0 / elapsed_ms as f32)
+    }
+
     #[allow(clippy::cast_possible_truncation)]
     #[must_use]
     #[tracing::instrument(level = "trace")]
@@ -361,6 +1019,7 @@ impl Scenario {
         trace!("Getting progress for scenario with id {}", self.id);
         #[allow(clippy::cast_precision_loss)]
         match self.status {
+            Status::Simulating => "Simulating".to_string(),
             Status::Running(0) => "ETC: ???".to_string(),
             Status::Running(_) => {
                 let now = Utc::now();
@@ -394,7 +1053,8 @@ impl Scenario {
         }
     }
 
-    /// Saves the scenario data to a file in the results directory.
+    /// Saves the scenario data to a file in the results directory,
+    /// compressed with the compression level of the storage settings.
     ///
     /// # Errors
     ///
@@ -402,15 +1062,20 @@ impl Scenario {
     #[tracing::instrument(level = "debug")]
     fn save_data(&self) -> Result<()> {
         debug!("Saving scenario data for scenario with id {}", self.id);
-        let path = Path::new("./results").join(&self.id);
+        let path = self.writable_directory()?;
         fs::create_dir_all(&path)?;
-        let mut f = File::create(path.join("data.bin"))?;
         let data = self
             .data
             .as_ref()
             .context("Data not available for saving")?;
-        bincode::serde::encode_into_std_write(data, &mut f, bincode::config::standard())
-            .context("Failed to serialize data to binary format")?;
+        write_file(
+            &path.join("data.bin"),
+            self.config.storage.compression_level,
+            |writer| {
+                bincode::serde::encode_into_std_write(data, writer, bincode::config::standard())
+                    .context("Failed to serialize data to binary format")
+            },
+        )?;
         Ok(())
     }
 
@@ -422,15 +1087,19 @@ impl Scenario {
     #[tracing::instrument(level = "debug")]
     fn save_results(&self) -> Result<()> {
         debug!("Saving scenario results for scenario with id {}", self.id);
-        let path = Path::new("./results").join(&self.id);
-        fs::create_dir_all(&path)?;
-        let mut f = File::create(path.join("results.bin"))?;
-        let results = self
-            .results
+        let path = self.writable_directory()?;
+        self.results
             .as_ref()
-            .context("Results not available for saving")?;
-        bincode::serde::encode_into_std_write(results, &mut f, bincode::config::standard())
-            .context("Failed to serialize results to binary format")?;
+            .context("Results not available for saving")?
+            .save(&path.join("results"), &self.config.storage)
+            .context("Failed to save results")?;
+        // remove results written by older versions as a single file
+        let legacy_path = path.join("results.bin");
+        if legacy_path.is_file() {
+            fs::remove_file(&legacy_path).with_context(|| {
+                format!("Failed to remove legacy results: {}", legacy_path.display())
+            })?;
+        }
         Ok(())
     }
 
@@ -445,42 +1114,39 @@ impl Scenario {
         if self.data.is_some() {
             return Ok(());
         }
-        let file_path = Path::new("./results").join(&self.id).join("data.bin");
-        if file_path.is_file() {
-            let file = File::open(&file_path)
-                .with_context(|| format!("Failed to open data file: {}", file_path.display()))?;
+        if let Some(file_path) = existing_file(&self.get_directory().join("data.bin")) {
+            let mut reader = open_reader(&file_path).context("Failed to open data file")?;
             self.data = Some(
-                bincode::serde::decode_from_std_read(
-                    &mut BufReader::new(file),
-                    bincode::config::standard(),
-                )
-                .context("Failed to deserialize data from binary format")?,
+                bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+                    .context("Failed to deserialize data from binary format")?,
             );
         }
         Ok(())
     }
 
-    /// Loads the scenario results from the results.bin file in the results directory if it exists.
+    /// Loads the scenario results from the results directory if they exist.
+    ///
+    /// Falls back to the single results.bin file written by older versions.
     ///
     /// # Errors
     ///
-    /// Returns an error if the results.bin file cannot be read or parsed.
+    /// Returns an error if the results cannot be read or parsed.
     #[tracing::instrument(level = "debug")]
     pub fn load_results(&mut self) -> Result<()> {
         debug!("Loading scenario results for scenario with id {}", self.id);
         if self.results.is_some() {
             return Ok(());
         }
-        let file_path = Path::new("./results").join(&self.id).join("results.bin");
-        if file_path.is_file() {
-            let file = File::open(&file_path)
-                .with_context(|| format!("Failed to open results file: {}", file_path.display()))?;
+        let path = self.get_directory();
+        if path.join("results").join(RESULTS_INDEX_FILE).is_file() {
+            self.results = Some(Results::load(&path.join("results"))?);
+            return Ok(());
+        }
+        if let Some(file_path) = existing_file(&path.join("results.bin")) {
+            let mut reader = open_reader(&file_path).context("Failed to open results file")?;
             self.results = Some(
-                bincode::serde::decode_from_std_read(
-                    &mut BufReader::new(file),
-                    bincode::config::standard(),
-                )
-                .context("Failed to deserialize results from binary format")?,
+                bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+                    .context("Failed to deserialize results from binary format")?,
             );
         }
         Ok(())
@@ -494,7 +1160,7 @@ impl Scenario {
     #[tracing::instrument(level = "debug")]
     pub fn save_npy(&self) -> Result<()> {
         debug!("Saving scenario data and results as npy");
-        let path = Path::new("./results").join(&self.id).join("npy");
+        let path = self.writable_directory()?.join("npy");
         self.data
             .as_ref()
             .context("Scenario data not available for NPY export")?
@@ -505,29 +1171,403 @@ impl Scenario {
             .save_npy(&path.join("results"))?;
         Ok(())
     }
+
+    /// Saves the estimated activation times, average delays and predicted
+    /// voxel types as NIFTI volumes aligned to the MRI scan of the model in
+    /// the results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if results or summary are missing, the model was not
+    /// built from an MRI scan or any save operation fails.
+    #[tracing::instrument(level = "debug")]
+    pub fn save_nii(&self) -> Result<()> {
+        debug!("Saving scenario results as nifti");
+        let path = self.writable_directory()?.join("nii");
+        let results = self
+            .results
+            .as_ref()
+            .context("Scenario results not available for NIFTI export")?;
+        let model = results
+            .model
+            .as_ref()
+            .context("Model not available for NIFTI export")?;
+        let summary = self
+            .summary
+            .as_ref()
+            .context("Scenario summary not available for NIFTI export")?;
+        export_results_to_nii(
+            &self.config.algorithm.model,
+            &model.spatial_description.voxels,
+            &results.estimations,
+            summary.threshold,
+            &path,
+        )
+    }
+
+    /// Saves virtual 12-lead ECG traces of the simulation and the algorithm
+    /// estimations as .npy, .csv and .png files in the results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if data or results are missing, or any save operation fails.
+    #[tracing::instrument(level = "debug")]
+    pub fn save_ecg(&self) -> Result<()> {
+        debug!("Saving virtual 12-lead ECG");
+        let path = self.writable_directory()?.join("ecg");
+        let electrodes = self.config.simulation.virtual_electrodes.as_ref();
+        let data = self
+            .data
+            .as_ref()
+            .context("Scenario data not available for ECG export")?;
+        let results = self
+            .results
+            .as_ref()
+            .context("Scenario results not available for ECG export")?;
+        let model = results
+            .model
+            .as_ref()
+            .context("Model not available in results for ECG export")?;
+        TwelveLeadEcg::from_system_states(
+            &data.simulation.system_states,
+            &data.simulation.model.spatial_description.voxels,
+            electrodes,
+        )?
+        .save(
+            &path.join("simulation"),
+            data.simulation.sample_rate_hz,
+            "Virtual ECG Simulation",
+        )?;
+        TwelveLeadEcg::from_system_states(
+            &results.estimations.system_states,
+            &model.spatial_description.voxels,
+            electrodes,
+        )?
+        .save(
+            &path.join("algorithm"),
+            data.simulation.sample_rate_hz,
+            "Virtual ECG Algorithm",
+        )?;
+        Ok(())
+    }
+
+    /// Saves plots of the simulated states, voxel types, activation times,
+    /// control function and measurements as .png files in the `img/simulation`
+    /// directory of the scenario.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is missing or any plot cannot be saved.
+    #[tracing::instrument(level = "debug")]
+    pub fn save_simulation_plots(&self) -> Result<()> {
+        debug!("Saving simulation plots");
+        let path = self.writable_directory()?.join("img").join("simulation");
+        fs::create_dir_all(&path).with_context(|| {
+            format!(
+                "Failed to create simulation plot directory: {}",
+                path.display()
+            )
+        })?;
+        let simulation = &self
+            .data
+            .as_ref()
+            .context("Scenario data not available for simulation plots")?
+            .simulation;
+        let voxels = &simulation.model.spatial_description.voxels;
+        states_spherical_plot(
+            &simulation.system_states_spherical,
+            &simulation.system_states_spherical_max,
+            &voxels.positions_mm,
+            voxels.size_mm,
+            &voxels.numbers,
+            Some(&path.join("states_max.png")),
+            None,
+            Some(StateSphericalPlotMode::ABS),
+            None,
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
+        voxel_type_plot(
+            &voxels.types,
+            &voxels.positions_mm,
+            voxels.size_mm,
+            Some(&path.join("voxel_types.png")),
+            None,
+            PlotTheme::Light,
+        )?;
+        activation_time_plot(
+            &simulation
+                .model
+                .functional_description
+                .ap_params
+                .activation_time_ms,
+            &voxels.positions_mm,
+            voxels.size_mm,
+            &path.join("activation_time.png"),
+            Some(PlotSlice::Z(0)),
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
+        standard_time_plot(
+            &simulation
+                .model
+                .functional_description
+                .control_function_values,
+            simulation.sample_rate_hz,
+            &path.join("control_function.png"),
+            "Control Function Simulation",
+            "u [A/mm^2]",
+            PlotTheme::Light,
+        )?;
+        measurement_butterfly_plot(
+            &simulation.measurements.slice(s![0, .., ..]),
+            simulation.sample_rate_hz,
+            &path.join("measurements.png"),
+            "Measurements Simulation",
+            &simulation
+                .model
+                .spatial_description
+                .sensors
+                .measurement_label(None),
+            PlotTheme::Light,
+        )?;
+        Ok(())
+    }
+
+    /// Re-evaluates the converged solution under perturbations of the
+    /// measurement matrix and saves the report as `robustness.toml` in the
+    /// results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if data or results are missing, or the analysis or
+    /// saving fails.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn analyze_robustness(&self, config: &PerturbationConfig) -> Result<RobustnessReport> {
+        debug!("Analyzing robustness of scenario with id {}", self.id);
+        let data = self
+            .data
+            .as_ref()
+            .context("Scenario data not available for robustness analysis")?;
+        let results = self
+            .results
+            .as_ref()
+            .context("Scenario results not available for robustness analysis")?;
+        let report = robustness::analyze(results, data, &self.config.algorithm, config)?;
+        let path = self.writable_directory()?;
+        fs::create_dir_all(&path)?;
+        report.save(&path.join("robustness.toml"))?;
+        Ok(report)
+    }
+
+    /// Adds the timings recorded for this scenario since the last save to
+    /// the performance report in the scenario directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scenario is read-only or the report cannot be
+    /// read or written.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save_performance_report(&self) -> Result<()> {
+        debug!("Saving performance report of scenario with id {}", self.id);
+        let path = self.writable_directory()?.join(PERFORMANCE_REPORT_FILE);
+        let mut report = if path.is_file() {
+            PerformanceReport::load(&path)?
+        } else {
+            PerformanceReport::default()
+        };
+        report.merge(&profiling::take_pending(&self.id));
+        report.save(&path)
+    }
+
+    /// Returns the saved performance report of the scenario together with
+    /// the timings recorded since, or `None` if there are neither.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn performance_report(&self) -> Option<PerformanceReport> {
+        debug!("Loading performance report of scenario with id {}", self.id);
+        let path = self.get_directory().join(PERFORMANCE_REPORT_FILE);
+        let saved = if path.is_file() {
+            PerformanceReport::load(&path)
+                .map_err(|e| warn!("Failed to load performance report: {:#}", e))
+                .ok()
+        } else {
+            None
+        };
+        match (saved, profiling::pending(&self.id)) {
+            (Some(mut report), Some(pending)) => {
+                report.merge(&pending);
+                Some(report)
+            }
+            (report, None) | (None, report) => report,
+        }
+    }
+}
+
+/// Copies the directory recursively, zstd compressing every file with the
+/// extension `bin`.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be created or a file cannot be
+/// copied.
+#[tracing::instrument(level = "debug")]
+fn copy_compressed(source: &Path, target: &Path) -> Result<()> {
+    debug!("Copying {} to {}", source.display(), target.display());
+    fs::create_dir_all(target)
+        .with_context(|| format!("Failed to create directory: {}", target.display()))?;
+    for entry in fs::read_dir(source)
+        .with_context(|| format!("Failed to read directory: {}", source.display()))?
+    {
+        let entry = entry.context("Failed to read directory entry")?;
+        let path = entry.path();
+        let target_path = target.join(entry.file_name());
+        if path.is_dir() {
+            copy_compressed(&path, &target_path)?;
+        } else if path.extension().is_some_and(|extension| extension == "bin") {
+            compress_file(
+                &path,
+                &compressed_path(&target_path),
+                ARCHIVE_COMPRESSION_LEVEL,
+            )?;
+        } else {
+            fs::copy(&path, &target_path)
+                .with_context(|| format!("Failed to copy {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the id of the single scenario stored in an archive.
+///
+/// All entries have to live below one top level directory that contains a
+/// scenario.toml file.
+///
+/// # Errors
+///
+/// Returns an error if the archive cannot be read or does not contain exactly one scenario.
+#[tracing::instrument(level = "debug")]
+fn archive_scenario_id(path: &Path) -> Result<String> {
+    debug!("Reading scenario id from archive {}", path.display());
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open archive file: {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to create zstd decoder")?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut id: Option<String> = None;
+    let mut has_scenario = false;
+    for entry in archive
+        .entries()
+        .context("Failed to read archive entries")?
+    {
+        let entry = entry.context("Failed to read archive entry")?;
+        let entry_path = entry.path().context("Invalid path in archive")?;
+        let mut components = entry_path.components();
+        let Some(Component::Normal(first)) = components.next() else {
+            bail!(
+                "Archive entry {} is not inside a scenario directory",
+                entry_path.display()
+            );
+        };
+        let first = first.to_string_lossy().into_owned();
+        if *id.get_or_insert_with(|| first.clone()) != first {
+            bail!("Archive contains more than one scenario");
+        }
+        has_scenario |= components.as_path() == Path::new("scenario.toml");
+    }
+    let id = id.context("Archive is empty")?;
+    if !has_scenario {
+        bail!("Archive does not contain {id}/scenario.toml");
+    }
+    Ok(id)
 }
 
 /// Runs the simulation for the given scenario, model, and data.
 ///
-/// Updates the results and summary structs with the output. Sends the final epoch
-/// count and summary via the provided channels. Saves the results to the scenario.
+/// Updates the results and summary structs with the output. Sends the progress
+/// of the data generation, the final epoch count and summary via the provided
+/// channels. Saves the results to the scenario.
 ///
 /// # Errors
 ///
 /// Returns an error if the model parameters are invalid, an unimplemented algorithm
 /// is selected, or any other simulation failure occurs.
-#[tracing::instrument(level = "info", skip_all, fields(id = %scenario.id))]
+#[tracing::instrument(level = "info", skip_all, fields(scenario_id = %scenario.id))]
 pub fn run(
     mut scenario: Scenario,
+    simulation_tx: &Sender<f32>,
     epoch_tx: &Sender<usize>,
     summary_tx: &Sender<Summary>,
 ) -> Result<()> {
     debug!("Running scenario with id {}", scenario.id);
 
+    // the fallbacks only change how this run is executed, the saved config
+    // keeps the algorithm that was requested
+    let mut algorithm_type = scenario.config.algorithm.algorithm_type.clone();
+    if algorithm_type == AlgorithmType::ModelBasedGPU
+        && !is_backend_available(scenario.config.algorithm.gpu_backend)
+    {
+        warn!(
+            "{:?} is not available, running scenario {} on the CPU instead",
+            scenario.config.algorithm.gpu_backend, scenario.id
+        );
+        algorithm_type = AlgorithmType::ModelBased;
+    }
+
+    if scenario.config.algorithm.model.common.sensor_array_motion == SensorArrayMotion::Trajectory {
+        match algorithm_type {
+            AlgorithmType::ModelBased | AlgorithmType::None => {}
+            AlgorithmType::ModelBasedGPU => {
+                warn!(
+                    "Continuous sensor array motion is not supported on the GPU, running scenario {} on the CPU instead",
+                    scenario.id
+                );
+                algorithm_type = AlgorithmType::ModelBased;
+            }
+            _ => warn!(
+                "Continuous sensor array motion is only interpolated by the model-based algorithm, \
+                the other algorithms use the measurement matrix of the first keyframe"
+            ),
+        }
+    }
+
+    for (range, value) in scenario.config.algorithm.out_of_range_parameters() {
+        warn!(
+            "{} is {value}, outside of the recommended range {} to {}",
+            range.name, range.min, range.max
+        );
+    }
+
     let simulation = &scenario.config.simulation;
 
-    let data = Data::from_simulation_config(simulation)
+    let mut data = Data::from_simulation_config_with_progress(simulation, simulation_tx)
         .context("Failed to create simulation data from config - invalid model parameters")?;
+    if algorithm_type == AlgorithmType::None {
+        return finish_simulation_only(scenario, data, epoch_tx, summary_tx);
+    }
+    // the estimation runs on a preprocessed copy of the measurements, the
+    // simulated ones are restored before the data is saved
+    let mut measurements = data.simulation.measurements.clone();
+    filter::preprocess(
+        &mut measurements,
+        &scenario.config.algorithm.preprocessing,
+        simulation.sample_rate_hz,
+    )
+    .context("Failed to preprocess measurements - invalid filter parameters")?;
+    let beat_detection = &scenario.config.algorithm.preprocessing.beat_detection;
+    if beat_detection.enabled {
+        let detected =
+            beats::segment_beats(&mut measurements, beat_detection, simulation.sample_rate_hz)
+                .context("Failed to segment measurements into beats")?;
+        info!(
+            "Detected {} beats, {} within the recording",
+            detected.triggers.len(),
+            detected.window_starts.len()
+        );
+        data.beats = Some(detected);
+    }
+    let simulated_measurements = std::mem::replace(&mut data.simulation.measurements, measurements);
     let mut model = Model::from_model_config(
         &scenario.config.algorithm.model,
         simulation.sample_rate_hz,
@@ -555,13 +1595,98 @@ pub fn run(
         number_of_snapshots,
         scenario.config.algorithm.batch_size,
         scenario.config.algorithm.optimizer,
+        scenario.config.algorithm.model.common.neighborhood_radius,
     );
+    results
+        .estimations
+        .channel_mask
+        .exclude(&scenario.config.algorithm.excluded_channels)
+        .context("Failed to exclude channels - invalid channel index")?;
+    if scenario.config.algorithm.mask_bad_channels {
+        results
+            .estimations
+            .channel_mask
+            .exclude(&data.simulation.sensor_faults.bad_channels())
+            .context("Failed to exclude faulty channels of the simulation")?;
+    }
+    results.derivatives.update_mask =
+        UpdateMask::from_config(&scenario.config.algorithm, &model.spatial_description)
+            .context("Failed to create the mask of the optimization region")?;
+    results.derivatives.parameter_bounds = ParameterBounds::from_config(
+        &scenario.config.algorithm.physiological_bounds,
+        &model.functional_description.ap_params,
+        model.spatial_description.voxels.size_mm,
+        scenario.config.simulation.sample_rate_hz,
+    )
+    .context("Failed to create the physiological parameter bounds")?;
+    if scenario.config.algorithm.measurement_diagnostics {
+        results.conditioning = Some(
+            calculate_measurement_conditioning(&model.functional_description.measurement_matrix)
+                .context("Failed to diagnose the measurement matrix")?,
+        );
+    }
 
     let mut summary = Summary::default();
+    // a resumed scenario continues from its autosave
+    let resume = load_resume_point(&scenario, &algorithm_type);
 
-    match scenario.config.algorithm.algorithm_type {
+    if let Initialization::FromScenario(id) = &scenario.config.algorithm.initialization {
+        match algorithm_type {
+            AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+                let ap_params = warm_start::load_ap_params(&scenario.root, id)
+                    .context("Failed to load the parameters to initialize from")?;
+                model
+                    .functional_description
+                    .ap_params
+                    .copy_from(&ap_params)
+                    .with_context(|| format!("Scenario {id} does not match the model"))?;
+                summary.initialized_from = Some(id.clone());
+            }
+            _ => warn!("Initializing from a scenario is only used by the model-based algorithms"),
+        }
+    }
+
+    // the ensemble members start from the model before the estimation
+    let ensemble_model =
+        (scenario.config.algorithm.ensemble.number_of_members > 0).then(|| model.clone());
+
+    if scenario.config.algorithm.coarse_to_fine.coarse_epochs > 0
+        && summary.initialized_from.is_some()
+    {
+        warn!("Skipping the coarse stage, the model is initialized from a previous scenario");
+    } else if scenario.config.algorithm.coarse_to_fine.coarse_epochs > 0 && resume.is_none() {
+        match algorithm_type {
+            AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+                coarse_to_fine::run(
+                    &mut model,
+                    &data,
+                    &scenario.config.algorithm,
+                    simulation.sample_rate_hz,
+                    simulation.duration_s,
+                )
+                .context("Failed to initialize the model on the coarse grid")?;
+            }
+            _ => warn!("Coarse to fine estimation is only used by the model-based algorithms"),
+        }
+    }
+
+    match algorithm_type {
         AlgorithmType::ModelBased => {
-            results.model = Some(model);
+            let resume = match resume {
+                Some((progress, autosaved)) => {
+                    info!(
+                        "Resuming scenario {} after epoch {}",
+                        scenario.id, progress.epoch
+                    );
+                    results = autosaved;
+                    summary = progress.summary.clone();
+                    Some(progress)
+                }
+                None => {
+                    results.model = Some(model);
+                    None
+                }
+            };
             run_model_based(
                 &mut scenario,
                 &mut results,
@@ -569,6 +1694,7 @@ pub fn run(
                 &mut summary,
                 epoch_tx,
                 summary_tx,
+                resume.as_ref(),
             )
             .context("Failed to execute model-based algorithm")?;
         }
@@ -589,10 +1715,54 @@ pub fn run(
                 .context("Failed to execute pseudo inverse algorithm")?;
             results.model = Some(model);
         }
+        AlgorithmType::KalmanFilter => {
+            run_kalman(&scenario, &model, &mut results, &data, &mut summary)
+                .context("Failed to execute Kalman filter algorithm")?;
+            results.model = Some(model);
+        }
+        AlgorithmType::MinimumNorm | AlgorithmType::SLoreta | AlgorithmType::ELoreta => {
+            run_inverse(&scenario, &model, &mut results, &data, &mut summary)
+                .context("Failed to execute inverse solver algorithm")?;
+            results.model = Some(model);
+        }
+        AlgorithmType::None => {
+            bail!("Scenarios without an algorithm are finished after the simulation")
+        }
+    }
+
+    if scenario.config.algorithm.parameter_confidence {
+        match algorithm_type {
+            AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+                let model = results
+                    .model
+                    .as_ref()
+                    .context("Model should be set after algorithm execution")?;
+                results.confidence = Some(
+                    calculate_parameter_confidence(
+                        &results.estimations,
+                        &model.functional_description,
+                        &data,
+                    )
+                    .context("Failed to calculate parameter confidence")?,
+                );
+            }
+            _ => warn!("Parameter confidence is only estimated for the model-based algorithms"),
+        }
     }
 
     calculate_plotting_arrays(&mut results, &data)?;
 
+    if let Some(ensemble_model) = ensemble_model {
+        results.ensemble = Some(
+            ensemble::run(&ensemble_model, &data, &scenario.config.algorithm)
+                .context("Failed to run ensemble")?,
+        );
+    }
+
+    if !scenario.config.algorithm.keep_step_metrics {
+        results.metrics.discard_step_metrics();
+    }
+
     metrics::calculate_final(
         &mut results.metrics,
         &results.estimations,
@@ -606,21 +1776,47 @@ pub fn run(
             .numbers,
     );
 
-    let optimal_threshold = results
-        .metrics
-        .dice_score_over_threshold
-        .argmax_skipnan()
-        .unwrap_or_default();
+    summary.set_optimal_threshold(&results.metrics);
 
-    #[allow(clippy::cast_precision_loss)]
-    {
-        summary.threshold = optimal_threshold as f32 / 100.0;
-    }
-    summary.dice = results.metrics.dice_score_over_threshold[optimal_threshold];
-    summary.iou = results.metrics.iou_over_threshold[optimal_threshold];
-    summary.recall = results.metrics.recall_over_threshold[optimal_threshold];
-    summary.precision = results.metrics.precision_over_threshold[optimal_threshold];
+    let model = results
+        .model
+        .as_ref()
+        .context("Model should be set after algorithm execution")?;
+    summary.velocities = calculate_velocity_statistics(
+        &results.estimations.average_delays,
+        &model.spatial_description.voxels.numbers,
+        &data.simulation.model.spatial_description.voxels.types,
+        model.spatial_description.voxels.size_mm,
+        data.simulation.sample_rate_hz,
+    );
+    summary.activation_time = calculate_activation_time_statistics(
+        &data
+            .simulation
+            .model
+            .functional_description
+            .ap_params
+            .activation_time_ms,
+        &model.functional_description.ap_params.activation_time_ms,
+    );
+    summary.localization = calculate_localization(
+        &predict_voxeltype(
+            &results.estimations,
+            &data.simulation.model.spatial_description.voxels.types,
+            &model.spatial_description.voxels.numbers,
+            summary.threshold,
+        ),
+        &data.simulation.model.spatial_description.voxels.types,
+        &data
+            .simulation
+            .model
+            .spatial_description
+            .voxels
+            .positions_mm,
+        model.spatial_description.voxels.size_mm,
+    );
 
+    summary.provenance = Some(Provenance::current());
+    data.simulation.measurements = simulated_measurements;
     scenario.results = Some(results);
     scenario.data = Some(data);
     scenario.summary = Some(summary.clone());
@@ -628,11 +1824,101 @@ pub fn run(
     scenario
         .save()
         .context("Failed to save completed scenario results")?;
+    if let Err(e) = autosave::remove(&scenario.get_directory()) {
+        warn!("Failed to remove autosave: {:#}", e);
+    }
+    // replaces the report of a previous run of this scenario
+    if let Err(e) = profiling::take_pending(&scenario.id)
+        .save(&scenario.get_directory().join(PERFORMANCE_REPORT_FILE))
+    {
+        warn!("Failed to save performance report: {:#}", e);
+    }
     let _ = epoch_tx.send(scenario.config.algorithm.epochs - 1);
     let _ = summary_tx.send(summary);
     Ok(())
 }
 
+/// Completes a scenario without an algorithm after the data is simulated.
+///
+/// Plots the simulation, saves the data and marks the scenario as done. The
+/// scenario has no results, only the provenance is recorded in its summary.
+#[tracing::instrument(level = "info", skip_all, fields(scenario_id = %scenario.id))]
+fn finish_simulation_only(
+    mut scenario: Scenario,
+    data: Data,
+    epoch_tx: &Sender<usize>,
+    summary_tx: &Sender<Summary>,
+) -> Result<()> {
+    info!(
+        "Scenario {} only simulates data, skipping the estimation",
+        scenario.id
+    );
+    let summary = Summary {
+        provenance: Some(Provenance::current()),
+        ..Default::default()
+    };
+    scenario.data = Some(data);
+    scenario
+        .save_simulation_plots()
+        .context("Failed to plot the simulation")?;
+    scenario.summary = Some(summary.clone());
+    scenario.status = Status::Done;
+    scenario.save().context("Failed to save simulated data")?;
+    // replaces the report of a previous run of this scenario
+    if let Err(e) = profiling::take_pending(&scenario.id)
+        .save(&scenario.get_directory().join(PERFORMANCE_REPORT_FILE))
+    {
+        warn!("Failed to save performance report: {:#}", e);
+    }
+    let _ = epoch_tx.send(scenario.config.algorithm.epochs - 1);
+    let _ = summary_tx.send(summary);
+    Ok(())
+}
+
+/// Copies the model settings shared by the algorithm and the simulation from
+/// the simulation config to the algorithm config and uses a single epoch for
+/// the algorithms that are not iterative.
+#[tracing::instrument(level = "trace", skip_all)]
+fn unify_config(config: &mut Config) {
+    trace!("Unifying algorithm and simulation configs");
+    let model = &mut config.algorithm.model;
+    let simulation = &config.simulation;
+    model.common.sensor_array_geometry = simulation.model.common.sensor_array_geometry.clone();
+    model.common.sensor_type = simulation.model.common.sensor_type;
+    model.common.sensor_array_modality = simulation.model.common.sensor_array_modality;
+    model.common.number_of_sensors = simulation.model.common.number_of_sensors;
+    model.common.sensor_array_radius_mm = simulation.model.common.sensor_array_radius_mm;
+    model.common.sensors_per_axis = simulation.model.common.sensors_per_axis;
+    model.common.sensor_array_size_mm = simulation.model.common.sensor_array_size_mm;
+    model.common.sensor_array_origin_mm = simulation.model.common.sensor_array_origin_mm;
+    model.common.voxel_size_mm = simulation.model.common.voxel_size_mm;
+    model.common.heart_offset_mm = simulation.model.common.heart_offset_mm;
+    model.common.sensor_array_motion = simulation.model.common.sensor_array_motion.clone();
+    model.common.sensor_array_motion_range_mm =
+        simulation.model.common.sensor_array_motion_range_mm;
+    model.common.sensor_array_motion_steps = simulation.model.common.sensor_array_motion_steps;
+    model
+        .common
+        .sensor_array_trajectory
+        .clone_from(&simulation.model.common.sensor_array_trajectory);
+    if let Some(handcrafted) = simulation.model.handcrafted.as_ref() {
+        if let Some(model_handcrafted) = model.handcrafted.as_mut() {
+            model_handcrafted.heart_size_mm = handcrafted.heart_size_mm;
+        }
+    }
+    if matches!(
+        config.algorithm.algorithm_type,
+        AlgorithmType::PseudoInverse
+            | AlgorithmType::KalmanFilter
+            | AlgorithmType::MinimumNorm
+            | AlgorithmType::SLoreta
+            | AlgorithmType::ELoreta
+            | AlgorithmType::None
+    ) {
+        config.algorithm.epochs = 1;
+    }
+}
+
 #[tracing::instrument(level = "trace", skip_all)]
 pub(crate) fn calculate_plotting_arrays(results: &mut Results, data: &Data) -> Result<()> {
     results
@@ -716,9 +2002,64 @@ fn run_pseudo_inverse(
     Ok(())
 }
 
+/// Runs the Kalman filter algorithm on the given scenario, model, and data.
+/// Estimates the system states and calculates summary metrics.
+///
+/// # Errors
+///
+/// Returns an error if the Kalman gain cannot be calculated.
+#[tracing::instrument(level = "info", skip_all)]
+fn run_kalman(
+    scenario: &Scenario,
+    model: &Model,
+    results: &mut Results,
+    data: &Data,
+    summary: &mut Summary,
+) -> Result<()> {
+    info!("Running Kalman filter algorithm");
+    run_kalman_filter(
+        &model.functional_description,
+        results,
+        data,
+        &scenario.config.algorithm,
+    )?;
+    summary.loss = results.metrics.loss_batch[0];
+    summary.loss_mse = results.metrics.loss_mse_batch[0];
+    summary.loss_maximum_regularization = results.metrics.loss_maximum_regularization_batch[0];
+    Ok(())
+}
+
+/// Runs the configured minimum-norm type inverse solver on the given
+/// scenario, model, and data and fills the summary losses.
+///
+/// # Errors
+///
+/// Returns an error if the inverse operator cannot be calculated.
+#[tracing::instrument(level = "info", skip_all)]
+fn run_inverse(
+    scenario: &Scenario,
+    model: &Model,
+    results: &mut Results,
+    data: &Data,
+    summary: &mut Summary,
+) -> Result<()> {
+    info!("Running inverse solver algorithm");
+    run_inverse_solver(
+        &model.functional_description,
+        results,
+        data,
+        &scenario.config.algorithm,
+    )?;
+    summary.loss = results.metrics.loss_batch[0];
+    summary.loss_mse = results.metrics.loss_mse_batch[0];
+    summary.loss_maximum_regularization = results.metrics.loss_maximum_regularization_batch[0];
+    Ok(())
+}
+
 /// Runs the model-based algorithm on the given scenario, model, and data.
 /// Calculates model parameters over epochs and calculates summary metrics.
-/// Reduces learning rate at intervals. Saves snapshots at intervals.
+/// Reduces learning rate at intervals. Saves snapshots and autosaves at
+/// intervals. Continues after the autosaved epoch if `resume` is given.
 /// Sends epoch and summary updates over channels.
 /// Exits early if loss becomes non-finite.
 #[tracing::instrument(level = "info", skip_all)]
@@ -729,25 +2070,75 @@ fn run_model_based(
     summary: &mut Summary,
     epoch_tx: &Sender<usize>,
     summary_tx: &Sender<Summary>,
+    resume: Option<&AutosaveProgress>,
 ) -> Result<()> {
     info!("Running model-based algorithm");
     let original_learning_rate = scenario.config.algorithm.learning_rate;
-    let mut batch_index = 0;
+    let original_freeze_gains = scenario.config.algorithm.freeze_gains;
+    let original_freeze_delays = scenario.config.algorithm.freeze_delays;
+    let mut learning_rate = original_learning_rate;
+    let mut batch_index = resume.map_or(0, |progress| progress.batch_index);
+    let start_epoch = resume.map_or(0, |progress| progress.epoch + 1);
+    let thread_pool = algorithm::build_thread_pool(scenario.config.algorithm.number_of_threads)?;
     for epoch_index in 0..scenario.config.algorithm.epochs {
-        if epoch_index == 0 {
-            scenario.config.algorithm.learning_rate = 0.0;
-        } else if epoch_index == 1 {
-            scenario.config.algorithm.learning_rate = original_learning_rate;
-        }
-        if scenario.config.algorithm.learning_rate_reduction_interval != 0
+        // correlates the log events of the epoch, nested in the scenario span
+        let _epoch_span = info_span!("epoch", epoch = epoch_index).entered();
+        if epoch_index > 0
+            && scenario.config.algorithm.learning_rate_reduction_interval != 0
             && (epoch_index % scenario.config.algorithm.learning_rate_reduction_interval == 0)
         {
-            scenario.config.algorithm.learning_rate *=
-                scenario.config.algorithm.learning_rate_reduction_factor;
+            learning_rate *= scenario.config.algorithm.learning_rate_reduction_factor;
+        }
+        // the learning rate reductions of the autosaved epochs are replayed
+        if epoch_index < start_epoch {
+            continue;
+        }
+        let (freeze_gains, freeze_delays, learning_rate_multiplier) = scenario
+            .config
+            .algorithm
+            .freeze_stage_at(epoch_index)
+            .map_or(
+                (original_freeze_gains, original_freeze_delays, 1.0),
+                |stage| {
+                    (
+                        stage.freeze_gains,
+                        stage.freeze_delays,
+                        stage.learning_rate_multiplier,
+                    )
+                },
+            );
+        scenario.config.algorithm.freeze_gains = freeze_gains;
+        scenario.config.algorithm.freeze_delays = freeze_delays;
+        scenario.config.algorithm.learning_rate = if epoch_index == 0 {
+            0.0
+        } else {
+            learning_rate * learning_rate_multiplier
+        };
+        let config = &scenario.config.algorithm;
+        match thread_pool.as_ref() {
+            Some(pool) => {
+                // keeps the scenario span active on the pool thread for profiling
+                let span = tracing::Span::current();
+                pool.install(|| {
+                    span.in_scope(|| algorithm::run_epoch(results, &mut batch_index, data, config))
+                })
+            }
+            None => algorithm::run_epoch(results, &mut batch_index, data, config),
         }
-        algorithm::run_epoch(results, &mut batch_index, data, &scenario.config.algorithm)
-            .with_context(|| format!("Failed to run algorithm epoch {epoch_index}"))?;
+        .with_context(|| format!("Failed to run algorithm epoch {epoch_index}"))?;
         scenario.status = Status::Running(epoch_index);
+        if scenario.config.algorithm.keep_step_metrics {
+            results.metrics.record_step_traces()?;
+        }
+        results.metrics.record_parameter_history(
+            &results
+                .model
+                .as_ref()
+                .context("Model should be set during algorithm execution")?
+                .functional_description
+                .ap_params,
+            &scenario.config.algorithm.parameter_recording,
+        )?;
 
         summary.loss = results.metrics.loss_batch[batch_index - 1];
         summary.loss_mse = results.metrics.loss_mse_batch[batch_index - 1];
@@ -772,6 +2163,8 @@ fn run_model_based(
                 );
         }
 
+        autosave_if_due(scenario, results, summary, epoch_index, batch_index);
+
         let _ = epoch_tx.send(epoch_index);
         let _ = summary_tx.send(summary.clone());
         // Check if algorithm diverged. If so return early
@@ -789,10 +2182,82 @@ fn run_model_based(
             .ap_params,
     )?;
     scenario.config.algorithm.learning_rate = original_learning_rate;
+    scenario.config.algorithm.freeze_gains = original_freeze_gains;
+    scenario.config.algorithm.freeze_delays = original_freeze_delays;
     Ok(())
 }
 
-#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+/// Returns the progress and results of the autosave to continue from if a
+/// model-based scenario was resumed after an interruption. An autosave that
+/// cannot be used is ignored, the scenario then starts from the first epoch.
+#[tracing::instrument(level = "debug", skip_all)]
+fn load_resume_point(
+    scenario: &Scenario,
+    algorithm_type: &AlgorithmType,
+) -> Option<(AutosaveProgress, Results)> {
+    if *algorithm_type != AlgorithmType::ModelBased || !scenario.has_autosave() {
+        return None;
+    }
+    debug!("Loading autosave of scenario {}", scenario.id);
+    match autosave::load(&scenario.get_directory()) {
+        Ok((progress, results)) if progress.epoch + 1 < scenario.config.algorithm.epochs => {
+            Some((progress, results))
+        }
+        Ok((progress, _)) => {
+            warn!(
+                "Ignoring autosave of scenario {} after epoch {}, it does not match the {} epochs of the config",
+                scenario.id, progress.epoch, scenario.config.algorithm.epochs
+            );
+            None
+        }
+        Err(e) => {
+            warn!(
+                "Failed to load autosave of scenario {}, starting from the first epoch: {:#}",
+                scenario.id, e
+            );
+            None
+        }
+    }
+}
+
+/// Autosaves the progress and results of a running model-based scenario if
+/// the autosave interval ends at the given epoch.
+///
+/// The last epoch is never autosaved since the final results are saved
+/// right after. A failed autosave is logged and does not stop the run.
+#[tracing::instrument(level = "debug", skip(scenario, results, summary))]
+fn autosave_if_due(
+    scenario: &Scenario,
+    results: &Results,
+    summary: &Summary,
+    epoch_index: usize,
+    batch_index: usize,
+) {
+    let algorithm = &scenario.config.algorithm;
+    if algorithm.autosave_interval == 0
+        || (epoch_index + 1) % algorithm.autosave_interval != 0
+        || epoch_index + 1 == algorithm.epochs
+    {
+        return;
+    }
+    let progress = AutosaveProgress {
+        epoch: epoch_index,
+        batch_index,
+        last_update: Some(Utc::now()),
+        summary: summary.clone(),
+    };
+    if let Err(e) = scenario.writable_directory().and_then(|directory| {
+        autosave::save(
+            &directory,
+            &progress,
+            results,
+            scenario.config.storage.compression_level,
+        )
+    }) {
+        warn!("Failed to autosave scenario {}: {:#}", scenario.id, e);
+    }
+}
+
 #[tracing::instrument(level = "info", skip_all)]
 fn run_model_based_gpu(
     scenario: &mut Scenario,
@@ -803,45 +2268,60 @@ fn run_model_based_gpu(
     summary_tx: &Sender<Summary>,
 ) -> Result<()> {
     info!("Running model-based algorithm on gpu");
+    if !scenario.config.algorithm.freeze_schedule.is_empty() {
+        warn!("Freeze schedule is not supported by the GPU algorithm and will be ignored");
+    }
+    if loss::active_terms(&scenario.config.algorithm).any(|term| !term.kind.is_fused()) {
+        warn!("Loss terms are not supported by the GPU algorithm and will be ignored");
+    }
+    if scenario.config.algorithm.physiological_bounds.is_enabled() {
+        warn!("Physiological bounds are not supported by the GPU algorithm and will be ignored");
+    }
+    if !scenario.config.algorithm.freeze_refractory {
+        warn!(
+            "Learning refractory times is not supported by the GPU algorithm and will be ignored"
+        );
+    }
+    if scenario.config.algorithm.autosave_interval > 0 {
+        warn!("Autosave is not supported by the GPU algorithm, an interrupted run starts over");
+    }
     // move data to gpu
-    let gpu = GPU::new()?;
-    let results_gpu = results.to_gpu(&gpu.queue)?;
-    let actual_measurements = data.simulation.measurements.to_gpu(&gpu.queue)?;
-    let number_of_states = results
-        .model
-        .as_ref()
-        .context("Model should be set during GPU algorithm execution")?
-        .spatial_description
-        .voxels
-        .count_states();
-    let number_of_sensors = results
-        .model
-        .as_ref()
-        .context("Model should be set during GPU algorithm execution")?
-        .spatial_description
-        .sensors
-        .count();
-    let number_of_steps = results.estimations.measurements.num_steps();
-    let mut epoch_kernel = EpochKernel::new(
-        &gpu,
-        &results_gpu,
-        &actual_measurements,
-        &scenario.config.algorithm,
-        number_of_states as i32,
-        number_of_sensors as i32,
-        number_of_steps as i32,
-    )?;
+    let mut backend = create_backend(results, data, &scenario.config.algorithm)?;
+    summary.gpu_memory_bytes = backend.memory_bytes();
 
     for epoch_index in 0..scenario.config.algorithm.epochs {
+        // correlates the log events of the epoch, nested in the scenario span
+        let _epoch_span = info_span!("epoch", epoch = epoch_index).entered();
         if epoch_index == 0 {
-            epoch_kernel.set_freeze_delays(true);
-            epoch_kernel.set_freeze_gains(true);
+            backend.set_freeze_delays(true);
+            backend.set_freeze_gains(true);
         } else if epoch_index == 1 {
-            epoch_kernel.set_freeze_delays(scenario.config.algorithm.freeze_delays);
-            epoch_kernel.set_freeze_gains(scenario.config.algorithm.freeze_gains);
+            backend.set_freeze_delays(scenario.config.algorithm.freeze_delays);
+            backend.set_freeze_gains(scenario.config.algorithm.freeze_gains);
+        }
+        backend.execute_epoch()?;
+        // prediction, derivation and update run on the device, only the
+        // losses of this batch are read back until the end
+        backend.read_batch_metrics(&mut results.metrics, epoch_index)?;
+        if scenario.config.algorithm.keep_step_metrics {
+            backend.read_step_metrics(&mut results.metrics)?;
+            results.metrics.record_step_traces()?;
+        }
+        if scenario.config.algorithm.parameter_recording.is_enabled() {
+            // the parameters live on the device, so recording them costs a
+            // read back of the estimations every epoch
+            let ap_params = &mut results
+                .model
+                .as_mut()
+                .context("Model should be set during GPU algorithm execution")?
+                .functional_description
+                .ap_params;
+            backend.read_estimations(&mut results.estimations, ap_params)?;
+            results.metrics.record_parameter_history(
+                ap_params,
+                &scenario.config.algorithm.parameter_recording,
+            )?;
         }
-        epoch_kernel.execute()?;
-        results.metrics.update_from_gpu(&results_gpu.metrics)?;
 
         summary.loss = results.metrics.loss_batch[epoch_index];
         summary.loss_mse = results.metrics.loss_mse_batch[epoch_index];
@@ -851,16 +2331,15 @@ fn run_model_based_gpu(
         if scenario.config.algorithm.snapshots_interval != 0
             && epoch_index % scenario.config.algorithm.snapshots_interval == 0
         {
-            results
-                .estimations
-                .update_from_gpu(&results_gpu.estimations)?;
-            results
-                .model
-                .as_mut()
-                .context("Model should be set during GPU algorithm execution")?
-                .functional_description
-                .ap_params
-                .update_from_gpu(&results_gpu.model.functional_description.ap_params)?;
+            backend.read_estimations(
+                &mut results.estimations,
+                &mut results
+                    .model
+                    .as_mut()
+                    .context("Model should be set during GPU algorithm execution")?
+                    .functional_description
+                    .ap_params,
+            )?;
             results
                 .snapshots
                 .as_mut()
@@ -883,7 +2362,7 @@ fn run_model_based_gpu(
             break;
         }
     }
-    results.update_from_gpu(&results_gpu)?;
+    backend.read_results(results)?;
     calculate_average_delays(
         &mut results.estimations.average_delays,
         &results
@@ -903,6 +2382,7 @@ fn run_model_based_gpu(
 /// * `Running`: Scenario is running the specified epoch.
 /// * `Aborted`: Scenario execution was aborted.
 /// * `Scheduled`: Scenario execution is scheduled but not yet running.
+/// * `Interrupted`: Scenario was running when the application terminated.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
 pub enum Status {
     Planning,
@@ -911,4 +2391,19 @@ pub enum Status {
     Running(usize),
     Aborted,
     Scheduled,
+    Interrupted,
+}
+
+/// Ways to recover a scenario that was interrupted by an application crash.
+///
+/// * `Reschedule`: Puts the scenario back into the queue. The autosave is
+///   discarded, so the scenario starts again from the first epoch.
+/// * `Resume`: Puts the scenario back into the queue to continue after the
+///   last autosaved epoch. Requires an autosave.
+/// * `Abort`: Marks the scenario as aborted.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RecoveryAction {
+    Reschedule,
+    Resume,
+    Abort,
 }
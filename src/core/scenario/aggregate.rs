@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use ndarray::Array1;
+use tracing::debug;
+
+use super::{results::Results, summary::Summary, Scenario};
+
+// two-sided 95 % quantiles of the student t distribution for 1 to 30
+// degrees of freedom, the normal quantile is used above
+const T_QUANTILES_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+const NORMAL_QUANTILE_95: f32 = 1.96;
+
+/// The summary metrics that are aggregated, by name.
+pub const AGGREGATED_METRICS: [(&str, fn(&Summary) -> Option<f32>); 12] = [
+    ("Loss", |summary| Some(summary.loss)),
+    ("MSE Loss", |summary| Some(summary.loss_mse)),
+    ("M. R. Loss", |summary| {
+        Some(summary.loss_maximum_regularization)
+    }),
+    ("Threshold", |summary| Some(summary.threshold)),
+    ("Dice", |summary| Some(summary.dice)),
+    ("IoU", |summary| Some(summary.iou)),
+    ("Recall", |summary| Some(summary.recall)),
+    ("Precision", |summary| Some(summary.precision)),
+    ("ROC AUC", |summary| Some(summary.roc_auc)),
+    ("PR AUC", |summary| Some(summary.precision_recall_auc)),
+    ("Act. Time MAE [ms]", |summary| {
+        summary
+            .activation_time
+            .as_ref()
+            .map(|statistics| statistics.mean_absolute_error_ms)
+    }),
+    ("Loc. Error [mm]", |summary| {
+        summary
+            .localization
+            .as_ref()
+            .map(|localization| localization.error_mm)
+    }),
+];
+
+/// Mean, sample standard deviation and 95 % confidence interval of the mean
+/// of one metric over repeated runs.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MetricStatistics {
+    pub count: usize,
+    pub mean: f32,
+    pub std: f32,
+    pub confidence_interval_lower: f32,
+    pub confidence_interval_upper: f32,
+}
+
+impl MetricStatistics {
+    /// Calculates the statistics of the given values.
+    ///
+    /// Returns `None` if no values are given. The confidence interval uses
+    /// the student t distribution and collapses to the mean for a single
+    /// value.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace")]
+    pub fn from_values(values: &[f32]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len();
+        let mean = values.iter().sum::<f32>() / count as f32;
+        let std = if count > 1 {
+            (values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f32>()
+                / (count - 1) as f32)
+                .sqrt()
+        } else {
+            0.0
+        };
+        let quantile = T_QUANTILES_95
+            .get(count.saturating_sub(2))
+            .copied()
+            .unwrap_or(NORMAL_QUANTILE_95);
+        let half_width = quantile * std / (count as f32).sqrt();
+        Some(Self {
+            count,
+            mean,
+            std,
+            confidence_interval_lower: mean - half_width,
+            confidence_interval_upper: mean + half_width,
+        })
+    }
+}
+
+/// Finished scenarios whose configs are identical except for their seeds,
+/// together with the statistics of their summary metrics.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScenarioGroup {
+    pub ids: Vec<String>,
+    // one entry per metric of AGGREGATED_METRICS, None if no run reports it
+    pub metrics: Vec<Option<MetricStatistics>>,
+}
+
+/// Groups the finished scenarios by their config modulo seeds and
+/// aggregates the summary metrics of every group.
+///
+/// Scenarios without a summary are skipped. Groups keep the order in which
+/// their first scenario appears.
+///
+/// # Errors
+///
+/// Returns an error if the configs cannot be compared.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn aggregate<'a>(
+    scenarios: impl IntoIterator<Item = &'a Scenario>,
+) -> Result<Vec<ScenarioGroup>> {
+    debug!("Aggregating scenarios over seeds");
+    Ok(group_by_config_modulo_seed(scenarios)?
+        .iter()
+        .map(|group| ScenarioGroup {
+            ids: group
+                .iter()
+                .map(|scenario| scenario.get_id().clone())
+                .collect(),
+            metrics: AGGREGATED_METRICS
+                .iter()
+                .map(|(_, metric)| {
+                    let values: Vec<f32> = group
+                        .iter()
+                        .filter_map(|scenario| scenario.summary.as_ref().and_then(metric))
+                        .collect();
+                    MetricStatistics::from_values(&values)
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+/// Groups the finished scenarios whose configs only differ in parameters
+/// named `seed`.
+///
+/// # Errors
+///
+/// Returns an error if the configs cannot be compared.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn group_by_config_modulo_seed<'a>(
+    scenarios: impl IntoIterator<Item = &'a Scenario>,
+) -> Result<Vec<Vec<&'a Scenario>>> {
+    debug!("Grouping scenarios by config modulo seed");
+    let mut groups: Vec<Vec<&Scenario>> = Vec::new();
+    for scenario in scenarios {
+        if scenario.summary.is_none() {
+            continue;
+        }
+        let mut matching_group = None;
+        for (index, group) in groups.iter().enumerate() {
+            let differs = group[0]
+                .config
+                .diff(&scenario.config)
+                .context("Failed to compare scenario configs")?
+                .iter()
+                .any(|diff| diff.path.rsplit('.').next() != Some("seed"));
+            if !differs {
+                matching_group = Some(index);
+                break;
+            }
+        }
+        match matching_group {
+            Some(index) => groups[index].push(scenario),
+            None => groups.push(vec![scenario]),
+        }
+    }
+    Ok(groups)
+}
+
+/// Loads the batch-wise loss curve of every scenario, from the loaded
+/// results if available or else from the metrics in the results directory.
+///
+/// # Errors
+///
+/// Returns an error if the metrics of a scenario cannot be read.
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn loss_curves(scenarios: &[&Scenario]) -> Result<Vec<Array1<f32>>> {
+    debug!("Loading loss curves");
+    scenarios
+        .iter()
+        .map(|scenario| {
+            if let Some(results) = &scenario.results {
+                return Ok((*results.metrics.loss_batch).clone());
+            }
+            let metrics = Results::load_metrics(&scenario.get_directory().join("results"))
+                .with_context(|| {
+                    format!("Failed to load metrics of scenario {}", scenario.get_id())
+                })?;
+            Ok((*metrics.loss_batch).clone())
+        })
+        .collect()
+}
+
+/// Returns the mean and the sample standard deviation of the curves at
+/// every index, truncated to the shortest curve.
+///
+/// Returns `None` if no curves are given.
+#[must_use]
+#[tracing::instrument(level = "debug", skip_all)]
+pub fn mean_and_std_band(curves: &[Array1<f32>]) -> Option<(Array1<f32>, Array1<f32>)> {
+    let length = curves.iter().map(Array1::len).min()?;
+    let mut mean = Array1::zeros(length);
+    let mut std = Array1::zeros(length);
+    for index in 0..length {
+        let values: Vec<f32> = curves.iter().map(|curve| curve[index]).collect();
+        let statistics = MetricStatistics::from_values(&values)?;
+        mean[index] = statistics.mean;
+        std[index] = statistics.std;
+    }
+    Some((mean, std))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::arr1;
+
+    use super::*;
+
+    #[test]
+    fn statistics_include_t_confidence_interval() -> Result<()> {
+        let statistics = MetricStatistics::from_values(&[1.0, 2.0, 3.0])
+            .context("Expected statistics for non-empty values")?;
+
+        assert_eq!(statistics.count, 3);
+        assert_relative_eq!(statistics.mean, 2.0);
+        assert_relative_eq!(statistics.std, 1.0);
+        assert_relative_eq!(
+            statistics.confidence_interval_upper,
+            2.0 + 4.303 / 3.0_f32.sqrt()
+        );
+        assert!(MetricStatistics::from_values(&[]).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn scenarios_differing_in_seed_are_grouped() -> Result<()> {
+        let mut scenarios = [Scenario::empty(), Scenario::empty(), Scenario::empty()];
+        for (scenario, dice) in scenarios.iter_mut().zip([0.4, 0.6, 0.9]) {
+            scenario.summary = Some(Summary {
+                dice,
+                ..Summary::default()
+            });
+        }
+        scenarios[1].config.simulation.structured_noise.seed = 7;
+        scenarios[2].config.algorithm.learning_rate *= 2.0;
+
+        let groups = aggregate(&scenarios)?;
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].ids.len(), 2);
+        let dice = groups[0].metrics[4].context("Expected dice statistics")?;
+        assert_relative_eq!(dice.mean, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn band_is_truncated_to_shortest_curve() -> Result<()> {
+        let (mean, std) = mean_and_std_band(&[arr1(&[1.0, 2.0, 3.0]), arr1(&[3.0, 4.0])])
+            .context("Expected a band for non-empty curves")?;
+
+        assert_eq!(mean, arr1(&[2.0, 3.0]));
+        assert_relative_eq!(std[0], 2.0_f32.sqrt());
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::{
+    compression::{compressed_path, existing_file, open_reader, temporary_path, write_file},
+    results::Results,
+    summary::Summary,
+};
+
+/// File in the scenario directory holding the last autosave of a run.
+pub const AUTOSAVE_FILE: &str = "autosave.bin";
+
+/// Progress of a running scenario as of the last autosave.
+///
+/// The results are stored last, so the progress can be read without
+/// deserializing them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutosaveProgress {
+    // last epoch that is included in the autosave
+    pub epoch: usize,
+    // index of the next batch, the per-batch metrics continue from here
+    pub batch_index: usize,
+    pub last_update: Option<DateTime<Utc>>,
+    pub summary: Summary,
+}
+
+// same layout as `Autosave`, so the results do not have to be cloned
+#[derive(Serialize)]
+struct AutosaveRef<'a> {
+    progress: &'a AutosaveProgress,
+    results: &'a Results,
+}
+
+#[derive(Deserialize)]
+struct Autosave {
+    progress: AutosaveProgress,
+    results: Results,
+}
+
+/// Writes the progress and results of a running scenario to the autosave
+/// file in the scenario directory, replacing the previous autosave.
+///
+/// The file is replaced atomically, so the previous autosave stays intact
+/// until the new one is complete.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+#[tracing::instrument(level = "debug", skip(progress, results))]
+pub fn save(
+    directory: &Path,
+    progress: &AutosaveProgress,
+    results: &Results,
+    compression_level: i32,
+) -> Result<()> {
+    debug!(
+        "Autosaving epoch {} to {}",
+        progress.epoch,
+        directory.display()
+    );
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Failed to create directory: {}", directory.display()))?;
+    write_file(
+        &directory.join(AUTOSAVE_FILE),
+        compression_level,
+        |writer| {
+            bincode::serde::encode_into_std_write(
+                AutosaveRef { progress, results },
+                writer,
+                bincode::config::standard(),
+            )
+            .context("Failed to serialize autosave to binary format")?;
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+/// Returns the autosave file in the scenario directory, if there is one.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn find(directory: &Path) -> Option<PathBuf> {
+    existing_file(&directory.join(AUTOSAVE_FILE))
+}
+
+/// Reads the progress stored in the autosave in the scenario directory.
+///
+/// # Errors
+///
+/// Returns an error if there is no autosave or it cannot be read.
+#[tracing::instrument(level = "debug")]
+pub fn load_progress(directory: &Path) -> Result<AutosaveProgress> {
+    debug!("Loading autosave progress from {}", directory.display());
+    let mut reader = open_autosave(directory)?;
+    bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+        .context("Failed to deserialize autosave progress")
+}
+
+/// Reads the progress and the results stored in the autosave in the
+/// scenario directory.
+///
+/// # Errors
+///
+/// Returns an error if there is no autosave or it cannot be read.
+#[tracing::instrument(level = "debug")]
+pub fn load(directory: &Path) -> Result<(AutosaveProgress, Results)> {
+    debug!("Loading autosave from {}", directory.display());
+    let mut reader = open_autosave(directory)?;
+    let autosave: Autosave =
+        bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+            .context("Failed to deserialize autosave")?;
+    Ok((autosave.progress, autosave.results))
+}
+
+/// Removes the autosave and a temporary file left behind by an interrupted
+/// autosave from the scenario directory.
+///
+/// # Errors
+///
+/// Returns an error if a file exists but cannot be removed.
+#[tracing::instrument(level = "debug")]
+pub fn remove(directory: &Path) -> Result<()> {
+    debug!("Removing autosave from {}", directory.display());
+    let path = directory.join(AUTOSAVE_FILE);
+    let compressed = compressed_path(&path);
+    for path in [
+        temporary_path(&compressed),
+        compressed,
+        temporary_path(&path),
+        path,
+    ] {
+        if path.is_file() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove autosave: {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "trace")]
+fn open_autosave(directory: &Path) -> Result<Box<dyn std::io::Read>> {
+    let path =
+        find(directory).with_context(|| format!("No autosave found in {}", directory.display()))?;
+    open_reader(&path).context("Failed to open autosave")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autosave_replaces_previous_and_reads_back() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_autosave");
+        let results = Results::get_default();
+        let mut progress = AutosaveProgress {
+            epoch: 9,
+            batch_index: 10,
+            last_update: Some(Utc::now()),
+            summary: Summary::default(),
+        };
+        save(&directory, &progress, &results, 0)?;
+        progress.epoch = 19;
+        progress.batch_index = 20;
+        save(&directory, &progress, &results, 3)?;
+
+        assert_eq!(
+            find(&directory),
+            Some(compressed_path(&directory.join(AUTOSAVE_FILE)))
+        );
+        assert!(!directory.join(AUTOSAVE_FILE).exists());
+        assert!(!temporary_path(&find(&directory).context("Autosave should exist")?).exists());
+        assert_eq!(load_progress(&directory)?, progress);
+        let (loaded_progress, loaded_results) = load(&directory)?;
+        assert_eq!(loaded_progress, progress);
+        assert_eq!(loaded_results, results);
+
+        remove(&directory)?;
+        assert_eq!(find(&directory), None);
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
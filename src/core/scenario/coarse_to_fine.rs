@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use tracing::info;
+
+use super::ensemble::estimate;
+use crate::core::{
+    config::algorithm::{Algorithm, AlgorithmType},
+    data::Data,
+    model::Model,
+};
+
+/// Estimates the all-pass parameters on a coarsened voxel grid and upsamples
+/// them onto the voxel grid of `model`.
+///
+/// The coarse model is created from the model config of the algorithm with the voxel size
+/// multiplied by the coarsening factor and always runs on the CPU. The
+/// coarse stage does not apply the freeze schedule.
+///
+/// # Errors
+///
+/// Returns an error if the coarsening factor is not larger than one, the
+/// coarse model cannot be created or its estimation fails.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn run(
+    model: &mut Model,
+    data: &Data,
+    algorithm: &Algorithm,
+    sample_rate_hz: f32,
+    duration_s: f32,
+) -> Result<()> {
+    let settings = &algorithm.coarse_to_fine;
+    anyhow::ensure!(
+        settings.coarsening_factor > 1.0,
+        "The coarsening factor has to be larger than one, got {}",
+        settings.coarsening_factor
+    );
+    info!(
+        "Running {} coarse epochs with {} times the voxel size",
+        settings.coarse_epochs, settings.coarsening_factor
+    );
+
+    let mut coarse_config = algorithm.model.clone();
+    coarse_config.common.voxel_size_mm *= settings.coarsening_factor;
+    let mut coarse_model = Model::from_model_config(&coarse_config, sample_rate_hz, duration_s)
+        .context("Failed to create coarse model")?;
+    coarse_model.synchronize_parameters(data);
+
+    let mut coarse_algorithm = algorithm.clone();
+    coarse_algorithm.algorithm_type = AlgorithmType::ModelBased;
+    coarse_algorithm.epochs = settings.coarse_epochs;
+    let results = estimate(coarse_model, data, &coarse_algorithm)
+        .context("Failed to estimate coarse model")?;
+    let coarse_model = results
+        .model
+        .as_ref()
+        .context("Model should be set after coarse estimation")?;
+
+    model.functional_description.ap_params.resample_from(
+        &model.spatial_description,
+        &coarse_model.functional_description.ap_params,
+        &coarse_model.spatial_description,
+    )
+}
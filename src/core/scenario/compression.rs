@@ -0,0 +1,283 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use tracing::trace;
+
+/// Extension appended to the name of zstd compressed files.
+pub const COMPRESSED_EXTENSION: &str = "zst";
+
+/// Returns the path of the compressed version of the given file, e.g.
+/// `data.bin.zst` for `data.bin`.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(COMPRESSED_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Returns true if the file is zstd compressed, judging by its extension.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == COMPRESSED_EXTENSION)
+}
+
+/// Returns the given file if it exists, otherwise its compressed version if
+/// that exists.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn existing_file(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let compressed = compressed_path(path);
+    compressed.is_file().then_some(compressed)
+}
+
+/// Opens the file for reading, decompressing it if it is zstd compressed.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened.
+#[tracing::instrument(level = "trace")]
+pub fn open_reader(path: &Path) -> Result<Box<dyn Read>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
+    if is_compressed(path) {
+        Ok(Box::new(
+            zstd::Decoder::new(file).context("Failed to create zstd decoder")?,
+        ))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Extension appended to files while they are written.
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+/// Returns the path a file is written to before it replaces the file at the
+/// given path, e.g. `scenario.toml.tmp` for `scenario.toml`.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(TEMPORARY_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Writes the file at `path` with the given function, zstd compressed into
+/// `{path}.zst` if the level is positive.
+///
+/// The file is replaced atomically, see [`write_atomically`]. The variant
+/// that is not written is removed, so loading never picks up stale
+/// contents. Returns the path of the written file and the value returned by
+/// `write`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be created, `write` fails or the
+/// stale variant cannot be removed.
+#[tracing::instrument(level = "trace", skip(write))]
+pub fn write_file<T>(
+    path: &Path,
+    level: i32,
+    write: impl FnOnce(&mut dyn Write) -> Result<T>,
+) -> Result<(PathBuf, T)> {
+    let compressed = compressed_path(path);
+    let (written, stale) = if level > 0 {
+        (compressed, path.to_path_buf())
+    } else {
+        (path.to_path_buf(), compressed)
+    };
+    let value = write_atomically(&written, |file| {
+        if level > 0 {
+            let mut encoder =
+                zstd::Encoder::new(file, level).context("Failed to create zstd encoder")?;
+            let value = write(&mut encoder)?;
+            let file = encoder.finish().context("Failed to finish zstd stream")?;
+            Ok((file, value))
+        } else {
+            let mut writer = BufWriter::new(file);
+            let value = write(&mut writer)?;
+            let file = writer
+                .into_inner()
+                .map_err(io::IntoInnerError::into_error)
+                .with_context(|| format!("Failed to write file: {}", written.display()))?;
+            Ok((file, value))
+        }
+    })?;
+    if stale.is_file() {
+        fs::remove_file(&stale)
+            .with_context(|| format!("Failed to remove stale file: {}", stale.display()))?;
+    }
+    Ok((written, value))
+}
+
+/// Writes the contents to the file at `path`, replacing it atomically.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+#[tracing::instrument(level = "trace", skip(contents))]
+pub fn write_bytes(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomically(path, |mut file| {
+        file.write_all(contents)
+            .with_context(|| format!("Failed to write file: {}", path.display()))?;
+        Ok((file, ()))
+    })
+}
+
+/// Writes the file at `path` with the given function through a temporary
+/// file, which is flushed to disk and then renamed to `path`.
+///
+/// A crash while writing leaves the previous file intact, at worst together
+/// with a partial temporary file that is overwritten by the next write. The
+/// temporary file is removed if `write` fails.
+///
+/// # Errors
+///
+/// Returns an error if the temporary file cannot be created, flushed or
+/// renamed, or `write` fails.
+#[tracing::instrument(level = "trace", skip(write))]
+fn write_atomically<T>(path: &Path, write: impl FnOnce(File) -> Result<(File, T)>) -> Result<T> {
+    let temporary = temporary_path(path);
+    let result = File::create(&temporary)
+        .with_context(|| format!("Failed to create file: {}", temporary.display()))
+        .and_then(write)
+        .and_then(|(file, value)| {
+            file.sync_all()
+                .with_context(|| format!("Failed to flush file: {}", temporary.display()))?;
+            fs::rename(&temporary, path).with_context(|| {
+                format!(
+                    "Failed to move {} to {}",
+                    temporary.display(),
+                    path.display()
+                )
+            })?;
+            Ok(value)
+        });
+    if result.is_err() && temporary.is_file() {
+        if let Err(e) = fs::remove_file(&temporary) {
+            trace!("Failed to remove {}: {}", temporary.display(), e);
+        }
+    }
+    result
+}
+
+/// Writes a zstd compressed copy of `source` to `target` and returns the
+/// size of the compressed file in bytes.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be read or the target cannot be
+/// written.
+#[tracing::instrument(level = "trace")]
+pub fn compress_file(source: &Path, target: &Path, level: i32) -> Result<u64> {
+    trace!("Compressing {} to {}", source.display(), target.display());
+    let mut reader = BufReader::new(
+        File::open(source).with_context(|| format!("Failed to open file: {}", source.display()))?,
+    );
+    let file = File::create(target)
+        .with_context(|| format!("Failed to create file: {}", target.display()))?;
+    let mut encoder = zstd::Encoder::new(file, level).context("Failed to create zstd encoder")?;
+    io::copy(&mut reader, &mut encoder)
+        .with_context(|| format!("Failed to compress {}", source.display()))?;
+    encoder.finish().context("Failed to finish zstd stream")?;
+    Ok(fs::metadata(target)?.len())
+}
+
+/// Returns the total size of all files below the given path in bytes.
+///
+/// Entries that cannot be read are counted as empty.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn disk_usage_bytes(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path).map_or(0, |entries| {
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| disk_usage_bytes(&entry.path()))
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compressed_file_reads_back() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_compression");
+        fs::create_dir_all(&directory)?;
+        let source = directory.join("data.bin");
+        let content = vec![7_u8; 4096];
+        fs::write(&source, &content)?;
+
+        let target = compressed_path(&source);
+        let size = compress_file(&source, &target, 3)?;
+        fs::remove_file(&source)?;
+
+        assert!(size < 4096);
+        assert_eq!(existing_file(&source), Some(target.clone()));
+        let mut read = Vec::new();
+        open_reader(&target)?.read_to_end(&mut read)?;
+        assert_eq!(read, content);
+        assert_eq!(disk_usage_bytes(&directory), size);
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn writing_removes_stale_variant() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_write_file");
+        fs::create_dir_all(&directory)?;
+        let path = directory.join("results.bin");
+
+        write_file(&path, 0, |writer| Ok(writer.write_all(b"plain")?))?;
+        let (written, ()) = write_file(&path, 3, |writer| Ok(writer.write_all(b"packed")?))?;
+
+        assert_eq!(written, compressed_path(&path));
+        assert!(!path.exists());
+        let mut read = String::new();
+        open_reader(&written)?.read_to_string(&mut read)?;
+        assert_eq!(read, "packed");
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn failed_write_keeps_previous_file() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_partial_write");
+        fs::create_dir_all(&directory)?;
+        let path = directory.join("data.bin");
+        write_file(&path, 0, |writer| Ok(writer.write_all(b"complete")?))?;
+
+        // the write is interrupted after part of the new contents
+        let result = write_file(&path, 0, |writer| {
+            writer.write_all(b"part")?;
+            anyhow::bail!("interrupted")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path)?, b"complete");
+        assert!(!temporary_path(&path).exists());
+
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use anyhow::{Context, Result};
+use ndarray::Array1;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use super::{calculate_plotting_arrays, results::Results};
+use crate::core::{
+    algorithm::{
+        calculate_pseudo_inverse, inverse::run_inverse_solver, kalman::run_kalman_filter,
+        refinement::update::UpdateMask, run_epoch,
+    },
+    config::algorithm::{Algorithm, AlgorithmType},
+    data::Data,
+    model::{functional::allpass::shapes::ActivationTimeMs, spatial::voxels::VoxelNumbers, Model},
+};
+
+/// Per-voxel spread of the estimations of an ensemble.
+///
+/// Both maps hold the standard deviation over the members. Voxels without
+/// a value in any member are `None`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct EnsembleStatistics {
+    pub number_of_members: usize,
+    pub activation_time_std_ms: ActivationTimeMs,
+    pub current_density_std: ActivationTimeMs,
+}
+
+impl EnsembleStatistics {
+    /// Creates the statistics from the activation time and current density
+    /// maps of the members.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no members or the maps differ in shape.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_members(
+        activation_times_ms: &[ActivationTimeMs],
+        current_densities: &[ActivationTimeMs],
+    ) -> Result<Self> {
+        debug!("Creating ensemble statistics");
+        anyhow::ensure!(
+            activation_times_ms.len() == current_densities.len(),
+            "Every ensemble member needs an activation time and a current density map"
+        );
+        Ok(Self {
+            number_of_members: activation_times_ms.len(),
+            activation_time_std_ms: standard_deviation(activation_times_ms)?,
+            current_density_std: standard_deviation(current_densities)?,
+        })
+    }
+}
+
+/// Repeats the estimation with the ensemble settings of the algorithm and
+/// returns the spread of the activation times and current densities.
+///
+/// Every member starts from `initial_model` with perturbed gains and uses
+/// its own noise realization of the measurements. The model-based members
+/// always run on the CPU and do not apply the freeze schedule.
+///
+/// # Errors
+///
+/// Returns an error if the ensemble settings are invalid or a member fails.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn run(
+    initial_model: &Model,
+    data: &Data,
+    algorithm: &Algorithm,
+) -> Result<EnsembleStatistics> {
+    let config = &algorithm.ensemble;
+    info!("Running ensemble with {} members", config.number_of_members);
+    let gain_dist = Normal::new(1.0, config.initial_gain_std)
+        .context("Invalid initial gain deviation of the ensemble")?;
+    let noise_dist = Normal::new(0.0, config.measurement_noise_std)
+        .context("Invalid measurement noise deviation of the ensemble")?;
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+
+    let mut member_data = data.clone();
+    let mut activation_times_ms = Vec::with_capacity(config.number_of_members);
+    let mut current_densities = Vec::with_capacity(config.number_of_members);
+    for member in 0..config.number_of_members {
+        let mut model = initial_model.clone();
+        model
+            .functional_description
+            .ap_params
+            .gains
+            .mapv_inplace(|gain| gain * gain_dist.sample(&mut rng));
+        member_data
+            .simulation
+            .measurements
+            .assign(&*data.simulation.measurements);
+        member_data
+            .simulation
+            .measurements
+            .mapv_inplace(|measurement| measurement + noise_dist.sample(&mut rng));
+
+        let results = estimate(model, &member_data, algorithm)
+            .with_context(|| format!("Failed to estimate ensemble member {member}"))?;
+        let model = results
+            .model
+            .as_ref()
+            .context("Model should be set after ensemble member estimation")?;
+        activation_times_ms.push(
+            model
+                .functional_description
+                .ap_params
+                .activation_time_ms
+                .clone(),
+        );
+        current_densities.push(voxel_map(
+            &results.estimations.system_states_spherical_max.magnitude,
+            &model.spatial_description.voxels.numbers,
+        ));
+        debug!("Finished ensemble member {member}");
+    }
+    EnsembleStatistics::from_members(&activation_times_ms, &current_densities)
+}
+
+/// Runs the algorithm on the given model and data and returns the results
+/// with the plotting arrays calculated.
+#[tracing::instrument(level = "debug", skip_all)]
+pub(super) fn estimate(model: Model, data: &Data, algorithm: &Algorithm) -> Result<Results> {
+    debug!("Estimating model");
+    let mut results = Results::new(
+        algorithm.epochs,
+        model.functional_description.control_function_values.shape()[0],
+        model.spatial_description.sensors.count(),
+        model.spatial_description.voxels.count_states(),
+        model.spatial_description.sensors.count_beats(),
+        0,
+        algorithm.batch_size,
+        algorithm.optimizer,
+        model
+            .functional_description
+            .ap_params
+            .neighborhood_radius()?,
+    );
+    results
+        .estimations
+        .channel_mask
+        .exclude(&algorithm.excluded_channels)
+        .context("Failed to exclude channels - invalid channel index")?;
+    if algorithm.mask_bad_channels {
+        results
+            .estimations
+            .channel_mask
+            .exclude(&data.simulation.sensor_faults.bad_channels())
+            .context("Failed to exclude faulty channels of the simulation")?;
+    }
+    results.derivatives.update_mask =
+        UpdateMask::from_config(algorithm, &model.spatial_description)
+            .context("Failed to create the mask of the optimization region")?;
+    match algorithm.algorithm_type {
+        AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+            results.model = Some(model);
+            let mut config = algorithm.clone();
+            let mut learning_rate = algorithm.learning_rate;
+            let mut batch_index = 0;
+            for epoch_index in 0..algorithm.epochs {
+                if epoch_index > 0
+                    && algorithm.learning_rate_reduction_interval != 0
+                    && epoch_index % algorithm.learning_rate_reduction_interval == 0
+                {
+                    learning_rate *= algorithm.learning_rate_reduction_factor;
+                }
+                config.learning_rate = if epoch_index == 0 { 0.0 } else { learning_rate };
+                run_epoch(&mut results, &mut batch_index, data, &config)?;
+            }
+        }
+        AlgorithmType::PseudoInverse => {
+            calculate_pseudo_inverse(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::KalmanFilter => {
+            run_kalman_filter(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::MinimumNorm | AlgorithmType::SLoreta | AlgorithmType::ELoreta => {
+            run_inverse_solver(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::None => {
+            anyhow::bail!("Scenarios without an algorithm have no ensemble member")
+        }
+    }
+    calculate_plotting_arrays(&mut results, data)?;
+    Ok(results)
+}
+
+/// Places the per-voxel values onto the voxel grid.
+#[tracing::instrument(level = "trace", skip_all)]
+fn voxel_map(values: &Array1<f32>, numbers: &VoxelNumbers) -> ActivationTimeMs {
+    let mut map = ActivationTimeMs::empty(numbers.raw_dim());
+    map.iter_mut()
+        .zip(numbers.iter())
+        .for_each(|(value, number)| *value = number.map(|number| values[number / 3]));
+    map
+}
+
+/// Calculates the standard deviation of every voxel over the given maps,
+/// ignoring the maps without a value at that voxel.
+///
+/// # Errors
+///
+/// Returns an error if no maps are given or they differ in shape.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn standard_deviation(maps: &[ActivationTimeMs]) -> Result<ActivationTimeMs> {
+    let first = maps.first().context("Ensemble has no members")?;
+    anyhow::ensure!(
+        maps.iter().all(|map| map.shape() == first.shape()),
+        "Ensemble members have different voxel grids"
+    );
+    let mut deviation = ActivationTimeMs::empty(first.raw_dim());
+    for (index, value) in deviation.indexed_iter_mut() {
+        let samples: Vec<f32> = maps.iter().filter_map(|map| map[index]).collect();
+        if samples.is_empty() {
+            continue;
+        }
+        let count = samples.len() as f32;
+        let mean = samples.iter().sum::<f32>() / count;
+        let variance = samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f32>()
+            / count;
+        *value = Some(variance.sqrt());
+    }
+    Ok(deviation)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::Dim;
+
+    use super::*;
+
+    #[test]
+    fn standard_deviation_ignores_missing_values() -> anyhow::Result<()> {
+        let mut first = ActivationTimeMs::empty(Dim([2, 1, 1]));
+        let mut second = ActivationTimeMs::empty(Dim([2, 1, 1]));
+        first[(0, 0, 0)] = Some(10.0);
+        second[(0, 0, 0)] = Some(20.0);
+        second[(1, 0, 0)] = Some(5.0);
+
+        let std = standard_deviation(&[first, second])?;
+
+        assert_relative_eq!(std[(0, 0, 0)].expect("Voxel to have a value"), 5.0);
+        assert_relative_eq!(std[(1, 0, 0)].expect("Voxel to have a value"), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn standard_deviation_without_members_is_an_error() {
+        assert!(standard_deviation(&[]).is_err());
+    }
+
+    #[test]
+    fn voxel_map_follows_voxel_numbers() {
+        let mut numbers = VoxelNumbers::empty([2, 1, 1]);
+        numbers[(1, 0, 0)] = Some(3);
+        let values = Array1::from_vec(vec![1.0, 2.0]);
+
+        let map = voxel_map(&values, &numbers);
+
+        assert_eq!(map[(0, 0, 0)], None);
+        assert_eq!(map[(1, 0, 0)], Some(2.0));
+    }
+}
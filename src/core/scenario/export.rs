@@ -0,0 +1,201 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use tracing::debug;
+
+use super::{summary::Summary, Scenario};
+
+const SUMMARY_CSV_HEADER: [&str; 23] = [
+    "id",
+    "status",
+    "algorithm_type",
+    "epochs",
+    "learning_rate",
+    "voxel_size_mm",
+    "loss",
+    "loss_mse",
+    "loss_maximum_regularization",
+    "dice",
+    "iou",
+    "precision",
+    "recall",
+    "threshold",
+    "roc_auc",
+    "precision_recall_auc",
+    "activation_time_mean_absolute_error_ms",
+    "activation_time_bias_ms",
+    "activation_time_std_ms",
+    "localization_error_mm",
+    "localization_extent_error_mm3",
+    "velocities_mean_m_per_s",
+    "gpu_memory_bytes",
+];
+
+/// Writes one row per scenario with its id, the key config fields and all
+/// summary metrics to a .csv file at the given path.
+///
+/// Metrics of scenarios without a summary, activation time statistics or
+/// localization are left empty. The mean velocities are written as
+/// `<voxel type>:<mean>` pairs separated by semicolons.
+///
+/// # Errors
+///
+/// Returns an error if directory or file creation, or writing fails.
+#[tracing::instrument(level = "debug", skip(scenarios))]
+pub fn save_summary_csv<'a>(
+    scenarios: impl IntoIterator<Item = &'a Scenario>,
+    path: &Path,
+) -> Result<()> {
+    debug!("Saving summary csv");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let mut writer = BufWriter::new(
+        File::create(path).with_context(|| format!("Failed to create file: {}", path.display()))?,
+    );
+    writeln!(writer, "{}", SUMMARY_CSV_HEADER.join(","))?;
+    for scenario in scenarios {
+        let row: Vec<String> = summary_csv_row(scenario)
+            .iter()
+            .map(|field| escape_csv_field(field))
+            .collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write file: {}", path.display()))?;
+    Ok(())
+}
+
+/// Returns the fields of the summary csv row of the given scenario, in the
+/// order of [`SUMMARY_CSV_HEADER`].
+#[tracing::instrument(level = "trace", skip_all)]
+fn summary_csv_row(scenario: &Scenario) -> Vec<String> {
+    let config = &scenario.config;
+    let mut row = vec![
+        scenario.get_id().clone(),
+        scenario.get_status_str(),
+        format!("{:?}", config.algorithm.algorithm_type),
+        config.algorithm.epochs.to_string(),
+        config.algorithm.learning_rate.to_string(),
+        config.simulation.model.common.voxel_size_mm.to_string(),
+    ];
+    match &scenario.summary {
+        Some(summary) => row.extend(summary_csv_fields(summary)),
+        None => row.resize(SUMMARY_CSV_HEADER.len(), String::new()),
+    }
+    row
+}
+
+/// Quotes the field if it contains a separator, a quote or a line break,
+/// e.g. the status "Done (1m, 5s)".
+#[tracing::instrument(level = "trace")]
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Returns the metric fields of the summary csv row.
+#[tracing::instrument(level = "trace", skip_all)]
+fn summary_csv_fields(summary: &Summary) -> Vec<String> {
+    let optional = |value: Option<f32>| value.map_or_else(String::new, |value| value.to_string());
+    let velocities = summary
+        .velocities
+        .iter()
+        .map(|velocity| format!("{:?}:{}", velocity.voxel_type, velocity.mean_m_per_s))
+        .collect::<Vec<String>>()
+        .join(";");
+    vec![
+        summary.loss.to_string(),
+        summary.loss_mse.to_string(),
+        summary.loss_maximum_regularization.to_string(),
+        summary.dice.to_string(),
+        summary.iou.to_string(),
+        summary.precision.to_string(),
+        summary.recall.to_string(),
+        summary.threshold.to_string(),
+        summary.roc_auc.to_string(),
+        summary.precision_recall_auc.to_string(),
+        optional(
+            summary
+                .activation_time
+                .as_ref()
+                .map(|statistics| statistics.mean_absolute_error_ms),
+        ),
+        optional(
+            summary
+                .activation_time
+                .as_ref()
+                .map(|statistics| statistics.bias_ms),
+        ),
+        optional(
+            summary
+                .activation_time
+                .as_ref()
+                .map(|statistics| statistics.std_ms),
+        ),
+        optional(
+            summary
+                .localization
+                .as_ref()
+                .map(|localization| localization.error_mm),
+        ),
+        optional(
+            summary
+                .localization
+                .as_ref()
+                .map(|localization| localization.extent_error_mm3),
+        ),
+        velocities,
+        summary.gpu_memory_bytes.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::{clean_files, setup_folder};
+
+    const COMMON_PATH: &str = "tests/core/scenario/export";
+
+    #[test]
+    fn summary_csv_has_one_row_per_scenario() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("summary.csv")];
+        clean_files(&files)?;
+
+        let without_summary = Scenario::empty();
+        let mut with_summary = Scenario::empty();
+        with_summary.summary = Some(Summary {
+            dice: 0.5,
+            ..Summary::default()
+        });
+
+        save_summary_csv([&without_summary, &with_summary], &files[0])?;
+
+        let content = fs::read_to_string(&files[0])?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        for line in &lines {
+            assert_eq!(line.split(',').count(), SUMMARY_CSV_HEADER.len());
+        }
+        assert_eq!(lines[2].split(',').nth(9), Some("0.5"));
+        Ok(())
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        assert_eq!(escape_csv_field("Done (1m, 5s)"), "\"Done (1m, 5s)\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_csv_field("0.5"), "0.5");
+    }
+}
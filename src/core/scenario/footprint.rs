@@ -0,0 +1,386 @@
+use std::mem::size_of;
+
+use anyhow::{Context, Result};
+use tracing::{debug, trace};
+
+use crate::core::{
+    algorithm::{gpu::GPU, refinement::Optimizer},
+    config::{algorithm::AlgorithmType, model::Model as ModelConfig, Config},
+    model::{functional::allpass::number_of_neighbors, spatial::SpatialDescription},
+};
+
+const F32_BYTES: usize = size_of::<f32>();
+
+/// Dimensions of a model as they determine the size of its arrays.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ModelDimensions {
+    pub number_of_states: usize,
+    pub number_of_sensors: usize,
+    pub number_of_beats: usize,
+    pub number_of_array_positions: usize,
+    pub number_of_steps: usize,
+    pub neighborhood_radius: usize,
+}
+
+impl ModelDimensions {
+    /// Builds the spatial description of the model to count its states and
+    /// sensors. The functional description, which holds the large arrays,
+    /// is not built.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spatial description cannot be built, e.g.
+    /// because the MRI segmentation cannot be read.
+    #[tracing::instrument(level = "debug", skip(config))]
+    pub fn from_model_config(config: &ModelConfig, number_of_steps: usize) -> Result<Self> {
+        debug!("Calculating model dimensions");
+        let spatial_description = SpatialDescription::from_model_config(config)
+            .context("Failed to build spatial description for the footprint estimate")?;
+        Ok(Self {
+            number_of_states: spatial_description.voxels.count_states(),
+            number_of_sensors: spatial_description.sensors.count(),
+            number_of_beats: spatial_description.sensors.count_beats(),
+            number_of_array_positions: spatial_description.sensors.count_array_positions(),
+            number_of_steps,
+            neighborhood_radius: config.common.neighborhood_radius,
+        })
+    }
+
+    /// Number of elements of the all-pass gains and their output indices.
+    #[must_use]
+    const fn gain_elements(&self) -> usize {
+        self.number_of_states * number_of_neighbors(self.neighborhood_radius) * 3
+    }
+
+    /// Number of elements of the all-pass coefficients and delays.
+    #[must_use]
+    const fn coef_elements(&self) -> usize {
+        self.number_of_states / 3 * number_of_neighbors(self.neighborhood_radius)
+    }
+
+    /// Number of elements of the measurement matrix.
+    #[must_use]
+    const fn measurement_matrix_elements(&self) -> usize {
+        self.number_of_array_positions * self.number_of_sensors * self.number_of_states
+    }
+
+    /// Number of elements of the system states over time.
+    #[must_use]
+    const fn system_state_elements(&self) -> usize {
+        self.number_of_steps * self.number_of_states
+    }
+
+    /// Number of elements of the measurements of all beats.
+    #[must_use]
+    const fn measurement_elements(&self) -> usize {
+        self.number_of_beats * self.number_of_steps * self.number_of_sensors
+    }
+
+    /// Bytes of the functional description of the model in host memory.
+    #[must_use]
+    const fn model_bytes(&self) -> usize {
+        self.gain_elements() * (F32_BYTES + size_of::<Option<usize>>())
+            + self.coef_elements() * (2 * F32_BYTES + size_of::<usize>())
+            + self.measurement_matrix_elements() * F32_BYTES
+            + (self.number_of_states
+                + self.number_of_sensors * self.number_of_sensors
+                + self.number_of_steps)
+                * F32_BYTES
+    }
+
+    /// Bytes of the estimated states, measurements and all-pass outputs.
+    /// The spherical states are as large as the states themselves.
+    #[must_use]
+    const fn estimation_bytes(&self) -> usize {
+        (2 * self.gain_elements() + 2 * self.system_state_elements() + self.measurement_elements())
+            * F32_BYTES
+    }
+
+    /// Bytes of the gradients and, for Adam, the moment estimates.
+    #[must_use]
+    const fn derivative_bytes(&self, optimizer: Optimizer) -> usize {
+        let moments = match optimizer {
+            Optimizer::Sgd => 0,
+            Optimizer::Adam => 2 * (self.gain_elements() + self.coef_elements()),
+        };
+        (3 * self.gain_elements() + self.coef_elements() + moments + 2 * self.number_of_states)
+            * F32_BYTES
+    }
+
+    /// Bytes of a single snapshot of the parameters and estimations.
+    #[must_use]
+    const fn snapshot_bytes(&self) -> usize {
+        (self.gain_elements()
+            + 2 * self.coef_elements()
+            + self.system_state_elements()
+            + self.measurement_elements())
+            * F32_BYTES
+    }
+}
+
+/// Estimated size and cost of a scenario, calculated from its configuration
+/// before it is scheduled.
+///
+/// The memory estimates cover the large arrays of the model, the simulated
+/// data and the algorithm and ignore everything that does not scale with
+/// the number of states, steps or sensors. They are meant to spot scenarios
+/// that cannot fit, not to predict the exact usage.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Footprint {
+    pub number_of_states: usize,
+    pub number_of_sensors: usize,
+    pub number_of_steps: usize,
+    pub number_of_beats: usize,
+    pub measurement_matrix_bytes: usize,
+    // floating point operations of the forward and backward pass of one epoch
+    pub flops_per_epoch: f64,
+    // peak host memory of data generation and estimation together
+    pub ram_bytes: usize,
+    // device memory, only set for the model-based GPU algorithm
+    pub vram_bytes: Option<usize>,
+}
+
+impl Footprint {
+    /// Estimates the footprint of a scenario with the given configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the spatial description of the simulation or
+    /// the algorithm model cannot be built.
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn estimate(config: &Config) -> Result<Self> {
+        debug!("Estimating scenario footprint");
+        let number_of_steps =
+            (config.simulation.duration_s * config.simulation.sample_rate_hz) as usize;
+        let simulation =
+            ModelDimensions::from_model_config(&config.simulation.model, number_of_steps)?;
+        let algorithm =
+            ModelDimensions::from_model_config(&config.algorithm.model, number_of_steps)?;
+        let algorithm_config = &config.algorithm;
+
+        let data_bytes = simulation.model_bytes()
+            + simulation.estimation_bytes()
+            + (2 * simulation.system_state_elements() + simulation.measurement_elements())
+                * F32_BYTES;
+
+        let number_of_snapshots = if algorithm_config.snapshots_interval == 0 {
+            0
+        } else {
+            algorithm_config.epochs / algorithm_config.snapshots_interval + 1
+        };
+        let batch_size = if algorithm_config.batch_size > 0 {
+            algorithm_config.batch_size
+        } else {
+            algorithm.number_of_beats
+        };
+        let number_of_batches = algorithm.number_of_beats.div_ceil(batch_size.max(1));
+        let metrics_bytes = 3 * algorithm_config.epochs * number_of_batches * F32_BYTES;
+
+        let algorithm_bytes = match algorithm_config.algorithm_type {
+            AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+                algorithm.derivative_bytes(algorithm_config.optimizer)
+                    + number_of_snapshots * algorithm.snapshot_bytes()
+                    + metrics_bytes
+            }
+            // the solvers of the remaining algorithms work on matrices of
+            // the size of the measurement matrix
+            AlgorithmType::PseudoInverse
+            | AlgorithmType::KalmanFilter
+            | AlgorithmType::MinimumNorm
+            | AlgorithmType::SLoreta
+            | AlgorithmType::ELoreta => 2 * algorithm.measurement_matrix_elements() * F32_BYTES,
+            AlgorithmType::None => 0,
+        };
+        // without an algorithm only the data is simulated
+        let ram_bytes = if algorithm_config.algorithm_type == AlgorithmType::None {
+            data_bytes
+        } else {
+            data_bytes + algorithm.model_bytes() + algorithm.estimation_bytes() + algorithm_bytes
+        };
+
+        let vram_bytes =
+            (algorithm_config.algorithm_type == AlgorithmType::ModelBasedGPU).then(|| {
+                // the GPU stores the output indices and delays as i32
+                algorithm.model_bytes()
+                    - algorithm.gain_elements() * (size_of::<Option<usize>>() - F32_BYTES)
+                    - algorithm.coef_elements() * (size_of::<usize>() - F32_BYTES)
+                    + algorithm.estimation_bytes()
+                    + algorithm.derivative_bytes(algorithm_config.optimizer)
+                    + algorithm.measurement_elements() * F32_BYTES
+            });
+
+        // a multiply and an add per all-pass connection and measurement
+        // matrix element in every step, the backward pass costs about twice
+        // as much as the forward pass
+        let flops_per_step = 2.0
+            * (algorithm.gain_elements() + algorithm.number_of_sensors * algorithm.number_of_states)
+                as f64;
+        let flops_per_epoch =
+            3.0 * flops_per_step * (algorithm.number_of_beats * algorithm.number_of_steps) as f64;
+
+        Ok(Self {
+            number_of_states: algorithm.number_of_states,
+            number_of_sensors: algorithm.number_of_sensors,
+            number_of_steps,
+            number_of_beats: algorithm.number_of_beats,
+            measurement_matrix_bytes: algorithm.measurement_matrix_elements() * F32_BYTES,
+            flops_per_epoch,
+            ram_bytes,
+            vram_bytes,
+        })
+    }
+
+    /// Returns a warning for every estimate that exceeds the given system
+    /// resources.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    #[tracing::instrument(level = "trace")]
+    pub fn warnings(&self, resources: &SystemResources) -> Vec<String> {
+        trace!("Checking footprint against system resources");
+        let mut warnings = Vec::new();
+        if let Some(ram_bytes) = resources.ram_bytes {
+            if self.ram_bytes as u64 > ram_bytes {
+                warnings.push(format!(
+                    "The scenario needs about {} of memory, but the system only has {}.",
+                    format_bytes(self.ram_bytes as u64),
+                    format_bytes(ram_bytes)
+                ));
+            }
+        }
+        if let (Some(vram_bytes), Some(available)) = (self.vram_bytes, resources.vram_bytes) {
+            if vram_bytes as u64 > available {
+                warnings.push(format!(
+                    "The scenario needs about {} of GPU memory, but the GPU only has {}.",
+                    format_bytes(vram_bytes as u64),
+                    format_bytes(available)
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Memory of the machine the application runs on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct SystemResources {
+    // None if the memory cannot be determined on this platform
+    pub ram_bytes: Option<u64>,
+    // None if no GPU is available
+    pub vram_bytes: Option<u64>,
+}
+
+impl SystemResources {
+    /// Queries the total memory of the system and the global memory of the
+    /// GPU.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn detect() -> Self {
+        debug!("Detecting system resources");
+        Self {
+            ram_bytes: total_ram_bytes(),
+            vram_bytes: GPU::global_memory_bytes(),
+        }
+    }
+}
+
+/// Reads the total memory from /proc/meminfo.
+#[cfg(target_os = "linux")]
+#[tracing::instrument(level = "trace")]
+fn total_ram_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let kilobytes = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+#[tracing::instrument(level = "trace")]
+fn total_ram_bytes() -> Option<u64> {
+    None
+}
+
+/// Formats a number of bytes with a binary unit, e.g. "1.5 GiB".
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace")]
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dimensions() -> ModelDimensions {
+        ModelDimensions {
+            number_of_states: 300,
+            number_of_sensors: 10,
+            number_of_beats: 2,
+            number_of_array_positions: 2,
+            number_of_steps: 100,
+            neighborhood_radius: 1,
+        }
+    }
+
+    #[test]
+    fn array_sizes_follow_dimensions() {
+        let dimensions = dimensions();
+        assert_eq!(dimensions.gain_elements(), 300 * 26 * 3);
+        assert_eq!(dimensions.coef_elements(), 100 * 26);
+        assert_eq!(dimensions.measurement_matrix_elements(), 2 * 10 * 300);
+        assert!(
+            dimensions.derivative_bytes(Optimizer::Adam)
+                > dimensions.derivative_bytes(Optimizer::Sgd)
+        );
+    }
+
+    #[test]
+    fn warns_when_resources_are_exceeded() {
+        let footprint = Footprint {
+            number_of_states: 300,
+            number_of_sensors: 10,
+            number_of_steps: 100,
+            number_of_beats: 2,
+            measurement_matrix_bytes: 24_000,
+            flops_per_epoch: 1e6,
+            ram_bytes: 2048,
+            vram_bytes: Some(512),
+        };
+        let sufficient = SystemResources {
+            ram_bytes: Some(4096),
+            vram_bytes: Some(4096),
+        };
+        let insufficient = SystemResources {
+            ram_bytes: Some(1024),
+            vram_bytes: Some(256),
+        };
+
+        assert!(footprint.warnings(&sufficient).is_empty());
+        assert_eq!(footprint.warnings(&insufficient).len(), 2);
+        assert!(footprint.warnings(&SystemResources::default()).is_empty());
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536 * 1024 * 1024), "1.5 GiB");
+    }
+}
@@ -0,0 +1,103 @@
+use std::{env, fmt, fs, process::Command};
+
+use serde::{Deserialize, Serialize};
+use tracing::trace;
+
+/// Commit the crate was built from, with a `-dirty` suffix if the working
+/// tree had uncommitted changes, or "unknown" if it was not built from a git
+/// repository.
+pub const GIT_HASH: &str = env!("CARDIOTRUST_GIT_HASH");
+
+/// Number of characters of the git hash shown in the UI.
+const SHORT_HASH_LENGTH: usize = 12;
+
+/// The code and the machine that produced a scenario or its results.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub git_hash: String,
+    pub crate_version: String,
+    pub hostname: String,
+}
+
+impl Provenance {
+    /// Returns the provenance of the running build on this machine.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn current() -> Self {
+        trace!("Collecting provenance");
+        Self {
+            git_hash: GIT_HASH.to_string(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            hostname: hostname(),
+        }
+    }
+
+    /// Returns the first characters of the git hash, keeping the `-dirty`
+    /// suffix.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn short_hash(&self) -> String {
+        let (hash, dirty) = self
+            .git_hash
+            .strip_suffix("-dirty")
+            .map_or((self.git_hash.as_str(), ""), |hash| (hash, "-dirty"));
+        let short: String = hash.chars().take(SHORT_HASH_LENGTH).collect();
+        format!("{short}{dirty}")
+    }
+}
+
+impl fmt::Display for Provenance {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "CardioTRust {} (commit {}) on {}",
+            self.crate_version,
+            self.short_hash(),
+            self.hostname
+        )
+    }
+}
+
+/// Returns the name of this machine or "unknown".
+///
+/// Uses the environment variables set by most shells and Windows, then
+/// /etc/hostname and finally the hostname command.
+#[tracing::instrument(level = "trace")]
+fn hostname() -> String {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .into_iter()
+        .find_map(|variable| env::var(variable).ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            Command::new("hostname")
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .map(|hostname| hostname.trim().to_string())
+        .filter(|hostname| !hostname.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_hash_keeps_dirty_suffix() {
+        let mut provenance = Provenance {
+            git_hash: "0123456789abcdef0123456789abcdef01234567-dirty".to_string(),
+            crate_version: "0.3.0".to_string(),
+            hostname: "lab".to_string(),
+        };
+        assert_eq!(provenance.short_hash(), "0123456789ab-dirty");
+        provenance.git_hash = "unknown".to_string();
+        assert_eq!(provenance.short_hash(), "unknown");
+        assert_eq!(
+            provenance.to_string(),
+            "CardioTRust 0.3.0 (commit unknown) on lab"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::{cmp::Ordering, collections::BTreeSet};
+
+use strum_macros::EnumIter;
+use tracing::trace;
+
+use super::{Scenario, Status};
+
+/// The keys the scenarios of the explorer can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, EnumIter)]
+pub enum SortColumn {
+    #[default]
+    Id,
+    Status,
+    Started,
+    Duration,
+    Dice,
+    Loss,
+}
+
+impl SortColumn {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Id => "ID",
+            Self::Status => "Status",
+            Self::Started => "Start date",
+            Self::Duration => "Duration",
+            Self::Dice => "Dice",
+            Self::Loss => "Loss",
+        }
+    }
+}
+
+/// Returns true if the id, the comment or one of the tags of the scenario
+/// contain the filter, ignoring case. An empty filter matches every scenario.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(scenario))]
+pub fn matches_filter(scenario: &Scenario, filter: &str) -> bool {
+    let filter = filter.trim().to_lowercase();
+    filter.is_empty()
+        || scenario.id.to_lowercase().contains(&filter)
+        || scenario.comment.to_lowercase().contains(&filter)
+        || scenario
+            .tags
+            .iter()
+            .any(|tag| tag.to_lowercase().contains(&filter))
+}
+
+/// Returns true if the scenario has all of the given tags. No tags match
+/// every scenario.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(scenario))]
+pub fn has_tags(scenario: &Scenario, tags: &BTreeSet<String>) -> bool {
+    tags.iter().all(|tag| scenario.tags.contains(tag))
+}
+
+/// Returns the tags used by any of the scenarios, sorted by name.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(scenarios))]
+pub fn all_tags<'a>(scenarios: impl IntoIterator<Item = &'a Scenario>) -> BTreeSet<String> {
+    scenarios
+        .into_iter()
+        .flat_map(|scenario| scenario.tags.iter().cloned())
+        .collect()
+}
+
+/// Returns the indices of the scenarios matching the filter and having all
+/// of the given tags, sorted by the given column.
+///
+/// Scenarios without a value for the column, e.g. without a summary when
+/// sorting by dice, come first in ascending order. Ties are broken by id.
+#[must_use]
+#[tracing::instrument(level = "trace", skip(scenarios))]
+pub fn sorted_indices<'a>(
+    scenarios: impl IntoIterator<Item = &'a Scenario>,
+    filter: &str,
+    tags: &BTreeSet<String>,
+    column: SortColumn,
+    descending: bool,
+) -> Vec<usize> {
+    trace!("Sorting and filtering scenarios");
+    let scenarios: Vec<&Scenario> = scenarios.into_iter().collect();
+    let mut indices: Vec<usize> = (0..scenarios.len())
+        .filter(|&index| {
+            matches_filter(scenarios[index], filter) && has_tags(scenarios[index], tags)
+        })
+        .collect();
+    indices.sort_by(|&a, &b| {
+        let ordering = compare(scenarios[a], scenarios[b], column)
+            .then_with(|| scenarios[a].id.cmp(&scenarios[b].id));
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    indices
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn compare(a: &Scenario, b: &Scenario, column: SortColumn) -> Ordering {
+    match column {
+        SortColumn::Id => a.id.cmp(&b.id),
+        SortColumn::Status => status_rank(&a.status).cmp(&status_rank(&b.status)),
+        SortColumn::Started => a.started.cmp(&b.started),
+        SortColumn::Duration => a.duration_s.cmp(&b.duration_s),
+        SortColumn::Dice => compare_optional(
+            a.summary.as_ref().map(|summary| summary.dice),
+            b.summary.as_ref().map(|summary| summary.dice),
+        ),
+        SortColumn::Loss => compare_optional(
+            a.summary.as_ref().map(|summary| summary.loss),
+            b.summary.as_ref().map(|summary| summary.loss),
+        ),
+    }
+}
+
+/// Orders active scenarios first and finished scenarios last.
+#[tracing::instrument(level = "trace")]
+const fn status_rank(status: &Status) -> u8 {
+    match status {
+        Status::Running(_) | Status::Simulating => 0,
+        Status::Scheduled => 1,
+        Status::Planning => 2,
+        Status::Interrupted => 3,
+        Status::Aborted => 4,
+        Status::Done => 5,
+    }
+}
+
+#[tracing::instrument(level = "trace")]
+fn compare_optional(a: Option<f32>, b: Option<f32>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scenario::summary::Summary;
+
+    fn scenario(id: &str, comment: &str, dice: Option<f32>) -> Scenario {
+        let mut scenario = Scenario::empty();
+        scenario.id = id.into();
+        scenario.comment = comment.into();
+        scenario.summary = dice.map(|dice| Summary {
+            dice,
+            ..Summary::default()
+        });
+        scenario
+    }
+
+    #[test]
+    fn filter_matches_id_and_comment() {
+        let scenarios = [
+            scenario("a", "Sheet AP", None),
+            scenario("b", "line ap", None),
+            scenario("sheet-c", "", None),
+        ];
+
+        assert_eq!(
+            sorted_indices(&scenarios, "sheet", &BTreeSet::new(), SortColumn::Id, false),
+            vec![0, 2]
+        );
+        assert_eq!(
+            sorted_indices(&scenarios, " ", &BTreeSet::new(), SortColumn::Id, false),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn filter_matches_tags() {
+        let mut scenarios = [
+            scenario("a", "", None),
+            scenario("b", "", None),
+            scenario("c", "", None),
+        ];
+        scenarios[0].add_tag("sweep-A");
+        scenarios[0].add_tag("revision-2-figs");
+        scenarios[1].add_tag(" sweep-A ");
+        scenarios[2].add_tag("sweep-B");
+
+        assert_eq!(
+            all_tags(&scenarios),
+            BTreeSet::from([
+                "revision-2-figs".to_string(),
+                "sweep-A".to_string(),
+                "sweep-B".to_string()
+            ])
+        );
+        let tags = BTreeSet::from(["sweep-A".to_string()]);
+        assert_eq!(
+            sorted_indices(&scenarios, "", &tags, SortColumn::Id, false),
+            vec![0, 1]
+        );
+        let tags = BTreeSet::from(["sweep-A".to_string(), "revision-2-figs".to_string()]);
+        assert_eq!(
+            sorted_indices(&scenarios, "", &tags, SortColumn::Id, false),
+            vec![0]
+        );
+        assert_eq!(
+            sorted_indices(
+                &scenarios,
+                "sweep-b",
+                &BTreeSet::new(),
+                SortColumn::Id,
+                false
+            ),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn missing_values_sort_first() {
+        let scenarios = [
+            scenario("a", "", Some(0.8)),
+            scenario("b", "", None),
+            scenario("c", "", Some(0.2)),
+        ];
+
+        assert_eq!(
+            sorted_indices(&scenarios, "", &BTreeSet::new(), SortColumn::Dice, false),
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            sorted_indices(&scenarios, "", &BTreeSet::new(), SortColumn::Dice, true),
+            vec![0, 2, 1]
+        );
+    }
+}
@@ -1,25 +1,42 @@
-use std::ops::Deref;
+use std::{fs, ops::Deref, path::Path};
 
 use anyhow::{Context, Result};
-use ndarray::{s, Array3, Array4};
+use ndarray::{s, Array, Array3, Array4, Dimension, IxDyn};
 use ocl::Queue;
-use serde::{Deserialize, Serialize};
-use tracing::{debug, trace};
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{debug, trace, warn};
 
-use super::algorithm::metrics::Metrics;
+use super::{
+    algorithm::metrics::Metrics,
+    compression::{open_reader, write_bytes},
+    ensemble::EnsembleStatistics,
+};
 use crate::core::{
     algorithm::{
+        diagnostics::MeasurementConditioning,
         estimation::{Estimations, EstimationsGPU},
         metrics::MetricsGPU,
         refinement::{
+            confidence::ParameterConfidence,
             derivation::{Derivatives, DerivativesGPU},
             Optimizer,
         },
     },
-    config::algorithm::Algorithm,
-    model::{functional::allpass::APParameters, Model, ModelGPU},
+    config::{
+        algorithm::{Algorithm, GpuPrecision},
+        storage::Storage,
+    },
+    model::{
+        functional::allpass::{number_of_neighbors, APParameters},
+        Model, ModelGPU,
+    },
 };
 
+/// Version of the split results storage written by [`Results::save`].
+const RESULTS_STORAGE_VERSION: u32 = 1;
+/// Name of the index file listing the parts of the stored results.
+pub const RESULTS_INDEX_FILE: &str = "index.toml";
+
 /// Results contains the outputs from running a scenario.
 ///
 /// This includes metrics, estimations, derivatives, snapshots,
@@ -31,6 +48,15 @@ pub struct Results {
     pub derivatives: Derivatives,
     pub snapshots: Option<Snapshots>,
     pub model: Option<Model>,
+    // spread of the ensemble members, if an ensemble was run
+    #[serde(default)]
+    pub ensemble: Option<EnsembleStatistics>,
+    // standard deviations of the all-pass parameters, if estimated
+    #[serde(default)]
+    pub confidence: Option<ParameterConfidence>,
+    // singular values of the measurement matrix, if diagnosed
+    #[serde(default)]
+    pub conditioning: Option<MeasurementConditioning>,
 }
 
 pub struct ResultsGPU {
@@ -40,6 +66,18 @@ pub struct ResultsGPU {
     pub model: ModelGPU,
 }
 
+impl ResultsGPU {
+    /// Returns the device memory occupied by the buffers in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn memory_bytes(&self) -> usize {
+        self.metrics.memory_bytes()
+            + self.estimations.memory_bytes()
+            + self.derivatives.memory_bytes()
+            + self.model.memory_bytes()
+    }
+}
+
 #[allow(
     clippy::useless_let_if_seq,
     clippy::cast_possible_truncation,
@@ -49,7 +87,8 @@ pub struct ResultsGPU {
 impl Results {
     /// Creates a new Results instance with empty estimations, derivatives,
     /// snapshots, and model. The metrics are initialized based on the provided
-    /// number of epochs, steps, sensors, and states.
+    /// number of epochs, steps, sensors, and states. The all-pass shapes follow
+    /// the neighborhood radius of the model.
     #[must_use]
     #[tracing::instrument(level = "debug")]
     pub fn new(
@@ -61,6 +100,7 @@ impl Results {
         number_of_snapshots: usize,
         batch_size: usize,
         optimizer: Optimizer,
+        neighborhood_radius: usize,
     ) -> Self {
         debug!("Creating results with empty estimations, derivatives, snapshots, and model");
         let estimations = Estimations::empty(
@@ -68,8 +108,9 @@ impl Results {
             number_of_sensors,
             number_of_steps,
             number_of_beats,
+            neighborhood_radius,
         );
-        let derivatives = Derivatives::new(number_of_states, optimizer);
+        let derivatives = Derivatives::new(number_of_states, optimizer, neighborhood_radius);
         let batch_size = if batch_size > 0 {
             batch_size
         } else {
@@ -85,6 +126,7 @@ impl Results {
                 number_of_steps,
                 number_of_states,
                 number_of_sensors,
+                neighborhood_radius,
             ))
         } else {
             None
@@ -96,6 +138,9 @@ impl Results {
             derivatives,
             model: None,
             snapshots,
+            ensemble: None,
+            confidence: None,
+            conditioning: None,
         }
     }
 
@@ -116,23 +161,134 @@ impl Results {
         Ok(())
     }
 
+    /// Saves the results split by concern into separate files in the given
+    /// directory, together with an index file listing the parts.
+    ///
+    /// The parts are compressed with the compression level of the storage
+    /// settings. The estimations, derivatives and snapshots are skipped if
+    /// they are not persisted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or any file cannot be written.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save(&self, path: &Path, storage: &Storage) -> Result<()> {
+        debug!("Saving results to {}", path.display());
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        let level = storage.compression_level;
+        let mut parts = vec![save_part(path, "metrics", &self.metrics, level)?];
+        if storage.persist_estimations {
+            parts.push(save_part(path, "estimations", &self.estimations, level)?);
+            parts.push(save_part(path, "derivatives", &self.derivatives, level)?);
+            if let Some(snapshots) = self.snapshots.as_ref() {
+                parts.push(save_part(path, "snapshots", snapshots, level)?);
+            }
+        }
+        if let Some(model) = self.model.as_ref() {
+            parts.push(save_part(path, "model", model, level)?);
+        }
+        if let Some(ensemble) = self.ensemble.as_ref() {
+            parts.push(save_part(path, "ensemble", ensemble, level)?);
+        }
+        if let Some(confidence) = self.confidence.as_ref() {
+            parts.push(save_part(path, "confidence", confidence, level)?);
+        }
+        if let Some(conditioning) = self.conditioning.as_ref() {
+            parts.push(save_part(path, "conditioning", conditioning, level)?);
+        }
+        let index = ResultsIndex {
+            version: RESULTS_STORAGE_VERSION,
+            parts,
+        };
+        index.save(path)
+    }
+
+    /// Loads results saved with [`Results::save`] from the given directory.
+    ///
+    /// Snapshots, model, ensemble, confidence and conditioning are optional
+    /// parts. If one of them is missing or cannot be read, a warning is
+    /// logged and it is set to `None`, so the remaining results stay usable. Estimations and
+    /// derivatives that were not persisted are replaced by empty ones with
+    /// the dimensions of the stored model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or a required part cannot be read.
+    #[tracing::instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading results from {}", path.display());
+        let index = ResultsIndex::load(path)?;
+        let model: Option<Model> = index.load_optional_part(path, "model");
+        let (estimations, derivatives) = if index.part("estimations").is_some() {
+            (
+                index.load_part(path, "estimations")?,
+                index.load_part(path, "derivatives")?,
+            )
+        } else {
+            debug!("Estimations were not persisted, creating empty ones");
+            empty_estimations(
+                model
+                    .as_ref()
+                    .context("Neither estimations nor a model were stored")?,
+            )?
+        };
+        Ok(Self {
+            metrics: index.load_part(path, "metrics")?,
+            estimations,
+            derivatives,
+            snapshots: index.load_optional_part(path, "snapshots"),
+            model,
+            ensemble: index.load_optional_part(path, "ensemble"),
+            confidence: index.load_optional_part(path, "confidence"),
+            conditioning: index.load_optional_part(path, "conditioning"),
+        })
+    }
+
+    /// Loads only the metrics of results saved with [`Results::save`],
+    /// without reading the estimations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index or the metrics cannot be read.
+    #[tracing::instrument(level = "debug")]
+    pub fn load_metrics(path: &Path) -> Result<Metrics> {
+        debug!("Loading metrics from {}", path.display());
+        ResultsIndex::load(path)?.load_part(path, "metrics")
+    }
+
     #[allow(clippy::missing_panics_doc)]
     #[tracing::instrument(level = "trace", skip_all)]
     pub fn to_gpu(&self, queue: &Queue) -> Result<ResultsGPU> {
+        self.to_gpu_with_precision(queue, GpuPrecision::Single)
+    }
+
+    /// Copies the results to the GPU, storing the system states and gains
+    /// in the given precision.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model is missing or a buffer cannot be created.
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
+    pub fn to_gpu_with_precision(
+        &self,
+        queue: &Queue,
+        precision: GpuPrecision,
+    ) -> Result<ResultsGPU> {
         Ok(ResultsGPU {
             metrics: self.metrics.to_gpu(queue)?,
-            estimations: self.estimations.to_gpu(queue)?,
+            estimations: self.estimations.to_gpu(queue, precision)?,
             derivatives: self.derivatives.to_gpu(queue)?,
             model: self
                 .model
                 .as_ref()
                 .context("Model not available")?
-                .to_gpu(queue)?,
+                .to_gpu(queue, precision)?,
         })
     }
 
     #[allow(clippy::missing_panics_doc)]
-    #[tracing::instrument(level = "trace", skip_all)]
+    #[tracing::instrument(level = "info", skip_all, fields(profile = "gpu_transfer"))]
     pub fn update_from_gpu(&mut self, results: &ResultsGPU) -> Result<()> {
         self.metrics.update_from_gpu(&results.metrics)?;
         self.estimations.update_from_gpu(&results.estimations)?;
@@ -150,6 +306,11 @@ impl Results {
     pub fn get_default() -> Self {
         let model = Model::get_default().expect("Failed to create default model for results");
         let algorithm_config = Algorithm::default();
+        let neighborhood_radius = model
+            .functional_description
+            .ap_params
+            .neighborhood_radius()
+            .expect("Default model to have a cubic neighborhood");
         Self {
             metrics: Metrics::new(
                 algorithm_config.epochs,
@@ -161,21 +322,167 @@ impl Results {
                 model.spatial_description.sensors.count(),
                 model.functional_description.control_function_values.len(),
                 model.functional_description.measurement_matrix.shape()[0],
+                neighborhood_radius,
             ),
             derivatives: Derivatives::new(
                 model.spatial_description.voxels.count_states(),
                 Optimizer::default(),
+                neighborhood_radius,
             ),
             model: Some(model),
             snapshots: None,
+            ensemble: None,
+            confidence: None,
+            conditioning: None,
+        }
+    }
+}
+
+/// Index of the separately stored parts of the results.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ResultsIndex {
+    pub version: u32,
+    pub parts: Vec<ResultsPart>,
+}
+
+/// A separately stored part of the results.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ResultsPart {
+    pub name: String,
+    pub file: String,
+    pub size_bytes: u64,
+}
+
+impl ResultsIndex {
+    /// Reads the index file from the given results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index file cannot be read or parsed, or was
+    /// written by a newer version.
+    #[tracing::instrument(level = "trace")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let file_path = path.join(RESULTS_INDEX_FILE);
+        let toml = fs::read_to_string(&file_path)
+            .with_context(|| format!("Failed to read results index: {}", file_path.display()))?;
+        let index: Self = toml::from_str(&toml).context("Failed to parse results index")?;
+        anyhow::ensure!(
+            index.version <= RESULTS_STORAGE_VERSION,
+            "Results storage version {} is not supported",
+            index.version
+        );
+        Ok(index)
+    }
+
+    /// Writes the index file to the given results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index file cannot be written.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let toml = toml::to_string(self).context("Failed to serialize results index")?;
+        write_bytes(&path.join(RESULTS_INDEX_FILE), toml.as_bytes())
+            .context("Failed to write results index")
+    }
+
+    /// Returns the part with the given name, if it was stored.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn part(&self, name: &str) -> Option<&ResultsPart> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn load_part<T: DeserializeOwned>(&self, path: &Path, name: &str) -> Result<T> {
+        let part = self
+            .part(name)
+            .with_context(|| format!("Results part {name} missing from index"))?;
+        let file_path = path.join(&part.file);
+        let mut reader = open_reader(&file_path)
+            .with_context(|| format!("Failed to open results part: {}", file_path.display()))?;
+        bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+            .with_context(|| format!("Failed to deserialize results part {name}"))
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn load_optional_part<T: DeserializeOwned>(&self, path: &Path, name: &str) -> Option<T> {
+        self.part(name)?;
+        match self.load_part(path, name) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Skipping unreadable results part {name}: {e:#}");
+                None
+            }
         }
     }
 }
 
+/// Writes one part of the results to `{name}.bin` in the given directory,
+/// or to `{name}.bin.zst` if the compression level is positive.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be created or written.
+#[tracing::instrument(level = "trace", skip(value))]
+fn save_part<T: Serialize>(
+    path: &Path,
+    name: &str,
+    value: &T,
+    compression_level: i32,
+) -> Result<ResultsPart> {
+    let (file_path, size_bytes) = write_file(
+        &path.join(format!("{name}.bin")),
+        compression_level,
+        |writer| {
+            bincode::serde::encode_into_std_write(value, writer, bincode::config::standard())
+                .with_context(|| format!("Failed to serialize results part {name}"))
+        },
+    )?;
+    let file = file_path
+        .file_name()
+        .context("Results part has no file name")?
+        .to_string_lossy()
+        .into_owned();
+    Ok(ResultsPart {
+        name: name.to_string(),
+        file,
+        size_bytes: u64::try_from(size_bytes)?,
+    })
+}
+
+/// Creates empty estimations and derivatives with the dimensions of the
+/// given model.
+///
+/// # Errors
+///
+/// Returns an error if the all-pass parameters of the model do not form a
+/// cubic neighborhood.
+#[tracing::instrument(level = "debug", skip_all)]
+fn empty_estimations(model: &Model) -> Result<(Estimations, Derivatives)> {
+    let neighborhood_radius = model
+        .functional_description
+        .ap_params
+        .neighborhood_radius()?;
+    let number_of_states = model.spatial_description.voxels.count_states();
+    Ok((
+        Estimations::empty(
+            number_of_states,
+            model.spatial_description.sensors.count(),
+            model.functional_description.control_function_values.len(),
+            model.functional_description.measurement_matrix.shape()[0],
+            neighborhood_radius,
+        ),
+        Derivatives::new(number_of_states, Optimizer::default(), neighborhood_radius),
+    ))
+}
+
 /// Snapshot contains estimations and functional description at a point in time.
 /// Used to capture model state during scenario execution.
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
-#[allow(clippy::unsafe_derive_deserialize)]
+///
+/// Snapshots are serialized as differences to the previous snapshot, see
+/// [`SnapshotDiffs`], and reconstructed when deserialized.
+#[derive(Debug, PartialEq, Clone)]
 pub struct Snapshots {
     pub ap_gains: GainsSnapshots,
     pub ap_coefs: CoefsSnapshots,
@@ -197,12 +504,25 @@ impl Snapshots {
         number_of_steps: usize,
         number_of_states: usize,
         number_of_sensors: usize,
+        neighborhood_radius: usize,
     ) -> Self {
         trace!("Creating snapshot with estimations and functional description");
         Self {
-            ap_gains: GainsSnapshots::new(number_of_snapshots, number_of_states),
-            ap_coefs: CoefsSnapshots::new(number_of_snapshots, number_of_states),
-            ap_delays: DelaysSnapshots::new(number_of_snapshots, number_of_states),
+            ap_gains: GainsSnapshots::new(
+                number_of_snapshots,
+                number_of_states,
+                neighborhood_radius,
+            ),
+            ap_coefs: CoefsSnapshots::new(
+                number_of_snapshots,
+                number_of_states,
+                neighborhood_radius,
+            ),
+            ap_delays: DelaysSnapshots::new(
+                number_of_snapshots,
+                number_of_states,
+                neighborhood_radius,
+            ),
             system_states: SystemStatesSnapshots::new(
                 number_of_snapshots,
                 number_of_steps,
@@ -247,14 +567,164 @@ impl Snapshots {
     }
 }
 
+impl Serialize for Snapshots {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        StoredSnapshots {
+            ap_gains: SnapshotDiffs::encode(&self.ap_gains.0, self.current_index),
+            ap_coefs: SnapshotDiffs::encode(&self.ap_coefs.0, self.current_index),
+            ap_delays: SnapshotDiffs::encode(&self.ap_delays.0, self.current_index),
+            system_states: SnapshotDiffs::encode(&self.system_states.0, self.current_index),
+            measurements: SnapshotDiffs::encode(&self.measurements.0, self.current_index),
+            current_index: self.current_index,
+            number_of_snapshots: self.number_of_snapshots,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Snapshots {
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        StoredSnapshots::deserialize(deserializer)?
+            .into_snapshots()
+            .map_err(|e| de::Error::custom(format!("Failed to reconstruct snapshots: {e}")))
+    }
+}
+
+/// Serialized form of [`Snapshots`].
+#[derive(Serialize, Deserialize)]
+struct StoredSnapshots {
+    ap_gains: SnapshotDiffs<f32>,
+    ap_coefs: SnapshotDiffs<f32>,
+    ap_delays: SnapshotDiffs<usize>,
+    system_states: SnapshotDiffs<f32>,
+    measurements: SnapshotDiffs<f32>,
+    current_index: usize,
+    number_of_snapshots: usize,
+}
+
+impl StoredSnapshots {
+    /// Reconstructs the snapshots from their differences.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any stored array does not match its stored shape.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn into_snapshots(self) -> Result<Snapshots> {
+        Ok(Snapshots {
+            ap_gains: GainsSnapshots(self.ap_gains.decode()?),
+            ap_coefs: CoefsSnapshots(self.ap_coefs.decode()?),
+            ap_delays: DelaysSnapshots(self.ap_delays.decode()?),
+            system_states: SystemStatesSnapshots(self.system_states.decode()?),
+            measurements: MeasurementsSnapshots(self.measurements.decode()?),
+            current_index: self.current_index,
+            number_of_snapshots: self.number_of_snapshots,
+        })
+    }
+}
+
+/// Snapshot array stored as the first snapshot plus, for every following
+/// snapshot, the flat indices and values of the elements that changed.
+///
+/// Only the taken snapshots are stored, the remaining ones are zero. Since
+/// the parameters change slowly late in training, most snapshots only
+/// contain a few changes.
+#[derive(Serialize, Deserialize)]
+struct SnapshotDiffs<T> {
+    shape: Vec<usize>,
+    first: Vec<T>,
+    changes: Vec<Vec<(usize, T)>>,
+}
+
+impl<T: Copy + PartialEq + Default> SnapshotDiffs<T> {
+    /// Encodes the first `taken` snapshots of the array, with the snapshots
+    /// along the first axis.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn encode<D: Dimension>(array: &Array<T, D>, taken: usize) -> Self {
+        let taken = taken.min(array.shape()[0]);
+        let mut snapshots = array.outer_iter().take(taken);
+        let first: Vec<T> = snapshots
+            .next()
+            .map(|snapshot| snapshot.iter().copied().collect())
+            .unwrap_or_default();
+        let mut previous = first.clone();
+        let changes = snapshots
+            .map(|snapshot| {
+                snapshot
+                    .iter()
+                    .zip(previous.iter_mut())
+                    .enumerate()
+                    .filter(|(_, (current, previous))| **current != **previous)
+                    .map(|(index, (current, previous))| {
+                        *previous = *current;
+                        (index, *current)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            shape: array.shape().to_vec(),
+            first,
+            changes,
+        }
+    }
+
+    /// Reconstructs the full snapshot array.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored data does not match the stored shape.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn decode<D: Dimension>(self) -> Result<Array<T, D>> {
+        let number_of_snapshots = self.shape.first().copied().unwrap_or_default();
+        let snapshot_length: usize = self.shape.iter().skip(1).product();
+        let mut data = vec![T::default(); number_of_snapshots * snapshot_length];
+        let taken = if self.first.is_empty() {
+            0
+        } else {
+            self.changes.len() + 1
+        };
+        anyhow::ensure!(
+            (self.first.is_empty() || self.first.len() == snapshot_length)
+                && (taken > 0 || self.changes.is_empty())
+                && taken <= number_of_snapshots,
+            "Stored snapshots do not match shape {:?}",
+            self.shape
+        );
+        data[..self.first.len()].copy_from_slice(&self.first);
+        for (snapshot, changes) in self.changes.iter().enumerate() {
+            let (previous, current) = data.split_at_mut((snapshot + 1) * snapshot_length);
+            let current = &mut current[..snapshot_length];
+            current.copy_from_slice(&previous[snapshot * snapshot_length..]);
+            for &(index, value) in changes {
+                *current
+                    .get_mut(index)
+                    .context("Stored snapshot change index out of bounds")? = value;
+            }
+        }
+        Array::from_shape_vec(IxDyn(&self.shape), data)?
+            .into_dimensionality::<D>()
+            .context("Stored snapshots have the wrong number of dimensions")
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct GainsSnapshots(Array3<f32>);
 
 impl GainsSnapshots {
     #[must_use]
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn new(number_of_snapshots: usize, number_of_states: usize) -> Self {
-        Self(Array3::zeros((number_of_snapshots, number_of_states, 78)))
+    pub fn new(
+        number_of_snapshots: usize,
+        number_of_states: usize,
+        neighborhood_radius: usize,
+    ) -> Self {
+        Self(Array3::zeros((
+            number_of_snapshots,
+            number_of_states,
+            number_of_neighbors(neighborhood_radius) * 3,
+        )))
     }
 }
 
@@ -273,11 +743,15 @@ pub struct CoefsSnapshots(Array3<f32>);
 impl CoefsSnapshots {
     #[must_use]
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn new(number_of_snapshots: usize, number_of_states: usize) -> Self {
+    pub fn new(
+        number_of_snapshots: usize,
+        number_of_states: usize,
+        neighborhood_radius: usize,
+    ) -> Self {
         Self(Array3::zeros((
             number_of_snapshots,
             number_of_states / 3,
-            26,
+            number_of_neighbors(neighborhood_radius),
         )))
     }
 }
@@ -297,11 +771,15 @@ pub struct DelaysSnapshots(Array3<usize>);
 impl DelaysSnapshots {
     #[must_use]
     #[tracing::instrument(level = "trace", skip_all)]
-    pub fn new(number_of_snapshots: usize, number_of_states: usize) -> Self {
+    pub fn new(
+        number_of_snapshots: usize,
+        number_of_states: usize,
+        neighborhood_radius: usize,
+    ) -> Self {
         Self(Array3::zeros((
             number_of_snapshots,
             number_of_states / 3,
-            26,
+            number_of_neighbors(neighborhood_radius),
         )))
     }
 }
@@ -380,6 +858,83 @@ mod tests {
 
     use super::*;
     use crate::core::algorithm::gpu::GPU;
+
+    #[test]
+    fn results_survive_split_storage() -> anyhow::Result<()> {
+        let path = Path::new("tests/core/scenario/results/split_storage");
+        let mut results = Results::get_default();
+        results.snapshots = Some(Snapshots::new(2, 1, 5, 6, 2, 1));
+
+        results.save(path, &Storage::default())?;
+        let loaded = Results::load(path)?;
+        let metrics = Results::load_metrics(path)?;
+
+        assert_eq!(loaded, results);
+        assert_eq!(metrics, results.metrics);
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_results_without_estimations_load() -> anyhow::Result<()> {
+        let path = Path::new("tests/core/scenario/results/compressed_storage");
+        let results = Results::get_default();
+        let storage = Storage {
+            compression_level: 3,
+            persist_estimations: false,
+        };
+
+        results.save(path, &storage)?;
+        let loaded = Results::load(path)?;
+
+        assert!(path.join("metrics.bin.zst").is_file());
+        assert!(!path.join("estimations.bin.zst").exists());
+        assert_eq!(loaded.metrics, results.metrics);
+        assert_eq!(loaded.model, results.model);
+        assert_eq!(
+            loaded.estimations.system_states.shape(),
+            results.estimations.system_states.shape()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_snapshots_are_skipped() -> anyhow::Result<()> {
+        let path = Path::new("tests/core/scenario/results/corrupted_snapshots");
+        let mut results = Results::get_default();
+        results.snapshots = Some(Snapshots::new(2, 1, 5, 6, 2, 1));
+
+        results.save(path, &Storage::default())?;
+        fs::write(path.join("snapshots.bin"), [0xFF, 0x00])?;
+        let loaded = Results::load(path)?;
+
+        assert!(loaded.snapshots.is_none());
+        assert_eq!(loaded.estimations, results.estimations);
+        Ok(())
+    }
+
+    #[test]
+    fn snapshots_survive_serialization_as_diffs() -> anyhow::Result<()> {
+        let mut snapshots = Snapshots::new(4, 1, 10, 6, 2, 1);
+        snapshots.system_states.0.fill(0.5);
+        snapshots.system_states.0[(1, 3, 2)] = 1.0;
+        snapshots.ap_delays.0[(2, 1, 5)] = 7;
+        snapshots.current_index = 3;
+        // snapshots that were not taken yet are not stored
+        snapshots.system_states.0.slice_mut(s![3, .., ..]).fill(0.0);
+
+        let config = bincode::config::standard();
+        let dense_size = bincode::serde::encode_to_vec(&*snapshots.ap_gains, config)?.len()
+            + bincode::serde::encode_to_vec(&*snapshots.ap_coefs, config)?.len()
+            + bincode::serde::encode_to_vec(&*snapshots.ap_delays, config)?.len()
+            + bincode::serde::encode_to_vec(&*snapshots.system_states, config)?.len()
+            + bincode::serde::encode_to_vec(&*snapshots.measurements, config)?.len();
+        let encoded = bincode::serde::encode_to_vec(&snapshots, config)?;
+        let (decoded, _): (Snapshots, usize) = bincode::serde::decode_from_slice(&encoded, config)?;
+
+        assert_eq!(decoded, snapshots);
+        assert!(encoded.len() < dense_size);
+        Ok(())
+    }
     #[test]
     #[allow(clippy::cast_precision_loss, clippy::similar_names)]
     fn test_results_gpu_transfer() -> anyhow::Result<()> {
@@ -0,0 +1,280 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ndarray::s;
+use ndarray_stats::QuantileExt;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use super::results::Results;
+use crate::core::{
+    algorithm::{
+        calculate_pseudo_inverse, inverse::run_inverse_solver, kalman::run_kalman_filter, metrics,
+        run_epoch,
+    },
+    config::algorithm::{Algorithm, AlgorithmType},
+    data::Data,
+    model::{functional::measurement::MeasurementMatrix, Model},
+};
+
+/// Settings for re-evaluating a converged solution under perturbed forward
+/// models.
+///
+/// Each trial multiplies every sensor's row of the measurement matrix with a
+/// gain factor drawn around one and moves every sensor by a normally
+/// distributed offset per axis.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PerturbationConfig {
+    pub sensor_gain_std: f32,
+    pub sensor_position_std_mm: f32,
+    pub number_of_trials: usize,
+    pub seed: u64,
+}
+
+impl Default for PerturbationConfig {
+    /// Returns a default `PerturbationConfig` with 5 % gain jitter, 1 mm
+    /// position jitter and 10 trials.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default perturbation config");
+        Self {
+            sensor_gain_std: 0.05,
+            sensor_position_std_mm: 1.0,
+            number_of_trials: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// Sensitivity of the segmentation metrics to measurement matrix
+/// perturbations.
+///
+/// The baseline is evaluated with the unperturbed measurement matrix using
+/// the same procedure as the trials, so that the difference only stems from
+/// the perturbations. `relative_dice_drop` is the mean dice loss relative to
+/// the baseline dice.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RobustnessReport {
+    pub config: PerturbationConfig,
+    pub baseline_dice: f32,
+    pub baseline_loss: f32,
+    pub trial_dice: Vec<f32>,
+    pub trial_loss: Vec<f32>,
+    pub mean_dice: f32,
+    pub std_dice: f32,
+    pub min_dice: f32,
+    pub relative_dice_drop: f32,
+}
+
+impl RobustnessReport {
+    /// Creates a report from the baseline and the per-trial dice scores and
+    /// losses.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "debug")]
+    pub fn from_trials(
+        config: PerturbationConfig,
+        baseline_dice: f32,
+        baseline_loss: f32,
+        trial_dice: Vec<f32>,
+        trial_loss: Vec<f32>,
+    ) -> Self {
+        debug!("Creating robustness report");
+        let number_of_trials = trial_dice.len().max(1) as f32;
+        let mean_dice = trial_dice.iter().sum::<f32>() / number_of_trials;
+        let std_dice = (trial_dice
+            .iter()
+            .map(|dice| (dice - mean_dice).powi(2))
+            .sum::<f32>()
+            / number_of_trials)
+            .sqrt();
+        let min_dice = trial_dice.iter().copied().fold(f32::INFINITY, f32::min);
+        let relative_dice_drop = if baseline_dice > 0.0 {
+            (baseline_dice - mean_dice) / baseline_dice
+        } else {
+            0.0
+        };
+        Self {
+            config,
+            baseline_dice,
+            baseline_loss,
+            trial_dice,
+            trial_loss,
+            mean_dice,
+            std_dice,
+            min_dice,
+            relative_dice_drop,
+        }
+    }
+
+    /// Saves the report as a TOML file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        debug!("Saving robustness report");
+        let toml = toml::to_string(self).context("Failed to serialize robustness report")?;
+        std::fs::write(path, toml)
+            .with_context(|| format!("Failed to write robustness report: {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Re-evaluates the converged model in `results` under random perturbations
+/// of the measurement matrix.
+///
+/// The estimated parameters are kept fixed, only the system states are
+/// estimated again with the perturbed measurement matrix.
+///
+/// # Errors
+///
+/// Returns an error if the results contain no model, the perturbation
+/// settings are invalid, or an evaluation fails.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn analyze(
+    results: &Results,
+    data: &Data,
+    algorithm: &Algorithm,
+    config: &PerturbationConfig,
+) -> Result<RobustnessReport> {
+    info!("Analyzing robustness against measurement matrix perturbations");
+    let model = results
+        .model
+        .as_ref()
+        .context("Model not available in results for robustness analysis")?;
+    let gain_dist =
+        Normal::new(1.0, config.sensor_gain_std).context("Invalid sensor gain deviation")?;
+    let position_dist = Normal::new(0.0, config.sensor_position_std_mm)
+        .context("Invalid sensor position deviation")?;
+    let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+
+    let (baseline_dice, baseline_loss) = evaluate(model.clone(), data, algorithm)?;
+
+    let mut trial_dice = Vec::with_capacity(config.number_of_trials);
+    let mut trial_loss = Vec::with_capacity(config.number_of_trials);
+    for trial in 0..config.number_of_trials {
+        let mut perturbed = model.clone();
+        perturbed
+            .spatial_description
+            .sensors
+            .positions_mm
+            .mapv_inplace(|position| position + position_dist.sample(&mut rng));
+        let mut measurement_matrix =
+            MeasurementMatrix::from_model_spatial_description(&perturbed.spatial_description)?;
+        for sensor in 0..measurement_matrix.shape()[1] {
+            let gain = gain_dist.sample(&mut rng);
+            measurement_matrix
+                .slice_mut(s![.., sensor, ..])
+                .mapv_inplace(|value| value * gain);
+        }
+        perturbed.functional_description.measurement_matrix = measurement_matrix;
+        let (dice, loss) = evaluate(perturbed, data, algorithm)
+            .with_context(|| format!("Failed to evaluate perturbation trial {trial}"))?;
+        debug!("Trial {trial}: dice {dice}, loss {loss}");
+        trial_dice.push(dice);
+        trial_loss.push(loss);
+    }
+
+    Ok(RobustnessReport::from_trials(
+        config.clone(),
+        baseline_dice,
+        baseline_loss,
+        trial_dice,
+        trial_loss,
+    ))
+}
+
+/// Estimates the system states with the given model without updating its
+/// parameters and returns the best dice score and the loss.
+#[tracing::instrument(level = "debug", skip_all)]
+fn evaluate(model: Model, data: &Data, algorithm: &Algorithm) -> Result<(f32, f32)> {
+    debug!("Evaluating model");
+    let mut results = Results::new(
+        1,
+        model.functional_description.control_function_values.shape()[0],
+        model.spatial_description.sensors.count(),
+        model.spatial_description.voxels.count_states(),
+        model.spatial_description.sensors.count_beats(),
+        0,
+        0,
+        algorithm.optimizer,
+        model
+            .functional_description
+            .ap_params
+            .neighborhood_radius()?,
+    );
+    match algorithm.algorithm_type {
+        AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU => {
+            let config = Algorithm {
+                learning_rate: 0.0,
+                batch_size: 0,
+                freeze_gains: true,
+                freeze_delays: true,
+                freeze_refractory: true,
+                freeze_schedule: Vec::new(),
+                ..algorithm.clone()
+            };
+            results.model = Some(model);
+            run_epoch(&mut results, &mut 0, data, &config)?;
+        }
+        AlgorithmType::PseudoInverse => {
+            calculate_pseudo_inverse(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::KalmanFilter => {
+            run_kalman_filter(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::MinimumNorm | AlgorithmType::SLoreta | AlgorithmType::ELoreta => {
+            run_inverse_solver(&model.functional_description, &mut results, data, algorithm)?;
+            results.model = Some(model);
+        }
+        AlgorithmType::None => {
+            anyhow::bail!("Scenarios without an algorithm have no perturbed evaluation")
+        }
+    }
+    let voxel_numbers = &results
+        .model
+        .as_ref()
+        .context("Model should be set after evaluation")?
+        .spatial_description
+        .voxels
+        .numbers;
+    metrics::calculate_final(
+        &mut results.metrics,
+        &results.estimations,
+        &data.simulation.model.spatial_description.voxels.types,
+        voxel_numbers,
+    );
+    let dice = *results.metrics.dice_score_over_threshold.max_skipnan();
+    let loss = results.metrics.loss_batch[0];
+    Ok((dice, loss))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn report_statistics() {
+        let report = RobustnessReport::from_trials(
+            PerturbationConfig::default(),
+            0.8,
+            1.0,
+            vec![0.6, 0.8],
+            vec![2.0, 1.5],
+        );
+
+        assert_relative_eq!(report.mean_dice, 0.7);
+        assert_relative_eq!(report.std_dice, 0.1, epsilon = 1e-6);
+        assert_relative_eq!(report.min_dice, 0.6);
+        assert_relative_eq!(report.relative_dice_drop, 0.125, epsilon = 1e-6);
+    }
+}
@@ -0,0 +1,734 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use rand::{seq::IndexedRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use super::{Scenario, Status};
+use crate::core::config::algorithm::Algorithm;
+
+/// Extension of the files the searches are persisted in, e.g.
+/// `<results directory>/<id>.search.toml`.
+pub const SEARCH_EXTENSION: &str = "search.toml";
+
+/// Number of finished trials before the TPE sampler stops sampling at random.
+const TPE_STARTUP_TRIALS: usize = 5;
+/// Fraction of the finished trials the TPE sampler considers good.
+const TPE_GOOD_QUANTILE: f32 = 0.25;
+/// Number of candidates the TPE sampler draws per parameter.
+const TPE_CANDIDATES: usize = 24;
+
+/// A range that is sampled uniformly in log space.
+///
+/// A range with equal bounds always yields that value.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct LogRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl LogRange {
+    /// Returns the logarithms of the bounds.
+    #[tracing::instrument(level = "trace")]
+    fn log_bounds(self) -> (f32, f32) {
+        (self.min.ln(), self.max.ln())
+    }
+}
+
+/// The hyperparameters a search draws its trials from.
+///
+/// A batch size of zero uses all beats in one batch, like in the algorithm
+/// config.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SearchSpace {
+    pub learning_rate: LogRange,
+    pub maximum_regularization_strength: LogRange,
+    pub smoothness_regularization_strength: LogRange,
+    pub batch_sizes: Vec<usize>,
+}
+
+impl Default for SearchSpace {
+    /// Returns a search space spanning two orders of magnitude around the
+    /// default learning rate and regularization strength.
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default search space");
+        Self {
+            learning_rate: LogRange {
+                min: 20.0,
+                max: 2000.0,
+            },
+            maximum_regularization_strength: LogRange {
+                min: 0.1,
+                max: 10.0,
+            },
+            smoothness_regularization_strength: LogRange {
+                min: 1e-3,
+                max: 1e-1,
+            },
+            batch_sizes: vec![0],
+        }
+    }
+}
+
+impl SearchSpace {
+    /// Checks that all ranges are positive and ordered and that there is at
+    /// least one batch size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid entry.
+    #[tracing::instrument(level = "debug")]
+    pub fn validate(&self) -> Result<()> {
+        for (name, range) in [
+            ("learning rate", self.learning_rate),
+            (
+                "maximum regularization strength",
+                self.maximum_regularization_strength,
+            ),
+            (
+                "smoothness regularization strength",
+                self.smoothness_regularization_strength,
+            ),
+        ] {
+            anyhow::ensure!(
+                range.min > 0.0 && range.min <= range.max,
+                "The {name} range [{}, {}] must be positive and ordered",
+                range.min,
+                range.max
+            );
+        }
+        anyhow::ensure!(
+            !self.batch_sizes.is_empty(),
+            "The search space needs at least one batch size"
+        );
+        Ok(())
+    }
+
+    /// Returns the continuous ranges in the order of
+    /// [`TrialParameters::continuous`].
+    #[tracing::instrument(level = "trace")]
+    fn ranges(&self) -> [LogRange; 3] {
+        [
+            self.learning_rate,
+            self.maximum_regularization_strength,
+            self.smoothness_regularization_strength,
+        ]
+    }
+}
+
+/// How the parameters of the next trial are chosen.
+///
+/// * `Random`: Uniformly in log space, independent of previous trials.
+/// * `Tpe`: Tree-structured Parzen estimator. After a few random trials, the
+///   finished trials are split into good and bad ones and the candidate with
+///   the highest ratio of the good to the bad density is chosen per
+///   parameter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Sampler {
+    Random,
+    #[default]
+    Tpe,
+}
+
+/// The summary metric a search optimizes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Objective {
+    #[default]
+    Loss,
+    Dice,
+}
+
+impl Objective {
+    /// Returns the value of the objective in a scenario summary, if it is
+    /// finite.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(scenario))]
+    pub fn value(self, scenario: &Scenario) -> Option<f32> {
+        let summary = scenario.summary.as_ref()?;
+        let value = match self {
+            Self::Loss => summary.loss,
+            Self::Dice => summary.dice,
+        };
+        value.is_finite().then_some(value)
+    }
+
+    /// Returns the value turned into a score where lower is better.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn score(self, value: f32) -> f32 {
+        match self {
+            Self::Loss => value,
+            Self::Dice => -value,
+        }
+    }
+}
+
+/// The hyperparameters of a single trial.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct TrialParameters {
+    pub learning_rate: f32,
+    pub maximum_regularization_strength: f32,
+    pub smoothness_regularization_strength: f32,
+    pub batch_size: usize,
+}
+
+impl TrialParameters {
+    /// Overwrites the searched parameters of the algorithm config.
+    #[tracing::instrument(level = "debug", skip(algorithm))]
+    pub fn apply(&self, algorithm: &mut Algorithm) {
+        debug!("Applying trial parameters to algorithm config");
+        algorithm.learning_rate = self.learning_rate;
+        algorithm.maximum_regularization_strength = self.maximum_regularization_strength;
+        algorithm.smoothness_regularization_strength = self.smoothness_regularization_strength;
+        algorithm.batch_size = self.batch_size;
+    }
+
+    /// Returns the continuous parameters in the order of
+    /// [`SearchSpace::ranges`].
+    #[tracing::instrument(level = "trace")]
+    fn continuous(&self) -> [f32; 3] {
+        [
+            self.learning_rate,
+            self.maximum_regularization_strength,
+            self.smoothness_regularization_strength,
+        ]
+    }
+}
+
+/// A trial of a search, run as its own child scenario.
+///
+/// `value` is the objective of the finished scenario. Trials that are
+/// finished without a value failed, e.g. because the scenario was aborted
+/// or deleted.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Trial {
+    pub scenario_id: String,
+    pub parameters: TrialParameters,
+    #[serde(default)]
+    pub value: Option<f32>,
+    #[serde(default)]
+    pub finished: bool,
+}
+
+/// A hyperparameter search over the algorithm config of a base scenario.
+///
+/// Every trial copies the config of the base scenario, applies the sampled
+/// parameters and is run by the scheduler like any other scenario. The
+/// search is saved after every change so that it resumes after a restart.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct HyperparameterSearch {
+    pub id: String,
+    pub base_scenario_id: String,
+    pub space: SearchSpace,
+    pub sampler: Sampler,
+    pub objective: Objective,
+    pub number_of_trials: usize,
+    // maximum number of trials that are scheduled or running at once
+    pub parallel_trials: usize,
+    pub seed: u64,
+    #[serde(default)]
+    pub trials: Vec<Trial>,
+}
+
+impl HyperparameterSearch {
+    /// Creates a new search over the given space for the base scenario.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the search space is invalid or no trial is
+    /// allowed to run.
+    #[tracing::instrument(level = "info")]
+    pub fn new(
+        base_scenario_id: &str,
+        space: SearchSpace,
+        sampler: Sampler,
+        objective: Objective,
+        number_of_trials: usize,
+        parallel_trials: usize,
+        seed: u64,
+    ) -> Result<Self> {
+        info!("Creating hyperparameter search for scenario {base_scenario_id}");
+        let search = Self {
+            id: format!(
+                "search-{}",
+                chrono::Utc::now().format("%Y-%m-%d-%H-%M-%S-%f")
+            ),
+            base_scenario_id: base_scenario_id.to_string(),
+            space,
+            sampler,
+            objective,
+            number_of_trials,
+            parallel_trials,
+            seed,
+            trials: Vec::new(),
+        };
+        search.validate()?;
+        Ok(search)
+    }
+
+    /// Checks that the search space is valid and that at least one trial is
+    /// allowed to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid setting.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn validate(&self) -> Result<()> {
+        self.space.validate()?;
+        anyhow::ensure!(
+            self.number_of_trials > 0 && self.parallel_trials > 0,
+            "A search needs at least one trial and one parallel trial"
+        );
+        Ok(())
+    }
+
+    /// Returns the number of trials that have not finished yet.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn pending_trials(&self) -> usize {
+        self.trials.iter().filter(|trial| !trial.finished).count()
+    }
+
+    /// Returns true if all trials have been created and finished.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn is_finished(&self) -> bool {
+        self.trials.len() >= self.number_of_trials && self.pending_trials() == 0
+    }
+
+    /// Returns the finished trial with the best objective value.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn best_trial(&self) -> Option<&Trial> {
+        self.trials
+            .iter()
+            .filter_map(|trial| {
+                trial
+                    .value
+                    .map(|value| (trial, self.objective.score(value)))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(trial, _)| trial)
+    }
+
+    /// Updates the pending trials from their scenarios and returns true if
+    /// any trial finished.
+    ///
+    /// Trials whose scenario is done take the objective value of its
+    /// summary. Trials whose scenario was aborted or no longer exists fail.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn update<'a>(&mut self, scenarios: impl IntoIterator<Item = &'a Scenario>) -> bool {
+        debug!("Updating trials of search {}", self.id);
+        let scenarios: Vec<&Scenario> = scenarios.into_iter().collect();
+        let mut changed = false;
+        for trial in self.trials.iter_mut().filter(|trial| !trial.finished) {
+            let scenario = scenarios
+                .iter()
+                .find(|scenario| *scenario.get_id() == trial.scenario_id);
+            match scenario.map(|scenario| scenario.get_status()) {
+                Some(Status::Done) => {
+                    trial.value = scenario.and_then(|scenario| self.objective.value(scenario));
+                    trial.finished = true;
+                }
+                Some(Status::Aborted) | None => {
+                    warn!(
+                        "Trial scenario {} of search {} failed",
+                        trial.scenario_id, self.id
+                    );
+                    trial.finished = true;
+                }
+                Some(_) => continue,
+            }
+            changed = true;
+        }
+        changed
+    }
+
+    /// Chooses the parameters of the next trial with the sampler of the
+    /// search.
+    ///
+    /// The random number generator is seeded with the seed of the search
+    /// and the number of trials, so a resumed search draws the same
+    /// parameters as an uninterrupted one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the search space has no batch size.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn suggest(&self) -> Result<TrialParameters> {
+        debug!("Suggesting parameters for the next trial");
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed.wrapping_add(self.trials.len() as u64));
+        let mut finished: Vec<(&TrialParameters, f32)> = self
+            .trials
+            .iter()
+            .filter_map(|trial| {
+                trial
+                    .value
+                    .map(|value| (&trial.parameters, self.objective.score(value)))
+            })
+            .collect();
+        if self.sampler == Sampler::Random || finished.len() < TPE_STARTUP_TRIALS {
+            return sample_random(&self.space, &mut rng);
+        }
+        finished.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let number_of_good = ((finished.len() as f32 * TPE_GOOD_QUANTILE).ceil() as usize).max(1);
+        let (good, bad) = finished.split_at(number_of_good);
+
+        let mut continuous = [0.0; 3];
+        for (dimension, (value, range)) in
+            continuous.iter_mut().zip(self.space.ranges()).enumerate()
+        {
+            let log_values = |trials: &[(&TrialParameters, f32)]| -> Vec<f32> {
+                trials
+                    .iter()
+                    .map(|(parameters, _)| parameters.continuous()[dimension].ln())
+                    .collect()
+            };
+            *value = sample_tpe_continuous(range, &log_values(good), &log_values(bad), &mut rng)?;
+        }
+        let batch_sizes = |trials: &[(&TrialParameters, f32)]| -> Vec<usize> {
+            trials
+                .iter()
+                .map(|(parameters, _)| parameters.batch_size)
+                .collect()
+        };
+        Ok(TrialParameters {
+            learning_rate: continuous[0],
+            maximum_regularization_strength: continuous[1],
+            smoothness_regularization_strength: continuous[2],
+            batch_size: sample_tpe_categorical(
+                &self.space.batch_sizes,
+                &batch_sizes(good),
+                &batch_sizes(bad),
+            )?,
+        })
+    }
+
+    /// Creates, schedules and saves the scenario of the next trial if the
+    /// search needs more trials and fewer than `parallel_trials` are
+    /// pending. Trials are tagged with the id of the search.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scenario could not be created or scheduled.
+    #[tracing::instrument(level = "info", skip_all)]
+    pub fn next_trial(&mut self, base: &Scenario) -> Result<Option<Scenario>> {
+        if self.trials.len() >= self.number_of_trials
+            || self.pending_trials() >= self.parallel_trials
+        {
+            return Ok(None);
+        }
+        let parameters = self
+            .suggest()
+            .context("Failed to suggest parameters for the next trial")?;
+        let mut scenario = Scenario::build(None)?;
+        scenario.config = base.config.clone();
+        parameters.apply(&mut scenario.config.algorithm);
+        scenario.comment = format!(
+            "Trial {} of {} ({})",
+            self.trials.len() + 1,
+            self.id,
+            base.comment
+        );
+        scenario.add_tag(&self.id);
+        scenario
+            .schedule()
+            .context("Failed to schedule trial scenario")?;
+        scenario.save().context("Failed to save trial scenario")?;
+        info!(
+            "Created trial {} of search {} as scenario {}",
+            self.trials.len() + 1,
+            self.id,
+            scenario.get_id()
+        );
+        self.trials.push(Trial {
+            scenario_id: scenario.get_id().clone(),
+            parameters,
+            value: None,
+            finished: false,
+        });
+        Ok(Some(scenario))
+    }
+
+    /// Saves the search to `<id>.search.toml` in the given directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the search could not be serialized or written.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save(&self, directory: &Path) -> Result<()> {
+        debug!("Saving search {}", self.id);
+        fs::create_dir_all(directory)
+            .with_context(|| format!("Failed to create directory: {}", directory.display()))?;
+        let toml = toml::to_string(&self).context("Failed to serialize search to TOML format")?;
+        let path = directory.join(format!("{}.{SEARCH_EXTENSION}", self.id));
+        fs::write(&path, toml)
+            .with_context(|| format!("Failed to write search: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Loads a search from the given file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read or parsed, or if the
+    /// search is invalid, e.g. because it was edited by hand.
+    #[tracing::instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading search from {}", path.display());
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read search: {}", path.display()))?;
+        let search: Self = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse search: {}", path.display()))?;
+        search
+            .validate()
+            .with_context(|| format!("Invalid search: {}", path.display()))?;
+        Ok(search)
+    }
+}
+
+/// Loads all searches from the given directory, sorted by id.
+///
+/// Searches that cannot be parsed or are invalid are skipped with a warning. A missing
+/// directory results in an empty list.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists but cannot be read.
+#[tracing::instrument(level = "info")]
+pub fn load_searches(directory: &Path) -> Result<Vec<HyperparameterSearch>> {
+    info!("Loading searches from {}", directory.display());
+    if !directory.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut searches = Vec::new();
+    for entry in fs::read_dir(directory).context("Failed to read results directory")? {
+        let path = entry.context("Failed to read directory entry")?.path();
+        if path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(&format!(".{SEARCH_EXTENSION}")))
+        {
+            match HyperparameterSearch::load(&path) {
+                Ok(search) => searches.push(search),
+                Err(e) => warn!("Failed to load search from {}: {}", path.display(), e),
+            }
+        }
+    }
+    searches.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(searches)
+}
+
+/// Draws every parameter uniformly in log space.
+#[tracing::instrument(level = "trace", skip(rng))]
+fn sample_random(space: &SearchSpace, rng: &mut ChaCha8Rng) -> Result<TrialParameters> {
+    let mut sample = |range: LogRange| {
+        let (low, high) = range.log_bounds();
+        if low >= high {
+            range.min
+        } else {
+            rng.random_range(low..=high).exp()
+        }
+    };
+    Ok(TrialParameters {
+        learning_rate: sample(space.learning_rate),
+        maximum_regularization_strength: sample(space.maximum_regularization_strength),
+        smoothness_regularization_strength: sample(space.smoothness_regularization_strength),
+        batch_size: *space
+            .batch_sizes
+            .choose(rng)
+            .context("The search space needs at least one batch size")?,
+    })
+}
+
+/// Draws candidates from the Parzen estimator of the good log values and
+/// returns the one with the highest ratio of the good to the bad density.
+#[tracing::instrument(level = "trace", skip(rng))]
+fn sample_tpe_continuous(
+    range: LogRange,
+    good: &[f32],
+    bad: &[f32],
+    rng: &mut ChaCha8Rng,
+) -> Result<f32> {
+    let (low, high) = range.log_bounds();
+    if low >= high {
+        return Ok(range.min);
+    }
+    let mut best = (f32::NEG_INFINITY, low);
+    for _ in 0..TPE_CANDIDATES {
+        let center = *good
+            .choose(rng)
+            .context("TPE needs at least one good trial")?;
+        let candidate = Normal::new(center, parzen_bandwidth(low, high, good.len()))
+            .map_or(center, |normal| normal.sample(rng))
+            .clamp(low, high);
+        let ratio = parzen_log_density(candidate, good, low, high)
+            - parzen_log_density(candidate, bad, low, high);
+        if ratio > best.0 {
+            best = (ratio, candidate);
+        }
+    }
+    Ok(best.1.exp())
+}
+
+/// Returns the batch size with the highest ratio of the smoothed frequencies
+/// among the good and the bad trials.
+#[tracing::instrument(level = "trace")]
+fn sample_tpe_categorical(batch_sizes: &[usize], good: &[usize], bad: &[usize]) -> Result<usize> {
+    #[allow(clippy::cast_precision_loss)]
+    let frequency = |batch_size: usize, trials: &[usize]| {
+        (trials.iter().filter(|&&size| size == batch_size).count() as f32 + 1.0)
+            / (trials.len() + batch_sizes.len()) as f32
+    };
+    batch_sizes
+        .iter()
+        .copied()
+        .max_by(|&a, &b| {
+            (frequency(a, good) / frequency(a, bad))
+                .total_cmp(&(frequency(b, good) / frequency(b, bad)))
+        })
+        .context("The search space needs at least one batch size")
+}
+
+/// Returns the kernel width, shrinking with the number of observations.
+#[tracing::instrument(level = "trace")]
+fn parzen_bandwidth(low: f32, high: f32, number_of_observations: usize) -> f32 {
+    #[allow(clippy::cast_precision_loss)]
+    let bandwidth = (high - low) / (number_of_observations as f32 + 1.0).sqrt();
+    bandwidth.max(1e-3 * (high - low))
+}
+
+/// Returns the log density of a mixture of a uniform prior over the range
+/// and a Gaussian kernel at every observation.
+#[tracing::instrument(level = "trace")]
+fn parzen_log_density(value: f32, observations: &[f32], low: f32, high: f32) -> f32 {
+    let bandwidth = parzen_bandwidth(low, high, observations.len());
+    let normalization = (2.0 * std::f32::consts::PI).sqrt() * bandwidth;
+    let kernels: f32 = observations
+        .iter()
+        .map(|observation| (-0.5 * ((value - observation) / bandwidth).powi(2)).exp())
+        .sum::<f32>()
+        / normalization;
+    #[allow(clippy::cast_precision_loss)]
+    let density = (1.0 / (high - low) + kernels) / (observations.len() as f32 + 1.0);
+    density.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(sampler: Sampler) -> Result<HyperparameterSearch> {
+        let space = SearchSpace {
+            batch_sizes: vec![0, 4, 8],
+            ..SearchSpace::default()
+        };
+        HyperparameterSearch::new("base", space, sampler, Objective::Loss, 20, 2, 3)
+    }
+
+    fn finish_trial(search: &mut HyperparameterSearch, value: f32) -> Result<()> {
+        let parameters = search.suggest()?;
+        search.trials.push(Trial {
+            scenario_id: format!("trial-{}", search.trials.len()),
+            parameters,
+            value: Some(value),
+            finished: true,
+        });
+        Ok(())
+    }
+
+    fn assert_in_space(parameters: &TrialParameters, space: &SearchSpace) {
+        for (value, range) in parameters.continuous().iter().zip(space.ranges()) {
+            assert!(
+                *value >= range.min * 0.999 && *value <= range.max * 1.001,
+                "{value} outside of [{}, {}]",
+                range.min,
+                range.max
+            );
+        }
+        assert!(space.batch_sizes.contains(&parameters.batch_size));
+    }
+
+    #[test]
+    fn samples_stay_in_search_space() -> Result<()> {
+        for sampler in [Sampler::Random, Sampler::Tpe] {
+            let mut search = search(sampler)?;
+            for trial in 0..12 {
+                let parameters = search.suggest()?;
+                assert_in_space(&parameters, &search.space);
+                #[allow(clippy::cast_precision_loss)]
+                finish_trial(&mut search, trial as f32)?;
+            }
+            assert_eq!(
+                search.best_trial().map(|trial| trial.value),
+                Some(Some(0.0))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_space_is_rejected() {
+        let space = SearchSpace {
+            learning_rate: LogRange { min: 0.0, max: 1.0 },
+            ..SearchSpace::default()
+        };
+
+        assert!(
+            HyperparameterSearch::new("base", space, Sampler::Tpe, Objective::Dice, 10, 1, 0)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn saved_search_resumes_identically() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_search");
+        let mut search = search(Sampler::Tpe)?;
+        for trial in 0..6 {
+            #[allow(clippy::cast_precision_loss)]
+            finish_trial(&mut search, 1.0 / (trial as f32 + 1.0))?;
+        }
+        search.save(&directory)?;
+
+        let loaded = load_searches(&directory)?
+            .into_iter()
+            .find(|loaded| loaded.id == search.id)
+            .context("Saved search should be loaded")?;
+
+        assert_eq!(loaded, search);
+        assert_eq!(loaded.suggest()?, search.suggest()?);
+        assert_eq!(loaded.pending_trials(), 0);
+        assert!(!loaded.is_finished());
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_saved_search_is_skipped() -> Result<()> {
+        let directory = std::env::temp_dir().join("cardiotrust_invalid_search");
+        let mut search = search(Sampler::Random)?;
+        search.space.batch_sizes.clear();
+        search.save(&directory)?;
+
+        assert!(HyperparameterSearch::load(
+            &directory.join(format!("{}.{SEARCH_EXTENSION}", search.id))
+        )
+        .is_err());
+        assert!(load_searches(&directory)?
+            .iter()
+            .all(|loaded| loaded.id != search.id));
+        assert!(search.suggest().is_err());
+        fs::remove_dir_all(&directory)?;
+        Ok(())
+    }
+}
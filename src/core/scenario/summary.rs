@@ -1,6 +1,13 @@
+use ndarray_stats::QuantileExt;
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
+use super::provenance::Provenance;
+use crate::core::algorithm::metrics::{
+    activation_time::ActivationTimeStatistics, localization::Localization,
+    velocity::VelocityStatistics, Metrics,
+};
+
 /// Summary contains summary statistics for evaluating a scenario.
 ///
 /// Fields:
@@ -21,6 +28,14 @@ use tracing::trace;
 /// - `precision`: The precision.
 /// - `recall`: The recall.
 /// - `threshold`: The optimum classification threshold.
+/// - `roc_auc`: Area under the ROC curve over all thresholds.
+/// - `precision_recall_auc`: Area under the precision-recall curve over all thresholds.
+/// - `velocities`: Estimated propagation velocity per ground-truth voxel type.
+/// - `activation_time`: Activation time error statistics, if any voxel is activated.
+/// - `localization`: Pathology centroid error and extent error, if any voxel is pathological.
+/// - `gpu_memory_bytes`: Device memory occupied by the GPU algorithm, zero on the CPU.
+/// - `initialized_from`: ID of the scenario the all-pass parameters were initialized from.
+/// - `provenance`: Build and machine that produced the results.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Summary {
     #[serde(default)]
@@ -39,12 +54,30 @@ pub struct Summary {
     pub recall: f32,
     #[serde(default)]
     pub threshold: f32,
+    #[serde(default)]
+    pub roc_auc: f32,
+    #[serde(default)]
+    pub precision_recall_auc: f32,
+    #[serde(default)]
+    pub velocities: Vec<VelocityStatistics>,
+    #[serde(default)]
+    pub activation_time: Option<ActivationTimeStatistics>,
+    #[serde(default)]
+    pub localization: Option<Localization>,
+    #[serde(default)]
+    pub gpu_memory_bytes: usize,
+    #[serde(default)]
+    pub initialized_from: Option<String>,
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
 }
 
 impl Default for Summary {
     /// Returns a `Summary` struct initialized with default values.
     ///
-    /// Default values are 0.0 for all fields.
+    /// Default values are 0 for all fields, no velocities, no activation
+    /// time statistics, no localization, no initialization scenario and no
+    /// provenance.
     #[tracing::instrument(level = "trace")]
     fn default() -> Self {
         trace!("Creating default summary");
@@ -57,6 +90,65 @@ impl Default for Summary {
             precision: 0.0,
             recall: 0.0,
             threshold: 0.0,
+            roc_auc: 0.0,
+            precision_recall_auc: 0.0,
+            velocities: Vec::new(),
+            activation_time: None,
+            localization: None,
+            gpu_memory_bytes: 0,
+            initialized_from: None,
+            provenance: None,
+        }
+    }
+}
+
+impl Summary {
+    /// Recreates the summary of a finished scenario from its stored metrics.
+    ///
+    /// The losses are taken from the last batch. The velocities, the
+    /// activation time statistics and the localization require the
+    /// simulation data and are left empty.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn from_metrics(metrics: &Metrics) -> Self {
+        trace!("Creating summary from metrics");
+        let mut summary = Self {
+            loss: metrics.loss_batch.last().copied().unwrap_or_default(),
+            loss_mse: metrics.loss_mse_batch.last().copied().unwrap_or_default(),
+            loss_maximum_regularization: metrics
+                .loss_maximum_regularization_batch
+                .last()
+                .copied()
+                .unwrap_or_default(),
+            ..Self::default()
+        };
+        summary.set_optimal_threshold(metrics);
+        summary
+    }
+
+    /// Sets the threshold and the classification scores to the threshold
+    /// with the highest DICE score, as well as the threshold independent
+    /// areas under the ROC and precision-recall curves.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn set_optimal_threshold(&mut self, metrics: &Metrics) {
+        trace!("Setting optimal threshold of summary");
+        let optimal_threshold = metrics
+            .dice_score_over_threshold
+            .argmax_skipnan()
+            .unwrap_or_default();
+        let score = |scores: &ndarray::Array1<f32>| {
+            scores.get(optimal_threshold).copied().unwrap_or_default()
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        {
+            self.threshold = optimal_threshold as f32 / 100.0;
         }
+        self.dice = score(&metrics.dice_score_over_threshold);
+        self.iou = score(&metrics.iou_over_threshold);
+        self.recall = score(&metrics.recall_over_threshold);
+        self.precision = score(&metrics.precision_over_threshold);
+        self.roc_auc = metrics.roc_auc();
+        self.precision_recall_auc = metrics.precision_recall_auc();
     }
 }
@@ -0,0 +1,143 @@
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use super::Scenario;
+use crate::{core::config::Config, settings::results_directory};
+
+/// Directory the scenario templates are stored in.
+pub const TEMPLATE_DIRECTORY: &str = "./templates";
+
+/// A reusable scenario configuration.
+///
+/// Templates are stored as `<name>.toml` in the ./templates directory and
+/// can be used to create new scenarios without re-entering every parameter.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Template {
+    pub name: String,
+    pub config: Config,
+    #[serde(default)]
+    pub comment: String,
+}
+
+impl Template {
+    /// Creates a template with the given name from the configuration and
+    /// comment of a scenario.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty or contains path separators.
+    #[tracing::instrument(level = "debug", skip(scenario))]
+    pub fn from_scenario(name: &str, scenario: &Scenario) -> Result<Self> {
+        debug!(
+            "Creating template {name} from scenario with id {}",
+            scenario.get_id()
+        );
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+            bail!("Invalid template name: '{name}'");
+        }
+        Ok(Self {
+            name: name.to_string(),
+            config: scenario.config.clone(),
+            comment: scenario.comment.clone(),
+        })
+    }
+
+    /// Saves the template to ./templates/<name>.toml, replacing a previous
+    /// template with the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template could not be serialized or written.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn save(&self) -> Result<()> {
+        info!("Saving template {}", self.name);
+        let path = Path::new(TEMPLATE_DIRECTORY);
+        fs::create_dir_all(path)
+            .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        let toml = toml::to_string(&self).context("Failed to serialize template to TOML format")?;
+        let file_path = path.join(format!("{}.toml", self.name));
+        fs::write(&file_path, toml)
+            .with_context(|| format!("Failed to write template: {}", file_path.display()))?;
+        Ok(())
+    }
+
+    /// Loads a template from the given file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read or parsed.
+    #[tracing::instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading template from {}", path.display());
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse template: {}", path.display()))
+    }
+
+    /// Creates and saves a new scenario using the configuration and comment
+    /// of the template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new scenario could not be saved.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn instantiate(&self) -> Result<Scenario> {
+        self.instantiate_in(results_directory())
+    }
+
+    /// Creates and saves a new scenario from the template like
+    /// [`Self::instantiate`], but in the given root instead of the results
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new scenario could not be saved.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn instantiate_in(&self, root: &Path) -> Result<Scenario> {
+        info!("Creating scenario from template {}", self.name);
+        let mut scenario = Scenario::build_in(root, None)?;
+        scenario.config = self.config.clone();
+        scenario.comment.clone_from(&self.comment);
+        scenario
+            .save()
+            .context("Failed to save scenario created from template")?;
+        Ok(scenario)
+    }
+}
+
+/// Loads all templates from the ./templates directory, sorted by name.
+///
+/// Templates that cannot be parsed are skipped with a warning. A missing
+/// directory results in an empty list.
+///
+/// # Errors
+///
+/// Returns an error if the templates directory exists but cannot be read.
+#[tracing::instrument(level = "info")]
+pub fn load_templates() -> Result<Vec<Template>> {
+    info!("Loading templates from {TEMPLATE_DIRECTORY}");
+    let dir = Path::new(TEMPLATE_DIRECTORY);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(dir).context("Failed to read templates directory")? {
+        let path = entry.context("Failed to read directory entry")?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            match Template::load(&path) {
+                Ok(template) => templates.push(template),
+                Err(e) => warn!("Failed to load template from {}: {}", path.display(), e),
+            }
+        }
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
@@ -1,4 +1,5 @@
 mod basic;
+mod end_to_end;
 mod line_ap;
 mod losslandscape;
 mod runtime;
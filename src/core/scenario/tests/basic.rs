@@ -1,34 +1,315 @@
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
 
-use crate::core::scenario::Scenario;
+use crate::{
+    core::scenario::{
+        provenance::Provenance, template::Template, RecoveryAction, Scenario, Status,
+    },
+    ScenarioBundle, ScenarioList,
+};
+
+/// Returns an empty root for the scenarios of a test in the temporary
+/// directory, so the tests never touch the results directory of the
+/// settings.
+#[tracing::instrument(level = "trace")]
+fn temp_root(name: &str) -> anyhow::Result<PathBuf> {
+    let root = std::env::temp_dir().join(format!("cardiotrust_{name}"));
+    if root.is_dir() {
+        fs::remove_dir_all(&root).context("Failed to remove test directory during setup")?;
+    }
+    Ok(root)
+}
 
 #[test]
 fn building_saves_scenario() -> anyhow::Result<()> {
-    let path = Path::new("./results/test");
-    if path.is_dir() {
-        fs::remove_dir_all(path)?;
-    }
-    let _scenario = Scenario::build(Some("test".to_string()))?;
+    let root = temp_root("building_root")?;
+    let path = &root.join("test");
+    let _scenario = Scenario::build_in(&root, Some("test".to_string()))?;
     assert!(path.is_dir());
     assert!(path.join("scenario.toml").is_file());
-    fs::remove_dir_all(path)?;
+    fs::remove_dir_all(&root)?;
     Ok(())
 }
 
 #[test]
 fn loading_scenarios_works() -> anyhow::Result<()> {
-    let path = Path::new("./results/test2");
-    if path.is_dir() {
-        fs::remove_dir_all(path).context("Failed to remove test directory during setup")?;
-    }
-    let scenario = Scenario::build(Some("test2".to_string()))?;
+    let root = temp_root("loading_root")?;
+    let path = &root.join("test2");
+    let scenario = Scenario::build_in(&root, Some("test2".to_string()))?;
 
     let loaded = Scenario::load(path)?;
 
     assert_eq!(scenario, loaded);
+    assert_eq!(loaded.created_with, Some(Provenance::current()));
+
+    fs::remove_dir_all(&root).context("Failed to remove test directory during cleanup")?;
+    Ok(())
+}
+
+#[test]
+fn archive_round_trip_restores_scenario() -> anyhow::Result<()> {
+    let root = temp_root("archive_round_trip_root")?;
+    let path = &root.join("test_archive");
+    let mut scenario = Scenario::build_in(&root, Some("test_archive".to_string()))?;
+    scenario.comment = "shared with collaborators".to_string();
+    scenario.save()?;
+    fs::create_dir_all(path.join("img"))?;
+    fs::write(path.join("img").join("loss.png"), b"png")?;
+
+    let archive_path = root.join("test_archive.tar.zst");
+    scenario.export_archive(&archive_path)?;
+    assert!(Scenario::import_archive_into(&archive_path, &root).is_err());
+    fs::remove_dir_all(path)?;
+
+    let imported = Scenario::import_archive_into(&archive_path, &root)?;
+
+    assert_eq!(scenario, imported);
+    assert!(path.join("img").join("loss.png").is_file());
+
+    fs::remove_dir_all(&root).context("Failed to remove test directory during cleanup")?;
+    Ok(())
+}
+
+#[test]
+fn duplicating_keeps_config_with_new_id() -> anyhow::Result<()> {
+    let root = temp_root("duplicate_root")?;
+    let mut scenario = Scenario::build_in(&root, Some("test_duplicate".to_string()))?;
+    scenario.config.algorithm.epochs = 7;
+    scenario.comment = "duplicate me".to_string();
+    scenario.add_tag("sweep-A");
+    let bundle = ScenarioBundle {
+        scenario,
+        join_handle: None,
+        simulation_rx: None,
+        epoch_rx: None,
+        summary_rx: None,
+    };
+
+    let duplicate = bundle.clone_with_new_id_in(&root)?;
+    let duplicate_path = root.join(duplicate.scenario.get_id());
+    let loaded = Scenario::load(&duplicate_path)?;
+
+    assert_ne!(duplicate.scenario.get_id(), bundle.scenario.get_id());
+    assert_eq!(loaded.config, bundle.scenario.config);
+    assert_eq!(loaded.comment, bundle.scenario.comment);
+    assert_eq!(loaded.tags(), ["sweep-A".to_string()]);
+    assert_eq!(*loaded.get_status(), Status::Planning);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn template_round_trip_creates_scenario() -> anyhow::Result<()> {
+    let root = temp_root("template_root")?;
+    let mut scenario = Scenario::empty();
+    scenario.config.algorithm.learning_rate = 42.0;
+    assert!(Template::from_scenario("", &scenario).is_err());
+    assert!(Template::from_scenario("../escape", &scenario).is_err());
+
+    let template = Template::from_scenario("test_template", &scenario)?;
+    template.save()?;
+    let template_path = Path::new("./templates").join("test_template.toml");
+    let loaded = Template::load(&template_path)?;
+    assert_eq!(template, loaded);
+
+    let created = loaded.instantiate_in(&root)?;
+    assert_eq!(created.config, scenario.config);
+    assert_eq!(*created.get_status(), Status::Planning);
+    assert_eq!(created.get_root(), root);
+
+    fs::remove_dir_all(&root)?;
+    fs::remove_file(template_path)?;
+    Ok(())
+}
+
+#[test]
+fn stale_running_scenario_is_marked_interrupted() {
+    let mut scenario = Scenario::empty();
+    scenario.set_running(3);
+
+    assert!(scenario.mark_interrupted_if_stale());
+    assert_eq!(*scenario.get_status(), Status::Interrupted);
+    assert!(!scenario.mark_interrupted_if_stale());
+}
+
+#[test]
+fn interrupted_scenario_can_be_rescheduled_or_aborted() -> anyhow::Result<()> {
+    let mut scenario = Scenario::empty();
+    assert!(scenario.recover(RecoveryAction::Abort).is_err());
+
+    scenario.set_simulating();
+    scenario.mark_interrupted_if_stale();
+    assert!(scenario.recover(RecoveryAction::Resume).is_err());
+    scenario.recover(RecoveryAction::Reschedule)?;
+    assert_eq!(*scenario.get_status(), Status::Scheduled);
+
+    scenario.set_running(1);
+    scenario.mark_interrupted_if_stale();
+    scenario.recover(RecoveryAction::Abort)?;
+    assert_eq!(*scenario.get_status(), Status::Aborted);
+    Ok(())
+}
+
+#[test]
+fn scenarios_from_archive_directories_are_read_only() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join("cardiotrust_archive_root");
+    let path = root.join("test_read_only");
+    fs::create_dir_all(&path)?;
+    fs::write(
+        path.join("scenario.toml"),
+        toml::to_string(&Scenario::empty())?,
+    )?;
+
+    let scenario_list = ScenarioList::load_from(&root, true)?;
+    let scenario = &scenario_list.entries[0].scenario;
+
+    assert!(scenario.is_read_only());
+    assert_eq!(scenario.get_root(), root);
+    assert!(scenario.save().is_err());
+    assert!(scenario.delete().is_err());
+    assert!(path.join("scenario.toml").is_file());
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn unloadable_scenarios_are_quarantined_and_repaired() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join("cardiotrust_quarantine_root");
+    let path = root.join("test_quarantine");
+    fs::create_dir_all(&path)?;
+    fs::write(
+        path.join("scenario.toml"),
+        "comment = \"kept\"\nconfig = 3\n",
+    )?;
+
+    let mut scenario_list = ScenarioList::load_from(&root, false)?;
+    assert!(scenario_list.entries.is_empty());
+    assert_eq!(scenario_list.quarantine.len(), 1);
+    assert_eq!(scenario_list.quarantine[0].path, path);
+
+    scenario_list.repair(0)?;
+    assert!(scenario_list.quarantine.is_empty());
+    let scenario = &scenario_list.entries[0].scenario;
+    assert_eq!(scenario.get_id(), "test_quarantine");
+    assert_eq!(scenario.comment, "kept");
+    assert_eq!(*scenario.get_status(), Status::Planning);
+    assert_eq!(Scenario::load(&path)?.comment, "kept");
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn archiving_moves_and_compresses_scenarios() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join("cardiotrust_archiving_root");
+    let archive_root = std::env::temp_dir().join("cardiotrust_archiving_archive");
+    for directory in [&root, &archive_root] {
+        if directory.is_dir() {
+            fs::remove_dir_all(directory)?;
+        }
+    }
+    let path = root.join("test_archiving");
+    fs::create_dir_all(path.join("results"))?;
+    let mut scenario = Scenario::empty();
+    scenario.id = "test_archiving".to_string();
+    fs::write(path.join("scenario.toml"), toml::to_string(&scenario)?)?;
+    fs::write(path.join("data.bin"), vec![1_u8; 4096])?;
+
+    let mut scenario_list = ScenarioList::load_from(&root, false)?;
+    let ids = BTreeSet::from([scenario_list.entries[0].scenario.get_id().clone()]);
+    assert!(scenario_list.disk_usage_bytes(&ids) > 4096);
+
+    assert_eq!(scenario_list.archive(&ids, &archive_root), 1);
+
+    let archived = &scenario_list.entries[0].scenario;
+    assert!(archived.is_read_only());
+    assert_eq!(archived.get_root(), archive_root);
+    assert!(!path.exists());
+    assert!(archive_root
+        .join("test_archiving")
+        .join("data.bin.zst")
+        .is_file());
+    assert_eq!(scenario_list.delete(&ids), 0);
+
+    fs::remove_dir_all(&root)?;
+    fs::remove_dir_all(&archive_root)?;
+    Ok(())
+}
+
+#[test]
+fn partial_scenario_write_keeps_previous_file() -> anyhow::Result<()> {
+    let root = temp_root("partial_write_root")?;
+    let path = &root.join("test_partial_write");
+    let mut scenario = Scenario::build_in(&root, Some("test_partial_write".to_string()))?;
+    // a crash while saving leaves a truncated temporary file behind
+    let toml = toml::to_string(&scenario)?;
+    fs::write(
+        path.join("scenario.toml.tmp"),
+        &toml.as_bytes()[..toml.len() / 2],
+    )?;
+
+    let loaded = Scenario::load(path)?;
+    assert_eq!(loaded.get_id(), scenario.get_id());
+
+    scenario.comment = "saved after the crash".to_string();
+    scenario.save()?;
+    assert!(!path.join("scenario.toml.tmp").exists());
+    assert_eq!(Scenario::load(path)?.comment, scenario.comment);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn building_rejects_existing_id() -> anyhow::Result<()> {
+    let root = temp_root("collision_root")?;
+    let path = &root.join("test_collision");
+    let mut scenario = Scenario::build_in(&root, Some("test_collision".to_string()))?;
+    scenario.comment = "first".to_string();
+    scenario.save()?;
+
+    assert!(Scenario::build_in(&root, Some("test_collision".to_string())).is_err());
+    assert_eq!(Scenario::load(path)?.comment, "first");
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn generated_ids_do_not_collide() -> anyhow::Result<()> {
+    let root = temp_root("generated_ids_root")?;
+    let scenarios = (0..8)
+        .map(|_| Scenario::build_in(&root, None))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let ids: BTreeSet<&String> = scenarios.iter().map(Scenario::get_id).collect();
+    assert_eq!(ids.len(), scenarios.len());
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn loading_rejects_id_of_other_directory() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join("cardiotrust_copied_root");
+    let path = root.join("test_copy");
+    fs::create_dir_all(&path)?;
+    // a scenario directory copied without changing the ID
+    let mut scenario = Scenario::empty();
+    scenario.id = "test_original".to_string();
+    fs::write(path.join("scenario.toml"), toml::to_string(&scenario)?)?;
+
+    assert!(Scenario::load(&path).is_err());
+    let mut scenario_list = ScenarioList::load_from(&root, false)?;
+    assert_eq!(scenario_list.quarantine.len(), 1);
+    scenario_list.repair(0)?;
+    assert_eq!(scenario_list.entries[0].scenario.get_id(), "test_copy");
 
-    fs::remove_dir_all(path).context("Failed to remove test directory during cleanup")?;
+    fs::remove_dir_all(&root)?;
     Ok(())
 }
@@ -0,0 +1,152 @@
+//! End-to-end tests running tiny handcrafted scenarios through the whole
+//! pipeline, from the simulation to the summary, as a safety net for
+//! refactors of the algorithms.
+
+use std::sync::mpsc::channel;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    core::{
+        config::algorithm::AlgorithmType,
+        scenario::{provenance::Provenance, run, summary::Summary, Scenario, Status},
+    },
+    settings::results_directory,
+};
+
+// a 65 x 90 x 5 mm heart with 5 mm voxels has 234 voxels
+const VOXEL_SIZE_MM: f32 = 5.0;
+const HEART_SIZE_MM: [f32; 3] = [65.0, 90.0, 5.0];
+// 300 steps at the default sample rate of 2 kHz
+const DURATION_S: f32 = 0.15;
+const MODEL_BASED_EPOCHS: usize = 5;
+
+/// Builds a tiny pathological scenario, runs it and returns the scenario
+/// loaded from disk together with the summary sent by the run.
+fn build_and_run(
+    id: &str,
+    algorithm_type: AlgorithmType,
+    epochs: usize,
+) -> Result<(Scenario, Summary)> {
+    let path = results_directory().join(id);
+    if path.is_dir() {
+        std::fs::remove_dir_all(&path).context("Failed to remove scenario of a previous run")?;
+    }
+    let mut scenario = Scenario::build(Some(id.to_string()))?;
+    scenario.config.simulation.duration_s = DURATION_S;
+    scenario.config.simulation.model.common.pathological = true;
+    for model in [
+        &mut scenario.config.simulation.model,
+        &mut scenario.config.algorithm.model,
+    ] {
+        model.common.voxel_size_mm = VOXEL_SIZE_MM;
+        model
+            .handcrafted
+            .as_mut()
+            .context("Default scenario should use a handcrafted model")?
+            .heart_size_mm = HEART_SIZE_MM;
+    }
+    scenario.config.algorithm.algorithm_type = algorithm_type;
+    scenario.config.algorithm.epochs = epochs;
+    scenario.config.algorithm.snapshots_interval = 0;
+    scenario.schedule()?;
+    scenario.save()?;
+
+    let (simulation_tx, _simulation_rx) = channel();
+    let (epoch_tx, _epoch_rx) = channel();
+    let (summary_tx, summary_rx) = channel();
+    run(scenario, &simulation_tx, &epoch_tx, &summary_tx)?;
+    let summary = summary_rx
+        .try_recv()
+        .context("Run should send the final summary")?;
+
+    let mut scenario = Scenario::load(&path)?;
+    scenario.load_results()?;
+    Ok((scenario, summary))
+}
+
+fn assert_summary_in_range(summary: &Summary) {
+    assert!(
+        summary.loss.is_finite() && summary.loss > 0.0,
+        "{summary:?}"
+    );
+    assert!(
+        summary.loss_mse.is_finite() && summary.loss_mse >= 0.0,
+        "{summary:?}"
+    );
+    for metric in [summary.dice, summary.iou, summary.precision, summary.recall] {
+        assert!((0.0..=1.0).contains(&metric), "{summary:?}");
+    }
+    assert!((0.0..=1.0).contains(&summary.threshold), "{summary:?}");
+}
+
+#[test]
+fn tiny_pseudo_inverse_scenario_runs_end_to_end() -> Result<()> {
+    let (scenario, summary) = build_and_run(
+        "test_end_to_end_pseudo_inverse",
+        AlgorithmType::PseudoInverse,
+        1,
+    )?;
+
+    assert_eq!(scenario.get_status(), &Status::Done);
+    assert!(scenario.results.is_some());
+    assert_summary_in_range(&summary);
+    let provenance = Some(Provenance::current());
+    assert_eq!(summary.provenance, provenance);
+    assert_eq!(
+        scenario
+            .summary
+            .as_ref()
+            .and_then(|summary| summary.provenance.clone()),
+        provenance
+    );
+
+    scenario.delete()?;
+    Ok(())
+}
+
+#[test]
+fn tiny_model_based_scenario_decreases_loss() -> Result<()> {
+    let (scenario, summary) = build_and_run(
+        "test_end_to_end_model_based",
+        AlgorithmType::ModelBased,
+        MODEL_BASED_EPOCHS,
+    )?;
+
+    assert_eq!(scenario.get_status(), &Status::Done);
+    assert_summary_in_range(&summary);
+    let loss = &scenario
+        .results
+        .as_ref()
+        .context("Results should be loaded")?
+        .metrics
+        .loss_batch;
+    assert_eq!(loss.len(), MODEL_BASED_EPOCHS);
+    assert!(loss.iter().all(|loss| loss.is_finite()), "{loss:?}");
+    assert!(
+        loss[MODEL_BASED_EPOCHS - 1] < loss[0],
+        "Loss should decrease over the epochs: {loss:?}"
+    );
+
+    scenario.delete()?;
+    Ok(())
+}
+
+#[test]
+fn tiny_simulation_only_scenario_saves_data_and_plots() -> Result<()> {
+    let (mut scenario, summary) =
+        build_and_run("test_end_to_end_simulation_only", AlgorithmType::None, 1)?;
+
+    assert_eq!(scenario.get_status(), &Status::Done);
+    assert!(scenario.results.is_none());
+    assert_eq!(summary.provenance, Some(Provenance::current()));
+    scenario.load_data()?;
+    assert!(scenario.data.is_some());
+    let plots = scenario.get_directory().join("img").join("simulation");
+    for file in ["states_max.png", "voxel_types.png", "measurements.png"] {
+        assert!(plots.join(file).is_file(), "Missing plot {file}");
+    }
+
+    scenario.delete()?;
+    Ok(())
+}
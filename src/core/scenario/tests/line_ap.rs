@@ -17,7 +17,10 @@ use crate::{
         scenario::{run, tests::SAVE_NPY, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::{line_plot, log_y_plot},
+    vis::plotting::{
+        png::line::{line_plot, log_y_plot},
+        PlotTheme,
+    },
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/line_ap/";
@@ -149,7 +152,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -157,7 +161,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -165,7 +170,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -173,7 +179,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .y_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -392,6 +399,7 @@ fn plot_results(
                     Some("Snapshot"),
                     None,
                     None,
+                    PlotTheme::Light,
                 )?;
 
                 line_plot(
@@ -403,6 +411,7 @@ fn plot_results(
                     Some("Snapshot"),
                     None,
                     None,
+                    PlotTheme::Light,
                 )?;
                 drop(scenario);
             }
@@ -443,6 +452,7 @@ fn plot_results(
                 Some("Epoch"),
                 Some(&labels),
                 None,
+                PlotTheme::Light,
             )?;
         }
     }
@@ -485,9 +495,12 @@ fn create_and_run(
                 )?;
                 if RUN_IN_TESTS {
                     let send_scenario = scenario.clone();
+                    let (simulation_tx, _) = channel();
                     let (epoch_tx, _) = channel();
                     let (summary_tx, _) = channel();
-                    let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                    let handle = thread::spawn(move || {
+                        run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                    });
                     println!("handle {handle:?}");
                     join_handles.push(handle);
                 }
@@ -520,9 +533,12 @@ fn create_and_run(
                 )?;
                 if RUN_IN_TESTS {
                     let send_scenario = scenario.clone();
+                    let (simulation_tx, _) = channel();
                     let (epoch_tx, _) = channel();
                     let (summary_tx, _) = channel();
-                    let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                    let handle = thread::spawn(move || {
+                        run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                    });
                     println!("handle {handle:?}");
                     join_handles.push(handle);
                 }
@@ -14,11 +14,17 @@ use super::{RUN_IN_TESTS, SAVE_NPY};
 use crate::{
     core::{
         algorithm::refinement::Optimizer,
-        config::{algorithm::APDerivative, model::ControlFunction},
+        config::{
+            algorithm::APDerivative,
+            model::{ControlFunction, SensorType},
+        },
         scenario::{run, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::{line_plot, log_y_plot},
+    vis::plotting::{
+        png::line::{line_plot, log_y_plot},
+        PlotTheme,
+    },
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/losslandscape/";
@@ -209,14 +215,17 @@ fn create_and_run(
                 gt_velocity,
                 initial_velocity,
                 single_sensor,
-                control_function,
+                control_function.clone(),
                 &id,
             )?;
             if RUN_IN_TESTS {
                 let send_scenario = scenario.clone();
+                let (simulation_tx, _) = channel();
                 let (epoch_tx, _) = channel();
                 let (summary_tx, _) = channel();
-                let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                let handle = thread::spawn(move || {
+                    run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                });
                 println!("handle {handle:?}");
                 join_handles.push(handle);
             }
@@ -255,7 +264,7 @@ fn build_scenario(
     // Configure Sensor
     if single_sensor {
         scenario.config.simulation.model.common.sensors_per_axis = [1, 1, 1];
-        scenario.config.simulation.model.common.three_d_sensors = false;
+        scenario.config.simulation.model.common.sensor_type = SensorType::SingleAxis;
         scenario
             .config
             .simulation
@@ -323,7 +332,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -331,7 +341,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -339,7 +350,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -347,7 +359,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_stop_percentage = 0.4;
+        .pathology_regions[0]
+        .y_stop_percentage = 0.4;
     // Copy settings to algorithm model
     scenario.config.algorithm.model = scenario.config.simulation.model.clone();
     // Adjust propagation velocities
@@ -471,6 +484,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> anyh
         Some("GT Delay"),
         None,
         None,
+        PlotTheme::Light,
     )
     .unwrap();
     line_plot(
@@ -482,6 +496,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> anyh
         Some("GT Delay"),
         None,
         None,
+        PlotTheme::Light,
     )?;
     Ok(())
 }
@@ -118,9 +118,12 @@ fn create_and_run(
                 )?;
                 if RUN_IN_TESTS {
                     let send_scenario = scenario.clone();
+                    let (simulation_tx, _) = channel();
                     let (epoch_tx, _) = channel();
                     let (summary_tx, _) = channel();
-                    let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                    let handle = thread::spawn(move || {
+                        run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                    });
                     println!("handle {handle:?}");
                     join_handles.push(handle);
                 }
@@ -280,7 +283,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -288,7 +292,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -296,7 +301,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -304,7 +310,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_y_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .y_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -19,7 +19,10 @@ use crate::{
         scenario::{run, tests::SAVE_NPY, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::{line_plot, log_y_plot},
+    vis::plotting::{
+        png::line::{line_plot, log_y_plot},
+        PlotTheme,
+    },
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/sensor_number/";
@@ -199,7 +202,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -207,7 +211,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -215,7 +220,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -223,7 +229,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .context("Handcrafted model should be available for pathology configuration")?
-        .pathology_y_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .y_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -494,6 +501,7 @@ fn plot_results(
                         Some("Snapshot"),
                         None,
                         None,
+                        PlotTheme::Light,
                     )
                     .context("Failed to create delays plot")?;
 
@@ -508,6 +516,7 @@ fn plot_results(
                         Some("Snapshot"),
                         None,
                         None,
+                        PlotTheme::Light,
                     )
                     .context("Failed to create delays error plot")?;
                     drop(scenario);
@@ -563,6 +572,7 @@ fn plot_results(
                 Some("Epoch"),
                 Some(&labels),
                 None,
+                PlotTheme::Light,
             )
             .context("Failed to create loss plot")?;
         }
@@ -607,10 +617,12 @@ fn create_and_run(
                     )?;
                     if RUN_IN_TESTS {
                         let send_scenario = scenario.clone();
+                        let (simulation_tx, _) = channel();
                         let (epoch_tx, _) = channel();
                         let (summary_tx, _) = channel();
-                        let handle =
-                            thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                        let handle = thread::spawn(move || {
+                            run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                        });
                         println!("handle {handle:?}");
                         join_handles.push(handle);
                     }
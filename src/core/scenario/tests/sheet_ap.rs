@@ -19,7 +19,10 @@ use crate::{
         scenario::{run, tests::SAVE_NPY, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::{line_plot, log_y_plot},
+    vis::plotting::{
+        png::line::{line_plot, log_y_plot},
+        PlotTheme,
+    },
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/sheet_ap/";
@@ -125,7 +128,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -133,7 +137,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -141,7 +146,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -149,7 +155,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .y_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -354,6 +361,7 @@ fn plot_results(
             Some("Epoch"),
             Some(&labels),
             None,
+            PlotTheme::Light,
         )?;
 
         let mut scenario = scenarios[min_loss_n].clone();
@@ -516,6 +524,7 @@ fn plot_results(
             Some("Snapshot"),
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         line_plot(
@@ -527,6 +536,7 @@ fn plot_results(
             Some("Snapshot"),
             None,
             None,
+            PlotTheme::Light,
         )?;
     }
     Ok(())
@@ -564,9 +574,12 @@ fn create_and_run(
                 )?;
                 if RUN_IN_TESTS {
                     let send_scenario = scenario.clone();
+                    let (simulation_tx, _) = channel();
                     let (epoch_tx, _) = channel();
                     let (summary_tx, _) = channel();
-                    let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                    let handle = thread::spawn(move || {
+                        run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                    });
                     println!("handle {handle:?}");
                     join_handles.push(handle);
                 }
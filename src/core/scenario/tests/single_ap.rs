@@ -20,7 +20,10 @@ use crate::{
         scenario::{run, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::{line_plot, log_y_plot},
+    vis::plotting::{
+        png::line::{line_plot, log_y_plot},
+        PlotTheme,
+    },
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/single_ap/";
@@ -220,7 +223,8 @@ fn build_scenario(target_velocity: f32, initial_velocity: f32, id: &str) -> Resu
         .context(
             "Handcrafted model configuration should be available for pathology x start adjustment",
         )?
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -230,7 +234,8 @@ fn build_scenario(target_velocity: f32, initial_velocity: f32, id: &str) -> Resu
         .context(
             "Handcrafted model configuration should be available for pathology x stop adjustment",
         )?
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -240,7 +245,8 @@ fn build_scenario(target_velocity: f32, initial_velocity: f32, id: &str) -> Resu
         .context(
             "Handcrafted model configuration should be available for pathology y start adjustment",
         )?
-        .pathology_y_start_percentage = 0.0;
+        .pathology_regions[0]
+        .y_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -250,7 +256,8 @@ fn build_scenario(target_velocity: f32, initial_velocity: f32, id: &str) -> Resu
         .context(
             "Handcrafted model configuration should be available for pathology y stop adjustment",
         )?
-        .pathology_y_stop_percentage = 0.4;
+        .pathology_regions[0]
+        .y_stop_percentage = 0.4;
     // Copy settings to algorithm model
     scenario.config.algorithm.model = scenario.config.simulation.model.clone();
     // Adjust propagation velocities
@@ -578,6 +585,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Epoch"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create loss plot")?;
 
@@ -597,6 +605,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Epoch"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create loss close-up plot")?;
 
@@ -609,6 +618,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Snapshot"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create AP coefficient plot")?;
 
@@ -621,6 +631,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Snapshot"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create AP coefficient error plot")?;
 
@@ -633,6 +644,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Snapshot"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create AP delay plot")?;
 
@@ -652,6 +664,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Snapshot"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create AP delay close-up plot")?;
 
@@ -664,6 +677,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> Resu
         Some("Snapshot"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create AP delay error plot")?;
 
@@ -702,9 +716,12 @@ fn create_and_run(
             let scenario = build_scenario(target_velocity, initial_velocity, &id)?;
             if RUN_IN_TESTS {
                 let send_scenario = scenario.clone();
+                let (simulation_tx, _) = channel();
                 let (epoch_tx, _) = channel();
                 let (summary_tx, _) = channel();
-                let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                let handle = thread::spawn(move || {
+                    run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                });
                 println!("handle {handle:?}");
                 join_handles.push(handle);
             }
@@ -10,7 +10,7 @@ use crate::{
         scenario::{run, Scenario},
     },
     tests::{clean_files, setup_folder},
-    vis::plotting::png::line::log_y_plot,
+    vis::plotting::{png::line::log_y_plot, PlotTheme},
 };
 
 const COMMON_PATH: &str = "tests/core/scenario/smoothness_regularization/";
@@ -115,7 +115,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_start_percentage = 0.0;
+        .pathology_regions[0]
+        .x_start_percentage = 0.0;
     scenario
         .config
         .simulation
@@ -123,7 +124,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_x_stop_percentage = 1.0;
+        .pathology_regions[0]
+        .x_stop_percentage = 1.0;
     scenario
         .config
         .simulation
@@ -131,7 +133,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_start_percentage = 0.4;
+        .pathology_regions[0]
+        .y_start_percentage = 0.4;
     scenario
         .config
         .simulation
@@ -139,7 +142,8 @@ fn build_scenario(
         .handcrafted
         .as_mut()
         .unwrap()
-        .pathology_y_stop_percentage = 0.6;
+        .pathology_regions[0]
+        .y_stop_percentage = 0.6;
     // Adjust propagation velocities
     scenario
         .config
@@ -249,6 +253,7 @@ fn plot_results(path: &Path, base_title: &str, scenarios: Vec<Scenario>) -> anyh
         Some("Epoch"),
         Some(&labels),
         None,
+        PlotTheme::Light,
     )
     .context("Failed to create plot")?;
     Ok(())
@@ -287,9 +292,12 @@ fn create_and_run(
             )?;
             if RUN_IN_TESTS {
                 let send_scenario = scenario.clone();
+                let (simulation_tx, _) = channel();
                 let (epoch_tx, _) = channel();
                 let (summary_tx, _) = channel();
-                let handle = thread::spawn(move || run(send_scenario, &epoch_tx, &summary_tx));
+                let handle = thread::spawn(move || {
+                    run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx)
+                });
                 println!("handle {handle:?}");
                 join_handles.push(handle);
             }
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::info;
+
+use super::{Scenario, Status};
+use crate::core::model::functional::allpass::APParameters;
+
+/// Loads the estimated all-pass parameters of the finished scenario with the
+/// given id from the results directory `root`.
+///
+/// # Errors
+///
+/// Returns an error if the scenario cannot be loaded, is not done or has no
+/// stored model.
+#[tracing::instrument(level = "info", skip(root))]
+pub fn load_ap_params(root: &Path, id: &str) -> Result<APParameters> {
+    info!("Loading AP parameters of scenario {id}");
+    let mut scenario = Scenario::load(&root.join(id))
+        .with_context(|| format!("Failed to load scenario {id} to initialize from"))?;
+    anyhow::ensure!(
+        scenario.status == Status::Done,
+        "Scenario {id} has not finished, its status is {}",
+        scenario.get_status_str()
+    );
+    scenario
+        .load_results()
+        .with_context(|| format!("Failed to load results of scenario {id}"))?;
+    let model = scenario
+        .results
+        .and_then(|results| results.model)
+        .with_context(|| format!("Scenario {id} has no stored model"))?;
+    Ok(model.functional_description.ap_params)
+}
@@ -0,0 +1,271 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{trace, warn};
+
+use crate::{
+    core::scenario::{summary::Summary, Scenario, Status},
+    ScenarioList,
+};
+
+/// File the dashboard settings are read from at startup.
+pub const DASHBOARD_FILE: &str = "./dashboard.toml";
+
+/// Settings of the web dashboard for remote monitoring of runs.
+///
+/// The HTTP server is only started if the application is built with the
+/// `dashboard` feature.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Default)]
+pub struct DashboardSettings {
+    // e.g. "127.0.0.1:8080", the dashboard is disabled if not set
+    #[serde(default)]
+    pub listen_address: Option<String>,
+}
+
+impl DashboardSettings {
+    /// Loads the dashboard settings from the given TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[tracing::instrument(level = "info")]
+    pub fn load(path: &Path) -> Result<Self> {
+        info!("Loading dashboard settings from {}", path.display());
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read dashboard settings: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse dashboard settings: {}", path.display()))
+    }
+}
+
+/// State of a single scenario as exposed by the dashboard.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct ScenarioStatus {
+    pub id: String,
+    pub status: String,
+    pub epoch: Option<usize>,
+    pub epochs: usize,
+    pub progress: f32,
+    pub etc: String,
+    pub comment: String,
+    pub tags: Vec<String>,
+    pub summary: Option<Summary>,
+}
+
+impl ScenarioStatus {
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn from_scenario(scenario: &Scenario) -> Self {
+        let epoch = match scenario.get_status() {
+            Status::Running(epoch) => Some(*epoch),
+            _ => None,
+        };
+        Self {
+            id: scenario.get_id().clone(),
+            status: scenario.get_status_str(),
+            epoch,
+            epochs: scenario.config.algorithm.epochs,
+            progress: scenario.get_progress(),
+            etc: scenario.get_etc(),
+            comment: scenario.comment.clone(),
+            tags: scenario.tags().to_vec(),
+            summary: scenario.summary.clone(),
+        }
+    }
+}
+
+/// Snapshot of the scenario list shared with the dashboard server thread.
+///
+/// Updated from the scenario list whenever it changes, so it reflects the
+/// epochs and summaries the scheduler receives from the running scenarios.
+#[derive(Resource, Debug)]
+pub struct Dashboard {
+    scenarios: Option<Arc<Mutex<Vec<ScenarioStatus>>>>,
+}
+
+impl Dashboard {
+    /// Creates a dashboard that is not serving anything.
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self { scenarios: None }
+    }
+
+    /// Starts the dashboard server on the configured address.
+    ///
+    /// Failures are only logged, the dashboard is disabled in that case.
+    #[must_use]
+    #[tracing::instrument(level = "info")]
+    pub fn start(settings: &DashboardSettings) -> Self {
+        let Some(address) = settings.listen_address.as_ref() else {
+            return Self::disabled();
+        };
+        #[cfg(feature = "dashboard")]
+        {
+            let scenarios = Arc::new(Mutex::new(Vec::new()));
+            match server::spawn(address, Arc::clone(&scenarios)) {
+                Ok(()) => {
+                    info!("Serving dashboard on http://{address}");
+                    Self {
+                        scenarios: Some(scenarios),
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to start dashboard on {address}: {e:#}");
+                    Self::disabled()
+                }
+            }
+        }
+        #[cfg(not(feature = "dashboard"))]
+        {
+            warn!(
+                "Dashboard on {address} is configured, but the application was built \
+                 without the dashboard feature"
+            );
+            Self::disabled()
+        }
+    }
+
+    /// Returns true if the dashboard server is running.
+    #[must_use]
+    pub const fn is_running(&self) -> bool {
+        self.scenarios.is_some()
+    }
+}
+
+impl Default for Dashboard {
+    /// Loads the dashboard settings from [`DASHBOARD_FILE`] if it exists and
+    /// starts the server, otherwise the dashboard is disabled.
+    #[tracing::instrument(level = "info")]
+    fn default() -> Self {
+        let path = Path::new(DASHBOARD_FILE);
+        if !path.exists() {
+            return Self::disabled();
+        }
+        match DashboardSettings::load(path) {
+            Ok(settings) => Self::start(&settings),
+            Err(e) => {
+                warn!("Failed to load dashboard settings: {}", e);
+                Self::disabled()
+            }
+        }
+    }
+}
+
+/// Copies the state of all scenarios into the snapshot served by the
+/// dashboard.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn update_dashboard(dashboard: Res<Dashboard>, scenario_list: Res<ScenarioList>) {
+    trace!("Running update_dashboard system.");
+    let Some(scenarios) = dashboard.scenarios.as_ref() else {
+        return;
+    };
+    let snapshot = scenario_list
+        .entries
+        .iter()
+        .map(|entry| ScenarioStatus::from_scenario(&entry.scenario))
+        .collect();
+    match scenarios.lock() {
+        Ok(mut scenarios) => *scenarios = snapshot,
+        Err(e) => warn!("Failed to acquire dashboard lock: {}", e),
+    }
+}
+
+#[cfg(feature = "dashboard")]
+mod server {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::{Context, Result};
+
+    use super::ScenarioStatus;
+    use crate::http::{self, Response};
+
+    const INDEX_HTML: &str = include_str!("dashboard/index.html");
+
+    /// Serves the dashboard from a separate thread.
+    #[tracing::instrument(level = "debug", skip(scenarios))]
+    pub(super) fn spawn(address: &str, scenarios: Arc<Mutex<Vec<ScenarioStatus>>>) -> Result<()> {
+        http::serve(address, move |path| {
+            let scenarios = scenarios
+                .lock()
+                .map_err(|e| anyhow::anyhow!("Failed to acquire dashboard lock: {e}"))?;
+            respond(path, &scenarios)
+        })
+    }
+
+    /// Returns the response for the given path.
+    ///
+    /// * `/` - the HTML dashboard
+    /// * `/api/scenarios` - all scenarios as JSON
+    /// * `/api/scenarios/<id>` - a single scenario as JSON
+    #[tracing::instrument(level = "trace", skip(scenarios))]
+    pub(super) fn respond(path: &str, scenarios: &[ScenarioStatus]) -> Result<Response> {
+        match path.trim_end_matches('/') {
+            "" | "/index.html" => Ok(Response::ok(
+                "text/html; charset=utf-8",
+                INDEX_HTML.to_string(),
+            )),
+            "/api/scenarios" => Ok(Response::ok(
+                "application/json",
+                serde_json::to_string(scenarios).context("Failed to serialize scenarios")?,
+            )),
+            path => match path
+                .strip_prefix("/api/scenarios/")
+                .and_then(|id| scenarios.iter().find(|scenario| scenario.id == id))
+            {
+                Some(scenario) => Ok(Response::ok(
+                    "application/json",
+                    serde_json::to_string(scenario).context("Failed to serialize scenario")?,
+                )),
+                None => Ok(Response::not_found()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_parse_from_toml() -> anyhow::Result<()> {
+        let settings: DashboardSettings = toml::from_str("listen_address = \"0.0.0.0:8080\"")?;
+        assert_eq!(settings.listen_address.as_deref(), Some("0.0.0.0:8080"));
+
+        let settings: DashboardSettings = toml::from_str("")?;
+        assert!(!Dashboard::start(&settings).is_running());
+        Ok(())
+    }
+
+    #[cfg(feature = "dashboard")]
+    #[test]
+    fn routes_scenarios() -> anyhow::Result<()> {
+        let scenarios = vec![ScenarioStatus {
+            id: "2024-01-01-00-00-00".to_string(),
+            status: "Running".to_string(),
+            epoch: Some(3),
+            epochs: 10,
+            progress: 0.3,
+            etc: String::new(),
+            comment: String::new(),
+            tags: Vec::new(),
+            summary: None,
+        }];
+
+        let response = server::respond("/api/scenarios", &scenarios)?;
+        assert_eq!(response.status, "200 OK");
+        assert!(response.body.contains("\"epoch\":3"));
+        let response = server::respond("/api/scenarios/2024-01-01-00-00-00", &scenarios)?;
+        assert_eq!(response.status, "200 OK");
+        let response = server::respond("/api/scenarios/unknown", &scenarios)?;
+        assert_eq!(response.status, "404 Not Found");
+        let response = server::respond("/", &scenarios)?;
+        assert!(response.content_type.starts_with("text/html"));
+        Ok(())
+    }
+}
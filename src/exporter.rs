@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use bevy::prelude::*;
+use tracing::{trace, warn};
+
+use crate::{
+    core::scenario::{Scenario, Status},
+    http::{self, Response},
+    settings::Settings,
+    ScenarioList,
+};
+
+/// Content type of the `OpenMetrics` text exposition format.
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Metrics of a single scenario as exposed to Prometheus.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScenarioMetrics {
+    pub id: String,
+    pub status: String,
+    pub epoch: Option<usize>,
+    pub progress: f32,
+    pub epochs_per_second: Option<f32>,
+    pub loss: Option<f32>,
+    pub loss_mse: Option<f32>,
+    // zero for scenarios that do not run on the GPU
+    pub gpu_memory_bytes: usize,
+}
+
+impl ScenarioMetrics {
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn from_scenario(scenario: &Scenario) -> Self {
+        let epoch = match scenario.get_status() {
+            Status::Running(epoch) => Some(*epoch),
+            _ => None,
+        };
+        let summary = scenario.summary.as_ref();
+        Self {
+            id: scenario.get_id().clone(),
+            status: scenario.get_status_str(),
+            epoch,
+            progress: scenario.get_progress(),
+            epochs_per_second: scenario.get_epochs_per_second(),
+            loss: summary.map(|summary| summary.loss),
+            loss_mse: summary.map(|summary| summary.loss_mse),
+            gpu_memory_bytes: summary.map_or(0, |summary| summary.gpu_memory_bytes),
+        }
+    }
+}
+
+/// Prometheus exporter for the progress of the scenarios.
+///
+/// Serves the metrics of all scenarios in the `OpenMetrics` text format on
+/// `/metrics` if a `metrics_endpoint` is configured in the [`Settings`].
+#[derive(Resource, Debug)]
+pub struct MetricsExporter {
+    scenarios: Option<Arc<Mutex<Vec<ScenarioMetrics>>>>,
+}
+
+impl MetricsExporter {
+    /// Creates an exporter that is not serving anything.
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self { scenarios: None }
+    }
+
+    /// Starts the exporter on the given address.
+    ///
+    /// Failures are only logged, the exporter is disabled in that case.
+    #[must_use]
+    #[tracing::instrument(level = "info")]
+    pub fn start(address: &str) -> Self {
+        let scenarios = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::clone(&scenarios);
+        let result = http::serve(address, move |path| {
+            let scenarios = shared
+                .lock()
+                .map_err(|e| anyhow::anyhow!("Failed to acquire metrics lock: {e}"))?;
+            respond(path, &scenarios)
+        });
+        match result {
+            Ok(()) => {
+                info!("Serving metrics on http://{address}/metrics");
+                Self {
+                    scenarios: Some(scenarios),
+                }
+            }
+            Err(e) => {
+                warn!("Failed to start metrics exporter on {address}: {e:#}");
+                Self::disabled()
+            }
+        }
+    }
+
+    /// Returns true if the exporter is serving metrics.
+    #[must_use]
+    pub const fn is_running(&self) -> bool {
+        self.scenarios.is_some()
+    }
+}
+
+impl FromWorld for MetricsExporter {
+    /// Starts the exporter if a `metrics_endpoint` is configured in the
+    /// [`Settings`], otherwise the exporter is disabled.
+    #[tracing::instrument(level = "info", skip_all)]
+    fn from_world(world: &mut World) -> Self {
+        world
+            .get_resource_or_init::<Settings>()
+            .metrics_endpoint
+            .clone()
+            .map_or_else(Self::disabled, |address| Self::start(&address))
+    }
+}
+
+/// Copies the metrics of all scenarios into the snapshot served by the
+/// exporter.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn update_metrics(exporter: Res<MetricsExporter>, scenario_list: Res<ScenarioList>) {
+    trace!("Running update_metrics system.");
+    let Some(scenarios) = exporter.scenarios.as_ref() else {
+        return;
+    };
+    let snapshot = scenario_list
+        .entries
+        .iter()
+        .map(|entry| ScenarioMetrics::from_scenario(&entry.scenario))
+        .collect();
+    match scenarios.lock() {
+        Ok(mut scenarios) => *scenarios = snapshot,
+        Err(e) => warn!("Failed to acquire metrics lock: {}", e),
+    }
+}
+
+/// Returns the metrics on `/metrics` and 404 for any other path.
+#[tracing::instrument(level = "trace", skip(scenarios))]
+fn respond(path: &str, scenarios: &[ScenarioMetrics]) -> Result<Response> {
+    if path.trim_end_matches('/') == "/metrics" {
+        Ok(Response::ok(OPENMETRICS_CONTENT_TYPE, render(scenarios)))
+    } else {
+        Ok(Response::not_found())
+    }
+}
+
+/// Renders the metrics of the scenarios in the `OpenMetrics` text format.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn render(scenarios: &[ScenarioMetrics]) -> String {
+    let mut output = String::new();
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for scenario in scenarios {
+        *counts.entry(scenario.status.as_str()).or_default() += 1;
+    }
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenarios",
+        "Number of scenarios per status.",
+        counts
+            .iter()
+            .map(|(status, count)| (format!("status=\"{}\"", escape(status)), *count as f64)),
+    );
+
+    let label = |scenario: &ScenarioMetrics| format!("scenario=\"{}\"", escape(&scenario.id));
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_status",
+        "Current status of the scenario, always 1.",
+        scenarios.iter().map(|scenario| {
+            (
+                format!(
+                    "{},status=\"{}\"",
+                    label(scenario),
+                    escape(&scenario.status)
+                ),
+                1.0,
+            )
+        }),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_epoch",
+        "Current epoch of the running scenario.",
+        scenarios
+            .iter()
+            .filter_map(|scenario| scenario.epoch.map(|epoch| (label(scenario), epoch as f64))),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_progress",
+        "Fraction of the epochs the running scenario has completed.",
+        scenarios
+            .iter()
+            .map(|scenario| (label(scenario), f64::from(scenario.progress))),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_epochs_per_second",
+        "Average number of epochs per second since the scenario started.",
+        scenarios.iter().filter_map(|scenario| {
+            scenario
+                .epochs_per_second
+                .map(|rate| (label(scenario), f64::from(rate)))
+        }),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_loss",
+        "Total loss of the latest epoch.",
+        scenarios
+            .iter()
+            .filter_map(|scenario| scenario.loss.map(|loss| (label(scenario), f64::from(loss)))),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_loss_mse",
+        "MSE loss of the latest epoch.",
+        scenarios.iter().filter_map(|scenario| {
+            scenario
+                .loss_mse
+                .map(|loss| (label(scenario), f64::from(loss)))
+        }),
+    );
+    write_gauge(
+        &mut output,
+        "cardiotrust_scenario_gpu_memory_bytes",
+        "Device memory occupied by the GPU algorithm of the scenario.",
+        scenarios
+            .iter()
+            .filter(|scenario| scenario.gpu_memory_bytes > 0)
+            .map(|scenario| (label(scenario), scenario.gpu_memory_bytes as f64)),
+    );
+
+    output.push_str("# EOF\n");
+    output
+}
+
+/// Writes the metadata and samples of a gauge, skipping gauges without
+/// samples.
+#[tracing::instrument(level = "trace", skip_all)]
+fn write_gauge(
+    output: &mut String,
+    name: &str,
+    help: &str,
+    samples: impl Iterator<Item = (String, f64)>,
+) {
+    let mut samples = samples.peekable();
+    if samples.peek().is_none() {
+        return;
+    }
+    // writing to a string cannot fail
+    let _ = writeln!(output, "# TYPE {name} gauge");
+    let _ = writeln!(output, "# HELP {name} {help}");
+    for (labels, value) in samples {
+        let _ = writeln!(output, "{name}{{{labels}}} {}", format_value(value));
+    }
+}
+
+/// Formats a sample value, using the spelling of `OpenMetrics` for
+/// non-finite numbers.
+#[tracing::instrument(level = "trace")]
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escapes a label value.
+#[tracing::instrument(level = "trace")]
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn running_scenario() -> ScenarioMetrics {
+        ScenarioMetrics {
+            id: "2024-01-01-00-00-00".to_string(),
+            status: "Running".to_string(),
+            epoch: Some(3),
+            progress: 0.5,
+            epochs_per_second: Some(2.0),
+            loss: Some(f32::INFINITY),
+            loss_mse: None,
+            gpu_memory_bytes: 1024,
+        }
+    }
+
+    #[test]
+    fn metrics_are_rendered_as_openmetrics() {
+        let output = render(&[running_scenario()]);
+
+        assert!(output.contains("# TYPE cardiotrust_scenario_progress gauge\n"));
+        assert!(output.contains("cardiotrust_scenarios{status=\"Running\"} 1\n"));
+        assert!(output
+            .contains("cardiotrust_scenario_progress{scenario=\"2024-01-01-00-00-00\"} 0.5\n"));
+        assert!(output.contains(
+            "cardiotrust_scenario_epochs_per_second{scenario=\"2024-01-01-00-00-00\"} 2\n"
+        ));
+        assert!(
+            output.contains("cardiotrust_scenario_loss{scenario=\"2024-01-01-00-00-00\"} +Inf\n")
+        );
+        assert!(output.contains(
+            "cardiotrust_scenario_gpu_memory_bytes{scenario=\"2024-01-01-00-00-00\"} 1024\n"
+        ));
+        assert!(!output.contains("cardiotrust_scenario_loss_mse"));
+        assert!(output.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn only_metrics_path_is_served() -> anyhow::Result<()> {
+        let scenarios = [running_scenario()];
+        assert_eq!(respond("/metrics", &scenarios)?.status, "200 OK");
+        assert_eq!(respond("/", &scenarios)?.status, "404 Not Found");
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+        Ok(())
+    }
+}
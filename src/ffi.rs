@@ -0,0 +1,291 @@
+//! C-compatible API of the forward model, enabled with the `ffi` feature.
+//!
+//! A model is created from a config file, simulated, and its outputs are
+//! copied into buffers provided by the caller. All arrays are written in
+//! row-major (C) order. Functions return `0` on success and `-1` on failure,
+//! in which case [`cardiotrust_last_error`] describes the failure. Panics are
+//! caught at the boundary and reported as failures, as unwinding into C is
+//! undefined behavior. The header
+//! `include/cardiotrust.h` is generated with cbindgen, see the justfile.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    fs,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    ptr, slice,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::{debug, error};
+
+use crate::core::{
+    config::{simulation::Simulation as SimulationConfig, Config},
+    data::simulation::Simulation,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Opaque handle of a forward model and its simulated outputs.
+pub struct CardiotrustModel {
+    simulation: Simulation,
+}
+
+/// Dimensions of the arrays written by [`cardiotrust_model_simulate`].
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CardiotrustDimensions {
+    pub number_of_beats: usize,
+    pub number_of_steps: usize,
+    pub number_of_sensors: usize,
+    pub number_of_states: usize,
+}
+
+/// Creates a forward model from a TOML config file, either a full scenario
+/// config or only its `simulation` table.
+///
+/// Returns a null pointer on failure. The model must be released with
+/// [`cardiotrust_model_free`].
+///
+/// # Safety
+///
+/// `config_path` must be a valid, null-terminated UTF-8 string.
+#[no_mangle]
+#[tracing::instrument(level = "debug")]
+pub unsafe extern "C" fn cardiotrust_model_create(
+    config_path: *const c_char,
+) -> *mut CardiotrustModel {
+    debug!("Creating forward model through the C API");
+    catch_panic(ptr::null_mut(), || {
+        if config_path.is_null() {
+            set_last_error(&anyhow!("Config path must not be null"));
+            return ptr::null_mut();
+        }
+        // SAFETY: the caller guarantees a valid null-terminated string
+        let config_path = unsafe { CStr::from_ptr(config_path) };
+        match create_model(config_path) {
+            Ok(model) => Box::into_raw(Box::new(model)),
+            Err(e) => {
+                set_last_error(&e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Releases a model created with [`cardiotrust_model_create`]. Passing a
+/// null pointer does nothing.
+///
+/// # Safety
+///
+/// `model` must be null or a pointer returned by
+/// [`cardiotrust_model_create`] that was not released before.
+#[no_mangle]
+#[tracing::instrument(level = "debug")]
+pub unsafe extern "C" fn cardiotrust_model_free(model: *mut CardiotrustModel) {
+    debug!("Releasing forward model through the C API");
+    catch_panic((), || {
+        if !model.is_null() {
+            // SAFETY: the caller guarantees the pointer stems from Box::into_raw
+            drop(unsafe { Box::from_raw(model) });
+        }
+    });
+}
+
+/// Writes the dimensions of the simulated arrays of the model.
+///
+/// # Safety
+///
+/// `model` must be a valid model and `dimensions` a valid pointer.
+#[no_mangle]
+#[tracing::instrument(level = "trace")]
+pub unsafe extern "C" fn cardiotrust_model_dimensions(
+    model: *const CardiotrustModel,
+    dimensions: *mut CardiotrustDimensions,
+) -> c_int {
+    catch_panic(-1, || {
+        // SAFETY: the caller guarantees valid pointers
+        let (Some(model), Some(dimensions)) =
+            (unsafe { model.as_ref() }, unsafe { dimensions.as_mut() })
+        else {
+            set_last_error(&anyhow!("Model and dimensions must not be null"));
+            return -1;
+        };
+        *dimensions = model.dimensions();
+        0
+    })
+}
+
+/// Runs the forward simulation and copies the measurements of shape
+/// (beats, steps, sensors) and, if `system_states` is not null, the system
+/// states of shape (steps, states) into the given buffers.
+///
+/// The buffer lengths are given in elements and must match the dimensions
+/// returned by [`cardiotrust_model_dimensions`].
+///
+/// # Safety
+///
+/// `model` must be a valid model. `measurements` must point to at least
+/// `measurements_len` writable floats, `system_states` must be null or point
+/// to at least `system_states_len` writable floats.
+#[no_mangle]
+#[tracing::instrument(level = "debug")]
+pub unsafe extern "C" fn cardiotrust_model_simulate(
+    model: *mut CardiotrustModel,
+    measurements: *mut f32,
+    measurements_len: usize,
+    system_states: *mut f32,
+    system_states_len: usize,
+) -> c_int {
+    debug!("Running forward simulation through the C API");
+    catch_panic(-1, || {
+        // SAFETY: the caller guarantees a valid model pointer
+        let Some(model) = (unsafe { model.as_mut() }) else {
+            set_last_error(&anyhow!("Model must not be null"));
+            return -1;
+        };
+        if measurements.is_null() {
+            set_last_error(&anyhow!("Measurement buffer must not be null"));
+            return -1;
+        }
+        // SAFETY: the caller guarantees the buffer lengths
+        let measurements = unsafe { slice::from_raw_parts_mut(measurements, measurements_len) };
+        let system_states = (!system_states.is_null())
+            .then(|| unsafe { slice::from_raw_parts_mut(system_states, system_states_len) });
+        match model.simulate(measurements, system_states) {
+            Ok(()) => 0,
+            Err(e) => {
+                set_last_error(&e);
+                -1
+            }
+        }
+    })
+}
+
+/// Returns the message of the last failure on the calling thread, or null if
+/// no call failed yet. The string stays valid until the next failing call on
+/// the same thread.
+#[no_mangle]
+#[tracing::instrument(level = "trace")]
+pub extern "C" fn cardiotrust_last_error() -> *const c_char {
+    catch_panic(ptr::null(), || {
+        LAST_ERROR.with(|last_error| {
+            last_error
+                .borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+impl CardiotrustModel {
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn dimensions(&self) -> CardiotrustDimensions {
+        let shape = self.simulation.measurements.shape();
+        CardiotrustDimensions {
+            number_of_beats: shape[0],
+            number_of_steps: shape[1],
+            number_of_sensors: shape[2],
+            number_of_states: self.simulation.system_states.shape()[1],
+        }
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn simulate(
+        &mut self,
+        measurements: &mut [f32],
+        system_states: Option<&mut [f32]>,
+    ) -> Result<()> {
+        check_buffer_length(
+            "measurement",
+            measurements.len(),
+            self.simulation.measurements.len(),
+        )?;
+        if let Some(system_states) = &system_states {
+            check_buffer_length(
+                "system state",
+                system_states.len(),
+                self.simulation.system_states.len(),
+            )?;
+        }
+        self.simulation.run()?;
+        measurements.copy_from_slice(
+            self.simulation
+                .measurements
+                .as_slice()
+                .context("Measurements are not contiguous")?,
+        );
+        if let Some(system_states) = system_states {
+            system_states.copy_from_slice(
+                self.simulation
+                    .system_states
+                    .as_slice()
+                    .context("System states are not contiguous")?,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[tracing::instrument(level = "trace")]
+fn check_buffer_length(name: &str, length: usize, expected: usize) -> Result<()> {
+    if length == expected {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "The {name} buffer holds {length} elements, but {expected} are required"
+        ))
+    }
+}
+
+#[tracing::instrument(level = "debug")]
+fn create_model(config_path: &CStr) -> Result<CardiotrustModel> {
+    let config_path = Path::new(
+        config_path
+            .to_str()
+            .context("Config path is not valid UTF-8")?,
+    );
+    let content = fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read config file: {}", config_path.display()))?;
+    let config = match toml::from_str::<Config>(&content) {
+        Ok(config) => config.simulation,
+        Err(config_error) => {
+            toml::from_str::<SimulationConfig>(&content).map_err(|simulation_error| {
+                anyhow!(
+                    "Failed to parse config file: {}\n\
+                    as a scenario config: {config_error}\n\
+                    as a simulation config: {simulation_error}",
+                    config_path.display()
+                )
+            })?
+        }
+    };
+    Ok(CardiotrustModel {
+        simulation: Simulation::from_config(&config)?,
+    })
+}
+
+/// Runs the body of a C API function and returns `on_panic` with the panic
+/// message as the last error if it panics.
+#[tracing::instrument(level = "trace", skip_all)]
+fn catch_panic<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        set_last_error(&anyhow!("C API call panicked: {message}"));
+        on_panic
+    })
+}
+
+#[tracing::instrument(level = "trace")]
+fn set_last_error(error: &anyhow::Error) {
+    error!("C API call failed: {:#}", error);
+    let message = CString::new(format!("{error:#}").replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
@@ -0,0 +1,194 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+/// Time a client may take to send its request or receive the response, so
+/// a stalled client cannot block the single serving thread.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Response of the embedded HTTP servers of the dashboard and the metrics
+/// exporter.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    /// Creates a successful response with the given body.
+    #[must_use]
+    pub const fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: "200 OK",
+            content_type,
+            body,
+        }
+    }
+
+    /// Creates a response for an unknown path.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn not_found() -> Self {
+        Self {
+            status: "404 Not Found",
+            content_type: "text/plain",
+            body: "Not found".to_string(),
+        }
+    }
+
+    /// Creates a response for any method other than GET.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn method_not_allowed() -> Self {
+        Self {
+            status: "405 Method Not Allowed",
+            content_type: "text/plain",
+            body: "Method not allowed".to_string(),
+        }
+    }
+
+    /// Creates a response for a request the handler failed to answer.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn internal_server_error() -> Self {
+        Self {
+            status: "500 Internal Server Error",
+            content_type: "text/plain",
+            body: "Internal server error".to_string(),
+        }
+    }
+}
+
+/// Binds the listener and answers GET requests from a separate thread.
+///
+/// The handler receives the request path and every connection is closed
+/// after the response, which is all the dashboard and Prometheus need.
+/// Connections time out after a few seconds and a failing handler is
+/// answered with a 500 response.
+///
+/// # Errors
+///
+/// Returns an error if the address cannot be bound.
+#[tracing::instrument(level = "debug", skip(handler))]
+pub fn serve<F>(address: &str, handler: F) -> Result<()>
+where
+    F: Fn(&str) -> Result<Response> + Send + 'static,
+{
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("Failed to bind to {address}"))?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream
+                .context("Failed to accept connection")
+                .and_then(|stream| handle(stream, &handler));
+            if let Err(e) = result {
+                warn!("HTTP request failed: {e:#}");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn handle<F>(mut stream: TcpStream, handler: &F) -> Result<()>
+where
+    F: Fn(&str) -> Result<Response>,
+{
+    stream
+        .set_read_timeout(Some(CONNECTION_TIMEOUT))
+        .context("Failed to set read timeout")?;
+    stream
+        .set_write_timeout(Some(CONNECTION_TIMEOUT))
+        .context("Failed to set write timeout")?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .context("Failed to read request line")?;
+    // skip the headers, GET requests never carry a body
+    let mut header = String::new();
+    while reader
+        .read_line(&mut header)
+        .context("Failed to read header")?
+        > 2
+    {
+        header.clear();
+    }
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    debug!("HTTP request: {method} {path}");
+
+    let response = if method == "GET" {
+        // the query string is not used by any of the endpoints
+        handler(path.split('?').next().unwrap_or_default()).unwrap_or_else(|e| {
+            warn!("HTTP handler failed for {path}: {e:#}");
+            Response::internal_server_error()
+        })
+    } else {
+        Response::method_not_allowed()
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+    .context("Failed to write response")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[tracing::instrument(level = "trace")]
+    fn get(address: &str, path: &str) -> Result<String> {
+        let mut stream = TcpStream::connect(address).context("Failed to connect")?;
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .context("Failed to send request")?;
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .context("Failed to read response")?;
+        Ok(response)
+    }
+
+    #[test]
+    fn failing_handler_answers_with_internal_server_error() -> Result<()> {
+        let address = "127.0.0.1:47831";
+        serve(address, |path| {
+            anyhow::ensure!(path == "/ok", "Unknown path {path}");
+            Ok(Response::ok("text/plain", "fine".to_string()))
+        })?;
+
+        assert!(get(address, "/fail")?.starts_with("HTTP/1.1 500 Internal Server Error"));
+        assert!(get(address, "/ok")?.ends_with("fine"));
+        Ok(())
+    }
+
+    #[test]
+    fn silent_client_does_not_block_the_server() -> Result<()> {
+        let address = "127.0.0.1:47832";
+        serve(address, |_| {
+            Ok(Response::ok("text/plain", "fine".to_string()))
+        })?;
+
+        // connects but never sends a request
+        let _silent = TcpStream::connect(address).context("Failed to connect")?;
+        assert!(get(address, "/")?.ends_with("fine"));
+        Ok(())
+    }
+}
@@ -0,0 +1,582 @@
+//! Ingestion of sensor samples streamed by an acquisition system.
+//!
+//! Over TCP and UDP every sample is sent as a frame of a little-endian `u32`
+//! sequence number followed by one little-endian `f32` per sensor. The
+//! subscriber connects to a TCP server, or binds the given address and
+//! receives UDP datagrams holding one or more whole frames. Lab streaming
+//! layer (LSL) outlets are resolved by name and require the `lsl` feature.
+//!
+//! Received samples are buffered into the [`Measurements`] shape for
+//! recording to disk and can be forwarded to the streaming estimator.
+
+use std::{
+    fmt,
+    io::{ErrorKind, Read},
+    net::{TcpStream, UdpSocket},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{sync_channel, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::data::shapes::Measurements;
+
+// how long a read blocks before checking for a stop request
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(100);
+// interval over which the sample rate is measured
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const SEQUENCE_BYTES: usize = 4;
+const MAX_DATAGRAM_BYTES: usize = 65_507;
+
+/// Transport over which the samples arrive.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum StreamProtocol {
+    #[default]
+    Tcp,
+    Udp,
+    Lsl,
+}
+
+impl fmt::Display for StreamProtocol {
+    #[tracing::instrument(level = "trace", skip(f))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "TCP"),
+            Self::Udp => write!(f, "UDP"),
+            Self::Lsl => write!(f, "LSL"),
+        }
+    }
+}
+
+/// Where and how to subscribe to a sample stream.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamSettings {
+    pub protocol: StreamProtocol,
+    // host:port of the TCP server or local UDP address, name of the LSL
+    // stream
+    pub address: String,
+    pub number_of_sensors: usize,
+    // nominal rate of the stream, used to count dropouts of LSL streams
+    pub sample_rate_hz: f32,
+}
+
+impl Default for StreamSettings {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default stream settings");
+        Self {
+            protocol: StreamProtocol::Tcp,
+            address: "127.0.0.1:5000".to_string(),
+            number_of_sensors: 1,
+            sample_rate_hz: 2000.0,
+        }
+    }
+}
+
+/// Counters for monitoring a running subscription.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct StreamStatistics {
+    pub received: usize,
+    // samples missing according to the sequence numbers or timestamps
+    pub dropped: usize,
+    // samples per second over the last rate window
+    pub rate_hz: f32,
+}
+
+/// A running subscription that receives samples on a separate thread.
+#[derive(Debug)]
+pub struct StreamSubscriber {
+    stop: Arc<AtomicBool>,
+    statistics: Arc<Mutex<StreamStatistics>>,
+    handle: Option<JoinHandle<Result<Array2<f32>>>>,
+    number_of_sensors: usize,
+}
+
+impl StreamSubscriber {
+    /// Connects to the stream and starts receiving samples, forwarding every
+    /// sample to `forward` if given.
+    ///
+    /// Blocks until the connection is established.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established.
+    #[tracing::instrument(level = "info", skip(forward))]
+    pub fn start(settings: &StreamSettings, forward: Option<Sender<Array1<f32>>>) -> Result<Self> {
+        info!(
+            "Subscribing to {} stream at {}",
+            settings.protocol, settings.address
+        );
+        anyhow::ensure!(
+            settings.number_of_sensors > 0,
+            "The stream must have at least one sensor"
+        );
+        let stop = Arc::new(AtomicBool::new(false));
+        let statistics = Arc::new(Mutex::new(StreamStatistics::default()));
+        let (ready_tx, ready_rx) = sync_channel(1);
+        let handle = {
+            let settings = settings.clone();
+            let stop = Arc::clone(&stop);
+            let statistics = Arc::clone(&statistics);
+            thread::spawn(move || {
+                let connection = match Connection::open(&settings) {
+                    Ok(connection) => {
+                        let _ = ready_tx.send(Ok(()));
+                        connection
+                    }
+                    Err(e) => {
+                        let message = format!("{e:#}");
+                        let _ = ready_tx.send(Err(e));
+                        anyhow::bail!(message);
+                    }
+                };
+                receive(
+                    connection,
+                    settings.number_of_sensors,
+                    forward.as_ref(),
+                    &stop,
+                    &statistics,
+                )
+            })
+        };
+        ready_rx
+            .recv()
+            .context("Stream thread ended before connecting")??;
+        Ok(Self {
+            stop,
+            statistics,
+            handle: Some(handle),
+            number_of_sensors: settings.number_of_sensors,
+        })
+    }
+
+    /// Returns the current counters of the subscription.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn statistics(&self) -> StreamStatistics {
+        self.statistics
+            .lock()
+            .map(|statistics| *statistics)
+            .unwrap_or_default()
+    }
+
+    /// Whether the receiving thread is still running.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn is_running(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Stops the subscription and returns the received samples as
+    /// measurements of a single beat with shape (1, steps, sensors).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if receiving failed.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn stop(mut self) -> Result<Measurements> {
+        info!("Stopping stream subscription");
+        self.stop.store(true, Ordering::Relaxed);
+        let samples = self
+            .handle
+            .take()
+            .context("Stream subscription was already stopped")?
+            .join()
+            .map_err(|_| anyhow::anyhow!("Stream thread panicked"))??;
+        let mut measurements = Measurements::empty(1, samples.nrows(), self.number_of_sensors);
+        measurements.at_beat_mut(0).assign(&samples);
+        Ok(measurements)
+    }
+
+    /// Stops the subscription and saves the received samples as
+    /// `measurements.npy` in the given directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if receiving failed or the file cannot be written.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn stop_and_record(self, directory: &Path) -> Result<Measurements> {
+        let measurements = self.stop()?;
+        measurements.save_npy(directory)?;
+        info!(
+            "Recorded {} samples to {}",
+            measurements.num_steps(),
+            directory.display()
+        );
+        Ok(measurements)
+    }
+}
+
+impl Drop for StreamSubscriber {
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns the names of the LSL streams visible on the network.
+///
+/// # Errors
+///
+/// Returns an error if resolving fails or the application was built without
+/// the `lsl` feature.
+#[tracing::instrument(level = "debug")]
+pub fn discover_lsl_streams() -> Result<Vec<String>> {
+    debug!("Discovering LSL streams");
+    #[cfg(feature = "lsl")]
+    {
+        let streams = lsl::resolve_streams(1.0)
+            .map_err(|e| anyhow::anyhow!("Failed to resolve LSL streams: {e:?}"))?;
+        Ok(streams.iter().map(lsl::StreamInfo::stream_name).collect())
+    }
+    #[cfg(not(feature = "lsl"))]
+    anyhow::bail!("The application was built without the lsl feature")
+}
+
+/// A sample and its position in the stream.
+#[derive(Debug, PartialEq)]
+struct Frame {
+    sequence: u64,
+    values: Array1<f32>,
+}
+
+enum Connection {
+    Tcp {
+        stream: TcpStream,
+        pending: Vec<u8>,
+    },
+    Udp(UdpSocket),
+    #[cfg(feature = "lsl")]
+    Lsl {
+        inlet: lsl::StreamInlet,
+        sample_rate_hz: f32,
+    },
+}
+
+impl Connection {
+    #[tracing::instrument(level = "debug")]
+    fn open(settings: &StreamSettings) -> Result<Self> {
+        match settings.protocol {
+            StreamProtocol::Tcp => {
+                let stream = TcpStream::connect(&settings.address).with_context(|| {
+                    format!("Failed to connect to TCP stream at {}", settings.address)
+                })?;
+                stream.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+                Ok(Self::Tcp {
+                    stream,
+                    pending: Vec::new(),
+                })
+            }
+            StreamProtocol::Udp => {
+                let socket = UdpSocket::bind(&settings.address).with_context(|| {
+                    format!("Failed to bind UDP stream at {}", settings.address)
+                })?;
+                socket.set_read_timeout(Some(RECEIVE_TIMEOUT))?;
+                Ok(Self::Udp(socket))
+            }
+            #[cfg(feature = "lsl")]
+            StreamProtocol::Lsl => {
+                let streams = lsl::resolve_byprop("name", &settings.address, 1, 5.0)
+                    .map_err(|e| anyhow::anyhow!("Failed to resolve LSL stream: {e:?}"))?;
+                let info = streams
+                    .first()
+                    .with_context(|| format!("No LSL stream named {}", settings.address))?;
+                #[allow(clippy::cast_sign_loss)]
+                let channels = info.channel_count() as usize;
+                anyhow::ensure!(
+                    channels == settings.number_of_sensors,
+                    "LSL stream has {channels} channels, but {} sensors are expected",
+                    settings.number_of_sensors
+                );
+                let inlet = lsl::StreamInlet::new(info, 360, 0, true)
+                    .map_err(|e| anyhow::anyhow!("Failed to open LSL inlet: {e:?}"))?;
+                Ok(Self::Lsl {
+                    inlet,
+                    sample_rate_hz: settings.sample_rate_hz,
+                })
+            }
+            #[cfg(not(feature = "lsl"))]
+            StreamProtocol::Lsl => {
+                anyhow::bail!("The application was built without the lsl feature")
+            }
+        }
+    }
+
+    /// Receives the frames that arrived within the receive timeout, or
+    /// `None` once the sender closed the stream.
+    #[tracing::instrument(level = "trace", skip_all)]
+    fn receive(&mut self, number_of_sensors: usize) -> Result<Option<Vec<Frame>>> {
+        let frame_bytes = SEQUENCE_BYTES + 4 * number_of_sensors;
+        match self {
+            Self::Tcp { stream, pending } => {
+                let mut buffer = [0; 8192];
+                match stream.read(&mut buffer) {
+                    Ok(0) => {
+                        info!("TCP stream was closed by the sender");
+                        return Ok(None);
+                    }
+                    Ok(length) => pending.extend_from_slice(&buffer[..length]),
+                    Err(e) if is_timeout(&e) => return Ok(Some(Vec::new())),
+                    Err(e) => return Err(e).context("Failed to read from TCP stream"),
+                }
+                let complete = pending.len() - pending.len() % frame_bytes;
+                let frames = parse_frames(&pending[..complete], number_of_sensors);
+                pending.drain(..complete);
+                Ok(Some(frames))
+            }
+            Self::Udp(socket) => {
+                let mut buffer = vec![0; MAX_DATAGRAM_BYTES];
+                match socket.recv(&mut buffer) {
+                    Ok(length) => {
+                        if length % frame_bytes != 0 {
+                            warn!("Discarding incomplete frame in UDP datagram of {length} bytes");
+                        }
+                        Ok(Some(parse_frames(&buffer[..length], number_of_sensors)))
+                    }
+                    Err(e) if is_timeout(&e) => Ok(Some(Vec::new())),
+                    Err(e) => Err(e).context("Failed to receive from UDP stream"),
+                }
+            }
+            #[cfg(feature = "lsl")]
+            Self::Lsl {
+                inlet,
+                sample_rate_hz,
+            } => {
+                use lsl::Pullable;
+                let (values, timestamp): (Vec<f32>, f64) = inlet
+                    .pull_sample(RECEIVE_TIMEOUT.as_secs_f64())
+                    .map_err(|e| anyhow::anyhow!("Failed to pull LSL sample: {e:?}"))?;
+                // LSL reports a timeout with a zero timestamp
+                if timestamp <= 0.0 {
+                    return Ok(Some(Vec::new()));
+                }
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let sequence = (timestamp * f64::from(*sample_rate_hz)).round() as u64;
+                Ok(Some(vec![Frame {
+                    sequence,
+                    values: Array1::from(values),
+                }]))
+            }
+        }
+    }
+}
+
+/// Receives frames until a stop is requested or the sender closes the
+/// stream and returns all samples with shape (steps, sensors).
+#[tracing::instrument(level = "debug", skip_all)]
+fn receive(
+    mut connection: Connection,
+    number_of_sensors: usize,
+    forward: Option<&Sender<Array1<f32>>>,
+    stop: &AtomicBool,
+    statistics: &Mutex<StreamStatistics>,
+) -> Result<Array2<f32>> {
+    let mut samples = Vec::new();
+    let mut monitor = DropoutMonitor::default();
+    let mut forward = forward;
+    while !stop.load(Ordering::Relaxed) {
+        let Some(frames) = connection.receive(number_of_sensors)? else {
+            break;
+        };
+        for frame in frames {
+            monitor.record(frame.sequence);
+            samples.extend(frame.values.iter());
+            if let Ok(mut statistics) = statistics.lock() {
+                *statistics = monitor.statistics();
+            }
+            if let Some(sender) = forward {
+                if sender.send(frame.values).is_err() {
+                    warn!("Streaming estimator stopped, no longer forwarding samples");
+                    forward = None;
+                }
+            }
+        }
+        monitor.update_rate();
+        if let Ok(mut statistics) = statistics.lock() {
+            *statistics = monitor.statistics();
+        }
+    }
+    let steps = samples.len() / number_of_sensors;
+    Array2::from_shape_vec((steps, number_of_sensors), samples)
+        .context("Failed to arrange received samples")
+}
+
+/// Counts received and missing samples and measures the sample rate.
+#[derive(Debug)]
+struct DropoutMonitor {
+    last_sequence: Option<u64>,
+    received: usize,
+    dropped: usize,
+    window_start: Instant,
+    window_received: usize,
+    rate_hz: f32,
+}
+
+impl Default for DropoutMonitor {
+    #[tracing::instrument(level = "trace")]
+    fn default() -> Self {
+        Self {
+            last_sequence: None,
+            received: 0,
+            dropped: 0,
+            window_start: Instant::now(),
+            window_received: 0,
+            rate_hz: 0.0,
+        }
+    }
+}
+
+impl DropoutMonitor {
+    #[allow(clippy::cast_possible_truncation)]
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn record(&mut self, sequence: u64) {
+        // a sequence number that does not increase, e.g. after a restart of
+        // the sender, is not counted as dropout
+        if let Some(last) = self.last_sequence {
+            if sequence > last + 1 {
+                self.dropped += (sequence - last - 1) as usize;
+            }
+        }
+        self.last_sequence = Some(sequence);
+        self.received += 1;
+        self.window_received += 1;
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn update_rate(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            self.rate_hz = self.window_received as f32 / elapsed.as_secs_f32();
+            self.window_start = Instant::now();
+            self.window_received = 0;
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    const fn statistics(&self) -> StreamStatistics {
+        StreamStatistics {
+            received: self.received,
+            dropped: self.dropped,
+            rate_hz: self.rate_hz,
+        }
+    }
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn parse_frames(bytes: &[u8], number_of_sensors: usize) -> Vec<Frame> {
+    bytes
+        .chunks_exact(SEQUENCE_BYTES + 4 * number_of_sensors)
+        .map(|frame| {
+            let (sequence, values) = frame.split_at(SEQUENCE_BYTES);
+            Frame {
+                sequence: u64::from(u32::from_le_bytes([
+                    sequence[0],
+                    sequence[1],
+                    sequence[2],
+                    sequence[3],
+                ])),
+                values: values
+                    .chunks_exact(4)
+                    .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[tracing::instrument(level = "trace")]
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, net::TcpListener, sync::mpsc::channel};
+
+    use approx::assert_relative_eq;
+    use ndarray::arr1;
+
+    use super::*;
+
+    fn encode(sequence: u32, values: &[f32]) -> Vec<u8> {
+        let mut bytes = sequence.to_le_bytes().to_vec();
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn frames_are_parsed_from_bytes() {
+        let mut bytes = encode(3, &[1.0, 2.0]);
+        bytes.extend(encode(4, &[3.0, 4.0]));
+
+        let frames = parse_frames(&bytes, 2);
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame {
+                    sequence: 3,
+                    values: arr1(&[1.0, 2.0])
+                },
+                Frame {
+                    sequence: 4,
+                    values: arr1(&[3.0, 4.0])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_in_sequence_are_counted_as_dropouts() {
+        let mut monitor = DropoutMonitor::default();
+
+        for sequence in [0, 1, 4, 5] {
+            monitor.record(sequence);
+        }
+
+        assert_eq!(monitor.statistics().received, 4);
+        assert_eq!(monitor.statistics().dropped, 2);
+    }
+
+    #[test]
+    fn tcp_samples_are_buffered_and_forwarded() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let settings = StreamSettings {
+            protocol: StreamProtocol::Tcp,
+            address: listener.local_addr()?.to_string(),
+            number_of_sensors: 2,
+            ..StreamSettings::default()
+        };
+        let (forward_tx, forward_rx) = channel();
+
+        let subscriber = StreamSubscriber::start(&settings, Some(forward_tx))?;
+        let (mut sender, _) = listener.accept()?;
+        sender.write_all(&encode(0, &[1.0, 2.0]))?;
+        sender.write_all(&encode(2, &[3.0, 4.0]))?;
+        let forwarded = forward_rx.recv_timeout(Duration::from_secs(5))?;
+        forward_rx.recv_timeout(Duration::from_secs(5))?;
+        let statistics = subscriber.statistics();
+        let measurements = subscriber.stop()?;
+
+        assert_eq!(forwarded, arr1(&[1.0, 2.0]));
+        assert_eq!(statistics.dropped, 1);
+        assert_eq!(measurements.shape(), &[1, 2, 2]);
+        assert_relative_eq!(measurements[(0, 1, 1)], 4.0);
+        Ok(())
+    }
+}
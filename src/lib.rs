@@ -11,24 +11,48 @@
     dead_code,
     private_interfaces
 )]
+#[cfg(feature = "benchmarks")]
+pub mod benchmarks;
 pub mod core;
+pub mod dashboard;
+pub mod exporter;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod http;
+pub mod ingest;
+pub mod notification;
+pub mod profiling;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reproducibility;
 pub mod scheduler;
+pub mod settings;
 pub mod tests;
 pub mod ui;
+pub mod validation;
 pub mod vis;
 
 use std::{
+    collections::BTreeSet,
     fs::{self, create_dir_all},
-    path::Path,
+    path::{Path, PathBuf},
     sync::{mpsc::Receiver, Mutex},
     thread::JoinHandle,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use bevy::prelude::*;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-use crate::core::scenario::{summary::Summary, Scenario};
+use crate::{
+    core::scenario::{
+        search::{load_searches, HyperparameterSearch},
+        summary::Summary,
+        template::{load_templates, Template},
+        Scenario,
+    },
+    settings::{results_directory, Settings},
+};
 
 #[derive(Resource, Debug, Default)]
 pub struct SelectedSenario {
@@ -39,13 +63,65 @@ pub struct SelectedSenario {
 pub struct ScenarioBundle {
     pub scenario: Scenario,
     pub join_handle: Option<JoinHandle<()>>,
+    pub simulation_rx: Option<Mutex<Receiver<f32>>>,
     pub epoch_rx: Option<Mutex<Receiver<usize>>>,
     pub summary_rx: Option<Mutex<Receiver<Summary>>>,
 }
 
+impl ScenarioBundle {
+    /// Creates a bundle for a new scenario with a freshly generated ID that
+    /// shares the configuration, comment and tags of this scenario. The new
+    /// scenario is saved, but data, results and status are not copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new scenario could not be saved.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn clone_with_new_id(&self) -> Result<Self> {
+        self.clone_with_new_id_in(results_directory())
+    }
+
+    /// Duplicates the scenario like [`Self::clone_with_new_id`], but creates
+    /// the new scenario in the given root instead of the results directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new scenario could not be saved.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn clone_with_new_id_in(&self, root: &Path) -> Result<Self> {
+        info!("Duplicating scenario with id {}", self.scenario.get_id());
+        let mut scenario = Scenario::build_in(root, None)?;
+        scenario.config = self.scenario.config.clone();
+        scenario.comment.clone_from(&self.scenario.comment);
+        for tag in self.scenario.tags() {
+            scenario.add_tag(tag);
+        }
+        scenario
+            .save()
+            .context("Failed to save duplicated scenario")?;
+        Ok(Self {
+            scenario,
+            join_handle: None,
+            simulation_rx: None,
+            epoch_rx: None,
+            summary_rx: None,
+        })
+    }
+}
+
+/// A scenario directory that could not be loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantinedScenario {
+    pub path: PathBuf,
+    pub error: String,
+    // quarantined scenarios of archive directories cannot be repaired
+    pub read_only: bool,
+}
+
 #[derive(Resource, Debug)]
 pub struct ScenarioList {
     pub entries: Vec<ScenarioBundle>,
+    pub quarantine: Vec<QuarantinedScenario>,
 }
 
 impl ScenarioList {
@@ -53,59 +129,226 @@ impl ScenarioList {
     pub const fn empty() -> Self {
         Self {
             entries: Vec::new(),
+            quarantine: Vec::new(),
         }
     }
 
-    /// Loads existing scenario results from the `./results` directory into a
-    /// [`ScenarioList`], sorting them by scenario ID. Creates the `./results`
-    /// directory if it does not exist.
+    /// Loads existing scenario results from the results directory and the
+    /// archive directories of the [`Settings`] into a [`ScenarioList`],
+    /// sorting them by scenario ID. Scenarios from archive directories are
+    /// read-only. Archive directories that cannot be read are skipped.
     ///
     /// # Errors
     ///
     /// Returns an error if the results directory cannot be created or read.
     #[tracing::instrument(level = "info")]
     pub fn load() -> Result<Self> {
-        info!("Loading scenarios from ./results");
-        let mut scenario_list = Self {
-            entries: Vec::<ScenarioBundle>::new(),
-        };
-        let dir = Path::new("./results");
-        create_dir_all(dir).context("Failed to create ./results directory")?;
+        let settings = Settings::global();
+        let mut scenario_list = Self::load_from(&settings.results_directory, false)?;
+        for root in &settings.archive_directories {
+            match Self::load_from(root, true) {
+                Ok(archive) => {
+                    scenario_list.entries.extend(archive.entries);
+                    scenario_list.quarantine.extend(archive.quarantine);
+                }
+                Err(e) => warn!("Failed to load archive directory {}: {}", root.display(), e),
+            }
+        }
+        scenario_list
+            .entries
+            .sort_by_key(|entry| entry.scenario.get_id().clone());
+        Ok(scenario_list)
+    }
 
-        let dir_entries = fs::read_dir(dir).context("Failed to read ./results directory")?;
+    /// Loads the scenarios in the given root directory into a
+    /// [`ScenarioList`], sorting them by scenario ID. Creates the directory
+    /// if it does not exist and is not read-only.
+    ///
+    /// Scenarios that were left running by a previous session are marked as
+    /// interrupted, since no worker thread exists for them anymore. The
+    /// change is only saved if the root is not read-only.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or read.
+    #[tracing::instrument(level = "info")]
+    pub fn load_from(root: &Path, read_only: bool) -> Result<Self> {
+        info!("Loading scenarios from {}", root.display());
+        let mut scenario_list = Self::empty();
+        if !read_only {
+            create_dir_all(root)
+                .with_context(|| format!("Failed to create directory {}", root.display()))?;
+        }
+
+        let dir_entries = fs::read_dir(root)
+            .with_context(|| format!("Failed to read directory {}", root.display()))?;
 
+        let mut number_of_interrupted = 0;
         for entry in dir_entries {
             let entry = entry.context("Failed to read directory entry")?;
             let path = entry.path();
             if path.is_dir() {
                 match Scenario::load(&path) {
-                    Ok(scenario) => {
+                    Ok(mut scenario) => {
+                        scenario.set_read_only(read_only);
+                        if scenario.mark_interrupted_if_stale() {
+                            number_of_interrupted += 1;
+                            scenario.restore_autosave_progress();
+                            if !read_only {
+                                if let Err(e) = scenario.save() {
+                                    warn!(
+                                        "Failed to save interrupted scenario {}: {}",
+                                        scenario.get_id(),
+                                        e
+                                    );
+                                }
+                            }
+                        }
                         scenario_list.entries.push(ScenarioBundle {
                             scenario,
                             join_handle: None,
+                            simulation_rx: None,
                             epoch_rx: None,
                             summary_rx: None,
                         });
                     }
                     Err(e) => {
-                        warn!("Failed to load scenario from {}: {}", path.display(), e);
+                        warn!("Failed to load scenario from {}: {:#}", path.display(), e);
+                        scenario_list.quarantine.push(QuarantinedScenario {
+                            path,
+                            error: format!("{e:#}"),
+                            read_only,
+                        });
                     }
                 }
             }
         }
+        if number_of_interrupted > 0 {
+            warn!(
+                "Found {} scenario(s) interrupted by a previous session",
+                number_of_interrupted
+            );
+        }
         if !scenario_list.entries.is_empty() {
             scenario_list
                 .entries
                 .sort_by_key(|entry| entry.scenario.get_id().clone());
         }
+        scenario_list.quarantine.sort_by(|a, b| a.path.cmp(&b.path));
         Ok(scenario_list)
     }
+
+    /// Attempts to repair the quarantined scenario at the given index, see
+    /// [`Scenario::repair`]. On success the scenario is moved from the
+    /// quarantine to the end of the entries, on failure the error of the quarantined
+    /// scenario is updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the index is out of bounds, the scenario belongs
+    /// to a read-only archive directory or the repair failed.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn repair(&mut self, index: usize) -> Result<()> {
+        let quarantined = self
+            .quarantine
+            .get_mut(index)
+            .with_context(|| format!("No quarantined scenario at index {index}"))?;
+        if quarantined.read_only {
+            bail!(
+                "Scenario in {} belongs to a read-only archive directory",
+                quarantined.path.display()
+            );
+        }
+        let scenario = match Scenario::repair(&quarantined.path) {
+            Ok(scenario) => scenario,
+            Err(e) => {
+                quarantined.error = format!("{e:#}");
+                return Err(e);
+            }
+        };
+        self.quarantine.remove(index);
+        self.entries.push(ScenarioBundle {
+            scenario,
+            join_handle: None,
+            simulation_rx: None,
+            epoch_rx: None,
+            summary_rx: None,
+        });
+        Ok(())
+    }
+
+    /// Returns the disk space occupied by the scenarios with the given ids
+    /// in bytes.
+    #[must_use]
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn disk_usage_bytes(&self, ids: &BTreeSet<String>) -> u64 {
+        self.entries
+            .iter()
+            .filter(|entry| ids.contains(entry.scenario.get_id()))
+            .map(|entry| entry.scenario.disk_usage_bytes())
+            .sum()
+    }
+
+    /// Deletes the scenarios with the given ids and removes them from the
+    /// list. Returns the number of deleted scenarios.
+    ///
+    /// Read-only and running scenarios are skipped, failures are logged and
+    /// keep the scenario in the list.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn delete(&mut self, ids: &BTreeSet<String>) -> usize {
+        let number_of_entries = self.entries.len();
+        self.entries.retain(|entry| {
+            let scenario = &entry.scenario;
+            if !ids.contains(scenario.get_id())
+                || scenario.is_read_only()
+                || entry.join_handle.is_some()
+            {
+                return true;
+            }
+            match scenario.delete() {
+                Ok(()) => false,
+                Err(e) => {
+                    warn!("Failed to delete scenario {}: {}", scenario.get_id(), e);
+                    true
+                }
+            }
+        });
+        number_of_entries - self.entries.len()
+    }
+
+    /// Moves the scenarios with the given ids into the archive directory,
+    /// see [`Scenario::move_to_archive`], and replaces their entries with
+    /// the read-only archived scenarios. Returns the number of archived
+    /// scenarios.
+    ///
+    /// Read-only and running scenarios are skipped, failures are logged and
+    /// keep the scenario in the results directory.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn archive(&mut self, ids: &BTreeSet<String>, archive_root: &Path) -> usize {
+        let mut number_of_archived = 0;
+        for entry in &mut self.entries {
+            let scenario = &entry.scenario;
+            if !ids.contains(scenario.get_id())
+                || scenario.is_read_only()
+                || entry.join_handle.is_some()
+            {
+                continue;
+            }
+            match scenario.move_to_archive(archive_root) {
+                Ok(archived) => {
+                    entry.scenario = archived;
+                    number_of_archived += 1;
+                }
+                Err(e) => warn!("Failed to archive scenario {}: {:#}", scenario.get_id(), e),
+            }
+        }
+        number_of_archived
+    }
 }
 
 impl Default for ScenarioList {
-    /// Loads existing scenario results from the `./results` directory into a
-    /// [`ScenarioList`], sorting them by scenario ID. Creates the `./results`
-    /// directory if it does not exist.
+    /// Loads existing scenario results from the results and archive
+    /// directories into a [`ScenarioList`], see [`ScenarioList::load`].
     ///
     /// This provides the default initialized state for the scenario list resource,
     /// populated from any existing results. If loading fails, returns an empty list.
@@ -114,9 +357,80 @@ impl Default for ScenarioList {
         match Self::load() {
             Ok(scenario_list) => scenario_list,
             Err(e) => {
-                warn!("Failed to load scenarios from ./results directory: {}", e);
+                warn!("Failed to load scenarios from results directory: {}", e);
                 Self::empty()
             }
         }
     }
 }
+
+/// The scenario templates available in the `./templates` directory.
+#[derive(Resource, Debug)]
+pub struct TemplateList {
+    pub entries: Vec<Template>,
+}
+
+impl TemplateList {
+    /// Adds the template to the list, replacing an entry with the same name,
+    /// and keeps the list sorted by name.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn insert(&mut self, template: Template) {
+        debug!("Inserting template {} into template list", template.name);
+        self.entries.retain(|entry| entry.name != template.name);
+        self.entries.push(template);
+        self.entries.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+impl Default for TemplateList {
+    /// Loads the templates from the `./templates` directory. If loading
+    /// fails, returns an empty list.
+    #[tracing::instrument(level = "info")]
+    fn default() -> Self {
+        match load_templates() {
+            Ok(entries) => Self { entries },
+            Err(e) => {
+                warn!("Failed to load templates from ./templates directory: {}", e);
+                Self {
+                    entries: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+/// The hyperparameter searches persisted in the results directory.
+#[derive(Resource, Debug)]
+pub struct SearchList {
+    pub entries: Vec<HyperparameterSearch>,
+}
+
+impl SearchList {
+    /// Returns the ids of the scenarios of the best trial of every search.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn best_trial_ids(&self) -> BTreeSet<String> {
+        self.entries
+            .iter()
+            .filter_map(|search| search.best_trial())
+            .map(|trial| trial.scenario_id.clone())
+            .collect()
+    }
+}
+
+impl Default for SearchList {
+    /// Loads the searches from the results directory, so that unfinished
+    /// searches resume. If loading fails, returns an empty list.
+    #[tracing::instrument(level = "info")]
+    fn default() -> Self {
+        match load_searches(results_directory()) {
+            Ok(entries) => Self { entries },
+            Err(e) => {
+                warn!("Failed to load searches from results directory: {}", e);
+                Self {
+                    entries: Vec::new(),
+                }
+            }
+        }
+    }
+}
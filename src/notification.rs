@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::scenario::summary::Summary;
+
+/// File the notification settings are read from at startup.
+pub const NOTIFICATIONS_FILE: &str = "./notifications.toml";
+
+/// Settings for notifying the user when a scenario stops running.
+///
+/// Desktop notifications and the webhook are only sent if the application
+/// is built with the `notifications` feature.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Notifications {
+    #[serde(default)]
+    pub desktop: bool,
+    // receives a JSON POST with the fields of `WebhookPayload`
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+impl Notifications {
+    /// Creates settings with all notifications disabled.
+    #[must_use]
+    pub const fn disabled() -> Self {
+        Self {
+            desktop: false,
+            webhook_url: None,
+        }
+    }
+
+    /// Loads the notification settings from the given TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[tracing::instrument(level = "info")]
+    pub fn load(path: &Path) -> Result<Self> {
+        info!("Loading notification settings from {}", path.display());
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read notification settings: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse notification settings: {}", path.display()))
+    }
+
+    /// Returns true if any notification channel is enabled.
+    #[must_use]
+    pub const fn is_enabled(&self) -> bool {
+        self.desktop || self.webhook_url.is_some()
+    }
+
+    /// Sends the enabled notifications for a scenario that stopped running.
+    ///
+    /// The notifications are sent from a separate thread, so a slow webhook
+    /// does not block the scheduler. Failures are only logged.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn notify(&self, scenario_id: &str, summary: Option<&Summary>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let payload = WebhookPayload::new(scenario_id, summary);
+        debug!("Sending notifications for scenario {scenario_id}");
+        #[cfg(feature = "notifications")]
+        {
+            let settings = self.clone();
+            std::thread::spawn(move || settings.send(&payload));
+        }
+        #[cfg(not(feature = "notifications"))]
+        warn!(
+            "Notifications for scenario {} are enabled, but the application was built \
+             without the notifications feature",
+            payload.scenario_id
+        );
+    }
+
+    #[cfg(feature = "notifications")]
+    #[tracing::instrument(level = "debug", skip(self))]
+    fn send(&self, payload: &WebhookPayload) {
+        if self.desktop {
+            if let Err(e) = notify_rust::Notification::new()
+                .summary("Cardio TRust")
+                .body(&payload.message())
+                .show()
+            {
+                warn!("Failed to show desktop notification: {e}");
+            }
+        }
+        if let Some(url) = self.webhook_url.as_ref() {
+            if let Err(e) = ureq::post(url).send_json(payload) {
+                warn!("Failed to send webhook notification to {url}: {e}");
+            }
+        }
+    }
+}
+
+impl Default for Notifications {
+    /// Loads the notification settings from [`NOTIFICATIONS_FILE`] if it
+    /// exists, otherwise all notifications are disabled.
+    #[tracing::instrument(level = "info")]
+    fn default() -> Self {
+        let path = Path::new(NOTIFICATIONS_FILE);
+        if !path.exists() {
+            return Self::disabled();
+        }
+        match Self::load(path) {
+            Ok(notifications) => notifications,
+            Err(e) => {
+                warn!("Failed to load notification settings: {}", e);
+                Self::disabled()
+            }
+        }
+    }
+}
+
+/// How a scenario stopped running.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Outcome {
+    Finished,
+    Diverged,
+}
+
+impl Outcome {
+    /// Classifies the scenario as diverged if its last loss is not finite.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn from_summary(summary: Option<&Summary>) -> Self {
+        match summary {
+            Some(summary) if !summary.loss.is_finite() => Self::Diverged,
+            _ => Self::Finished,
+        }
+    }
+}
+
+/// Body of the webhook request.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub scenario_id: String,
+    pub outcome: Outcome,
+    pub loss: Option<f32>,
+    pub dice: Option<f32>,
+}
+
+impl WebhookPayload {
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn new(scenario_id: &str, summary: Option<&Summary>) -> Self {
+        Self {
+            scenario_id: scenario_id.to_string(),
+            outcome: Outcome::from_summary(summary),
+            loss: summary.map(|summary| summary.loss),
+            dice: summary.map(|summary| summary.dice),
+        }
+    }
+
+    /// Human readable message used for desktop notifications.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    fn message(&self) -> String {
+        match self.outcome {
+            Outcome::Finished => self.dice.map_or_else(
+                || format!("Scenario {} finished.", self.scenario_id),
+                |dice| {
+                    format!(
+                        "Scenario {} finished with dice {dice:.3}.",
+                        self.scenario_id
+                    )
+                },
+            ),
+            Outcome::Diverged => format!("Scenario {} diverged.", self.scenario_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_finite_loss_is_divergence() {
+        let summary = Summary {
+            loss: f32::NAN,
+            ..Default::default()
+        };
+
+        assert_eq!(Outcome::from_summary(Some(&summary)), Outcome::Diverged);
+        assert_eq!(
+            Outcome::from_summary(Some(&Summary::default())),
+            Outcome::Finished
+        );
+        assert_eq!(Outcome::from_summary(None), Outcome::Finished);
+    }
+
+    #[test]
+    fn settings_parse_from_toml() -> anyhow::Result<()> {
+        let notifications: Notifications =
+            toml::from_str("webhook_url = \"http://localhost:8080/hook\"")?;
+
+        assert!(!notifications.desktop);
+        assert!(notifications.is_enabled());
+        Ok(())
+    }
+}
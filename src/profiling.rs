@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+use tracing::{
+    debug,
+    field::{Field, Visit},
+    span, warn, Metadata, Subscriber,
+};
+use tracing_subscriber::{
+    filter::filter_fn, layer::Context as LayerContext, registry::LookupSpan, Layer,
+};
+
+/// Name of the performance report in the scenario directory.
+pub const PERFORMANCE_REPORT_FILE: &str = "performance.json";
+
+/// Span field naming the scenario a span and its children belong to.
+pub const SCENARIO_FIELD: &str = "scenario_id";
+
+/// Span field naming the [`PerformanceCategory`] of a span.
+pub const CATEGORY_FIELD: &str = "profile";
+
+// timings recorded since the last flush, keyed by scenario id
+static PENDING_REPORTS: LazyLock<Mutex<HashMap<String, PerformanceReport>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The parts of a scenario whose run time is reported.
+///
+/// Spans are assigned to a category with a `profile` field holding the
+/// lowercase variant name, e.g.
+/// `#[tracing::instrument(level = "info", fields(profile = "prediction"))]`.
+/// GPU kernels are only enqueued by their spans, so the time they take
+/// mostly shows up in the blocking transfers.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    EnumIter,
+    Display,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PerformanceCategory {
+    Derivation,
+    Prediction,
+    GpuTransfer,
+    Plotting,
+}
+
+impl PerformanceCategory {
+    /// Returns the category with the given `profile` field value.
+    // called from the layer, see the note on its callbacks
+    #[must_use]
+    pub fn from_field(value: &str) -> Option<Self> {
+        match value {
+            "derivation" => Some(Self::Derivation),
+            "prediction" => Some(Self::Prediction),
+            "gpu_transfer" => Some(Self::GpuTransfer),
+            "plotting" => Some(Self::Plotting),
+            _ => None,
+        }
+    }
+}
+
+/// Accumulated time spent in the spans of one category.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct CategoryTiming {
+    pub busy_s: f64,
+    pub calls: u64,
+}
+
+/// Time spent per [`PerformanceCategory`] while running and plotting a
+/// scenario.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PerformanceReport {
+    pub timings: BTreeMap<PerformanceCategory, CategoryTiming>,
+}
+
+impl PerformanceReport {
+    /// Adds a single call of the given duration to the category.
+    // called from the layer, see the note on its callbacks
+    pub fn record(&mut self, category: PerformanceCategory, busy: Duration) {
+        let timing = self.timings.entry(category).or_default();
+        timing.busy_s += busy.as_secs_f64();
+        timing.calls += 1;
+    }
+
+    /// Adds the timings of the other report to this one.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn merge(&mut self, other: &Self) {
+        debug!("Merging performance reports");
+        for (category, timing) in &other.timings {
+            let total = self.timings.entry(*category).or_default();
+            total.busy_s += timing.busy_s;
+            total.calls += timing.calls;
+        }
+    }
+
+    /// Returns the total time of all categories in seconds.
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn total_s(&self) -> f64 {
+        self.timings.values().map(|timing| timing.busy_s).sum()
+    }
+
+    /// Saves the report as a JSON file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        debug!("Saving performance report");
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize performance report")?;
+        std::fs::write(path, json)
+            .with_context(|| format!("Failed to write performance report: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Loads a report from the JSON file at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[tracing::instrument(level = "debug")]
+    pub fn load(path: &Path) -> Result<Self> {
+        debug!("Loading performance report");
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read performance report: {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse performance report: {}", path.display()))
+    }
+}
+
+/// Returns the timings recorded for the scenario since the last call to
+/// [`take_pending`].
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn pending(scenario_id: &str) -> Option<PerformanceReport> {
+    match PENDING_REPORTS.lock() {
+        Ok(reports) => reports.get(scenario_id).cloned(),
+        Err(e) => {
+            warn!("Performance reports are poisoned: {}", e);
+            None
+        }
+    }
+}
+
+/// Removes and returns the timings recorded for the scenario.
+#[must_use]
+#[tracing::instrument(level = "debug")]
+pub fn take_pending(scenario_id: &str) -> PerformanceReport {
+    debug!("Taking pending performance report");
+    match PENDING_REPORTS.lock() {
+        Ok(mut reports) => reports.remove(scenario_id).unwrap_or_default(),
+        Err(e) => {
+            warn!("Performance reports are poisoned: {}", e);
+            PerformanceReport::default()
+        }
+    }
+}
+
+// called from the layer, see the note on its callbacks
+fn record_pending(scenario_id: &str, category: PerformanceCategory, busy: Duration) {
+    if let Ok(mut reports) = PENDING_REPORTS.lock() {
+        reports
+            .entry(scenario_id.to_string())
+            .or_default()
+            .record(category, busy);
+    }
+}
+
+/// A tracing layer that accumulates the busy time of categorized spans per
+/// scenario.
+///
+/// A categorized span is attributed to the closest span with a
+/// `scenario_id` field, which may be the span itself. Categorized spans
+/// nested in a span of the same category are not counted again.
+#[derive(Debug, Default)]
+pub struct ProfilingLayer;
+
+impl ProfilingLayer {
+    /// Returns the layer with a filter that only passes the spans relevant
+    /// for profiling.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn filtered<S>() -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        debug!("Creating profiling layer");
+        Self.with_filter(filter_fn(is_profiled))
+    }
+}
+
+// called from the layer, see the note on its callbacks
+fn is_profiled(metadata: &Metadata<'_>) -> bool {
+    metadata.is_span()
+        && (metadata.fields().field(CATEGORY_FIELD).is_some()
+            || metadata.fields().field(SCENARIO_FIELD).is_some())
+}
+
+/// Profiling state stored in the extensions of a span.
+#[derive(Debug, Default)]
+struct SpanTiming {
+    scenario_id: Option<String>,
+    category: Option<PerformanceCategory>,
+    entered: Option<Instant>,
+    busy: Duration,
+}
+
+// the visitor and layer callbacks run inside the subscriber and are not
+// instrumented, since their spans would re-enter it
+impl Visit for SpanTiming {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            SCENARIO_FIELD => self.scenario_id = Some(value.to_string()),
+            CATEGORY_FIELD => self.category = PerformanceCategory::from_field(value),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            SCENARIO_FIELD => self.scenario_id = Some(format!("{value:?}")),
+            CATEGORY_FIELD => {
+                self.category = PerformanceCategory::from_field(&format!("{value:?}"));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<S> Layer<S> for ProfilingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut timing = SpanTiming::default();
+        attrs.record(&mut timing);
+        span.extensions_mut().insert(timing);
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: LayerContext<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                timing.entered = Some(Instant::now());
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: LayerContext<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                if let Some(entered) = timing.entered.take() {
+                    timing.busy += entered.elapsed();
+                }
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let (category, busy) = {
+            let extensions = span.extensions();
+            let Some(timing) = extensions.get::<SpanTiming>() else {
+                return;
+            };
+            let Some(category) = timing.category else {
+                return;
+            };
+            (category, timing.busy)
+        };
+        let mut scenario_id = None;
+        for ancestor in span.scope() {
+            let extensions = ancestor.extensions();
+            let Some(timing) = extensions.get::<SpanTiming>() else {
+                continue;
+            };
+            if ancestor.id() != id && timing.category == Some(category) {
+                return;
+            }
+            if timing.scenario_id.is_some() {
+                scenario_id.clone_from(&timing.scenario_id);
+                break;
+            }
+        }
+        if let Some(scenario_id) = scenario_id {
+            record_pending(&scenario_id, category, busy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn nested_spans_are_counted_once_per_category() {
+        let subscriber = tracing_subscriber::registry().with(ProfilingLayer::filtered());
+        tracing::subscriber::with_default(subscriber, || {
+            let scenario = info_span!("run", scenario_id = "profiling_test");
+            let _scenario = scenario.enter();
+            for _ in 0..2 {
+                let derivation = info_span!("outer", profile = "derivation");
+                let _derivation = derivation.enter();
+                let nested = info_span!("inner", profile = "derivation");
+                let _nested = nested.enter();
+                let prediction = info_span!("prediction", profile = "prediction");
+                let _prediction = prediction.enter();
+            }
+            info_span!("plotting", profile = "plotting").in_scope(|| {});
+        });
+
+        let report = take_pending("profiling_test");
+
+        assert_eq!(report.timings[&PerformanceCategory::Derivation].calls, 2);
+        assert_eq!(report.timings[&PerformanceCategory::Prediction].calls, 2);
+        assert!(!report
+            .timings
+            .contains_key(&PerformanceCategory::GpuTransfer));
+        assert_eq!(report.timings[&PerformanceCategory::Plotting].calls, 1);
+        assert!(take_pending("profiling_test").timings.is_empty());
+    }
+
+    #[test]
+    fn spans_without_scenario_are_not_recorded() {
+        let subscriber = tracing_subscriber::registry().with(ProfilingLayer::filtered());
+        tracing::subscriber::with_default(subscriber, || {
+            info_span!("orphan", profile = "plotting").in_scope(|| {});
+        });
+
+        assert!(pending("").is_none());
+    }
+
+    #[test]
+    fn merge_adds_timings() {
+        let mut report = PerformanceReport::default();
+        report.record(PerformanceCategory::Plotting, Duration::from_secs(1));
+        let mut other = PerformanceReport::default();
+        other.record(PerformanceCategory::Plotting, Duration::from_secs(2));
+        other.record(PerformanceCategory::Prediction, Duration::from_secs(3));
+
+        report.merge(&other);
+
+        assert_eq!(report.timings[&PerformanceCategory::Plotting].calls, 2);
+        assert!((report.total_s() - 6.0).abs() < 1e-9);
+    }
+}
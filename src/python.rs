@@ -0,0 +1,186 @@
+use std::{path::PathBuf, sync::mpsc::channel};
+
+use anyhow::{Context, Result};
+use numpy::{PyArray1, PyArray2, PyArray3, ToPyArray};
+use pyo3::prelude::*;
+use tracing::debug;
+
+use crate::core::{
+    config::Config,
+    data::Data,
+    scenario::{results::Results, run, Scenario},
+};
+
+/// Python module `cardiotrust_py`, built with maturin and the `python`
+/// feature.
+#[pymodule]
+#[tracing::instrument(level = "debug", skip_all)]
+fn cardiotrust_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyScenario>()?;
+    Ok(())
+}
+
+/// A scenario as seen from Python.
+///
+/// The config is exchanged as a TOML string in the format of the
+/// `scenario.toml` files. The arrays of the data and results are returned as
+/// NumPy copies, so they stay valid when the scenario is run again.
+#[pyclass(name = "Scenario", module = "cardiotrust_py")]
+pub struct PyScenario {
+    scenario: Scenario,
+}
+
+#[pymethods]
+impl PyScenario {
+    /// Creates a new scenario with the default config in the results
+    /// directory.
+    #[new]
+    #[pyo3(signature = (id=None))]
+    #[tracing::instrument(level = "debug")]
+    fn new(id: Option<String>) -> Result<Self> {
+        debug!("Creating scenario from python");
+        Ok(Self {
+            scenario: Scenario::build(id)?,
+        })
+    }
+
+    /// Loads the scenario stored in the given directory together with its
+    /// data and results, if they exist.
+    #[staticmethod]
+    #[tracing::instrument(level = "debug")]
+    fn load(path: PathBuf) -> Result<Self> {
+        debug!("Loading scenario from python");
+        let mut scenario = Scenario::load(&path)?;
+        scenario.load_data()?;
+        scenario.load_results()?;
+        Ok(Self { scenario })
+    }
+
+    #[getter]
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn id(&self) -> String {
+        self.scenario.get_id().clone()
+    }
+
+    #[getter]
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn status(&self) -> String {
+        self.scenario.get_status_str()
+    }
+
+    /// Returns the config as a TOML string.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn config_toml(&self) -> Result<String> {
+        toml::to_string(&self.scenario.config).context("Failed to serialize config")
+    }
+
+    /// Replaces the config with the given TOML string and saves the
+    /// scenario.
+    #[tracing::instrument(level = "debug", skip(self, config))]
+    fn set_config_toml(&mut self, config: &str) -> Result<()> {
+        debug!("Setting config from python");
+        self.scenario.config =
+            toml::from_str::<Config>(config).context("Failed to parse config")?;
+        self.scenario.save()
+    }
+
+    /// Returns the summary as a TOML string, or `None` if the scenario has
+    /// not finished yet.
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn summary_toml(&self) -> Result<Option<String>> {
+        self.scenario
+            .summary
+            .as_ref()
+            .map(|summary| toml::to_string(summary).context("Failed to serialize summary"))
+            .transpose()
+    }
+
+    /// Schedules and runs the scenario, blocking until it is done, and
+    /// reloads it with its data and results.
+    #[tracing::instrument(level = "info", skip(self))]
+    fn run(&mut self) -> Result<()> {
+        debug!("Running scenario from python");
+        self.scenario.schedule()?;
+        let directory = self.scenario.get_directory();
+        let (simulation_tx, _simulation_rx) = channel();
+        let (epoch_tx, _epoch_rx) = channel();
+        let (summary_tx, _summary_rx) = channel();
+        run(
+            self.scenario.clone(),
+            &simulation_tx,
+            &epoch_tx,
+            &summary_tx,
+        )?;
+        let mut scenario = Scenario::load(&directory)?;
+        scenario.load_data()?;
+        scenario.load_results()?;
+        self.scenario = scenario;
+        Ok(())
+    }
+
+    /// Simulated measurements of shape (beats, steps, sensors).
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn simulated_measurements<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyArray3<f32>>> {
+        Ok(self.data()?.simulation.measurements.to_pyarray(py))
+    }
+
+    /// Simulated system states of shape (steps, states).
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn simulated_system_states<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyArray2<f32>>> {
+        Ok(self.data()?.simulation.system_states.to_pyarray(py))
+    }
+
+    /// Simulated activation time per state in ms.
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn simulated_activation_times<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Bound<'py, PyArray1<f32>>> {
+        Ok(self.data()?.simulation.activation_times.to_pyarray(py))
+    }
+
+    /// Estimated measurements of shape (beats, steps, sensors).
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn estimated_measurements<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyArray3<f32>>> {
+        Ok(self.results()?.estimations.measurements.to_pyarray(py))
+    }
+
+    /// Estimated system states of shape (steps, states).
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn estimated_system_states<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyArray2<f32>>> {
+        Ok(self.results()?.estimations.system_states.to_pyarray(py))
+    }
+
+    /// Estimated activation time per state in ms.
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn estimated_activation_times<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Result<Bound<'py, PyArray1<f32>>> {
+        Ok(self.results()?.estimations.activation_times.to_pyarray(py))
+    }
+
+    /// Loss per batch over all epochs.
+    #[tracing::instrument(level = "trace", skip(self, py))]
+    fn loss<'py>(&self, py: Python<'py>) -> Result<Bound<'py, PyArray1<f32>>> {
+        Ok(self.results()?.metrics.loss_batch.to_pyarray(py))
+    }
+}
+
+impl PyScenario {
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn data(&self) -> Result<&Data> {
+        self.scenario
+            .data
+            .as_ref()
+            .context("Scenario has no data, run or load it first")
+    }
+
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn results(&self) -> Result<&Results> {
+        self.scenario
+            .results
+            .as_ref()
+            .context("Scenario has no results, run or load it first")
+    }
+}
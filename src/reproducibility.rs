@@ -0,0 +1,220 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::{
+    core::scenario::{provenance::GIT_HASH, summary::Summary, Scenario, ARCHIVE_COMPRESSION_LEVEL},
+    settings::results_directory,
+};
+
+/// Name of the manifest at the root of a reproducibility bundle.
+pub const BUNDLE_MANIFEST_FILE: &str = "manifest.toml";
+
+/// Describes the contents of a reproducibility bundle and the code version
+/// that produced them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub crate_version: String,
+    /// Commit the crate was built from, see [`GIT_HASH`].
+    pub git_hash: String,
+    pub created: DateTime<Utc>,
+    pub scenarios: Vec<BundledScenario>,
+}
+
+/// A single scenario inside a reproducibility bundle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundledScenario {
+    pub id: String,
+    pub status: String,
+    pub summary: Option<Summary>,
+    /// Paths of the bundled files relative to the archive root.
+    pub files: Vec<String>,
+}
+
+/// Packages the configs, summaries, metrics and figures of the given
+/// scenarios into a zstd compressed tar archive with a manifest.
+///
+/// Heavy arrays like data, estimations, derivatives and the model are left
+/// out. Each scenario is stored below a directory named after its id.
+///
+/// # Errors
+///
+/// Returns an error if a scenario cannot be loaded or the archive cannot be written.
+#[tracing::instrument(level = "info")]
+pub fn export_bundle(ids: &[String], path: &Path) -> Result<BundleManifest> {
+    info!(
+        "Exporting reproducibility bundle with {} scenarios to {}",
+        ids.len(),
+        path.display()
+    );
+    if ids.is_empty() {
+        bail!("No scenarios selected for the reproducibility bundle");
+    }
+    let mut scenarios = Vec::with_capacity(ids.len());
+    let mut sources = Vec::new();
+    for id in ids {
+        let (scenario, scenario_sources) = collect_scenario(id)?;
+        scenarios.push(scenario);
+        sources.extend(scenario_sources);
+    }
+    let manifest = BundleManifest {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: GIT_HASH.to_string(),
+        created: Utc::now(),
+        scenarios,
+    };
+    let manifest_toml =
+        toml::to_string(&manifest).context("Failed to serialize bundle manifest")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create bundle directory: {}", parent.display()))?;
+    }
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create bundle file: {}", path.display()))?;
+    let encoder = zstd::Encoder::new(file, ARCHIVE_COMPRESSION_LEVEL)
+        .context("Failed to create zstd encoder")?;
+    let mut builder = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_toml.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(u64::try_from(manifest.created.timestamp()).unwrap_or_default());
+    header.set_cksum();
+    builder
+        .append_data(&mut header, BUNDLE_MANIFEST_FILE, manifest_toml.as_bytes())
+        .context("Failed to add manifest to bundle")?;
+    for (source, name) in &sources {
+        builder
+            .append_path_with_name(source, name)
+            .with_context(|| format!("Failed to add {} to bundle", source.display()))?;
+    }
+    builder
+        .into_inner()
+        .context("Failed to finish tar archive")?
+        .finish()
+        .context("Failed to finish zstd stream")?;
+    Ok(manifest)
+}
+
+/// Reads the manifest of a reproducibility bundle.
+///
+/// # Errors
+///
+/// Returns an error if the bundle cannot be read or contains no valid manifest.
+#[tracing::instrument(level = "debug")]
+pub fn read_manifest(path: &Path) -> Result<BundleManifest> {
+    debug!("Reading bundle manifest from {}", path.display());
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open bundle file: {}", path.display()))?;
+    let decoder = zstd::Decoder::new(file).context("Failed to create zstd decoder")?;
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries().context("Failed to read bundle entries")? {
+        let mut entry = entry.context("Failed to read bundle entry")?;
+        if entry.path().context("Invalid path in bundle")? == Path::new(BUNDLE_MANIFEST_FILE) {
+            let mut contents = String::new();
+            entry
+                .read_to_string(&mut contents)
+                .context("Failed to read bundle manifest")?;
+            return toml::from_str(&contents).context("Failed to parse bundle manifest");
+        }
+    }
+    bail!("Bundle {} does not contain a manifest", path.display())
+}
+
+/// Collects the manifest entry of a scenario and the files to bundle for it,
+/// as pairs of source path and path inside the archive.
+///
+/// # Errors
+///
+/// Returns an error if the scenario cannot be loaded or its figures cannot be listed.
+#[tracing::instrument(level = "debug")]
+fn collect_scenario(id: &str) -> Result<(BundledScenario, Vec<(PathBuf, String)>)> {
+    debug!("Collecting bundle files for scenario with id {id}");
+    let scenario_path = results_directory().join(id);
+    let scenario = Scenario::load(&scenario_path)
+        .with_context(|| format!("Failed to load scenario with id {id}"))?;
+    let mut sources = vec![(
+        scenario_path.join("scenario.toml"),
+        format!("{id}/scenario.toml"),
+    )];
+    let metrics_path = scenario_path.join("results").join("metrics.bin");
+    if metrics_path.is_file() {
+        sources.push((metrics_path, format!("{id}/metrics.bin")));
+    } else {
+        warn!("Scenario with id {id} has no stored metrics, bundling config only");
+    }
+    let image_path = scenario_path.join("img");
+    if image_path.is_dir() {
+        let mut figures = Vec::new();
+        for entry in fs::read_dir(&image_path)
+            .with_context(|| format!("Failed to read figures: {}", image_path.display()))?
+        {
+            let entry = entry.context("Failed to read figure entry")?;
+            if entry.path().is_file() {
+                figures.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        figures.sort();
+        sources.extend(
+            figures
+                .into_iter()
+                .map(|figure| (image_path.join(&figure), format!("{id}/img/{figure}"))),
+        );
+    }
+    let bundled = BundledScenario {
+        id: id.to_string(),
+        status: scenario.get_status_str(),
+        summary: scenario.summary,
+        files: sources.iter().map(|(_, name)| name.clone()).collect(),
+    };
+    Ok((bundled, sources))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_lists_scenario_files() -> Result<()> {
+        let id = "test_bundle".to_string();
+        let scenario_path = results_directory().join(&id);
+        if scenario_path.is_dir() {
+            fs::remove_dir_all(&scenario_path)?;
+        }
+        Scenario::build(Some(id.clone()))?;
+        fs::create_dir_all(scenario_path.join("img"))?;
+        fs::write(scenario_path.join("img").join("loss.png"), b"png")?;
+        fs::write(scenario_path.join("data.bin"), b"heavy")?;
+        let bundle_path = Path::new("./archives").join("test_bundle.tar.zst");
+
+        let manifest = export_bundle(&[id.clone()], &bundle_path)?;
+        let read = read_manifest(&bundle_path)?;
+
+        assert_eq!(manifest, read);
+        assert_eq!(read.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            read.scenarios[0].files,
+            vec![
+                "test_bundle/scenario.toml".to_string(),
+                "test_bundle/img/loss.png".to_string()
+            ]
+        );
+
+        fs::remove_dir_all(&scenario_path)?;
+        fs::remove_file(&bundle_path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn bundle_requires_scenarios() {
+        let bundle_path = Path::new("./archives").join("test_empty_bundle.tar.zst");
+        assert!(export_bundle(&[], &bundle_path).is_err());
+    }
+}
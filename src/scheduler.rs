@@ -8,8 +8,15 @@ use bevy::prelude::*;
 use tracing::error;
 
 use crate::{
-    core::scenario::{run, Status},
-    ScenarioList,
+    core::{
+        algorithm::gpu::GPU,
+        scenario::{run, Status},
+    },
+    dashboard::{update_dashboard, Dashboard},
+    exporter::{update_metrics, MetricsExporter},
+    notification::Notifications,
+    settings::{results_directory, Settings},
+    ScenarioBundle, ScenarioList, SearchList,
 };
 
 #[allow(clippy::module_name_repetitions)]
@@ -20,13 +27,33 @@ impl Plugin for SchedulerPlugin {
     #[tracing::instrument(level = "info", skip(app))]
     fn build(&self, app: &mut App) {
         info!("Initializing scheduler plugin.");
+        // detect a missing OpenCL runtime once at startup instead of when the
+        // first GPU scenario is scheduled
+        GPU::is_available();
         app.init_state::<SchedulerState>()
             .init_resource::<NumberOfJobs>()
+            .init_resource::<Notifications>()
+            .init_resource::<Dashboard>()
+            .init_resource::<Settings>()
+            .init_resource::<MetricsExporter>()
             .add_systems(
                 Update,
                 start_scenarios.run_if(in_state(SchedulerState::Available)),
             )
-            .add_systems(Update, check_scenarios);
+            .add_systems(
+                Update,
+                (
+                    check_scenarios,
+                    update_dashboard
+                        .after(check_scenarios)
+                        .run_if(resource_changed::<ScenarioList>),
+                    update_metrics
+                        .after(check_scenarios)
+                        .run_if(resource_changed::<ScenarioList>),
+                    autosave_scenarios.after(check_scenarios),
+                    drive_searches.after(check_scenarios),
+                ),
+            );
     }
 }
 
@@ -61,7 +88,7 @@ impl Default for NumberOfJobs {
 }
 
 /// Starts scenarios from the scenario list that are scheduled, spawning threads
-/// to run them and tracking their status. Limits number of concurrent scenarios
+/// to run them and tracking their status. Read-only scenarios are never started. Limits number of concurrent scenarios
 /// based on provided resource. Updates state if max concurrent reached.
 #[allow(clippy::needless_pass_by_value)]
 #[tracing::instrument(level = "trace", skip(commands))]
@@ -82,30 +109,30 @@ pub fn start_scenarios(
         >= number_of_jobs.value
     {
         commands.insert_resource(NextState::Pending(SchedulerState::Unavailale));
-    } else if let Some(entry) = scenario_list
-        .entries
-        .iter_mut()
-        .find(|entry| *entry.scenario.get_status() == Status::Scheduled)
-    {
+    } else if let Some(entry) = scenario_list.entries.iter_mut().find(|entry| {
+        *entry.scenario.get_status() == Status::Scheduled && !entry.scenario.is_read_only()
+    }) {
         let send_scenario = entry.scenario.clone();
+        let (simulation_tx, simulation_rx) = channel();
         let (epoch_tx, epoch_rx) = channel();
         let (summary_tx, summary_rx) = channel();
         let handle = thread::spawn(move || {
-            if let Err(e) = run(send_scenario, &epoch_tx, &summary_tx) {
+            if let Err(e) = run(send_scenario, &simulation_tx, &epoch_tx, &summary_tx) {
                 tracing::error!("Scenario failed: {:?}", e);
             }
         });
         entry.scenario.set_simulating();
         entry.join_handle = Some(handle);
+        entry.simulation_rx = Some(Mutex::new(simulation_rx));
         entry.epoch_rx = Some(Mutex::new(epoch_rx));
         entry.summary_rx = Some(Mutex::new(summary_rx));
     }
 }
 
 /// Checks the status of running scenarios, updating their epoch and summary if
-/// available. Removes finished scenarios from tracking. Checks if the scheduler
-/// should be marked as available based on running scenario count and current
-/// scheduler state.
+/// available. Removes finished scenarios from tracking and sends the configured
+/// notifications for them. Checks if the scheduler should be marked as
+/// available based on running scenario count and current scheduler state.
 ///
 /// # Panics
 ///
@@ -118,6 +145,7 @@ pub fn check_scenarios(
     mut scenario_list: ResMut<ScenarioList>,
     number_of_jobs: Res<NumberOfJobs>,
     scheduler_state: Res<State<SchedulerState>>,
+    notifications: Res<Notifications>,
 ) {
     trace!("Running check_scenarios system.");
     scenario_list
@@ -132,6 +160,16 @@ pub fn check_scenarios(
             let mut epoch_poisoned = false;
             let mut summary_poisoned = false;
 
+            // Handle simulation progress receiver, all but the latest progress are dropped
+            if let Some(progress) = entry
+                .simulation_rx
+                .as_ref()
+                .and_then(|simulation_rx| simulation_rx.lock().ok())
+                .and_then(|receiver| receiver.try_iter().last())
+            {
+                entry.scenario.set_simulation_progress(progress);
+            }
+
             // Handle epoch receiver
             if let Some(epoch_rx) = &entry.epoch_rx {
                 match epoch_rx.lock() {
@@ -185,8 +223,19 @@ pub fn check_scenarios(
             // Handle join handle
             if let Some(join_handle) = &entry.join_handle {
                 if join_handle.is_finished() {
+                    // the last summaries may still be queued
+                    if let Some(summary) = entry
+                        .summary_rx
+                        .as_ref()
+                        .and_then(|summary_rx| summary_rx.lock().ok())
+                        .and_then(|receiver| receiver.try_iter().last())
+                    {
+                        entry.scenario.summary = Some(summary);
+                    }
+                    notifications.notify(entry.scenario.get_id(), entry.scenario.summary.as_ref());
                     entry.scenario.set_done();
                     entry.join_handle = None;
+                    entry.simulation_rx = None;
                     entry.epoch_rx = None;
                     entry.summary_rx = None;
                     if let Err(e) = entry.scenario.save() {
@@ -205,6 +254,7 @@ pub fn check_scenarios(
             if cleanup_needed || epoch_poisoned || summary_poisoned {
                 entry.scenario.set_done();
                 entry.join_handle = None;
+                entry.simulation_rx = None;
                 entry.epoch_rx = None;
                 entry.summary_rx = None;
             }
@@ -223,3 +273,92 @@ pub fn check_scenarios(
         commands.insert_resource(NextState::Pending(SchedulerState::Available));
     }
 }
+
+/// Saves the running scenarios at the autosave interval of the [`Settings`],
+/// so that their progress is not lost if the application crashes.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn autosave_scenarios(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    scenario_list: Res<ScenarioList>,
+    mut since_last_save_s: Local<f32>,
+) {
+    trace!("Running autosave_scenarios system.");
+    if settings.autosave_interval_s == 0 {
+        return;
+    }
+    *since_last_save_s += time.delta_secs();
+    #[allow(clippy::cast_precision_loss)]
+    if *since_last_save_s < settings.autosave_interval_s as f32 {
+        return;
+    }
+    *since_last_save_s = 0.0;
+    scenario_list
+        .entries
+        .iter()
+        .filter(|entry| {
+            discriminant(entry.scenario.get_status()) == discriminant(&Status::Running(1))
+        })
+        .for_each(|entry| {
+            if let Err(e) = entry.scenario.save() {
+                error!(
+                    "Failed to autosave scenario {}: {}",
+                    entry.scenario.get_id(),
+                    e
+                );
+            }
+        });
+}
+
+/// Advances the hyperparameter searches of the [`SearchList`].
+///
+/// Records the objective of trials whose scenario finished and creates new
+/// trial scenarios from the base scenario until every search has run its
+/// number of trials. Searches whose base scenario no longer exists stop
+/// creating trials. Changed searches are saved to the results directory.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn drive_searches(mut searches: ResMut<SearchList>, mut scenario_list: ResMut<ScenarioList>) {
+    trace!("Running drive_searches system.");
+    for search in &mut searches.entries {
+        if search.is_finished() {
+            continue;
+        }
+        let mut changed = search.update(scenario_list.entries.iter().map(|entry| &entry.scenario));
+        let mut created = Vec::new();
+        if let Some(base) = scenario_list
+            .entries
+            .iter()
+            .find(|entry| *entry.scenario.get_id() == search.base_scenario_id)
+        {
+            loop {
+                match search.next_trial(&base.scenario) {
+                    Ok(Some(scenario)) => created.push(scenario),
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to create trial of search {}: {}", search.id, e);
+                        break;
+                    }
+                }
+            }
+        }
+        if !created.is_empty() {
+            changed = true;
+            scenario_list
+                .entries
+                .extend(created.into_iter().map(|scenario| ScenarioBundle {
+                    scenario,
+                    join_handle: None,
+                    simulation_rx: None,
+                    epoch_rx: None,
+                    summary_rx: None,
+                }));
+        }
+        if changed {
+            if let Err(e) = search.save(results_directory()) {
+                error!("Failed to save search {}: {}", search.id, e);
+            }
+        }
+    }
+}
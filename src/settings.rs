@@ -0,0 +1,248 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, level_filters::LevelFilter, warn};
+
+/// File the global settings are read from at startup.
+pub const SETTINGS_FILE: &str = "./settings.toml";
+/// Environment variable that points to a different settings file, so that
+/// several instances can run against different result stores.
+pub const SETTINGS_FILE_VARIABLE: &str = "CARDIOTRUST_SETTINGS";
+
+/// Verbosity of the log output.
+///
+/// Debug and trace messages are compiled out by the tracing features, so
+/// info is the most verbose level.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+}
+
+impl From<LogLevel> for LevelFilter {
+    #[tracing::instrument(level = "trace")]
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+        }
+    }
+}
+
+/// Format of the log files.
+///
+/// JSON logs contain one object per event together with the fields of the
+/// enclosing spans, e.g. the scenario id and epoch of algorithm runs, so
+/// they can be ingested into a log aggregation system.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Device that new scenarios run their algorithm on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Device {
+    #[default]
+    Cpu,
+    Gpu,
+}
+
+/// Color theme of the user interface and of generated plots.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+/// Application wide settings that are not part of any scenario.
+///
+/// The settings are read once at startup from [`SETTINGS_FILE`], or the
+/// file given in [`SETTINGS_FILE_VARIABLE`]. Changes to the directories and
+/// the logging take effect after a restart.
+#[derive(Resource, Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    // scenarios are loaded from and saved to this directory
+    #[serde(default = "default_results_directory")]
+    pub results_directory: PathBuf,
+    // further results directories whose scenarios are shown read-only
+    #[serde(default)]
+    pub archive_directories: Vec<PathBuf>,
+    // daily rotated log files are written to this directory
+    #[serde(default = "default_log_directory")]
+    pub log_directory: PathBuf,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    // preselected in the algorithm of new scenarios
+    #[serde(default)]
+    pub default_device: Device,
+    #[serde(default)]
+    pub theme: Theme,
+    // running scenarios are saved at this interval, zero disables autosave
+    #[serde(default = "default_autosave_interval_s")]
+    pub autosave_interval_s: u64,
+    // listen address of the Prometheus metrics exporter, e.g. "0.0.0.0:9100"
+    #[serde(default)]
+    pub metrics_endpoint: Option<String>,
+}
+
+#[tracing::instrument(level = "trace")]
+fn default_results_directory() -> PathBuf {
+    PathBuf::from("./results")
+}
+
+#[tracing::instrument(level = "trace")]
+fn default_log_directory() -> PathBuf {
+    PathBuf::from("./logs")
+}
+
+const fn default_autosave_interval_s() -> u64 {
+    60
+}
+
+impl Settings {
+    /// Creates the built-in settings that are used if no settings file
+    /// exists.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn fallback() -> Self {
+        debug!("Creating fallback settings");
+        Self {
+            results_directory: default_results_directory(),
+            archive_directories: Vec::new(),
+            log_directory: default_log_directory(),
+            log_level: LogLevel::default(),
+            log_format: LogFormat::default(),
+            default_device: Device::default(),
+            theme: Theme::default(),
+            autosave_interval_s: default_autosave_interval_s(),
+            metrics_endpoint: None,
+        }
+    }
+
+    /// Returns the path of the settings file, which can be overridden with
+    /// the [`SETTINGS_FILE_VARIABLE`] environment variable.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn path() -> PathBuf {
+        std::env::var_os(SETTINGS_FILE_VARIABLE)
+            .map_or_else(|| PathBuf::from(SETTINGS_FILE), PathBuf::from)
+    }
+
+    /// Returns the settings the application was started with.
+    ///
+    /// The settings file is read on the first call, later calls return the
+    /// same settings even if the file changed in the meantime.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn global() -> &'static Self {
+        static SETTINGS: OnceLock<Settings> = OnceLock::new();
+        SETTINGS.get_or_init(|| {
+            let path = Self::path();
+            if !path.exists() {
+                return Self::fallback();
+            }
+            match Self::load(&path) {
+                Ok(settings) => settings,
+                Err(e) => {
+                    warn!("Failed to load settings: {}", e);
+                    Self::fallback()
+                }
+            }
+        })
+    }
+
+    /// Loads the settings from the given TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed.
+    #[tracing::instrument(level = "info")]
+    pub fn load(path: &Path) -> Result<Self> {
+        info!("Loading settings from {}", path.display());
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read settings: {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse settings: {}", path.display()))
+    }
+
+    /// Saves the settings to the given TOML file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the settings cannot be serialized or written.
+    #[tracing::instrument(level = "info", skip(self))]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        info!("Saving settings to {}", path.display());
+        let contents = toml::to_string(self).context("Failed to serialize settings")?;
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write settings: {}", path.display()))
+    }
+}
+
+impl Default for Settings {
+    /// Returns the settings the application was started with, see
+    /// [`Settings::global`].
+    #[tracing::instrument(level = "info")]
+    fn default() -> Self {
+        Self::global().clone()
+    }
+}
+
+/// Returns the directory scenarios are loaded from and saved to.
+#[must_use]
+#[tracing::instrument(level = "trace")]
+pub fn results_directory() -> &'static Path {
+    &Settings::global().results_directory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_parse_from_toml() -> anyhow::Result<()> {
+        let settings: Settings = toml::from_str(
+            "results_directory = \"/data/results\"\n\
+             log_format = \"Json\"\n\
+             theme = \"Dark\"\n\
+             metrics_endpoint = \"127.0.0.1:9100\"",
+        )?;
+        assert_eq!(settings.results_directory, Path::new("/data/results"));
+        assert_eq!(settings.log_format, LogFormat::Json);
+        assert_eq!(settings.theme, Theme::Dark);
+        assert_eq!(settings.metrics_endpoint.as_deref(), Some("127.0.0.1:9100"));
+        assert_eq!(settings.log_directory, Path::new("./logs"));
+
+        let settings: Settings = toml::from_str("")?;
+        assert_eq!(settings, Settings::fallback());
+        Ok(())
+    }
+
+    #[test]
+    fn settings_roundtrip() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("cardiotrust_settings_roundtrip.toml");
+        let settings = Settings {
+            default_device: Device::Gpu,
+            autosave_interval_s: 0,
+            ..Settings::fallback()
+        };
+        settings.save(&path)?;
+        assert_eq!(Settings::load(&path)?, settings);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
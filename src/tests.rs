@@ -1,3 +1,5 @@
+pub mod golden;
+
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
@@ -0,0 +1,180 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::trace;
+
+/// Directory the golden images of the regression tests are stored in.
+pub const GOLDEN_DIRECTORY: &str = "tests/goldens";
+/// Directory rendered images are written to when they differ from their
+/// golden image.
+pub const MISMATCH_DIRECTORY: &str = "tests/goldens_mismatch";
+/// Environment variable that replaces the golden images with the rendered
+/// ones when set to anything but `0`, e.g. with `just bless-plots`.
+pub const BLESS_VARIABLE: &str = "CARDIOTRUST_BLESS";
+
+/// How much a rendered image may differ from its golden image.
+///
+/// A pixel differs if any of its channels differs by more than `channel`.
+/// The comparison fails if more than `mismatched_fraction` of all pixels
+/// differ, which leaves room for anti-aliasing differences between font
+/// rasterizers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GoldenTolerance {
+    pub channel: u8,
+    pub mismatched_fraction: f32,
+}
+
+impl Default for GoldenTolerance {
+    /// Returns a tolerance of 8 per channel for 0.5 % of the pixels.
+    #[tracing::instrument(level = "trace")]
+    fn default() -> Self {
+        Self {
+            channel: 8,
+            mismatched_fraction: 0.005,
+        }
+    }
+}
+
+/// Compares an RGB image to the golden image `<name>.png` in the golden
+/// directory.
+///
+/// The golden image is written instead if the bless variable is set, so new
+/// and intentionally changed plots are recorded and can be reviewed in the
+/// diff before committing them. On a mismatch, the rendered image is written
+/// to the mismatch directory for inspection.
+///
+/// # Errors
+///
+/// Returns an error if the golden image is missing, if the images differ in
+/// size or by more than the tolerance, or if an image cannot be read or
+/// written.
+#[tracing::instrument(level = "trace", skip(data))]
+pub fn assert_matches_golden(
+    name: &str,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    tolerance: GoldenTolerance,
+) -> Result<()> {
+    trace!("Comparing {name} to its golden image");
+    let golden_path = Path::new(GOLDEN_DIRECTORY).join(format!("{name}.png"));
+    let bless = std::env::var(BLESS_VARIABLE).is_ok_and(|value| value != "0");
+    if bless {
+        return save_rgb(&golden_path, data, width, height);
+    }
+    anyhow::ensure!(
+        golden_path.is_file(),
+        "Golden image {} of plot {name} is missing, run `just bless-plots` to record it",
+        golden_path.display()
+    );
+
+    let golden = image::open(&golden_path)
+        .with_context(|| format!("Failed to read golden image {}", golden_path.display()))?
+        .to_rgb8();
+    let mismatch = if golden.dimensions() == (width, height) {
+        let fraction = mismatched_fraction(golden.as_raw(), data, tolerance.channel);
+        (fraction > tolerance.mismatched_fraction).then(|| {
+            format!(
+                "{:.2} % of the pixels differ, {:.2} % are allowed",
+                fraction * 100.0,
+                tolerance.mismatched_fraction * 100.0
+            )
+        })
+    } else {
+        Some(format!(
+            "the size changed from {:?} to {:?}",
+            golden.dimensions(),
+            (width, height)
+        ))
+    };
+    if let Some(reason) = mismatch {
+        let actual_path = PathBuf::from(MISMATCH_DIRECTORY).join(format!("{name}.png"));
+        save_rgb(&actual_path, data, width, height)?;
+        anyhow::bail!(
+            "Plot {name} does not match its golden image: {reason}. The rendered image is in \
+             {}, set {BLESS_VARIABLE}=1 to accept it.",
+            actual_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the fraction of RGB pixels that differ by more than the channel
+/// tolerance in any channel. Images of different length count as entirely
+/// different.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn mismatched_fraction(expected: &[u8], actual: &[u8], channel_tolerance: u8) -> f32 {
+    if expected.len() != actual.len() || expected.is_empty() {
+        return 1.0;
+    }
+    let mismatched = expected
+        .chunks_exact(3)
+        .zip(actual.chunks_exact(3))
+        .filter(|(expected, actual)| {
+            expected
+                .iter()
+                .zip(actual.iter())
+                .any(|(expected, actual)| expected.abs_diff(*actual) > channel_tolerance)
+        })
+        .count();
+    #[allow(clippy::cast_precision_loss)]
+    let fraction = mismatched as f32 / (expected.len() / 3) as f32;
+    fraction
+}
+
+#[tracing::instrument(level = "trace", skip(data))]
+fn save_rgb(path: &Path, data: &[u8], width: u32, height: u32) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    image::save_buffer_with_format(
+        path,
+        data,
+        width,
+        height,
+        image::ColorType::Rgb8,
+        image::ImageFormat::Png,
+    )
+    .with_context(|| format!("Failed to write image {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    #[test]
+    fn small_channel_differences_are_tolerated() {
+        let expected = [10, 20, 30, 40, 50, 60];
+        let slightly_off = [12, 20, 30, 40, 50, 55];
+        let one_pixel_off = [10, 20, 30, 40, 90, 60];
+
+        assert_relative_eq!(mismatched_fraction(&expected, &slightly_off, 8), 0.0);
+        assert_relative_eq!(mismatched_fraction(&expected, &one_pixel_off, 8), 0.5);
+        assert_relative_eq!(mismatched_fraction(&expected, &expected[..3], 8), 1.0);
+    }
+
+    #[test]
+    fn missing_golden_image_is_an_error() {
+        // blessing records the image instead
+        if std::env::var(BLESS_VARIABLE).is_ok_and(|value| value != "0") {
+            return;
+        }
+        let result = assert_matches_golden(
+            "missing_golden_image",
+            &[0; 3],
+            1,
+            1,
+            GoldenTolerance::default(),
+        );
+
+        let error = result.expect_err("A missing golden image should fail the comparison");
+        assert!(error.to_string().contains("just bless-plots"), "{error}");
+        assert!(!Path::new(GOLDEN_DIRECTORY)
+            .join("missing_golden_image.png")
+            .exists());
+    }
+}
@@ -1,7 +1,14 @@
+mod aggregate;
 pub mod colors;
+mod diff;
 mod explorer;
+mod palette;
+mod performance;
 mod results;
 mod scenario;
+mod search;
+mod settings;
+mod stream;
 mod topbar;
 mod vol;
 
@@ -11,13 +18,16 @@ use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
 
 use self::{
     explorer::draw_ui_explorer,
+    palette::draw_command_palette,
     results::{
         draw_ui_results, reset_result_images, PlaybackSpeed, ResultImages, SelectedResultImage,
     },
     scenario::draw_ui_scenario,
+    settings::{apply_theme, draw_ui_settings, UiPlotTheme},
     topbar::draw_ui_topbar,
     vol::draw_ui_volumetric,
 };
+use crate::settings::Settings;
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Debug)]
@@ -32,13 +42,22 @@ impl Plugin for UiPlugin {
             .init_resource::<ResultImages>()
             .init_resource::<SelectedResultImage>()
             .init_resource::<PlaybackSpeed>()
+            .init_resource::<Settings>()
+            .init_resource::<UiPlotTheme>()
             .add_plugins(EguiPlugin::default())
             .add_systems(Update, enable_camera_motion)
             .add_systems(Update, toggle_ui_type_on_f2)
+            .add_systems(EguiPrimaryContextPass, apply_theme)
             .add_systems(
                 EguiPrimaryContextPass,
                 draw_ui_topbar.run_if(in_state(UiType::EGui)),
             )
+            .add_systems(
+                EguiPrimaryContextPass,
+                draw_command_palette
+                    .run_if(in_state(UiType::EGui))
+                    .after(draw_ui_topbar),
+            )
             .add_systems(
                 EguiPrimaryContextPass,
                 draw_ui_explorer
@@ -63,6 +82,12 @@ impl Plugin for UiPlugin {
                     .run_if(in_state(UiState::Volumetric).and(in_state(UiType::EGui)))
                     .after(draw_ui_topbar),
             )
+            .add_systems(
+                EguiPrimaryContextPass,
+                draw_ui_settings
+                    .run_if(in_state(UiState::Settings).and(in_state(UiType::EGui)))
+                    .after(draw_ui_topbar),
+            )
             .add_systems(Update, reset_result_images);
     }
 }
@@ -70,7 +95,7 @@ impl Plugin for UiPlugin {
 /// An enum representing the different UI states of the application.
 ///
 /// The default state is `Explorer`. The other states are `Scenario`,
-/// `Results`, and `Volumetric`.
+/// `Results`, `Volumetric` and `Settings`.
 ///
 /// This allows conditional rendering of different UI components
 /// depending on the current state.
@@ -81,6 +106,7 @@ pub enum UiState {
     Scenario,
     Results,
     Volumetric,
+    Settings,
 }
 
 impl Default for UiState {
@@ -0,0 +1,195 @@
+use egui_extras::{Column, TableBuilder};
+use egui_plot::{Line, Plot, PlotPoints};
+use ndarray::Array1;
+use tracing::{error, trace};
+
+use crate::{
+    core::scenario::aggregate::{
+        aggregate, loss_curves, mean_and_std_band, ScenarioGroup, AGGREGATED_METRICS,
+    },
+    ScenarioList,
+};
+
+/// The state of the window aggregating repeated runs with different seeds.
+#[derive(Debug, Default)]
+pub struct ScenarioAggregation {
+    pub open: bool,
+    pub groups: Vec<ScenarioGroup>,
+    pub selected: Option<usize>,
+    // mean and standard deviation of the loss curves of the selected group
+    pub loss_band: Option<(Array1<f32>, Array1<f32>)>,
+}
+
+/// Draws a window that groups the finished scenarios by their config modulo
+/// seeds and shows the mean, standard deviation and 95 % confidence interval
+/// of the summary metrics of the selected group, as well as its loss curves
+/// as mean ± standard deviation.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_aggregate(
+    context: &egui::Context,
+    scenario_list: &ScenarioList,
+    aggregation: &mut ScenarioAggregation,
+) {
+    trace!("Drawing scenario aggregation window");
+    let mut open = aggregation.open;
+    egui::Window::new("Aggregate Seeds")
+        .open(&mut open)
+        .default_width(600.0)
+        .show(context, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
+                    match aggregate(scenario_list.entries.iter().map(|entry| &entry.scenario)) {
+                        Ok(groups) => aggregation.groups = groups,
+                        Err(e) => error!("Failed to aggregate scenarios: {}", e),
+                    }
+                    aggregation.selected = None;
+                    aggregation.loss_band = None;
+                }
+                draw_group_selection(ui, aggregation);
+                if ui.button("Plot loss").clicked() {
+                    aggregation.loss_band = load_loss_band(scenario_list, aggregation);
+                }
+            });
+            ui.separator();
+            let Some(group) = aggregation
+                .selected
+                .and_then(|index| aggregation.groups.get(index))
+            else {
+                ui.label("Refresh and select a group of scenarios.");
+                return;
+            };
+            ui.label(format!("Scenarios: {}", group.ids.join(", ")));
+            draw_metric_table(ui, group);
+            if let Some((mean, std)) = &aggregation.loss_band {
+                draw_loss_band(ui, mean, std);
+            }
+        });
+    aggregation.open = open;
+}
+
+/// Draws a combo box to select one of the groups by its first scenario.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_group_selection(ui: &mut egui::Ui, aggregation: &mut ScenarioAggregation) {
+    trace!("Drawing group selection for aggregation");
+    let label = |group: &ScenarioGroup| format!("{} ({} runs)", group.ids[0], group.ids.len());
+    let selected_text = aggregation
+        .selected
+        .and_then(|index| aggregation.groups.get(index))
+        .map_or_else(|| "Select".to_string(), label);
+    let mut selected = aggregation.selected;
+    egui::ComboBox::new("cb_aggregate_group", "")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            for (index, group) in aggregation.groups.iter().enumerate() {
+                ui.selectable_value(&mut selected, Some(index), label(group));
+            }
+        });
+    if selected != aggregation.selected {
+        aggregation.selected = selected;
+        aggregation.loss_band = None;
+    }
+}
+
+/// Loads the loss curves of the selected group and returns their band.
+#[tracing::instrument(skip_all, level = "trace")]
+fn load_loss_band(
+    scenario_list: &ScenarioList,
+    aggregation: &ScenarioAggregation,
+) -> Option<(Array1<f32>, Array1<f32>)> {
+    trace!("Loading loss band for aggregation");
+    let group = aggregation
+        .selected
+        .and_then(|index| aggregation.groups.get(index))?;
+    let scenarios: Vec<_> = scenario_list
+        .entries
+        .iter()
+        .map(|entry| &entry.scenario)
+        .filter(|scenario| group.ids.contains(scenario.get_id()))
+        .collect();
+    match loss_curves(&scenarios) {
+        Ok(curves) => mean_and_std_band(&curves),
+        Err(e) => {
+            error!("Failed to load loss curves: {}", e);
+            None
+        }
+    }
+}
+
+/// Draws the table of aggregated metrics of the group.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_metric_table(ui: &mut egui::Ui, group: &ScenarioGroup) {
+    trace!("Drawing aggregated metric table");
+    TableBuilder::new(ui)
+        .column(Column::auto().resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(200.0).resizable(true))
+        .column(Column::remainder())
+        .striped(true)
+        .header(30.0, |mut header| {
+            for heading in ["Metric", "Mean", "Std", "95 % CI", "N"] {
+                header.col(|ui| {
+                    ui.heading(heading);
+                });
+            }
+        })
+        .body(|mut body| {
+            for ((name, _), statistics) in AGGREGATED_METRICS.iter().zip(&group.metrics) {
+                body.row(20.0, |mut row| {
+                    row.col(|ui| {
+                        ui.label(*name);
+                    });
+                    let Some(statistics) = statistics else {
+                        for _ in 0..4 {
+                            row.col(|ui| {
+                                ui.label("-");
+                            });
+                        }
+                        return;
+                    };
+                    row.col(|ui| {
+                        ui.label(format!("{:.3e}", statistics.mean));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.3e}", statistics.std));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "[{:.3e}, {:.3e}]",
+                            statistics.confidence_interval_lower,
+                            statistics.confidence_interval_upper
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.label(statistics.count.to_string());
+                    });
+                });
+            }
+        });
+}
+
+/// Plots the mean loss over the batches with lines at ± one standard
+/// deviation.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_loss_band(ui: &mut egui::Ui, mean: &Array1<f32>, std: &Array1<f32>) {
+    trace!("Drawing aggregated loss band");
+    let points = |sign: f32| -> PlotPoints {
+        mean.iter()
+            .zip(std.iter())
+            .enumerate()
+            .map(|(index, (mean, std))| {
+                #[allow(clippy::cast_precision_loss)]
+                let x = index as f64;
+                [x, f64::from(sign.mul_add(*std, *mean))]
+            })
+            .collect()
+    };
+    Plot::new("aggregate_loss_plot")
+        .height(250.0)
+        .legend(egui_plot::Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new("Mean loss", points(0.0)));
+            plot_ui.line(Line::new("+1 SD", points(1.0)));
+            plot_ui.line(Line::new("-1 SD", points(-1.0)));
+        });
+}
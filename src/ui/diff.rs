@@ -0,0 +1,124 @@
+use egui_extras::{Column, TableBuilder};
+use tracing::{error, trace};
+
+use crate::{core::scenario::Scenario, ScenarioList};
+
+/// The scenarios selected for comparison in the diff window.
+#[derive(Debug, Default)]
+pub struct ScenarioDiff {
+    pub open: bool,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// Draws a window that lists every configuration parameter that differs
+/// between two selected scenarios, with their values side by side.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_diff(
+    context: &egui::Context,
+    scenario_list: &ScenarioList,
+    diff: &mut ScenarioDiff,
+) {
+    trace!("Drawing scenario diff window");
+    let mut open = diff.open;
+    egui::Window::new("Compare Scenarios")
+        .open(&mut open)
+        .default_width(600.0)
+        .show(context, |ui| {
+            ui.horizontal(|ui| {
+                draw_scenario_selection(ui, "cb_diff_left", scenario_list, &mut diff.left);
+                draw_scenario_selection(ui, "cb_diff_right", scenario_list, &mut diff.right);
+            });
+            ui.separator();
+            let find = |id: Option<&String>| {
+                id.and_then(|id| {
+                    scenario_list
+                        .entries
+                        .iter()
+                        .map(|entry| &entry.scenario)
+                        .find(|scenario| scenario.get_id() == id)
+                })
+            };
+            let (Some(left), Some(right)) = (find(diff.left.as_ref()), find(diff.right.as_ref()))
+            else {
+                ui.label("Select two scenarios to compare.");
+                return;
+            };
+            draw_diff_table(ui, left, right);
+        });
+    diff.open = open;
+}
+
+/// Draws a combo box to select one of the scenarios by ID.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_scenario_selection(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    scenario_list: &ScenarioList,
+    selection: &mut Option<String>,
+) {
+    trace!("Drawing scenario selection for diff");
+    egui::ComboBox::new(id_salt, "")
+        .selected_text(selection.as_deref().unwrap_or("Select"))
+        .show_ui(ui, |ui| {
+            for entry in &scenario_list.entries {
+                let id = entry.scenario.get_id();
+                if ui
+                    .selectable_label(selection.as_ref() == Some(id), id)
+                    .clicked()
+                {
+                    *selection = Some(id.clone());
+                }
+            }
+        });
+}
+
+/// Draws the table of differing parameters of the two scenarios.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_diff_table(ui: &mut egui::Ui, left: &Scenario, right: &Scenario) {
+    trace!("Drawing scenario diff table");
+    let diffs = match left.config.diff(&right.config) {
+        Ok(diffs) => diffs,
+        Err(e) => {
+            error!("Failed to compare scenario configs: {}", e);
+            return;
+        }
+    };
+    if diffs.is_empty() {
+        ui.label("The configurations are identical.");
+        return;
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        TableBuilder::new(ui)
+            .column(Column::auto().resizable(true))
+            .column(Column::initial(150.0).resizable(true))
+            .column(Column::remainder())
+            .striped(true)
+            .header(30.0, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading(left.get_id());
+                });
+                header.col(|ui| {
+                    ui.heading(right.get_id());
+                });
+            })
+            .body(|mut body| {
+                for diff in &diffs {
+                    body.row(20.0, |mut row| {
+                        row.col(|ui| {
+                            ui.label(&diff.path);
+                        });
+                        row.col(|ui| {
+                            ui.label(diff.left.as_deref().unwrap_or("-"));
+                        });
+                        row.col(|ui| {
+                            ui.label(diff.right.as_deref().unwrap_or("-"));
+                        });
+                    });
+                }
+            });
+    });
+}
@@ -1,26 +1,117 @@
-use std::mem::discriminant;
+use std::{
+    collections::BTreeSet,
+    mem::discriminant,
+    path::{Path, PathBuf},
+};
 
+use anyhow::Result;
 use bevy::prelude::*;
 use bevy_editor_cam::prelude::{EditorCam, EnabledMotion};
 use bevy_egui::{egui, EguiContexts};
 use egui::ProgressBar;
 use egui_extras::{Column, TableBuilder};
+use strum::IntoEnumIterator;
 use tracing::error;
 
-use super::UiState;
+use super::{
+    aggregate::{draw_ui_aggregate, ScenarioAggregation},
+    diff::{draw_ui_diff, ScenarioDiff},
+    search::{draw_ui_search, SearchWindow},
+    UiState,
+};
 use crate::{
-    core::scenario::{Scenario, Status},
-    ScenarioBundle, ScenarioList, SelectedSenario,
+    core::{
+        config::algorithm::AlgorithmType,
+        scenario::{
+            export::save_summary_csv,
+            footprint::format_bytes,
+            query::{all_tags, has_tags, matches_filter, sorted_indices, SortColumn},
+            Scenario, Status,
+        },
+    },
+    settings::{Device, Settings},
+    ScenarioBundle, ScenarioList, SearchList, SelectedSenario, TemplateList,
 };
 
+/// The filter, sort order and selection of the scenario explorer.
+#[derive(Debug, Default)]
+pub struct ExplorerView {
+    pub filter: String,
+    // only scenarios with all of these tags are shown
+    pub tags: BTreeSet<String>,
+    pub sort_column: SortColumn,
+    pub descending: bool,
+    // ids of the scenarios selected for batch actions
+    pub selected: BTreeSet<String>,
+    // action waiting for confirmation and the disk space of the scenarios
+    // it applies to in bytes
+    pub pending: Option<(BatchAction, u64)>,
+}
+
+/// Actions that are applied to all selected scenarios.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchAction {
+    Schedule,
+    Export,
+    Delete,
+    Archive,
+}
+
+/// Creates a new scenario running on the default device of the settings,
+/// adds it to the list and selects it.
+///
+/// # Errors
+///
+/// Returns an error if the scenario could not be built.
+#[tracing::instrument(skip_all, level = "debug")]
+pub(super) fn create_new_scenario(
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &mut SelectedSenario,
+    settings: &Settings,
+) -> Result<()> {
+    let mut scenario = Scenario::build(None)?;
+    if settings.default_device == Device::Gpu {
+        scenario.config.algorithm.algorithm_type = AlgorithmType::ModelBasedGPU;
+        if let Err(e) = scenario.save() {
+            error!("Failed to save new scenario: {}", e);
+        }
+    }
+    scenario_list.entries.push(ScenarioBundle {
+        scenario,
+        join_handle: None,
+        simulation_rx: None,
+        epoch_rx: None,
+        summary_rx: None,
+    });
+    selected_scenario.index = Some(scenario_list.entries.len() - 1);
+    Ok(())
+}
+
 /// Draws the UI for the scenario explorer.
 ///
-/// This displays a table with columns for scenario ID, status, losses, metrics,
-/// and allows creating new scenarios and selecting one to view/edit details.
+/// This displays a table with columns for scenario ID, status, start date,
+/// losses, metrics including the pathology localization error in mm,
+/// the results directory the scenario was loaded from, its tags and allows creating new scenarios and selecting one to view/edit details.
+///
+/// The rows can be filtered by a text contained in the ID, comment or tags,
+/// by a set of tags the scenarios must all have and sorted by status, start date, duration, dice or loss. Scenarios selected
+/// with the checkboxes can be scheduled, exported to archives or deleted at
+/// once. Deleting and archiving have to be confirmed in a dialog that shows
+/// the disk space of the selected scenarios. Archiving moves the scenarios
+/// into the first archive directory of the settings.
 ///
 /// Uses egui to create the table and columns. Loops through the scenarios
 /// from the `ScenarioList` resource to populate the rows. Inserts a new row
-/// when the New button is clicked.
+/// when the New button is clicked, a scenario archive is imported or a new
+/// scenario is created from one of the listed templates. New scenarios run on
+/// the default device of the settings. Scenarios that could not be loaded are
+/// listed as quarantined below and can be repaired. The Compare button
+/// opens a window showing the config differences between two scenarios and
+/// the export button writes the summaries of all scenarios to `summary.csv`
+/// in the results directory. The Aggregate button opens a window with the
+/// statistics of repeated runs that only differ in their seeds. The Search
+/// button opens a window to start hyperparameter searches, the best trial of
+/// every search is marked with a star.
 #[allow(clippy::module_name_repetitions, clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
 pub fn draw_ui_explorer(
@@ -28,7 +119,15 @@ pub fn draw_ui_explorer(
     mut contexts: EguiContexts,
     mut scenario_list: ResMut<ScenarioList>,
     mut selected_scenario: ResMut<SelectedSenario>,
+    templates: Res<TemplateList>,
+    settings: Res<Settings>,
     mut cameras: Query<&mut EditorCam, With<Camera>>,
+    mut archive_path: Local<String>,
+    mut diff: Local<ScenarioDiff>,
+    mut aggregation: Local<ScenarioAggregation>,
+    mut view: Local<ExplorerView>,
+    mut searches: ResMut<SearchList>,
+    mut search_window: Local<SearchWindow>,
 ) {
     trace!("Drawing UI for explorer tab");
     let ctx = match contexts.ctx_mut() {
@@ -38,6 +137,7 @@ pub fn draw_ui_explorer(
             return;
         }
     };
+    let archive_root = settings.archive_directories.first().map(PathBuf::as_path);
     egui::CentralPanel::default().show(ctx, |ui| {
         for mut camera in &mut cameras {
             if ui.ui_contains_pointer() {
@@ -48,9 +148,24 @@ pub fn draw_ui_explorer(
                 };
             }
         }
+        draw_toolbar(
+            ui,
+            &mut scenario_list,
+            &mut selected_scenario,
+            &mut view,
+            archive_root,
+        );
+        let indices = sorted_indices(
+            scenario_list.entries.iter().map(|entry| &entry.scenario),
+            &view.filter,
+            &view.tags,
+            view.sort_column,
+            view.descending,
+        );
         TableBuilder::new(ui)
             .column(Column::auto().resizable(true))
             .column(Column::initial(150.0).resizable(true))
+            .column(Column::initial(150.0).resizable(true))
             .column(Column::initial(100.0).resizable(true))
             .column(Column::initial(75.0).resizable(true))
             .column(Column::initial(75.0).resizable(true))
@@ -59,6 +174,9 @@ pub fn draw_ui_explorer(
             .column(Column::initial(75.0).resizable(true))
             .column(Column::initial(75.0).resizable(true))
             .column(Column::initial(75.0).resizable(true))
+            .column(Column::initial(75.0).resizable(true))
+            .column(Column::initial(150.0).resizable(true))
+            .column(Column::initial(120.0).resizable(true))
             .column(Column::remainder())
             .header(20.0, |mut header| {
                 header.col(|ui| {
@@ -67,6 +185,9 @@ pub fn draw_ui_explorer(
                 header.col(|ui| {
                     ui.heading("\nStatus\n");
                 });
+                header.col(|ui| {
+                    ui.heading("\nStarted\n");
+                });
                 header.col(|ui| {
                     ui.heading("\nLoss\n");
                 });
@@ -91,48 +212,441 @@ pub fn draw_ui_explorer(
                 header.col(|ui| {
                     ui.heading("\nPrecision");
                 });
+                header.col(|ui| {
+                    ui.heading("\nLoc. Error");
+                });
+                header.col(|ui| {
+                    ui.heading("\nRoot");
+                });
+                header.col(|ui| {
+                    ui.heading("\nTags");
+                });
                 header.col(|ui| {
                     ui.heading("\nComment");
                 });
             })
             .body(|mut body| {
-                for index in 0..scenario_list.entries.len() {
+                let best_trials = searches.best_trial_ids();
+                for index in indices {
                     draw_row(
                         &mut commands,
                         &mut body,
                         index,
                         &mut scenario_list,
                         &mut selected_scenario,
+                        &mut view.selected,
+                        &best_trials,
                     );
                 }
                 body.row(30.0, |mut row| {
                     row.col(|ui| {
                         if ui.button("New").clicked() {
-                            scenario_list.entries.push(ScenarioBundle {
-                                scenario: Scenario::build(None)
-                                    .expect("Failed to create new scenario"),
-                                join_handle: None,
-                                epoch_rx: None,
-                                summary_rx: None,
-                            });
-                            selected_scenario.index = Some(scenario_list.entries.len() - 1);
-                            commands.insert_resource(NextState::Pending(UiState::Scenario));
+                            match create_new_scenario(
+                                &mut scenario_list,
+                                &mut selected_scenario,
+                                &settings,
+                            ) {
+                                Ok(()) => {
+                                    commands.insert_resource(NextState::Pending(UiState::Scenario));
+                                }
+                                Err(e) => error!("Failed to create new scenario: {}", e),
+                            }
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut *archive_path)
+                                .hint_text("./archives/<id>.tar.zst"),
+                        );
+                    });
+                    row.col(|ui| {
+                        if ui.button("Import").clicked() {
+                            match Scenario::import_archive(Path::new(archive_path.trim())) {
+                                Ok(scenario) => {
+                                    scenario_list.entries.push(ScenarioBundle {
+                                        scenario,
+                                        join_handle: None,
+                                        simulation_rx: None,
+                                        epoch_rx: None,
+                                        summary_rx: None,
+                                    });
+                                    archive_path.clear();
+                                }
+                                Err(e) => error!("Failed to import scenario: {}", e),
+                            }
+                        }
+                    });
+                    row.col(|ui| {
+                        if ui.button("Compare").clicked() {
+                            diff.open = true;
+                        }
+                    });
+                    row.col(|ui| {
+                        if ui.button("Export summary CSV").clicked() {
+                            let path = settings.results_directory.join("summary.csv");
+                            match save_summary_csv(
+                                scenario_list.entries.iter().map(|entry| &entry.scenario),
+                                &path,
+                            ) {
+                                Ok(()) => info!("Saved summary csv to {}", path.display()),
+                                Err(e) => error!("Failed to export summary csv: {}", e),
+                            }
+                        }
+                    });
+                    row.col(|ui| {
+                        if ui.button("Aggregate").clicked() {
+                            aggregation.open = true;
+                        }
+                    });
+                    row.col(|ui| {
+                        if ui.button("Search").clicked() {
+                            search_window.open = true;
                         }
                     });
-                    row.col(|_ui| {});
-                    row.col(|_ui| {});
-                    row.col(|_ui| {});
-                    row.col(|_ui| {});
-                    row.col(|_ui| {});
-                    row.col(|_ui| {});
                     row.col(|_ui| {});
                     row.col(|_ui| {});
                     row.col(|_ui| {});
                 });
+                for template in &templates.entries {
+                    body.row(30.0, |mut row| {
+                        row.col(|ui| {
+                            if ui.button(format!("New from {}", template.name)).clicked() {
+                                match template.instantiate() {
+                                    Ok(scenario) => {
+                                        scenario_list.entries.push(ScenarioBundle {
+                                            scenario,
+                                            join_handle: None,
+                                            simulation_rx: None,
+                                            epoch_rx: None,
+                                            summary_rx: None,
+                                        });
+                                        selected_scenario.index =
+                                            Some(scenario_list.entries.len() - 1);
+                                        commands
+                                            .insert_resource(NextState::Pending(UiState::Scenario));
+                                    }
+                                    Err(e) => error!(
+                                        "Failed to create scenario from template {}: {}",
+                                        template.name, e
+                                    ),
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label("Template");
+                        });
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|ui| {
+                            ui.label(&template.comment);
+                        });
+                    });
+                }
+                let mut repair = None;
+                for (index, quarantined) in scenario_list.quarantine.iter().enumerate() {
+                    body.row(30.0, |mut row| {
+                        row.col(|ui| {
+                            if ui
+                                .add_enabled(!quarantined.read_only, egui::Button::new("Repair"))
+                                .on_hover_text(
+                                    "Rebuilds scenario.toml from its readable fields and \
+                                     regenerates a missing summary from the stored results.",
+                                )
+                                .clicked()
+                            {
+                                repair = Some(index);
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.label("Quarantined");
+                        });
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|_ui| {});
+                        row.col(|ui| {
+                            ui.label(quarantined.path.display().to_string());
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Label::new(&quarantined.error).truncate())
+                                .on_hover_text(&quarantined.error);
+                        });
+                    });
+                }
+                if let Some(index) = repair {
+                    if let Err(e) = scenario_list.repair(index) {
+                        error!("Failed to repair scenario: {:#}", e);
+                    }
+                }
+            });
+    });
+    draw_confirmation(
+        ctx,
+        &mut scenario_list,
+        &mut selected_scenario,
+        &mut view,
+        archive_root,
+    );
+    draw_ui_diff(ctx, &scenario_list, &mut diff);
+    draw_ui_aggregate(ctx, &scenario_list, &mut aggregation);
+    draw_ui_search(ctx, &scenario_list, &mut searches, &mut search_window);
+}
+
+/// Draws the filter, the sort order and the batch actions above the
+/// scenario table.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_toolbar(
+    ui: &mut egui::Ui,
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &mut SelectedSenario,
+    view: &mut ExplorerView,
+    archive_root: Option<&Path>,
+) {
+    trace!("Drawing explorer toolbar");
+    ui.horizontal(|ui| {
+        ui.label("Filter");
+        ui.add(egui::TextEdit::singleline(&mut view.filter).hint_text("ID, comment or tag"));
+        let tags = all_tags(scenario_list.entries.iter().map(|entry| &entry.scenario));
+        // drop the tags that no scenario has anymore
+        view.tags.retain(|tag| tags.contains(tag));
+        let selected_tags = if view.tags.is_empty() {
+            "All tags".to_string()
+        } else {
+            view.tags.iter().cloned().collect::<Vec<_>>().join(", ")
+        };
+        egui::ComboBox::new("cb_explorer_tags", "")
+            .selected_text(selected_tags)
+            .show_ui(ui, |ui| {
+                if tags.is_empty() {
+                    ui.weak("No scenario has tags.");
+                }
+                for tag in tags {
+                    let mut checked = view.tags.contains(&tag);
+                    if ui.checkbox(&mut checked, &tag).changed() {
+                        if checked {
+                            view.tags.insert(tag);
+                        } else {
+                            view.tags.remove(&tag);
+                        }
+                    }
+                }
+            });
+        ui.separator();
+        ui.label("Sort by");
+        egui::ComboBox::new("cb_explorer_sort", "")
+            .selected_text(view.sort_column.name())
+            .show_ui(ui, |ui| {
+                for column in SortColumn::iter() {
+                    ui.selectable_value(&mut view.sort_column, column, column.name());
+                }
             });
+        ui.checkbox(&mut view.descending, "Descending");
+        ui.separator();
+        // drop the selection of scenarios that no longer exist
+        view.selected.retain(|id| {
+            scenario_list
+                .entries
+                .iter()
+                .any(|entry| entry.scenario.get_id() == id)
+        });
+        ui.label(format!("{} selected", view.selected.len()));
+        if ui.button("Select visible").clicked() {
+            for entry in &scenario_list.entries {
+                if matches_filter(&entry.scenario, &view.filter)
+                    && has_tags(&entry.scenario, &view.tags)
+                {
+                    view.selected.insert(entry.scenario.get_id().clone());
+                }
+            }
+        }
+        if ui.button("Clear selection").clicked() {
+            view.selected.clear();
+        }
+        let any_selected = !view.selected.is_empty();
+        let mut action = None;
+        if ui
+            .add_enabled(any_selected, egui::Button::new("Schedule"))
+            .clicked()
+        {
+            action = Some(BatchAction::Schedule);
+        }
+        if ui
+            .add_enabled(any_selected, egui::Button::new("Export"))
+            .on_hover_text("Exports every selected scenario to ./archives/<id>.tar.zst.")
+            .clicked()
+        {
+            action = Some(BatchAction::Export);
+        }
+        if ui
+            .add_enabled(
+                any_selected && archive_root.is_some(),
+                egui::Button::new("Archive"),
+            )
+            .on_hover_text(
+                "Moves the selected scenarios into the first archive directory of the \
+                 settings and compresses their data and results. Read-only and running \
+                 scenarios are skipped.",
+            )
+            .clicked()
+        {
+            view.pending = Some((
+                BatchAction::Archive,
+                scenario_list.disk_usage_bytes(&view.selected),
+            ));
+        }
+        if ui
+            .add_enabled(any_selected, egui::Button::new("Delete"))
+            .on_hover_text("Read-only and running scenarios are skipped.")
+            .clicked()
+        {
+            view.pending = Some((
+                BatchAction::Delete,
+                scenario_list.disk_usage_bytes(&view.selected),
+            ));
+        }
+        if let Some(action) = action {
+            apply_batch_action(
+                action,
+                scenario_list,
+                selected_scenario,
+                &view.selected,
+                archive_root,
+            );
+        }
     });
 }
 
+/// Draws a dialog asking to confirm the pending delete or archive action,
+/// stating the disk space it frees in the results directory.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_confirmation(
+    context: &egui::Context,
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &mut SelectedSenario,
+    view: &mut ExplorerView,
+    archive_root: Option<&Path>,
+) {
+    trace!("Drawing batch action confirmation");
+    let Some((action, bytes)) = view.pending else {
+        return;
+    };
+    let number_of_scenarios = view.selected.len();
+    let message = match action {
+        BatchAction::Archive => format!(
+            "Move {number_of_scenarios} scenario(s) to {}? This frees {} in the results \
+             directory, the data and results are compressed in the archive.",
+            archive_root.map_or_else(String::new, |root| root.display().to_string()),
+            format_bytes(bytes)
+        ),
+        _ => format!(
+            "Permanently delete {number_of_scenarios} scenario(s)? This frees {}.",
+            format_bytes(bytes)
+        ),
+    };
+    let mut confirmed = false;
+    let mut cancelled = false;
+    egui::Window::new("Confirm")
+        .collapsible(false)
+        .resizable(false)
+        .show(context, |ui| {
+            ui.label(message);
+            ui.horizontal(|ui| {
+                confirmed = ui.button("Confirm").clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+    if confirmed {
+        apply_batch_action(
+            action,
+            scenario_list,
+            selected_scenario,
+            &view.selected,
+            archive_root,
+        );
+        view.selected.clear();
+    }
+    if confirmed || cancelled {
+        view.pending = None;
+    }
+}
+
+/// Applies the action to every scenario whose id is in `selected`.
+///
+/// Scenarios the action cannot be applied to are skipped and logged.
+#[tracing::instrument(skip(scenario_list, selected_scenario), level = "info")]
+fn apply_batch_action(
+    action: BatchAction,
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &mut SelectedSenario,
+    selected: &BTreeSet<String>,
+    archive_root: Option<&Path>,
+) {
+    info!(
+        "Applying {:?} to {} selected scenarios",
+        action,
+        selected.len()
+    );
+    match action {
+        BatchAction::Schedule => {
+            for entry in &mut scenario_list.entries {
+                let scenario = &mut entry.scenario;
+                if !selected.contains(scenario.get_id()) || scenario.is_read_only() {
+                    continue;
+                }
+                if *scenario.get_status() != Status::Planning {
+                    continue;
+                }
+                if let Err(e) = scenario.schedule() {
+                    error!("Failed to schedule scenario {}: {:#}", scenario.get_id(), e);
+                }
+            }
+        }
+        BatchAction::Export => {
+            for entry in &scenario_list.entries {
+                let scenario = &entry.scenario;
+                if !selected.contains(scenario.get_id()) {
+                    continue;
+                }
+                let path = scenario.default_archive_path();
+                match scenario.export_archive(&path) {
+                    Ok(()) => info!("Exported scenario to {}", path.display()),
+                    Err(e) => error!("Failed to export scenario {}: {}", scenario.get_id(), e),
+                }
+            }
+        }
+        BatchAction::Delete => {
+            let number_of_deleted = scenario_list.delete(selected);
+            info!("Deleted {} scenarios", number_of_deleted);
+            selected_scenario.index = None;
+        }
+        BatchAction::Archive => {
+            let Some(archive_root) = archive_root else {
+                error!("No archive directory configured in the settings");
+                return;
+            };
+            let number_of_archived = scenario_list.archive(selected, archive_root);
+            info!(
+                "Moved {} scenarios to {}",
+                number_of_archived,
+                archive_root.display()
+            );
+        }
+    }
+}
+
 /// Draws a row in the scenario list table.
 ///
 /// For the scenario at the given index, this renders UI elements to show the
@@ -146,21 +660,36 @@ fn draw_row(
     index: usize,
     scenario_list: &mut ResMut<ScenarioList>,
     selected_scenario: &mut ResMut<SelectedSenario>,
+    selected: &mut BTreeSet<String>,
+    best_trials: &BTreeSet<String>,
 ) {
     trace!("Drawing row in scenario list table");
     body.row(30.0, |mut row| {
         row.col(|ui| {
-            if ui
-                .button(scenario_list.entries[index].scenario.get_id())
-                .clicked()
-            {
+            let id = scenario_list.entries[index].scenario.get_id();
+            let mut is_selected = selected.contains(id);
+            if ui.checkbox(&mut is_selected, "").changed() {
+                if is_selected {
+                    selected.insert(id.clone());
+                } else {
+                    selected.remove(id);
+                }
+            }
+            let button = if best_trials.contains(id) {
+                ui.button(egui::RichText::new(format!("★ {id}")).strong())
+                    .on_hover_text("Best trial of a hyperparameter search")
+            } else {
+                ui.button(id)
+            };
+            if button.clicked() {
                 selected_scenario.index = Some(index);
                 commands.insert_resource(NextState::Pending(UiState::Scenario));
             }
         });
         row.col(|ui| {
-            if discriminant(scenario_list.entries[index].scenario.get_status())
-                == discriminant(&Status::Running(1))
+            let status = scenario_list.entries[index].scenario.get_status();
+            if discriminant(status) == discriminant(&Status::Running(1))
+                || *status == Status::Simulating
             {
                 ui.add(
                     ProgressBar::new(scenario_list.entries[index].scenario.get_progress())
@@ -171,6 +700,12 @@ fn draw_row(
                 ui.label(scenario_list.entries[index].scenario.get_status_str());
             }
         });
+        row.col(|ui| {
+            match scenario_list.entries[index].scenario.started {
+                Some(started) => ui.label(started.format("%Y-%m-%d %H:%M").to_string()),
+                None => ui.label("-"),
+            };
+        });
         row.col(|ui| {
             match &scenario_list.entries[index].scenario.summary {
                 Some(summary) => ui.label(format!("{:.3e}", summary.loss)),
@@ -220,11 +755,36 @@ fn draw_row(
             };
         });
         row.col(|ui| {
+            match scenario_list.entries[index]
+                .scenario
+                .summary
+                .as_ref()
+                .and_then(|summary| summary.localization.as_ref())
+            {
+                Some(localization) => ui.label(format!("{:.3e}", localization.error_mm)),
+                None => ui.label("-"),
+            };
+        });
+        row.col(|ui| {
+            let scenario = &scenario_list.entries[index].scenario;
+            let root = scenario.get_root().display();
+            if scenario.is_read_only() {
+                ui.label(format!("{root} (read-only)"));
+            } else {
+                ui.label(root.to_string());
+            }
+        });
+        row.col(|ui| {
+            ui.label(scenario_list.entries[index].scenario.tags().join(", "));
+        });
+        row.col(|ui| {
+            let read_only = scenario_list.entries[index].scenario.is_read_only();
             if ui
                 .add(
                     egui::TextEdit::multiline(&mut scenario_list.entries[index].scenario.comment)
                         .desired_width(f32::INFINITY)
-                        .desired_rows(2),
+                        .desired_rows(2)
+                        .interactive(!read_only),
                 )
                 .lost_focus()
             {
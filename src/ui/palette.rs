@@ -0,0 +1,337 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use egui::{Key, KeyboardShortcut, Modifiers};
+use strum::IntoEnumIterator;
+use tracing::{debug, error, trace};
+
+use super::{
+    explorer::create_new_scenario,
+    results::{spawn_gif_generation, GifType, ImageType, PlaybackSpeed, SelectedResultImage},
+    settings::UiPlotTheme,
+    topbar::{load_selected_results, selected_is_done},
+    UiState,
+};
+use crate::{
+    core::scenario::Status, scheduler::SchedulerState, settings::Settings,
+    vis::plotting::PlotTheme, ScenarioList, SelectedSenario,
+};
+
+const PALETTE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::P);
+
+/// The actions that can be run from the command palette or with a keyboard
+/// shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiCommand {
+    NewScenario,
+    ToggleSchedule,
+    Show(UiState),
+    NextImageType,
+    PreviousImageType,
+    GenerateGif(GifType),
+    StartScheduler,
+    StopScheduler,
+}
+
+impl UiCommand {
+    /// Returns all commands in the order they are listed in the palette.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn all() -> Vec<Self> {
+        let mut commands = vec![Self::NewScenario, Self::ToggleSchedule];
+        commands.extend(
+            [
+                UiState::Explorer,
+                UiState::Scenario,
+                UiState::Results,
+                UiState::Volumetric,
+                UiState::Settings,
+            ]
+            .map(Self::Show),
+        );
+        commands.extend([Self::NextImageType, Self::PreviousImageType]);
+        commands.extend(GifType::iter().map(Self::GenerateGif));
+        commands.extend([Self::StartScheduler, Self::StopScheduler]);
+        commands
+    }
+
+    /// Returns the name of the command shown in the palette.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn label(self) -> String {
+        match self {
+            Self::NewScenario => "New scenario".to_string(),
+            Self::ToggleSchedule => "Schedule / unschedule scenario".to_string(),
+            Self::Show(state) => format!("Show {state:?}"),
+            Self::NextImageType => "Next result image".to_string(),
+            Self::PreviousImageType => "Previous result image".to_string(),
+            Self::GenerateGif(gif_type) => format!("Generate {gif_type} GIF"),
+            Self::StartScheduler => "Start scheduler".to_string(),
+            Self::StopScheduler => "Stop scheduler".to_string(),
+        }
+    }
+
+    /// Returns the keyboard shortcut of the command, if it has one.
+    #[must_use]
+    pub const fn shortcut(self) -> Option<KeyboardShortcut> {
+        let key = match self {
+            Self::NewScenario => Key::N,
+            Self::ToggleSchedule => Key::Enter,
+            Self::Show(UiState::Explorer) => Key::Num1,
+            Self::Show(UiState::Scenario) => Key::Num2,
+            Self::Show(UiState::Results) => Key::Num3,
+            Self::Show(UiState::Volumetric) => Key::Num4,
+            Self::Show(UiState::Settings) => Key::Num5,
+            Self::NextImageType => Key::ArrowRight,
+            Self::PreviousImageType => Key::ArrowLeft,
+            Self::GenerateGif(GifType::Composite) => Key::G,
+            Self::GenerateGif(_) => return None,
+            Self::StartScheduler | Self::StopScheduler => {
+                return Some(KeyboardShortcut::new(Modifiers::NONE, Key::F5));
+            }
+        };
+        Some(KeyboardShortcut::new(Modifiers::COMMAND, key))
+    }
+}
+
+/// The open state and search text of the command palette.
+#[derive(Debug, Default)]
+pub struct CommandPalette {
+    pub open: bool,
+    pub query: String,
+}
+
+/// Runs the commands whose keyboard shortcut was pressed and draws the
+/// command palette, which is toggled with Ctrl+P.
+///
+/// Shortcuts are ignored while a text field has the keyboard focus and
+/// commands that do not apply to the current view or the selected scenario
+/// are hidden from the palette. Start and stop of the scheduler share F5.
+#[allow(clippy::needless_pass_by_value, clippy::too_many_arguments)]
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_command_palette(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    ui_state: Res<State<UiState>>,
+    scheduler_state: Res<State<SchedulerState>>,
+    mut scenario_list: ResMut<ScenarioList>,
+    mut selected_scenario: ResMut<SelectedSenario>,
+    settings: Res<Settings>,
+    mut selected_image: ResMut<SelectedResultImage>,
+    playback_speed: Res<PlaybackSpeed>,
+    plot_theme: Res<UiPlotTheme>,
+    mut palette: Local<CommandPalette>,
+) {
+    trace!("Running system to handle commands.");
+    let context = match contexts.ctx_mut() {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            error!("EGUI context not available for command palette: {}", e);
+            return;
+        }
+    };
+    let available: Vec<UiCommand> = UiCommand::all()
+        .into_iter()
+        .filter(|command| {
+            is_available(
+                *command,
+                *ui_state.get(),
+                *scheduler_state.get(),
+                &scenario_list,
+                &selected_scenario,
+            )
+        })
+        .collect();
+
+    let mut selected = None;
+    if context.input_mut(|input| input.consume_shortcut(&PALETTE_SHORTCUT)) {
+        palette.open = !palette.open;
+        palette.query.clear();
+    } else if !palette.open && !context.wants_keyboard_input() {
+        selected = context.input_mut(|input| {
+            available.iter().copied().find(|command| {
+                command
+                    .shortcut()
+                    .is_some_and(|shortcut| input.consume_shortcut(&shortcut))
+            })
+        });
+    }
+
+    if palette.open {
+        selected = draw_palette_window(context, &mut palette, &available).or(selected);
+    }
+
+    if let Some(command) = selected {
+        run_command(
+            command,
+            &mut commands,
+            &mut scenario_list,
+            &mut selected_scenario,
+            &settings,
+            &mut selected_image,
+            playback_speed.value,
+            plot_theme.0,
+        );
+    }
+}
+
+/// Draws the palette with a search field and the matching commands.
+/// Returns the command that was clicked or confirmed with Enter and closes
+/// the palette, which Escape also does.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_palette_window(
+    context: &egui::Context,
+    palette: &mut CommandPalette,
+    available: &[UiCommand],
+) -> Option<UiCommand> {
+    let query = palette.query.to_lowercase();
+    let matching: Vec<UiCommand> = available
+        .iter()
+        .copied()
+        .filter(|command| command.label().to_lowercase().contains(&query))
+        .collect();
+    let mut selected = None;
+    egui::Window::new("Command Palette")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 60.0])
+        .collapsible(false)
+        .resizable(false)
+        .title_bar(false)
+        .show(context, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text("Type a command")
+                    .desired_width(400.0),
+            );
+            response.request_focus();
+            if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                selected = matching.first().copied();
+            }
+            ui.separator();
+            for command in &matching {
+                ui.horizontal(|ui| {
+                    if ui.selectable_label(false, command.label()).clicked() {
+                        selected = Some(*command);
+                    }
+                    if let Some(shortcut) = command.shortcut() {
+                        ui.weak(context.format_shortcut(&shortcut));
+                    }
+                });
+            }
+            if matching.is_empty() {
+                ui.weak("No matching command.");
+            }
+        });
+    if selected.is_some() || context.input(|input| input.key_pressed(Key::Escape)) {
+        palette.open = false;
+        palette.query.clear();
+    }
+    selected
+}
+
+/// Returns true if the command applies to the current view and the selected
+/// scenario.
+#[tracing::instrument(skip_all, level = "trace")]
+fn is_available(
+    command: UiCommand,
+    ui_state: UiState,
+    scheduler_state: SchedulerState,
+    scenario_list: &ScenarioList,
+    selected_scenario: &SelectedSenario,
+) -> bool {
+    let selected = selected_scenario
+        .index
+        .and_then(|index| scenario_list.entries.get(index))
+        .map(|entry| &entry.scenario);
+    let done = selected_is_done(scenario_list, selected_scenario);
+    match command {
+        UiCommand::NewScenario => true,
+        UiCommand::ToggleSchedule => selected.is_some_and(|scenario| {
+            !scenario.is_read_only()
+                && matches!(scenario.get_status(), Status::Planning | Status::Scheduled)
+        }),
+        UiCommand::Show(state) if state == ui_state => false,
+        UiCommand::Show(UiState::Explorer | UiState::Settings) => true,
+        UiCommand::Show(UiState::Scenario) => selected.is_some(),
+        UiCommand::Show(UiState::Results | UiState::Volumetric) => done,
+        UiCommand::NextImageType | UiCommand::PreviousImageType | UiCommand::GenerateGif(_) => {
+            ui_state == UiState::Results && done
+        }
+        UiCommand::StartScheduler => scheduler_state == SchedulerState::Paused,
+        UiCommand::StopScheduler => scheduler_state != SchedulerState::Paused,
+    }
+}
+
+#[tracing::instrument(skip_all, level = "debug", fields(command = ?command))]
+fn run_command(
+    command: UiCommand,
+    commands: &mut Commands,
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &mut SelectedSenario,
+    settings: &Settings,
+    selected_image: &mut SelectedResultImage,
+    playback_speed: f32,
+    plot_theme: PlotTheme,
+) {
+    debug!("Running command {}", command.label());
+    match command {
+        UiCommand::NewScenario => {
+            match create_new_scenario(scenario_list, selected_scenario, settings) {
+                Ok(()) => commands.insert_resource(NextState::Pending(UiState::Scenario)),
+                Err(e) => error!("Failed to create new scenario: {}", e),
+            }
+        }
+        UiCommand::ToggleSchedule => {
+            let Some(entry) = selected_scenario
+                .index
+                .and_then(|index| scenario_list.entries.get_mut(index))
+            else {
+                return;
+            };
+            let scenario = &mut entry.scenario;
+            let result = if scenario.get_status() == &Status::Planning {
+                scenario.schedule()
+            } else {
+                scenario.unschedule()
+            };
+            if let Err(e) = result {
+                error!("Failed to change schedule of scenario: {:#}", e);
+            }
+        }
+        UiCommand::Show(state) => {
+            if matches!(state, UiState::Results | UiState::Volumetric)
+                && !load_selected_results(scenario_list, selected_scenario)
+            {
+                return;
+            }
+            commands.insert_resource(NextState::Pending(state));
+        }
+        UiCommand::NextImageType | UiCommand::PreviousImageType => {
+            let image_types: Vec<ImageType> = ImageType::iter().collect();
+            let Some(position) = image_types
+                .iter()
+                .position(|image_type| *image_type == selected_image.image_type)
+            else {
+                return;
+            };
+            let offset = if command == UiCommand::NextImageType {
+                1
+            } else {
+                image_types.len() - 1
+            };
+            selected_image.image_type = image_types[(position + offset) % image_types.len()];
+        }
+        UiCommand::GenerateGif(gif_type) => {
+            if let Some(entry) = selected_scenario
+                .index
+                .and_then(|index| scenario_list.entries.get(index))
+            {
+                spawn_gif_generation(&entry.scenario, gif_type, playback_speed, plot_theme);
+            }
+        }
+        UiCommand::StartScheduler => {
+            commands.insert_resource(NextState::Pending(SchedulerState::Available));
+        }
+        UiCommand::StopScheduler => {
+            commands.insert_resource(NextState::Pending(SchedulerState::Paused));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use egui_extras::{Column, TableBuilder};
+use strum::IntoEnumIterator;
+use tracing::{error, trace};
+
+use crate::{
+    core::scenario::Scenario,
+    profiling::{PerformanceCategory, PerformanceReport},
+};
+
+/// The state of the window showing the performance report of the selected
+/// scenario.
+#[derive(Debug, Default)]
+pub struct PerformancePanel {
+    pub open: bool,
+    pub report: Option<PerformanceReport>,
+}
+
+impl PerformancePanel {
+    /// Opens the window and loads the report of the scenario.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn open(&mut self, scenario: &Scenario) {
+        self.open = true;
+        self.report = scenario.performance_report();
+    }
+}
+
+/// Draws a window with the time spent in derivation, prediction, GPU
+/// transfer and plotting of the selected scenario.
+///
+/// Timings recorded after the scenario finished, e.g. from plotting, are
+/// shown as well and only written to the report when saving.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_performance(
+    context: &egui::Context,
+    scenario: Option<&Scenario>,
+    panel: &mut PerformancePanel,
+) {
+    trace!("Drawing performance window");
+    let mut open = panel.open;
+    egui::Window::new("Performance")
+        .open(&mut open)
+        .default_width(400.0)
+        .show(context, |ui| {
+            let Some(scenario) = scenario else {
+                ui.label("No scenario selected.");
+                return;
+            };
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
+                    panel.report = scenario.performance_report();
+                }
+                if ui
+                    .add_enabled(!scenario.is_read_only(), egui::Button::new("Save"))
+                    .clicked()
+                {
+                    if let Err(e) = scenario.save_performance_report() {
+                        error!("Failed to save performance report: {}", e);
+                    }
+                    panel.report = scenario.performance_report();
+                }
+            });
+            ui.separator();
+            match &panel.report {
+                Some(report) => draw_timing_table(ui, report),
+                None => {
+                    ui.label("No timings recorded for this scenario.");
+                }
+            }
+        });
+    panel.open = open;
+}
+
+/// Draws the table of timings per category.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_timing_table(ui: &mut egui::Ui, report: &PerformanceReport) {
+    trace!("Drawing performance timing table");
+    let total_s = report.total_s();
+    TableBuilder::new(ui)
+        .column(Column::auto().resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(80.0).resizable(true))
+        .column(Column::remainder())
+        .striped(true)
+        .header(30.0, |mut header| {
+            for heading in ["Category", "Time [s]", "Calls", "Share"] {
+                header.col(|ui| {
+                    ui.heading(heading);
+                });
+            }
+        })
+        .body(|mut body| {
+            for category in PerformanceCategory::iter() {
+                let timing = report.timings.get(&category).copied().unwrap_or_default();
+                body.row(20.0, |mut row| {
+                    row.col(|ui| {
+                        ui.label(category.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.3}", timing.busy_s));
+                    });
+                    row.col(|ui| {
+                        ui.label(timing.calls.to_string());
+                    });
+                    row.col(|ui| {
+                        if total_s > 0.0 {
+                            ui.label(format!("{:.1} %", 100.0 * timing.busy_s / total_s));
+                        } else {
+                            ui.label("-");
+                        }
+                    });
+                });
+            }
+        });
+}
@@ -10,26 +10,57 @@ use bevy::prelude::*;
 use bevy_editor_cam::prelude::{EditorCam, EnabledMotion};
 use bevy_egui::{egui, EguiContexts};
 use egui::{Slider, Spinner};
-use ndarray::s;
+use ndarray::{s, Array1, Array2, ArrayBase, Data, Ix2};
 use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter};
 
+use super::{
+    performance::{draw_ui_performance, PerformancePanel},
+    settings::UiPlotTheme,
+};
 use crate::{
     core::{
-        algorithm::metrics::predict_voxeltype,
-        model::functional::allpass::shapes::ActivationTimeMs, scenario::Scenario,
+        algorithm::{
+            diagnostics::calculate_measurement_conditioning,
+            metrics::{
+                activation_time::{activation_time_pairs, ActivationTimeStatistics},
+                parameter_history::ParameterHistory,
+                predict_voxeltype,
+                velocity::calculate_velocity_statistics,
+            },
+        },
+        data::simulation::Simulation,
+        model::{functional::allpass::shapes::ActivationTimeMs, spatial::sensors::Sensors},
+        scenario::{robustness::PerturbationConfig, Scenario},
     },
     vis::plotting::{
-        gif::states::states_spherical_plot_over_time,
+        gif::{
+            composite::{composite_plot_over_time, CompositePanel},
+            quiver::states_quiver_plot_over_time,
+            states::states_spherical_plot_over_time,
+        },
         png::{
             activation_time::activation_time_plot,
+            bland_altman::bland_altman_plot,
             delay::average_delay_plot,
-            line::{standard_log_y_plot, standard_time_plot, standard_y_plot},
+            isochrone::{
+                activation_time_isochrone_overlay_plot, activation_time_isochrone_plot,
+                DEFAULT_ISOCHRONE_INTERVAL_MS,
+            },
+            line::{
+                line_plot, measurement_butterfly_plot, small_multiples_time_plot,
+                standard_log_y_plot, standard_time_plot, standard_y_plot,
+            },
             propagation_speed::average_propagation_speed_plot,
+            quiver::{peak_time_step, states_quiver_plot},
+            residual::{sensor_residual_plot, ResidualStatistic},
             states::states_spherical_plot,
+            uncertainty::standard_deviation_plot,
+            velocity::velocity_box_plot,
             voxel_type::voxel_type_plot,
+            PngBundle,
         },
-        PlotSlice, StateSphericalPlotMode,
+        PlotColorMap, PlotFormat, PlotSlice, PlotTheme, StateSphericalPlotMode,
     },
     ScenarioList, SelectedSenario,
 };
@@ -49,9 +80,19 @@ pub enum ImageType {
     StatesMaxAlgorithm,
     StatesMaxSimulation,
     StatesMaxDelta,
+    CurrentDensityQuiverAlgorithm,
+    CurrentDensityQuiverSimulation,
     ActivationTimeAlgorithm,
     ActivationTimeSimulation,
     ActivationTimeDelta,
+    ActivationTimeIsochronesAlgorithm,
+    ActivationTimeIsochronesSimulation,
+    ActivationTimeIsochronesOverlay,
+    ActivationTimeBlandAltman,
+    ActivationTimeStd,
+    CurrentDensityStd,
+    GainStd,
+    DelayStd,
     VoxelTypesAlgorithm,
     VoxelTypesSimulation,
     VoxelTypesPrediction,
@@ -60,11 +101,18 @@ pub enum ImageType {
     AverageDelayAlgorithm,
     AveragePropagationSpeedAlgorithm,
     AverageDelayDelta,
+    VelocityPerVoxelTypeSimulation,
+    VelocityPerVoxelTypeAlgorithm,
     // Metrics
     Dice,
     IoU,
     Recall,
     Precision,
+    Roc,
+    PrecisionRecall,
+    DiceOverLambda,
+    ParameterHistory,
+    MeasurementSingularValues,
     // Losses
     LossEpoch,
     Loss,
@@ -82,12 +130,125 @@ pub enum ImageType {
     MeasurementAlgorithm,
     MeasurementSimulation,
     MeasurementDelta,
+    MeasurementsButterflyAlgorithm,
+    MeasurementsButterflySimulation,
+    ResidualPerSensorMean,
+    ResidualPerSensorPeak,
+}
+
+impl ImageType {
+    /// Returns true if the image shows a single measurement channel and
+    /// therefore depends on the selected sensor.
+    #[must_use]
+    pub const fn is_per_sensor(self) -> bool {
+        matches!(
+            self,
+            Self::MeasurementAlgorithm | Self::MeasurementSimulation | Self::MeasurementDelta
+        )
+    }
+
+    /// Returns true if the image is a matrix plot and therefore depends on
+    /// the selected color options.
+    #[must_use]
+    pub const fn is_color_mapped(self) -> bool {
+        matches!(
+            self,
+            Self::StatesMaxAlgorithm
+                | Self::StatesMaxSimulation
+                | Self::StatesMaxDelta
+                | Self::ActivationTimeAlgorithm
+                | Self::ActivationTimeSimulation
+                | Self::ActivationTimeDelta
+                | Self::ActivationTimeStd
+                | Self::CurrentDensityStd
+                | Self::GainStd
+                | Self::DelayStd
+                | Self::AverageDelaySimulation
+                | Self::AveragePropagationSpeedSimulation
+                | Self::AverageDelayAlgorithm
+                | Self::AveragePropagationSpeedAlgorithm
+                | Self::AverageDelayDelta
+        )
+    }
+
+    /// Returns true if the image only shows the simulation and can be drawn
+    /// without results, e.g. for scenarios that only simulate data.
+    #[must_use]
+    pub const fn is_simulation(self) -> bool {
+        matches!(
+            self,
+            Self::StatesMaxSimulation
+                | Self::CurrentDensityQuiverSimulation
+                | Self::ActivationTimeSimulation
+                | Self::ActivationTimeIsochronesSimulation
+                | Self::VoxelTypesSimulation
+                | Self::AverageDelaySimulation
+                | Self::AveragePropagationSpeedSimulation
+                | Self::VelocityPerVoxelTypeSimulation
+                | Self::ControlFunctionSimulation
+                | Self::StateSimulation
+                | Self::MeasurementSimulation
+                | Self::MeasurementsButterflySimulation
+        )
+    }
+
+    /// Returns true if the image shows the difference between simulation
+    /// and algorithm.
+    #[must_use]
+    pub const fn is_delta(self) -> bool {
+        matches!(
+            self,
+            Self::StatesMaxDelta | Self::ActivationTimeDelta | Self::AverageDelayDelta
+        )
+    }
+}
+
+/// The color options used by the matrix image types.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PlotColorOptions {
+    // None selects RdBu for delta images and the plot theme's color map
+    // otherwise
+    pub color_map: Option<PlotColorMap>,
+    // fixed color limits (-limit, limit), None scales the colors to the data
+    pub limit: Option<f32>,
+    // theme the images are generated in, dark images get their own file names
+    pub theme: PlotTheme,
+}
+
+impl PlotColorOptions {
+    /// Returns the color map used for the given image type.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn color_map(self, image_type: ImageType) -> PlotColorMap {
+        self.color_map.unwrap_or(if image_type.is_delta() {
+            PlotColorMap::RdBu
+        } else {
+            self.theme.color_map()
+        })
+    }
+
+    /// Returns the fixed symmetric color range, if any.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn range(self) -> Option<(f32, f32)> {
+        self.limit.map(|limit| (-limit, limit))
+    }
+}
+
+/// The sensor channel(s) shown by the per-sensor image types.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum SensorSelection {
+    Single(usize),
+    All,
 }
 
 #[derive(EnumIter, Debug, PartialEq, Eq, Hash, Display, Clone, Copy)]
 pub enum GifType {
     StatesAlgorithm,
     StatesSimulation,
+    QuiverAlgorithm,
+    QuiverSimulation,
+    Composite,
 }
 
 #[derive(Resource, Debug)]
@@ -98,6 +259,35 @@ pub struct ResultImages {
 #[derive(Resource, Default, Debug)]
 pub struct SelectedResultImage {
     pub image_type: ImageType,
+    pub sensor_index: usize,
+    pub all_sensors: bool,
+    pub color_map: Option<PlotColorMap>,
+    pub fixed_limits: bool,
+    pub color_limit: f32,
+}
+
+impl SelectedResultImage {
+    /// Returns the sensor selection used for per-sensor image types.
+    #[must_use]
+    pub const fn sensor_selection(&self) -> SensorSelection {
+        if self.all_sensors {
+            SensorSelection::All
+        } else {
+            SensorSelection::Single(self.sensor_index)
+        }
+    }
+
+    /// Returns the color options used for the matrix image types in the
+    /// given theme.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn color_options(&self, theme: PlotTheme) -> PlotColorOptions {
+        PlotColorOptions {
+            color_map: self.color_map,
+            limit: (self.fixed_limits && self.color_limit > 0.0).then_some(self.color_limit),
+            theme,
+        }
+    }
 }
 
 #[derive(Resource, Default, Debug)]
@@ -130,7 +320,8 @@ impl ResultImages {
     }
 }
 
-/// Resets the `ResultImages` if the selected scenario has changed.
+/// Resets the `ResultImages` if the selected scenario or the plot theme has
+/// changed.
 ///
 /// This allows the result images to be cleared when switching between scenarios,
 /// so that the new images can be loaded.
@@ -139,9 +330,10 @@ impl ResultImages {
 pub fn reset_result_images(
     mut result_images: ResMut<ResultImages>,
     selected_scenario: Res<SelectedSenario>,
+    plot_theme: Res<UiPlotTheme>,
 ) {
     trace!("Runing system to check if result images need to be reset");
-    if selected_scenario.is_changed() {
+    if selected_scenario.is_changed() || plot_theme.is_changed() {
         result_images.reset();
     }
 }
@@ -150,7 +342,8 @@ pub fn reset_result_images(
 ///
 /// Allows selecting the result image type to display, generating gifs, exporting data,
 /// and loading/displaying the image. Handles async image loading in the background.
-/// Resets images when switching scenarios.
+/// Resets images when switching scenarios. The build and machine that produced
+/// the results are shown above the image.
 #[allow(clippy::module_name_repetitions, clippy::needless_pass_by_value)]
 #[tracing::instrument(skip_all, level = "trace")]
 pub fn draw_ui_results(
@@ -160,7 +353,9 @@ pub fn draw_ui_results(
     scenario_list: Res<ScenarioList>,
     selected_scenario: Res<SelectedSenario>,
     mut playback_speed: ResMut<PlaybackSpeed>,
+    plot_theme: Res<UiPlotTheme>,
     mut cameras: Query<&mut EditorCam, With<Camera>>,
+    mut performance: Local<PerformancePanel>,
 ) {
     trace!("Runing system to draw results UI");
     let ctx = match contexts.ctx_mut() {
@@ -194,55 +389,155 @@ pub fn draw_ui_results(
                 .selected_text(selected_image.image_type.to_string())
                 .width(300.0)
                 .show_ui(ui, |ui| {
-                    ImageType::iter().for_each(|image_type| {
-                        ui.selectable_value(
-                            &mut selected_image.image_type,
-                            image_type,
-                            image_type.to_string(),
-                        );
+                    // scenarios that only simulate data have no results
+                    let simulation_only = selected_scenario.index.is_some_and(|index| {
+                        scenario_list.entries[index].scenario.results.is_none()
                     });
+                    ImageType::iter()
+                        .filter(|image_type| !simulation_only || image_type.is_simulation())
+                        .for_each(|image_type| {
+                            ui.selectable_value(
+                                &mut selected_image.image_type,
+                                image_type,
+                                image_type.to_string(),
+                            );
+                        });
                 });
+            if selected_image.image_type.is_per_sensor() {
+                let number_of_sensors = selected_scenario
+                    .index
+                    .and_then(|index| scenario_list.entries[index].scenario.data.as_ref())
+                    .map_or(1, |data| data.simulation.measurements.num_sensors());
+                let previous_selection = selected_image.sensor_selection();
+                ui.add_enabled(
+                    !selected_image.all_sensors,
+                    Slider::new(
+                        &mut selected_image.sensor_index,
+                        0..=number_of_sensors.saturating_sub(1),
+                    )
+                    .text("Sensor"),
+                );
+                ui.checkbox(&mut selected_image.all_sensors, "All sensors");
+                if selected_image.sensor_selection() != previous_selection {
+                    result_images
+                        .image_bundles
+                        .insert(selected_image.image_type, ImageBundle::default());
+                }
+            }
+            if selected_image.image_type.is_color_mapped() {
+                let previous_options = selected_image.color_options(plot_theme.0);
+                egui::ComboBox::new("cb_color_map", "")
+                    .selected_text(
+                        selected_image
+                            .color_map
+                            .map_or_else(|| "Auto".to_string(), |color_map| color_map.to_string()),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selected_image.color_map, None, "Auto");
+                        PlotColorMap::iter().for_each(|color_map| {
+                            ui.selectable_value(
+                                &mut selected_image.color_map,
+                                Some(color_map),
+                                color_map.to_string(),
+                            );
+                        });
+                    });
+                ui.checkbox(&mut selected_image.fixed_limits, "Fixed limits");
+                ui.add_enabled(
+                    selected_image.fixed_limits,
+                    egui::DragValue::new(&mut selected_image.color_limit)
+                        .speed(0.01)
+                        .range(0.0..=f32::MAX)
+                        .prefix("±"),
+                );
+                if selected_image.color_options(plot_theme.0) != previous_options {
+                    ImageType::iter()
+                        .filter(|image_type| image_type.is_color_mapped())
+                        .for_each(|image_type| {
+                            result_images
+                                .image_bundles
+                                .insert(image_type, ImageBundle::default());
+                        });
+                }
+            }
             ui.add(Slider::new(&mut playback_speed.value, 0.001..=0.1));
-            if ui
-                .add(egui::Button::new("Generate Algorithm Gif"))
-                .clicked()
-            {
+            for (gif_type, label) in [
+                (GifType::StatesAlgorithm, "Generate Algorithm Gif"),
+                (GifType::StatesSimulation, "Generate Simulation Gif"),
+                (GifType::QuiverAlgorithm, "Generate Algorithm Quiver Gif"),
+                (GifType::QuiverSimulation, "Generate Simulation Quiver Gif"),
+                (GifType::Composite, "Generate Composite Gif"),
+            ] {
+                if ui.add(egui::Button::new(label)).clicked() {
+                    if let Some(index) = selected_scenario.index {
+                        spawn_gif_generation(
+                            &scenario_list.entries[index].scenario,
+                            gif_type,
+                            playback_speed.value,
+                            plot_theme.0,
+                        );
+                    } else {
+                        error!("No scenario selected for GIF generation");
+                    }
+                }
+            }
+            if ui.add(egui::Button::new("Export SVG")).clicked() {
                 if let Some(index) = selected_scenario.index {
                     let scenario = &scenario_list.entries[index].scenario;
                     let send_scenario = scenario.clone();
-                    let send_playback_speed = playback_speed.value;
+                    let image_type = selected_image.image_type;
+                    let sensors = selected_image.sensor_selection();
+                    let colors = selected_image.color_options(plot_theme.0);
                     thread::spawn(move || {
-                        if let Err(e) = generate_gifs(
+                        if let Err(e) = generate_image(
                             send_scenario,
-                            GifType::StatesAlgorithm,
-                            send_playback_speed,
+                            image_type,
+                            sensors,
+                            colors,
+                            PlotFormat::Svg,
                         ) {
-                            error!("Failed to generate algorithm GIF: {}", e);
+                            error!("Failed to export SVG for type {:?}: {}", image_type, e);
                         }
                     });
                 } else {
-                    error!("No scenario selected for GIF generation");
+                    error!("No scenario selected for SVG export");
                 }
             }
-            if ui
-                .add(egui::Button::new("Generate Simulation Gif"))
-                .clicked()
-            {
+            if ui.add(egui::Button::new("Export virtual ECG")).clicked() {
                 if let Some(index) = selected_scenario.index {
                     let scenario = &scenario_list.entries[index].scenario;
                     let send_scenario = scenario.clone();
-                    let send_playback_speed = playback_speed.value;
                     thread::spawn(move || {
-                        if let Err(e) = generate_gifs(
-                            send_scenario,
-                            GifType::StatesSimulation,
-                            send_playback_speed,
-                        ) {
-                            error!("Failed to generate simulation GIF: {}", e);
+                        if let Err(e) = send_scenario.save_ecg() {
+                            error!("Failed to export virtual ECG: {}", e);
+                        }
+                    });
+                } else {
+                    error!("No scenario selected for virtual ECG export");
+                }
+            }
+            if ui.add(egui::Button::new("Analyze robustness")).clicked() {
+                if let Some(index) = selected_scenario.index {
+                    let scenario = &scenario_list.entries[index].scenario;
+                    let send_scenario = scenario.clone();
+                    thread::spawn(move || {
+                        match send_scenario.analyze_robustness(&PerturbationConfig::default()) {
+                            Ok(report) => info!(
+                                "Robustness analysis done: baseline dice {:.3}, perturbed dice {:.3} +- {:.3}",
+                                report.baseline_dice, report.mean_dice, report.std_dice
+                            ),
+                            Err(e) => error!("Failed to analyze robustness: {}", e),
                         }
                     });
                 } else {
-                    error!("No scenario selected for GIF generation");
+                    error!("No scenario selected for robustness analysis");
+                }
+            }
+            if ui.add(egui::Button::new("Performance")).clicked() {
+                if let Some(index) = selected_scenario.index {
+                    performance.open(&scenario_list.entries[index].scenario);
+                } else {
+                    error!("No scenario selected for performance report");
                 }
             }
             if ui.add(egui::Button::new("Export to .npy")).clicked() {
@@ -258,7 +553,23 @@ pub fn draw_ui_results(
                     error!("No scenario selected for NPY export");
                 }
             }
+            if ui.add(egui::Button::new("Export to .nii")).clicked() {
+                if let Some(index) = selected_scenario.index {
+                    let scenario = &scenario_list.entries[index].scenario;
+                    let send_scenario = scenario.clone();
+                    thread::spawn(move || {
+                        if let Err(e) = send_scenario.save_nii() {
+                            error!("Failed to export scenario to NIFTI: {}", e);
+                        }
+                    });
+                } else {
+                    error!("No scenario selected for NIFTI export");
+                }
+            }
         });
+        if let Some(index) = selected_scenario.index {
+            draw_provenance(ui, &scenario_list.entries[index].scenario);
+        }
         let Some(image_bundle) = result_images
             .image_bundles
             .get_mut(&selected_image.image_type)
@@ -275,16 +586,24 @@ pub fn draw_ui_results(
             let scenario = &scenario_list.entries[index].scenario;
             let send_scenario = scenario.clone();
             let image_type = selected_image.image_type;
+            let sensors = selected_image.sensor_selection();
+            let colors = selected_image.color_options(plot_theme.0);
             match image_bundle.join_handle.as_mut() {
                 Some(join_handle) => {
                     if join_handle.is_finished() {
                         image_bundle.path =
-                            Some(get_image_path(scenario, selected_image.image_type));
+                            Some(get_image_path(scenario, image_type, sensors, colors));
                     }
                 }
                 None => {
                     image_bundle.join_handle = Some(thread::spawn(move || {
-                        if let Err(e) = generate_image(send_scenario, image_type) {
+                        if let Err(e) = generate_image(
+                            send_scenario,
+                            image_type,
+                            sensors,
+                            colors,
+                            PlotFormat::Png,
+                        ) {
                             error!("Failed to generate image for type {:?}: {}", image_type, e);
                         }
                     }));
@@ -296,24 +615,217 @@ pub fn draw_ui_results(
             ui.label("No scenario selected");
         }
     });
+    draw_ui_performance(
+        ctx,
+        selected_scenario
+            .index
+            .map(|index| &scenario_list.entries[index].scenario),
+        &mut performance,
+    );
+}
+
+/// Draws the build and machine that produced the results of the scenario
+/// and, if they differ, the ones it was created with.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_provenance(ui: &mut egui::Ui, scenario: &Scenario) {
+    let produced_by = scenario
+        .summary
+        .as_ref()
+        .and_then(|summary| summary.provenance.as_ref());
+    let text = match (produced_by, scenario.created_with.as_ref()) {
+        (Some(produced_by), Some(created_with)) if produced_by != created_with => {
+            format!("Results produced by {produced_by}, scenario created with {created_with}")
+        }
+        (Some(produced_by), _) => format!("Results produced by {produced_by}"),
+        (None, Some(created_with)) => format!("Scenario created with {created_with}"),
+        (None, None) => "Provenance unknown, the scenario predates its recording".to_string(),
+    };
+    let response = ui.weak(text);
+    if let Some(produced_by) = produced_by {
+        response.on_hover_text(format!("Git hash: {}", produced_by.git_hash));
+    }
 }
 
 /// Returns the file path for the image of the given type for the provided scenario.
-/// Joins the results directory, scenario ID, image folder, image type string,
+/// Joins the scenario directory, image folder, image file name,
 /// and png extension to generate the path.
 #[tracing::instrument(level = "debug")]
-fn get_image_path(scenario: &Scenario, image_type: ImageType) -> String {
+fn get_image_path(
+    scenario: &Scenario,
+    image_type: ImageType,
+    sensors: SensorSelection,
+    colors: PlotColorOptions,
+) -> String {
     debug!("Generating image path");
-    Path::new("file://results")
-        .join(scenario.get_id())
+    let path = scenario
+        .get_directory()
         .join("img")
-        .join(image_type.to_string())
-        .with_extension("png")
-        .to_string_lossy()
-        .into_owned()
+        .join(get_image_file_name(image_type, sensors, colors))
+        .with_extension("png");
+    format!("file://{}", path.display())
+}
+
+/// Returns the file name (without extension) for the image of the given type.
+/// Per-sensor image types get the sensor selection and matrix image types
+/// non-default color options appended so that every variant is cached
+/// separately. Images drawn with the dark plot theme get a `_dark` suffix.
+#[tracing::instrument(level = "trace")]
+fn get_image_file_name(
+    image_type: ImageType,
+    sensors: SensorSelection,
+    colors: PlotColorOptions,
+) -> String {
+    let file_name = if image_type.is_color_mapped() {
+        let mut file_name = image_type.to_string();
+        if let Some(color_map) = colors.color_map {
+            file_name = format!("{file_name}_{color_map}");
+        }
+        if let Some(limit) = colors.limit {
+            file_name = format!("{file_name}_limit_{limit}");
+        }
+        file_name
+    } else if !image_type.is_per_sensor() {
+        image_type.to_string()
+    } else {
+        match sensors {
+            SensorSelection::Single(index) => format!("{image_type}_sensor_{index}"),
+            SensorSelection::All => format!("{image_type}_all_sensors"),
+        }
+    };
+    match colors.theme {
+        PlotTheme::Light => file_name,
+        PlotTheme::Dark => format!("{file_name}_dark"),
+    }
+}
+
+/// Plots the measurements of a single beat for the selected sensor(s).
+///
+/// `measurements` has shape (steps, sensors). A single sensor is plotted as a
+/// standard time plot, all sensors as small multiples.
+#[tracing::instrument(level = "trace", skip(measurements))]
+fn measurement_plot<A>(
+    measurements: &ArrayBase<A, Ix2>,
+    sensors: SensorSelection,
+    sensor_description: &Sensors,
+    sample_rate_hz: f32,
+    path: &Path,
+    name: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle>
+where
+    A: Data<Elem = f32>,
+{
+    match sensors {
+        SensorSelection::Single(index) => {
+            if index >= measurements.shape()[1] {
+                return Err(anyhow::anyhow!(
+                    "Sensor index {index} out of bounds for {} sensors",
+                    measurements.shape()[1]
+                ));
+            }
+            standard_time_plot(
+                &measurements.slice(s![.., index]),
+                sample_rate_hz,
+                path,
+                &format!("Measurement {index} {name}"),
+                &sensor_description.measurement_label(Some(index)),
+                theme,
+            )
+        }
+        SensorSelection::All => small_multiples_time_plot(
+            measurements,
+            sample_rate_hz,
+            path,
+            &format!("Measurements {name}"),
+            None,
+            theme,
+        ),
+    }
+}
+
+/// Plots a per-step loss. Uses the traces of all epochs if they were
+/// recorded and falls back to the last epoch otherwise.
+#[tracing::instrument(level = "trace", skip(last_epoch, traces))]
+fn step_metric_plot(
+    last_epoch: &Array1<f32>,
+    traces: Option<&Array2<f32>>,
+    path: &Path,
+    title: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    if let Some(traces) = traces {
+        let steps = Array1::from_iter(traces.iter().copied());
+        return standard_y_plot(
+            &steps,
+            path,
+            &format!("{title} (All Epochs)"),
+            "Loss",
+            "Step",
+            theme,
+        );
+    }
+    if last_epoch.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Per-step losses were not stored, enable keeping step metrics in the algorithm settings"
+        ));
+    }
+    standard_y_plot(last_epoch, path, title, "Loss", "Step", theme)
+}
+
+/// Plots the recorded parameters over the epochs, one line per recorded
+/// voxel delay and statistic.
+#[tracing::instrument(level = "trace", skip(history))]
+fn parameter_history_plot(
+    history: Option<&ParameterHistory>,
+    path: &Path,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    let Some(history) = history.filter(|history| history.num_epochs() > 0) else {
+        return Err(anyhow::anyhow!(
+            "Parameter history was not recorded, enable it in the algorithm settings"
+        ));
+    };
+    let mut names = Vec::new();
+    let mut ys = Vec::new();
+    for (column, voxel) in history
+        .voxel_delays
+        .columns()
+        .into_iter()
+        .zip(&history.voxels)
+    {
+        names.push(format!("Delay voxel {voxel}"));
+        ys.push(column.to_owned());
+    }
+    for (name, values) in [
+        ("Gains mean", &history.gains_mean),
+        ("Gains std", &history.gains_std),
+        ("Delays mean", &history.delays_mean),
+        ("Delays std", &history.delays_std),
+    ] {
+        if !values.is_empty() {
+            names.push(name.to_string());
+            ys.push(Array1::from(values.clone()));
+        }
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let epochs = Array1::from_iter((0..history.num_epochs()).map(|epoch| epoch as f32));
+    let labels: Vec<&str> = names.iter().map(String::as_str).collect();
+    line_plot(
+        Some(&epochs),
+        ys.iter().collect(),
+        Some(path),
+        Some("Parameter History"),
+        Some("Value"),
+        Some("Epoch"),
+        Some(&labels),
+        None,
+        theme,
+    )
 }
 
 /// Generates the image for the given scenario and image type.
+///
+/// The image is saved as PNG for display in the UI or as SVG for export.
 #[allow(
     clippy::needless_pass_by_value,
     clippy::too_many_lines,
@@ -323,17 +835,39 @@ fn get_image_path(scenario: &Scenario, image_type: ImageType) -> String {
     clippy::used_underscore_binding,
     unreachable_code
 )]
-#[tracing::instrument(level = "debug")]
-fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
+#[tracing::instrument(
+    level = "info",
+    skip(scenario),
+    fields(scenario_id = %scenario.get_id(), profile = "plotting")
+)]
+fn generate_image(
+    scenario: Scenario,
+    image_type: ImageType,
+    sensors: SensorSelection,
+    colors: PlotColorOptions,
+    format: PlotFormat,
+) -> Result<()> {
     debug!("Generating image");
-    let mut path = Path::new("results").join(scenario.get_id()).join("img");
+    let mut path = scenario.get_directory().join("img");
     fs::create_dir_all(&path)
         .with_context(|| format!("Failed to create image directory: {}", path.display()))?;
-    path = path.join(image_type.to_string()).with_extension("png");
+    path = path
+        .join(get_image_file_name(image_type, sensors, colors))
+        .with_extension(format.extension());
     if path.is_file() {
         return Ok(());
     }
     let _file_name = path.with_extension("");
+    let Some(data) = scenario.data.as_ref() else {
+        return Err(anyhow::anyhow!(
+            "Scenario data not available for image generation"
+        ));
+    };
+    if image_type.is_simulation() {
+        simulation_image(&data.simulation, image_type, &path, sensors, colors)
+            .with_context(|| format!("Failed to generate plot for image type: {image_type:?}"))?;
+        return Ok(());
+    }
     let Some(results) = scenario.results.as_ref() else {
         return Err(anyhow::anyhow!(
             "Scenario results not available for image generation"
@@ -345,11 +879,6 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "Model not available in results for image generation"
         ));
     };
-    let Some(data) = scenario.data.as_ref() else {
-        return Err(anyhow::anyhow!(
-            "Scenario data not available for image generation"
-        ));
-    };
     let metrics = &results.metrics;
     match image_type {
         // might want to return this at some later point
@@ -363,25 +892,25 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             None,
             Some(StateSphericalPlotMode::ABS),
             None,
-            None,
-        ),
-        ImageType::StatesMaxSimulation => states_spherical_plot(
-            &data.simulation.system_states_spherical,
-            &data.simulation.system_states_spherical_max,
-            &data
-                .simulation
-                .model
-                .spatial_description
-                .voxels
-                .positions_mm,
-            data.simulation.model.spatial_description.voxels.size_mm,
-            &data.simulation.model.spatial_description.voxels.numbers,
-            Some(&path),
-            None,
-            Some(StateSphericalPlotMode::ABS),
-            None,
-            None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         ),
+        // returned above, as they do not need the results
+        ImageType::StatesMaxSimulation
+        | ImageType::CurrentDensityQuiverSimulation
+        | ImageType::ActivationTimeSimulation
+        | ImageType::ActivationTimeIsochronesSimulation
+        | ImageType::VoxelTypesSimulation
+        | ImageType::AverageDelaySimulation
+        | ImageType::AveragePropagationSpeedSimulation
+        | ImageType::VelocityPerVoxelTypeSimulation
+        | ImageType::ControlFunctionSimulation
+        | ImageType::StateSimulation
+        | ImageType::MeasurementSimulation
+        | ImageType::MeasurementsButterflySimulation => {
+            simulation_image(&data.simulation, image_type, &path, sensors, colors)
+        }
         ImageType::StatesMaxDelta => states_spherical_plot(
             &(&data.simulation.system_states_spherical - &estimations.system_states_spherical),
             &(&data.simulation.system_states_spherical_max
@@ -393,26 +922,31 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             None,
             Some(StateSphericalPlotMode::ABS),
             None,
-            None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         ),
-        ImageType::ActivationTimeAlgorithm => activation_time_plot(
-            &model.functional_description.ap_params.activation_time_ms,
+        ImageType::CurrentDensityQuiverAlgorithm => states_quiver_plot(
+            &estimations.system_states,
             &model.spatial_description.voxels.positions_mm,
             model.spatial_description.voxels.size_mm,
-            &path,
+            &model.spatial_description.voxels.numbers,
+            Some(&path),
             Some(PlotSlice::Z(0)),
+            peak_time_step(&estimations.system_states),
+            None,
+            true,
+            colors.theme,
         ),
-        ImageType::ActivationTimeSimulation => activation_time_plot(
-            &data
-                .simulation
-                .model
-                .functional_description
-                .ap_params
-                .activation_time_ms,
+        ImageType::ActivationTimeAlgorithm => activation_time_plot(
+            &model.functional_description.ap_params.activation_time_ms,
             &model.spatial_description.voxels.positions_mm,
             model.spatial_description.voxels.size_mm,
             &path,
             Some(PlotSlice::Z(0)),
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         ),
         ImageType::ActivationTimeDelta => {
             let gt = &data
@@ -439,26 +973,117 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
                 model.spatial_description.voxels.size_mm,
                 &path,
                 Some(PlotSlice::Z(0)),
+                colors.range(),
+                Some(colors.color_map(image_type)),
+                colors.theme,
             )
         }
-        ImageType::VoxelTypesAlgorithm => voxel_type_plot(
-            &model.spatial_description.voxels.types,
+        ImageType::ActivationTimeIsochronesAlgorithm => activation_time_isochrone_plot(
+            &model.functional_description.ap_params.activation_time_ms,
             &model.spatial_description.voxels.positions_mm,
             model.spatial_description.voxels.size_mm,
-            Some(&path),
-            None,
+            &path,
+            Some(PlotSlice::Z(0)),
+            DEFAULT_ISOCHRONE_INTERVAL_MS,
+            colors.theme,
         ),
-        ImageType::VoxelTypesSimulation => voxel_type_plot(
-            &data.simulation.model.spatial_description.voxels.types,
+        ImageType::ActivationTimeIsochronesOverlay => activation_time_isochrone_overlay_plot(
             &data
                 .simulation
                 .model
-                .spatial_description
-                .voxels
-                .positions_mm,
-            data.simulation.model.spatial_description.voxels.size_mm,
+                .functional_description
+                .ap_params
+                .activation_time_ms,
+            &model.functional_description.ap_params.activation_time_ms,
+            &model.spatial_description.voxels.positions_mm,
+            model.spatial_description.voxels.size_mm,
+            &path,
+            Some(PlotSlice::Z(0)),
+            DEFAULT_ISOCHRONE_INTERVAL_MS,
+            colors.theme,
+        ),
+        ImageType::ActivationTimeBlandAltman => {
+            let pairs = activation_time_pairs(
+                &data
+                    .simulation
+                    .model
+                    .functional_description
+                    .ap_params
+                    .activation_time_ms,
+                &model.functional_description.ap_params.activation_time_ms,
+            );
+            let errors_ms: Vec<f32> = pairs
+                .iter()
+                .map(|(simulated, estimated)| estimated - simulated)
+                .collect();
+            let statistics =
+                ActivationTimeStatistics::from_errors(&errors_ms).ok_or_else(|| {
+                    anyhow::anyhow!("No voxel is activated in both simulation and estimation")
+                })?;
+            bland_altman_plot(
+                &pairs,
+                &statistics,
+                &path,
+                "Activation Time Bland-Altman",
+                "[ms]",
+                colors.theme,
+            )
+        }
+        ImageType::ActivationTimeStd | ImageType::CurrentDensityStd => {
+            let ensemble = results.ensemble.as_ref().ok_or_else(|| {
+                anyhow::anyhow!("Ensemble not available - enable it in the algorithm settings")
+            })?;
+            let (standard_deviation, quantity, unit) = if image_type == ImageType::ActivationTimeStd
+            {
+                (&ensemble.activation_time_std_ms, "Activation time", "[ms]")
+            } else {
+                (&ensemble.current_density_std, "Current density", "[A/mm^2]")
+            };
+            standard_deviation_plot(
+                standard_deviation,
+                &model.spatial_description.voxels.positions_mm,
+                model.spatial_description.voxels.size_mm,
+                &path,
+                Some(PlotSlice::Z(0)),
+                quantity,
+                unit,
+                colors.range(),
+                Some(colors.color_map(image_type)),
+                colors.theme,
+            )
+        }
+        ImageType::GainStd | ImageType::DelayStd => {
+            let confidence = results.confidence.as_ref().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Parameter confidence not available - enable it in the algorithm settings"
+                )
+            })?;
+            let numbers = &model.spatial_description.voxels.numbers;
+            let (standard_deviation, quantity, unit) = if image_type == ImageType::GainStd {
+                (confidence.gains_std_map(numbers), "Gain", "[a.u.]")
+            } else {
+                (confidence.delays_std_map(numbers), "Delay", "[samples]")
+            };
+            standard_deviation_plot(
+                &standard_deviation,
+                &model.spatial_description.voxels.positions_mm,
+                model.spatial_description.voxels.size_mm,
+                &path,
+                Some(PlotSlice::Z(0)),
+                quantity,
+                unit,
+                colors.range(),
+                Some(colors.color_map(image_type)),
+                colors.theme,
+            )
+        }
+        ImageType::VoxelTypesAlgorithm => voxel_type_plot(
+            &model.spatial_description.voxels.types,
+            &model.spatial_description.voxels.positions_mm,
+            model.spatial_description.voxels.size_mm,
             Some(&path),
             None,
+            colors.theme,
         ),
         ImageType::VoxelTypesPrediction => voxel_type_plot(
             &predict_voxeltype(
@@ -476,35 +1101,8 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             model.spatial_description.voxels.size_mm,
             Some(&path),
             None,
+            colors.theme,
         ),
-        ImageType::AverageDelaySimulation => Ok(average_delay_plot(
-            &data.simulation.average_delays,
-            &data.simulation.model.spatial_description.voxels.numbers,
-            &data
-                .simulation
-                .model
-                .spatial_description
-                .voxels
-                .positions_mm,
-            data.simulation.model.spatial_description.voxels.size_mm,
-            &path,
-            None,
-            None,
-        )?),
-        ImageType::AveragePropagationSpeedSimulation => Ok(average_propagation_speed_plot(
-            &data.simulation.average_delays,
-            &data.simulation.model.spatial_description.voxels.numbers,
-            &data
-                .simulation
-                .model
-                .spatial_description
-                .voxels
-                .positions_mm,
-            data.simulation.model.spatial_description.voxels.size_mm,
-            data.simulation.sample_rate_hz,
-            &path,
-            None,
-        )?),
         ImageType::AverageDelayAlgorithm => Ok(average_delay_plot(
             &estimations.average_delays,
             &model.spatial_description.voxels.numbers,
@@ -513,6 +1111,9 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             &path,
             None,
             None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         )?),
         ImageType::AveragePropagationSpeedAlgorithm => Ok(average_propagation_speed_plot(
             &estimations.average_delays,
@@ -522,6 +1123,9 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             data.simulation.sample_rate_hz,
             &path,
             None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         )?),
         ImageType::AverageDelayDelta => Ok(average_delay_plot(
             &(&data.simulation.average_delays - &estimations.average_delays),
@@ -531,28 +1135,51 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             &path,
             None,
             None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
         )?),
+        ImageType::VelocityPerVoxelTypeAlgorithm => velocity_box_plot(
+            &calculate_velocity_statistics(
+                &estimations.average_delays,
+                &model.spatial_description.voxels.numbers,
+                &data.simulation.model.spatial_description.voxels.types,
+                model.spatial_description.voxels.size_mm,
+                data.simulation.sample_rate_hz,
+            ),
+            &path,
+            "Propagation Velocity per Voxel Type Algorithm",
+            colors.theme,
+        ),
         ImageType::LossEpoch => standard_log_y_plot(
             &metrics.loss_batch,
             &path,
             "Sum Loss Per Epoch",
             "Loss",
             "Epoch",
+            colors.theme,
+        ),
+        ImageType::Loss => step_metric_plot(
+            &metrics.loss,
+            metrics.step_traces.as_ref().map(|traces| &traces.loss),
+            &path,
+            "Loss Per Step",
+            colors.theme,
         ),
-        ImageType::Loss => standard_y_plot(&metrics.loss, &path, "Loss Per Step", "Loss", "Step"),
         ImageType::LossMseEpoch => standard_log_y_plot(
             &metrics.loss_mse_batch,
             &path,
             "Sum MSE Loss Per Epoch",
             "Loss",
             "Epoch",
+            colors.theme,
         ),
-        ImageType::LossMse => standard_y_plot(
+        ImageType::LossMse => step_metric_plot(
             &metrics.loss_mse,
+            metrics.step_traces.as_ref().map(|traces| &traces.loss_mse),
             &path,
             "MSE Loss Per Step",
-            "Loss",
-            "Step",
+            colors.theme,
         ),
         ImageType::LossMaximumRegularizationEpoch => standard_log_y_plot(
             &metrics.loss_maximum_regularization_batch,
@@ -560,13 +1187,17 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "Sum Max. Reg. Loss Per Epoch",
             "Loss",
             "Epoch",
+            colors.theme,
         ),
-        ImageType::LossMaximumRegularization => standard_y_plot(
+        ImageType::LossMaximumRegularization => step_metric_plot(
             &metrics.loss_maximum_regularization,
+            metrics
+                .step_traces
+                .as_ref()
+                .map(|traces| &traces.loss_maximum_regularization),
             &path,
             "Max. Reg. Loss Per Step",
-            "Loss",
-            "Step",
+            colors.theme,
         ),
         ImageType::Dice => standard_y_plot(
             &metrics.dice_score_over_threshold,
@@ -574,6 +1205,7 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "Dice Score over Threshold",
             "Dice Score",
             "Threshold * 100",
+            colors.theme,
         ),
         ImageType::IoU => standard_y_plot(
             &metrics.iou_over_threshold,
@@ -581,6 +1213,7 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "IoU over Threshold",
             "IoU",
             "Threshold * 100",
+            colors.theme,
         ),
         ImageType::Recall => standard_y_plot(
             &metrics.recall_over_threshold,
@@ -588,6 +1221,7 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "Recall over Threshold",
             "Recall",
             "Threshold * 100",
+            colors.theme,
         ),
         ImageType::Precision => standard_y_plot(
             &metrics.precision_over_threshold,
@@ -595,24 +1229,98 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             "Precision over Threshold",
             "Precision",
             "Threshold * 100",
+            colors.theme,
+        ),
+        ImageType::Roc => line_plot(
+            Some(&metrics.false_positive_rate_over_threshold),
+            vec![
+                &metrics.recall_over_threshold,
+                &metrics.false_positive_rate_over_threshold,
+            ],
+            Some(&path),
+            Some(&format!("ROC Curve (AUC = {:.3})", metrics.roc_auc())),
+            Some("True Positive Rate"),
+            Some("False Positive Rate"),
+            Some(&vec!["ROC", "Chance"]),
+            None,
+            colors.theme,
         ),
+        ImageType::PrecisionRecall => line_plot(
+            Some(&metrics.recall_over_threshold),
+            vec![&metrics.precision_over_threshold],
+            Some(&path),
+            Some(&format!(
+                "Precision-Recall Curve (AUC = {:.3})",
+                metrics.precision_recall_auc()
+            )),
+            Some("Precision"),
+            Some("Recall"),
+            None,
+            None,
+            colors.theme,
+        ),
+        ImageType::DiceOverLambda => {
+            if metrics.regularization_lambdas.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Regularization path not calculated, enable it for the pseudo inverse"
+                ));
+            }
+            line_plot(
+                Some(&metrics.regularization_lambdas.mapv(f32::log10)),
+                vec![&metrics.dice_score_over_lambda],
+                Some(&path),
+                Some("Dice Score over Lambda"),
+                Some("Dice Score"),
+                Some("log10(Lambda)"),
+                None,
+                None,
+                colors.theme,
+            )
+        }
+        ImageType::ParameterHistory => {
+            parameter_history_plot(metrics.parameter_history.as_ref(), &path, colors.theme)
+        }
+        ImageType::MeasurementSingularValues => {
+            // diagnosed on demand if it was not enabled for the run
+            let conditioning = match results.conditioning.as_ref() {
+                Some(conditioning) => conditioning.clone(),
+                None => calculate_measurement_conditioning(
+                    &model.functional_description.measurement_matrix,
+                )?,
+            };
+            #[allow(clippy::cast_precision_loss)]
+            let indices = Array1::from_iter(
+                (0..conditioning.singular_values.len()).map(|index| index as f32),
+            );
+            // zero singular values are drawn at the smallest positive value
+            let singular_values = conditioning
+                .singular_values
+                .mapv(|value| value.max(f32::MIN_POSITIVE).log10());
+            let title = format!(
+                "Measurement Matrix Singular Values (condition number {:.3e}, rank {} of {})",
+                conditioning.condition_number,
+                conditioning.effective_rank,
+                conditioning.number_of_states
+            );
+            line_plot(
+                Some(&indices),
+                vec![&singular_values],
+                Some(&path),
+                Some(&title),
+                Some("log10(Singular Value)"),
+                Some("Index"),
+                None,
+                None,
+                colors.theme,
+            )
+        }
         ImageType::ControlFunctionAlgorithm => standard_time_plot(
             &model.functional_description.control_function_values,
             scenario.config.simulation.sample_rate_hz,
             &path,
             "Control Function Algorithm",
             "u [A/mm^2]",
-        ),
-        ImageType::ControlFunctionSimulation => standard_time_plot(
-            &data
-                .simulation
-                .model
-                .functional_description
-                .control_function_values,
-            scenario.config.simulation.sample_rate_hz,
-            &path,
-            "Control Function Simulation",
-            "u [A/mm^2]",
+            colors.theme,
         ),
         ImageType::ControlFunctionDelta => standard_time_plot(
             &(&*model.functional_description.control_function_values
@@ -625,6 +1333,7 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             &path,
             "Control Function Delta",
             "u [A/mm^2]",
+            colors.theme,
         ),
         ImageType::StateAlgorithm => standard_time_plot(
             &estimations.system_states.slice(s![.., 0]).to_owned(),
@@ -632,13 +1341,7 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             &path,
             "System State 0 Algorithm",
             "j [A/mm^2]",
-        ),
-        ImageType::StateSimulation => standard_time_plot(
-            &data.simulation.system_states.slice(s![.., 0]).to_owned(),
-            scenario.config.simulation.sample_rate_hz,
-            &path,
-            "System State 0 Simulation",
-            "j [A/mm^2]",
+            colors.theme,
         ),
         ImageType::StateDelta => standard_time_plot(
             &(&estimations.system_states.slice(s![.., 0]).to_owned()
@@ -647,34 +1350,236 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
             &path,
             "System State 0 Delta",
             "j [A/mm^2]",
+            colors.theme,
         ),
-        ImageType::MeasurementAlgorithm => standard_time_plot(
-            &estimations.measurements.slice(s![0, .., 0]).to_owned(),
+        ImageType::MeasurementAlgorithm => measurement_plot(
+            &estimations.measurements.slice(s![0, .., ..]),
+            sensors,
+            &data.simulation.model.spatial_description.sensors,
             scenario.config.simulation.sample_rate_hz,
             &path,
-            "Measurement 0 Algorithm",
-            "z [pT]",
+            "Algorithm",
+            colors.theme,
         ),
-        ImageType::MeasurementSimulation => standard_time_plot(
-            &data.simulation.measurements.slice(s![0, .., 0]).to_owned(),
+        ImageType::MeasurementDelta => measurement_plot(
+            &(&estimations.measurements.slice(s![0, .., ..])
+                - &data.simulation.measurements.slice(s![0, .., ..])),
+            sensors,
+            &data.simulation.model.spatial_description.sensors,
             scenario.config.simulation.sample_rate_hz,
             &path,
-            "Measurement 0 Simulation",
-            "z [pT]",
+            "Delta",
+            colors.theme,
         ),
-        ImageType::MeasurementDelta => standard_time_plot(
-            &(&estimations.measurements.slice(s![0, .., 0]).to_owned()
-                - &data.simulation.measurements.slice(s![0, .., 0]).to_owned()),
+        ImageType::MeasurementsButterflyAlgorithm => measurement_butterfly_plot(
+            &estimations.measurements.slice(s![0, .., ..]),
             scenario.config.simulation.sample_rate_hz,
             &path,
-            "Measurement 0 Delta",
-            "z [pT]",
+            "Measurements Algorithm",
+            &data
+                .simulation
+                .model
+                .spatial_description
+                .sensors
+                .measurement_label(None),
+            colors.theme,
         ),
+        ImageType::ResidualPerSensorMean | ImageType::ResidualPerSensorPeak => {
+            let (statistic, title) = if image_type == ImageType::ResidualPerSensorMean {
+                (ResidualStatistic::Mean, "Mean Absolute Residual per Sensor")
+            } else {
+                (ResidualStatistic::Peak, "Peak Absolute Residual per Sensor")
+            };
+            let sensor_description = &data.simulation.model.spatial_description.sensors;
+            sensor_residual_plot(
+                &(&estimations.measurements.slice(s![0, .., ..])
+                    - &data.simulation.measurements.slice(s![0, .., ..])),
+                &sensor_description.positions_mm,
+                statistic,
+                &path,
+                title,
+                sensor_description
+                    .measurement_label(None)
+                    .trim_start_matches("z "),
+                colors.theme,
+            )
+        }
     }
     .with_context(|| format!("Failed to generate plot for image type: {image_type:?}"))?;
     Ok(())
 }
 
+/// Generates an image of the simulation, which only needs the simulated
+/// data and is therefore also available for scenarios without results.
+#[tracing::instrument(level = "debug", skip(simulation))]
+fn simulation_image(
+    simulation: &Simulation,
+    image_type: ImageType,
+    path: &Path,
+    sensors: SensorSelection,
+    colors: PlotColorOptions,
+) -> Result<PngBundle> {
+    match image_type {
+        ImageType::StatesMaxSimulation => states_spherical_plot(
+            &simulation.system_states_spherical,
+            &simulation.system_states_spherical_max,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            &simulation.model.spatial_description.voxels.numbers,
+            Some(path),
+            None,
+            Some(StateSphericalPlotMode::ABS),
+            None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
+        ),
+        ImageType::CurrentDensityQuiverSimulation => states_quiver_plot(
+            &simulation.system_states,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            &simulation.model.spatial_description.voxels.numbers,
+            Some(path),
+            Some(PlotSlice::Z(0)),
+            peak_time_step(&simulation.system_states),
+            None,
+            true,
+            colors.theme,
+        ),
+        ImageType::ActivationTimeSimulation => activation_time_plot(
+            &simulation
+                .model
+                .functional_description
+                .ap_params
+                .activation_time_ms,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            path,
+            Some(PlotSlice::Z(0)),
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
+        ),
+        ImageType::ActivationTimeIsochronesSimulation => activation_time_isochrone_plot(
+            &simulation
+                .model
+                .functional_description
+                .ap_params
+                .activation_time_ms,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            path,
+            Some(PlotSlice::Z(0)),
+            DEFAULT_ISOCHRONE_INTERVAL_MS,
+            colors.theme,
+        ),
+        ImageType::VoxelTypesSimulation => voxel_type_plot(
+            &simulation.model.spatial_description.voxels.types,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            Some(path),
+            None,
+            colors.theme,
+        ),
+        ImageType::AverageDelaySimulation => Ok(average_delay_plot(
+            &simulation.average_delays,
+            &simulation.model.spatial_description.voxels.numbers,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            path,
+            None,
+            None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
+        )?),
+        ImageType::AveragePropagationSpeedSimulation => Ok(average_propagation_speed_plot(
+            &simulation.average_delays,
+            &simulation.model.spatial_description.voxels.numbers,
+            &simulation.model.spatial_description.voxels.positions_mm,
+            simulation.model.spatial_description.voxels.size_mm,
+            simulation.sample_rate_hz,
+            path,
+            None,
+            colors.range(),
+            Some(colors.color_map(image_type)),
+            colors.theme,
+        )?),
+        ImageType::VelocityPerVoxelTypeSimulation => velocity_box_plot(
+            &calculate_velocity_statistics(
+                &simulation.average_delays,
+                &simulation.model.spatial_description.voxels.numbers,
+                &simulation.model.spatial_description.voxels.types,
+                simulation.model.spatial_description.voxels.size_mm,
+                simulation.sample_rate_hz,
+            ),
+            path,
+            "Propagation Velocity per Voxel Type Simulation",
+            colors.theme,
+        ),
+        ImageType::ControlFunctionSimulation => standard_time_plot(
+            &simulation
+                .model
+                .functional_description
+                .control_function_values,
+            simulation.sample_rate_hz,
+            path,
+            "Control Function Simulation",
+            "u [A/mm^2]",
+            colors.theme,
+        ),
+        ImageType::StateSimulation => standard_time_plot(
+            &simulation.system_states.slice(s![.., 0]).to_owned(),
+            simulation.sample_rate_hz,
+            path,
+            "System State 0 Simulation",
+            "j [A/mm^2]",
+            colors.theme,
+        ),
+        ImageType::MeasurementSimulation => measurement_plot(
+            &simulation.measurements.slice(s![0, .., ..]),
+            sensors,
+            &simulation.model.spatial_description.sensors,
+            simulation.sample_rate_hz,
+            path,
+            "Simulation",
+            colors.theme,
+        ),
+        ImageType::MeasurementsButterflySimulation => measurement_butterfly_plot(
+            &simulation.measurements.slice(s![0, .., ..]),
+            simulation.sample_rate_hz,
+            path,
+            "Measurements Simulation",
+            &simulation
+                .model
+                .spatial_description
+                .sensors
+                .measurement_label(None),
+            colors.theme,
+        ),
+        _ => Err(anyhow::anyhow!(
+            "Image type {image_type:?} is not a simulation image"
+        )),
+    }
+}
+
+/// Generates the GIF of the given type for the scenario in a background
+/// thread. Existing GIFs are kept.
+#[tracing::instrument(level = "debug", skip(scenario))]
+pub(super) fn spawn_gif_generation(
+    scenario: &Scenario,
+    gif_type: GifType,
+    playback_speed: f32,
+    theme: PlotTheme,
+) {
+    let send_scenario = scenario.clone();
+    thread::spawn(move || {
+        if let Err(e) = generate_gifs(send_scenario, gif_type, playback_speed, theme) {
+            error!("Failed to generate {} GIF: {}", gif_type, e);
+        }
+    });
+}
+
 /// Generates animated GIF visualizations of the system states over time from the simulation results.
 ///
 /// For each GIF type specified, renders frames showing the system state values across all voxels
@@ -685,10 +1590,19 @@ fn generate_image(scenario: Scenario, image_type: ImageType) -> Result<()> {
     clippy::too_many_lines,
     clippy::useless_let_if_seq
 )]
-#[tracing::instrument(level = "debug")]
-fn generate_gifs(scenario: Scenario, gif_type: GifType, playback_speed: f32) -> Result<()> {
+#[tracing::instrument(
+    level = "info",
+    skip(scenario),
+    fields(scenario_id = %scenario.get_id(), profile = "plotting")
+)]
+fn generate_gifs(
+    scenario: Scenario,
+    gif_type: GifType,
+    playback_speed: f32,
+    theme: PlotTheme,
+) -> Result<()> {
     debug!("Generating GIFs for scenario {}", scenario.get_id());
-    let mut path = Path::new("results").join(scenario.get_id()).join("img");
+    let mut path = scenario.get_directory().join("img");
     fs::create_dir_all(&path)
         .with_context(|| format!("Failed to create GIF directory: {}", path.display()))?;
     path = path.join(gif_type.to_string()).with_extension("gif");
@@ -724,6 +1638,7 @@ fn generate_gifs(scenario: Scenario, gif_type: GifType, playback_speed: f32) ->
             Some(StateSphericalPlotMode::ABS),
             Some(playback_speed),
             Some(20),
+            theme,
         ),
         GifType::StatesSimulation => states_spherical_plot_over_time(
             &data.simulation.system_states_spherical,
@@ -742,6 +1657,60 @@ fn generate_gifs(scenario: Scenario, gif_type: GifType, playback_speed: f32) ->
             Some(StateSphericalPlotMode::ABS),
             Some(playback_speed),
             Some(20),
+            theme,
+        ),
+        GifType::QuiverAlgorithm => states_quiver_plot_over_time(
+            &estimations.system_states,
+            &model.spatial_description.voxels.positions_mm,
+            model.spatial_description.voxels.size_mm,
+            scenario.config.simulation.sample_rate_hz,
+            &model.spatial_description.voxels.numbers,
+            Some(path.as_path()),
+            Some(PlotSlice::Z(0)),
+            true,
+            Some(playback_speed),
+            Some(20),
+            theme,
+        ),
+        GifType::QuiverSimulation => states_quiver_plot_over_time(
+            &data.simulation.system_states,
+            &data
+                .simulation
+                .model
+                .spatial_description
+                .voxels
+                .positions_mm,
+            data.simulation.model.spatial_description.voxels.size_mm,
+            scenario.config.simulation.sample_rate_hz,
+            &data.simulation.model.spatial_description.voxels.numbers,
+            Some(path.as_path()),
+            Some(PlotSlice::Z(0)),
+            true,
+            Some(playback_speed),
+            Some(20),
+            theme,
+        ),
+        GifType::Composite => composite_plot_over_time(
+            CompositePanel {
+                states: &data.simulation.system_states_spherical,
+                states_max: &data.simulation.system_states_spherical_max,
+                measurements: data.simulation.measurements.slice(s![0, .., ..]),
+            },
+            CompositePanel {
+                states: &estimations.system_states_spherical,
+                states_max: &estimations.system_states_spherical_max,
+                measurements: estimations.measurements.slice(s![0, .., ..]),
+            },
+            &model.spatial_description.voxels.positions_mm,
+            model.spatial_description.voxels.size_mm,
+            scenario.config.simulation.sample_rate_hz,
+            &model.spatial_description.voxels.numbers,
+            Some(path.as_path()),
+            Some(PlotSlice::Z(0)),
+            None,
+            Some(playback_speed),
+            Some(20),
+            theme,
         ),
     }
     .with_context(|| format!("Failed to generate GIF for type: {gif_type:?}"))?;
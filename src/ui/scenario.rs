@@ -1,41 +1,71 @@
 mod algorithm;
 pub mod common;
 mod data;
+mod footprint;
+mod history;
+mod notes;
+mod storage;
 
 use bevy::prelude::*;
 use bevy_editor_cam::prelude::{EditorCam, EnabledMotion};
 use bevy_egui::{egui, EguiContexts};
 use egui::Align;
-use tracing::error;
+use tracing::{error, info};
 
-use self::{algorithm::draw_ui_scenario_algoriothm, data::draw_ui_scenario_data};
+use self::{
+    algorithm::draw_ui_scenario_algoriothm,
+    data::draw_ui_scenario_data,
+    footprint::{draw_ui_scenario_footprint, FootprintEstimate},
+    history::ConfigHistory,
+    notes::{draw_ui_scenario_notes, NotesPanel},
+    storage::draw_ui_scenario_storage,
+};
 use crate::{
     core::{
-        config::model::{
-            Handcrafted, Mri, DEFAULT_HEART_OFFSET_HANDCRAFTED, DEFAULT_HEART_OFFSET_MRI,
+        config::{
+            model::{Handcrafted, Mri, DEFAULT_HEART_OFFSET_HANDCRAFTED, DEFAULT_HEART_OFFSET_MRI},
+            validation::{ConfigIssue, Severity},
         },
-        scenario::{Scenario, Status},
+        scenario::{template::Template, RecoveryAction, Scenario, Status},
     },
-    ScenarioBundle, ScenarioList, SelectedSenario,
+    ScenarioList, SelectedSenario, TemplateList,
 };
 
-const FIRST_COLUMN_WIDTH: f32 = 150.0;
-const SECOND_COLUMN_WIDTH: f32 = 200.0;
-const PADDING: f32 = 20.0;
-const ROW_HEIGHT: f32 = 30.0;
+pub(super) const FIRST_COLUMN_WIDTH: f32 = 150.0;
+pub(super) const SECOND_COLUMN_WIDTH: f32 = 200.0;
+pub(super) const PADDING: f32 = 20.0;
+pub(super) const ROW_HEIGHT: f32 = 30.0;
+
+const UNDO_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+const REDO_SHORTCUTS: [egui::KeyboardShortcut; 2] = [
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y),
+    egui::KeyboardShortcut::new(
+        egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+        egui::Key::Z,
+    ),
+];
 
 /// Draws the UI for the selected scenario.
 ///
 /// This handles:
 /// - The top bar with scenario list and controls
+/// - The panel with the tags and markdown notes of the selected scenario
 /// - The central panel showing details of the selected scenario
-#[allow(clippy::module_name_repetitions)]
+/// - Undoing and redoing config edits of scenarios in planning, also with
+///   Ctrl+Z and Ctrl+Y or Ctrl+Shift+Z outside of text fields
+#[allow(clippy::module_name_repetitions, clippy::too_many_arguments)]
 #[tracing::instrument(skip_all, level = "trace")]
 pub fn draw_ui_scenario(
     mut contexts: EguiContexts,
     mut scenarios: ResMut<ScenarioList>,
     mut selected_scenario: ResMut<SelectedSenario>,
+    mut templates: ResMut<TemplateList>,
     mut cameras: Query<&mut EditorCam, With<Camera>>,
+    mut template_name: Local<String>,
+    mut footprint: Local<FootprintEstimate>,
+    mut history: Local<ConfigHistory>,
+    mut notes: Local<NotesPanel>,
 ) {
     trace!("Running system to draw scenario UI.");
     let context = match contexts.ctx_mut() {
@@ -50,7 +80,10 @@ pub fn draw_ui_scenario(
         context,
         &mut scenarios,
         &mut selected_scenario,
+        &mut templates,
+        &mut template_name,
         &mut cameras,
+        &mut history,
     );
 
     let Some(index) = selected_scenario.index else {
@@ -62,22 +95,49 @@ pub fn draw_ui_scenario(
         return;
     };
     let scenario = &mut entry.scenario;
-    draw_ui_scenario_central_panel(context, scenario, &mut cameras);
+    let editable = *scenario.get_status() == Status::Planning && !scenario.is_read_only();
+    if editable && !context.wants_keyboard_input() {
+        let (undo, redo) = context.input_mut(|input| {
+            (
+                input.consume_shortcut(&UNDO_SHORTCUT),
+                REDO_SHORTCUTS
+                    .iter()
+                    .any(|shortcut| input.consume_shortcut(shortcut)),
+            )
+        });
+        if undo {
+            history.undo(&mut scenario.config);
+        } else if redo {
+            history.redo(&mut scenario.config);
+        }
+    }
+    draw_ui_scenario_notes(context, scenario, &mut notes);
+    draw_ui_scenario_central_panel(context, scenario, &mut footprint, &mut cameras);
+    if editable {
+        let interacting = context.is_using_pointer() || context.wants_keyboard_input();
+        history.track(scenario.get_id(), &scenario.config, interacting);
+    } else {
+        history.clear();
+    }
 }
 
 /// Draws the top bar UI for the scenario view.
 ///
 /// This shows:
-/// - The ID and status of the selected scenario
+/// - The ID, status and root directory of the selected scenario
 /// - Controls to change the status and save the scenario
-/// - A text area to edit the scenario description
-/// - Buttons to copy, delete or select a different scenario
-#[tracing::instrument(skip(context), level = "trace")]
+/// - Buttons to duplicate, delete, export or select a different scenario
+/// - A field and button to save the configuration as a template
+/// - Buttons to undo and redo config edits
+#[tracing::instrument(skip(context, history), level = "trace")]
 fn draw_ui_scenario_topbar(
     context: &egui::Context,
     scenarios: &mut ResMut<ScenarioList>,
     selected_scenario: &mut ResMut<SelectedSenario>,
+    templates: &mut ResMut<TemplateList>,
+    template_name: &mut String,
     cameras: &mut Query<&mut EditorCam, With<Camera>>,
+    history: &mut ConfigHistory,
 ) {
     trace!("Running system to draw scenario topbar.");
     egui::TopBottomPanel::top("scenario_status").show(context, |ui| {
@@ -107,6 +167,16 @@ fn draw_ui_scenario_topbar(
             ui.separator();
             ui.label(format!("Status: {}", scenario.get_status_str()));
             ui.separator();
+            let read_only = scenario.is_read_only();
+            if read_only {
+                ui.label(format!(
+                    "Root: {} (read-only)",
+                    scenario.get_root().display()
+                ));
+            } else {
+                ui.label(format!("Root: {}", scenario.get_root().display()));
+            }
+            ui.separator();
             ui.vertical(|ui| {
                 let mut handcrafted = scenario.config.algorithm.model.handcrafted.is_some();
                 let simulation = &mut scenario.config.simulation;
@@ -137,10 +207,22 @@ fn draw_ui_scenario_topbar(
             });
             ui.separator();
             match scenario.get_status() {
+                _ if read_only => (),
                 Status::Planning => {
-                    if ui.button("Schedule").clicked() {
+                    let errors = scenario
+                        .config_issues()
+                        .iter()
+                        .filter(|issue| issue.severity == Severity::Error)
+                        .count();
+                    if ui
+                        .add_enabled(errors == 0, egui::Button::new("Schedule"))
+                        .on_disabled_hover_text(format!(
+                            "{errors} invalid parameters, see the settings below."
+                        ))
+                        .clicked()
+                    {
                         if let Err(e) = scenario.schedule() {
-                            error!("Failed to schedule scenario: {}", e);
+                            error!("Failed to schedule scenario: {:#}", e);
                         }
                     }
                 }
@@ -151,51 +233,86 @@ fn draw_ui_scenario_topbar(
                         }
                     }
                 }
+                Status::Interrupted => {
+                    if scenario.has_autosave() && ui.button("Resume").clicked() {
+                        if let Err(e) = scenario.recover(RecoveryAction::Resume) {
+                            error!("Failed to resume scenario: {}", e);
+                        }
+                    } else if ui.button("Reschedule").clicked() {
+                        if let Err(e) = scenario.recover(RecoveryAction::Reschedule) {
+                            error!("Failed to reschedule scenario: {}", e);
+                        }
+                    } else if ui.button("Mark Aborted").clicked() {
+                        if let Err(e) = scenario.recover(RecoveryAction::Abort) {
+                            error!("Failed to abort scenario: {}", e);
+                        }
+                    }
+                }
                 _ => (),
             }
-            if ui.button("Save").clicked() {
+            ui.add(
+                egui::TextEdit::singleline(template_name)
+                    .hint_text("Template name")
+                    .desired_width(120.0),
+            );
+            if ui.button("Save as Template").clicked() {
+                match Template::from_scenario(template_name, scenario)
+                    .and_then(|template| template.save().map(|()| template))
+                {
+                    Ok(template) => {
+                        templates.insert(template);
+                        template_name.clear();
+                    }
+                    Err(e) => error!("Failed to save template: {}", e),
+                }
+            }
+            ui.separator();
+            let planning = *scenario.get_status() == Status::Planning && !read_only;
+            if ui
+                .add_enabled(planning && history.can_undo(), egui::Button::new("Undo"))
+                .on_hover_text("Undo the last config edit (Ctrl+Z).")
+                .clicked()
+            {
+                history.undo(&mut scenario.config);
+            } else if ui
+                .add_enabled(planning && history.can_redo(), egui::Button::new("Redo"))
+                .on_hover_text("Redo the last undone config edit (Ctrl+Y).")
+                .clicked()
+            {
+                history.redo(&mut scenario.config);
+            }
+            ui.separator();
+            if ui
+                .add_enabled(!read_only, egui::Button::new("Save"))
+                .clicked()
+            {
                 if let Err(e) = scenario.save() {
                     error!("Failed to save scenario: {}", e);
                 }
-            } else if ui.button("Delete").clicked() {
+            } else if ui
+                .add_enabled(!read_only, egui::Button::new("Delete"))
+                .clicked()
+            {
                 if let Err(e) = scenario.delete() {
                     error!("Failed to delete scenario: {}", e);
                 } else {
                     scenarios.entries.remove(index);
                     selected_scenario.index = Some(0);
                 }
-            } else if ui.button("Copy").clicked() {
-                let mut new_scenario =
-                    Scenario::build(None).expect("Failed to create new scenario");
-                new_scenario.config = scenario.config.clone();
-                new_scenario.comment.clone_from(&scenario.comment);
-                scenarios.entries.push(ScenarioBundle {
-                    scenario: new_scenario,
-                    join_handle: None,
-                    epoch_rx: None,
-                    summary_rx: None,
-                });
-                selected_scenario.index = Some(scenarios.entries.len() - 1);
-            }
-            ui.separator();
-            let Some(index) = selected_scenario.index else {
-                error!("No scenario selected for comment editing");
-                return;
-            };
-            let Some(entry) = scenarios.entries.get_mut(index) else {
-                error!(
-                    "Selected scenario index {} is out of bounds for comment editing",
-                    index
-                );
-                return;
-            };
-            let scenario = &mut entry.scenario;
-            if ui
-                .add(egui::TextEdit::multiline(&mut scenario.comment).desired_width(f32::INFINITY))
-                .lost_focus()
-            {
-                if let Err(e) = scenario.save() {
-                    error!("Failed to save scenario: {}", e);
+            } else if ui.button("Duplicate").clicked() {
+                match entry.clone_with_new_id() {
+                    Ok(duplicate) => {
+                        scenarios.entries.push(duplicate);
+                        selected_scenario.index = Some(scenarios.entries.len() - 1);
+                    }
+                    Err(e) => error!("Failed to duplicate scenario: {}", e),
+                }
+            } else if ui.button("Export").clicked() {
+                let path = scenario.default_archive_path();
+                if let Err(e) = scenario.export_archive(&path) {
+                    error!("Failed to export scenario: {}", e);
+                } else {
+                    info!("Exported scenario to {}", path.display());
                 }
             }
         });
@@ -207,10 +324,15 @@ fn draw_ui_scenario_topbar(
 /// Splits the panel into two columns using egui columns.
 /// The left column calls `draw_ui_scenario_data` to show scenario data.
 /// The right column calls `draw_ui_scenario_algorithm` to show algorithm settings.
-#[tracing::instrument(skip(context), level = "trace")]
+/// Above the columns, the footprint of scenarios in planning can be estimated
+/// and the storage settings are shown. Invalid and suspicious parameters of
+/// scenarios in planning are listed at the top of each column.
+/// Scenarios from read-only archive directories cannot be edited.
+#[tracing::instrument(skip(context, footprint), level = "trace")]
 fn draw_ui_scenario_central_panel(
     context: &egui::Context,
     scenario: &mut Scenario,
+    footprint: &mut FootprintEstimate,
     cameras: &mut Query<&mut EditorCam, With<Camera>>,
 ) {
     trace!("Running system to draw scenario central panel");
@@ -224,9 +346,40 @@ fn draw_ui_scenario_central_panel(
                 };
             }
         }
+        if scenario.is_read_only() {
+            ui.disable();
+        }
+        draw_ui_scenario_footprint(ui, scenario, footprint);
+        draw_ui_scenario_storage(ui, scenario);
+        let issues = if *scenario.get_status() == Status::Planning {
+            scenario.config_issues()
+        } else {
+            Vec::new()
+        };
         ui.columns(2, |columns| {
-            draw_ui_scenario_data(&mut columns[0], scenario);
-            draw_ui_scenario_algoriothm(&mut columns[1], scenario);
+            draw_ui_scenario_data(&mut columns[0], scenario, &issues);
+            draw_ui_scenario_algoriothm(&mut columns[1], scenario, &issues);
         });
     });
 }
+
+/// Lists the invalid and suspicious parameters below the given section of
+/// the config, errors in red and warnings in yellow.
+#[tracing::instrument(skip(ui, issues), level = "trace")]
+pub(super) fn draw_config_issues(ui: &mut egui::Ui, issues: &[ConfigIssue], section: &str) {
+    for issue in issues
+        .iter()
+        .filter(|issue| issue.path.starts_with(section))
+    {
+        let color = match issue.severity {
+            Severity::Error => egui::Color32::RED,
+            Severity::Warning => egui::Color32::YELLOW,
+        };
+        let path = issue
+            .path
+            .strip_prefix(section)
+            .unwrap_or(&issue.path)
+            .trim_start_matches('.');
+        ui.label(egui::RichText::new(format!("{path}: {}", issue.message)).color(color));
+    }
+}
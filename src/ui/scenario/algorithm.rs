@@ -1,19 +1,41 @@
+use std::path::PathBuf;
+
 use egui_extras::{Column, TableBuilder};
-use tracing::trace;
+use strum::IntoEnumIterator;
+use tracing::{error, trace};
 
 use super::{
-    common::draw_ui_scenario_common, FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH,
+    common::{draw_percentage_row, draw_ui_scenario_common},
+    draw_config_issues, FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH,
 };
 use crate::core::{
-    algorithm::refinement::Optimizer,
-    config::algorithm::{Algorithm, AlgorithmType},
+    algorithm::{
+        gpu::backend::is_backend_available,
+        refinement::{
+            loss::{self, LossTermKind},
+            Optimizer,
+        },
+    },
+    config::{
+        algorithm::{
+            Algorithm, AlgorithmPreset, AlgorithmType, FrozenParameters, GpuBackend, GpuPrecision,
+            Initialization, OptimizationRegion, RegularizationSelection,
+        },
+        model::PathologyRegion,
+        validation::ConfigIssue,
+    },
+    model::spatial::voxels::VoxelType,
     scenario::{Scenario, Status},
 };
 
 /// Draws the UI elements for the algorithm.
 #[allow(clippy::too_many_lines)]
-#[tracing::instrument(skip(parent), level = "trace")]
-pub fn draw_ui_scenario_algoriothm(parent: &mut egui::Ui, scenario: &mut Scenario) {
+#[tracing::instrument(skip(parent, issues), level = "trace")]
+pub fn draw_ui_scenario_algoriothm(
+    parent: &mut egui::Ui,
+    scenario: &mut Scenario,
+    issues: &[ConfigIssue],
+) {
     trace!("Running system to draw scenario algorithm UI.");
     if *scenario.get_status() != Status::Planning {
         parent.disable();
@@ -24,16 +46,230 @@ pub fn draw_ui_scenario_algoriothm(parent: &mut egui::Ui, scenario: &mut Scenari
         .show(parent, |ui| {
             ui.heading("Algorithm");
             ui.separator();
+            draw_config_issues(ui, issues, "algorithm");
+            draw_range_warnings(ui, algorithm);
             draw_algorithm_settings(ui, algorithm);
+            if algorithm.algorithm_type != AlgorithmType::None {
+                draw_preprocessing_settings(ui, algorithm);
+            }
             if algorithm.algorithm_type == AlgorithmType::ModelBased {
                 draw_optimizer_settings(ui, algorithm);
                 draw_regularization_settings(ui, algorithm);
                 draw_metrics_settings(ui, algorithm);
                 draw_ui_scenario_common(ui, &mut algorithm.model);
             }
+            if matches!(
+                algorithm.algorithm_type,
+                AlgorithmType::ModelBased | AlgorithmType::ModelBasedGPU
+            ) {
+                draw_optimization_region_settings(ui, algorithm);
+            }
+            if matches!(
+                algorithm.algorithm_type,
+                AlgorithmType::KalmanFilter
+                    | AlgorithmType::MinimumNorm
+                    | AlgorithmType::SLoreta
+                    | AlgorithmType::ELoreta
+            ) {
+                draw_ui_scenario_common(ui, &mut algorithm.model);
+            }
         });
 }
 
+/// Lists all parameters that lie outside of their recommended range.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_range_warnings(ui: &mut egui::Ui, algorithm: &Algorithm) {
+    for (range, value) in algorithm.out_of_range_parameters() {
+        ui.label(
+            egui::RichText::new(format!(
+                "{} is {value}, outside of the recommended range {} to {}.",
+                range.name, range.min, range.max
+            ))
+            .color(egui::Color32::YELLOW),
+        );
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_preprocessing_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
+    ui.label(egui::RichText::new("Preprocessing Settings").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                let preprocessing = &mut algorithm.preprocessing;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Band-pass");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut preprocessing.band_pass, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Wether or not to apply a zero-phase Butterworth band-pass to the measurements.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Low Cutoff");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut preprocessing.band_pass_low_hz, 0.01..=100.0).suffix(" Hz"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Lower edge of the band-pass. Default: 0.5 Hz.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("High Cutoff");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut preprocessing.band_pass_high_hz, 1.0..=1000.0).suffix(" Hz"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Upper edge of the band-pass. Default: 100.0 Hz.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Order");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut preprocessing.band_pass_order, 1..=8));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Order of the Butterworth filter at each edge. Default: 4.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Notch");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut preprocessing.notch, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Wether or not to apply a zero-phase notch filter to the measurements.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Notch Frequency");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut preprocessing.notch_frequency_hz, 45.0..=65.0).suffix(" Hz"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Frequency removed by the notch filter. Default: 50.0 Hz.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Quality Factor");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut preprocessing.notch_quality_factor, 1.0..=100.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Quality factor of the notch filter, higher values give a narrower notch. Default: 30.0.").truncate());
+                    });
+                });
+                let beat_detection = &mut preprocessing.beat_detection;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Beat Detection");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut beat_detection.enabled, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Wether or not to detect beats in the measurements, concatenated over the sensor array positions.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Trigger Channel");
+                    });
+                    row.col(|ui| {
+                        let mut use_channel = beat_detection.trigger_channel.is_some();
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut use_channel, "");
+                            let mut channel = beat_detection.trigger_channel.unwrap_or(0);
+                            ui.add_enabled(use_channel, egui::DragValue::new(&mut channel));
+                            beat_detection.trigger_channel = use_channel.then_some(channel);
+                        });
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Channel whose peaks trigger the beats. Uses the RMS over all channels if disabled. Default: RMS.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Trigger Threshold");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut beat_detection.threshold, 0.05..=1.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Minimum height of a trigger peak relative to the largest peak. Default: 0.6.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Refractory Period");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut beat_detection.refractory_ms, 10.0..=2000.0).suffix(" ms"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Minimum time between two triggers. Default: 250.0 ms.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Pre-Trigger Time");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut beat_detection.pre_trigger_ms, 0.0..=1000.0).suffix(" ms"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Time between the start of a beat and its trigger. Default: 100.0 ms.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Average Beats");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut beat_detection.average, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Wether or not to replace every beat with the average of all detected beats before the estimation.").truncate());
+                    });
+                });
+            });
+    });
+}
+
 #[tracing::instrument(skip_all, level = "trace")]
 fn draw_regularization_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
     ui.label(egui::RichText::new("Regulariztion Settings").underline());
@@ -82,21 +318,278 @@ fn draw_regularization_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
                             );
                         });
                     });
-                    // Regularization strength
+                    // Loss terms
+                    for kind in LossTermKind::iter() {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label(kind.to_string());
+                            });
+                            row.col(|ui| {
+                                let mut weight = loss::weight(algorithm, kind);
+                                if ui
+                                    .add(egui::DragValue::new(&mut weight).speed(0.01))
+                                    .changed()
+                                {
+                                    loss::set_weight(algorithm, kind, weight);
+                                }
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The weighting of the loss term, zero disables it.",
+                                    )
+                                    .truncate(),
+                                );
+                            });
+                        });
+                    }
+                    // Physiological bounds
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Regularization\nstrength");
+                            ui.label("Minimum velocity\n[m/s]");
                         });
                         row.col(|ui| {
                             ui.add(egui::Slider::new(
-                                &mut algorithm.maximum_regularization_strength,
-                                0.0..=1000.0,
+                                &mut algorithm.physiological_bounds.min_velocity_m_per_s,
+                                0.0..=10.0,
                             ));
                         });
                         row.col(|ui| {
                             ui.add(
-                                egui::Label::new("The weighting of the regularization term.")
+                                egui::Label::new(
+                                    "Lower bound of the conduction velocity, which bounds the delays from above. Zero disables it.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Maximum velocity\n[m/s]");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(
+                                &mut algorithm.physiological_bounds.max_velocity_m_per_s,
+                                0.0..=10.0,
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Upper bound of the conduction velocity, which bounds the delays from below. Zero disables it.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Maximum gain");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(
+                                &mut algorithm.physiological_bounds.max_gain,
+                                0.0..=10.0,
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Cap on the magnitude of the gains. Zero disables it.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+            });
+    });
+}
+
+/// Draws the selection of the voxels whose parameters are optimized and
+/// the frozen parameters per voxel type.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_optimization_region_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
+    ui.label(egui::RichText::new("Optimization Region").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .body(|mut body| {
+                let region = &mut algorithm.optimization_region;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Region");
+                    });
+                    row.col(|ui| {
+                        let selected = match region {
+                            OptimizationRegion::All => "All voxels",
+                            OptimizationRegion::Cuboid(_) => "Cuboid",
+                            OptimizationRegion::NiftiLabel { .. } => "NIfTI label",
+                        };
+                        egui::ComboBox::new("cb_optimization_region", "")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui
+                                    .selectable_label(
+                                        matches!(region, OptimizationRegion::All),
+                                        "All voxels",
+                                    )
+                                    .clicked()
+                                {
+                                    *region = OptimizationRegion::All;
+                                }
+                                if ui
+                                    .selectable_label(
+                                        matches!(region, OptimizationRegion::Cuboid(_)),
+                                        "Cuboid",
+                                    )
+                                    .clicked()
+                                    && !matches!(region, OptimizationRegion::Cuboid(_))
+                                {
+                                    *region =
+                                        OptimizationRegion::Cuboid(PathologyRegion::default());
+                                }
+                                if ui
+                                    .selectable_label(
+                                        matches!(region, OptimizationRegion::NiftiLabel { .. }),
+                                        "NIfTI label",
+                                    )
+                                    .clicked()
+                                    && !matches!(region, OptimizationRegion::NiftiLabel { .. })
+                                {
+                                    *region = OptimizationRegion::NiftiLabel {
+                                        path: PathBuf::from("assets/segmentation.nii"),
+                                        label: 1,
+                                    };
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The voxels whose gains and delays are optimized. \
+                                All other voxels keep their model values.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                match region {
+                    OptimizationRegion::All => {}
+                    OptimizationRegion::Cuboid(cuboid) => {
+                        let extents = [
+                            (
+                                "X Start",
+                                &mut cuboid.x_start_percentage,
+                                "The start of the region in x-direction in percent.",
+                            ),
+                            (
+                                "X Stop",
+                                &mut cuboid.x_stop_percentage,
+                                "The end of the region in x-direction in percent.",
+                            ),
+                            (
+                                "Y Start",
+                                &mut cuboid.y_start_percentage,
+                                "The start of the region in y-direction in percent.",
+                            ),
+                            (
+                                "Y Stop",
+                                &mut cuboid.y_stop_percentage,
+                                "The end of the region in y-direction in percent.",
+                            ),
+                            (
+                                "Z Start",
+                                &mut cuboid.z_start_percentage,
+                                "The start of the region in z-direction in percent.",
+                            ),
+                            (
+                                "Z Stop",
+                                &mut cuboid.z_stop_percentage,
+                                "The end of the region in z-direction in percent.",
+                            ),
+                        ];
+                        for (name, value, description) in extents {
+                            draw_percentage_row(&mut body, name, value, description);
+                        }
+                    }
+                    OptimizationRegion::NiftiLabel { path, label } => {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Path");
+                            });
+                            row.col(|ui| {
+                                let mut path_string = path
+                                    .to_str()
+                                    .unwrap_or_else(|| {
+                                        error!("Region path contains invalid UTF-8: {path:?}");
+                                        "<invalid path>"
+                                    })
+                                    .to_string();
+                                ui.add(egui::TextEdit::singleline(&mut path_string));
+                                *path = PathBuf::from(path_string);
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The path to a NIfTI label volume on the grid \
+                                        of the MRI scan.",
+                                    )
                                     .truncate(),
+                                );
+                            });
+                        });
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Label");
+                            });
+                            row.col(|ui| {
+                                ui.add(egui::DragValue::new(label));
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new("The label of the optimized voxels.")
+                                        .truncate(),
+                                );
+                            });
+                        });
+                    }
+                }
+                for voxel_type in VoxelType::iter().filter(|voxel_type| voxel_type.is_connectable())
+                {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label(format!("Freeze {voxel_type:?}"));
+                        });
+                        row.col(|ui| {
+                            let mut frozen = algorithm
+                                .frozen_voxel_types
+                                .get(&voxel_type)
+                                .copied()
+                                .unwrap_or_default();
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut frozen.gains, "Gains");
+                                ui.checkbox(&mut frozen.delays, "Delays");
+                            });
+                            if frozen == FrozenParameters::default() {
+                                algorithm.frozen_voxel_types.remove(&voxel_type);
+                            } else {
+                                algorithm.frozen_voxel_types.insert(voxel_type, frozen);
+                            }
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The parameters that keep their model values \
+                                    in voxels of this type.",
+                                )
+                                .truncate(),
                             );
                         });
                     });
@@ -130,6 +623,32 @@ fn draw_algorithm_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
                 });
             })
             .body(|mut body| {
+                // preset
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Preset");
+                    });
+                    row.col(|ui| {
+                        egui::ComboBox::new("cb_algorithm_preset", "")
+                            .selected_text("Select")
+                            .show_ui(ui, |ui| {
+                                for preset in AlgorithmPreset::ALL {
+                                    if ui.selectable_label(false, preset.name()).clicked() {
+                                        preset.apply(algorithm);
+                                    }
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Sets the optimization parameters to recommended \
+                                values. The model settings are kept.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
                 // algorithm type
                 let algorithm_type = &mut algorithm.algorithm_type;
                 body.row(ROW_HEIGHT, |mut row| {
@@ -145,147 +664,578 @@ fn draw_algorithm_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
                                     AlgorithmType::ModelBased,
                                     "Model Based",
                                 );
+                                let gpu_available = is_backend_available(GpuBackend::OpenCl)
+                                    || is_backend_available(GpuBackend::Wgpu);
+                                ui.add_enabled_ui(gpu_available, |ui| {
+                                    ui.selectable_value(
+                                        algorithm_type,
+                                        AlgorithmType::ModelBasedGPU,
+                                        "Model Based on GPU",
+                                    )
+                                })
+                                .inner
+                                .on_disabled_hover_text(
+                                    "No GPU was found. Install an OpenCL runtime \
+                                    or a Vulkan, Metal or DX12 driver for your \
+                                    GPU to enable this option.",
+                                );
                                 ui.selectable_value(
                                     algorithm_type,
-                                    AlgorithmType::ModelBasedGPU,
-                                    "Model Based on GPU",
+                                    AlgorithmType::PseudoInverse,
+                                    "Pseudo Inverse",
+                                );
+                                ui.selectable_value(
+                                    algorithm_type,
+                                    AlgorithmType::KalmanFilter,
+                                    "Kalman Filter",
+                                );
+                                ui.selectable_value(
+                                    algorithm_type,
+                                    AlgorithmType::MinimumNorm,
+                                    "Minimum Norm",
+                                );
+                                ui.selectable_value(
+                                    algorithm_type,
+                                    AlgorithmType::SLoreta,
+                                    "sLORETA",
+                                );
+                                ui.selectable_value(
+                                    algorithm_type,
+                                    AlgorithmType::ELoreta,
+                                    "eLORETA",
+                                );
+                                ui.selectable_value(
+                                    algorithm_type,
+                                    AlgorithmType::None,
+                                    "None (simulation only)",
+                                );
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The algorhim used for estimating the \
+                                     current densities. None only simulates \
+                                     the data.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if algorithm_type == &AlgorithmType::ModelBased {
+                    // Epochs
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Epochs");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(&mut algorithm.epochs, 1..=1_000_000));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new("The number of epochs to run the algorithm for.")
+                                    .truncate(),
+                            );
+                        });
+                    });
+                    // Batch size
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Batch size");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(&mut algorithm.batch_size, 0..=50000));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The batch size to use for the algorithm.\
+                                After how many samples to update the weights.\
+                                Default: 0 - one update per Epoch.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Number of threads
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Threads");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(&mut algorithm.number_of_threads, 0..=256));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The number of threads used to calculate the derivatives.\
+                                Default: 0 - one thread per core.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Freeze gains
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Freeze gains");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.freeze_gains, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Wether or not to freeze the gains\
+                                    preventing them from being changed.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Freeze delays
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Freeze delays");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.freeze_delays, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Wether or not to freeze the delays\
+                                    preventing them from being changed.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Freeze refractory times
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Freeze refractory");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.freeze_refractory, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Whether or not to freeze the refractory times \
+                                    of the re-entrant connections.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                if algorithm_type == &AlgorithmType::ModelBasedGPU {
+                    // Epochs
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Epochs");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(&mut algorithm.epochs, 1..=1_000_000));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new("The number of epochs to run the algorithm for.")
+                                    .truncate(),
+                            );
+                        });
+                    });
+                    // Freeze gains
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Freeze gains");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.freeze_gains, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Wether or not to freeze the gains\
+                                    preventing them from being changed.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Freeze delays
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Freeze delays");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.freeze_delays, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Wether or not to freeze the delays\
+                                    preventing them from being changed.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // GPU backend
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("GPU backend");
+                        });
+                        row.col(|ui| {
+                            egui::ComboBox::new("cb_gpu_backend", "")
+                                .selected_text(format!("{:?}", algorithm.gpu_backend))
+                                .show_ui(ui, |ui| {
+                                    for (backend, label) in
+                                        [(GpuBackend::OpenCl, "OpenCL"), (GpuBackend::Wgpu, "wgpu")]
+                                    {
+                                        ui.add_enabled_ui(is_backend_available(backend), |ui| {
+                                            ui.selectable_value(
+                                                &mut algorithm.gpu_backend,
+                                                backend,
+                                                label,
+                                            )
+                                        });
+                                    }
+                                });
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The compute API the GPU algorithm runs on. \
+                                    wgpu uses Vulkan, Metal or DX12.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // GPU precision
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("GPU precision");
+                        });
+                        row.col(|ui| {
+                            egui::ComboBox::new("cb_gpu_precision", "")
+                                .selected_text(format!("{:?}", algorithm.gpu_precision))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut algorithm.gpu_precision,
+                                        GpuPrecision::Single,
+                                        "Single",
+                                    );
+                                    ui.selectable_value(
+                                        &mut algorithm.gpu_precision,
+                                        GpuPrecision::Half,
+                                        "Half",
+                                    );
+                                });
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The precision the system states and gains are \
+                                    stored in. Half precision halves their memory.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                if algorithm_type == &AlgorithmType::ModelBased
+                    || algorithm_type == &AlgorithmType::ModelBasedGPU
+                {
+                    // Keep step metrics
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Keep step metrics");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut algorithm.keep_step_metrics, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Wether or not to store the losses of every step \
+                                    of every epoch. Otherwise only the per-batch \
+                                    losses are stored.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Parameter history
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Record parameters");
+                        });
+                        row.col(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.checkbox(
+                                    &mut algorithm.parameter_recording.statistics,
+                                    "Statistics",
                                 );
-                                ui.selectable_value(
-                                    algorithm_type,
-                                    AlgorithmType::PseudoInverse,
-                                    "Pseudo Inverse",
+                                // the text is kept while editing, otherwise a
+                                // trailing comma would be dropped every frame
+                                let id = ui.id().with("recorded_voxels");
+                                let mut voxels = ui
+                                    .data_mut(|data| data.get_temp::<String>(id))
+                                    .unwrap_or_else(|| {
+                                        algorithm
+                                            .parameter_recording
+                                            .voxels
+                                            .iter()
+                                            .map(ToString::to_string)
+                                            .collect::<Vec<_>>()
+                                            .join(", ")
+                                    });
+                                let response = ui.add(
+                                    egui::TextEdit::singleline(&mut voxels)
+                                        .hint_text("Voxels")
+                                        .desired_width(80.0),
                                 );
+                                if response.changed() {
+                                    algorithm.parameter_recording.voxels = voxels
+                                        .split(',')
+                                        .filter_map(|voxel| voxel.trim().parse().ok())
+                                        .collect();
+                                    ui.data_mut(|data| data.insert_temp(id, voxels));
+                                } else if response.lost_focus() {
+                                    ui.data_mut(|data| data.remove::<String>(id));
+                                }
                             });
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Record the mean and standard deviation of the gains \
+                                    and delays and the delays of the given comma \
+                                    separated voxels after every epoch. Reading them \
+                                    back slows down the GPU algorithm.",
+                                )
+                                .truncate(),
+                            );
+                        });
                     });
-                    row.col(|ui| {
-                        ui.add(
-                            egui::Label::new(
-                                "The algorhim used for estimating the \
-                                     current densities.",
-                            )
-                            .truncate(),
-                        );
-                    });
-                });
-                if algorithm_type == &AlgorithmType::ModelBased {
-                    // Epochs
+                    // Parameter confidence
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Epochs");
+                            ui.label("Parameter confidence");
                         });
                         row.col(|ui| {
-                            ui.add(egui::Slider::new(&mut algorithm.epochs, 1..=1_000_000));
+                            ui.checkbox(&mut algorithm.parameter_confidence, "");
                         });
                         row.col(|ui| {
                             ui.add(
-                                egui::Label::new("The number of epochs to run the algorithm for.")
-                                    .truncate(),
+                                egui::Label::new(
+                                    "Whether or not to estimate the standard deviations \
+                                    of the gains and delays after convergence from the \
+                                    Gauss-Newton approximation of the Hessian.",
+                                )
+                                .truncate(),
                             );
                         });
                     });
-                    // Batch size
+                }
+                if algorithm_type == &AlgorithmType::ModelBased
+                    || algorithm_type == &AlgorithmType::ModelBasedGPU
+                    || algorithm_type == &AlgorithmType::KalmanFilter
+                {
+                    // Mask bad channels
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Batch size");
+                            ui.label("Mask bad channels");
                         });
                         row.col(|ui| {
-                            ui.add(egui::Slider::new(&mut algorithm.batch_size, 0..=50000));
+                            ui.checkbox(&mut algorithm.mask_bad_channels, "");
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "The batch size to use for the algorithm.\
-                                After how many samples to update the weights.\
-                                Default: 0 - one update per Epoch.",
+                                    "Wether or not to exclude the dropped and saturated \
+                                    channels of the simulation from the residuals.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
-                    // Freeze gains
+                }
+                if algorithm_type == &AlgorithmType::KalmanFilter {
+                    // Process covariance
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Freeze gains");
+                            ui.label("Process covariance");
                         });
                         row.col(|ui| {
-                            ui.checkbox(&mut algorithm.freeze_gains, "");
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut algorithm.kalman_process_covariance,
+                                    1e-10..=1e2,
+                                )
+                                .logarithmic(true),
+                            );
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "Wether or not to freeze the gains\
-                                    preventing them from being changed.",
+                                    "The variance of the process noise of the \
+                                    Kalman filter. Default: 1e-2.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
-                    // Freeze delays
+                    // Measurement covariance
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Freeze delays");
+                            ui.label("Measurement covariance");
                         });
                         row.col(|ui| {
-                            ui.checkbox(&mut algorithm.freeze_delays, "");
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut algorithm.kalman_measurement_covariance,
+                                    1e-10..=1e2,
+                                )
+                                .logarithmic(true),
+                            );
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "Wether or not to freeze the delays\
-                                    preventing them from being changed.",
+                                    "The variance of the measurement noise of the \
+                                    Kalman filter. Default: 1e-3.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
                 }
-                if algorithm_type == &AlgorithmType::ModelBasedGPU {
-                    // Epochs
+                if algorithm_type == &AlgorithmType::PseudoInverse {
+                    // Regularization path
+                    let path = &mut algorithm.regularization_path;
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Epochs");
+                            ui.label("Number of lambdas");
                         });
                         row.col(|ui| {
-                            ui.add(egui::Slider::new(&mut algorithm.epochs, 1..=1_000_000));
+                            ui.add(egui::Slider::new(&mut path.number_of_lambdas, 0..=100));
                         });
                         row.col(|ui| {
                             ui.add(
-                                egui::Label::new("The number of epochs to run the algorithm for.")
-                                    .truncate(),
+                                egui::Label::new(
+                                    "The number of Tikhonov regularization parameters \
+                                    evaluated in addition to the regular solution. \
+                                    Zero disables the sweep.",
+                                )
+                                .truncate(),
                             );
                         });
                     });
-                    // Freeze gains
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Freeze gains");
+                            ui.label("Minimum lambda");
                         });
                         row.col(|ui| {
-                            ui.checkbox(&mut algorithm.freeze_gains, "");
+                            ui.add(
+                                egui::Slider::new(&mut path.min_lambda, 1e-12..=1e4)
+                                    .logarithmic(true),
+                            );
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "Wether or not to freeze the gains\
-                                    preventing them from being changed.",
+                                    "The smallest regularization parameter of the sweep. \
+                                    Default: 1e-6.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
-                    // Freeze delays
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Freeze delays");
+                            ui.label("Maximum lambda");
                         });
                         row.col(|ui| {
-                            ui.checkbox(&mut algorithm.freeze_delays, "");
+                            ui.add(
+                                egui::Slider::new(&mut path.max_lambda, 1e-12..=1e4)
+                                    .logarithmic(true),
+                            );
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "Wether or not to freeze the delays\
-                                    preventing them from being changed.",
+                                    "The largest regularization parameter of the sweep. \
+                                    Default: 1e2.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                if matches!(
+                    algorithm_type,
+                    AlgorithmType::MinimumNorm | AlgorithmType::SLoreta | AlgorithmType::ELoreta
+                ) {
+                    // Regularization selection
+                    let selection = &mut algorithm.regularization_selection;
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Regularization selection");
+                        });
+                        row.col(|ui| {
+                            egui::ComboBox::new("cb_regularization_selection", "")
+                                .selected_text(format!("{selection:?}"))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        selection,
+                                        RegularizationSelection::GeneralizedCrossValidation,
+                                        "GCV",
+                                    );
+                                    ui.selectable_value(
+                                        selection,
+                                        RegularizationSelection::LCurve,
+                                        "L-Curve",
+                                    );
+                                    ui.selectable_value(
+                                        selection,
+                                        RegularizationSelection::Fixed,
+                                        "Fixed",
+                                    );
+                                });
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "How the regularization strength is chosen. \
+                                    GCV and L-Curve select it per beat.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Regularization strength
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Regularization strength");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut algorithm.regularization_strength,
+                                    1e-6..=1e1,
+                                )
+                                .logarithmic(true),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The fixed regularization strength relative to the \
+                                    mean eigenvalue of the gram matrix. Default: 1e-2.",
                                 )
                                 .truncate(),
                             );
@@ -296,6 +1246,7 @@ fn draw_algorithm_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
     });
 }
 
+#[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
 fn draw_metrics_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
     ui.label(egui::RichText::new("Metrics Settings").underline());
@@ -344,6 +1295,212 @@ fn draw_metrics_settings(ui: &mut egui::Ui, algorithm: &mut Algorithm) {
                             );
                         });
                     });
+                    // Autosave interval
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Autosave interval");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut algorithm.autosave_interval, 0..=10000)
+                                    .suffix(" Epochs"),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "How often to autosave the results, so that an \
+                                interrupted scenario can be resumed. \
+                                Only used by the model-based CPU algorithm. \
+                                Default: 100 - 0 disables autosave.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                // Measurement diagnostics
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Measurement diagnostics");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut algorithm.measurement_diagnostics, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Whether or not to calculate the singular values and the \
+                                condition number of the measurement matrix before running, \
+                                to tell an ill-posed sensor geometry from an algorithm issue.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                // Ensemble
+                let ensemble = &mut algorithm.ensemble;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Ensemble members");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut ensemble.number_of_members, 0..=50));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The number of additional estimations used for the \
+                                standard deviation maps. Zero disables the ensemble.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if ensemble.number_of_members > 0 {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Initial gain std");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut ensemble.initial_gain_std, 0.0..=1.0)
+                                    .fixed_decimals(2),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The relative standard deviation of the initial \
+                                    gains of each member. Default: 0.1.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Measurement noise std");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut ensemble.measurement_noise_std, 0.0..=1e-1)
+                                    .logarithmic(true),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The standard deviation of the noise added to the \
+                                    measurements of each member. Default: 1e-3.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Ensemble seed");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::DragValue::new(&mut ensemble.seed));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new("The seed of the perturbations of the members.")
+                                    .truncate(),
+                            );
+                        });
+                    });
+                }
+                // Initialization
+                let initialization = &mut algorithm.initialization;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Initialize from scenario");
+                    });
+                    row.col(|ui| {
+                        let mut from_scenario =
+                            matches!(initialization, Initialization::FromScenario(_));
+                        if ui.checkbox(&mut from_scenario, "").changed() {
+                            *initialization = if from_scenario {
+                                Initialization::FromScenario(String::new())
+                            } else {
+                                Initialization::Model
+                            };
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Start from the estimated gains and delays of a finished \
+                                scenario with the same voxel grid instead of the model \
+                                values.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if let Initialization::FromScenario(id) = initialization {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Scenario ID");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::TextEdit::singleline(id));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The ID of the scenario to initialize from, it has \
+                                    to be in the same results directory.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                // Coarse to fine
+                let coarse_to_fine = &mut algorithm.coarse_to_fine;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Coarse epochs");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::DragValue::new(&mut coarse_to_fine.coarse_epochs));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The number of epochs on a coarsened voxel grid that \
+                                initialize the gains and delays. Zero disables the \
+                                coarse stage.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if coarse_to_fine.coarse_epochs > 0 {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Coarsening factor");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut coarse_to_fine.coarsening_factor, 1.5..=4.0)
+                                    .fixed_decimals(1),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The voxel size of the coarse grid relative to the \
+                                    model voxels. Default: 2.0.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
                 }
             });
     });
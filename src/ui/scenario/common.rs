@@ -4,7 +4,10 @@ use egui_extras::{Column, TableBuilder};
 use tracing::{error, trace};
 
 use super::{FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH};
-use crate::core::config::model::{ControlFunction, Handcrafted, Model, Mri};
+use crate::core::config::model::{
+    ControlFunction, FiberOrientation, Handcrafted, Model, Mri, PacingSite, PathologyRegion,
+    TorsoModel,
+};
 
 /// Draws ui for settings common to data generation and optimization.
 #[allow(clippy::too_many_lines, clippy::module_name_repetitions)]
@@ -14,6 +17,7 @@ pub fn draw_ui_scenario_common(ui: &mut egui::Ui, model: &mut Model) {
     draw_measurement_settings(ui, model);
     draw_functional_settings(ui, model);
     draw_velocity_settings(ui, model);
+    draw_fiber_settings(ui, model);
     if let Some(handcrafted) = model.handcrafted.as_mut() {
         draw_handcrafted_settings(ui, handcrafted, model.common.pathological);
     }
@@ -22,6 +26,7 @@ pub fn draw_ui_scenario_common(ui: &mut egui::Ui, model: &mut Model) {
     }
 }
 
+#[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
 fn draw_measurement_settings(ui: &mut egui::Ui, model: &mut Model) {
     ui.label(egui::RichText::new("Measurement Settings").underline());
@@ -96,6 +101,63 @@ fn draw_measurement_settings(ui: &mut egui::Ui, model: &mut Model) {
                         );
                     });
                 });
+                // Torso model
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Torso Model");
+                    });
+                    row.col(|ui| {
+                        let torso_model = &mut model.common.torso_model;
+                        let spherical = matches!(torso_model, TorsoModel::Spherical { .. });
+                        egui::ComboBox::new("cb_torso_model", "")
+                            .selected_text(if spherical { "Spherical" } else { "Unbounded" })
+                            .show_ui(ui, |ui| {
+                                if ui.selectable_label(!spherical, "Unbounded").clicked() {
+                                    *torso_model = TorsoModel::Unbounded;
+                                }
+                                if ui.selectable_label(spherical, "Spherical").clicked()
+                                    && !spherical
+                                {
+                                    *torso_model = TorsoModel::Spherical {
+                                        center_mm: model.common.heart_offset_mm,
+                                    };
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The volume conductor used for the measurement \
+                                matrix. Spherical includes the volume currents \
+                                of a spherical torso.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if let TorsoModel::Spherical { center_mm } = &mut model.common.torso_model {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Torso Center");
+                        });
+                        row.col(|ui| {
+                            ui.horizontal(|ui| {
+                                for component in center_mm.iter_mut() {
+                                    ui.add(egui::DragValue::new(component).suffix(" mm"));
+                                }
+                            });
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The center of the spherical torso in mm. \
+                                    All sensors have to lie outside of the torso.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
             });
     });
 }
@@ -131,8 +193,14 @@ fn draw_functional_settings(ui: &mut egui::Ui, model: &mut Model) {
                         ui.label("Control function");
                     });
                     row.col(|ui| {
+                        let selected = match control_function {
+                            ControlFunction::Ohara => "Ohara",
+                            ControlFunction::Triangle => "Triangle",
+                            ControlFunction::Ramp => "Ramp",
+                            ControlFunction::FromFile { .. } => "From file",
+                        };
                         egui::ComboBox::new("cb_control_function", "")
-                            .selected_text(format!("{control_function:?}"))
+                            .selected_text(selected)
                             .show_ui(ui, |ui| {
                                 ui.selectable_value(
                                     control_function,
@@ -144,6 +212,16 @@ fn draw_functional_settings(ui: &mut egui::Ui, model: &mut Model) {
                                     ControlFunction::Ohara,
                                     "Ohara",
                                 );
+                                if ui
+                                    .selectable_label(selected == "From file", "From file")
+                                    .clicked()
+                                    && selected != "From file"
+                                {
+                                    *control_function = ControlFunction::FromFile {
+                                        path: PathBuf::from("assets/control_function.npy"),
+                                        sample_rate_hz: 2000.0,
+                                    };
+                                }
                             });
                     });
                     row.col(|ui| {
@@ -156,6 +234,142 @@ fn draw_functional_settings(ui: &mut egui::Ui, model: &mut Model) {
                         );
                     });
                 });
+                if let ControlFunction::FromFile {
+                    path,
+                    sample_rate_hz,
+                } = &mut model.common.control_function
+                {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Path");
+                        });
+                        row.col(|ui| {
+                            let mut path_string = path
+                                .to_str()
+                                .unwrap_or_else(|| {
+                                    error!(
+                                        "Control function path contains invalid UTF-8: {path:?}"
+                                    );
+                                    "<invalid path>"
+                                })
+                                .to_string();
+                            ui.add(egui::TextEdit::singleline(&mut path_string));
+                            *path = PathBuf::from(path_string);
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The path to a .npy or .csv file with one beat of \
+                                    the waveform at 60 bpm.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("File sample rate");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::DragValue::new(sample_rate_hz)
+                                    .range(1.0..=100_000.0)
+                                    .suffix(" Hz"),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The sample rate of the waveform in the file, it is \
+                                    resampled to the sample rate of the scenario.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+                // Heart rate
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Heart rate");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Slider::new(&mut model.common.heart_rate_bpm, 30.0..=200.0)
+                                .suffix(" bpm"),
+                        );
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Rate at which the control function repeats. \
+                                    Default: 60 bpm.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                // Action potential duration
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("APD factor");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(
+                            &mut model.common.action_potential_duration_factor,
+                            0.25..=2.0,
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Duration of the action potential relative to the \
+                                    reference control function. Default: 1.0.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                // Rate adaptation
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("APD rate adaptation");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut model.common.action_potential_rate_adaptation, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Whether or not to shorten the action potential at \
+                                    higher heart rates following Bazett's formula.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                // Neighborhood radius
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Neighborhood radius");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(
+                            &mut model.common.neighborhood_radius,
+                            1..=3,
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "How many voxels away along each axis neighbors are \
+                                    connected. One gives the 26-voxel neighborhood, larger \
+                                    values let fast conduction bypass voxels.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
                 // Pathological
                 body.row(ROW_HEIGHT, |mut row| {
                     row.col(|ui| {
@@ -195,7 +409,103 @@ fn draw_functional_settings(ui: &mut egui::Ui, model: &mut Model) {
                             );
                         });
                     });
+                    // Current Factor in Border Zone
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Current Factor \nin border zone");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(
+                                &mut model.common.current_factor_in_border_zone,
+                                0.0..=1.0,
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "A factor describing how much to reduce the \
+                                    current densities in border zone voxels \
+                                    surrounding the pathological tissue.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    // Re-entry
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Re-entry");
+                        });
+                        row.col(|ui| {
+                            ui.checkbox(&mut model.common.reentry, "");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Whether or not pathological and border zone \
+                                    voxels can be activated again, allowing \
+                                    re-entrant activation.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                    if model.common.reentry {
+                        // Refractory period
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Refractory Period");
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Slider::new(
+                                        &mut model.common.refractory_period_ms,
+                                        10.0..=500.0,
+                                    )
+                                    .suffix(" ms"),
+                                );
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The time after which a voxel can be \
+                                        activated again.",
+                                    )
+                                    .truncate(),
+                                );
+                            });
+                        });
+                    }
+                }
+                // Pacing sites
+                let mut removed = None;
+                for (index, site) in model.common.pacing_sites.iter_mut().enumerate() {
+                    if draw_pacing_site_rows(&mut body, index, site) {
+                        removed = Some(index);
+                    }
+                }
+                if let Some(index) = removed {
+                    model.common.pacing_sites.remove(index);
                 }
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Pacing Sites");
+                    });
+                    row.col(|ui| {
+                        if ui.button("Add Site").clicked() {
+                            model.common.pacing_sites.push(PacingSite::default());
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Adds a site stimulated in addition to the \
+                                    sinoatrial node, e.g. an ectopic focus.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
             });
     });
 }
@@ -382,6 +692,33 @@ fn draw_velocity_settings(ui: &mut egui::Ui, model: &mut Model) {
                             );
                         });
                     });
+                    // Border Zone
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Border Zone");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Slider::new(
+                                    &mut model.common.propagation_velocities.border_zone,
+                                    0.01..=10.0,
+                                )
+                                .suffix(" m/s"),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Desired propagation velocity in the \
+                                    border zone around pathological tissue \
+                                    in m/s. Note that the maximum propagation \
+                                    velocity is limited by the voxel size \
+                                    and sample rate.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
                 }
             });
     });
@@ -389,6 +726,198 @@ fn draw_velocity_settings(ui: &mut egui::Ui, model: &mut Model) {
 
 #[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
+fn draw_fiber_settings(ui: &mut egui::Ui, model: &mut Model) {
+    ui.label(egui::RichText::new("Fiber Settings").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                // Orientation
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Orientation");
+                    });
+                    row.col(|ui| {
+                        let orientation = &mut model.common.fiber_orientation;
+                        let selected = match orientation {
+                            None => "Isotropic",
+                            Some(FiberOrientation::Uniform { .. }) => "Uniform",
+                            Some(FiberOrientation::Helical { .. }) => "Helical",
+                            Some(FiberOrientation::Nifti { .. }) => "Nifti",
+                        };
+                        egui::ComboBox::new("cb_fiber_orientation", "")
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui
+                                    .selectable_label(selected == "Isotropic", "Isotropic")
+                                    .clicked()
+                                {
+                                    *orientation = None;
+                                }
+                                if ui
+                                    .selectable_label(selected == "Uniform", "Uniform")
+                                    .clicked()
+                                    && selected != "Uniform"
+                                {
+                                    *orientation = Some(FiberOrientation::Uniform {
+                                        direction: [1.0, 0.0, 0.0],
+                                    });
+                                }
+                                if ui
+                                    .selectable_label(selected == "Helical", "Helical")
+                                    .clicked()
+                                    && selected != "Helical"
+                                {
+                                    *orientation = Some(FiberOrientation::default());
+                                }
+                                if ui.selectable_label(selected == "Nifti", "Nifti").clicked()
+                                    && selected != "Nifti"
+                                {
+                                    *orientation = Some(FiberOrientation::Nifti {
+                                        path: PathBuf::from("assets/fibers.nii"),
+                                    });
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Where the fiber direction of each voxel is taken \
+                                    from. Isotropic ignores fibers.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                match model.common.fiber_orientation.as_mut() {
+                    None => {}
+                    Some(FiberOrientation::Uniform { direction }) => {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Direction");
+                            });
+                            row.col(|ui| {
+                                ui.horizontal(|ui| {
+                                    for component in direction.iter_mut() {
+                                        ui.add(egui::DragValue::new(component).speed(0.01));
+                                    }
+                                });
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The fiber direction in every voxel. \
+                                        It is normalized to unit length.",
+                                    )
+                                    .truncate(),
+                                );
+                            });
+                        });
+                    }
+                    Some(FiberOrientation::Helical {
+                        endocardial_angle_deg,
+                        epicardial_angle_deg,
+                    }) => {
+                        let angles = [
+                            (
+                                "Endocardial Angle",
+                                endocardial_angle_deg,
+                                "The fiber angle to the x-axis in the lowest z-layer.",
+                            ),
+                            (
+                                "Epicardial Angle",
+                                epicardial_angle_deg,
+                                "The fiber angle to the x-axis in the highest z-layer.",
+                            ),
+                        ];
+                        for (name, angle, description) in angles {
+                            body.row(ROW_HEIGHT, |mut row| {
+                                row.col(|ui| {
+                                    ui.label(name);
+                                });
+                                row.col(|ui| {
+                                    ui.add(egui::Slider::new(angle, -90.0..=90.0).suffix(" °"));
+                                });
+                                row.col(|ui| {
+                                    ui.add(egui::Label::new(description).truncate());
+                                });
+                            });
+                        }
+                    }
+                    Some(FiberOrientation::Nifti { path }) => {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Path");
+                            });
+                            row.col(|ui| {
+                                let mut path_string = path
+                                    .to_str()
+                                    .unwrap_or_else(|| {
+                                        error!("Fiber path contains invalid UTF-8: {path:?}");
+                                        "<invalid path>"
+                                    })
+                                    .to_string();
+                                ui.add(egui::TextEdit::singleline(&mut path_string));
+                                *path = PathBuf::from(path_string);
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The path to a .nii file with a fiber vector \
+                                        per voxel on the grid of the MRI segmentation.",
+                                    )
+                                    .truncate(),
+                                );
+                            });
+                        });
+                    }
+                }
+                // Transverse velocity ratio
+                if model.common.fiber_orientation.is_some() {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Transverse Ratio");
+                        });
+                        row.col(|ui| {
+                            ui.add(egui::Slider::new(
+                                &mut model.common.transverse_velocity_ratio,
+                                0.01..=1.0,
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "The propagation velocity across the fibers \
+                                    relative to the velocity along them.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    });
+                }
+            });
+    });
+}
+
+#[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
+#[tracing::instrument(skip_all, level = "trace")]
 fn draw_handcrafted_settings(ui: &mut egui::Ui, handcrafted: &mut Handcrafted, patholoical: bool) {
     ui.label(egui::RichText::new("Handcrafted Model Settings").underline());
     ui.group(|ui| {
@@ -454,6 +983,18 @@ fn draw_handcrafted_settings(ui: &mut egui::Ui, handcrafted: &mut Handcrafted, p
                         );
                     });
                 });
+                draw_percentage_row(
+                    &mut body,
+                    "Z Start SA",
+                    &mut handcrafted.sa_z_start_percentage,
+                    "The start of the sinoatrial node in z-direction in percent.",
+                );
+                draw_percentage_row(
+                    &mut body,
+                    "Z Stop SA",
+                    &mut handcrafted.sa_z_stop_percentage,
+                    "The end of the sinoatrial node in z-direction in percent.",
+                );
                 // include atrium
                 body.row(ROW_HEIGHT, |mut row| {
                     row.col(|ui| {
@@ -526,6 +1067,18 @@ fn draw_handcrafted_settings(ui: &mut egui::Ui, handcrafted: &mut Handcrafted, p
                             );
                         });
                     });
+                    draw_percentage_row(
+                        &mut body,
+                        "Z Start AV",
+                        &mut handcrafted.av_z_start_percentage,
+                        "The start of the atrioventricular node in z-direction in percent.",
+                    );
+                    draw_percentage_row(
+                        &mut body,
+                        "Z Stop AV",
+                        &mut handcrafted.av_z_stop_percentage,
+                        "The end of the atrioventricular node in z-direction in percent.",
+                    );
                 }
 
                 // include hps
@@ -626,87 +1179,87 @@ fn draw_handcrafted_settings(ui: &mut egui::Ui, handcrafted: &mut Handcrafted, p
                             );
                         });
                     });
-                }
-                if patholoical {
-                    // pathology x start
+                    // layered ventricle wall
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("X Start Pathology");
+                            ui.label("Layered Wall");
                         });
                         row.col(|ui| {
-                            ui.add(egui::Slider::new(
-                                &mut handcrafted.pathology_x_start_percentage,
-                                0.0..=1.0,
-                            ));
+                            ui.checkbox(&mut handcrafted.layered_ventricle_wall, "");
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "The start of the pathology \
-                                    in x-direction in percent.",
+                                    "Wether or not to place the His-Purkinje-System \
+                                    only in the lowest z-layer, so the ventricular \
+                                    wall above it is excited transmurally.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
-                    // pathology x stop
+                    if !handcrafted.layered_ventricle_wall {
+                        draw_percentage_row(
+                            &mut body,
+                            "Z Start HPS",
+                            &mut handcrafted.hps_z_start_percentage,
+                            "The start of the His-Purkinje-System in z-direction in percent.",
+                        );
+                        draw_percentage_row(
+                            &mut body,
+                            "Z Stop HPS",
+                            &mut handcrafted.hps_z_stop_percentage,
+                            "The end of the His-Purkinje-System in z-direction in percent.",
+                        );
+                    }
+                }
+                if patholoical {
+                    let mut removed = None;
+                    for (index, region) in handcrafted.pathology_regions.iter_mut().enumerate() {
+                        if draw_pathology_region_rows(&mut body, index, region) {
+                            removed = Some(index);
+                        }
+                    }
+                    if let Some(index) = removed {
+                        handcrafted.pathology_regions.remove(index);
+                    }
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("X Stop Pathology");
-                        });
-                        row.col(|ui| {
-                            ui.add(egui::Slider::new(
-                                &mut handcrafted.pathology_x_stop_percentage,
-                                0.0..=1.0,
-                            ));
+                            ui.label("Border Zone Width");
                         });
                         row.col(|ui| {
                             ui.add(
-                                egui::Label::new(
-                                    "The end of the pathology \
-                                    in x-direction in percent.",
-                                )
-                                .truncate(),
+                                egui::Slider::new(&mut handcrafted.border_zone_width_voxels, 0..=5)
+                                    .suffix(" voxels"),
                             );
                         });
-                    });
-                    // pathology y start
-                    body.row(ROW_HEIGHT, |mut row| {
-                        row.col(|ui| {
-                            ui.label("Y Start Pathology");
-                        });
-                        row.col(|ui| {
-                            ui.add(egui::Slider::new(
-                                &mut handcrafted.pathology_y_start_percentage,
-                                0.0..=1.0,
-                            ));
-                        });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "The start of the pathology \
-                                    in y-direction in percent.",
+                                    "The width of the border zone placed \
+                                    around the pathology regions in voxels. \
+                                    Zero disables the border zone.",
                                 )
                                 .truncate(),
                             );
                         });
                     });
-                    // pathology y start
                     body.row(ROW_HEIGHT, |mut row| {
                         row.col(|ui| {
-                            ui.label("Y Stop Pathology");
+                            ui.label("Pathology Regions");
                         });
                         row.col(|ui| {
-                            ui.add(egui::Slider::new(
-                                &mut handcrafted.pathology_y_stop_percentage,
-                                0.0..=1.0,
-                            ));
+                            if ui.button("Add Region").clicked() {
+                                handcrafted
+                                    .pathology_regions
+                                    .push(PathologyRegion::default());
+                            }
                         });
                         row.col(|ui| {
                             ui.add(
                                 egui::Label::new(
-                                    "The end of the pathology \
-                                    in y-direction in percent.",
+                                    "Adds another cuboid region \
+                                    of pathological tissue.",
                                 )
                                 .truncate(),
                             );
@@ -715,13 +1268,191 @@ fn draw_handcrafted_settings(ui: &mut egui::Ui, handcrafted: &mut Handcrafted, p
                 }
             });
         if patholoical {
-            ui.add_space(7.0 * ROW_HEIGHT);
+            let pathology_rows = handcrafted.pathology_regions.len() * PATHOLOGY_REGION_ROWS + 2;
+            ui.add_space((3 + pathology_rows) as f32 * ROW_HEIGHT);
         } else {
             ui.add_space(2.0 * ROW_HEIGHT);
         }
     });
 }
 
+/// Draws a table row with a slider for a value given in percent.
+#[tracing::instrument(skip(body, value), level = "trace")]
+pub(super) fn draw_percentage_row(
+    body: &mut egui_extras::TableBody,
+    name: &str,
+    value: &mut f32,
+    description: &str,
+) {
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label(name);
+        });
+        row.col(|ui| {
+            ui.add(egui::Slider::new(value, 0.0..=1.0));
+        });
+        row.col(|ui| {
+            ui.add(egui::Label::new(description).truncate());
+        });
+    });
+}
+
+/// Number of table rows drawn per pathology region.
+/// Draws the table rows for a single pacing site.
+///
+/// Returns true if the site should be removed.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_pacing_site_rows(
+    body: &mut egui_extras::TableBody,
+    index: usize,
+    site: &mut PacingSite,
+) -> bool {
+    let mut remove = false;
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label(egui::RichText::new(format!("Pacing Site {}", index + 1)).strong());
+        });
+        row.col(|ui| {
+            remove = ui.button("Remove").clicked();
+        });
+        row.col(|ui| {
+            ui.add(
+                egui::Label::new("A site stimulated by the delayed control function.").truncate(),
+            );
+        });
+    });
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label("Position");
+        });
+        row.col(|ui| {
+            ui.horizontal(|ui| {
+                for coordinate in &mut site.position_mm {
+                    ui.add(egui::DragValue::new(coordinate).speed(1.0).suffix(" mm"));
+                }
+            });
+        });
+        row.col(|ui| {
+            ui.add(
+                egui::Label::new(
+                    "The position of the site, the closest heart voxel \
+                    is stimulated.",
+                )
+                .truncate(),
+            );
+        });
+    });
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label("Onset");
+        });
+        row.col(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut site.onset_ms)
+                    .range(0.0..=10_000.0)
+                    .suffix(" ms"),
+            );
+        });
+        row.col(|ui| {
+            ui.add(
+                egui::Label::new(
+                    "The delay of the stimulus relative to the \
+                    sinoatrial node.",
+                )
+                .truncate(),
+            );
+        });
+    });
+    remove
+}
+
+const PATHOLOGY_REGION_ROWS: usize = 8;
+
+/// Draws the table rows for a single pathology region.
+///
+/// Returns true if the region should be removed.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_pathology_region_rows(
+    body: &mut egui_extras::TableBody,
+    index: usize,
+    region: &mut PathologyRegion,
+) -> bool {
+    let mut remove = false;
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label(egui::RichText::new(format!("Pathology {}", index + 1)).strong());
+        });
+        row.col(|ui| {
+            remove = ui.button("Remove").clicked();
+        });
+        row.col(|ui| {
+            ui.add(egui::Label::new("A cuboid region of pathological tissue.").truncate());
+        });
+    });
+    let extents = [
+        (
+            "X Start",
+            &mut region.x_start_percentage,
+            "The start of the pathology in x-direction in percent.",
+        ),
+        (
+            "X Stop",
+            &mut region.x_stop_percentage,
+            "The end of the pathology in x-direction in percent.",
+        ),
+        (
+            "Y Start",
+            &mut region.y_start_percentage,
+            "The start of the pathology in y-direction in percent.",
+        ),
+        (
+            "Y Stop",
+            &mut region.y_stop_percentage,
+            "The end of the pathology in y-direction in percent.",
+        ),
+        (
+            "Z Start",
+            &mut region.z_start_percentage,
+            "The start of the pathology in z-direction in percent.",
+        ),
+        (
+            "Z Stop",
+            &mut region.z_stop_percentage,
+            "The end of the pathology in z-direction in percent.",
+        ),
+    ];
+    for (name, value, description) in extents {
+        draw_percentage_row(body, name, value, description);
+    }
+    body.row(ROW_HEIGHT, |mut row| {
+        row.col(|ui| {
+            ui.label("Current Factor");
+        });
+        row.col(|ui| {
+            ui.horizontal(|ui| {
+                let mut own_factor = region.current_factor.is_some();
+                ui.checkbox(&mut own_factor, "");
+                if own_factor {
+                    let factor = region.current_factor.get_or_insert(0.0);
+                    ui.add(egui::Slider::new(factor, 0.0..=1.0));
+                } else {
+                    region.current_factor = None;
+                }
+            });
+        });
+        row.col(|ui| {
+            ui.add(
+                egui::Label::new(
+                    "Wether or not to use an own current factor \
+                    for this region instead of the common one.",
+                )
+                .truncate(),
+            );
+        });
+    });
+    remove
+}
+
 #[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
 fn draw_mri_settings(ui: &mut egui::Ui, mri: &mut Mri, _patholoical: bool) {
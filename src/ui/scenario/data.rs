@@ -1,16 +1,20 @@
+use std::path::PathBuf;
+
 use egui::Align;
 use egui_extras::{Column, TableBuilder};
-use tracing::trace;
+use tracing::{error, trace};
 
-use super::{common::draw_ui_scenario_common, ROW_HEIGHT};
+use super::{common::draw_ui_scenario_common, draw_config_issues, ROW_HEIGHT};
 use crate::{
     core::{
         config::{
             model::{
-                SensorArrayGeometry, SensorArrayMotion, DEFAULT_SENSOR_ORIGIN_CUBE,
+                SensorArrayGeometry, SensorArrayModality, SensorArrayMotion, SensorArrayTrajectory,
+                SensorType, DEFAULT_GRADIOMETER_BASELINE_MM, DEFAULT_SENSOR_ORIGIN_CUBE,
                 DEFAULT_SENSOR_ORIGIN_CYLINDER,
             },
             simulation::Simulation,
+            validation::ConfigIssue,
         },
         scenario::{Scenario, Status},
     },
@@ -19,8 +23,12 @@ use crate::{
 
 /// Draws the data section of the scenario UI.
 #[allow(clippy::too_many_lines, clippy::module_name_repetitions)]
-#[tracing::instrument(skip(parent), level = "trace")]
-pub fn draw_ui_scenario_data(parent: &mut egui::Ui, scenario: &mut Scenario) {
+#[tracing::instrument(skip(parent, issues), level = "trace")]
+pub fn draw_ui_scenario_data(
+    parent: &mut egui::Ui,
+    scenario: &mut Scenario,
+    issues: &[ConfigIssue],
+) {
     trace!("Running system to draw scenario data UI.");
     if *scenario.get_status() != Status::Planning {
         parent.disable();
@@ -33,8 +41,12 @@ pub fn draw_ui_scenario_data(parent: &mut egui::Ui, scenario: &mut Scenario) {
         .show(parent, |ui| {
             ui.heading("Simulation");
             ui.separator();
+            draw_config_issues(ui, issues, "simulation");
             draw_basic_settings(ui, simulation);
             draw_sensor_settings(ui, simulation);
+            draw_beat_variability_settings(ui, simulation);
+            draw_structured_noise_settings(ui, simulation);
+            draw_sensor_faults_settings(ui, simulation);
             draw_general_heart_settings(ui, simulation);
             draw_ui_scenario_common(ui, &mut simulation.model);
         });
@@ -106,6 +118,285 @@ fn draw_basic_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
     });
 }
 
+#[allow(clippy::too_many_lines)]
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_beat_variability_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
+    ui.label(egui::RichText::new("Beat Variability").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                let variability = &mut simulation.beat_variability;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Heart Rate Jitter");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(
+                            &mut variability.heart_rate_jitter_std,
+                            0.0..=0.5,
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Relative standard deviation of the per-beat heart rate. \
+                                Default: 0.0.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Amplitude Scaling");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(
+                            &mut variability.amplitude_scaling_std,
+                            0.0..=0.5,
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Relative standard deviation of the per-beat amplitude. \
+                                Default: 0.0.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Sensor Displacement");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Slider::new(
+                                &mut variability.sensor_displacement_std_mm,
+                                0.0..=20.0,
+                            )
+                            .suffix(" mm"),
+                        );
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Standard deviation of the per-beat sensor array displacement \
+                                along each axis. Default: 0.0 mm.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Seed");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::DragValue::new(&mut variability.seed));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Seed of the random number generator used for the beat \
+                                variability. Default: 0.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+            });
+    });
+}
+
+#[allow(clippy::too_many_lines)]
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_structured_noise_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
+    ui.label(egui::RichText::new("Structured Noise").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                let noise = &mut simulation.structured_noise;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Baseline Drift");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut noise.baseline_drift_amplitude, 0.0..=100.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Peak amplitude of the sinusoidal baseline drift, e.g. caused by respiration. Default: 0.0.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Drift Frequency");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut noise.baseline_drift_frequency_hz, 0.01..=2.0).suffix(" Hz"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Frequency of the baseline drift. Default: 0.25 Hz.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Powerline");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut noise.powerline_amplitude, 0.0..=100.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Peak amplitude of the powerline interference. Default: 0.0.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Powerline Frequency");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut noise.powerline_frequency_hz, 45.0..=65.0).suffix(" Hz"));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Frequency of the powerline interference, usually 50 or 60 Hz. Default: 50.0 Hz.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("1/f Noise");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut noise.pink_noise_amplitude, 0.0..=100.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("RMS amplitude of the 1/f noise. Default: 0.0.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Seed");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::DragValue::new(&mut noise.seed));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Seed of the random number generator used for the structured noise. Default: 0.").truncate());
+                    });
+                });
+            });
+    });
+}
+
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_sensor_faults_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
+    ui.label(egui::RichText::new("Sensor Faults").underline());
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                let faults = &mut simulation.sensor_faults;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Dropout Fraction");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut faults.dropout_fraction, 0.0..=1.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Fraction of the sensors that record a flat zero signal. Default: 0.0.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Saturation Fraction");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut faults.saturation_fraction, 0.0..=1.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Fraction of the sensors that saturate. Default: 0.0.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Saturation Level");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut faults.saturation_level, 0.0..=1.0));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Clipping level of the saturated sensors relative to their peak amplitude. Default: 0.5.").truncate());
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Seed");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::DragValue::new(&mut faults.seed));
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Label::new("Seed of the random number generator used to draw the faulty sensors. Default: 0.").truncate());
+                    });
+                });
+            });
+    });
+}
+
 #[allow(clippy::too_many_lines)]
 #[tracing::instrument(skip_all, level = "trace")]
 fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
@@ -139,8 +430,14 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                         ui.label("Sensor Geometry");
                     });
                     row.col(|ui| {
+                        let selected = match sensor_geometry {
+                            SensorArrayGeometry::Cube => "Cube",
+                            SensorArrayGeometry::SparseCube => "SparseCube",
+                            SensorArrayGeometry::Cylinder => "Cylinder",
+                            SensorArrayGeometry::FromFile { .. } => "FromFile",
+                        };
                         egui::ComboBox::new("cb_sensor_geometry", "")
-                            .selected_text(format!("{sensor_geometry:?}"))
+                            .selected_text(selected)
                             .show_ui(ui, |ui| {
                                 ui.selectable_value(
                                     sensor_geometry,
@@ -157,6 +454,15 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                                     SensorArrayGeometry::Cylinder,
                                     "Cylinder",
                                 );
+                                if ui
+                                    .selectable_label(selected == "FromFile", "From File")
+                                    .clicked()
+                                    && selected != "FromFile"
+                                {
+                                    *sensor_geometry = SensorArrayGeometry::FromFile {
+                                        path: PathBuf::from("assets/sensor_positions_mm.npy"),
+                                    };
+                                }
                             });
                     });
                     row.col(|ui| {
@@ -170,7 +476,9 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                 });// end row
                 if last_value != *sensor_geometry {
                     match sensor_geometry {
-                        SensorArrayGeometry::Cube | SensorArrayGeometry::SparseCube => {
+                        SensorArrayGeometry::Cube
+                        | SensorArrayGeometry::SparseCube
+                        | SensorArrayGeometry::FromFile { .. } => {
                             simulation.model.common.sensor_array_origin_mm =
                                 DEFAULT_SENSOR_ORIGIN_CUBE;
                         }
@@ -200,29 +508,128 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                                     SensorArrayMotion::Grid,
                                     "Grid",
                                 );
+                                ui.selectable_value(
+                                    sensor_motion,
+                                    SensorArrayMotion::Trajectory,
+                                    "Trajectory",
+                                );
                             });
                     });
                     row.col(|ui| {
                         ui.add(
                             egui::Label::new(
-                                "Whether the sensor array is static or moving along a grid. Default: Grid.",
+                                "Whether the sensor array is static, moving along a grid between beats \
+                                or moving along a continuous trajectory. Default: Grid.",
                             )
                             .truncate(),
                         );
                     });
                 });// end row
-                    // 3D sensors?
+                    // sensor type
+                let sensor_type = &mut simulation.model.common.sensor_type;
                 body.row(ROW_HEIGHT, |mut row| {
                     row.col(|ui| {
-                        ui.label("3D Sensors");
+                        ui.label("Sensor Type");
                     });
                     row.col(|ui| {
-                            ui.add(egui::Checkbox::new(&mut simulation.model.common.three_d_sensors, ""));
+                        let selected_text = match sensor_type {
+                            SensorType::SingleAxis => "SingleAxis",
+                            SensorType::ThreeAxis => "ThreeAxis",
+                            SensorType::Gradiometer { .. } => "Gradiometer",
+                        };
+                        let baseline_mm = match sensor_type {
+                            SensorType::Gradiometer { baseline_mm } => *baseline_mm,
+                            _ => DEFAULT_GRADIOMETER_BASELINE_MM,
+                        };
+                        egui::ComboBox::new("cb_sensor_type", "")
+                            .selected_text(selected_text)
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    sensor_type,
+                                    SensorType::SingleAxis,
+                                    "SingleAxis",
+                                );
+                                ui.selectable_value(
+                                    sensor_type,
+                                    SensorType::ThreeAxis,
+                                    "ThreeAxis",
+                                );
+                                ui.selectable_value(
+                                    sensor_type,
+                                    SensorType::Gradiometer { baseline_mm },
+                                    "Gradiometer",
+                                );
+                            });
                     });
                     row.col(|ui| {
                         ui.add(
                             egui::Label::new(
-                                "Whether to use 3D sensors or not. Default: true.",
+                                "Whether each sensor position holds a single magnetometer \
+                                with alternating axis, three magnetometers along x, y and z \
+                                or three axial gradiometers. Default: ThreeAxis.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                }); // end row
+                if let SensorType::Gradiometer { baseline_mm } =
+                    &mut simulation.model.common.sensor_type
+                {
+                    // gradiometer baseline
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("Gradiometer baseline [mm]");
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::DragValue::new(baseline_mm)
+                                    .speed(1.0)
+                                    .range(1.0..=200.0),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    "Distance between the two coils of each gradiometer \
+                                    along its axis. Default: 50.",
+                                )
+                                .truncate(),
+                            );
+                        });
+                    }); // end row
+                }
+                // sensor_modality
+                let sensor_modality = &mut simulation.model.common.sensor_array_modality;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Sensor Modality");
+                    });
+                    row.col(|ui| {
+                        egui::ComboBox::new("cb_sensor_modality", "")
+                            .selected_text(format!("{sensor_modality:?}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    sensor_modality,
+                                    SensorArrayModality::Magnetic,
+                                    "Magnetic",
+                                );
+                                ui.selectable_value(
+                                    sensor_modality,
+                                    SensorArrayModality::Electric,
+                                    "Electric",
+                                );
+                                ui.selectable_value(
+                                    sensor_modality,
+                                    SensorArrayModality::Mixed,
+                                    "Mixed",
+                                );
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Whether the array consists of magnetometers, electric potential \
+                                electrodes or both. Default: Magnetic.",
                             )
                             .truncate(),
                         );
@@ -329,6 +736,35 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                             });
                         });
                     }
+
+                    SensorArrayGeometry::FromFile { path } => {
+                        // Sensor position file
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Sensor positions");
+                            });
+                            row.col(|ui| {
+                                let mut path_string = path
+                                    .to_str()
+                                    .unwrap_or_else(|| {
+                                        error!("Sensor position path contains invalid UTF-8: {path:?}");
+                                        "<invalid path>"
+                                    })
+                                    .to_string();
+                                ui.add(egui::TextEdit::singleline(&mut path_string));
+                                *path = PathBuf::from(path_string);
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new(
+                                        "The path to a .npy file with one sensor position in mm \
+                                        per row, e.g. written by the sensor_placement tool.",
+                                    )
+                                    .truncate(),
+                                );
+                            });
+                        });
+                    }
                 }
                 // Then render the number of sensors if needed for either SparseCube or Cylinder
                 if matches!(sensor_geometry, SensorArrayGeometry::SparseCube | SensorArrayGeometry::Cylinder) {
@@ -383,6 +819,77 @@ fn draw_sensor_settings(ui: &mut egui::Ui, simulation: &mut Simulation) {
                     });
                 }); // end row
                 }
+                if sensor_motion == &SensorArrayMotion::Trajectory {
+                let trajectory = &mut simulation.model.common.sensor_array_trajectory;
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Trajectory");
+                    });
+                    row.col(|ui| {
+                        let selected_text = match trajectory {
+                            SensorArrayTrajectory::Orbit { .. } => "Orbit",
+                            SensorArrayTrajectory::Waypoints { .. } => "Waypoints",
+                        };
+                        egui::ComboBox::new("cb_sensor_trajectory", "")
+                            .selected_text(selected_text)
+                            .show_ui(ui, |ui| {
+                                if ui
+                                    .selectable_label(matches!(trajectory, SensorArrayTrajectory::Orbit { .. }), "Orbit")
+                                    .clicked()
+                                {
+                                    *trajectory = SensorArrayTrajectory::default();
+                                }
+                                if ui
+                                    .selectable_label(matches!(trajectory, SensorArrayTrajectory::Waypoints { .. }), "Waypoints")
+                                    .clicked()
+                                {
+                                    *trajectory = SensorArrayTrajectory::Waypoints { waypoints: Vec::new() };
+                                }
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new("Whether the array orbits around the sensor origin or follows a list of waypoints. Default: Orbit.").truncate(),
+                        );
+                    });
+                }); // end row
+                match trajectory {
+                    SensorArrayTrajectory::Orbit { radius_mm, period_s, number_of_keyframes } => {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Orbit");
+                            });
+                            row.col(|ui| {
+                                ui.with_layout(egui::Layout::left_to_right(Align::TOP), |ui| {
+                                    ui.add(egui::DragValue::new(radius_mm).prefix("r: ").suffix(" mm"));
+                                    ui.add(egui::DragValue::new(period_s).speed(0.01).range(0.01..=100.0).prefix("T: ").suffix(" s"));
+                                    ui.add(egui::DragValue::new(number_of_keyframes).range(1..=360).prefix("n: "));
+                                });
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new("The radius and period of the orbit and the number of keyframes at which the measurement matrix is calculated.").truncate(),
+                                );
+                            });
+                        }); // end row
+                    }
+                    SensorArrayTrajectory::Waypoints { waypoints } => {
+                        body.row(ROW_HEIGHT, |mut row| {
+                            row.col(|ui| {
+                                ui.label("Waypoints");
+                            });
+                            row.col(|ui| {
+                                ui.label(format!("{}", waypoints.len()));
+                            });
+                            row.col(|ui| {
+                                ui.add(
+                                    egui::Label::new("The number of waypoints, given as time and offset to the sensor origin in the scenario config.").truncate(),
+                                );
+                            });
+                        }); // end row
+                    }
+                }
+                }
             });
     });
 }
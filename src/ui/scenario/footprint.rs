@@ -0,0 +1,218 @@
+use egui_extras::{Column, TableBuilder};
+use tracing::trace;
+
+use super::{FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH};
+use crate::core::{
+    config::Config,
+    scenario::{
+        footprint::{format_bytes, Footprint, SystemResources},
+        Scenario, Status,
+    },
+};
+
+/// Latest footprint estimate and the configuration it was calculated for.
+///
+/// The estimate builds the spatial description of both models, which is
+/// too slow to repeat every frame, so it is only calculated on request.
+#[derive(Debug, Default)]
+pub struct FootprintEstimate {
+    id: String,
+    config: Option<Config>,
+    footprint: Option<Result<Footprint, String>>,
+    resources: Option<SystemResources>,
+}
+
+/// Draws the estimated memory and compute footprint of a scenario in
+/// planning, warning if it exceeds the resources of the system.
+#[allow(clippy::too_many_lines)]
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_scenario_footprint(
+    ui: &mut egui::Ui,
+    scenario: &Scenario,
+    estimate: &mut FootprintEstimate,
+) {
+    trace!("Running system to draw scenario footprint UI.");
+    if *scenario.get_status() != Status::Planning {
+        return;
+    }
+    if estimate.id != *scenario.get_id() {
+        *estimate = FootprintEstimate {
+            id: scenario.get_id().clone(),
+            ..FootprintEstimate::default()
+        };
+    }
+    ui.horizontal(|ui| {
+        ui.label(egui::RichText::new("Footprint").underline());
+        if ui.button("Estimate").clicked() {
+            estimate.footprint =
+                Some(Footprint::estimate(&scenario.config).map_err(|e| format!("{e:#}")));
+            estimate.config = Some(scenario.config.clone());
+            estimate
+                .resources
+                .get_or_insert_with(SystemResources::detect);
+        }
+        if estimate
+            .config
+            .as_ref()
+            .is_some_and(|config| *config != scenario.config)
+        {
+            ui.label("The configuration changed since the last estimate.");
+        }
+    });
+    let footprint = match &estimate.footprint {
+        None => return,
+        Some(Err(e)) => {
+            ui.label(
+                egui::RichText::new(format!("Failed to estimate footprint: {e}"))
+                    .color(egui::Color32::RED),
+            );
+            return;
+        }
+        Some(Ok(footprint)) => *footprint,
+    };
+    for warning in footprint.warnings(&estimate.resources.unwrap_or_default()) {
+        ui.label(egui::RichText::new(warning).color(egui::Color32::YELLOW));
+    }
+    ui.group(|ui| {
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Estimate");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("States");
+                    });
+                    row.col(|ui| {
+                        ui.label(footprint.number_of_states.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Number of system states of the algorithm model, three per voxel.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Measurements");
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "{} x {} x {}",
+                            footprint.number_of_beats,
+                            footprint.number_of_steps,
+                            footprint.number_of_sensors
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new("Number of beats, time steps and sensors.").truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Measurement matrix");
+                    });
+                    row.col(|ui| {
+                        ui.label(format_bytes(footprint.measurement_matrix_bytes as u64));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Size of the measurement matrix of the algorithm model.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("FLOPs per epoch");
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.2e}", footprint.flops_per_epoch));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Floating point operations of the forward and backward pass \
+                                 of one epoch of the model-based algorithm.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("RAM");
+                    });
+                    row.col(|ui| {
+                        let resources = estimate.resources.unwrap_or_default();
+                        ui.label(resources.ram_bytes.map_or_else(
+                            || format_bytes(footprint.ram_bytes as u64),
+                            |ram_bytes| {
+                                format!(
+                                    "{} of {}",
+                                    format_bytes(footprint.ram_bytes as u64),
+                                    format_bytes(ram_bytes)
+                                )
+                            },
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Approximate peak memory of data generation and estimation.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                if let Some(vram_bytes) = footprint.vram_bytes {
+                    body.row(ROW_HEIGHT, |mut row| {
+                        row.col(|ui| {
+                            ui.label("VRAM");
+                        });
+                        row.col(|ui| {
+                            let resources = estimate.resources.unwrap_or_default();
+                            ui.label(resources.vram_bytes.map_or_else(
+                                || format_bytes(vram_bytes as u64),
+                                |available| {
+                                    format!(
+                                        "{} of {}",
+                                        format_bytes(vram_bytes as u64),
+                                        format_bytes(available)
+                                    )
+                                },
+                            ));
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                egui::Label::new("Approximate device memory of the GPU algorithm.")
+                                    .truncate(),
+                            );
+                        });
+                    });
+                }
+            });
+    });
+}
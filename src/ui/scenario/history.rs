@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use tracing::trace;
+
+use crate::core::config::Config;
+
+/// Number of edits that can be undone.
+const HISTORY_LENGTH: usize = 100;
+
+/// Undo and redo stacks of the config edits of the selected scenario.
+///
+/// Edits are recorded by comparing the config to the last recorded one
+/// whenever the user is not interacting with a widget, so dragging a slider
+/// or typing into a field is undone as a single edit.
+#[derive(Debug, Default)]
+pub struct ConfigHistory {
+    scenario_id: Option<String>,
+    // the config as of the last recorded edit
+    current: Option<Config>,
+    undo: VecDeque<Config>,
+    redo: Vec<Config>,
+}
+
+impl ConfigHistory {
+    /// Records the config of the scenario as an edit if it changed since the
+    /// last call.
+    ///
+    /// Selecting another scenario starts a new history. While `interacting`
+    /// is true, the edit is still in progress and not recorded yet.
+    #[tracing::instrument(level = "trace", skip(self, config))]
+    pub fn track(&mut self, scenario_id: &str, config: &Config, interacting: bool) {
+        if self.scenario_id.as_deref() != Some(scenario_id) {
+            trace!("Starting config history of scenario {scenario_id}");
+            *self = Self {
+                scenario_id: Some(scenario_id.to_string()),
+                current: Some(config.clone()),
+                ..Self::default()
+            };
+            return;
+        }
+        if interacting || self.current.as_ref() == Some(config) {
+            return;
+        }
+        trace!("Recording config edit");
+        if let Some(previous) = self.current.replace(config.clone()) {
+            if self.undo.len() == HISTORY_LENGTH {
+                self.undo.pop_front();
+            }
+            self.undo.push_back(previous);
+        }
+        self.redo.clear();
+    }
+
+    /// Forgets all edits, e.g. once the scenario leaves the planning phase.
+    #[tracing::instrument(level = "trace", skip(self))]
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[must_use]
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Restores the config before the last edit.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn undo(&mut self, config: &mut Config) {
+        if let Some(previous) = self.undo.pop_back() {
+            trace!("Undoing config edit");
+            if let Some(current) = self.current.replace(previous.clone()) {
+                self.redo.push(current);
+            }
+            *config = previous;
+        }
+    }
+
+    /// Restores the config of the last undone edit.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn redo(&mut self, config: &mut Config) {
+        if let Some(next) = self.redo.pop() {
+            trace!("Redoing config edit");
+            if let Some(current) = self.current.replace(next.clone()) {
+                self.undo.push_back(current);
+            }
+            *config = next;
+        }
+    }
+}
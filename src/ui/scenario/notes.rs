@@ -0,0 +1,109 @@
+use egui_commonmark::{CommonMarkCache, CommonMarkViewer};
+use tracing::{error, trace};
+
+use crate::core::scenario::Scenario;
+
+const NOTES_WIDTH: f32 = 320.0;
+
+/// Edit state of the notes panel and the cache of the markdown renderer.
+#[derive(Default)]
+pub struct NotesPanel {
+    id: String,
+    editing: bool,
+    new_tag: String,
+    cache: CommonMarkCache,
+}
+
+/// Draws the tags and the notes of the scenario in a panel on the right.
+///
+/// The notes are the comment of the scenario rendered as markdown. They can
+/// be edited in a text field after clicking Edit. Tags are added with the
+/// text field below them and removed by clicking them. Every change is saved
+/// right away, except for scenarios of read-only archive directories, which
+/// cannot be changed.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_scenario_notes(
+    context: &egui::Context,
+    scenario: &mut Scenario,
+    panel: &mut NotesPanel,
+) {
+    trace!("Running system to draw scenario notes UI.");
+    if panel.id != *scenario.get_id() {
+        panel.id.clone_from(scenario.get_id());
+        panel.editing = false;
+        panel.new_tag.clear();
+    }
+    let read_only = scenario.is_read_only();
+    egui::SidePanel::right("scenario_notes")
+        .resizable(true)
+        .default_width(NOTES_WIDTH)
+        .show(context, |ui| {
+            let mut changed = false;
+            ui.label(egui::RichText::new("Tags").underline());
+            ui.horizontal_wrapped(|ui| {
+                let mut removed = None;
+                for tag in scenario.tags() {
+                    if ui
+                        .add_enabled(!read_only, egui::Button::new(format!("{tag} ✖")).small())
+                        .on_hover_text("Remove tag")
+                        .clicked()
+                    {
+                        removed = Some(tag.clone());
+                    }
+                }
+                if let Some(tag) = removed {
+                    changed |= scenario.remove_tag(&tag);
+                }
+                if scenario.tags().is_empty() {
+                    ui.weak("No tags.");
+                }
+            });
+            if !read_only {
+                ui.horizontal(|ui| {
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut panel.new_tag)
+                            .hint_text("New tag, e.g. sweep-A")
+                            .desired_width(180.0),
+                    );
+                    let submitted = response.lost_focus()
+                        && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                    if (ui.button("Add").clicked() || submitted) && scenario.add_tag(&panel.new_tag)
+                    {
+                        panel.new_tag.clear();
+                        changed = true;
+                    }
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(egui::RichText::new("Notes").underline());
+                if !read_only {
+                    let label = if panel.editing { "Done" } else { "Edit" };
+                    if ui.button(label).clicked() {
+                        panel.editing = !panel.editing;
+                        changed |= !panel.editing;
+                    }
+                }
+            });
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if panel.editing && !read_only {
+                    let response = ui.add(
+                        egui::TextEdit::multiline(&mut scenario.comment)
+                            .hint_text("Markdown notes")
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(20),
+                    );
+                    changed |= response.lost_focus();
+                } else if scenario.comment.trim().is_empty() {
+                    ui.weak("No notes.");
+                } else {
+                    CommonMarkViewer::new().show(ui, &mut panel.cache, &scenario.comment);
+                }
+            });
+            if changed {
+                if let Err(e) = scenario.save() {
+                    error!("Failed to save scenario: {}", e);
+                }
+            }
+        });
+}
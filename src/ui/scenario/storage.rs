@@ -0,0 +1,66 @@
+use egui_extras::{Column, TableBuilder};
+use tracing::trace;
+
+use super::{FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH};
+use crate::core::scenario::{Scenario, Status};
+
+/// Draws the settings of how the data and results of the scenario are
+/// saved. They can only be changed while the scenario is in planning.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_scenario_storage(ui: &mut egui::Ui, scenario: &mut Scenario) {
+    trace!("Running system to draw scenario storage UI.");
+    let enabled = *scenario.get_status() == Status::Planning;
+    let storage = &mut scenario.config.storage;
+    ui.label(egui::RichText::new("Storage").underline());
+    ui.group(|ui| {
+        if !enabled {
+            ui.disable();
+        }
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .body(|mut body| {
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Compression level");
+                    });
+                    row.col(|ui| {
+                        ui.add(egui::Slider::new(&mut storage.compression_level, 0..=19));
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "The zstd level of the saved data and results. \
+                                Zero saves them uncompressed, higher levels are \
+                                smaller but slower to save.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Persist estimations");
+                    });
+                    row.col(|ui| {
+                        ui.checkbox(&mut storage.persist_estimations, "");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Save the estimations, derivatives and snapshots. \
+                                Without them only the metrics, the model and the \
+                                summary are available after a restart.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+            });
+    });
+}
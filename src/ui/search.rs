@@ -0,0 +1,285 @@
+use egui_extras::{Column, TableBuilder};
+use tracing::{error, info, trace};
+
+use crate::{
+    core::scenario::search::{HyperparameterSearch, LogRange, Objective, Sampler, SearchSpace},
+    settings::results_directory,
+    ScenarioList, SearchList,
+};
+
+/// The settings of a new search and the selected search of the window.
+#[derive(Debug)]
+pub struct SearchWindow {
+    pub open: bool,
+    pub base: Option<String>,
+    pub space: SearchSpace,
+    // comma separated batch sizes of the search space
+    pub batch_sizes: String,
+    pub sampler: Sampler,
+    pub objective: Objective,
+    pub number_of_trials: usize,
+    pub parallel_trials: usize,
+    pub seed: u64,
+    pub selected: Option<String>,
+}
+
+impl Default for SearchWindow {
+    /// Returns a closed window with the default search space and 20 trials,
+    /// two of them in parallel.
+    fn default() -> Self {
+        Self {
+            open: false,
+            base: None,
+            space: SearchSpace::default(),
+            batch_sizes: "0".to_string(),
+            sampler: Sampler::default(),
+            objective: Objective::default(),
+            number_of_trials: 20,
+            parallel_trials: 2,
+            seed: 0,
+            selected: None,
+        }
+    }
+}
+
+/// Draws a window to start hyperparameter searches over the learning rate,
+/// the regularization strengths and the batch size of a base scenario, and
+/// lists the trials of the selected search with the best trial highlighted.
+///
+/// The trials are created and scheduled by the scheduler, so a search only
+/// makes progress while the scheduler is running.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_search(
+    context: &egui::Context,
+    scenario_list: &ScenarioList,
+    searches: &mut SearchList,
+    window: &mut SearchWindow,
+) {
+    trace!("Drawing hyperparameter search window");
+    let mut open = window.open;
+    egui::Window::new("Hyperparameter Search")
+        .open(&mut open)
+        .default_width(600.0)
+        .show(context, |ui| {
+            draw_new_search(ui, scenario_list, searches, window);
+            ui.separator();
+            draw_search_selection(ui, searches, window);
+            let Some(search) = window
+                .selected
+                .as_ref()
+                .and_then(|id| searches.entries.iter().find(|search| &search.id == id))
+            else {
+                ui.label("Start or select a search.");
+                return;
+            };
+            let finished = search.trials.iter().filter(|trial| trial.finished).count();
+            ui.label(format!(
+                "Base scenario {}, {} of {} trials finished.",
+                search.base_scenario_id, finished, search.number_of_trials
+            ));
+            draw_trial_table(ui, search);
+        });
+    window.open = open;
+}
+
+/// Draws the settings of a new search and the button to start it.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_new_search(
+    ui: &mut egui::Ui,
+    scenario_list: &ScenarioList,
+    searches: &mut SearchList,
+    window: &mut SearchWindow,
+) {
+    trace!("Drawing new search settings");
+    egui::Grid::new("grid_new_search").show(ui, |ui| {
+        ui.label("Base scenario");
+        egui::ComboBox::new("cb_search_base", "")
+            .selected_text(window.base.as_deref().unwrap_or("Select"))
+            .show_ui(ui, |ui| {
+                for entry in &scenario_list.entries {
+                    let id = entry.scenario.get_id();
+                    ui.selectable_value(&mut window.base, Some(id.clone()), id);
+                }
+            });
+        ui.end_row();
+        for (label, range) in [
+            ("Learning rate", &mut window.space.learning_rate),
+            (
+                "Max. regularization",
+                &mut window.space.maximum_regularization_strength,
+            ),
+            (
+                "Smoothness",
+                &mut window.space.smoothness_regularization_strength,
+            ),
+        ] {
+            draw_range(ui, label, range);
+            ui.end_row();
+        }
+        ui.label("Batch sizes");
+        ui.add(egui::TextEdit::singleline(&mut window.batch_sizes).hint_text("0, 4, 8"))
+            .on_hover_text("Comma separated, 0 uses all beats in one batch.");
+        ui.end_row();
+        ui.label("Sampler");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut window.sampler, Sampler::Tpe, "TPE");
+            ui.selectable_value(&mut window.sampler, Sampler::Random, "Random");
+        });
+        ui.end_row();
+        ui.label("Objective");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut window.objective, Objective::Loss, "Minimize loss");
+            ui.selectable_value(&mut window.objective, Objective::Dice, "Maximize dice");
+        });
+        ui.end_row();
+        ui.label("Trials");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut window.number_of_trials).range(1..=1000));
+            ui.label("in parallel");
+            ui.add(egui::DragValue::new(&mut window.parallel_trials).range(1..=64));
+            ui.label("seed");
+            ui.add(egui::DragValue::new(&mut window.seed));
+        });
+        ui.end_row();
+    });
+    if ui
+        .add_enabled(window.base.is_some(), egui::Button::new("Start search"))
+        .clicked()
+    {
+        let Some(base) = window.base.as_deref() else {
+            return;
+        };
+        let batch_sizes: Result<Vec<usize>, _> = window
+            .batch_sizes
+            .split(',')
+            .map(|size| size.trim().parse::<usize>())
+            .collect();
+        let Ok(batch_sizes) = batch_sizes else {
+            error!("Invalid batch sizes: {}", window.batch_sizes);
+            return;
+        };
+        let space = SearchSpace {
+            batch_sizes,
+            ..window.space.clone()
+        };
+        let search = HyperparameterSearch::new(
+            base,
+            space,
+            window.sampler,
+            window.objective,
+            window.number_of_trials,
+            window.parallel_trials,
+            window.seed,
+        )
+        .and_then(|search| search.save(results_directory()).map(|()| search));
+        match search {
+            Ok(search) => {
+                info!("Started search {}", search.id);
+                window.selected = Some(search.id.clone());
+                searches.entries.push(search);
+            }
+            Err(e) => error!("Failed to start search: {}", e),
+        }
+    }
+}
+
+/// Draws the bounds of a log range.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_range(ui: &mut egui::Ui, label: &str, range: &mut LogRange) {
+    ui.label(label);
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::DragValue::new(&mut range.min)
+                .speed(0.01)
+                .range(1e-9..=f32::MAX),
+        );
+        ui.label("to");
+        ui.add(
+            egui::DragValue::new(&mut range.max)
+                .speed(0.01)
+                .range(1e-9..=f32::MAX),
+        );
+    });
+}
+
+/// Draws a combo box to select one of the searches by ID.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_search_selection(ui: &mut egui::Ui, searches: &SearchList, window: &mut SearchWindow) {
+    trace!("Drawing search selection");
+    egui::ComboBox::new("cb_search_selection", "Search")
+        .selected_text(window.selected.as_deref().unwrap_or("Select"))
+        .show_ui(ui, |ui| {
+            for search in &searches.entries {
+                ui.selectable_value(&mut window.selected, Some(search.id.clone()), &search.id);
+            }
+        });
+}
+
+/// Draws the trials of the search, marking the best one with a star.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_trial_table(ui: &mut egui::Ui, search: &HyperparameterSearch) {
+    trace!("Drawing trial table");
+    let best = search.best_trial().map(|trial| trial.scenario_id.as_str());
+    TableBuilder::new(ui)
+        .column(Column::auto().resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(100.0).resizable(true))
+        .column(Column::initial(75.0).resizable(true))
+        .column(Column::remainder())
+        .striped(true)
+        .header(30.0, |mut header| {
+            for heading in [
+                "Scenario",
+                "Learning rate",
+                "Max. reg.",
+                "Smoothness",
+                "Batch size",
+                "Value",
+            ] {
+                header.col(|ui| {
+                    ui.heading(heading);
+                });
+            }
+        })
+        .body(|mut body| {
+            for trial in &search.trials {
+                body.row(20.0, |mut row| {
+                    row.col(|ui| {
+                        if best == Some(trial.scenario_id.as_str()) {
+                            ui.label(
+                                egui::RichText::new(format!("★ {}", trial.scenario_id)).strong(),
+                            );
+                        } else {
+                            ui.label(&trial.scenario_id);
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.label(format!("{:.3e}", trial.parameters.learning_rate));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "{:.3e}",
+                            trial.parameters.maximum_regularization_strength
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.label(format!(
+                            "{:.3e}",
+                            trial.parameters.smoothness_regularization_strength
+                        ));
+                    });
+                    row.col(|ui| {
+                        ui.label(trial.parameters.batch_size.to_string());
+                    });
+                    row.col(|ui| {
+                        match (trial.value, trial.finished) {
+                            (Some(value), _) => ui.label(format!("{value:.3e}")),
+                            (None, true) => ui.label("failed"),
+                            (None, false) => ui.label("-"),
+                        };
+                    });
+                });
+            }
+        });
+}
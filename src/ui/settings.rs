@@ -0,0 +1,325 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_editor_cam::prelude::{EditorCam, EnabledMotion};
+use bevy_egui::{egui, EguiContexts};
+use egui_extras::{Column, TableBuilder};
+use tracing::error;
+
+use super::scenario::{FIRST_COLUMN_WIDTH, PADDING, ROW_HEIGHT, SECOND_COLUMN_WIDTH};
+use crate::{
+    settings::{Device, LogFormat, LogLevel, Settings, Theme},
+    vis::plotting::PlotTheme,
+};
+
+/// Draws the UI for the global application settings.
+///
+/// Changes are applied to the [`Settings`] resource immediately and written
+/// to the settings file with the Save button. The directories, the log level
+/// and format and the metrics endpoint are only read at startup.
+#[allow(clippy::module_name_repetitions, clippy::too_many_lines)]
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_settings(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<Settings>,
+    mut cameras: Query<&mut EditorCam, With<Camera>>,
+) {
+    trace!("Running system to draw settings UI.");
+    let ctx = match contexts.ctx_mut() {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            error!("EGUI context not available for settings UI: {}", e);
+            return;
+        }
+    };
+    egui::CentralPanel::default().show(ctx, |ui| {
+        for mut camera in &mut cameras {
+            if ui.ui_contains_pointer() {
+                camera.enabled_motion = EnabledMotion {
+                    pan: false,
+                    orbit: false,
+                    zoom: false,
+                };
+            }
+        }
+        ui.heading("Settings");
+        ui.separator();
+        let width = ui.available_width();
+        TableBuilder::new(ui)
+            .column(Column::exact(FIRST_COLUMN_WIDTH))
+            .column(Column::exact(SECOND_COLUMN_WIDTH))
+            .column(Column::exact(
+                width - FIRST_COLUMN_WIDTH - SECOND_COLUMN_WIDTH - PADDING,
+            ))
+            .striped(true)
+            .header(ROW_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Parameter");
+                });
+                header.col(|ui| {
+                    ui.heading("Value");
+                });
+                header.col(|ui| {
+                    ui.heading("Description");
+                });
+            })
+            .body(|mut body| {
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Results Directory");
+                    });
+                    row.col(|ui| {
+                        let mut directory = settings.results_directory.display().to_string();
+                        if ui.text_edit_singleline(&mut directory).changed() {
+                            settings.results_directory = PathBuf::from(directory);
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Directory scenarios are loaded from and saved to. \
+                                 Takes effect after a restart. Default: ./results.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Archive Directories");
+                    });
+                    row.col(|ui| {
+                        let mut directories = settings
+                            .archive_directories
+                            .iter()
+                            .map(|directory| directory.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join(";");
+                        if ui.text_edit_singleline(&mut directories).changed() {
+                            settings.archive_directories = directories
+                                .split(';')
+                                .map(str::trim)
+                                .filter(|directory| !directory.is_empty())
+                                .map(PathBuf::from)
+                                .collect();
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Further results directories, separated by semicolons, whose \
+                                 scenarios are shown read-only. Takes effect after a restart. \
+                                 Default: none.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Log Directory");
+                    });
+                    row.col(|ui| {
+                        let mut directory = settings.log_directory.display().to_string();
+                        if ui.text_edit_singleline(&mut directory).changed() {
+                            settings.log_directory = PathBuf::from(directory);
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Directory the daily log files are written to. \
+                                 Takes effect after a restart. Default: ./logs.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Log Level");
+                    });
+                    row.col(|ui| {
+                        let log_level = &mut settings.log_level;
+                        egui::ComboBox::new("cb_log_level", "")
+                            .selected_text(format!("{log_level:?}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(log_level, LogLevel::Error, "Error");
+                                ui.selectable_value(log_level, LogLevel::Warn, "Warn");
+                                ui.selectable_value(log_level, LogLevel::Info, "Info");
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Most verbose level that is logged. \
+                                 Takes effect after a restart. Default: Info.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Log Format");
+                    });
+                    row.col(|ui| {
+                        let log_format = &mut settings.log_format;
+                        egui::ComboBox::new("cb_log_format", "")
+                            .selected_text(format!("{log_format:?}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(log_format, LogFormat::Text, "Text");
+                                ui.selectable_value(log_format, LogFormat::Json, "Json");
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Format of the log files, JSON for log aggregation. \
+                                 Takes effect after a restart. Default: Text.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Default Device");
+                    });
+                    row.col(|ui| {
+                        let device = &mut settings.default_device;
+                        egui::ComboBox::new("cb_default_device", "")
+                            .selected_text(format!("{device:?}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(device, Device::Cpu, "CPU");
+                                ui.selectable_value(device, Device::Gpu, "GPU");
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Device the model-based algorithm of new scenarios runs on. \
+                                 Default: CPU.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Theme");
+                    });
+                    row.col(|ui| {
+                        let theme = &mut settings.theme;
+                        egui::ComboBox::new("cb_theme", "")
+                            .selected_text(format!("{theme:?}"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(theme, Theme::System, "System");
+                                ui.selectable_value(theme, Theme::Dark, "Dark");
+                                ui.selectable_value(theme, Theme::Light, "Light");
+                            });
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Color theme of the user interface and of generated plots. \
+                                 Default: System.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Autosave Interval");
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut settings.autosave_interval_s)
+                                .range(0..=3600)
+                                .suffix(" s"),
+                        );
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Interval at which running scenarios are saved. \
+                                 Zero disables autosave. Default: 60 s.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+                body.row(ROW_HEIGHT, |mut row| {
+                    row.col(|ui| {
+                        ui.label("Metrics Endpoint");
+                    });
+                    row.col(|ui| {
+                        let mut endpoint = settings.metrics_endpoint.clone().unwrap_or_default();
+                        if ui
+                            .add(
+                                egui::TextEdit::singleline(&mut endpoint).hint_text("0.0.0.0:9100"),
+                            )
+                            .changed()
+                        {
+                            settings.metrics_endpoint =
+                                Some(endpoint).filter(|endpoint| !endpoint.trim().is_empty());
+                        }
+                    });
+                    row.col(|ui| {
+                        ui.add(
+                            egui::Label::new(
+                                "Listen address of the Prometheus metrics exporter. \
+                                 Takes effect after a restart. Default: disabled.",
+                            )
+                            .truncate(),
+                        );
+                    });
+                });
+            });
+        ui.separator();
+        if ui.button("Save").clicked() {
+            if let Err(e) = settings.save(&Settings::path()) {
+                error!("Failed to save settings: {}", e);
+            }
+        }
+    });
+}
+
+/// The theme the result images are generated with.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UiPlotTheme(pub PlotTheme);
+
+/// Applies the theme of the [`Settings`] to the EGUI context whenever it
+/// changes.
+///
+/// The plot theme follows the resolved theme of the context, so with the
+/// system theme newly generated plots match the UI as well.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn apply_theme(
+    mut contexts: EguiContexts,
+    settings: Res<Settings>,
+    mut plot_theme: ResMut<UiPlotTheme>,
+    mut applied: Local<Option<Theme>>,
+) {
+    let Ok(ctx) = contexts.ctx_mut() else {
+        return;
+    };
+    if *applied != Some(settings.theme) {
+        trace!("Applying {:?} theme", settings.theme);
+        ctx.set_theme(match settings.theme {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Dark => egui::ThemePreference::Dark,
+            Theme::Light => egui::ThemePreference::Light,
+        });
+        *applied = Some(settings.theme);
+    }
+    let theme = match ctx.theme() {
+        egui::Theme::Dark => PlotTheme::Dark,
+        egui::Theme::Light => PlotTheme::Light,
+    };
+    if plot_theme.0 != theme {
+        plot_theme.0 = theme;
+    }
+}
@@ -0,0 +1,198 @@
+use std::{path::Path, sync::mpsc::channel};
+
+use anyhow::{Context, Result};
+use tracing::{debug, error, info, trace};
+
+use crate::{
+    core::{algorithm::streaming::ChannelSource, scenario::Scenario},
+    ingest::{discover_lsl_streams, StreamProtocol, StreamSettings, StreamSubscriber},
+    vis::live::LiveStream,
+};
+
+/// The state of the window for subscribing to a stream of sensor samples.
+#[derive(Debug)]
+pub struct StreamPanel {
+    pub open: bool,
+    pub settings: StreamSettings,
+    pub subscriber: Option<StreamSubscriber>,
+    pub discovered: Vec<String>,
+    pub record: bool,
+    pub record_directory: String,
+    pub feed_estimator: bool,
+}
+
+impl Default for StreamPanel {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default stream panel");
+        Self {
+            open: false,
+            settings: StreamSettings::default(),
+            subscriber: None,
+            discovered: Vec::new(),
+            record: true,
+            record_directory: "recordings".to_string(),
+            feed_estimator: false,
+        }
+    }
+}
+
+/// Draws a window to select a TCP, UDP or LSL stream, connect to it, and
+/// monitor its sample rate and dropouts. The received samples are recorded
+/// to disk on disconnect and can be fed to the streaming estimator with the
+/// estimated model of the selected scenario.
+#[tracing::instrument(skip_all, level = "trace")]
+pub fn draw_ui_stream(
+    context: &egui::Context,
+    panel: &mut StreamPanel,
+    live_stream: &mut LiveStream,
+    scenario: Option<&Scenario>,
+) {
+    trace!("Drawing stream input window");
+    let mut open = panel.open;
+    egui::Window::new("Stream Input")
+        .open(&mut open)
+        .default_width(350.0)
+        .show(context, |ui| {
+            let connected = panel.subscriber.is_some();
+            ui.add_enabled_ui(!connected, |ui| draw_stream_selection(ui, panel));
+            ui.separator();
+            ui.add_enabled_ui(!connected, |ui| {
+                ui.checkbox(&mut panel.record, "Record to disk");
+                ui.horizontal(|ui| {
+                    ui.label("Directory:");
+                    ui.text_edit_singleline(&mut panel.record_directory);
+                });
+                ui.checkbox(&mut panel.feed_estimator, "Feed live estimation");
+            });
+            ui.separator();
+            if connected {
+                if ui.button("Disconnect").clicked() {
+                    disconnect(panel, live_stream);
+                }
+            } else if ui.button("Connect").clicked() {
+                if let Err(e) = connect(panel, live_stream, scenario) {
+                    error!("Failed to connect to stream: {e:#}");
+                }
+            }
+            if let Some(subscriber) = &panel.subscriber {
+                let statistics = subscriber.statistics();
+                ui.label(format!("Received: {}", statistics.received));
+                ui.label(format!("Dropped: {}", statistics.dropped));
+                ui.label(format!("Rate: {:.1} Hz", statistics.rate_hz));
+                if !subscriber.is_running() {
+                    ui.label("Stream ended, disconnect to keep the recording.");
+                }
+            }
+        });
+    panel.open = open;
+}
+
+/// Draws the protocol, address and stream layout inputs.
+#[tracing::instrument(skip_all, level = "trace")]
+fn draw_stream_selection(ui: &mut egui::Ui, panel: &mut StreamPanel) {
+    trace!("Drawing stream selection");
+    let settings = &mut panel.settings;
+    egui::ComboBox::new("cb_stream_protocol", "Protocol")
+        .selected_text(settings.protocol.to_string())
+        .show_ui(ui, |ui| {
+            for protocol in [
+                StreamProtocol::Tcp,
+                StreamProtocol::Udp,
+                StreamProtocol::Lsl,
+            ] {
+                ui.selectable_value(&mut settings.protocol, protocol, protocol.to_string());
+            }
+        });
+    ui.horizontal(|ui| {
+        ui.label(if settings.protocol == StreamProtocol::Lsl {
+            "Stream name:"
+        } else {
+            "Address:"
+        });
+        ui.text_edit_singleline(&mut settings.address);
+    });
+    if settings.protocol == StreamProtocol::Lsl {
+        if ui.button("Discover").clicked() {
+            match discover_lsl_streams() {
+                Ok(streams) => panel.discovered = streams,
+                Err(e) => error!("Failed to discover LSL streams: {e:#}"),
+            }
+        }
+        for name in &panel.discovered {
+            if ui
+                .selectable_label(settings.address == *name, name)
+                .clicked()
+            {
+                settings.address.clone_from(name);
+            }
+        }
+    }
+    ui.horizontal(|ui| {
+        ui.label("Sensors:");
+        ui.add(egui::DragValue::new(&mut settings.number_of_sensors).range(1..=10_000));
+    });
+    ui.horizontal(|ui| {
+        ui.label("Sample rate [Hz]:");
+        ui.add(egui::DragValue::new(&mut settings.sample_rate_hz).range(1.0..=100_000.0));
+    });
+}
+
+/// Subscribes to the selected stream and starts the streaming estimator if
+/// requested.
+#[tracing::instrument(skip_all, level = "info")]
+fn connect(
+    panel: &mut StreamPanel,
+    live_stream: &mut LiveStream,
+    scenario: Option<&Scenario>,
+) -> Result<()> {
+    info!("Connecting to stream.");
+    let forward = if panel.feed_estimator {
+        let scenario = scenario.context("Select a scenario to feed the live estimation")?;
+        let model = scenario
+            .results
+            .as_ref()
+            .and_then(|results| results.model.as_ref())
+            .context("Scenario has no estimated model")?;
+        let (sample_tx, sample_rx) = channel();
+        live_stream.start(
+            model,
+            &scenario.config.algorithm,
+            Box::new(ChannelSource(sample_rx)),
+        )?;
+        Some(sample_tx)
+    } else {
+        None
+    };
+    match StreamSubscriber::start(&panel.settings, forward) {
+        Ok(subscriber) => {
+            panel.subscriber = Some(subscriber);
+            Ok(())
+        }
+        Err(e) => {
+            live_stream.stop();
+            Err(e)
+        }
+    }
+}
+
+/// Stops the subscription, recording the received samples if requested.
+/// The streaming estimator finishes once the forwarded samples run out.
+#[tracing::instrument(skip_all, level = "info")]
+fn disconnect(panel: &mut StreamPanel, live_stream: &mut LiveStream) {
+    info!("Disconnecting from stream.");
+    let Some(subscriber) = panel.subscriber.take() else {
+        return;
+    };
+    let result = if panel.record {
+        let directory = Path::new(&panel.record_directory)
+            .join(chrono::Utc::now().format("%Y-%m-%d-%H-%M-%S").to_string());
+        subscriber.stop_and_record(&directory)
+    } else {
+        subscriber.stop()
+    };
+    if let Err(e) = result {
+        error!("Stream failed: {e:#}");
+        live_stream.stop();
+    }
+}
@@ -66,65 +66,36 @@ pub fn draw_ui_topbar(
             if ui
                 .add_enabled(
                     ui_state.get() != &UiState::Results
-                        && selected_scenario.index.is_some()
-                        && selected_scenario.index.is_some_and(|index| {
-                            scenario_list
-                                .entries
-                                .get(index)
-                                .is_some_and(|entry| entry.scenario.get_status() == &Status::Done)
-                        }),
+                        && selected_is_done(&scenario_list, &selected_scenario),
                     egui::Button::new("Results"),
                 )
                 .clicked()
             {
-                if let Some(index) = selected_scenario.index {
-                    if let Some(entry) = scenario_list.entries.get_mut(index) {
-                        let scenario = &mut entry.scenario;
-                        if let Err(e) = scenario.load_data() {
-                            error!("Failed to load scenario data: {}", e);
-                        }
-                        if let Err(e) = scenario.load_results() {
-                            error!("Failed to load scenario results: {}", e);
-                        }
-                        commands.insert_resource(NextState::Pending(UiState::Results));
-                    } else {
-                        error!("Selected scenario index {} is out of bounds", index);
-                    }
-                } else {
-                    error!("No scenario selected for Results view");
+                if load_selected_results(&mut scenario_list, &selected_scenario) {
+                    commands.insert_resource(NextState::Pending(UiState::Results));
                 }
             }
             if ui
                 .add_enabled(
                     ui_state.get() != &UiState::Volumetric
-                        && selected_scenario.index.is_some()
-                        && selected_scenario.index.is_some_and(|index| {
-                            scenario_list
-                                .entries
-                                .get(index)
-                                .is_some_and(|entry| entry.scenario.get_status() == &Status::Done)
-                        }),
+                        && selected_is_done(&scenario_list, &selected_scenario),
                     egui::Button::new("Volumetric"),
                 )
                 .clicked()
             {
-                if let Some(index) = selected_scenario.index {
-                    if let Some(entry) = scenario_list.entries.get_mut(index) {
-                        let scenario = &mut entry.scenario;
-                        if let Err(e) = scenario.load_data() {
-                            error!("Failed to load scenario data: {}", e);
-                        }
-                        if let Err(e) = scenario.load_results() {
-                            error!("Failed to load scenario results: {}", e);
-                        }
-                        commands.insert_resource(NextState::Pending(UiState::Volumetric));
-                    } else {
-                        error!("Selected scenario index {} is out of bounds", index);
-                    }
-                } else {
-                    error!("No scenario selected for Volumetric view");
+                if load_selected_results(&mut scenario_list, &selected_scenario) {
+                    commands.insert_resource(NextState::Pending(UiState::Volumetric));
                 }
             }
+            if ui
+                .add_enabled(
+                    ui_state.get() != &UiState::Settings,
+                    egui::Button::new("Settings"),
+                )
+                .clicked()
+            {
+                commands.insert_resource(NextState::Pending(UiState::Settings));
+            }
             ui.add(Separator::default().spacing(200.0));
             if ui
                 .add_enabled(
@@ -149,3 +120,44 @@ pub fn draw_ui_topbar(
         });
     });
 }
+
+/// Returns true if the selected scenario is done, so its results can be
+/// shown.
+#[must_use]
+#[tracing::instrument(skip_all, level = "trace")]
+pub(super) fn selected_is_done(
+    scenario_list: &ScenarioList,
+    selected_scenario: &SelectedSenario,
+) -> bool {
+    selected_scenario.index.is_some_and(|index| {
+        scenario_list
+            .entries
+            .get(index)
+            .is_some_and(|entry| entry.scenario.get_status() == &Status::Done)
+    })
+}
+
+/// Loads the data and results of the selected scenario, which the results
+/// and volumetric views need. Returns false if no scenario is selected.
+#[tracing::instrument(skip_all, level = "debug")]
+pub(super) fn load_selected_results(
+    scenario_list: &mut ScenarioList,
+    selected_scenario: &SelectedSenario,
+) -> bool {
+    let Some(index) = selected_scenario.index else {
+        error!("No scenario selected for the results");
+        return false;
+    };
+    let Some(entry) = scenario_list.entries.get_mut(index) else {
+        error!("Selected scenario index {} is out of bounds", index);
+        return false;
+    };
+    let scenario = &mut entry.scenario;
+    if let Err(e) = scenario.load_data() {
+        error!("Failed to load scenario data: {}", e);
+    }
+    if let Err(e) = scenario.load_results() {
+        error!("Failed to load scenario results: {}", e);
+    }
+    true
+}
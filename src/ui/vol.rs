@@ -1,12 +1,17 @@
+use anyhow::{Context, Result};
 use bevy::prelude::*;
 use bevy_editor_cam::controller::component::{EditorCam, EnabledMotion};
 use bevy_egui::{egui, EguiContexts};
 use egui_plot::{Line, Plot, PlotPoints, VLine};
+use ndarray::s;
 use tracing::error;
 
+use super::stream::{draw_ui_stream, StreamPanel};
 use crate::{
+    core::{algorithm::streaming::ReplaySource, scenario::Scenario},
     vis::{
         cutting_plane::CuttingPlaneSettings,
+        live::LiveStream,
         options::{ColorMode, ColorOptions, VisibilityOptions},
         sample_tracker::SampleTracker,
         sensors::BacketSettings,
@@ -36,6 +41,8 @@ pub fn draw_ui_volumetric(
     mut sensor_bracket_settings: ResMut<BacketSettings>,
     mut cameras: Query<&mut EditorCam, With<Camera>>,
     mut ev_setup: EventWriter<SetupHeartAndSensors>,
+    mut live_stream: ResMut<LiveStream>,
+    mut stream_panel: Local<StreamPanel>,
     selected_scenario: Res<SelectedSenario>,
     scenario_list: Res<ScenarioList>,
 ) {
@@ -171,7 +178,9 @@ pub fn draw_ui_volumetric(
                         .and_then(|s| s.results.as_ref())
                         .and_then(|r| r.model.as_ref())
                         .map_or(0, |m| {
-                            m.spatial_description.sensors.array_offsets_mm.shape()[0]
+                            m.spatial_description
+                                .sensors
+                                .count_beats()
                                 .saturating_sub(1)
                         }),
                 ));
@@ -192,6 +201,32 @@ pub fn draw_ui_volumetric(
                 }
             }
         });
+        ui.label(egui::RichText::new("Live estimation").underline());
+        ui.group(|ui| {
+            if live_stream.is_active() {
+                if ui.button("Stop live replay").clicked() {
+                    live_stream.stop();
+                }
+            } else if ui
+                .add_enabled(scenario.is_some(), egui::Button::new("Start live replay"))
+                .clicked()
+            {
+                if let Some(scenario) = scenario {
+                    if let Err(e) =
+                        start_live_replay(&mut live_stream, scenario, color_options.playbackspeed)
+                    {
+                        error!("Failed to start live replay: {e:#}");
+                    }
+                }
+            }
+            if ui.button("Stream input").clicked() {
+                stream_panel.open = true;
+            }
+            if let Some(update) = &live_stream.latest {
+                ui.label(format!("Samples: {}", update.sample_count));
+                ui.label(format!("Residual RMS: {:.3e}", update.residual_rms));
+            }
+        });
         ui.label(egui::RichText::new("Visibility").underline());
         ui.group(|ui| {
             let mut visible = visibility_options.heart;
@@ -284,6 +319,7 @@ pub fn draw_ui_volumetric(
             }
         });
     });
+    draw_ui_stream(ctx, &mut stream_panel, &mut live_stream, scenario);
     if let Some(scenario) = scenario {
         let ctx = match contexts.ctx_mut() {
             Ok(ctx) => ctx,
@@ -332,13 +368,58 @@ pub fn draw_ui_volumetric(
                     "Current Time",
                     sample_tracker.current_sample as f64 / samplerate_hz,
                 );
+                #[allow(clippy::cast_precision_loss)]
+                let beat_lines: Vec<VLine> = scenario
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.beats.as_ref())
+                    .map(|beats| {
+                        beats.triggers_in_beat(
+                            sample_tracker.selected_beat,
+                            sample_tracker.max_sample,
+                        )
+                    })
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|trigger| {
+                        VLine::new("Beat Trigger", trigger as f64 / samplerate_hz)
+                            .color(egui::Color32::GRAY)
+                    })
+                    .collect();
                 Plot::new("my_plot")
                     .include_x(0)
                     .include_x(1)
                     .show(ui, |plot_ui| {
                         plot_ui.line(sin_line);
                         plot_ui.vline(v_line);
+                        for beat_line in beat_lines {
+                            plot_ui.vline(beat_line);
+                        }
                     });
             });
     }
 }
+
+/// Replays the first beat of the simulated measurements of the scenario
+/// through the streaming estimator, using the estimated model, at the given
+/// fraction of real-time speed.
+#[tracing::instrument(skip(live_stream, scenario), level = "info")]
+fn start_live_replay(
+    live_stream: &mut LiveStream,
+    scenario: &Scenario,
+    playback_speed: f32,
+) -> Result<()> {
+    info!("Starting live replay of scenario {}.", scenario.get_id());
+    let model = scenario
+        .results
+        .as_ref()
+        .and_then(|results| results.model.as_ref())
+        .context("Scenario has no estimated model")?;
+    let data = scenario.data.as_ref().context("Scenario has no data")?;
+    let source = ReplaySource::new(
+        data.simulation.measurements.slice(s![0, .., ..]),
+        scenario.config.simulation.sample_rate_hz,
+        playback_speed,
+    )?;
+    live_stream.start(model, &scenario.config.algorithm, Box::new(source))
+}
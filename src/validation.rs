@@ -0,0 +1,347 @@
+use std::f64::consts::PI;
+
+use anyhow::{Context, Result};
+use ndarray::{s, Array2};
+use physical_constants::VACUUM_MAG_PERMEABILITY;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::core::{
+    algorithm::calculate_pseudo_inverse,
+    config::{
+        algorithm::{Algorithm, RegularizationPath},
+        model::Model as ModelConfig,
+    },
+    data::Data,
+    model::{spatial::SpatialDescription, Model},
+    scenario::results::Results,
+};
+
+/// Settings for the comparison against the analytic field of a single
+/// rotating current dipole.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DipoleValidation {
+    /// Model providing the voxel grid and the sensors. The grid should be
+    /// coarse enough for the pseudo inverse to be overdetermined.
+    pub model: ModelConfig,
+    pub sample_rate_hz: f32,
+    pub duration_s: f32,
+    /// Frequency with which the dipole rotates in the x-y plane.
+    pub rotation_frequency_hz: f32,
+    /// Magnitude of the current density in the dipole voxel in A/m^2.
+    pub current_density: f32,
+    /// Maximum relative error of the forward model.
+    pub forward_tolerance: f32,
+    /// Maximum relative error of the system states recovered by the
+    /// pseudo inverse.
+    pub inverse_tolerance: f32,
+}
+
+impl Default for DipoleValidation {
+    #[tracing::instrument(level = "debug")]
+    fn default() -> Self {
+        debug!("Creating default dipole validation");
+        let mut model = ModelConfig::default();
+        model.common.voxel_size_mm = 20.0;
+        Self {
+            model,
+            sample_rate_hz: 1000.0,
+            duration_s: 0.05,
+            rotation_frequency_hz: 20.0,
+            current_density: 1.0,
+            forward_tolerance: 1e-4,
+            inverse_tolerance: 1e-2,
+        }
+    }
+}
+
+/// The outcome of the single dipole validation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DipoleValidationReport {
+    /// Index of the first state of the dipole voxel.
+    pub dipole_state: usize,
+    pub number_of_states: usize,
+    pub number_of_sensors: usize,
+    /// Relative error between the measurement matrix applied to the dipole
+    /// and the analytic field at the sensors.
+    pub forward_relative_error: f32,
+    /// Relative error between the system states estimated by the pseudo
+    /// inverse and the dipole.
+    pub inverse_relative_error: f32,
+    /// Whether the voxel with the largest estimated current is the dipole
+    /// voxel.
+    pub localized: bool,
+    pub forward_tolerance: f32,
+    pub inverse_tolerance: f32,
+}
+
+impl DipoleValidationReport {
+    /// Returns true if both errors are within tolerance and the dipole was
+    /// localized.
+    #[must_use]
+    #[tracing::instrument(level = "debug")]
+    pub fn passed(&self) -> bool {
+        self.forward_relative_error <= self.forward_tolerance
+            && self.inverse_relative_error <= self.inverse_tolerance
+            && self.localized
+    }
+}
+
+/// Simulates a single rotating dipole in the voxel closest to the center of
+/// the heart, compares the forward model against the analytic Biot-Savart
+/// field and checks that the pseudo inverse recovers the dipole.
+///
+/// # Errors
+///
+/// Returns an error if the model could not be created, the pseudo inverse
+/// fails or the problem is underdetermined.
+#[tracing::instrument(level = "info", skip_all)]
+pub fn validate_single_dipole(config: &DipoleValidation) -> Result<DipoleValidationReport> {
+    info!("Running single dipole validation");
+    let model = Model::from_model_config(&config.model, config.sample_rate_hz, config.duration_s)
+        .context("Failed to create model for dipole validation")?;
+    let spatial_description = &model.spatial_description;
+    let number_of_states = spatial_description.voxels.count_states();
+    let number_of_sensors = spatial_description.sensors.count();
+    let number_of_steps = model.functional_description.control_function_values.shape()[0];
+    anyhow::ensure!(
+        number_of_states <= number_of_sensors,
+        "Dipole validation needs at least as many sensors ({number_of_sensors}) as states ({number_of_states}), use a coarser grid"
+    );
+
+    let dipole_state = central_voxel_state(spatial_description)?;
+    let system_states =
+        rotating_dipole_states(config, number_of_steps, number_of_states, dipole_state);
+    let analytic_measurements =
+        analytic_measurements(spatial_description, &system_states, dipole_state)?;
+
+    let measurement_matrix = model.functional_description.measurement_matrix.at_beat(0);
+    let forward_measurements = system_states.dot(&measurement_matrix.t());
+    let forward_relative_error = relative_error(&forward_measurements, &analytic_measurements);
+
+    let mut data = Data::empty(
+        number_of_sensors,
+        number_of_states,
+        number_of_steps,
+        spatial_description.voxels.types.raw_dim(),
+        1,
+        config.model.common.neighborhood_radius,
+    );
+    data.simulation
+        .measurements
+        .at_beat_mut(0)
+        .assign(&analytic_measurements);
+    data.simulation.system_states.assign(&system_states);
+
+    let algorithm_config = Algorithm {
+        regularization_path: RegularizationPath {
+            number_of_lambdas: 0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut results = Results::new(
+        algorithm_config.epochs,
+        number_of_steps,
+        number_of_sensors,
+        number_of_states,
+        1,
+        0,
+        algorithm_config.batch_size,
+        algorithm_config.optimizer,
+        config.model.common.neighborhood_radius,
+    );
+    calculate_pseudo_inverse(
+        &model.functional_description,
+        &mut results,
+        &data,
+        &algorithm_config,
+    )
+    .context("Failed to calculate pseudo inverse for dipole validation")?;
+
+    let estimated_states = &*results.estimations.system_states;
+    let inverse_relative_error = relative_error(estimated_states, &system_states);
+    let localized = strongest_voxel_state(estimated_states) == dipole_state;
+
+    let report = DipoleValidationReport {
+        dipole_state,
+        number_of_states,
+        number_of_sensors,
+        forward_relative_error,
+        inverse_relative_error,
+        localized,
+        forward_tolerance: config.forward_tolerance,
+        inverse_tolerance: config.inverse_tolerance,
+    };
+    info!(
+        "Dipole validation: forward error {:.3e}, inverse error {:.3e}, localized {}",
+        report.forward_relative_error, report.inverse_relative_error, report.localized
+    );
+    Ok(report)
+}
+
+/// Returns the first state of the connectable voxel closest to the mean
+/// position of all connectable voxels.
+#[tracing::instrument(level = "debug", skip_all)]
+fn central_voxel_state(spatial_description: &SpatialDescription) -> Result<usize> {
+    let voxels = &spatial_description.voxels;
+    let connectable: Vec<_> = voxels
+        .types
+        .indexed_iter()
+        .filter(|(_, voxel_type)| voxel_type.is_connectable())
+        .map(|(index, _)| index)
+        .collect();
+    anyhow::ensure!(
+        !connectable.is_empty(),
+        "Model contains no connectable voxels"
+    );
+
+    let position = |(x, y, z): (usize, usize, usize)| voxels.positions_mm.slice(s![x, y, z, ..]);
+    #[allow(clippy::cast_precision_loss)]
+    let center = connectable
+        .iter()
+        .map(|index| position(*index).to_owned())
+        .reduce(|a, b| a + b)
+        .context("Model contains no connectable voxels")?
+        / connectable.len() as f32;
+
+    let closest = connectable
+        .iter()
+        .min_by(|a, b| {
+            let distance_a = (&position(**a) - &center).mapv(|v| v.powi(2)).sum();
+            let distance_b = (&position(**b) - &center).mapv(|v| v.powi(2)).sum();
+            distance_a.total_cmp(&distance_b)
+        })
+        .context("Model contains no connectable voxels")?;
+    voxels.numbers[*closest].context("Voxel number not initialized for connectable voxel")
+}
+
+/// Returns the system states of a dipole rotating in the x-y plane.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "debug", skip_all)]
+fn rotating_dipole_states(
+    config: &DipoleValidation,
+    number_of_steps: usize,
+    number_of_states: usize,
+    dipole_state: usize,
+) -> Array2<f32> {
+    let mut system_states = Array2::zeros((number_of_steps, number_of_states));
+    for step in 0..number_of_steps {
+        let angle = 2.0 * std::f32::consts::PI * config.rotation_frequency_hz * step as f32
+            / config.sample_rate_hz;
+        system_states[(step, dipole_state)] = config.current_density * angle.cos();
+        system_states[(step, dipole_state + 1)] = config.current_density * angle.sin();
+    }
+    system_states
+}
+
+/// Calculates the field of the current dipole at every sensor in pT.
+///
+/// Evaluates B = mu_0 / (4 pi) * (J V x r) / |r|^3 in double precision,
+/// independently of the measurement matrix.
+#[allow(clippy::cast_possible_truncation)]
+#[tracing::instrument(level = "debug", skip_all)]
+fn analytic_measurements(
+    spatial_description: &SpatialDescription,
+    system_states: &Array2<f32>,
+    dipole_state: usize,
+) -> Result<Array2<f32>> {
+    let voxels = &spatial_description.voxels;
+    let sensors = &spatial_description.sensors;
+    let dipole_index = voxels
+        .numbers
+        .indexed_iter()
+        .find(|(_, number)| **number == Some(dipole_state))
+        .map(|(index, _)| index)
+        .context("Dipole voxel not found in voxel numbers")?;
+    let dipole_position_m = voxels
+        .positions_mm
+        .slice(s![dipole_index.0, dipole_index.1, dipole_index.2, ..])
+        .mapv(|v| f64::from(v) / 1000.0);
+    let voxel_volume_m3 = (f64::from(voxels.size_mm) / 1000.0).powi(3);
+    let factor = VACUUM_MAG_PERMEABILITY / (4.0 * PI) * voxel_volume_m3 * 1e12;
+
+    let mut measurements = Array2::zeros((system_states.shape()[0], sensors.count()));
+    for sensor in 0..sensors.count() {
+        let position_m = (&sensors.positions_mm.slice(s![sensor, ..])
+            + &sensors.array_offsets_mm.slice(s![0, ..]))
+            .mapv(|v| f64::from(v) / 1000.0);
+        let r = &position_m - &dipole_position_m;
+        let distance_cubed = r.mapv(|v| v.powi(2)).sum().sqrt().powi(3);
+        let orientation = sensors
+            .orientations_xyz
+            .slice(s![sensor, ..])
+            .mapv(f64::from);
+
+        for (step, states) in system_states.outer_iter().enumerate() {
+            let j = [
+                f64::from(states[dipole_state]),
+                f64::from(states[dipole_state + 1]),
+                f64::from(states[dipole_state + 2]),
+            ];
+            let field = [
+                j[1].mul_add(r[2], -j[2] * r[1]),
+                j[2].mul_add(r[0], -j[0] * r[2]),
+                j[0].mul_add(r[1], -j[1] * r[0]),
+            ];
+            let projected = field[2].mul_add(
+                orientation[2],
+                field[0].mul_add(orientation[0], field[1] * orientation[1]),
+            );
+            measurements[(step, sensor)] = (factor * projected / distance_cubed) as f32;
+        }
+    }
+    Ok(measurements)
+}
+
+/// Returns the Frobenius norm of the difference relative to the reference.
+#[tracing::instrument(level = "trace", skip_all)]
+fn relative_error(actual: &Array2<f32>, reference: &Array2<f32>) -> f32 {
+    let difference = (actual - reference).mapv(|v| v.powi(2)).sum().sqrt();
+    let norm = reference.mapv(|v| v.powi(2)).sum().sqrt();
+    if norm > 0.0 {
+        difference / norm
+    } else {
+        difference
+    }
+}
+
+/// Returns the first state of the voxel with the largest summed current
+/// density magnitude over all steps.
+#[tracing::instrument(level = "trace", skip_all)]
+fn strongest_voxel_state(system_states: &Array2<f32>) -> usize {
+    (0..system_states.shape()[1])
+        .step_by(3)
+        .map(|state| {
+            let magnitude: f32 = system_states
+                .outer_iter()
+                .map(|states| {
+                    (states[state].powi(2) + states[state + 1].powi(2) + states[state + 2].powi(2))
+                        .sqrt()
+                })
+                .sum();
+            (state, magnitude)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(0, |(state, _)| state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_dipole_recovered() -> Result<()> {
+        let report = validate_single_dipole(&DipoleValidation::default())?;
+        assert!(
+            report.forward_relative_error <= report.forward_tolerance,
+            "forward model deviates from analytic field: {report:?}"
+        );
+        assert!(
+            report.inverse_relative_error <= report.inverse_tolerance,
+            "pseudo inverse does not recover dipole: {report:?}"
+        );
+        assert!(report.localized, "dipole not localized: {report:?}");
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 pub mod cutting_plane;
 pub mod heart;
+pub mod live;
 pub mod options;
 pub mod plotting;
 pub mod room;
@@ -26,8 +27,10 @@ use torso::update_torso_visibility;
 
 use self::{
     heart::{
-        init_voxels, on_color_mode_changed, update_heart_voxel_colors, MaterialAtlas, MeshAtlas,
+        init_voxels, on_color_mode_changed, update_heart_voxel_colors,
+        update_heart_voxel_colors_live, MaterialAtlas, MeshAtlas,
     },
+    live::{live_stream_active, receive_live_updates, LiveStream},
     options::ColorOptions,
     sample_tracker::{init_sample_tracker, update_sample_index, SampleTracker},
     sensors::spawn_sensors,
@@ -61,6 +64,7 @@ impl Plugin for VisPlugin {
             .init_resource::<ColorOptions>()
             .init_resource::<VisibilityOptions>()
             .init_resource::<BacketSettings>()
+            .init_resource::<LiveStream>()
             .add_event::<SetupHeartAndSensors>()
             .add_systems(
                 PreStartup,
@@ -96,9 +100,19 @@ impl Plugin for VisPlugin {
             )
             .add_systems(
                 Update,
-                (update_heart_voxel_colors, update_heart_voxel_visibility)
+                (
+                    update_heart_voxel_colors.run_if(not(live_stream_active)),
+                    update_heart_voxel_visibility,
+                )
                     .run_if(in_state(UiState::Volumetric))
                     .after(update_sample_index),
+            )
+            .add_systems(
+                Update,
+                (receive_live_updates, update_heart_voxel_colors_live)
+                    .chain()
+                    .run_if(in_state(UiState::Volumetric))
+                    .run_if(live_stream_active),
             );
     }
 }
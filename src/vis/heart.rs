@@ -11,6 +11,7 @@ use tracing::error;
 
 use super::{
     cutting_plane::CuttingPlaneSettings,
+    live::LiveStream,
     options::{ColorMode, ColorOptions, VisibilityOptions},
     sample_tracker::SampleTracker,
 };
@@ -218,6 +219,37 @@ pub(crate) fn update_heart_voxel_colors(
     });
 }
 
+/// Colors the voxels by the magnitude of the latest estimate of the live
+/// stream, normalized to the largest magnitude seen so far.
+#[allow(
+    clippy::needless_pass_by_value,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(skip_all, level = "trace")]
+pub(crate) fn update_heart_voxel_colors_live(
+    live: Res<LiveStream>,
+    materials: Res<MaterialAtlas>,
+    mut query: Query<(&mut MeshMaterial3d<StandardMaterial>, &VoxelData)>,
+) {
+    trace!("Running system to update heart voxel colors from live stream.");
+    let Some(update) = live.latest.as_ref() else {
+        return;
+    };
+    let scaling = if live.max_magnitude > 0.0 {
+        255.0 / live.max_magnitude
+    } else {
+        0.0
+    };
+    query.par_iter_mut().for_each(|(mut material, data)| {
+        let Some(magnitude) = update.magnitudes.get(data.index / 3) else {
+            return;
+        };
+        let index = ((magnitude * scaling) as usize).clamp(0, 255);
+        material.0 = materials.scalar[index].clone();
+    });
+}
+
 #[allow(clippy::needless_pass_by_value)]
 #[tracing::instrument(level = "trace", skip_all)]
 pub(crate) fn update_heart_voxel_visibility(
@@ -429,6 +461,12 @@ pub const fn type_to_color(voxel_type: VoxelType) -> Color {
             blue: 0.114,
             alpha,
         }),
+        VoxelType::BorderZone => Color::Srgba(Srgba {
+            red: 0.878,
+            green: 0.663,
+            blue: 0.337,
+            alpha,
+        }),
         VoxelType::Torso => Color::Srgba(Srgba {
             red: 0.63,
             green: 0.69,
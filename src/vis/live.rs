@@ -0,0 +1,131 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+use bevy::prelude::*;
+use tracing::error;
+
+use crate::core::{
+    algorithm::streaming::{run_stream, SampleSource, StreamUpdate, StreamingEstimator},
+    config::algorithm::Algorithm,
+    model::Model,
+};
+
+/// A running streaming estimation whose latest estimate colors the heart
+/// voxels instead of the recorded samples.
+#[derive(Resource, Default)]
+pub struct LiveStream {
+    receiver: Option<Mutex<Receiver<StreamUpdate>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<usize>>>,
+    pub latest: Option<StreamUpdate>,
+    // largest voxel magnitude seen so far, used to normalize the colors
+    pub max_magnitude: f32,
+}
+
+impl LiveStream {
+    /// Starts estimating the samples of the source on a separate thread,
+    /// stopping a previous stream first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the streaming estimator cannot be created.
+    #[tracing::instrument(level = "info", skip_all)]
+    pub fn start(
+        &mut self,
+        model: &Model,
+        config: &Algorithm,
+        mut source: Box<dyn SampleSource>,
+    ) -> Result<()> {
+        info!("Starting live stream.");
+        self.stop();
+        let mut estimator = StreamingEstimator::new(model, config)?;
+        let (update_tx, update_rx) = channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        self.handle = Some(thread::spawn(move || {
+            run_stream(&mut estimator, source.as_mut(), &update_tx, &thread_stop)
+        }));
+        self.receiver = Some(Mutex::new(update_rx));
+        self.stop = stop;
+        self.latest = None;
+        self.max_magnitude = 0.0;
+        Ok(())
+    }
+
+    /// Requests the stream to stop and waits for its thread to finish.
+    #[tracing::instrument(level = "info", skip_all)]
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.receiver = None;
+        self.join();
+    }
+
+    /// Whether a stream is running.
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
+    fn join(&mut self) {
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+        match handle.join() {
+            Ok(Ok(processed)) => info!("Live stream finished after {processed} samples."),
+            Ok(Err(e)) => error!("Live stream failed: {e:#}"),
+            Err(_) => error!("Live stream thread panicked"),
+        }
+    }
+}
+
+/// Keeps the most recent update of the live stream and cleans up once the
+/// stream has ended.
+#[tracing::instrument(level = "trace", skip_all)]
+pub(crate) fn receive_live_updates(mut live: ResMut<LiveStream>) {
+    trace!("Running system to receive live stream updates.");
+    let Some(receiver) = live.receiver.as_ref() else {
+        return;
+    };
+    let mut latest = None;
+    let mut finished = false;
+    match receiver.lock() {
+        Ok(receiver) => loop {
+            match receiver.try_recv() {
+                Ok(update) => latest = Some(update),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished = true;
+                    break;
+                }
+            }
+        },
+        Err(_) => {
+            error!("Live stream receiver is poisoned");
+            finished = true;
+        }
+    }
+    if let Some(update) = latest {
+        let max = update.magnitudes.iter().copied().fold(0.0, f32::max);
+        live.max_magnitude = live.max_magnitude.max(max);
+        live.latest = Some(update);
+    }
+    if finished {
+        live.receiver = None;
+        live.join();
+    }
+}
+
+/// Run condition that is true while a live stream is running.
+#[allow(clippy::needless_pass_by_value)]
+#[tracing::instrument(level = "trace", skip_all)]
+pub(crate) fn live_stream_active(live: Res<LiveStream>) -> bool {
+    live.is_active()
+}
@@ -1,7 +1,19 @@
 pub mod gif;
+#[cfg(test)]
+mod golden_tests;
 pub mod png;
 
-use plotters::style::RGBColor;
+use std::{path::Path, sync::OnceLock};
+
+use anyhow::Result;
+use plotters::{
+    chart::MeshStyle,
+    coord::ranged1d::{Ranged, ValueFormatter},
+    prelude::DrawingBackend,
+    style::{Color, IntoFont, RGBColor, TextStyle, BLACK, WHITE},
+};
+use scarlet::colormap::{ColorMap, ListedColorMap};
+use strum_macros::{Display, EnumIter};
 use tracing::trace;
 
 const STANDARD_RESOLUTION: (u32, u32) = (800, 600);
@@ -43,6 +55,27 @@ const COLORS: [RGBColor; 12] = [
     RGBColor(149, 144, 144), // Gray
 ];
 
+// background and foreground of dark plots, close to the dark EGUI panels
+const DARK_BACKGROUND: RGBColor = RGBColor(27, 27, 27);
+const DARK_FOREGROUND: RGBColor = RGBColor(220, 220, 220);
+const DARK_GRID_OPACITY: f64 = 0.3;
+const DARK_FINE_GRID_OPACITY: f64 = 0.1;
+
+// ColorBrewer RdBu, from red (low) over white to blue (high)
+const RDBU: [RGBColor; 11] = [
+    RGBColor(103, 0, 31),
+    RGBColor(178, 24, 43),
+    RGBColor(214, 96, 77),
+    RGBColor(244, 165, 130),
+    RGBColor(253, 219, 199),
+    RGBColor(247, 247, 247),
+    RGBColor(209, 229, 240),
+    RGBColor(146, 197, 222),
+    RGBColor(67, 147, 195),
+    RGBColor(33, 102, 172),
+    RGBColor(5, 48, 97),
+];
+
 /// Allocates a buffer for storing pixel data for an image of the given width and height.
 ///
 /// The buffer is allocated as a `Vec<u8>` with 3 bytes per pixel (for RGB color). The size of the
@@ -56,6 +89,196 @@ fn allocate_buffer(width: u32, height: u32) -> Vec<u8> {
     buffer
 }
 
+/// Saves a rendered RGB buffer as a PNG file.
+#[tracing::instrument(level = "trace", skip(buffer))]
+fn save_png(path: &Path, buffer: &[u8], width: u32, height: u32) -> Result<()> {
+    trace!("Saving plot as PNG.");
+    image::save_buffer_with_format(
+        path,
+        buffer,
+        width,
+        height,
+        image::ColorType::Rgb8,
+        image::ImageFormat::Png,
+    )?;
+    Ok(())
+}
+
+/// File format of saved plots.
+///
+/// The plotting functions pick the format from the extension of the given
+/// path. PNG is rasterized, SVG keeps the plot as vector graphics so it can
+/// be used in publications directly. The returned `PngBundle` is always
+/// rasterized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlotFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl PlotFormat {
+    /// Returns the format matching the extension of the path, falling back
+    /// to PNG for unknown extensions.
+    #[must_use]
+    #[tracing::instrument(level = "trace")]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("svg") => Self::Svg,
+            _ => Self::Png,
+        }
+    }
+
+    /// Returns the file extension of the format.
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+/// Color map used by the matrix plots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, EnumIter, Display)]
+pub enum PlotColorMap {
+    #[default]
+    Viridis,
+    Magma,
+    RdBu,
+}
+
+impl PlotColorMap {
+    /// Returns true if the color map is diverging. Diverging color maps are
+    /// centered at zero when no color limits are given.
+    #[must_use]
+    pub const fn is_diverging(self) -> bool {
+        matches!(self, Self::RdBu)
+    }
+
+    /// Maps a value between zero and one to a color, values outside are
+    /// clamped.
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    #[tracing::instrument(level = "trace")]
+    pub fn color(self, value: f32) -> RGBColor {
+        static VIRIDIS: OnceLock<ListedColorMap> = OnceLock::new();
+        static MAGMA: OnceLock<ListedColorMap> = OnceLock::new();
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let listed = |color_map: &ListedColorMap| {
+            let color: scarlet::color::RGBColor = color_map.transform_single(f64::from(value));
+            RGBColor(
+                (color.r * f64::from(u8::MAX)) as u8,
+                (color.g * f64::from(u8::MAX)) as u8,
+                (color.b * f64::from(u8::MAX)) as u8,
+            )
+        };
+        match self {
+            Self::Viridis => listed(VIRIDIS.get_or_init(ListedColorMap::viridis)),
+            Self::Magma => listed(MAGMA.get_or_init(ListedColorMap::magma)),
+            Self::RdBu => {
+                let position = value * (RDBU.len() - 1) as f32;
+                let index = (position.floor() as usize).min(RDBU.len() - 2);
+                let fraction = position - index as f32;
+                let (low, high) = (RDBU[index], RDBU[index + 1]);
+                let mix = |low: u8, high: u8| {
+                    fraction
+                        .mul_add(f32::from(high) - f32::from(low), f32::from(low))
+                        .round() as u8
+                };
+                RGBColor(mix(low.0, high.0), mix(low.1, high.1), mix(low.2, high.2))
+            }
+        }
+    }
+}
+
+/// Color theme of the generated plots.
+///
+/// The theme is passed to every plotting function, so the UI can generate its
+/// plots in its own theme while exported files stay light. Light plots are
+/// drawn black on white, dark plots light on dark with magma as default color
+/// map, since the dark end of viridis blends into a dark background.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlotTheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl PlotTheme {
+    /// Returns the background color of the plots.
+    #[must_use]
+    pub const fn background(self) -> RGBColor {
+        match self {
+            Self::Light => WHITE,
+            Self::Dark => DARK_BACKGROUND,
+        }
+    }
+
+    /// Returns the color of text, axes and outlines.
+    #[must_use]
+    pub const fn foreground(self) -> RGBColor {
+        match self {
+            Self::Light => BLACK,
+            Self::Dark => DARK_FOREGROUND,
+        }
+    }
+
+    /// Returns the color map used when a plot does not ask for a specific one.
+    #[must_use]
+    pub const fn color_map(self) -> PlotColorMap {
+        match self {
+            Self::Light => PlotColorMap::Viridis,
+            Self::Dark => PlotColorMap::Magma,
+        }
+    }
+}
+
+/// Returns the font of plot titles in the color of the given theme.
+#[tracing::instrument(level = "trace")]
+fn caption_font(theme: PlotTheme) -> TextStyle<'static> {
+    CAPTION_STYLE.into_font().color(&theme.foreground())
+}
+
+/// Returns the font of axis labels and legends in the color of the given
+/// theme.
+#[tracing::instrument(level = "trace")]
+fn axis_font(theme: PlotTheme) -> TextStyle<'static> {
+    AXIS_STYLE.into_font().color(&theme.foreground())
+}
+
+/// Applies the given theme to the axes and grid of a chart mesh.
+trait ThemedMesh {
+    fn themed(&mut self, theme: PlotTheme) -> &mut Self;
+}
+
+impl<X, Y, DB> ThemedMesh for MeshStyle<'_, '_, X, Y, DB>
+where
+    X: Ranged + ValueFormatter<X::ValueType>,
+    Y: Ranged + ValueFormatter<Y::ValueType>,
+    DB: DrawingBackend,
+{
+    #[tracing::instrument(level = "trace", skip(self))]
+    fn themed(&mut self, theme: PlotTheme) -> &mut Self {
+        if theme == PlotTheme::Light {
+            return self;
+        }
+        let foreground = theme.foreground();
+        self.axis_style(foreground)
+            .axis_desc_style(axis_font(theme))
+            .bold_line_style(foreground.mix(DARK_GRID_OPACITY))
+            .light_line_style(foreground.mix(DARK_FINE_GRID_OPACITY))
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum PlotSlice {
     X(usize),
@@ -1,4 +1,6 @@
+pub mod composite;
 pub mod matrix;
+pub mod quiver;
 pub mod states;
 pub mod voxel_type;
 
@@ -0,0 +1,361 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use anyhow::Result;
+use gif::{Encoder, Frame, Repeat};
+use ndarray::{s, Array1, ArrayBase, ArrayView1, ArrayView2, Data, Ix2};
+use ndarray_stats::QuantileExt;
+use plotters::{coord::Shift, prelude::*};
+use tracing::trace;
+
+use super::GifBundle;
+use crate::{
+    core::{
+        data::shapes::{SystemStatesSpherical, SystemStatesSphericalMax},
+        model::spatial::voxels::{VoxelNumbers, VoxelPositions},
+    },
+    vis::plotting::{
+        allocate_buffer, axis_font, caption_font,
+        gif::{DEFAULT_FPS, DEFAULT_PLAYBACK_SPEED},
+        png::{states::states_spherical_plot, PngBundle},
+        PlotSlice, PlotTheme, StateSphericalPlotMode, ThemedMesh, AXIS_LABEL_AREA, CHART_MARGIN,
+        COLORS, LEGEND_OPACITY, LEGEND_PATH_LENGTH,
+    },
+};
+
+const TRACE_HEIGHT: u32 = 300;
+
+/// The system states and measurements of either the simulation or the
+/// algorithm, shown side by side in the composite animation.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CompositePanel<'a> {
+    pub states: &'a SystemStatesSpherical,
+    pub states_max: &'a SystemStatesSphericalMax,
+    // shape (steps, sensors), a single beat
+    pub measurements: ArrayView2<'a, f32>,
+}
+
+/// Renders a composite frame for evenly spaced time steps and combines the
+/// frames into a GIF.
+///
+/// Every frame shows the states slice of the simulation and of the algorithm
+/// next to each other and below them the measurement trace of one sensor with
+/// a cursor at the current time. Both states slices share the same color
+/// range. If no sensor is given, the sensor with the largest simulated
+/// peak-to-peak amplitude is shown.
+#[allow(
+    clippy::too_many_arguments,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+#[tracing::instrument(level = "trace", skip(simulation, algorithm))]
+pub(crate) fn composite_plot_over_time(
+    simulation: CompositePanel<'_>,
+    algorithm: CompositePanel<'_>,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    sample_rate_hz: f32,
+    voxel_numbers: &VoxelNumbers,
+    path: Option<&Path>,
+    slice: Option<PlotSlice>,
+    sensor: Option<usize>,
+    playback_speed: Option<f32>,
+    fps: Option<u32>,
+    theme: PlotTheme,
+) -> Result<GifBundle> {
+    trace!("Generating composite plot over time");
+
+    let playback_speed = playback_speed.unwrap_or(DEFAULT_PLAYBACK_SPEED);
+    let fps = fps.unwrap_or(DEFAULT_FPS);
+
+    if playback_speed <= 0.0 {
+        return Err(anyhow::anyhow!("Playback speed must be greater than 0"));
+    }
+
+    if fps == 0 {
+        return Err(anyhow::anyhow!("FPS must be greater than 0"));
+    }
+
+    if sample_rate_hz <= 0.0 {
+        return Err(anyhow::anyhow!("Sample rate must be greater than 0"));
+    }
+
+    let number_of_sensors = simulation.measurements.shape()[1];
+    if algorithm.measurements.shape() != simulation.measurements.shape() {
+        return Err(anyhow::anyhow!(
+            "Simulation and algorithm measurements must have the same shape"
+        ));
+    }
+    let sensor = match sensor {
+        Some(sensor) if sensor >= number_of_sensors => {
+            return Err(anyhow::anyhow!(
+                "Sensor index {sensor} out of bounds for {number_of_sensors} sensors"
+            ));
+        }
+        Some(sensor) => sensor,
+        None => strongest_sensor(&simulation.measurements)?,
+    };
+    let simulation_trace = simulation.measurements.slice(s![.., sensor]);
+    let algorithm_trace = algorithm.measurements.slice(s![.., sensor]);
+
+    let sample_number = simulation.states.magnitude.shape()[0].min(simulation_trace.len());
+    let image_number = (fps as f32 / playback_speed) as usize;
+    let sample_step = (sample_number / image_number.max(1)).max(1);
+
+    let mut frames: Vec<Vec<u8>> = Vec::with_capacity(image_number);
+
+    let time_indices: Vec<usize> = (0..sample_number).step_by(sample_step).collect();
+
+    let mut width = 0;
+    let mut height = 0;
+
+    let range = Some((
+        0.0,
+        simulation
+            .states_max
+            .magnitude
+            .max_skipnan()
+            .max(*algorithm.states_max.magnitude.max_skipnan()),
+    ));
+
+    for time_index in time_indices {
+        let [simulation_frame, algorithm_frame] = [simulation, algorithm].map(|panel| {
+            states_spherical_plot(
+                panel.states,
+                panel.states_max,
+                voxel_positions_mm,
+                voxel_size_mm,
+                voxel_numbers,
+                None,
+                slice,
+                Some(StateSphericalPlotMode::ABS),
+                Some(time_index),
+                range,
+                None,
+                theme,
+            )
+        });
+        let (simulation_frame, algorithm_frame) = (simulation_frame?, algorithm_frame?);
+
+        width = simulation_frame.width + algorithm_frame.width;
+        let states_height = simulation_frame.height.max(algorithm_frame.height);
+        height = states_height + TRACE_HEIGHT;
+
+        let mut buffer = allocate_buffer(width, height);
+        let background = theme.background();
+        for pixel in buffer.chunks_exact_mut(3) {
+            pixel.copy_from_slice(&[background.0, background.1, background.2]);
+        }
+        blit(&mut buffer, width, &simulation_frame, 0);
+        blit(&mut buffer, width, &algorithm_frame, simulation_frame.width);
+
+        {
+            let root =
+                BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
+            let (_, trace_area) = root.split_vertically(states_height);
+            draw_trace(
+                trace_area,
+                &simulation_trace,
+                &algorithm_trace,
+                sample_rate_hz,
+                time_index,
+                &format!("Sensor {sensor}"),
+                theme,
+            )?;
+        }
+
+        frames.push(buffer);
+    }
+
+    if let Some(path) = path {
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut encoder = Encoder::new(&mut file, width as u16, height as u16, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for frame in &frames {
+            let mut frame = Frame::from_rgb(width as u16, height as u16, frame);
+            frame.delay = (100.0 / fps as f32) as u16;
+            encoder.write_frame(&frame)?;
+        }
+    }
+
+    Ok(GifBundle {
+        data: frames,
+        width,
+        height,
+        fps,
+    })
+}
+
+/// Returns the index of the sensor with the largest peak-to-peak amplitude.
+#[tracing::instrument(level = "trace", skip_all)]
+fn strongest_sensor<A>(measurements: &ArrayBase<A, Ix2>) -> Result<usize>
+where
+    A: Data<Elem = f32>,
+{
+    let amplitudes = measurements
+        .columns()
+        .into_iter()
+        .map(|trace| trace.max_skipnan() - trace.min_skipnan())
+        .collect::<Array1<f32>>();
+    Ok(amplitudes.argmax_skipnan()?)
+}
+
+/// Copies an RGB image into the top row of the canvas, starting at the
+/// given column.
+#[tracing::instrument(level = "trace", skip_all)]
+fn blit(canvas: &mut [u8], canvas_width: u32, image: &PngBundle, x_offset: u32) {
+    let row_length = image.width as usize * 3;
+    for (row, pixels) in image.data.chunks_exact(row_length).enumerate() {
+        let start = (row * canvas_width as usize + x_offset as usize) * 3;
+        canvas[start..start + row_length].copy_from_slice(pixels);
+    }
+}
+
+/// Draws the simulated and estimated measurement trace with a vertical
+/// cursor at the given time step.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip_all)]
+fn draw_trace<DB>(
+    root: DrawingArea<DB, Shift>,
+    simulation: &ArrayView1<f32>,
+    algorithm: &ArrayView1<f32>,
+    sample_rate_hz: f32,
+    time_index: usize,
+    title: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let time_ms = |index: usize| index as f32 / sample_rate_hz * 1000.0;
+    let y_min = simulation.min_skipnan().min(*algorithm.min_skipnan());
+    let y_max = simulation.max_skipnan().max(*algorithm.max_skipnan());
+    let y_margin = ((y_max - y_min) * 0.1).max(f32::EPSILON);
+    let (y_min, y_max) = (y_min - y_margin, y_max + y_margin);
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(
+            0.0..time_ms(simulation.len().saturating_sub(1)),
+            y_min..y_max,
+        )?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .x_desc("t [ms]")
+        .x_label_style(axis_font(theme))
+        .y_desc("Measurement [a.u.]")
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    for (i, (trace, label)) in [(simulation, "Simulation"), (algorithm, "Algorithm")]
+        .into_iter()
+        .enumerate()
+    {
+        let color = &COLORS[i % COLORS.len()];
+        chart
+            .draw_series(LineSeries::new(
+                trace
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| (time_ms(index), *value)),
+                color,
+            ))?
+            .label(label)
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
+            });
+    }
+
+    let cursor = time_ms(time_index);
+    chart.draw_series(std::iter::once(PathElement::new(
+        vec![(cursor, y_min), (cursor, y_max)],
+        theme.foreground().stroke_width(2),
+    )))?;
+
+    chart
+        .configure_series_labels()
+        .background_style(theme.background().mix(LEGEND_OPACITY))
+        .border_style(theme.foreground())
+        .label_font(axis_font(theme))
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::*;
+    use crate::{
+        core::{config::simulation::Simulation as SimulationConfig, data::Data},
+        tests::{clean_files, setup_folder},
+    };
+
+    const COMMON_PATH: &str = "tests/vis/plotting/gif/composite";
+
+    #[test]
+    fn test_strongest_sensor() -> Result<()> {
+        let mut measurements = ndarray::Array2::<f32>::zeros((10, 3));
+        measurements[(2, 1)] = 1.0;
+        measurements[(4, 2)] = -3.0;
+        measurements[(5, 2)] = 0.5;
+
+        assert_eq!(strongest_sensor(&measurements)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "expensive integration test"]
+    fn test_composite_default() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("composite_default.gif")];
+        clean_files(&files)?;
+
+        let mut simulation_config = SimulationConfig::default();
+        simulation_config.model.common.pathological = true;
+        let data = Data::from_simulation_config(&simulation_config)
+            .context("Failed to create simulation data for composite GIF test")?;
+        let panel = CompositePanel {
+            states: &data.simulation.system_states_spherical,
+            states_max: &data.simulation.system_states_spherical_max,
+            measurements: data.simulation.measurements.slice(s![0, .., ..]),
+        };
+
+        composite_plot_over_time(
+            panel,
+            panel,
+            &data
+                .simulation
+                .model
+                .spatial_description
+                .voxels
+                .positions_mm,
+            data.simulation.model.spatial_description.voxels.size_mm,
+            simulation_config.sample_rate_hz,
+            &data.simulation.model.spatial_description.voxels.numbers,
+            Some(files[0].as_path()),
+            Some(PlotSlice::Z(0)),
+            None,
+            Some(0.2),
+            Some(10),
+            PlotTheme::Light,
+        )
+        .context("Failed to generate composite GIF for test")?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+}
@@ -6,7 +6,7 @@ use ndarray_stats::QuantileExt;
 use tracing::trace;
 
 use super::GifBundle;
-use crate::vis::plotting::{gif::_DEFAULT_TIME_PER_FRAME_MS, png::matrix::matrix_plot};
+use crate::vis::plotting::{gif::_DEFAULT_TIME_PER_FRAME_MS, png::matrix::matrix_plot, PlotTheme};
 
 #[allow(
     clippy::too_many_arguments,
@@ -30,6 +30,7 @@ pub(crate) fn matrix_over_slices_plot<A>(
     resolution: Option<(u32, u32)>,
     flip_axis: Option<(bool, bool)>,
     time_per_frame_ms: Option<u32>,
+    theme: PlotTheme,
 ) -> anyhow::Result<GifBundle>
 where
     A: ndarray::Data<Elem = f32>,
@@ -80,6 +81,7 @@ where
         let frame = matrix_plot(
             &data.index_axis(axis, slice),
             Some(range),
+            None,
             step,
             offset,
             None,
@@ -89,6 +91,7 @@ where
             unit,
             resolution,
             flip_axis,
+            theme,
         )?;
         frames.push(frame.data);
 
@@ -134,7 +137,20 @@ mod test {
     fn test_matrix_over_slices_plot_valid_input() {
         let data = Array3::<f32>::zeros((10, 10, 10));
         let result = matrix_over_slices_plot(
-            &data, None, None, None, None, None, None, None, None, None, None, None, None,
+            &data,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PlotTheme::Light,
         );
         assert!(result.is_ok());
     }
@@ -156,6 +172,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         );
         assert!(result.is_err());
     }
@@ -177,6 +194,7 @@ mod test {
             None,
             None,
             Some(0),
+            PlotTheme::Light,
         );
         assert!(result.is_err());
     }
@@ -211,6 +229,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
         assert!(!result.data.is_empty());
         Ok(())
@@ -0,0 +1,163 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use gif::{Encoder, Frame, Repeat};
+use tracing::trace;
+
+use super::GifBundle;
+use crate::{
+    core::{
+        data::shapes::SystemStates,
+        model::spatial::voxels::{VoxelNumbers, VoxelPositions},
+    },
+    vis::plotting::{
+        gif::{DEFAULT_FPS, DEFAULT_PLAYBACK_SPEED},
+        png::quiver::{max_current_density, states_quiver_plot},
+        PlotSlice, PlotTheme,
+    },
+};
+
+/// Renders the current density quiver plot of a slice for evenly spaced
+/// time steps and combines the frames into a GIF.
+///
+/// All frames share the arrow scale of the largest current density, so
+/// arrow lengths are comparable over time.
+#[allow(
+    clippy::too_many_arguments,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+#[tracing::instrument(level = "trace")]
+pub(crate) fn states_quiver_plot_over_time(
+    states: &SystemStates,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    sample_rate_hz: f32,
+    voxel_numbers: &VoxelNumbers,
+    path: Option<&Path>,
+    slice: Option<PlotSlice>,
+    show_magnitude: bool,
+    playback_speed: Option<f32>,
+    fps: Option<u32>,
+    theme: PlotTheme,
+) -> anyhow::Result<GifBundle> {
+    trace!("Generating quiver plot over time");
+
+    let playback_speed = playback_speed.unwrap_or(DEFAULT_PLAYBACK_SPEED);
+    let fps = fps.unwrap_or(DEFAULT_FPS);
+
+    if playback_speed <= 0.0 {
+        return Err(anyhow::anyhow!("Playback speed must be greater than 0"));
+    }
+
+    if fps == 0 {
+        return Err(anyhow::anyhow!("FPS must be greater than 0"));
+    }
+
+    if sample_rate_hz <= 0.0 {
+        return Err(anyhow::anyhow!("Sample rate must be greater than 0"));
+    }
+
+    let sample_number = states.shape()[0];
+    let image_number = (fps as f32 / playback_speed) as usize;
+    let sample_step = (sample_number / image_number.max(1)).max(1);
+
+    let mut frames: Vec<Vec<u8>> = Vec::with_capacity(image_number);
+
+    let time_indices: Vec<usize> = (0..sample_number).step_by(sample_step).collect();
+
+    let mut width = 0;
+    let mut height = 0;
+
+    let max_magnitude = Some(max_current_density(states));
+
+    for time_index in time_indices {
+        let frame = states_quiver_plot(
+            states,
+            voxel_positions_mm,
+            voxel_size_mm,
+            voxel_numbers,
+            None,
+            slice,
+            time_index,
+            max_magnitude,
+            show_magnitude,
+            theme,
+        )?;
+        frames.push(frame.data);
+
+        width = frame.width;
+        height = frame.height;
+    }
+
+    if let Some(path) = path {
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut encoder = Encoder::new(&mut file, width as u16, height as u16, &[])?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        for frame in &frames {
+            let mut frame = Frame::from_rgb(width as u16, height as u16, frame);
+            frame.delay = (100.0 / fps as f32) as u16;
+            encoder.write_frame(&frame)?;
+        }
+    }
+
+    Ok(GifBundle {
+        data: frames,
+        width,
+        height,
+        fps,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use anyhow::Context;
+
+    use super::*;
+    use crate::{
+        core::{config::simulation::Simulation as SimulationConfig, data::Data},
+        tests::{clean_files, setup_folder},
+    };
+
+    const COMMON_PATH: &str = "tests/vis/plotting/gif/quiver";
+
+    #[test]
+    #[ignore = "expensive integration test"]
+    fn test_states_quiver_default() -> anyhow::Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("quiver_default.gif")];
+        clean_files(&files)?;
+
+        let mut simulation_config = SimulationConfig::default();
+        simulation_config.model.common.pathological = true;
+        let data = Data::from_simulation_config(&simulation_config)
+            .context("Failed to create simulation data for GIF quiver test")?;
+
+        states_quiver_plot_over_time(
+            &data.simulation.system_states,
+            &data
+                .simulation
+                .model
+                .spatial_description
+                .voxels
+                .positions_mm,
+            data.simulation.model.spatial_description.voxels.size_mm,
+            simulation_config.sample_rate_hz,
+            &data.simulation.model.spatial_description.voxels.numbers,
+            Some(files[0].as_path()),
+            Some(PlotSlice::Z(0)),
+            true,
+            Some(0.2),
+            Some(10),
+            PlotTheme::Light,
+        )
+        .context("Failed to generate quiver GIF for test")?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+}
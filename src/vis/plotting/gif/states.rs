@@ -13,7 +13,7 @@ use crate::{
     vis::plotting::{
         gif::{DEFAULT_FPS, DEFAULT_PLAYBACK_SPEED},
         png::states::states_spherical_plot,
-        PlotSlice, StateSphericalPlotMode,
+        PlotSlice, PlotTheme, StateSphericalPlotMode,
     },
 };
 
@@ -36,6 +36,7 @@ pub(crate) fn states_spherical_plot_over_time(
     mode: Option<StateSphericalPlotMode>,
     playback_speed: Option<f32>,
     fps: Option<u32>,
+    theme: PlotTheme,
 ) -> anyhow::Result<GifBundle> {
     trace!("Generating spherixal state plot over time");
 
@@ -82,6 +83,8 @@ pub(crate) fn states_spherical_plot_over_time(
             mode,
             Some(time_index),
             range,
+            None,
+            theme,
         )?;
         frames.push(frame.data);
 
@@ -154,6 +157,7 @@ mod test {
             Some(StateSphericalPlotMode::ABS),
             Some(0.2),
             Some(10),
+            PlotTheme::Light,
         )
         .context("Failed to generate spherical states GIF for test")?;
 
@@ -192,6 +196,7 @@ mod test {
             Some(StateSphericalPlotMode::ANGLE),
             Some(0.2),
             Some(10),
+            PlotTheme::Light,
         )
         .context("Failed to generate spherical states angle GIF for test")?;
 
@@ -7,7 +7,9 @@ use tracing::trace;
 use super::GifBundle;
 use crate::{
     core::model::spatial::voxels::{VoxelPositions, VoxelTypes},
-    vis::plotting::{gif::_DEFAULT_TIME_PER_FRAME_MS, png::voxel_type::voxel_type_plot, PlotSlice},
+    vis::plotting::{
+        gif::_DEFAULT_TIME_PER_FRAME_MS, png::voxel_type::voxel_type_plot, PlotSlice, PlotTheme,
+    },
 };
 
 #[allow(
@@ -25,6 +27,7 @@ pub(crate) fn voxel_types_over_slices_plot(
     axis: Option<Axis>,
     path: Option<&Path>,
     time_per_frame_ms: Option<u32>,
+    theme: PlotTheme,
 ) -> anyhow::Result<GifBundle>
 where
 {
@@ -56,7 +59,7 @@ where
             Axis(2) => Some(PlotSlice::Z(slice)),
             _ => unreachable!(),
         };
-        let frame = voxel_type_plot(types, voxel_positions_mm, voxel_size_mm, None, slice)?;
+        let frame = voxel_type_plot(types, voxel_positions_mm, voxel_size_mm, None, slice, theme)?;
         frames.push(frame.data);
 
         width = frame.width;
@@ -0,0 +1,176 @@
+//! Regression tests comparing the main plot types to golden images, so that
+//! changes to the layout, color maps or color bars are noticed.
+//!
+//! Intentional changes are accepted by regenerating the golden images with
+//! `just bless-plots` and committing them.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ndarray::{Array1, Array2};
+
+use super::{
+    png::{
+        bland_altman::bland_altman_plot,
+        line::{line_plot, log_y_plot},
+        matrix::matrix_plot,
+        velocity::velocity_box_plot,
+        PngBundle,
+    },
+    PlotColorMap, PlotTheme,
+};
+use crate::{
+    core::{
+        algorithm::metrics::{
+            activation_time::ActivationTimeStatistics, velocity::VelocityStatistics,
+        },
+        model::spatial::voxels::VoxelType,
+    },
+    tests::{
+        golden::{assert_matches_golden, GoldenTolerance},
+        setup_folder,
+    },
+};
+
+const COMMON_PATH: &str = "tests/vis/plotting/golden";
+
+fn assert_golden(name: &str, bundle: &PngBundle) -> Result<()> {
+    assert_matches_golden(
+        name,
+        &bundle.data,
+        bundle.width,
+        bundle.height,
+        GoldenTolerance::default(),
+    )
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn ramp(rows: usize, columns: usize) -> Array2<f32> {
+    Array2::from_shape_fn((rows, columns), |(row, column)| {
+        (row as f32 - 4.0) * 0.5 + column as f32 * 0.25
+    })
+}
+
+#[test]
+fn line_plot_matches_golden() -> Result<()> {
+    let x = Array1::linspace(0.0, 10.0, 100);
+    let y = x.map(|x| x * x);
+    let z = x.map(|x| 50.0 * x.sin());
+    let labels = vec!["x^2", "50 sin(x)"];
+    let bundle = line_plot(
+        Some(&x),
+        vec![&y, &z],
+        None,
+        Some("Line plot"),
+        Some("y [a.u.]"),
+        Some("x [a.u.]"),
+        Some(&labels),
+        None,
+        PlotTheme::Light,
+    )?;
+
+    assert_golden("line_plot", &bundle)
+}
+
+#[test]
+fn log_y_plot_matches_golden() -> Result<()> {
+    let x = Array1::linspace(1.0, 100.0, 100);
+    let y = x.map(|x| (-x / 20.0_f32).exp());
+    let bundle = log_y_plot(
+        Some(&x),
+        vec![&y],
+        None,
+        Some("Log plot"),
+        Some("Loss [a.u.]"),
+        Some("Epoch"),
+        None,
+        None,
+        PlotTheme::Light,
+    )?;
+
+    assert_golden("log_y_plot", &bundle)
+}
+
+#[test]
+fn matrix_plot_matches_golden() -> Result<()> {
+    for (name, color_map) in [
+        ("matrix_plot_viridis", PlotColorMap::Viridis),
+        ("matrix_plot_rdbu", PlotColorMap::RdBu),
+    ] {
+        let bundle = matrix_plot(
+            &ramp(9, 12),
+            None,
+            Some(color_map),
+            Some((2.5, 2.5)),
+            None,
+            None,
+            Some("Matrix plot"),
+            Some("y [mm]"),
+            Some("x [mm]"),
+            Some("[a.u.]"),
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
+
+        assert_golden(name, &bundle)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn velocity_box_plot_matches_golden() -> Result<()> {
+    setup_folder(COMMON_PATH)?;
+    let statistics = [
+        (VoxelType::Atrium, 1.0),
+        (VoxelType::Ventricle, 0.6),
+        (VoxelType::Pathological, 0.2),
+    ]
+    .map(|(voxel_type, median)| VelocityStatistics {
+        voxel_type,
+        count: 10,
+        mean_m_per_s: median,
+        std_m_per_s: 0.1,
+        min_m_per_s: median - 0.3,
+        lower_quartile_m_per_s: median - 0.1,
+        median_m_per_s: median,
+        upper_quartile_m_per_s: median + 0.1,
+        max_m_per_s: median + 0.3,
+    });
+    let bundle = velocity_box_plot(
+        &statistics,
+        &Path::new(COMMON_PATH).join("velocity_box_plot.png"),
+        "Velocities",
+        PlotTheme::Light,
+    )?;
+
+    assert_golden("velocity_box_plot", &bundle)
+}
+
+#[test]
+#[allow(clippy::cast_precision_loss)]
+fn bland_altman_plot_matches_golden() -> Result<()> {
+    setup_folder(COMMON_PATH)?;
+    let pairs: Vec<(f32, f32)> = (0..20)
+        .map(|index| {
+            let reference = index as f32 * 5.0;
+            (reference, reference + (index % 5) as f32 - 2.0)
+        })
+        .collect();
+    let errors: Vec<f32> = pairs
+        .iter()
+        .map(|(reference, estimate)| estimate - reference)
+        .collect();
+    let statistics = ActivationTimeStatistics::from_errors(&errors)
+        .context("Statistics should exist for non-empty errors")?;
+    let bundle = bland_altman_plot(
+        &pairs,
+        &statistics,
+        &Path::new(COMMON_PATH).join("bland_altman_plot.png"),
+        "Activation time",
+        "ms",
+        PlotTheme::Light,
+    )?;
+
+    assert_golden("bland_altman_plot", &bundle)
+}
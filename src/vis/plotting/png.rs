@@ -1,9 +1,15 @@
 pub mod activation_time;
+pub mod bland_altman;
 pub mod delay;
+pub mod isochrone;
 pub mod line;
 pub mod matrix;
 pub mod propagation_speed;
+pub mod quiver;
+pub mod residual;
 pub mod states;
+pub mod uncertainty;
+pub mod velocity;
 pub mod voxel_type;
 
 #[allow(clippy::module_name_repetitions)]
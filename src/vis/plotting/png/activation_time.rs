@@ -1,17 +1,88 @@
 use std::path::Path;
 
 use anyhow::Result;
-use ndarray::Axis;
+use ndarray::{Array2, Axis};
 use tracing::trace;
 
 use super::PngBundle;
 use crate::{
     core::model::{functional::allpass::shapes::ActivationTimeMs, spatial::voxels::VoxelPositions},
-    vis::plotting::{png::matrix::matrix_plot, PlotSlice},
+    vis::plotting::{png::matrix::matrix_plot, PlotColorMap, PlotSlice, PlotTheme},
 };
 
+/// An axis-aligned slice of the activation times together with the
+/// placement of the slice in the plot.
+#[derive(Debug)]
+pub(crate) struct ActivationTimeSlice {
+    pub data: Array2<Option<f32>>,
+    pub offset: (f32, f32),
+    // e.g. "z-index = 0, z = 12 mm"
+    pub location: String,
+    pub x_label: &'static str,
+    pub y_label: &'static str,
+    pub flip_axis: (bool, bool),
+}
+
+impl ActivationTimeSlice {
+    /// Extracts the given slice (x, y or z) of the activation time matrix.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub(crate) fn new(
+        activation_time_ms: &ActivationTimeMs,
+        voxel_positions_mm: &VoxelPositions,
+        slice: PlotSlice,
+    ) -> Self {
+        trace!("Extracting activation time slice");
+        match slice {
+            PlotSlice::X(index) => {
+                let x = voxel_positions_mm[(index, 0, 0, 0)];
+                Self {
+                    data: activation_time_ms.index_axis(Axis(0), index).to_owned(),
+                    offset: (
+                        voxel_positions_mm[(0, 0, 0, 1)],
+                        voxel_positions_mm[(0, 0, 0, 2)],
+                    ),
+                    location: format!("x-index = {index}, x = {x} mm"),
+                    x_label: "y [mm]",
+                    y_label: "z [mm]",
+                    flip_axis: (true, false),
+                }
+            }
+            PlotSlice::Y(index) => {
+                let y = voxel_positions_mm[(0, index, 0, 1)];
+                Self {
+                    data: activation_time_ms.index_axis(Axis(1), index).to_owned(),
+                    offset: (
+                        voxel_positions_mm[(0, 0, 0, 0)],
+                        voxel_positions_mm[(0, 0, 0, 2)],
+                    ),
+                    location: format!("y-index = {index}, y = {y} mm"),
+                    x_label: "x [mm]",
+                    y_label: "z [mm]",
+                    flip_axis: (false, false),
+                }
+            }
+            PlotSlice::Z(index) => {
+                let z = voxel_positions_mm[(0, 0, index, 2)];
+                Self {
+                    data: activation_time_ms.index_axis(Axis(2), index).to_owned(),
+                    offset: (
+                        voxel_positions_mm[(0, 0, 0, 0)],
+                        voxel_positions_mm[(0, 0, 0, 1)],
+                    ),
+                    location: format!("z-index = {index}, z = {z} mm"),
+                    x_label: "x [mm]",
+                    y_label: "y [mm]",
+                    flip_axis: (false, false),
+                }
+            }
+        }
+    }
+}
+
 /// Plots the activation time for a given slice (x, y or z) of the
 /// activation time matrix.
+///
+/// Without a range the colors are scaled to the data.
 #[tracing::instrument(level = "trace")]
 pub(crate) fn activation_time_plot(
     activation_time_ms: &ActivationTimeMs,
@@ -19,74 +90,33 @@ pub(crate) fn activation_time_plot(
     voxel_size_mm: f32,
     path: &Path,
     slice: Option<PlotSlice>,
+    range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    theme: PlotTheme,
 ) -> Result<PngBundle> {
     trace!("Generating activation time plot");
-    let slice = slice.unwrap_or(PlotSlice::Z(0));
-    let step = Some((voxel_size_mm, voxel_size_mm));
-
-    let (data, offset, title, x_label, y_label, flip_axis) = match slice {
-        PlotSlice::X(index) => {
-            let data = activation_time_ms
-                .index_axis(Axis(0), index)
-                .map(|value| value.unwrap_or(0.0));
-            let offset = Some((
-                voxel_positions_mm[(0, 0, 0, 1)],
-                voxel_positions_mm[(0, 0, 0, 2)],
-            ));
-            let x = voxel_positions_mm[(index, 0, 0, 0)];
-            let title = format!("Activation time x-index = {index}, x = {x} mm");
-            let x_label = Some("y [mm]");
-            let y_label = Some("z [mm]");
-            let flip_axis = Some((true, false));
-
-            (data, offset, title, x_label, y_label, flip_axis)
-        }
-        PlotSlice::Y(index) => {
-            let data = activation_time_ms
-                .index_axis(Axis(1), index)
-                .map(|value| value.unwrap_or(0.0));
-            let offset = Some((
-                voxel_positions_mm[(0, 0, 0, 0)],
-                voxel_positions_mm[(0, 0, 0, 2)],
-            ));
-            let y = voxel_positions_mm[(0, index, 0, 1)];
-            let title = format!("Activation time y-index = {index}, y = {y} mm");
-            let x_label = Some("x [mm]");
-            let y_label = Some("z [mm]");
-            let flip_axis = Some((false, false));
-
-            (data, offset, title, x_label, y_label, flip_axis)
-        }
-        PlotSlice::Z(index) => {
-            let data = activation_time_ms
-                .index_axis(Axis(2), index)
-                .map(|value| value.unwrap_or(0.0));
-            let offset = Some((
-                voxel_positions_mm[(0, 0, 0, 0)],
-                voxel_positions_mm[(0, 0, 0, 1)],
-            ));
-            let z = voxel_positions_mm[(0, 0, index, 2)];
-            let title = format!("Activation time z-index = {index}, z = {z} mm");
-            let x_label = Some("x [mm]");
-            let y_label = Some("y [mm]");
-            let flip_axis = Some((false, false));
-
-            (data, offset, title, x_label, y_label, flip_axis)
-        }
-    };
+    let slice = ActivationTimeSlice::new(
+        activation_time_ms,
+        voxel_positions_mm,
+        slice.unwrap_or(PlotSlice::Z(0)),
+    );
+    let data = slice.data.map(|value| value.unwrap_or(0.0));
+    let title = format!("Activation time {}", slice.location);
 
     matrix_plot(
         &data,
-        None,
-        step,
-        offset,
+        range,
+        color_map,
+        Some((voxel_size_mm, voxel_size_mm)),
+        Some(slice.offset),
         Some(path),
         Some(title.as_str()),
-        y_label,
-        x_label,
+        Some(slice.y_label),
+        Some(slice.x_label),
         Some("[ms]"),
         None,
-        flip_axis,
+        Some(slice.flip_axis),
+        theme,
     )
 }
 
@@ -128,6 +158,9 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             files[0].as_path(),
             Some(PlotSlice::Z(0)),
+            None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -162,6 +195,9 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             files[0].as_path(),
             Some(PlotSlice::X(10)),
+            None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -196,6 +232,9 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             files[0].as_path(),
             Some(PlotSlice::Y(5)),
+            None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
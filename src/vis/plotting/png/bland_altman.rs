@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use anyhow::Result;
+use plotters::{coord::Shift, prelude::*};
+use tracing::trace;
+
+use super::PngBundle;
+use crate::{
+    core::algorithm::metrics::activation_time::ActivationTimeStatistics,
+    vis::plotting::{
+        allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotTheme, ThemedMesh,
+        AXIS_LABEL_AREA, CHART_MARGIN, COLORS, LEGEND_OPACITY, LEGEND_PATH_LENGTH,
+        STANDARD_RESOLUTION,
+    },
+};
+
+const POINT_SIZE: i32 = 2;
+const POINT_OPACITY: f64 = 0.5;
+
+/// Plots a Bland-Altman scatter of the given (reference, estimate) pairs.
+///
+/// Every pair is drawn at its mean over its difference (estimate minus
+/// reference), together with the bias and the 95 % limits of agreement
+/// from the statistics. The plot is saved as PNG or SVG depending on the
+/// file extension.
+#[tracing::instrument(level = "trace", skip(pairs))]
+pub(crate) fn bland_altman_plot(
+    pairs: &[(f32, f32)],
+    statistics: &ActivationTimeStatistics,
+    path: &Path,
+    title: &str,
+    unit: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating Bland-Altman plot");
+    if pairs.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Bland-Altman plot requires at least one pair"
+        ));
+    }
+    let points: Vec<(f32, f32)> = pairs
+        .iter()
+        .map(|(reference, estimate)| ((reference + estimate) / 2.0, estimate - reference))
+        .collect();
+
+    let (width, height) = STANDARD_RESOLUTION;
+
+    let mut buffer = allocate_buffer(width, height);
+    draw_bland_altman(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        &points,
+        statistics,
+        title,
+        unit,
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height)?,
+        PlotFormat::Svg => draw_bland_altman(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            &points,
+            statistics,
+            title,
+            unit,
+            theme,
+        )?,
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws the Bland-Altman scatter on the given drawing area, see
+/// [`bland_altman_plot`].
+#[tracing::instrument(level = "trace", skip(root, points))]
+fn draw_bland_altman<DB>(
+    root: DrawingArea<DB, Shift>,
+    points: &[(f32, f32)],
+    statistics: &ActivationTimeStatistics,
+    title: &str,
+    unit: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (x_min, x_max, y_min, y_max) = points.iter().fold(
+        (
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            statistics.lower_limit_of_agreement_ms,
+            statistics.upper_limit_of_agreement_ms,
+        ),
+        |(x_min, x_max, y_min, y_max), (x, y)| {
+            (x_min.min(*x), x_max.max(*x), y_min.min(*y), y_max.max(*y))
+        },
+    );
+    let x_margin = ((x_max - x_min) * 0.05).max(1.0);
+    let y_margin = ((y_max - y_min) * 0.1).max(1.0);
+    let x_range = (x_min - x_margin)..(x_max + x_margin);
+    let y_range = (y_min - y_margin)..(y_max + y_margin);
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range.clone(), y_range)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .x_desc(format!("Mean of simulation and estimation {unit}"))
+        .x_label_style(axis_font(theme))
+        .y_desc(format!("Estimation - simulation {unit}"))
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    let point_color = COLORS[0].mix(POINT_OPACITY);
+    chart.draw_series(
+        points
+            .iter()
+            .map(|point| Circle::new(*point, POINT_SIZE, point_color.filled())),
+    )?;
+
+    let lines = [
+        (
+            statistics.bias_ms,
+            format!("Bias {:.2}", statistics.bias_ms),
+        ),
+        (
+            statistics.upper_limit_of_agreement_ms,
+            format!("+1.96 SD {:.2}", statistics.upper_limit_of_agreement_ms),
+        ),
+        (
+            statistics.lower_limit_of_agreement_ms,
+            format!("-1.96 SD {:.2}", statistics.lower_limit_of_agreement_ms),
+        ),
+    ];
+    for (i, (y, label)) in lines.into_iter().enumerate() {
+        let color = &COLORS[(i + 1) % COLORS.len()];
+        chart
+            .draw_series(LineSeries::new(
+                [(x_range.start, y), (x_range.end, y)],
+                color.stroke_width(2),
+            ))?
+            .label(label)
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
+            });
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(theme.background().mix(LEGEND_OPACITY))
+        .border_style(theme.foreground())
+        .label_font(axis_font(theme))
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tests::{clean_files, setup_folder};
+
+    const COMMON_PATH: &str = "tests/vis/plotting/png/bland_altman";
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_bland_altman_plot() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("bland_altman_plot.png")];
+        clean_files(&files)?;
+
+        let pairs: Vec<(f32, f32)> = (0..100)
+            .map(|i| {
+                let reference = i as f32;
+                (
+                    reference,
+                    reference + (i as f32 * 0.7).sin().mul_add(3.0, 1.0),
+                )
+            })
+            .collect();
+        let errors: Vec<f32> = pairs.iter().map(|(r, e)| e - r).collect();
+        let statistics = ActivationTimeStatistics::from_errors(&errors)
+            .ok_or_else(|| anyhow::anyhow!("Expected statistics for non-empty errors"))?;
+
+        bland_altman_plot(
+            &pairs,
+            &statistics,
+            files[0].as_path(),
+            "Activation time agreement",
+            "[ms]",
+            PlotTheme::Light,
+        )?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+}
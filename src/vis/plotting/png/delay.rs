@@ -10,11 +10,12 @@ use crate::{
         algorithm::refinement::derivation::AverageDelays,
         model::spatial::voxels::{VoxelNumbers, VoxelPositions},
     },
-    vis::plotting::{png::matrix::matrix_plot, PlotSlice},
+    vis::plotting::{png::matrix::matrix_plot, PlotColorMap, PlotSlice, PlotTheme},
 };
 
 /// Plots the activation time for a given slice (x, y or z) of the
 /// activation time matrix.
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(level = "trace")]
 pub(crate) fn average_delay_plot(
     average_delays: &AverageDelays,
@@ -24,6 +25,9 @@ pub(crate) fn average_delay_plot(
     path: &Path,
     max_delay_displayed_samples: Option<f32>,
     slice: Option<PlotSlice>,
+    range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    theme: PlotTheme,
 ) -> anyhow::Result<PngBundle> {
     trace!("Generating activation time plot");
     let slice = slice.unwrap_or(PlotSlice::Z(0));
@@ -91,7 +95,8 @@ pub(crate) fn average_delay_plot(
 
     matrix_plot(
         &data,
-        None,
+        range,
+        color_map,
         step,
         offset,
         Some(path),
@@ -101,6 +106,7 @@ pub(crate) fn average_delay_plot(
         Some("[samples]"),
         None,
         flip_axis,
+        theme,
     )
     .context("Failed to generate delay matrix plot")
 }
@@ -150,6 +156,9 @@ mod test {
             files[0].as_path(),
             Some(10.0),
             Some(PlotSlice::Z(0)),
+            None,
+            None,
+            PlotTheme::Light,
         )
         .context("Failed to generate average delay plot for test")?;
 
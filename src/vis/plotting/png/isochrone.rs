@@ -0,0 +1,428 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use ndarray::Array2;
+use plotters::{coord::Shift, prelude::*};
+use scarlet::colormap::{ColorMap, ListedColorMap};
+use tracing::trace;
+
+use super::{activation_time::ActivationTimeSlice, PngBundle};
+use crate::{
+    core::model::{functional::allpass::shapes::ActivationTimeMs, spatial::voxels::VoxelPositions},
+    vis::plotting::{
+        allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotSlice, PlotTheme,
+        ThemedMesh, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, CAPTION_STYLE, CHART_MARGIN,
+        LEGEND_OPACITY, LEGEND_PATH_LENGTH, STANDARD_RESOLUTION,
+    },
+};
+
+/// Time between two isochrones if not configured otherwise.
+pub const DEFAULT_ISOCHRONE_INTERVAL_MS: f32 = 10.0;
+
+/// Part of an isochrone within a single grid cell, in grid coordinates.
+pub(crate) type Segment = ((f32, f32), (f32, f32));
+
+/// An isochrone of a slice, made of unconnected segments.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Isochrone {
+    pub time_ms: f32,
+    pub segments: Vec<Segment>,
+}
+
+/// Calculates the isochrones of a slice of activation times every
+/// `interval_ms` using marching squares.
+///
+/// The activation times lie on the grid points, cells with a voxel that is
+/// never activated are skipped. Saddle cells are resolved with the mean of
+/// their corners. Only isochrones with at least one segment are returned.
+///
+/// # Errors
+///
+/// Returns an error if the interval is not positive.
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+#[tracing::instrument(level = "trace", skip(activation_time_ms))]
+pub(crate) fn isochrones(
+    activation_time_ms: &Array2<Option<f32>>,
+    interval_ms: f32,
+) -> Result<Vec<Isochrone>> {
+    trace!("Calculating isochrones");
+    if interval_ms <= 0.0 || !interval_ms.is_finite() {
+        bail!("Isochrone interval must be positive, got {interval_ms} ms");
+    }
+    let Some(max_ms) = activation_time_ms
+        .iter()
+        .flatten()
+        .copied()
+        .reduce(f32::max)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let (dim_x, dim_y) = activation_time_ms.dim();
+    let mut result = Vec::new();
+    let mut time_ms = interval_ms;
+    while time_ms <= max_ms {
+        let mut segments = Vec::new();
+        for x in 0..dim_x.saturating_sub(1) {
+            for y in 0..dim_y.saturating_sub(1) {
+                let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+                let Some(values) = corners
+                    .iter()
+                    .map(|corner| activation_time_ms[*corner])
+                    .collect::<Option<Vec<f32>>>()
+                else {
+                    continue;
+                };
+                segments.extend(cell_segments(&corners, &values, time_ms));
+            }
+        }
+        if !segments.is_empty() {
+            result.push(Isochrone { time_ms, segments });
+        }
+        time_ms += interval_ms;
+    }
+    Ok(result)
+}
+
+/// Returns the segments of the isochrone at `time_ms` within a single cell.
+///
+/// The corners are given counter-clockwise, edge `i` connects corner `i`
+/// with the next one.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace")]
+fn cell_segments(corners: &[(usize, usize); 4], values: &[f32], time_ms: f32) -> Vec<Segment> {
+    let activated = values
+        .iter()
+        .map(|value| *value >= time_ms)
+        .collect::<Vec<bool>>();
+    let crossing = |edge: usize| {
+        let (start, end) = (edge, (edge + 1) % 4);
+        let fraction = (time_ms - values[start]) / (values[end] - values[start]);
+        let (x_start, y_start) = (corners[start].0 as f32, corners[start].1 as f32);
+        let (x_end, y_end) = (corners[end].0 as f32, corners[end].1 as f32);
+        (
+            fraction.mul_add(x_end - x_start, x_start),
+            fraction.mul_add(y_end - y_start, y_start),
+        )
+    };
+    let edges = (0..4)
+        .filter(|edge| activated[*edge] != activated[(edge + 1) % 4])
+        .collect::<Vec<usize>>();
+    match edges.as_slice() {
+        [first, second] => vec![(crossing(*first), crossing(*second))],
+        [_, _, _, _] => {
+            let center_activated = values.iter().sum::<f32>() / 4.0 >= time_ms;
+            if center_activated == activated[0] {
+                // corners 0 and 2 are connected through the center
+                vec![(crossing(0), crossing(1)), (crossing(2), crossing(3))]
+            } else {
+                vec![(crossing(3), crossing(0)), (crossing(1), crossing(2))]
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Plots the activation time isochrones of a slice every `interval_ms`.
+///
+/// # Errors
+///
+/// Returns an error if the interval is not positive or the plot cannot be
+/// drawn or saved.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn activation_time_isochrone_plot(
+    activation_time_ms: &ActivationTimeMs,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    path: &Path,
+    slice: Option<PlotSlice>,
+    interval_ms: f32,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating activation time isochrone plot");
+    let slice = ActivationTimeSlice::new(
+        activation_time_ms,
+        voxel_positions_mm,
+        slice.unwrap_or(PlotSlice::Z(0)),
+    );
+    let title = format!("Isochrones every {interval_ms} ms {}", slice.location);
+    isochrone_plot(
+        &[(&slice.data, false)],
+        &slice,
+        voxel_size_mm,
+        path,
+        &title,
+        interval_ms,
+        theme,
+    )
+}
+
+/// Plots the isochrones of the simulation (solid) and the algorithm
+/// (dashed) in one figure, using the same color for the same time.
+///
+/// # Errors
+///
+/// Returns an error if the interval is not positive or the plot cannot be
+/// drawn or saved.
+#[tracing::instrument(level = "trace")]
+pub(crate) fn activation_time_isochrone_overlay_plot(
+    simulation_ms: &ActivationTimeMs,
+    algorithm_ms: &ActivationTimeMs,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    path: &Path,
+    slice: Option<PlotSlice>,
+    interval_ms: f32,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating activation time isochrone overlay plot");
+    let slice = slice.unwrap_or(PlotSlice::Z(0));
+    let simulation = ActivationTimeSlice::new(simulation_ms, voxel_positions_mm, slice);
+    let algorithm = ActivationTimeSlice::new(algorithm_ms, voxel_positions_mm, slice);
+    let title = format!(
+        "Isochrones every {interval_ms} ms {} (solid: simulation, dashed: algorithm)",
+        simulation.location
+    );
+    isochrone_plot(
+        &[(&simulation.data, false), (&algorithm.data, true)],
+        &simulation,
+        voxel_size_mm,
+        path,
+        &title,
+        interval_ms,
+        theme,
+    )
+}
+
+/// Draws the isochrones of several activation time slices of the same
+/// geometry. Every layer is drawn solid or dashed, the levels are colored
+/// with the viridis color map over the times of all layers.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::too_many_lines
+)]
+#[tracing::instrument(level = "trace", skip(layers, geometry))]
+fn isochrone_plot(
+    layers: &[(&Array2<Option<f32>>, bool)],
+    geometry: &ActivationTimeSlice,
+    voxel_size_mm: f32,
+    path: &Path,
+    title: &str,
+    interval_ms: f32,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    if voxel_size_mm <= 0.0 {
+        bail!("Voxel size must be greater than zero");
+    }
+    let layers = layers
+        .iter()
+        .map(|(data, dashed)| Ok((isochrones(data, interval_ms)?, *dashed)))
+        .collect::<Result<Vec<_>>>()?;
+    let max_time_ms = layers
+        .iter()
+        .flat_map(|(isochrones, _)| isochrones.iter().map(|isochrone| isochrone.time_ms))
+        .fold(interval_ms, f32::max);
+
+    let (dim_x, dim_y) = geometry.data.dim();
+    let ratio = ((dim_x as f32) / (dim_y.max(1) as f32)).clamp(0.1, 10.0);
+    let (width, height) = if ratio > 1.0 {
+        (
+            STANDARD_RESOLUTION.0 + AXIS_LABEL_AREA + CHART_MARGIN,
+            (STANDARD_RESOLUTION.0 as f32 / ratio) as u32
+                + AXIS_LABEL_AREA
+                + CHART_MARGIN
+                + 2 * CAPTION_STYLE.1 as u32,
+        )
+    } else {
+        (
+            (STANDARD_RESOLUTION.0 as f32 * ratio) as u32 + AXIS_LABEL_AREA + CHART_MARGIN,
+            STANDARD_RESOLUTION.0 + AXIS_LABEL_AREA + CHART_MARGIN + 2 * CAPTION_STYLE.1 as u32,
+        )
+    };
+    let mut buffer = allocate_buffer(width, height);
+    draw_isochrones(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        &layers,
+        geometry,
+        voxel_size_mm,
+        title,
+        max_time_ms,
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height)?,
+        PlotFormat::Svg => draw_isochrones(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            &layers,
+            geometry,
+            voxel_size_mm,
+            title,
+            max_time_ms,
+            theme,
+        )?,
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws the isochrones on the given drawing area, see [`isochrone_plot`].
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::too_many_lines
+)]
+#[tracing::instrument(level = "trace", skip(root, layers, geometry))]
+fn draw_isochrones<DB>(
+    root: DrawingArea<DB, Shift>,
+    layers: &[(Vec<Isochrone>, bool)],
+    geometry: &ActivationTimeSlice,
+    voxel_size_mm: f32,
+    title: &str,
+    max_time_ms: f32,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (dim_x, dim_y) = geometry.data.dim();
+    let (x_offset, y_offset) = geometry.offset;
+    let (flip_x, flip_y) = geometry.flip_axis;
+    let x_min = x_offset - voxel_size_mm / 2.0;
+    let x_max = (dim_x as f32).mul_add(voxel_size_mm, x_min);
+    let y_min = y_offset - voxel_size_mm / 2.0;
+    let y_max = (dim_y as f32).mul_add(voxel_size_mm, y_min);
+    let x_range = if flip_x { x_max..x_min } else { x_min..x_max };
+    let y_range = if flip_y { y_max..y_min } else { y_min..y_max };
+
+    let color_map = ListedColorMap::viridis();
+    let color = |time_ms: f32| {
+        let color: scarlet::color::RGBColor =
+            color_map.transform_single(f64::from(time_ms / max_time_ms));
+        RGBColor(
+            (color.r * f64::from(u8::MAX)) as u8,
+            (color.g * f64::from(u8::MAX)) as u8,
+            (color.b * f64::from(u8::MAX)) as u8,
+        )
+    };
+    let to_mm = |(x, y): (f32, f32)| {
+        (
+            x.mul_add(voxel_size_mm, x_offset),
+            y.mul_add(voxel_size_mm, y_offset),
+        )
+    };
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(geometry.x_label)
+        .x_label_style(axis_font(theme))
+        .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
+        .y_desc(geometry.y_label)
+        .y_label_style(axis_font(theme))
+        .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
+        .draw()?;
+
+    let mut labeled = Vec::new();
+    for (isochrones, dashed) in layers {
+        for isochrone in isochrones {
+            let color = color(isochrone.time_ms);
+            let style = color.stroke_width(2);
+            let series = chart.draw_series(isochrone.segments.iter().map(|(start, end)| {
+                let (start, end) = (to_mm(*start), to_mm(*end));
+                if *dashed {
+                    // only the middle half of every segment is drawn,
+                    // since the segments span a single voxel this
+                    // yields dashes of about half the voxel size
+                    let quarter = ((end.0 - start.0) / 4.0, (end.1 - start.1) / 4.0);
+                    PathElement::new(
+                        vec![
+                            (start.0 + quarter.0, start.1 + quarter.1),
+                            (end.0 - quarter.0, end.1 - quarter.1),
+                        ],
+                        style,
+                    )
+                } else {
+                    PathElement::new(vec![start, end], style)
+                }
+            }))?;
+            // every time is listed once in the legend
+            if !labeled.contains(&isochrone.time_ms.to_bits()) {
+                labeled.push(isochrone.time_ms.to_bits());
+                series
+                    .label(format!("{} ms", isochrone.time_ms))
+                    .legend(move |(x, y)| {
+                        PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
+                    });
+            }
+        }
+    }
+
+    if !labeled.is_empty() {
+        chart
+            .configure_series_labels()
+            .background_style(theme.background().mix(LEGEND_OPACITY))
+            .border_style(theme.foreground())
+            .label_font(axis_font(theme))
+            .draw()?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+
+    #[test]
+    fn isochrones_follow_linear_activation() -> Result<()> {
+        // activation spreads along the first axis with 10 ms per voxel
+        let activation_time_ms = arr2(&[
+            [Some(0.0), Some(0.0)],
+            [Some(10.0), Some(10.0)],
+            [Some(20.0), None],
+        ]);
+
+        let isochrones = isochrones(&activation_time_ms, 5.0)?;
+
+        // the cell with the missing voxel contributes nothing
+        assert_eq!(isochrones.len(), 1);
+        assert_relative_eq!(isochrones[0].time_ms, 5.0);
+        assert_eq!(isochrones[0].segments.len(), 1);
+        let ((x_start, _), (x_end, _)) = isochrones[0].segments[0];
+        assert_relative_eq!(x_start, 0.5);
+        assert_relative_eq!(x_end, 0.5);
+        assert!(super::isochrones(&activation_time_ms, 0.0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn saddle_cells_produce_two_segments() {
+        let corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        let segments = cell_segments(&corners, &[10.0, 0.0, 10.0, 0.0], 4.0);
+        assert_eq!(segments.len(), 2);
+    }
+}
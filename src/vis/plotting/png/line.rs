@@ -1,24 +1,33 @@
 use std::{io, path::Path};
 
 use anyhow::Result;
-use ndarray::{s, Array1, ArrayBase, Data, Ix1};
+use ndarray::{s, Array1, ArrayBase, Axis, Data, Ix1, Ix2};
 use ndarray_stats::QuantileExt;
-use plotters::prelude::*;
+use plotters::{coord::Shift, prelude::*};
 use tracing::trace;
 
 use super::PngBundle;
 use crate::{
     core::data::shapes::SystemStates,
     vis::plotting::{
-        allocate_buffer, AXIS_LABEL_AREA, AXIS_STYLE, CAPTION_STYLE, CHART_MARGIN, COLORS,
-        LEGEND_OPACITY, LEGEND_PATH_LENGTH, STANDARD_RESOLUTION, X_MARGIN, Y_MARGIN,
+        allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotTheme, ThemedMesh,
+        AXIS_LABEL_AREA, CHART_MARGIN, COLORS, LEGEND_OPACITY, LEGEND_PATH_LENGTH,
+        STANDARD_RESOLUTION, X_MARGIN, Y_MARGIN,
     },
 };
 
+const SMALL_MULTIPLE_RESOLUTION: (u32, u32) = (240, 160);
+const SMALL_MULTIPLE_TITLE_HEIGHT: u32 = 50;
+const SMALL_MULTIPLE_MARGIN: u32 = 5;
+const SMALL_MULTIPLE_STYLE: (&str, i32) = ("Arial", 14);
+
+const BUTTERFLY_CHANNEL_OPACITY: f64 = 0.5;
+const BUTTERFLY_RMS_WIDTH: u32 = 3;
+
 /// Generates an XY plot from the provided x and y data.
 ///
-/// Saves the plot to the optionally provided path as a PNG,
-/// returns the raw pixel buffer.
+/// Saves the plot to the optionally provided path as a PNG or SVG,
+/// depending on the extension of the path, returns the raw pixel buffer.
 #[allow(clippy::cast_precision_loss, clippy::too_many_arguments)]
 #[tracing::instrument(level = "trace")]
 pub fn line_plot<A>(
@@ -30,6 +39,7 @@ pub fn line_plot<A>(
     x_label: Option<&str>,
     item_labels: Option<&Vec<&str>>,
     resolution: Option<(u32, u32)>,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: Data<Elem = f32>,
@@ -77,86 +87,31 @@ where
     let y_label = y_label.unwrap_or("y");
     let x_label = x_label.unwrap_or("x");
 
-    let x_min = x.min()?;
-    let x_max = x.max()?;
-    let mut y_min = f32::INFINITY;
-    let mut y_max = -f32::INFINITY;
-
-    for y in &ys {
-        let min = y.min()?;
-        let max = y.max()?;
-        y_min = y_min.min(*min);
-        y_max = y_max.max(*max);
-    }
-
-    let x_range = x_max - x_min;
-    let y_range = y_max - y_min;
-
-    let x_min = x_min - x_range * X_MARGIN;
-    let x_max = x_max + x_range * X_MARGIN;
-    let y_min = y_range.mul_add(-Y_MARGIN, y_min);
-    let y_max = y_range.mul_add(Y_MARGIN, y_max);
-
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
-        root.fill(&WHITE)?;
-
-        let mut chart = ChartBuilder::on(&root)
-            .caption(title, CAPTION_STYLE.into_font())
-            .margin(CHART_MARGIN)
-            .x_label_area_size(AXIS_LABEL_AREA)
-            .y_label_area_size(AXIS_LABEL_AREA)
-            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
-
-        chart
-            .configure_mesh()
-            .x_desc(x_label)
-            .x_label_style(AXIS_STYLE.into_font())
-            .y_desc(y_label)
-            .y_label_style(AXIS_STYLE.into_font())
-            .draw()?;
-
-        for (i, y) in ys.iter().enumerate() {
-            let color = &COLORS[i % COLORS.len()];
-            if let Some(item_labels) = item_labels {
-                chart
-                    .draw_series(LineSeries::new(
-                        x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
-                        color,
-                    ))?
-                    .label(item_labels[i])
-                    .legend(move |(x, y)| {
-                        PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
-                    });
-            } else {
-                chart.draw_series(LineSeries::new(
-                    x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
-                    color,
-                ))?;
-            }
-        }
-
-        if item_labels.is_some() {
-            chart
-                .configure_series_labels()
-                .background_style(WHITE.mix(LEGEND_OPACITY))
-                .border_style(BLACK)
-                .label_font(AXIS_STYLE.into_font())
-                .draw()?;
-        }
-
-        root.present()?;
-    } // dropping bitmap backend
+    draw_line(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        x,
+        &ys,
+        title,
+        y_label,
+        x_label,
+        item_labels,
+        theme,
+    )?;
 
     if let Some(path) = path {
-        image::save_buffer_with_format(
-            path,
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-            image::ImageFormat::Png,
-        )?;
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_line(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                x,
+                &ys,
+                title,
+                y_label,
+                x_label,
+                item_labels,
+                theme,
+            )?,
+        }
     }
 
     Ok(PngBundle {
@@ -177,6 +132,7 @@ pub fn log_y_plot<A>(
     x_label: Option<&str>,
     item_labels: Option<&Vec<&str>>,
     resolution: Option<(u32, u32)>,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: Data<Elem = f32>,
@@ -224,86 +180,31 @@ where
     let y_label = y_label.unwrap_or("y");
     let x_label = x_label.unwrap_or("x");
 
-    let x_min = x.min()?;
-    let x_max = x.max()?;
-    let mut y_min = f32::INFINITY;
-    let mut y_max = -f32::INFINITY;
-
-    for y in &ys {
-        let min = y.min()?;
-        let max = y.max()?;
-        y_min = y_min.min(*min);
-        y_max = y_max.max(*max);
-    }
-
-    let x_range = x_max - x_min;
-
-    let x_min = x_min - x_range * X_MARGIN;
-    let x_max = x_max + x_range * X_MARGIN;
-    let y_min = (y_min * 0.1).max(1e-20);
-    let y_max = y_max * 10.0;
-
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
-        root.fill(&WHITE)?;
-
-        let mut chart = ChartBuilder::on(&root)
-            .caption(title, CAPTION_STYLE.into_font())
-            .margin(CHART_MARGIN)
-            .x_label_area_size(AXIS_LABEL_AREA)
-            .y_label_area_size(AXIS_LABEL_AREA)
-            .build_cartesian_2d(x_min..x_max, (y_min..y_max).log_scale())?;
-
-        chart
-            .configure_mesh()
-            .x_desc(x_label)
-            .x_label_style(AXIS_STYLE.into_font())
-            .y_desc(y_label)
-            .y_label_style(AXIS_STYLE.into_font())
-            .y_label_formatter(&|y| format!("{y:e}"))
-            .draw()?;
-
-        for (i, y) in ys.iter().enumerate() {
-            let color = &COLORS[i % COLORS.len()];
-            if let Some(item_labels) = item_labels {
-                chart
-                    .draw_series(LineSeries::new(
-                        x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
-                        color,
-                    ))?
-                    .label(item_labels[i])
-                    .legend(move |(x, y)| {
-                        PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
-                    });
-            } else {
-                chart.draw_series(LineSeries::new(
-                    x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
-                    color,
-                ))?;
-            }
-        }
-
-        if item_labels.is_some() {
-            chart
-                .configure_series_labels()
-                .background_style(WHITE.mix(LEGEND_OPACITY))
-                .border_style(BLACK)
-                .label_font(AXIS_STYLE.into_font())
-                .draw()?;
-        }
-
-        root.present()?;
-    } // dropping bitmap backend
+    draw_log_y(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        x,
+        &ys,
+        title,
+        y_label,
+        x_label,
+        item_labels,
+        theme,
+    )?;
 
     if let Some(path) = path {
-        image::save_buffer_with_format(
-            path,
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-            image::ImageFormat::Png,
-        )?;
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_log_y(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                x,
+                &ys,
+                title,
+                y_label,
+                x_label,
+                item_labels,
+                theme,
+            )?,
+        }
     }
 
     Ok(PngBundle {
@@ -316,7 +217,7 @@ where
 /// Generates a standard y plot from the provided y values.
 ///
 /// Plots the y values against their index. Saves the plot to the provided path
-/// as a PNG image. Applies the provided title, axis labels, etc.
+/// as a PNG or SVG image. Applies the provided title, axis labels, etc.
 ///
 /// Returns the plot data as a `Vec<u8>`, or an error if the plot could not be
 /// generated.
@@ -327,6 +228,7 @@ pub fn standard_y_plot<A>(
     title: &str,
     y_label: &str,
     x_label: &str,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: Data<Elem = f32>,
@@ -341,6 +243,7 @@ where
         Some(x_label),
         None,
         None,
+        theme,
     )
 }
 
@@ -351,6 +254,7 @@ pub fn standard_log_y_plot<A>(
     title: &str,
     y_label: &str,
     x_label: &str,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: Data<Elem = f32>,
@@ -365,13 +269,14 @@ where
         Some(x_label),
         None,
         None,
+        theme,
     )
 }
 
 /// Generates a standard time plot from the provided y values and sample rate.
 ///
 /// Plots the y values against time in seconds based on the provided sample rate.
-/// Saves the plot to the provided path as a PNG image. Applies the provided
+/// Saves the plot to the provided path as a PNG or SVG image. Applies the provided
 /// title and axis labels.
 ///
 /// Returns the plot data as a `Vec<u8>`, or an error if the plot could not be
@@ -384,6 +289,7 @@ pub fn standard_time_plot<A>(
     path: &Path,
     title: &str,
     y_label: &str,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: Data<Elem = f32>,
@@ -406,15 +312,191 @@ where
         Some("t [s]"),
         None,
         None,
+        theme,
     )
 }
 
+/// Generates a grid of small time plots, one panel per column of `ys`.
+///
+/// `ys` is expected to have shape (steps, channels), e.g. all sensors of a
+/// single beat. Every panel shares the time axis derived from the sample rate
+/// and is labeled with the provided channel label, or "Sensor" followed by the
+/// channel index if no labels are given. Saves the plot to the provided path
+/// as a PNG or SVG image.
+///
+/// Returns the plot data, or an error if the plot could not be generated.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace", skip(ys))]
+pub fn small_multiples_time_plot<A>(
+    ys: &ArrayBase<A, Ix2>,
+    sample_rate_hz: f32,
+    path: &Path,
+    title: &str,
+    channel_labels: Option<&[&str]>,
+    theme: PlotTheme,
+) -> Result<PngBundle>
+where
+    A: Data<Elem = f32>,
+{
+    trace!("Generating small multiples time plot.");
+    if sample_rate_hz <= 0.0 {
+        return Err(std::io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "sample_rate_hz must be greater than zero",
+        )
+        .into());
+    }
+    let number_of_steps = ys.shape()[0];
+    let number_of_channels = ys.shape()[1];
+    if number_of_steps == 0 || number_of_channels == 0 {
+        return Err(std::io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ys must contain at least one step and one channel",
+        )
+        .into());
+    }
+    if let Some(channel_labels) = channel_labels {
+        if channel_labels.len() != number_of_channels {
+            return Err(std::io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "if not None, channel_labels must have one entry per channel",
+            )
+            .into());
+        }
+    }
+
+    let columns = (number_of_channels as f32).sqrt().ceil() as usize;
+    let rows = number_of_channels.div_ceil(columns);
+    let width = SMALL_MULTIPLE_RESOLUTION.0 * columns as u32;
+    let height = SMALL_MULTIPLE_RESOLUTION.1 * rows as u32 + SMALL_MULTIPLE_TITLE_HEIGHT;
+
+    let mut buffer = allocate_buffer(width, height);
+
+    draw_small_multiples(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        ys,
+        sample_rate_hz,
+        title,
+        channel_labels,
+        (rows, columns),
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height)?,
+        PlotFormat::Svg => draw_small_multiples(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            ys,
+            sample_rate_hz,
+            title,
+            channel_labels,
+            (rows, columns),
+            theme,
+        )?,
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Calculates the root mean square over all channels for every step.
+///
+/// `ys` is expected to have shape (steps, channels).
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(ys))]
+pub fn rms_over_channels<A>(ys: &ArrayBase<A, Ix2>) -> Array1<f32>
+where
+    A: Data<Elem = f32>,
+{
+    trace!("Calculating rms over channels.");
+    let number_of_channels = ys.shape()[1].max(1) as f32;
+    ys.map_axis(Axis(1), |channels| {
+        (channels.iter().map(|y| y * y).sum::<f32>() / number_of_channels).sqrt()
+    })
+}
+
+/// Generates a butterfly plot of all channels in `ys` with an RMS envelope.
+///
+/// `ys` is expected to have shape (steps, channels), e.g. all sensors of a
+/// single beat. Every channel is drawn as a thin gray trace, the positive and
+/// negative RMS over channels are drawn on top. Saves the plot to the provided
+/// path as a PNG or SVG image.
+///
+/// Returns the plot data, or an error if the plot could not be generated.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(ys))]
+pub fn measurement_butterfly_plot<A>(
+    ys: &ArrayBase<A, Ix2>,
+    sample_rate_hz: f32,
+    path: &Path,
+    title: &str,
+    y_label: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle>
+where
+    A: Data<Elem = f32>,
+{
+    trace!("Generating butterfly plot.");
+    if sample_rate_hz <= 0.0 {
+        return Err(std::io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "sample_rate_hz must be greater than zero",
+        )
+        .into());
+    }
+    let number_of_steps = ys.shape()[0];
+    if number_of_steps == 0 || ys.shape()[1] == 0 {
+        return Err(std::io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ys must contain at least one step and one channel",
+        )
+        .into());
+    }
+
+    let (width, height) = STANDARD_RESOLUTION;
+    let mut buffer = allocate_buffer(width, height);
+
+    draw_measurement_butterfly(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        ys,
+        sample_rate_hz,
+        title,
+        y_label,
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height)?,
+        PlotFormat::Svg => draw_measurement_butterfly(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            ys,
+            sample_rate_hz,
+            title,
+            y_label,
+            theme,
+        )?,
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
 /// Generates a plot of the x, y, and z values for a specific state index from
 /// the provided system state data.
 ///
 /// Plots the x, y, and z values for the state at the given index against time
 /// in seconds based on the provided sample rate. Saves the plot to the provided
-/// path as a PNG image. Applies the provided title and axis labels.
+/// path as a PNG or SVG image. Applies the provided title and axis labels.
 ///
 /// `system_states` - The system state data to extract values from.
 /// `state_index` - The index of the state to plot.
@@ -432,6 +514,7 @@ pub fn plot_state_xyz(
     sample_rate_hz: f32,
     path: &Path,
     title: &str,
+    theme: PlotTheme,
 ) -> Result<PngBundle> {
     trace!("Generating state xyz plot.");
 
@@ -457,9 +540,347 @@ pub fn plot_state_xyz(
         Some("t [s]"),
         Some(&labels),
         None,
+        theme,
     )
 }
 
+/// Draws a line plot on the given drawing area, see [`line_plot`].
+#[tracing::instrument(level = "trace", skip(root, x, ys))]
+fn draw_line<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    x: &Array1<f32>,
+    ys: &[&ArrayBase<A, Ix1>],
+    title: &str,
+    y_label: &str,
+    x_label: &str,
+    item_labels: Option<&Vec<&str>>,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: Data<Elem = f32>,
+{
+    let x_min = x.min()?;
+    let x_max = x.max()?;
+    let mut y_min = f32::INFINITY;
+    let mut y_max = -f32::INFINITY;
+
+    for y in ys {
+        let min = y.min()?;
+        let max = y.max()?;
+        y_min = y_min.min(*min);
+        y_max = y_max.max(*max);
+    }
+
+    let x_range = x_max - x_min;
+    let y_range = y_max - y_min;
+
+    let x_min = x_min - x_range * X_MARGIN;
+    let x_max = x_max + x_range * X_MARGIN;
+    let y_min = y_range.mul_add(-Y_MARGIN, y_min);
+    let y_max = y_range.mul_add(Y_MARGIN, y_max);
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    for (i, y) in ys.iter().enumerate() {
+        let color = &COLORS[i % COLORS.len()];
+        if let Some(item_labels) = item_labels {
+            chart
+                .draw_series(LineSeries::new(
+                    x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+                    color,
+                ))?
+                .label(item_labels[i])
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
+                });
+        } else {
+            chart.draw_series(LineSeries::new(
+                x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+                color,
+            ))?;
+        }
+    }
+
+    if item_labels.is_some() {
+        chart
+            .configure_series_labels()
+            .background_style(theme.background().mix(LEGEND_OPACITY))
+            .border_style(theme.foreground())
+            .label_font(axis_font(theme))
+            .draw()?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Draws a line plot with logarithmic y-axis on the given drawing area, see [`log_y_plot`].
+#[tracing::instrument(level = "trace", skip(root, x, ys))]
+fn draw_log_y<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    x: &Array1<f32>,
+    ys: &[&ArrayBase<A, Ix1>],
+    title: &str,
+    y_label: &str,
+    x_label: &str,
+    item_labels: Option<&Vec<&str>>,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: Data<Elem = f32>,
+{
+    let x_min = x.min()?;
+    let x_max = x.max()?;
+    let mut y_min = f32::INFINITY;
+    let mut y_max = -f32::INFINITY;
+
+    for y in ys {
+        let min = y.min()?;
+        let max = y.max()?;
+        y_min = y_min.min(*min);
+        y_max = y_max.max(*max);
+    }
+
+    let x_range = x_max - x_min;
+
+    let x_min = x_min - x_range * X_MARGIN;
+    let x_max = x_max + x_range * X_MARGIN;
+    let y_min = (y_min * 0.1).max(1e-20);
+    let y_max = y_max * 10.0;
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_min..x_max, (y_min..y_max).log_scale())?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .y_label_formatter(&|y| format!("{y:e}"))
+        .draw()?;
+
+    for (i, y) in ys.iter().enumerate() {
+        let color = &COLORS[i % COLORS.len()];
+        if let Some(item_labels) = item_labels {
+            chart
+                .draw_series(LineSeries::new(
+                    x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+                    color,
+                ))?
+                .label(item_labels[i])
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], color)
+                });
+        } else {
+            chart.draw_series(LineSeries::new(
+                x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+                color,
+            ))?;
+        }
+    }
+
+    if item_labels.is_some() {
+        chart
+            .configure_series_labels()
+            .background_style(theme.background().mix(LEGEND_OPACITY))
+            .border_style(theme.foreground())
+            .label_font(axis_font(theme))
+            .draw()?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Draws small multiples on the given drawing area, see [`small_multiples_time_plot`].
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(root, ys))]
+fn draw_small_multiples<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    ys: &ArrayBase<A, Ix2>,
+    sample_rate_hz: f32,
+    title: &str,
+    channel_labels: Option<&[&str]>,
+    grid: (usize, usize),
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: Data<Elem = f32>,
+{
+    let number_of_steps = ys.shape()[0];
+    let number_of_channels = ys.shape()[1];
+    let (rows, columns) = grid;
+
+    let x = Array1::linspace(
+        0.0,
+        number_of_steps as f32 / sample_rate_hz,
+        number_of_steps,
+    );
+    let x_min = *x.min()?;
+    let x_max = *x.max()?;
+
+    root.fill(&theme.background())?;
+    let root = root.titled(title, caption_font(theme))?;
+    let panels = root.split_evenly((rows, columns));
+
+    for (channel, panel) in panels.iter().enumerate().take(number_of_channels) {
+        let y = ys.slice(s![.., channel]);
+        let y_min = *y.min()?;
+        let y_max = *y.max()?;
+        let y_range = (y_max - y_min).max(f32::EPSILON);
+        let y_min = y_range.mul_add(-Y_MARGIN, y_min);
+        let y_max = y_range.mul_add(Y_MARGIN, y_max);
+
+        let caption = channel_labels.map_or_else(
+            || format!("Sensor {channel}"),
+            |channel_labels| channel_labels[channel].to_string(),
+        );
+        let mut chart = ChartBuilder::on(panel)
+            .caption(
+                caption,
+                SMALL_MULTIPLE_STYLE.into_font().color(&theme.foreground()),
+            )
+            .margin(SMALL_MULTIPLE_MARGIN)
+            .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+        chart
+            .configure_mesh()
+            .themed(theme)
+            .disable_mesh()
+            .x_labels(0)
+            .y_labels(0)
+            .draw()?;
+
+        chart.draw_series(LineSeries::new(
+            x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+            &COLORS[0],
+        ))?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+/// Draws a butterfly plot on the given drawing area, see [`measurement_butterfly_plot`].
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(root, ys))]
+fn draw_measurement_butterfly<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    ys: &ArrayBase<A, Ix2>,
+    sample_rate_hz: f32,
+    title: &str,
+    y_label: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: Data<Elem = f32>,
+{
+    let number_of_steps = ys.shape()[0];
+
+    let x = Array1::linspace(
+        0.0,
+        number_of_steps as f32 / sample_rate_hz,
+        number_of_steps,
+    );
+    let rms = rms_over_channels(ys);
+
+    let x_min = *x.min()?;
+    let x_max = *x.max()?;
+    let rms_max = *rms.max()?;
+    let y_min = (*ys.min()?).min(-rms_max);
+    let y_max = (*ys.max()?).max(rms_max);
+    let y_range = (y_max - y_min).max(f32::EPSILON);
+    let y_min = y_range.mul_add(-Y_MARGIN, y_min);
+    let y_max = y_range.mul_add(Y_MARGIN, y_max);
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_min..x_max, y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .x_desc("t [s]")
+        .x_label_style(axis_font(theme))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    let channel_color = COLORS[11].mix(BUTTERFLY_CHANNEL_OPACITY);
+    for y in ys.axis_iter(Axis(1)) {
+        chart.draw_series(LineSeries::new(
+            x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)),
+            &channel_color,
+        ))?;
+    }
+
+    let rms_color = &COLORS[0];
+    chart
+        .draw_series(LineSeries::new(
+            x.iter().zip(rms.iter()).map(|(x, y)| (*x, *y)),
+            rms_color.stroke_width(BUTTERFLY_RMS_WIDTH),
+        ))?
+        .label("RMS")
+        .legend(move |(x, y)| {
+            PathElement::new(vec![(x, y), (x + LEGEND_PATH_LENGTH, y)], rms_color)
+        });
+    chart.draw_series(LineSeries::new(
+        x.iter().zip(rms.iter()).map(|(x, y)| (*x, -*y)),
+        rms_color.stroke_width(BUTTERFLY_RMS_WIDTH),
+    ))?;
+
+    chart
+        .configure_series_labels()
+        .background_style(theme.background().mix(LEGEND_OPACITY))
+        .border_style(theme.foreground())
+        .label_font(axis_font(theme))
+        .draw()?;
+
+    root.present()?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use anyhow::Context;
@@ -487,12 +908,44 @@ mod test {
             Some("y [a.u.]"),
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
         Ok(())
     }
 
+    #[test]
+    fn test_line_plot_svg() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())
+            .context("Failed to setup test folder for svg line plot test")?;
+        let files = vec![path.join("line_plot.svg")];
+        clean_files(&files).context("Failed to clean test files for svg line plot test")?;
+
+        let x = Array1::linspace(0.0, 10.0, 100);
+        let y = x.map(|x| x * x);
+        let bundle = line_plot(
+            Some(&x),
+            vec![&y],
+            Some(files[0].as_path()),
+            Some("y=x^2"),
+            Some("x [a.u.]"),
+            Some("y [a.u.]"),
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
+
+        let svg = std::fs::read_to_string(&files[0])?;
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(
+            bundle.data.len(),
+            (bundle.width * bundle.height * 3) as usize
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_log_y_plot() -> anyhow::Result<()> {
         let path = Path::new(COMMON_PATH);
@@ -512,6 +965,7 @@ mod test {
             Some("y [a.u.]"),
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -537,6 +991,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -553,7 +1008,17 @@ mod test {
 
         let x = Array1::linspace(0.0, 10.0, 100);
         let y = x.map(|x| x * x);
-        line_plot(None, vec![&y], None, None, None, None, None, None)?;
+        line_plot(
+            None,
+            vec![&y],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
 
         assert!(!files[0].is_file());
         Ok(())
@@ -564,7 +1029,17 @@ mod test {
         let x = Array1::linspace(0.0, 10.0, 100);
         let y = x.map(|x| x * x);
 
-        let bundle = line_plot(None, vec![&y], None, None, None, None, None, None)?;
+        let bundle = line_plot(
+            None,
+            vec![&y],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
 
         assert_eq!(
             bundle.data.len(),
@@ -589,6 +1064,7 @@ mod test {
             None,
             None,
             Some(resolution),
+            PlotTheme::Light,
         )
         .context("Failed to generate line plot with custom resolution")?;
 
@@ -604,7 +1080,18 @@ mod test {
         let x = Array1::linspace(0.0, 10.0, 100);
         let y = Array1::zeros(90);
 
-        assert!(line_plot(Some(&x), vec![&y], None, None, None, None, None, None).is_err());
+        assert!(line_plot(
+            Some(&x),
+            vec![&y],
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PlotTheme::Light
+        )
+        .is_err());
     }
 
     #[test]
@@ -628,6 +1115,7 @@ mod test {
             Some("y [a.u.]"),
             None,
             None,
+            PlotTheme::Light,
         )
         .context("Failed to generate line plot with multiple y series")?;
 
@@ -662,6 +1150,7 @@ mod test {
             Some("y [a.u.]"),
             Some(&labels),
             None,
+            PlotTheme::Light,
         )
         .context("Failed to generate line plot with series labels")?;
 
@@ -696,6 +1185,7 @@ mod test {
             Some("y [a.u.]"),
             Some(&labels),
             None,
+            PlotTheme::Light,
         );
 
         assert!(result.is_err());
@@ -713,8 +1203,15 @@ mod test {
 
         let y = Array1::from_vec(vec![1.0, 2.0, 3.0]);
 
-        standard_y_plot(&y, files[0].as_path(), "Test Plot", "Y", "X")
-            .context("Failed to generate standard y plot")?;
+        standard_y_plot(
+            &y,
+            files[0].as_path(),
+            "Test Plot",
+            "Y",
+            "X",
+            PlotTheme::Light,
+        )
+        .context("Failed to generate standard y plot")?;
 
         assert!(files[0].is_file());
         Ok(())
@@ -729,7 +1226,14 @@ mod test {
 
         let y = Array1::from_vec(vec![]);
 
-        let result = standard_y_plot(&y, files[0].as_path(), "Test Plot", "Y", "X");
+        let result = standard_y_plot(
+            &y,
+            files[0].as_path(),
+            "Test Plot",
+            "Y",
+            "X",
+            PlotTheme::Light,
+        );
 
         assert!(result.is_err());
         assert!(!files[0].is_file());
@@ -746,7 +1250,14 @@ mod test {
 
         let y = Array1::from_vec(vec![1.0, 2.0, 3.0]);
 
-        let result = standard_y_plot(&y, files[0].as_path(), "Test Plot", "Y", "X");
+        let result = standard_y_plot(
+            &y,
+            files[0].as_path(),
+            "Test Plot",
+            "Y",
+            "X",
+            PlotTheme::Light,
+        );
 
         assert!(result.is_err());
         assert!(!files[0].exists());
@@ -768,8 +1279,15 @@ mod test {
         let title = "Test Plot";
         let y_label = "Y Label";
 
-        standard_time_plot(&y, sample_rate_hz, files[0].as_path(), title, y_label)
-            .context("Failed to generate standard time plot")?;
+        standard_time_plot(
+            &y,
+            sample_rate_hz,
+            files[0].as_path(),
+            title,
+            y_label,
+            PlotTheme::Light,
+        )
+        .context("Failed to generate standard time plot")?;
 
         assert!(files[0].is_file());
         Ok(())
@@ -790,7 +1308,14 @@ mod test {
         let title = "Test Plot";
         let y_label = "Y Label";
 
-        let result = standard_time_plot(&y, sample_rate_hz, files[0].as_path(), title, y_label);
+        let result = standard_time_plot(
+            &y,
+            sample_rate_hz,
+            files[0].as_path(),
+            title,
+            y_label,
+            PlotTheme::Light,
+        );
 
         assert!(result.is_err());
         assert!(!files[0].is_file());
@@ -811,13 +1336,102 @@ mod test {
         let title = "Test Plot";
         let y_label = "Y Label";
 
-        let result = standard_time_plot(&y, sample_rate_hz, files[0].as_path(), title, y_label);
+        let result = standard_time_plot(
+            &y,
+            sample_rate_hz,
+            files[0].as_path(),
+            title,
+            y_label,
+            PlotTheme::Light,
+        );
 
         assert!(result.is_err());
         assert!(!files[0].is_file());
         Ok(())
     }
 
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_small_multiples_time_plot() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("small_multiples_time_plot.png")];
+        clean_files(&files)?;
+
+        let ys =
+            ndarray::Array2::from_shape_fn((100, 7), |(t, c)| (t as f32 / 10.0 + c as f32).sin());
+
+        small_multiples_time_plot(
+            &ys,
+            100.0,
+            files[0].as_path(),
+            "Test Plot",
+            None,
+            PlotTheme::Light,
+        )
+        .context("Failed to create small multiples time plot")?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_multiples_time_plot_empty() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("small_multiples_time_plot_empty.png")];
+        clean_files(&files)?;
+
+        let ys = ndarray::Array2::<f32>::zeros((100, 0));
+
+        let result = small_multiples_time_plot(
+            &ys,
+            100.0,
+            files[0].as_path(),
+            "Test Plot",
+            None,
+            PlotTheme::Light,
+        );
+
+        assert!(result.is_err());
+        assert!(!files[0].is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rms_over_channels() {
+        let ys = ndarray::arr2(&[[3.0, -3.0], [0.0, 0.0], [1.0, -1.0]]);
+
+        let rms = rms_over_channels(&ys);
+
+        assert_eq!(rms, Array1::from_vec(vec![3.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_measurement_butterfly_plot() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("measurement_butterfly_plot.png")];
+        clean_files(&files)?;
+
+        let ys =
+            ndarray::Array2::from_shape_fn((100, 7), |(t, c)| (t as f32 / 10.0 + c as f32).sin());
+
+        measurement_butterfly_plot(
+            &ys,
+            100.0,
+            files[0].as_path(),
+            "Test Plot",
+            "z [pT]",
+            PlotTheme::Light,
+        )
+        .context("Failed to create butterfly plot")?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+
     #[test]
     #[allow(clippy::cast_precision_loss)]
     fn test_xyz_state_plot_basic() -> Result<()> {
@@ -837,8 +1451,15 @@ mod test {
         let title = "Test Plot";
         let sample_rate_hz = 10.0;
 
-        plot_state_xyz(&system_states, 1, sample_rate_hz, files[0].as_path(), title)
-            .context("Failed to create XYZ state plot")?;
+        plot_state_xyz(
+            &system_states,
+            1,
+            sample_rate_hz,
+            files[0].as_path(),
+            title,
+            PlotTheme::Light,
+        )
+        .context("Failed to create XYZ state plot")?;
 
         assert!(files[0].is_file());
         Ok(())
@@ -855,7 +1476,14 @@ mod test {
         let title = "Test Plot";
         let sample_rate_hz = 10.0;
 
-        let results = plot_state_xyz(&system_states, 5, sample_rate_hz, files[0].as_path(), title);
+        let results = plot_state_xyz(
+            &system_states,
+            5,
+            sample_rate_hz,
+            files[0].as_path(),
+            title,
+            PlotTheme::Light,
+        );
 
         assert!(results.is_err());
         assert!(!files[0].is_file());
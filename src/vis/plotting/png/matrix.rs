@@ -3,23 +3,26 @@ use std::{f32::consts::PI, io, path::Path};
 use anyhow::Result;
 use ndarray::{ArrayBase, Ix2};
 use ndarray_stats::QuantileExt;
-use plotters::prelude::*;
-use scarlet::colormap::{ColorMap, ListedColorMap};
+use plotters::{coord::Shift, prelude::*};
 use tracing::trace;
 
 use super::PngBundle;
 use crate::vis::plotting::{
-    allocate_buffer, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, AXIS_STYLE, CAPTION_STYLE, CHART_MARGIN,
+    allocate_buffer, axis_font, caption_font, save_png, PlotColorMap, PlotFormat, PlotTheme,
+    ThemedMesh, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, AXIS_STYLE, CAPTION_STYLE, CHART_MARGIN,
     COLORBAR_BOTTOM_MARGIN, COLORBAR_COLOR_NUMBERS, COLORBAR_TOP_MARGIN, COLORBAR_WIDTH,
     LABEL_AREA_RIGHT_MARGIN, LABEL_AREA_WIDTH, STANDARD_RESOLUTION, UNIT_AREA_TOP_MARGIN,
 };
 
 /// Generates a 2D matrix plot from the given input data array.
 ///
-/// The matrix values are mapped to colors based on the viridis color map.
-/// Additional options allow customizing the axis ranges, labels, title,
-/// output resolution, etc. If a file path is provided the plot is saved
-/// to that location. The raw pixel buffer is returned.
+/// The matrix values are mapped to colors based on the given color map
+/// (the one of the current [`PlotTheme`](crate::vis::plotting::PlotTheme) by
+/// default). Without a range the colors are scaled to the data,
+/// symmetrically around zero for diverging color maps. Additional options
+/// allow customizing the axis ranges, labels, title, output resolution, etc.
+/// If a file path is provided the plot is saved to that location. The raw
+/// pixel buffer is returned.
 #[allow(
     clippy::cast_precision_loss,
     clippy::too_many_arguments,
@@ -32,6 +35,7 @@ use crate::vis::plotting::{
 pub fn matrix_plot<A>(
     data: &ArrayBase<A, Ix2>,
     range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
     step: Option<(f32, f32)>,
     offset: Option<(f32, f32)>,
     path: Option<&Path>,
@@ -41,6 +45,7 @@ pub fn matrix_plot<A>(
     unit: Option<&str>,
     resolution: Option<(u32, u32)>,
     flip_axis: Option<(bool, bool)>,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: ndarray::Data<Elem = f32>,
@@ -100,156 +105,39 @@ where
     );
 
     let mut buffer = allocate_buffer(width, height);
-
-    let (x_offset, y_offset) = offset.map_or((0.0, 0.0), |offset| offset);
-    let (flip_x, flip_y) = flip_axis.map_or((false, false), |flip_axis| flip_axis);
-
-    let title = title.unwrap_or("Plot");
-    let y_label = y_label.unwrap_or("y");
-    let x_label = x_label.unwrap_or("x");
-    let unit = unit.unwrap_or("[a.u.]");
-
-    let (data_min, data_max) = if let Some(range) = range {
-        range
-    } else {
-        (*data.min()?, *data.max()?)
-    };
-
-    let data_range = (data_max - data_min).max(f32::EPSILON);
-
-    let x_min = x_offset - x_step / 2.0;
-    let x_max = (dim_x as f32).mul_add(x_step, x_offset - x_step / 2.0);
-    let y_min = y_offset - y_step / 2.0;
-    let y_max = (dim_y as f32).mul_add(y_step, y_offset - y_step / 2.0);
-
-    let x_range = if flip_x { x_max..x_min } else { x_min..x_max };
-    let y_range = if flip_y { y_max..y_min } else { y_min..y_max };
-
-    let color_map = ListedColorMap::viridis();
-
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
-        root.fill(&WHITE)?;
-        let (root_width, root_height) = root.dim_in_pixel();
-
-        let colorbar_area = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        );
-
-        let (colorbar_width, colorbar_height) = colorbar_area.dim_in_pixel();
-
-        for i in 0..COLORBAR_COLOR_NUMBERS {
-            let color: scarlet::color::RGBColor =
-                color_map.transform_single(1.0 - i as f64 / (COLORBAR_COLOR_NUMBERS - 1) as f64);
-            let color = RGBColor(
-                (color.r * u8::MAX as f64) as u8,
-                (color.g * u8::MAX as f64) as u8,
-                (color.b * u8::MAX as f64) as u8,
-            );
-            colorbar_area.draw(&Rectangle::new(
-                [
-                    (0, (i * colorbar_height / COLORBAR_COLOR_NUMBERS) as i32),
-                    (
-                        colorbar_width as i32,
-                        ((i + 1) * colorbar_height / COLORBAR_COLOR_NUMBERS) as i32,
-                    ),
-                ],
-                color.filled(),
-            ))?;
-        }
-
-        // Drawing labels for the colorbar
-        let label_area = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - LABEL_AREA_WIDTH,
-            LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        let num_labels = 4; // Number of labels on the colorbar
-        for i in 0..=num_labels {
-            label_area.draw(&Text::new(
-                format!(
-                    "{:.2}",
-                    (i as f32 / num_labels as f32).mul_add(-data_range, data_max)
-                ),
-                (5, (i * colorbar_height / num_labels) as i32),
-                AXIS_STYLE.into_font(),
-            ))?;
-        }
-
-        // Drawing units for colorbar
-        let unit_area = root.margin(
-            root_height - colorbar_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
-            UNIT_AREA_TOP_MARGIN,
-            root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        unit_area.draw(&Text::new(
-            unit,
-            (
-                COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
-                COLORBAR_TOP_MARGIN as i32 / 2,
-            ),
-            AXIS_STYLE.into_font(),
-        ))?;
-
-        let mut chart = ChartBuilder::on(&root)
-            .caption(title, CAPTION_STYLE.into_font())
-            .margin(CHART_MARGIN)
-            .margin_right(
-                CHART_MARGIN + COLORBAR_WIDTH + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-            ) // make room for colorbar
-            .x_label_area_size(AXIS_LABEL_AREA)
-            .y_label_area_size(AXIS_LABEL_AREA)
-            .build_cartesian_2d(x_range, y_range)?;
-
-        chart
-            .configure_mesh()
-            .disable_mesh()
-            .x_desc(x_label)
-            .x_label_style(AXIS_STYLE.into_font())
-            .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
-            .y_desc(y_label)
-            .y_label_style(AXIS_STYLE.into_font())
-            .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
-            .draw()?;
-
-        chart.draw_series(data.indexed_iter().map(|((index_x, index_y), &value)| {
-            // Map the value to a color
-            let color_value = (value - data_min) / (data_range);
-            let color: scarlet::color::RGBColor =
-                color_map.transform_single(f64::from(color_value));
-            let color = RGBColor(
-                (color.r * u8::MAX as f64) as u8,
-                (color.g * u8::MAX as f64) as u8,
-                (color.b * u8::MAX as f64) as u8,
-            );
-            let start = (
-                (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            let end = (
-                ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            Rectangle::new([start, end], color.filled())
-        }))?;
-
-        root.present()?;
-    } // dropping bitmap backend
+    draw_matrix(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        data,
+        range,
+        color_map,
+        step,
+        offset,
+        title,
+        y_label,
+        x_label,
+        unit,
+        flip_axis,
+        theme,
+    )?;
 
     if let Some(path) = path {
-        image::save_buffer_with_format(
-            path,
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-            image::ImageFormat::Png,
-        )?;
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_matrix(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                data,
+                range,
+                color_map,
+                step,
+                offset,
+                title,
+                y_label,
+                x_label,
+                unit,
+                flip_axis,
+                theme,
+            )?,
+        }
     }
 
     Ok(PngBundle {
@@ -279,6 +167,7 @@ pub fn matrix_angle_plot<A>(
     x_label: Option<&str>,
     resolution: Option<(u32, u32)>,
     flip_axis: Option<(bool, bool)>,
+    theme: PlotTheme,
 ) -> Result<PngBundle>
 where
     A: ndarray::Data<Elem = f32>,
@@ -328,8 +217,6 @@ where
         |resolution| resolution,
     );
 
-    let mut buffer = allocate_buffer(width, height);
-
     let (x_step, y_step) = step.map_or((1.0, 1.0), |step| step);
 
     if x_step <= 0.0 {
@@ -347,12 +234,181 @@ where
         .into());
     }
 
+    let mut buffer = allocate_buffer(width, height);
+    draw_matrix_angle(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        theta,
+        phi,
+        step,
+        offset,
+        title,
+        y_label,
+        x_label,
+        flip_axis,
+        theme,
+    )?;
+
+    if let Some(path) = path {
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_matrix_angle(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                theta,
+                phi,
+                step,
+                offset,
+                title,
+                y_label,
+                x_label,
+                flip_axis,
+                theme,
+            )?,
+        }
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws a vertical colorbar with value labels and unit on the right side of
+/// the given drawing area, leaving the rest of the area untouched.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap
+)]
+#[tracing::instrument(level = "trace", skip(root))]
+pub(super) fn draw_colorbar<DB>(
+    root: &DrawingArea<DB, Shift>,
+    data_max: f32,
+    data_range: f32,
+    color_map: PlotColorMap,
+    unit: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (root_width, root_height) = root.dim_in_pixel();
+
+    let colorbar_area = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    );
+
+    let (colorbar_width, colorbar_height) = colorbar_area.dim_in_pixel();
+
+    for i in 0..COLORBAR_COLOR_NUMBERS {
+        let color = color_map.color(1.0 - i as f32 / (COLORBAR_COLOR_NUMBERS - 1) as f32);
+        colorbar_area.draw(&Rectangle::new(
+            [
+                (0, (i * colorbar_height / COLORBAR_COLOR_NUMBERS) as i32),
+                (
+                    colorbar_width as i32,
+                    ((i + 1) * colorbar_height / COLORBAR_COLOR_NUMBERS) as i32,
+                ),
+            ],
+            color.filled(),
+        ))?;
+    }
+
+    // Drawing labels for the colorbar
+    let label_area = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - LABEL_AREA_WIDTH,
+        LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    let num_labels = 4; // Number of labels on the colorbar
+    for i in 0..=num_labels {
+        label_area.draw(&Text::new(
+            format!(
+                "{:.2}",
+                (i as f32 / num_labels as f32).mul_add(-data_range, data_max)
+            ),
+            (5, (i * colorbar_height / num_labels) as i32),
+            axis_font(theme),
+        ))?;
+    }
+
+    // Drawing units for colorbar
+    let unit_area = root.margin(
+        root_height - colorbar_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
+        UNIT_AREA_TOP_MARGIN,
+        root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    unit_area.draw(&Text::new(
+        unit,
+        (
+            COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
+            COLORBAR_TOP_MARGIN as i32 / 2,
+        ),
+        axis_font(theme),
+    ))?;
+
+    Ok(())
+}
+
+/// Draws a matrix plot on the given drawing area, see [`matrix_plot`].
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::too_many_arguments,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap,
+    clippy::cast_lossless
+)]
+#[tracing::instrument(level = "trace", skip(root, data))]
+fn draw_matrix<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    data: &ArrayBase<A, Ix2>,
+    range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    step: Option<(f32, f32)>,
+    offset: Option<(f32, f32)>,
+    title: Option<&str>,
+    y_label: Option<&str>,
+    x_label: Option<&str>,
+    unit: Option<&str>,
+    flip_axis: Option<(bool, bool)>,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: ndarray::Data<Elem = f32>,
+{
+    let (x_step, y_step) = step.map_or((1.0, 1.0), |step| step);
+    let dim_x = data.shape()[0];
+    let dim_y = data.shape()[1];
+
     let (x_offset, y_offset) = offset.map_or((0.0, 0.0), |offset| offset);
     let (flip_x, flip_y) = flip_axis.map_or((false, false), |flip_axis| flip_axis);
 
     let title = title.unwrap_or("Plot");
     let y_label = y_label.unwrap_or("y");
     let x_label = x_label.unwrap_or("x");
+    let unit = unit.unwrap_or("[a.u.]");
+
+    let color_map = color_map.unwrap_or_else(|| theme.color_map());
+
+    let (data_min, data_max) = if let Some(range) = range {
+        range
+    } else if color_map.is_diverging() {
+        let limit = data.min()?.abs().max(data.max()?.abs());
+        (-limit, limit)
+    } else {
+        (*data.min()?, *data.max()?)
+    };
+
+    let data_range = (data_max - data_min).max(f32::EPSILON);
 
     let x_min = x_offset - x_step / 2.0;
     let x_max = (dim_x as f32).mul_add(x_step, x_offset - x_step / 2.0);
@@ -362,199 +418,269 @@ where
     let x_range = if flip_x { x_max..x_min } else { x_min..x_max };
     let y_range = if flip_y { y_max..y_min } else { y_min..y_max };
 
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
-        root.fill(&WHITE)?;
-        let (root_width, root_height) = root.dim_in_pixel();
-
-        let colorbar_phi_area = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - 2 * COLORBAR_WIDTH - 2 * LABEL_AREA_WIDTH - 2 * LABEL_AREA_RIGHT_MARGIN,
-            2 * LABEL_AREA_WIDTH + 2 * LABEL_AREA_RIGHT_MARGIN + COLORBAR_WIDTH,
+    root.fill(&theme.background())?;
+    draw_colorbar(&root, data_max, data_range, color_map, unit, theme)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .margin_right(CHART_MARGIN + COLORBAR_WIDTH + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN) // make room for colorbar
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
+        .draw()?;
+
+    chart.draw_series(data.indexed_iter().map(|((index_x, index_y), &value)| {
+        // Map the value to a color
+        let color_value = (value - data_min) / (data_range);
+        let color = color_map.color(color_value);
+        let start = (
+            (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
         );
+        let end = (
+            ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
+        );
+        Rectangle::new([start, end], color.filled())
+    }))?;
 
-        let (colorbar_phi_width, colorbar_phi_height) = colorbar_phi_area.dim_in_pixel();
-
-        for i in 0..COLORBAR_COLOR_NUMBERS {
-            let h = (i as f64 / COLORBAR_COLOR_NUMBERS as f64 + 0.5) % 1.0;
-            let v = 0.5;
-            let s = 1.0;
-            // Map the value to a color
-            let color = HSLColor(h, s, v);
-            colorbar_phi_area.draw(&Rectangle::new(
-                [
-                    (0, (i * colorbar_phi_height / COLORBAR_COLOR_NUMBERS) as i32),
-                    (
-                        colorbar_phi_width as i32,
-                        ((i + 1) * colorbar_phi_height / COLORBAR_COLOR_NUMBERS) as i32,
-                    ),
-                ],
-                color.filled(),
-            ))?;
-        }
+    root.present()?;
+
+    Ok(())
+}
+
+/// Draws a matrix angle plot on the given drawing area, see [`matrix_angle_plot`].
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::too_many_arguments,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap,
+    clippy::cast_lossless
+)]
+#[tracing::instrument(level = "trace", skip(root))]
+fn draw_matrix_angle<DB, A>(
+    root: DrawingArea<DB, Shift>,
+    theta: &ArrayBase<A, Ix2>,
+    phi: &ArrayBase<A, Ix2>,
+    step: Option<(f32, f32)>,
+    offset: Option<(f32, f32)>,
+    title: Option<&str>,
+    y_label: Option<&str>,
+    x_label: Option<&str>,
+    flip_axis: Option<(bool, bool)>,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    A: ndarray::Data<Elem = f32>,
+{
+    let (x_step, y_step) = step.map_or((1.0, 1.0), |step| step);
+    let dim_x = theta.shape()[0];
+    let dim_y = theta.shape()[1];
 
-        // Drawing labels for the colorbar
-        let label_area_phi = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - 2 * LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN - COLORBAR_WIDTH,
-            LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        let num_labels = 4; // Number of labels on the colorbar
-        for i in 0..=num_labels {
-            label_area_phi.draw(&Text::new(
-                format!(
-                    "{:.2}",
-                    (i as f32 / num_labels as f32).mul_add(-360.0, 360.0)
+    let (x_offset, y_offset) = offset.map_or((0.0, 0.0), |offset| offset);
+    let (flip_x, flip_y) = flip_axis.map_or((false, false), |flip_axis| flip_axis);
+
+    let title = title.unwrap_or("Plot");
+    let y_label = y_label.unwrap_or("y");
+    let x_label = x_label.unwrap_or("x");
+
+    let x_min = x_offset - x_step / 2.0;
+    let x_max = (dim_x as f32).mul_add(x_step, x_offset - x_step / 2.0);
+    let y_min = y_offset - y_step / 2.0;
+    let y_max = (dim_y as f32).mul_add(y_step, y_offset - y_step / 2.0);
+
+    let x_range = if flip_x { x_max..x_min } else { x_min..x_max };
+    let y_range = if flip_y { y_max..y_min } else { y_min..y_max };
+
+    root.fill(&theme.background())?;
+    let (root_width, root_height) = root.dim_in_pixel();
+
+    let colorbar_phi_area = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - 2 * COLORBAR_WIDTH - 2 * LABEL_AREA_WIDTH - 2 * LABEL_AREA_RIGHT_MARGIN,
+        2 * LABEL_AREA_WIDTH + 2 * LABEL_AREA_RIGHT_MARGIN + COLORBAR_WIDTH,
+    );
+
+    let (colorbar_phi_width, colorbar_phi_height) = colorbar_phi_area.dim_in_pixel();
+
+    for i in 0..COLORBAR_COLOR_NUMBERS {
+        let h = (i as f64 / COLORBAR_COLOR_NUMBERS as f64 + 0.5) % 1.0;
+        let v = 0.5;
+        let s = 1.0;
+        // Map the value to a color
+        let color = HSLColor(h, s, v);
+        colorbar_phi_area.draw(&Rectangle::new(
+            [
+                (0, (i * colorbar_phi_height / COLORBAR_COLOR_NUMBERS) as i32),
+                (
+                    colorbar_phi_width as i32,
+                    ((i + 1) * colorbar_phi_height / COLORBAR_COLOR_NUMBERS) as i32,
                 ),
-                (5, (i * colorbar_phi_height / num_labels) as i32),
-                AXIS_STYLE.into_font(),
-            ))?;
-        }
+            ],
+            color.filled(),
+        ))?;
+    }
 
-        // Drawing units for colorbar
-        let unit_area_phi = root.margin(
-            root_height - colorbar_phi_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
-            UNIT_AREA_TOP_MARGIN,
-            root_width - 2 * COLORBAR_WIDTH - 2 * LABEL_AREA_WIDTH - 2 * LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        unit_area_phi.draw(&Text::new(
-            "phi [°]",
-            (
-                COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
-                COLORBAR_TOP_MARGIN as i32 / 2,
+    // Drawing labels for the colorbar
+    let label_area_phi = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - 2 * LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN - COLORBAR_WIDTH,
+        LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    let num_labels = 4; // Number of labels on the colorbar
+    for i in 0..=num_labels {
+        label_area_phi.draw(&Text::new(
+            format!(
+                "{:.2}",
+                (i as f32 / num_labels as f32).mul_add(-360.0, 360.0)
             ),
-            AXIS_STYLE.into_font(),
+            (5, (i * colorbar_phi_height / num_labels) as i32),
+            axis_font(theme),
         ))?;
+    }
 
-        let mut chart = ChartBuilder::on(&root)
-            .caption(title, CAPTION_STYLE.into_font())
-            .margin(CHART_MARGIN)
-            .margin_right(
-                CHART_MARGIN
-                    + 2 * COLORBAR_WIDTH
-                    + 2 * LABEL_AREA_WIDTH
-                    + 2 * LABEL_AREA_RIGHT_MARGIN,
-            ) // make room for colorbar
-            .x_label_area_size(AXIS_LABEL_AREA)
-            .y_label_area_size(AXIS_LABEL_AREA)
-            .build_cartesian_2d(x_range, y_range)?;
-
-        let colorbar_theta_area = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        );
+    // Drawing units for colorbar
+    let unit_area_phi = root.margin(
+        root_height - colorbar_phi_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
+        UNIT_AREA_TOP_MARGIN,
+        root_width - 2 * COLORBAR_WIDTH - 2 * LABEL_AREA_WIDTH - 2 * LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    unit_area_phi.draw(&Text::new(
+        "phi [°]",
+        (
+            COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
+            COLORBAR_TOP_MARGIN as i32 / 2,
+        ),
+        axis_font(theme),
+    ))?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .margin_right(
+            CHART_MARGIN + 2 * COLORBAR_WIDTH + 2 * LABEL_AREA_WIDTH + 2 * LABEL_AREA_RIGHT_MARGIN,
+        ) // make room for colorbar
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    let colorbar_theta_area = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    );
 
-        let (colorbar_theta_width, colorbar_theta_height) = colorbar_theta_area.dim_in_pixel();
-
-        for i in 0..COLORBAR_COLOR_NUMBERS {
-            let h = 0.5;
-            let v = i as f64 / COLORBAR_COLOR_NUMBERS as f64;
-            let s = 1.0;
-            // Map the value to a color
-            let color = HSLColor(h, s, v);
-            colorbar_theta_area.draw(&Rectangle::new(
-                [
-                    (
-                        0,
-                        (i * colorbar_theta_height / COLORBAR_COLOR_NUMBERS) as i32,
-                    ),
-                    (
-                        colorbar_theta_width as i32,
-                        ((i + 1) * colorbar_theta_height / COLORBAR_COLOR_NUMBERS) as i32,
-                    ),
-                ],
-                color.filled(),
-            ))?;
-        }
+    let (colorbar_theta_width, colorbar_theta_height) = colorbar_theta_area.dim_in_pixel();
 
-        // Drawing labels for the colorbar
-        let label_area_theta = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        let num_labels = 4; // Number of labels on the colorbar
-        for i in 0..=num_labels {
-            label_area_theta.draw(&Text::new(
-                format!(
-                    "{:.2}",
-                    (i as f32 / num_labels as f32).mul_add(-180.0, 180.0)
+    for i in 0..COLORBAR_COLOR_NUMBERS {
+        let h = 0.5;
+        let v = i as f64 / COLORBAR_COLOR_NUMBERS as f64;
+        let s = 1.0;
+        // Map the value to a color
+        let color = HSLColor(h, s, v);
+        colorbar_theta_area.draw(&Rectangle::new(
+            [
+                (
+                    0,
+                    (i * colorbar_theta_height / COLORBAR_COLOR_NUMBERS) as i32,
                 ),
-                (5, (i * colorbar_theta_height / num_labels) as i32),
-                AXIS_STYLE.into_font(),
-            ))?;
-        }
+                (
+                    colorbar_theta_width as i32,
+                    ((i + 1) * colorbar_theta_height / COLORBAR_COLOR_NUMBERS) as i32,
+                ),
+            ],
+            color.filled(),
+        ))?;
+    }
 
-        // Drawing units for colorbar
-        let unit_area_theta = root.margin(
-            root_height - colorbar_theta_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
-            UNIT_AREA_TOP_MARGIN,
-            root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        ); // Adjust margins to align with the colorbar
-        unit_area_theta.draw(&Text::new(
-            "theta [°]",
-            (
-                COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
-                COLORBAR_TOP_MARGIN as i32 / 2,
+    // Drawing labels for the colorbar
+    let label_area_theta = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    let num_labels = 4; // Number of labels on the colorbar
+    for i in 0..=num_labels {
+        label_area_theta.draw(&Text::new(
+            format!(
+                "{:.2}",
+                (i as f32 / num_labels as f32).mul_add(-180.0, 180.0)
             ),
-            AXIS_STYLE.into_font(),
+            (5, (i * colorbar_theta_height / num_labels) as i32),
+            axis_font(theme),
         ))?;
+    }
 
-        chart
-            .configure_mesh()
-            .disable_mesh()
-            .x_desc(x_label)
-            .x_label_style(AXIS_STYLE.into_font())
-            .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
-            .y_desc(y_label)
-            .y_label_style(AXIS_STYLE.into_font())
-            .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
-            .draw()?;
-
-        chart.draw_series(theta.indexed_iter().map(|((index_x, index_y), &theta)| {
-            let h = (phi[(index_x, index_y)] + PI) / (2.0 * PI);
-            let v = theta / PI;
-            let s = 1.0;
-            // Map the value to a color
-            let color = HSLColor(h as f64, s, v as f64);
-            let start = (
-                (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            let end = (
-                ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            Rectangle::new([start, end], color.filled())
-        }))?;
-
-        root.present()?;
-    } // dropping bitmap backend
+    // Drawing units for colorbar
+    let unit_area_theta = root.margin(
+        root_height - colorbar_theta_height - COLORBAR_TOP_MARGIN - COLORBAR_BOTTOM_MARGIN,
+        UNIT_AREA_TOP_MARGIN,
+        root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    ); // Adjust margins to align with the colorbar
+    unit_area_theta.draw(&Text::new(
+        "theta [°]",
+        (
+            COLORBAR_WIDTH as i32 / 2 - AXIS_STYLE.1,
+            COLORBAR_TOP_MARGIN as i32 / 2,
+        ),
+        axis_font(theme),
+    ))?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
+        .draw()?;
+
+    chart.draw_series(theta.indexed_iter().map(|((index_x, index_y), &theta)| {
+        let h = (phi[(index_x, index_y)] + PI) / (2.0 * PI);
+        let v = theta / PI;
+        let s = 1.0;
+        // Map the value to a color
+        let color = HSLColor(h as f64, s, v as f64);
+        let start = (
+            (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
+        );
+        let end = (
+            ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
+        );
+        Rectangle::new([start, end], color.filled())
+    }))?;
 
-    if let Some(path) = path {
-        image::save_buffer_with_format(
-            path,
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-            image::ImageFormat::Png,
-        )?;
-    }
+    root.present()?;
 
-    Ok(PngBundle {
-        data: buffer,
-        width,
-        height,
-    })
+    Ok(())
 }
+
 #[cfg(test)]
 mod test {
 
@@ -585,6 +711,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -592,6 +719,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -619,6 +747,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -626,6 +755,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -653,6 +783,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -660,6 +791,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -687,6 +819,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -694,6 +827,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -721,6 +855,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -728,6 +863,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -749,6 +885,7 @@ mod test {
             None,
             None,
             None,
+            None,
             Some(files[0].as_path()),
             Some("Custom Title"),
             Some("Custom X"),
@@ -756,6 +893,7 @@ mod test {
             Some("Custom Unit"),
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -778,6 +916,41 @@ mod test {
             Some((0.0, 10.0)),
             None,
             None,
+            None,
+            Some(files[0].as_path()),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            PlotTheme::Light,
+        )?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_matrix_plot_diverging() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("matrix_plot_diverging.png")];
+        clean_files(&files)?;
+
+        let mut data = Array2::zeros((4, 4));
+        data[(0, 0)] = 5.0;
+        data[(3, 3)] = -2.0;
+
+        assert_eq!(PlotColorMap::RdBu.color(0.5), RGBColor(247, 247, 247));
+
+        matrix_plot(
+            &data,
+            None,
+            Some(PlotColorMap::RdBu),
+            None,
+            None,
             Some(files[0].as_path()),
             None,
             None,
@@ -785,6 +958,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -805,6 +979,7 @@ mod test {
         matrix_plot(
             &data,
             None,
+            None,
             Some((0.25, 0.25)),
             None,
             Some(files[0].as_path()),
@@ -814,6 +989,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -835,6 +1011,7 @@ mod test {
             &data,
             None,
             None,
+            None,
             Some((10.0, 100.0)),
             Some(files[0].as_path()),
             None,
@@ -843,6 +1020,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -863,6 +1041,7 @@ mod test {
         let results = matrix_plot(
             &data,
             None,
+            None,
             Some((0.0, 1.0)),
             None,
             Some(files[0].as_path()),
@@ -872,6 +1051,7 @@ mod test {
             None,
             None,
             None,
+            PlotTheme::Light,
         );
 
         assert!(results.is_err());
@@ -11,11 +11,12 @@ use crate::{
         algorithm::refinement::derivation::AverageDelays,
         model::spatial::voxels::{VoxelNumbers, VoxelPositions},
     },
-    vis::plotting::{png::matrix::matrix_plot, PlotSlice},
+    vis::plotting::{png::matrix::matrix_plot, PlotColorMap, PlotSlice, PlotTheme},
 };
 
 /// Plots the activation time for a given slice (x, y or z) of the
 /// activation time matrix.
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(level = "trace")]
 pub(crate) fn average_propagation_speed_plot(
     average_delays: &AverageDelays,
@@ -25,6 +26,9 @@ pub(crate) fn average_propagation_speed_plot(
     sample_rate_hz: f32,
     path: &Path,
     slice: Option<PlotSlice>,
+    range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    theme: PlotTheme,
 ) -> anyhow::Result<PngBundle> {
     trace!("Generating activation time plot");
     let slice = slice.unwrap_or(PlotSlice::Z(0));
@@ -93,7 +97,8 @@ pub(crate) fn average_propagation_speed_plot(
 
     matrix_plot(
         &data,
-        None,
+        range,
+        color_map,
         step,
         offset,
         Some(path),
@@ -103,6 +108,7 @@ pub(crate) fn average_propagation_speed_plot(
         Some("[m/s]"),
         None,
         flip_axis,
+        theme,
     )
     .context("Failed to generate propagation speed matrix plot")
 }
@@ -152,6 +158,9 @@ mod test {
             data.simulation.sample_rate_hz,
             files[0].as_path(),
             Some(PlotSlice::Z(0)),
+            None,
+            None,
+            PlotTheme::Light,
         )
         .context("Failed to generate average propagation speed plot for test")?;
 
@@ -0,0 +1,423 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use ndarray::{Array2, Axis};
+use plotters::{coord::Shift, prelude::*};
+use scarlet::colormap::{ColorMap, ListedColorMap};
+use tracing::trace;
+
+use super::PngBundle;
+use crate::{
+    core::{
+        data::shapes::SystemStates,
+        model::spatial::voxels::{VoxelNumbers, VoxelPositions},
+    },
+    vis::plotting::{
+        allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotSlice, PlotTheme,
+        ThemedMesh, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, CAPTION_STYLE, CHART_MARGIN,
+        STANDARD_RESOLUTION,
+    },
+};
+
+/// Length of the longest arrow relative to the voxel size.
+const ARROW_LENGTH: f32 = 0.9;
+/// Length of the arrow heads relative to the arrow length.
+const ARROW_HEAD_LENGTH: f32 = 0.3;
+/// Angle between the arrow heads and the shaft in radians.
+const ARROW_HEAD_ANGLE: f32 = 0.45;
+
+/// Returns the time step with the largest total current density, which is
+/// a sensible default for a single quiver plot.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn peak_time_step(states: &SystemStates) -> usize {
+    trace!("Searching time step with peak current density");
+    states
+        .axis_iter(Axis(0))
+        .map(|row| row.iter().map(|value| value.abs()).sum::<f32>())
+        .enumerate()
+        .fold((0, f32::MIN), |peak, (step, total)| {
+            if total > peak.1 {
+                (step, total)
+            } else {
+                peak
+            }
+        })
+        .0
+}
+
+/// Returns the largest current density magnitude of a voxel over all time
+/// steps, used to keep the arrows comparable between GIF frames.
+#[must_use]
+#[tracing::instrument(level = "trace", skip_all)]
+pub fn max_current_density(states: &SystemStates) -> f32 {
+    trace!("Calculating maximum current density");
+    states
+        .axis_iter(Axis(0))
+        .flat_map(|row| {
+            row.exact_chunks(3)
+                .into_iter()
+                .map(|state| state.iter().map(|value| value * value).sum::<f32>().sqrt())
+                .collect::<Vec<f32>>()
+        })
+        .fold(0.0, f32::max)
+}
+
+/// Returns the line segments of an arrow with the given center and
+/// direction, consisting of the shaft and the two heads.
+#[tracing::instrument(level = "trace")]
+fn arrow(center: (f32, f32), direction: (f32, f32)) -> [[(f32, f32); 2]; 3] {
+    let tip = (center.0 + direction.0 / 2.0, center.1 + direction.1 / 2.0);
+    let tail = (center.0 - direction.0 / 2.0, center.1 - direction.1 / 2.0);
+    let head = |angle: f32| {
+        let (sin, cos) = angle.sin_cos();
+        let back = (
+            -direction.0 * ARROW_HEAD_LENGTH,
+            -direction.1 * ARROW_HEAD_LENGTH,
+        );
+        [
+            tip,
+            (
+                tip.0 + back.0.mul_add(cos, -back.1 * sin),
+                tip.1 + back.0.mul_add(sin, back.1 * cos),
+            ),
+        ]
+    };
+    [[tail, tip], head(ARROW_HEAD_ANGLE), head(-ARROW_HEAD_ANGLE)]
+}
+
+/// Plots the current density of a slice (x, y or z) at a single time step
+/// as arrows, using the components that lie within the slice.
+///
+/// The arrows are scaled so that a magnitude of `max_magnitude` spans most
+/// of a voxel, it defaults to the largest magnitude in the slice. If
+/// `show_magnitude` is set, the in-plane magnitude is drawn as a heatmap
+/// behind the arrows. The plot is only saved if a path is given.
+///
+/// # Errors
+///
+/// Returns an error if the voxel size is not positive, the time step is out
+/// of bounds or the plot cannot be drawn or saved.
+#[allow(
+    clippy::too_many_arguments,
+    clippy::too_many_lines,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace")]
+pub(crate) fn states_quiver_plot(
+    states: &SystemStates,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    voxel_numbers: &VoxelNumbers,
+    path: Option<&Path>,
+    slice: Option<PlotSlice>,
+    time_step: usize,
+    max_magnitude: Option<f32>,
+    show_magnitude: bool,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating current density quiver plot");
+    if voxel_size_mm <= 0.0 {
+        bail!("Voxel size must be a positive number");
+    }
+    if time_step >= states.shape()[0] {
+        bail!(
+            "Time step {time_step} out of bounds for {} time steps",
+            states.shape()[0]
+        );
+    }
+    let slice = slice.unwrap_or(PlotSlice::Z(0));
+
+    // the state offsets of the two in-plane components
+    let (numbers, offset, location, components, x_label, y_label, flip_axis) = match slice {
+        PlotSlice::X(index) => (
+            voxel_numbers.index_axis(Axis(0), index),
+            (
+                voxel_positions_mm[(0, 0, 0, 1)],
+                voxel_positions_mm[(0, 0, 0, 2)],
+            ),
+            format!("x-index = {index}"),
+            (1, 2),
+            "y [mm]",
+            "z [mm]",
+            (true, false),
+        ),
+        PlotSlice::Y(index) => (
+            voxel_numbers.index_axis(Axis(1), index),
+            (
+                voxel_positions_mm[(0, 0, 0, 0)],
+                voxel_positions_mm[(0, 0, 0, 2)],
+            ),
+            format!("y-index = {index}"),
+            (0, 2),
+            "x [mm]",
+            "z [mm]",
+            (false, false),
+        ),
+        PlotSlice::Z(index) => (
+            voxel_numbers.index_axis(Axis(2), index),
+            (
+                voxel_positions_mm[(0, 0, 0, 0)],
+                voxel_positions_mm[(0, 0, 0, 1)],
+            ),
+            format!("z-index = {index}"),
+            (0, 1),
+            "x [mm]",
+            "y [mm]",
+            (false, false),
+        ),
+    };
+
+    let mut vectors: Array2<Option<(f32, f32)>> = Array2::from_elem(numbers.raw_dim(), None);
+    for ((x, y), number) in numbers.indexed_iter() {
+        vectors[(x, y)] = number.as_ref().map(|number| {
+            (
+                states[(time_step, *number + components.0)],
+                states[(time_step, *number + components.1)],
+            )
+        });
+    }
+    let magnitude = |(u, v): (f32, f32)| u.hypot(v);
+    let max_magnitude = max_magnitude.unwrap_or_else(|| {
+        vectors
+            .iter()
+            .flatten()
+            .map(|vector| magnitude(*vector))
+            .fold(0.0, f32::max)
+    });
+    let scale = if max_magnitude > 0.0 {
+        ARROW_LENGTH * voxel_size_mm / max_magnitude
+    } else {
+        0.0
+    };
+
+    let (dim_x, dim_y) = vectors.dim();
+    let ratio = ((dim_x as f32) / (dim_y.max(1) as f32)).clamp(0.1, 10.0);
+    let (width, height) = if ratio > 1.0 {
+        (
+            STANDARD_RESOLUTION.0 + AXIS_LABEL_AREA + CHART_MARGIN,
+            (STANDARD_RESOLUTION.0 as f32 / ratio) as u32
+                + AXIS_LABEL_AREA
+                + CHART_MARGIN
+                + CAPTION_STYLE.1 as u32,
+        )
+    } else {
+        (
+            (STANDARD_RESOLUTION.0 as f32 * ratio) as u32 + AXIS_LABEL_AREA + CHART_MARGIN,
+            STANDARD_RESOLUTION.0 + AXIS_LABEL_AREA + CHART_MARGIN + CAPTION_STYLE.1 as u32,
+        )
+    };
+
+    let title = format!(
+        "Current density ({location}, time-index = {time_step}, max = {max_magnitude:.2e} A/mm^2)"
+    );
+    let mut buffer = allocate_buffer(width, height);
+    draw_quiver(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        &vectors,
+        offset,
+        flip_axis,
+        voxel_size_mm,
+        &title,
+        x_label,
+        y_label,
+        max_magnitude,
+        scale,
+        show_magnitude,
+        theme,
+    )?;
+
+    if let Some(path) = path {
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_quiver(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                &vectors,
+                offset,
+                flip_axis,
+                voxel_size_mm,
+                &title,
+                x_label,
+                y_label,
+                max_magnitude,
+                scale,
+                show_magnitude,
+                theme,
+            )?,
+        }
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws the quiver plot on the given drawing area, see [`states_quiver_plot`].
+#[allow(
+    clippy::too_many_arguments,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace", skip(root, vectors))]
+fn draw_quiver<DB>(
+    root: DrawingArea<DB, Shift>,
+    vectors: &Array2<Option<(f32, f32)>>,
+    offset: (f32, f32),
+    flip_axis: (bool, bool),
+    voxel_size_mm: f32,
+    title: &str,
+    x_label: &str,
+    y_label: &str,
+    max_magnitude: f32,
+    scale: f32,
+    show_magnitude: bool,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (dim_x, dim_y) = vectors.dim();
+    let magnitude = |(u, v): (f32, f32)| u.hypot(v);
+    let (x_offset, y_offset) = offset;
+    let (flip_x, flip_y) = flip_axis;
+    let x_min = x_offset - voxel_size_mm / 2.0;
+    let x_max = (dim_x as f32).mul_add(voxel_size_mm, x_min);
+    let y_min = y_offset - voxel_size_mm / 2.0;
+    let y_max = (dim_y as f32).mul_add(voxel_size_mm, y_min);
+    let x_range = if flip_x { x_max..x_min } else { x_min..x_max };
+    let y_range = if flip_y { y_max..y_min } else { y_min..y_max };
+
+    let color_map = ListedColorMap::viridis();
+    let center = |x: usize, y: usize| {
+        (
+            (x as f32).mul_add(voxel_size_mm, x_offset),
+            (y as f32).mul_add(voxel_size_mm, y_offset),
+        )
+    };
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
+        .draw()?;
+
+    if show_magnitude {
+        chart.draw_series(vectors.indexed_iter().filter_map(|((x, y), vector)| {
+            let vector = (*vector)?;
+            let color_value = if max_magnitude > 0.0 {
+                (magnitude(vector) / max_magnitude).min(1.0)
+            } else {
+                0.0
+            };
+            let color: scarlet::color::RGBColor =
+                color_map.transform_single(f64::from(color_value));
+            let color = RGBColor(
+                (color.r * f64::from(u8::MAX)) as u8,
+                (color.g * f64::from(u8::MAX)) as u8,
+                (color.b * f64::from(u8::MAX)) as u8,
+            );
+            let (center_x, center_y) = center(x, y);
+            let half = voxel_size_mm / 2.0;
+            Some(Rectangle::new(
+                [
+                    (center_x - half, center_y - half),
+                    (center_x + half, center_y + half),
+                ],
+                color.filled(),
+            ))
+        }))?;
+    }
+
+    chart.draw_series(
+        vectors
+            .indexed_iter()
+            .filter_map(|((x, y), vector)| {
+                let (u, v) = (*vector)?;
+                (magnitude((u, v)) > 0.0).then(|| arrow(center(x, y), (u * scale, v * scale)))
+            })
+            .flatten()
+            .map(|line| PathElement::new(line.to_vec(), theme.foreground().stroke_width(1))),
+    )?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        core::{config::simulation::Simulation as SimulationConfig, data::Data},
+        tests::{clean_files, setup_folder},
+    };
+    const COMMON_PATH: &str = "tests/vis/plotting/png/quiver";
+
+    #[test]
+    fn arrow_points_in_direction() {
+        let [shaft, left, right] = arrow((1.0, 1.0), (2.0, 0.0));
+
+        assert_eq!(shaft, [(0.0, 1.0), (2.0, 1.0)]);
+        assert_eq!(left[0], (2.0, 1.0));
+        assert!(left[1].0 < 2.0);
+        assert_relative_eq!(left[1].0, right[1].0);
+        assert_relative_eq!(left[1].1 - 1.0, 1.0 - right[1].1);
+    }
+
+    #[test]
+    fn test_states_quiver_plot_default() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("quiver_default.png")];
+        clean_files(&files)?;
+
+        let mut simulation_config = SimulationConfig::default();
+        simulation_config.model.common.pathological = true;
+        let data = Data::from_simulation_config(&simulation_config)?;
+        let states = &data.simulation.system_states;
+
+        states_quiver_plot(
+            states,
+            &data
+                .simulation
+                .model
+                .spatial_description
+                .voxels
+                .positions_mm,
+            data.simulation.model.spatial_description.voxels.size_mm,
+            &data.simulation.model.spatial_description.voxels.numbers,
+            Some(files[0].as_path()),
+            Some(PlotSlice::Z(0)),
+            peak_time_step(states),
+            None,
+            true,
+            PlotTheme::Light,
+        )?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+}
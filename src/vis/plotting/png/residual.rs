@@ -0,0 +1,312 @@
+use std::path::Path;
+
+use anyhow::Result;
+use ndarray::{Array1, Array2, ArrayBase, Axis, Data, Ix2};
+use ndarray_stats::QuantileExt;
+use plotters::{coord::Shift, prelude::*};
+use tracing::trace;
+
+use super::{matrix::draw_colorbar, PngBundle};
+use crate::vis::plotting::{
+    allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotTheme, ThemedMesh,
+    AXIS_LABEL_AREA, CAPTION_STYLE, CHART_MARGIN, COLORBAR_WIDTH, LABEL_AREA_RIGHT_MARGIN,
+    LABEL_AREA_WIDTH, STANDARD_RESOLUTION,
+};
+
+const SENSOR_MARKER_SIZE: i32 = 10;
+const AXIS_NAMES: [&str; 3] = ["x", "y", "z"];
+
+/// How the residual of a sensor is summarized over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResidualStatistic {
+    // mean of the absolute residual
+    Mean,
+    // maximum of the absolute residual
+    Peak,
+}
+
+/// A sensor position of the array together with the summarized residual of
+/// all sensors at that position.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SensorResidual {
+    pub position_mm: [f32; 3],
+    pub value: f32,
+}
+
+/// Summarizes the residuals of shape (steps, sensors) per sensor position.
+///
+/// Sensors at the same position (e.g. the three axes of a vector
+/// magnetometer) are combined, taking the mean of the time-averaged or the
+/// maximum of the peak residuals.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(residuals))]
+pub(crate) fn sensor_residuals<A>(
+    residuals: &ArrayBase<A, Ix2>,
+    sensor_positions_mm: &Array2<f32>,
+    statistic: ResidualStatistic,
+) -> Result<Vec<SensorResidual>>
+where
+    A: Data<Elem = f32>,
+{
+    trace!("Summarizing residuals per sensor position");
+    if residuals.shape()[1] != sensor_positions_mm.shape()[0] {
+        return Err(anyhow::anyhow!(
+            "Residuals of {} sensors do not match {} sensor positions",
+            residuals.shape()[1],
+            sensor_positions_mm.shape()[0]
+        ));
+    }
+    let absolute = residuals.mapv(f32::abs);
+    let per_sensor: Array1<f32> = match statistic {
+        ResidualStatistic::Mean => absolute
+            .mean_axis(Axis(0))
+            .ok_or_else(|| anyhow::anyhow!("Residuals must contain at least one time step"))?,
+        ResidualStatistic::Peak => absolute
+            .columns()
+            .into_iter()
+            .map(|column| *column.max_skipnan())
+            .collect(),
+    };
+
+    let mut positions: Vec<(SensorResidual, usize)> = Vec::new();
+    for (position, &value) in sensor_positions_mm
+        .rows()
+        .into_iter()
+        .zip(per_sensor.iter())
+    {
+        let position_mm = [position[0], position[1], position[2]];
+        if let Some((sensor, count)) = positions
+            .iter_mut()
+            .find(|(sensor, _)| sensor.position_mm == position_mm)
+        {
+            sensor.value = match statistic {
+                ResidualStatistic::Mean => sensor.value + value,
+                ResidualStatistic::Peak => sensor.value.max(value),
+            };
+            *count += 1;
+        } else {
+            positions.push((SensorResidual { position_mm, value }, 1));
+        }
+    }
+
+    Ok(positions
+        .into_iter()
+        .map(|(mut sensor, count)| {
+            if statistic == ResidualStatistic::Mean {
+                sensor.value /= count as f32;
+            }
+            sensor
+        })
+        .collect())
+}
+
+/// Plots the summarized residual per sensor as a 2D layout of the sensor
+/// array.
+///
+/// The sensor positions are projected onto the two axes along which the
+/// array is the most extended. The plot is saved as PNG or SVG depending on
+/// the file extension.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+#[tracing::instrument(level = "trace", skip(residuals))]
+pub(crate) fn sensor_residual_plot<A>(
+    residuals: &ArrayBase<A, Ix2>,
+    sensor_positions_mm: &Array2<f32>,
+    statistic: ResidualStatistic,
+    path: &Path,
+    title: &str,
+    unit: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle>
+where
+    A: Data<Elem = f32>,
+{
+    trace!("Generating sensor residual plot");
+    let sensors = sensor_residuals(residuals, sensor_positions_mm, statistic)?;
+    let axes = layout_axes(sensor_positions_mm)?;
+
+    let width = STANDARD_RESOLUTION.0
+        + AXIS_LABEL_AREA
+        + CHART_MARGIN
+        + COLORBAR_WIDTH
+        + LABEL_AREA_WIDTH
+        + LABEL_AREA_RIGHT_MARGIN;
+    let height = STANDARD_RESOLUTION.0 + AXIS_LABEL_AREA + CHART_MARGIN + CAPTION_STYLE.1 as u32;
+
+    let mut buffer = allocate_buffer(width, height);
+    draw_sensor_residuals(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        &sensors,
+        axes,
+        title,
+        unit,
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height)?,
+        PlotFormat::Svg => draw_sensor_residuals(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            &sensors,
+            axes,
+            title,
+            unit,
+            theme,
+        )?,
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Returns the two coordinate axes along which the sensor array has the
+/// largest extent, in ascending order.
+#[tracing::instrument(level = "trace", skip_all)]
+fn layout_axes(sensor_positions_mm: &Array2<f32>) -> Result<(usize, usize)> {
+    let mut extents = (0..3)
+        .map(|axis| {
+            let column = sensor_positions_mm.column(axis);
+            Ok((axis, column.max()? - column.min()?))
+        })
+        .collect::<Result<Vec<(usize, f32)>>>()?;
+    extents.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (first, second) = (extents[0].0, extents[1].0);
+    Ok((first.min(second), first.max(second)))
+}
+
+/// Draws the sensor residual layout on the given drawing area, see
+/// [`sensor_residual_plot`].
+#[tracing::instrument(level = "trace", skip(root, sensors))]
+fn draw_sensor_residuals<DB>(
+    root: DrawingArea<DB, Shift>,
+    sensors: &[SensorResidual],
+    axes: (usize, usize),
+    title: &str,
+    unit: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let (x_axis, y_axis) = axes;
+    let coordinate_range = |axis: usize| {
+        let (min, max) =
+            sensors
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), sensor| {
+                    (
+                        min.min(sensor.position_mm[axis]),
+                        max.max(sensor.position_mm[axis]),
+                    )
+                });
+        let margin = ((max - min) * 0.1).max(1.0);
+        (min - margin)..(max + margin)
+    };
+    let data_max = sensors
+        .iter()
+        .fold(0.0_f32, |max, sensor| max.max(sensor.value))
+        .max(f32::EPSILON);
+    let color_map = theme.color_map();
+
+    root.fill(&theme.background())?;
+    draw_colorbar(&root, data_max, data_max, color_map, unit, theme)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .margin_right(CHART_MARGIN + COLORBAR_WIDTH + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN) // make room for colorbar
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(coordinate_range(x_axis), coordinate_range(y_axis))?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(format!("{} [mm]", AXIS_NAMES[x_axis]))
+        .x_label_style(axis_font(theme))
+        .y_desc(format!("{} [mm]", AXIS_NAMES[y_axis]))
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    chart.draw_series(sensors.iter().map(|sensor| {
+        let position = (sensor.position_mm[x_axis], sensor.position_mm[y_axis]);
+        Circle::new(
+            position,
+            SENSOR_MARKER_SIZE,
+            color_map.color(sensor.value / data_max).filled(),
+        )
+    }))?;
+    chart.draw_series(sensors.iter().map(|sensor| {
+        let position = (sensor.position_mm[x_axis], sensor.position_mm[y_axis]);
+        Circle::new(position, SENSOR_MARKER_SIZE, theme.foreground())
+    }))?;
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use approx::assert_relative_eq;
+    use ndarray::arr2;
+
+    use super::*;
+    use crate::tests::{clean_files, setup_folder};
+
+    const COMMON_PATH: &str = "tests/vis/plotting/png/residual";
+
+    #[test]
+    fn test_sensor_residuals_combine_positions() -> Result<()> {
+        let positions = arr2(&[[0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [10.0, 0.0, 0.0]]);
+        let residuals = arr2(&[[1.0, -3.0, 2.0], [-1.0, 1.0, 0.0]]);
+
+        let mean = sensor_residuals(&residuals, &positions, ResidualStatistic::Mean)?;
+        assert_eq!(mean.len(), 2);
+        assert_relative_eq!(mean[0].value, 1.5);
+        assert_relative_eq!(mean[1].value, 1.0);
+
+        let peak = sensor_residuals(&residuals, &positions, ResidualStatistic::Peak)?;
+        assert_relative_eq!(peak[0].value, 3.0);
+        assert_relative_eq!(peak[1].value, 2.0);
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_sensor_residual_plot() -> Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("sensor_residual_plot.png")];
+        clean_files(&files)?;
+
+        let mut positions = Array2::zeros((16, 3));
+        for (i, mut position) in positions.rows_mut().into_iter().enumerate() {
+            position[0] = (i % 4) as f32 * 20.0;
+            position[1] = (i / 4) as f32 * 20.0;
+            position[2] = 100.0;
+        }
+        let residuals = Array2::from_shape_fn((50, 16), |(t, s)| (t as f32 * 0.1).sin() * s as f32);
+
+        sensor_residual_plot(
+            &residuals,
+            &positions,
+            ResidualStatistic::Mean,
+            files[0].as_path(),
+            "Residual per sensor",
+            "[pT]",
+            PlotTheme::Light,
+        )?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+}
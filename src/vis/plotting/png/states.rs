@@ -12,7 +12,7 @@ use crate::{
     },
     vis::plotting::{
         png::matrix::{matrix_angle_plot, matrix_plot},
-        PlotSlice, StatePlotMode, StateSphericalPlotMode,
+        PlotColorMap, PlotSlice, PlotTheme, StatePlotMode, StateSphericalPlotMode,
     },
 };
 
@@ -27,6 +27,7 @@ pub(crate) fn states_plot(
     slice: Option<PlotSlice>,
     mode: Option<StatePlotMode>,
     time_step: usize,
+    theme: PlotTheme,
 ) -> Result<PngBundle> {
     trace!("Generating activation time plot");
     let slice = slice.unwrap_or(PlotSlice::Z(0));
@@ -94,6 +95,7 @@ pub(crate) fn states_plot(
     matrix_plot(
         &data,
         None,
+        None,
         step,
         offset,
         Some(path),
@@ -103,6 +105,7 @@ pub(crate) fn states_plot(
         Some("[A/mm^2]"),
         None,
         flip_axis,
+        theme,
     )
 }
 
@@ -119,6 +122,8 @@ pub(crate) fn states_spherical_plot(
     mode: Option<StateSphericalPlotMode>,
     time_step: Option<usize>,
     range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    theme: PlotTheme,
 ) -> Result<PngBundle> {
     trace!("Generating activation time plot");
     let slice = slice.unwrap_or(PlotSlice::Z(0));
@@ -195,6 +200,7 @@ pub(crate) fn states_spherical_plot(
             matrix_plot(
                 &data,
                 range,
+                color_map,
                 step,
                 offset,
                 path,
@@ -204,6 +210,7 @@ pub(crate) fn states_spherical_plot(
                 Some("[A/mm^2]"),
                 None,
                 flip_axis,
+                theme,
             )
         }
         StateSphericalPlotMode::ANGLE => {
@@ -246,6 +253,7 @@ pub(crate) fn states_spherical_plot(
                 x_label,
                 None,
                 flip_axis,
+                theme,
             )
         }
     }
@@ -287,6 +295,7 @@ mod test {
             Some(PlotSlice::Z(0)),
             Some(StatePlotMode::X),
             350,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -319,6 +328,7 @@ mod test {
             Some(PlotSlice::X(10)),
             Some(StatePlotMode::X),
             350,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -350,6 +360,7 @@ mod test {
             Some(PlotSlice::Y(5)),
             Some(StatePlotMode::X),
             350,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -382,6 +393,7 @@ mod test {
             Some(PlotSlice::Z(0)),
             Some(StatePlotMode::Y),
             350,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -414,6 +426,7 @@ mod test {
             Some(PlotSlice::Z(0)),
             Some(StatePlotMode::Z),
             350,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -448,6 +461,8 @@ mod test {
             Some(StateSphericalPlotMode::ABS),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -482,6 +497,8 @@ mod test {
             Some(StateSphericalPlotMode::ABS),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -516,6 +533,8 @@ mod test {
             Some(StateSphericalPlotMode::ABS),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -550,6 +569,8 @@ mod test {
             Some(StateSphericalPlotMode::ANGLE),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -584,6 +605,8 @@ mod test {
             Some(StateSphericalPlotMode::ANGLE),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -618,6 +641,8 @@ mod test {
             Some(StateSphericalPlotMode::ANGLE),
             Some(350),
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -652,6 +677,8 @@ mod test {
             Some(StateSphericalPlotMode::ABS),
             None,
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -686,6 +713,8 @@ mod test {
             Some(StateSphericalPlotMode::ANGLE),
             None,
             None,
+            None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
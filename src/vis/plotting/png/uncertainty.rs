@@ -0,0 +1,55 @@
+use std::path::Path;
+
+use anyhow::Result;
+use tracing::trace;
+
+use super::{activation_time::ActivationTimeSlice, PngBundle};
+use crate::{
+    core::model::{functional::allpass::shapes::ActivationTimeMs, spatial::voxels::VoxelPositions},
+    vis::plotting::{png::matrix::matrix_plot, PlotColorMap, PlotSlice, PlotTheme},
+};
+
+/// Plots the standard deviation of a quantity over the ensemble members for
+/// a given slice (x, y or z) of the voxel grid.
+///
+/// Voxels without a value are shown as zero. Without a range the colors are
+/// scaled to the data.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(level = "trace")]
+pub(crate) fn standard_deviation_plot(
+    standard_deviation: &ActivationTimeMs,
+    voxel_positions_mm: &VoxelPositions,
+    voxel_size_mm: f32,
+    path: &Path,
+    slice: Option<PlotSlice>,
+    quantity: &str,
+    unit: &str,
+    range: Option<(f32, f32)>,
+    color_map: Option<PlotColorMap>,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating standard deviation plot");
+    let slice = ActivationTimeSlice::new(
+        standard_deviation,
+        voxel_positions_mm,
+        slice.unwrap_or(PlotSlice::Z(0)),
+    );
+    let data = slice.data.map(|value| value.unwrap_or(0.0));
+    let title = format!("{quantity} standard deviation {}", slice.location);
+
+    matrix_plot(
+        &data,
+        range,
+        color_map,
+        Some((voxel_size_mm, voxel_size_mm)),
+        Some(slice.offset),
+        Some(path),
+        Some(title.as_str()),
+        Some(slice.y_label),
+        Some(slice.x_label),
+        Some(unit),
+        None,
+        Some(slice.flip_axis),
+        theme,
+    )
+}
@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use plotters::{
+    coord::Shift,
+    prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
+};
+use tracing::trace;
+
+use super::PngBundle;
+use crate::{
+    core::algorithm::metrics::velocity::VelocityStatistics,
+    vis::plotting::{
+        allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotTheme, ThemedMesh,
+        AXIS_LABEL_AREA, CHART_MARGIN, COLORS, STANDARD_RESOLUTION, Y_MARGIN,
+    },
+};
+
+const BOX_HALF_WIDTH: f32 = 0.3;
+const CAP_HALF_WIDTH: f32 = 0.1;
+const BOX_OPACITY: f64 = 0.5;
+const MEDIAN_WIDTH: u32 = 3;
+const MEAN_MARKER_SIZE: u32 = 5;
+
+/// Draws a box plot of the propagation velocity per voxel type.
+///
+/// The boxes span the quartiles, the whiskers the minimum and maximum, the
+/// thick line marks the median and the circle the mean. Saves the plot to the
+/// given path as a PNG or SVG, depending on its extension, and returns the raw
+/// pixel buffer.
+///
+/// # Errors
+///
+/// Returns an error if no statistics are given or drawing fails.
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(statistics))]
+pub fn velocity_box_plot(
+    statistics: &[VelocityStatistics],
+    path: &Path,
+    title: &str,
+    theme: PlotTheme,
+) -> Result<PngBundle> {
+    trace!("Generating velocity box plot.");
+    anyhow::ensure!(
+        !statistics.is_empty(),
+        "No propagation velocities available for box plot"
+    );
+    let (width, height) = STANDARD_RESOLUTION;
+    let mut buffer = allocate_buffer(width, height);
+    draw_velocity_box(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        statistics,
+        title,
+        theme,
+    )?;
+
+    match PlotFormat::from_path(path) {
+        PlotFormat::Png => save_png(path, &buffer, width, height),
+        PlotFormat::Svg => draw_velocity_box(
+            SVGBackend::new(path, (width, height)).into_drawing_area(),
+            statistics,
+            title,
+            theme,
+        ),
+    }
+    .with_context(|| format!("Failed to save velocity box plot: {}", path.display()))?;
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws the velocity box plot on the given drawing area, see [`velocity_box_plot`].
+#[allow(clippy::cast_precision_loss)]
+#[tracing::instrument(level = "trace", skip(root, statistics))]
+fn draw_velocity_box<DB>(
+    root: DrawingArea<DB, Shift>,
+    statistics: &[VelocityStatistics],
+    title: &str,
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let y_min = statistics
+        .iter()
+        .map(|s| s.min_m_per_s)
+        .fold(f32::INFINITY, f32::min)
+        .min(0.0);
+    let y_max = statistics
+        .iter()
+        .map(|s| s.max_m_per_s)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let y_range = (y_max - y_min).max(f32::EPSILON);
+    let y_min = y_range.mul_add(-Y_MARGIN, y_min);
+    let y_max = y_range.mul_add(Y_MARGIN, y_max);
+    let x_max = statistics.len() as f32 - 0.5;
+
+    root.fill(&theme.background())?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(-0.5f32..x_max, y_min..y_max)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_x_mesh()
+        .x_labels(0)
+        .x_desc("Voxel Type")
+        .y_desc("Velocity [m/s]")
+        .y_label_style(axis_font(theme))
+        .draw()?;
+
+    for (index, statistic) in statistics.iter().enumerate() {
+        let x = index as f32;
+        let color = COLORS[index % COLORS.len()];
+        chart.draw_series([
+            Rectangle::new(
+                [
+                    (x - BOX_HALF_WIDTH, statistic.lower_quartile_m_per_s),
+                    (x + BOX_HALF_WIDTH, statistic.upper_quartile_m_per_s),
+                ],
+                color.mix(BOX_OPACITY).filled(),
+            ),
+            Rectangle::new(
+                [
+                    (x - BOX_HALF_WIDTH, statistic.lower_quartile_m_per_s),
+                    (x + BOX_HALF_WIDTH, statistic.upper_quartile_m_per_s),
+                ],
+                color.stroke_width(1),
+            ),
+        ])?;
+        chart.draw_series(
+            [
+                vec![
+                    (x, statistic.min_m_per_s),
+                    (x, statistic.lower_quartile_m_per_s),
+                ],
+                vec![
+                    (x, statistic.upper_quartile_m_per_s),
+                    (x, statistic.max_m_per_s),
+                ],
+                vec![
+                    (x - CAP_HALF_WIDTH, statistic.min_m_per_s),
+                    (x + CAP_HALF_WIDTH, statistic.min_m_per_s),
+                ],
+                vec![
+                    (x - CAP_HALF_WIDTH, statistic.max_m_per_s),
+                    (x + CAP_HALF_WIDTH, statistic.max_m_per_s),
+                ],
+            ]
+            .into_iter()
+            .map(|points| PathElement::new(points, color)),
+        )?;
+        chart.draw_series(std::iter::once(PathElement::new(
+            vec![
+                (x - BOX_HALF_WIDTH, statistic.median_m_per_s),
+                (x + BOX_HALF_WIDTH, statistic.median_m_per_s),
+            ],
+            theme.foreground().stroke_width(MEDIAN_WIDTH),
+        )))?;
+        chart.draw_series(std::iter::once(Circle::new(
+            (x, statistic.mean_m_per_s),
+            MEAN_MARKER_SIZE,
+            theme.foreground(),
+        )))?;
+        chart.draw_series(std::iter::once(Text::new(
+            format!("{:?} (n={})", statistic.voxel_type, statistic.count),
+            (x, y_min),
+            axis_font(theme).pos(Pos::new(HPos::Center, VPos::Bottom)),
+        )))?;
+    }
+
+    root.present()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        core::model::spatial::voxels::VoxelType,
+        tests::{clean_files, setup_folder},
+    };
+    const COMMON_PATH: &str = "tests/vis/plotting/png/velocity";
+
+    #[test]
+    fn test_velocity_box_plot() -> anyhow::Result<()> {
+        let path = Path::new(COMMON_PATH);
+        setup_folder(path.to_path_buf())?;
+        let files = vec![path.join("test_velocity_box_plot.png")];
+        clean_files(&files)?;
+
+        let statistics: Vec<VelocityStatistics> = [
+            (VoxelType::Atrium, vec![0.8, 1.0, 1.1, 0.9]),
+            (VoxelType::Ventricle, vec![0.5, 0.6, 0.7]),
+            (VoxelType::Pathological, vec![0.1, 0.2]),
+        ]
+        .into_iter()
+        .filter_map(|(voxel_type, velocities)| {
+            VelocityStatistics::from_velocities(voxel_type, velocities)
+        })
+        .collect();
+
+        velocity_box_plot(
+            &statistics,
+            files[0].as_path(),
+            "Propagation Velocity",
+            PlotTheme::Light,
+        )?;
+
+        assert!(files[0].is_file());
+        Ok(())
+    }
+
+    #[test]
+    fn empty_statistics_are_rejected() {
+        assert!(velocity_box_plot(&[], Path::new(COMMON_PATH), "Empty", PlotTheme::Light).is_err());
+    }
+}
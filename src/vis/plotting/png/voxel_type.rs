@@ -2,8 +2,8 @@ use std::path::Path;
 
 use anyhow::Result;
 use bevy::color::ColorToPacked;
-use ndarray::Axis;
-use plotters::prelude::*;
+use ndarray::{ArrayView2, Axis};
+use plotters::{coord::Shift, prelude::*};
 use scarlet::colormap::ListedColorMap;
 use strum::IntoEnumIterator;
 use tracing::trace;
@@ -14,9 +14,10 @@ use crate::{
     vis::{
         heart::type_to_color,
         plotting::{
-            allocate_buffer, PlotSlice, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, AXIS_STYLE,
-            CAPTION_STYLE, CHART_MARGIN, COLORBAR_BOTTOM_MARGIN, COLORBAR_TOP_MARGIN,
-            COLORBAR_WIDTH, LABEL_AREA_RIGHT_MARGIN, LABEL_AREA_WIDTH, STANDARD_RESOLUTION,
+            allocate_buffer, axis_font, caption_font, save_png, PlotFormat, PlotSlice, PlotTheme,
+            ThemedMesh, AXIS_LABEL_AREA, AXIS_LABEL_NUM_MAX, AXIS_STYLE, CAPTION_STYLE,
+            CHART_MARGIN, COLORBAR_BOTTOM_MARGIN, COLORBAR_TOP_MARGIN, COLORBAR_WIDTH,
+            LABEL_AREA_RIGHT_MARGIN, LABEL_AREA_WIDTH, STANDARD_RESOLUTION,
         },
     },
 };
@@ -36,6 +37,7 @@ pub fn voxel_type_plot(
     voxel_size_mm: f32,
     path: Option<&Path>,
     slice: Option<PlotSlice>,
+    theme: PlotTheme,
 ) -> Result<PngBundle> {
     trace!("Generating voxel type plot.");
 
@@ -119,6 +121,69 @@ pub fn voxel_type_plot(
     };
 
     let mut buffer = allocate_buffer(width, height);
+    draw_voxel_types(
+        BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area(),
+        &data,
+        voxel_size_mm,
+        offset,
+        &title,
+        x_label,
+        y_label,
+        flip_axis,
+        theme,
+    )?;
+
+    if let Some(path) = path {
+        match PlotFormat::from_path(path) {
+            PlotFormat::Png => save_png(path, &buffer, width, height)?,
+            PlotFormat::Svg => draw_voxel_types(
+                SVGBackend::new(path, (width, height)).into_drawing_area(),
+                &data,
+                voxel_size_mm,
+                offset,
+                &title,
+                x_label,
+                y_label,
+                flip_axis,
+                theme,
+            )?,
+        }
+    }
+
+    Ok(PngBundle {
+        data: buffer,
+        width,
+        height,
+    })
+}
+
+/// Draws the voxel types on the given drawing area, see [`voxel_type_plot`].
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::too_many_arguments,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_possible_wrap,
+    clippy::cast_lossless
+)]
+#[tracing::instrument(level = "trace", skip(root, data))]
+fn draw_voxel_types<DB>(
+    root: DrawingArea<DB, Shift>,
+    data: &ArrayView2<VoxelType>,
+    voxel_size_mm: f32,
+    offset: (f32, f32),
+    title: &str,
+    x_label: Option<&str>,
+    y_label: Option<&str>,
+    flip_axis: (bool, bool),
+    theme: PlotTheme,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let dim_x = data.shape()[0];
+    let dim_y = data.shape()[1];
 
     let (x_step, y_step) = (voxel_size_mm, voxel_size_mm);
 
@@ -138,103 +203,84 @@ pub fn voxel_type_plot(
 
     let _color_map = ListedColorMap::viridis();
 
-    {
-        let root = BitMapBackend::with_buffer(&mut buffer[..], (width, height)).into_drawing_area();
-        root.fill(&WHITE)?;
-        let (root_width, _root_height) = root.dim_in_pixel();
-
-        let legend_area = root.margin(
-            COLORBAR_TOP_MARGIN,
-            COLORBAR_BOTTOM_MARGIN,
-            root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
-            LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-        );
+    root.fill(&theme.background())?;
+    let (root_width, _root_height) = root.dim_in_pixel();
 
-        let (legend_width, legend_height) = legend_area.dim_in_pixel();
+    let legend_area = root.margin(
+        COLORBAR_TOP_MARGIN,
+        COLORBAR_BOTTOM_MARGIN,
+        root_width - COLORBAR_WIDTH - LABEL_AREA_WIDTH - LABEL_AREA_RIGHT_MARGIN,
+        LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
+    );
 
-        let num_types = VoxelType::iter().count() as u32;
-        let single_space = (legend_height / (2 * num_types - 1)) as i32;
+    let (legend_width, legend_height) = legend_area.dim_in_pixel();
 
-        for (i, voxel_type) in VoxelType::iter().enumerate() {
-            let color = type_to_color(voxel_type);
-            let color = color.to_linear().to_u8_array();
-            let color = RGBColor(color[0], color[1], color[2]);
-            let start = (
-                legend_width as i32 / 2 - single_space / 2,
-                i as i32 * (single_space + single_space),
-            );
-            let end = (
-                legend_width as i32 / 2 + single_space / 2,
-                i as i32 * (single_space + single_space) + single_space,
-            );
-            legend_area.draw(&Rectangle::new([start, end], color.filled()))?;
-            legend_area.draw(&Rectangle::new([start, end], BLACK))?;
-            legend_area.draw(&Text::new(
-                format!("{voxel_type:?}"),
-                (
-                    legend_width as i32 / 2 + single_space * 2 / 3,
-                    i as i32 * (single_space + single_space) + single_space / 2 - AXIS_STYLE.1 / 2,
-                ),
-                AXIS_STYLE.into_font(),
-            ))?;
-        }
+    let num_types = VoxelType::iter().count() as u32;
+    let single_space = (legend_height / (2 * num_types - 1)) as i32;
 
-        let mut chart = ChartBuilder::on(&root)
-            .caption(title, CAPTION_STYLE.into_font())
-            .margin(CHART_MARGIN)
-            .margin_right(
-                CHART_MARGIN + COLORBAR_WIDTH + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN,
-            ) // make room for colorbar
-            .x_label_area_size(AXIS_LABEL_AREA)
-            .y_label_area_size(AXIS_LABEL_AREA)
-            .build_cartesian_2d(x_range, y_range)?;
-
-        chart
-            .configure_mesh()
-            .disable_mesh()
-            .x_desc(x_label)
-            .x_label_style(AXIS_STYLE.into_font())
-            .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
-            .y_desc(y_label)
-            .y_label_style(AXIS_STYLE.into_font())
-            .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
-            .draw()?;
-
-        chart.draw_series(data.indexed_iter().map(|((index_x, index_y), &value)| {
-            // Map the value to a color
-            let color = type_to_color(value);
-            let color = color.to_linear().to_u8_array();
-            let color = RGBColor(color[0], color[1], color[2]);
-            let start = (
-                (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            let end = (
-                ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
-                ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
-            );
-            Rectangle::new([start, end], color.filled())
-        }))?;
+    for (i, voxel_type) in VoxelType::iter().enumerate() {
+        let color = type_to_color(voxel_type);
+        let color = color.to_linear().to_u8_array();
+        let color = RGBColor(color[0], color[1], color[2]);
+        let start = (
+            legend_width as i32 / 2 - single_space / 2,
+            i as i32 * (single_space + single_space),
+        );
+        let end = (
+            legend_width as i32 / 2 + single_space / 2,
+            i as i32 * (single_space + single_space) + single_space,
+        );
+        legend_area.draw(&Rectangle::new([start, end], color.filled()))?;
+        legend_area.draw(&Rectangle::new([start, end], theme.foreground()))?;
+        legend_area.draw(&Text::new(
+            format!("{voxel_type:?}"),
+            (
+                legend_width as i32 / 2 + single_space * 2 / 3,
+                i as i32 * (single_space + single_space) + single_space / 2 - AXIS_STYLE.1 / 2,
+            ),
+            axis_font(theme),
+        ))?;
+    }
 
-        root.present()?;
-    } // dropping bitmap backend
+    let mut chart = ChartBuilder::on(&root)
+        .caption(title, caption_font(theme))
+        .margin(CHART_MARGIN)
+        .margin_right(CHART_MARGIN + COLORBAR_WIDTH + LABEL_AREA_WIDTH + LABEL_AREA_RIGHT_MARGIN) // make room for colorbar
+        .x_label_area_size(AXIS_LABEL_AREA)
+        .y_label_area_size(AXIS_LABEL_AREA)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    chart
+        .configure_mesh()
+        .themed(theme)
+        .disable_mesh()
+        .x_desc(x_label)
+        .x_label_style(axis_font(theme))
+        .x_labels(dim_x.min(AXIS_LABEL_NUM_MAX))
+        .y_desc(y_label)
+        .y_label_style(axis_font(theme))
+        .y_labels(dim_y.min(AXIS_LABEL_NUM_MAX))
+        .draw()?;
+
+    chart.draw_series(data.indexed_iter().map(|((index_x, index_y), &value)| {
+        // Map the value to a color
+        let color = type_to_color(value);
+        let color = color.to_linear().to_u8_array();
+        let color = RGBColor(color[0], color[1], color[2]);
+        let start = (
+            (index_x as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            (index_y as f32).mul_add(y_step, y_offset - y_step / 2.0),
+        );
+        let end = (
+            ((index_x + 1) as f32).mul_add(x_step, x_offset - x_step / 2.0),
+            ((index_y + 1) as f32).mul_add(y_step, y_offset - y_step / 2.0),
+        );
+        Rectangle::new([start, end], color.filled())
+    }))?;
 
-    if let Some(path) = path {
-        image::save_buffer_with_format(
-            path,
-            &buffer,
-            width,
-            height,
-            image::ColorType::Rgb8,
-            image::ImageFormat::Png,
-        )?;
-    }
+    root.present()?;
 
-    Ok(PngBundle {
-        data: buffer,
-        width,
-        height,
-    })
+    Ok(())
 }
 
 #[cfg(test)]
@@ -270,6 +316,7 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             Some(files[0].as_path()),
             None,
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -299,6 +346,7 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             Some(files[0].as_path()),
             Some(PlotSlice::X(10)),
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
@@ -328,6 +376,7 @@ mod test {
             data.simulation.model.spatial_description.voxels.size_mm,
             Some(files[0].as_path()),
             Some(PlotSlice::Y(5)),
+            PlotTheme::Light,
         )?;
 
         assert!(files[0].is_file());
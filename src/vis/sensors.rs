@@ -3,7 +3,7 @@ use ndarray::Array2;
 use tracing::error;
 
 use super::{options::VisibilityOptions, sample_tracker::SampleTracker};
-use crate::core::scenario::Scenario;
+use crate::core::{model::spatial::sensors::SensorModality, scenario::Scenario};
 
 #[derive(Component)]
 pub(crate) struct SensorData {
@@ -61,11 +61,22 @@ pub(crate) fn spawn_sensors(
         ..Default::default()
     });
 
+    let material_electrode = materials.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 1.0, 0.0),
+        metallic: 0.0,
+        ..Default::default()
+    });
+
     for index_sensor in 0..sensors.positions_mm.shape()[0] {
-        let material = match index_sensor % 3 {
-            0 => material_red.clone(),
-            1 => materials_green.clone(),
-            _ => material_blue.clone(),
+        let electrode = sensors.modalities[index_sensor] == SensorModality::Electric;
+        let material = if electrode {
+            material_electrode.clone()
+        } else {
+            match index_sensor % 3 {
+                0 => material_red.clone(),
+                1 => materials_green.clone(),
+                _ => material_blue.clone(),
+            }
         };
         let mut positions_mm = Array2::zeros((motion_steps, 3));
         for i in 0..motion_steps {
@@ -90,7 +101,11 @@ pub(crate) fn spawn_sensors(
             MeshMaterial3d(material),
             Transform::from_xyz(x_pos_mm, y_pos_mm, z_pos_mm)
                 .with_scale(Vec3::ONE * 15.0) // this should be a parameter, changeable via the gui...
-                .with_rotation(Quat::from_rotation_arc(-Vec3::Z, rot)),
+                .with_rotation(if electrode {
+                    Quat::IDENTITY
+                } else {
+                    Quat::from_rotation_arc(-Vec3::Z, rot)
+                }),
             SensorData {
                 positions_mm,
                 _orientation: rot,